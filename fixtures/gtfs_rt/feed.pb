@@ -0,0 +1,22 @@
+
+
+2.0+
+1&
+
+M1F120180601x
+
+2
+	
+B42F1 8
+33
+
+NEW20180601 "NATM"CDGM
+4
+
+M1F1201806029
+5*4
+*M1**GDLM08R
+
+Strikeen
+
+Grève
\ No newline at end of file
@@ -0,0 +1,324 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! Correction of a model with rule files.
+//!
+//! The complementary code rules are csv files with the
+//! `object_type,object_id,object_system,object_code` columns, adding
+//! codes to the objects.
+//!
+//! The property rules are csv files with the
+//! `object_type,object_id,property_name,property_old_value,property_value`
+//! columns, overriding a property of an object.  When
+//! `property_old_value` is not empty, the rule is applied only if the
+//! current value of the property matches it.  The supported
+//! properties are:
+//! - network: `network_name`, `network_url`, `network_timezone`
+//! - line: `line_name`, `line_code`, `line_color`,
+//!   `line_text_color`, `line_sort_order`, `network_id`,
+//!   `commercial_mode_id`
+//! - route: `route_name`, `direction_type`, `destination_id`
+//! - stop_area and stop_point: `stop_name`
+//! - trip: `trip_headsign`, `physical_mode_id`, `company_id`
+//!
+//! The object types are the NTFS ones: `network`, `line`, `route`,
+//! `stop_area`, `stop_point` and `trip`.
+
+use collection::{CollectionWithId, Id};
+use csv;
+use failure::ResultExt;
+use model::{Collections, Model};
+use objects::{Codes, ObjectType, Rgb};
+use report::Warning;
+use std::path::Path;
+use Result;
+
+#[derive(Deserialize, Debug)]
+struct ComplementaryCode {
+    object_type: ObjectType,
+    object_id: String,
+    object_system: String,
+    object_code: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct PropertyRule {
+    object_type: ObjectType,
+    object_id: String,
+    property_name: String,
+    property_old_value: Option<String>,
+    property_value: String,
+}
+
+pub(crate) fn read_rules<P, T>(path: P) -> Result<Vec<T>>
+where
+    P: AsRef<Path>,
+    for<'de> T: ::serde::Deserialize<'de>,
+{
+    let path = path.as_ref();
+    info!("Reading {:?}", path);
+    let mut rdr = csv::Reader::from_path(path).with_context(ctx_from_path!(path))?;
+    let rules = rdr
+        .deserialize()
+        .collect::<::std::result::Result<_, _>>()
+        .with_context(ctx_from_path!(path))?;
+    Ok(rules)
+}
+
+fn add_code<T: Id<T> + Codes>(
+    collection: &mut CollectionWithId<T>,
+    rule: &ComplementaryCode,
+) -> bool {
+    collection
+        .get_mut(&rule.object_id)
+        .map(|mut obj| {
+            obj.codes_mut()
+                .push((rule.object_system.clone(), rule.object_code.clone()))
+        })
+        .is_some()
+}
+
+fn apply_complementary_code(c: &mut Collections, rule: &ComplementaryCode) -> Result<()> {
+    let found = match rule.object_type {
+        ObjectType::Network => add_code(&mut c.networks, rule),
+        ObjectType::Line => add_code(&mut c.lines, rule),
+        ObjectType::Route => add_code(&mut c.routes, rule),
+        ObjectType::StopArea => add_code(&mut c.stop_areas, rule),
+        ObjectType::StopPoint => add_code(&mut c.stop_points, rule),
+        ObjectType::VehicleJourney => add_code(&mut c.vehicle_journeys, rule),
+        ref object_type => bail!("object type {} not supported", object_type.as_str()),
+    };
+    ensure!(found, "{} not found", rule.object_id);
+    Ok(())
+}
+
+// Replaces the value if the old value of the rule, when given, is
+// the current one.
+fn update<T, F>(value: &mut T, rule: &PropertyRule, to_string: F, new_value: T) -> Result<()>
+where
+    F: Fn(&T) -> String,
+{
+    if let Some(ref old_value) = rule.property_old_value {
+        let current = to_string(value);
+        ensure!(
+            &current == old_value,
+            "{} is {:?} instead of {:?}",
+            rule.property_name,
+            current,
+            old_value
+        );
+    }
+    *value = new_value;
+    Ok(())
+}
+
+fn update_string<S>(value: &mut S, rule: &PropertyRule) -> Result<()>
+where
+    S: AsRef<str> + From<String>,
+{
+    let new_value = rule.property_value.clone().into();
+    update(value, rule, |v| v.as_ref().to_string(), new_value)
+}
+
+fn update_opt_string<S>(value: &mut Option<S>, rule: &PropertyRule) -> Result<()>
+where
+    S: AsRef<str> + From<String>,
+{
+    let new_value = Some(rule.property_value.clone())
+        .filter(|v| !v.is_empty())
+        .map(S::from);
+    let to_string = |v: &Option<S>| v.as_ref().map(|v| v.as_ref().to_string()).unwrap_or_default();
+    update(value, rule, to_string, new_value)
+}
+
+fn update_color(value: &mut Option<Rgb>, rule: &PropertyRule) -> Result<()> {
+    let new_value = if rule.property_value.is_empty() {
+        None
+    } else {
+        Some(rule.property_value.parse()?)
+    };
+    let to_string = |v: &Option<Rgb>| v.as_ref().map(|c| c.to_string()).unwrap_or_default();
+    update(value, rule, to_string, new_value)
+}
+
+fn check_ref<T>(collection: &CollectionWithId<T>, rule: &PropertyRule) -> Result<()> {
+    ensure!(
+        collection.get(&rule.property_value).is_some(),
+        "{} {} not found",
+        rule.property_name,
+        rule.property_value
+    );
+    Ok(())
+}
+
+fn unknown_property(rule: &PropertyRule) -> Result<()> {
+    bail!(
+        "property {} not supported for {}",
+        rule.property_name,
+        rule.object_type.as_str()
+    )
+}
+
+fn apply_property(c: &mut Collections, rule: &PropertyRule) -> Result<()> {
+    let not_found = || format_err!("{} not found", rule.object_id);
+    match rule.object_type {
+        ObjectType::Network => {
+            let mut network = c.networks.get_mut(&rule.object_id).ok_or_else(not_found)?;
+            match rule.property_name.as_str() {
+                "network_name" => update_string(&mut network.name, rule),
+                "network_url" => update_opt_string(&mut network.url, rule),
+                "network_timezone" => update_opt_string(&mut network.timezone, rule),
+                _ => unknown_property(rule),
+            }
+        }
+        ObjectType::Line => {
+            match rule.property_name.as_str() {
+                "network_id" => check_ref(&c.networks, rule)?,
+                "commercial_mode_id" => check_ref(&c.commercial_modes, rule)?,
+                _ => {}
+            }
+            let mut line = c.lines.get_mut(&rule.object_id).ok_or_else(not_found)?;
+            match rule.property_name.as_str() {
+                "line_name" => update_string(&mut line.name, rule),
+                "line_code" => update_opt_string(&mut line.code, rule),
+                "line_color" => update_color(&mut line.color, rule),
+                "line_text_color" => update_color(&mut line.text_color, rule),
+                "line_sort_order" => {
+                    let new_value = if rule.property_value.is_empty() {
+                        None
+                    } else {
+                        Some(rule.property_value.parse()?)
+                    };
+                    let to_string = |v: &Option<u32>| v.map(|v| v.to_string()).unwrap_or_default();
+                    update(&mut line.sort_order, rule, to_string, new_value)
+                }
+                "network_id" => update_string(&mut line.network_id, rule),
+                "commercial_mode_id" => update_string(&mut line.commercial_mode_id, rule),
+                _ => unknown_property(rule),
+            }
+        }
+        ObjectType::Route => {
+            if rule.property_name == "destination_id" && !rule.property_value.is_empty() {
+                check_ref(&c.stop_areas, rule)?;
+            }
+            let mut route = c.routes.get_mut(&rule.object_id).ok_or_else(not_found)?;
+            match rule.property_name.as_str() {
+                "route_name" => update_string(&mut route.name, rule),
+                "direction_type" => update_opt_string(&mut route.direction_type, rule),
+                "destination_id" => update_opt_string(&mut route.destination_id, rule),
+                _ => unknown_property(rule),
+            }
+        }
+        ObjectType::StopArea => {
+            let mut stop_area = c
+                .stop_areas
+                .get_mut(&rule.object_id)
+                .ok_or_else(not_found)?;
+            match rule.property_name.as_str() {
+                "stop_name" => update_string(&mut stop_area.name, rule),
+                _ => unknown_property(rule),
+            }
+        }
+        ObjectType::StopPoint => {
+            let mut stop_point = c
+                .stop_points
+                .get_mut(&rule.object_id)
+                .ok_or_else(not_found)?;
+            match rule.property_name.as_str() {
+                "stop_name" => update_string(&mut stop_point.name, rule),
+                _ => unknown_property(rule),
+            }
+        }
+        ObjectType::VehicleJourney => {
+            match rule.property_name.as_str() {
+                "physical_mode_id" => check_ref(&c.physical_modes, rule)?,
+                "company_id" => check_ref(&c.companies, rule)?,
+                _ => {}
+            }
+            let mut vj = c
+                .vehicle_journeys
+                .get_mut(&rule.object_id)
+                .ok_or_else(not_found)?;
+            match rule.property_name.as_str() {
+                "trip_headsign" => update_opt_string(&mut vj.headsign, rule),
+                "physical_mode_id" => update_string(&mut vj.physical_mode_id, rule),
+                "company_id" => update_string(&mut vj.company_id, rule),
+                _ => unknown_property(rule),
+            }
+        }
+        ref object_type => bail!("object type {} not supported", object_type.as_str()),
+    }
+}
+
+pub(crate) fn warning(path: &Path, line: usize, e: &::Error) -> Warning {
+    let warning = Warning {
+        file: path.display().to_string(),
+        line: None,
+        object_id: None,
+        issue: None,
+        handling: None,
+        message: format!("rule {} ignored: {}", line, e),
+    };
+    warn!("{}", warning);
+    warning
+}
+
+/// Applies the complementary code rules, then the property rules, to
+/// the model.  The rules that cannot be applied, for example because
+/// the object does not exist, are ignored and returned as warnings.
+/// Fails if a rule file cannot be read.
+pub fn apply_rules<P: AsRef<Path>>(
+    model: Model,
+    complementary_code_rules_files: &[P],
+    property_rules_files: &[P],
+) -> Result<(Model, Vec<Warning>)> {
+    let mut c = model.into_collections();
+    let warnings = apply_rules_to_collections(
+        &mut c,
+        complementary_code_rules_files,
+        property_rules_files,
+    )?;
+    Ok((Model::new(c)?, warnings))
+}
+
+/// Applies the rules to the collections, as `apply_rules`, for
+/// example before they are checked and indexed by `Model::new`.
+pub fn apply_rules_to_collections<P: AsRef<Path>>(
+    c: &mut Collections,
+    complementary_code_rules_files: &[P],
+    property_rules_files: &[P],
+) -> Result<Vec<Warning>> {
+    let mut warnings = vec![];
+    for path in complementary_code_rules_files {
+        let path = path.as_ref();
+        let rules: Vec<ComplementaryCode> = read_rules(path)?;
+        for (i, rule) in rules.iter().enumerate() {
+            if let Err(e) = apply_complementary_code(c, rule) {
+                warnings.push(warning(path, i + 1, &e));
+            }
+        }
+    }
+    for path in property_rules_files {
+        let path = path.as_ref();
+        let rules: Vec<PropertyRule> = read_rules(path)?;
+        for (i, rule) in rules.iter().enumerate() {
+            if let Err(e) = apply_property(c, rule) {
+                warnings.push(warning(path, i + 1, &e));
+            }
+        }
+    }
+    Ok(warnings)
+}
@@ -0,0 +1,66 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+#[macro_use]
+extern crate log;
+extern crate navitia_model;
+extern crate structopt;
+
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+use navitia_model::cli;
+use navitia_model::Result;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "apply-rules", about = "Apply rules to an ntfs.")]
+struct Opt {
+    #[structopt(flatten)]
+    input: cli::InputOptions,
+
+    #[structopt(flatten)]
+    output: cli::OutputOptions,
+
+    /// complementary code rules file, can be repeated
+    #[structopt(short = "c", long = "complementary-code-rules", parse(from_os_str))]
+    complementary_code_rules_files: Vec<PathBuf>,
+
+    /// property rules file, can be repeated
+    #[structopt(short = "p", long = "property-rules", parse(from_os_str))]
+    property_rules_files: Vec<PathBuf>,
+    #[structopt(flatten)]
+    log: cli::LogOptions,
+}
+
+fn run(opt: Opt) -> Result<()> {
+    info!("Launching apply-rules...");
+
+    let model = navitia_model::ntfs::read(opt.input.input)?;
+    let (model, warnings) = navitia_model::apply_rules::apply_rules(
+        model,
+        &opt.complementary_code_rules_files,
+        &opt.property_rules_files,
+    )?;
+    info!("{} rules ignored", warnings.len());
+    opt.output.write_ntfs(&model)?;
+    Ok(())
+}
+
+fn main() {
+    let opt = Opt::from_args();
+    opt.log.init_logger();
+    cli::run(|| run(opt));
+}
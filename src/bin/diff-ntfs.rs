@@ -0,0 +1,71 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+#[macro_use]
+extern crate log;
+extern crate navitia_model;
+extern crate structopt;
+
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+use navitia_model::cli;
+use navitia_model::Result;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "diff-ntfs", about = "Print the differences between two ntfs.")]
+struct Opt {
+    /// directory of the old ntfs
+    #[structopt(name = "OLD", parse(from_os_str))]
+    old: PathBuf,
+
+    /// directory of the new ntfs
+    #[structopt(name = "NEW", parse(from_os_str))]
+    new: PathBuf,
+
+    /// also print the identifiers of the objects added, removed and
+    /// modified
+    #[structopt(long = "details")]
+    details: bool,
+
+    #[structopt(flatten)]
+    log: cli::LogOptions,
+}
+
+fn run(opt: Opt) -> Result<()> {
+    info!("Launching diff-ntfs...");
+
+    let old = navitia_model::ntfs::read(opt.old)?;
+    let new = navitia_model::ntfs::read(opt.new)?;
+    let diff = navitia_model::diff::diff(&old, &new);
+    print!("{}", diff.summary());
+    if opt.details {
+        for c in &diff.collections {
+            for (change, ids) in &[("+", &c.added), ("-", &c.removed), ("~", &c.modified)] {
+                for id in ids.iter() {
+                    println!("{} {} {}", change, c.collection, id);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let opt = Opt::from_args();
+    opt.log.init_logger();
+    cli::run(|| run(opt));
+}
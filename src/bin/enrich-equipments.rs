@@ -0,0 +1,67 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+#[macro_use]
+extern crate log;
+extern crate navitia_model;
+extern crate structopt;
+
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+use navitia_model::cli;
+use navitia_model::Result;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "enrich-equipments",
+    about = "Enrich the equipments of the stops from an accessibility file."
+)]
+struct Opt {
+    #[structopt(flatten)]
+    input: cli::InputOptions,
+
+    #[structopt(flatten)]
+    output: cli::OutputOptions,
+
+    /// csv file of the accessibility of the stops (stop_id,
+    /// wheelchair_boarding, sheltered, elevator, escalator,
+    /// visual_announcement, audible_announcement)
+    #[structopt(long = "accessibility", short = "a", parse(from_os_str))]
+    accessibility: PathBuf,
+
+    #[structopt(flatten)]
+    log: cli::LogOptions,
+}
+
+fn run(opt: Opt) -> Result<()> {
+    info!("Launching enrich-equipments...");
+
+    let accessibilities = navitia_model::equipments::read_accessibility(opt.accessibility)?;
+    let model = navitia_model::ntfs::read(opt.input.input)?;
+    let mut collections = model.into_collections();
+    navitia_model::equipments::enrich_equipments(&mut collections, &accessibilities)?;
+
+    let model = navitia_model::Model::new(collections)?;
+    opt.output.write_ntfs(&model)?;
+    Ok(())
+}
+
+fn main() {
+    let opt = Opt::from_args();
+    opt.log.init_logger();
+    cli::run(|| run(opt));
+}
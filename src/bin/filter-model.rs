@@ -0,0 +1,112 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+extern crate chrono;
+#[macro_use]
+extern crate log;
+extern crate navitia_model;
+extern crate structopt;
+
+use chrono::NaiveDate;
+use structopt::StructOpt;
+
+use navitia_model::cli;
+use navitia_model::filter::{self, BoundingBox, Filter};
+use navitia_model::Result;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "filter-model",
+    about = "Extract a part of an ntfs by network, line, physical mode, period or area, \
+             or remove networks and lines."
+)]
+struct Opt {
+    #[structopt(flatten)]
+    input: cli::InputOptions,
+
+    #[structopt(flatten)]
+    output: cli::OutputOptions,
+
+    /// network to keep, can be repeated, all the networks are kept
+    /// if not given
+    #[structopt(short = "n", long = "network")]
+    networks: Vec<String>,
+
+    /// line to keep, can be repeated, all the lines are kept if not
+    /// given
+    #[structopt(short = "l", long = "line")]
+    lines: Vec<String>,
+
+    /// network to remove, can be repeated
+    #[structopt(long = "exclude-network")]
+    excluded_networks: Vec<String>,
+
+    /// line to remove, can be repeated
+    #[structopt(long = "exclude-line")]
+    excluded_lines: Vec<String>,
+
+    /// physical mode to keep, can be repeated, all the physical modes
+    /// are kept if not given
+    #[structopt(short = "m", long = "physical-mode")]
+    physical_modes: Vec<String>,
+
+    /// first day to keep, as YYYYMMDD
+    #[structopt(long = "start-date", parse(try_from_str = "cli::parse_date"))]
+    start_date: Option<NaiveDate>,
+
+    /// last day to keep, as YYYYMMDD
+    #[structopt(long = "end-date", parse(try_from_str = "cli::parse_date"))]
+    end_date: Option<NaiveDate>,
+
+    /// area in which the stops are kept, as
+    /// min_lon,min_lat,max_lon,max_lat
+    #[structopt(long = "bbox")]
+    bounding_box: Option<BoundingBox>,
+    #[structopt(flatten)]
+    log: cli::LogOptions,
+}
+
+fn run(opt: Opt) -> Result<()> {
+    info!("Launching filter-model...");
+
+    let period = match (opt.start_date, opt.end_date) {
+        (None, None) => None,
+        (start, end) => Some((
+            start.unwrap_or(chrono::naive::MIN_DATE),
+            end.unwrap_or(chrono::naive::MAX_DATE),
+        )),
+    };
+    let filter = Filter {
+        network_ids: opt.networks.into_iter().collect(),
+        line_ids: opt.lines.into_iter().collect(),
+        excluded_network_ids: opt.excluded_networks.into_iter().collect(),
+        excluded_line_ids: opt.excluded_lines.into_iter().collect(),
+        physical_mode_ids: opt.physical_modes.into_iter().collect(),
+        period,
+        bounding_box: opt.bounding_box,
+    };
+
+    let model = navitia_model::ntfs::read(opt.input.input)?;
+    let model = filter::filter(model, &filter)?;
+    opt.output.write_ntfs(&model)?;
+    Ok(())
+}
+
+fn main() {
+    let opt = Opt::from_args();
+    opt.log.init_logger();
+    cli::run(|| run(opt));
+}
@@ -13,56 +13,95 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see
 // <http://www.gnu.org/licenses/>.
-
-extern crate env_logger;
+extern crate chrono;
 #[macro_use]
 extern crate log;
 extern crate navitia_model;
 #[macro_use]
 extern crate structopt;
 
+use chrono::NaiveDate;
 use std::path::PathBuf;
 use structopt::StructOpt;
 
+use navitia_model::cli;
 use navitia_model::Result;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "gtfs2ntfs", about = "Convert a GTFS to an NTFS.")]
 struct Opt {
-    /// input directory.
-    #[structopt(short = "i", long = "input", parse(from_os_str), default_value = ".")]
-    input: PathBuf,
+    #[structopt(flatten)]
+    input: cli::InputOptions,
+
+    #[structopt(flatten)]
+    output: cli::OutputOptions,
+
+    #[structopt(flatten)]
+    read: cli::ReadOptions,
+
+    /// first day of the validity period of the converted data, as
+    /// YYYYMMDD
+    #[structopt(
+        short = "s",
+        long = "start-date",
+        parse(try_from_str = "cli::parse_date"),
+        requires = "end_date"
+    )]
+    start_date: Option<NaiveDate>,
 
-    /// output directory
-    #[structopt(short = "o", long = "output", parse(from_os_str))]
-    output: PathBuf,
+    /// last day of the validity period of the converted data, as
+    /// YYYYMMDD
+    #[structopt(
+        short = "e",
+        long = "end-date",
+        parse(try_from_str = "cli::parse_date"),
+        requires = "start_date"
+    )]
+    end_date: Option<NaiveDate>,
 
-    /// config file
-    #[structopt(short = "c", long = "config", parse(from_os_str))]
-    config_path: Option<PathBuf>,
+    /// CSV file where to write the mappings between the GTFS
+    /// identifiers and the identifiers generated by the conversion
+    #[structopt(long = "id-mappings", parse(from_os_str))]
+    id_mappings: Option<PathBuf>,
 
-    /// prefix
-    #[structopt(short = "p", long = "prefix")]
-    prefix: Option<String>,
+    /// JSON file where to write the issues met while reading, as
+    /// structured entries
+    #[structopt(long = "import-log", parse(from_os_str))]
+    import_log: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    log: cli::LogOptions,
 }
 
-fn run() -> Result<()> {
+fn run(opt: Opt) -> Result<()> {
     info!("Launching gtfs2ntfs...");
 
-    let opt = Opt::from_args();
-
-    let objects = navitia_model::gtfs::read(opt.input, opt.config_path, opt.prefix)?;
+    let (objects, warnings) = navitia_model::gtfs::read_with_profile(
+        opt.input.input,
+        opt.read.config_path.clone(),
+        None,
+        &opt.read.profile,
+    )?;
+    if let Some(import_log) = opt.import_log {
+        navitia_model::report::write_warnings(&warnings, import_log)?;
+    }
+    let objects = opt.read.add_prefix(objects)?;
+    let objects = match (opt.start_date, opt.end_date) {
+        (Some(start), Some(end)) => {
+            navitia_model::filter::restrict_validity_period(objects, start, end)?
+        }
+        _ => objects,
+    };
 
-    navitia_model::ntfs::write(&objects, opt.output)?;
+    if let Some(id_mappings) = opt.id_mappings {
+        navitia_model::export::id_mappings::write_to_path(&objects, id_mappings)?;
+    }
+    opt.output.write_ntfs(&objects)?;
     Ok(())
 }
 
 fn main() {
-    env_logger::init();
-    if let Err(err) = run() {
-        for cause in err.iter_chain() {
-            eprintln!("{}", cause);
-        }
-        std::process::exit(1);
-    }
+    let opt = Opt::from_args();
+    opt.log.init_logger();
+    cli::run(|| run(opt));
 }
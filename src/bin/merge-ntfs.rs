@@ -14,7 +14,6 @@
 // along with this program.  If not, see
 // <http://www.gnu.org/licenses/>.
 
-extern crate env_logger;
 #[macro_use]
 extern crate log;
 extern crate navitia_model;
@@ -24,7 +23,10 @@ extern crate structopt;
 use std::path::PathBuf;
 use structopt::StructOpt;
 
-use navitia_model::model::Collections;
+use navitia_model::cli;
+use navitia_model::duplicates::StopMatching;
+use navitia_model::merge::MergeOptions;
+use navitia_model::model::MergeStrategy;
 use navitia_model::Result;
 #[macro_use]
 extern crate failure;
@@ -36,35 +38,89 @@ struct Opt {
     #[structopt(name = "INPUTS", parse(from_os_str))]
     input_directories: Vec<PathBuf>,
 
-    /// output directory
-    #[structopt(short = "o", long = "output", parse(from_os_str))]
-    output: PathBuf,
+    #[structopt(flatten)]
+    output: cli::OutputOptions,
+
+    /// prefix of the identifiers of an input, given once for each
+    /// input in the same order
+    #[structopt(short = "p", long = "prefix")]
+    prefixes: Vec<String>,
+
+    /// separator between the prefixes and the identifiers
+    #[structopt(long = "prefix-separator", default_value = ":")]
+    prefix_separator: String,
+
+    /// how to handle the objects whose identifier is already in a
+    /// previous input: fail, keep-first, keep-second or rename:<suffix>
+    #[structopt(long = "on-collision", default_value = "fail")]
+    on_collision: MergeStrategy,
+
+    /// the strategy of a given collection, as stop_areas=keep-first,
+    /// overriding --on-collision and the keep-first strategy of the
+    /// contributors and the modes
+    #[structopt(
+        long = "collection-strategy",
+        parse(try_from_str = "parse_collection_strategy")
+    )]
+    collection_strategies: Vec<(String, MergeStrategy)>,
+
+    /// remove the trips published by several contributors, keeping
+    /// the one of the first input
+    #[structopt(long = "remove-duplicate-trips")]
+    remove_duplicate_trips: bool,
+
+    /// merge the close stop areas with similar names, keeping the one
+    /// of the first input
+    #[structopt(long = "dedupe-stops")]
+    dedupe_stops: bool,
+
+    /// also merge the close stop points with similar names of the
+    /// merged stop areas
+    #[structopt(long = "dedupe-stop-points")]
+    dedupe_stop_points: bool,
+
+    #[structopt(flatten)]
+    log: cli::LogOptions,
 }
 
-fn run() -> Result<()> {
-    info!("Launching merge...");
+fn parse_collection_strategy(s: &str) -> Result<(String, MergeStrategy)> {
+    match s.find('=') {
+        Some(i) => Ok((s[..i].to_string(), s[i + 1..].parse()?)),
+        None => bail!(
+            "invalid collection strategy {:?}, expected <collection>=<strategy>",
+            s
+        ),
+    }
+}
 
-    let opt = Opt::from_args();
+fn run(opt: Opt) -> Result<()> {
+    info!("Launching merge...");
     if opt.input_directories.len() < 2 {
         bail!("merge-ntfs process should have at least two input directories")
-    } else {
-        let mut collections = Collections::default();
-        for input_directory in opt.input_directories {
-            let to_append_model = navitia_model::ntfs::read(input_directory)?;
-            collections.merge(to_append_model.into_collections())?;
-        }
-        let model = navitia_model::Model::new(collections)?;
-        navitia_model::ntfs::write(&model, opt.output)?;
-        Ok(())
     }
+    let mut options = MergeOptions {
+        prefixes: opt.prefixes.into_iter().map(Some).collect(),
+        prefix_separator: opt.prefix_separator,
+        remove_duplicate_trips: opt.remove_duplicate_trips,
+        ..Default::default()
+    };
+    options.strategies.default = opt.on_collision;
+    for (collection, strategy) in opt.collection_strategies {
+        options.strategies.insert(&collection, strategy)?;
+    }
+    if opt.dedupe_stops || opt.dedupe_stop_points {
+        options.stop_matching = Some(StopMatching {
+            merge_stop_points: opt.dedupe_stop_points,
+            ..Default::default()
+        });
+    }
+    let model = navitia_model::merge(&opt.input_directories, &options)?;
+    opt.output.write_ntfs(&model)?;
+    Ok(())
 }
 
 fn main() {
-    env_logger::init();
-    if let Err(err) = run() {
-        for cause in err.iter_chain() {
-            eprintln!("{}", cause);
-        }
-        std::process::exit(1);
-    }
+    let opt = Opt::from_args();
+    opt.log.init_logger();
+    cli::run(|| run(opt));
 }
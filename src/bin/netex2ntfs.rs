@@ -13,56 +13,50 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see
 // <http://www.gnu.org/licenses/>.
-
-extern crate env_logger;
 #[macro_use]
 extern crate log;
 extern crate navitia_model;
 #[macro_use]
 extern crate structopt;
 
-use std::path::PathBuf;
 use structopt::StructOpt;
 
+use navitia_model::cli;
 use navitia_model::Result;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "netex2ntfs", about = "Convert Netex data to an NTFS.")]
 struct Opt {
-    /// input directory.
-    #[structopt(short = "i", long = "input", parse(from_os_str), default_value = ".")]
-    input: PathBuf,
+    #[structopt(flatten)]
+    input: cli::InputOptions,
 
-    /// output directory
-    #[structopt(short = "o", long = "output", parse(from_os_str))]
-    output: PathBuf,
+    #[structopt(flatten)]
+    output: cli::OutputOptions,
 
-    /// config file
-    #[structopt(short = "c", long = "config", parse(from_os_str))]
-    config_path: Option<PathBuf>,
+    #[structopt(flatten)]
+    read: cli::ReadOptions,
 
-    /// prefix
-    #[structopt(short = "p", long = "prefix")]
-    prefix: Option<String>,
+    #[structopt(flatten)]
+    log: cli::LogOptions,
 }
 
-fn run() -> Result<()> {
+fn run(opt: Opt) -> Result<()> {
     info!("Launching netex2ntfs...");
 
-    let opt = Opt::from_args();
-
-    let objects = navitia_model::netex::read(opt.input, opt.config_path, opt.prefix)?;
+    let (objects, _) = navitia_model::netex::read_with_profile(
+        opt.input.input,
+        opt.read.config_path.clone(),
+        None,
+        &opt.read.profile,
+    )?;
+    let objects = opt.read.add_prefix(objects)?;
 
-    navitia_model::ntfs::write(&objects, opt.output)?;
+    opt.output.write_ntfs(&objects)?;
     Ok(())
 }
 
 fn main() {
-    env_logger::init();
-    if let Err(err) = run() {
-        for cause in err.iter_chain() {
-            eprintln!("{}", cause);
-        }
-        std::process::exit(1);
-    }
+    let opt = Opt::from_args();
+    opt.log.init_logger();
+    cli::run(|| run(opt));
 }
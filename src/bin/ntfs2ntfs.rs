@@ -13,8 +13,6 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see
 // <http://www.gnu.org/licenses/>.
-
-extern crate env_logger;
 #[macro_use]
 extern crate log;
 extern crate navitia_model;
@@ -24,26 +22,50 @@ extern crate structopt;
 use std::path::PathBuf;
 use structopt::StructOpt;
 
+use navitia_model::cli;
 use navitia_model::Result;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "ntfs2ntfs", about = "Convert an NTFS to an NTFS.")]
 struct Opt {
-    /// input directory.
-    #[structopt(short = "i", long = "input", parse(from_os_str), default_value = ".")]
-    input: PathBuf,
+    #[structopt(flatten)]
+    input: cli::InputOptions,
 
     /// output directory
     #[structopt(short = "o", long = "output", parse(from_os_str))]
     output: Option<PathBuf>,
+
+    /// replace the identifiers not respecting the NTFS constraints
+    /// (the original ones are kept as object codes).
+    #[structopt(long = "sanitize-ids")]
+    sanitize_ids: bool,
+
+    /// parse profile, strict to fail on the first invalid element
+    /// but for the optional ones, permissive to skip the invalid
+    /// elements
+    #[structopt(long = "profile", default_value = "strict")]
+    profile: navitia_model::report::ParseProfile,
+
+    /// JSON file where to write the issues met while reading, as
+    /// structured entries
+    #[structopt(long = "import-log", parse(from_os_str))]
+    import_log: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    log: cli::LogOptions,
 }
 
-fn run() -> Result<()> {
+fn run(opt: Opt) -> Result<()> {
     info!("Launching ntfs2ntfs...");
 
-    let opt = Opt::from_args();
-
-    let objects = navitia_model::ntfs::read(opt.input)?;
+    let (mut objects, warnings) =
+        navitia_model::ntfs::read_with_profile(opt.input.input, &opt.profile)?;
+    if let Some(import_log) = opt.import_log {
+        navitia_model::report::write_warnings(&warnings, import_log)?;
+    }
+    if opt.sanitize_ids {
+        objects = navitia_model::ntfs::sanitize_ids(objects)?;
+    }
 
     if let Some(output) = opt.output {
         navitia_model::ntfs::write(&objects, output)?;
@@ -52,11 +74,7 @@ fn run() -> Result<()> {
 }
 
 fn main() {
-    env_logger::init();
-    if let Err(err) = run() {
-        for cause in err.iter_chain() {
-            eprintln!("{}", cause);
-        }
-        std::process::exit(1);
-    }
+    let opt = Opt::from_args();
+    opt.log.init_logger();
+    cli::run(|| run(opt));
 }
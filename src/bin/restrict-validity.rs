@@ -0,0 +1,74 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+extern crate chrono;
+#[macro_use]
+extern crate log;
+extern crate navitia_model;
+extern crate structopt;
+
+use chrono::NaiveDate;
+use structopt::StructOpt;
+
+use navitia_model::cli;
+use navitia_model::Result;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "restrict-validity",
+    about = "Restrict the validity period of an ntfs."
+)]
+struct Opt {
+    #[structopt(flatten)]
+    input: cli::InputOptions,
+
+    #[structopt(flatten)]
+    output: cli::OutputOptions,
+
+    /// first day of the validity period, as YYYYMMDD
+    #[structopt(
+        short = "s",
+        long = "start-date",
+        parse(try_from_str = "cli::parse_date")
+    )]
+    start_date: NaiveDate,
+
+    /// last day of the validity period, as YYYYMMDD
+    #[structopt(
+        short = "e",
+        long = "end-date",
+        parse(try_from_str = "cli::parse_date")
+    )]
+    end_date: NaiveDate,
+    #[structopt(flatten)]
+    log: cli::LogOptions,
+}
+
+fn run(opt: Opt) -> Result<()> {
+    info!("Launching restrict-validity...");
+
+    let model = navitia_model::ntfs::read(opt.input.input)?;
+    let model =
+        navitia_model::filter::restrict_validity_period(model, opt.start_date, opt.end_date)?;
+    opt.output.write_ntfs(&model)?;
+    Ok(())
+}
+
+fn main() {
+    let opt = Opt::from_args();
+    opt.log.init_logger();
+    cli::run(|| run(opt));
+}
@@ -14,7 +14,6 @@
 // along with this program.  If not, see
 // <http://www.gnu.org/licenses/>.
 
-extern crate env_logger;
 #[macro_use]
 extern crate log;
 extern crate navitia_model;
@@ -24,18 +23,17 @@ extern crate structopt;
 use std::path::PathBuf;
 use structopt::StructOpt;
 
+use navitia_model::cli;
 use navitia_model::Result;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "transfers", about = "Generate transfers.")]
 struct Opt {
-    /// input directory.
-    #[structopt(short = "i", long = "input", parse(from_os_str), default_value = ".")]
-    input: PathBuf,
+    #[structopt(flatten)]
+    input: cli::InputOptions,
 
-    /// output directory
-    #[structopt(short = "o", long = "output", parse(from_os_str))]
-    output: PathBuf,
+    #[structopt(flatten)]
+    output: cli::OutputOptions,
 
     #[structopt(
         long = "max-distance",
@@ -62,14 +60,33 @@ struct Opt {
         help = "Waiting time at stop in second"
     )]
     waiting_time: u32,
+
+    /// max walking time in seconds of the generated transfers
+    #[structopt(long = "max-duration")]
+    max_duration: Option<u32>,
+
+    /// csv file of the stop points pairs (from_stop_id, to_stop_id)
+    /// between which no transfer must be generated
+    #[structopt(long = "exclusion-rules", short = "e", parse(from_os_str))]
+    exclusion_rules: Option<PathBuf>,
+
+    /// csv file of the minimum transfer times or forbidden transfers
+    /// between physical modes or networks
+    #[structopt(long = "rules", short = "r", parse(from_os_str))]
+    rules: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    log: cli::LogOptions,
 }
 
-fn run() -> Result<()> {
+fn run(opt: Opt) -> Result<()> {
     info!("Launching transfers...");
 
-    let opt = Opt::from_args();
-
-    let model = navitia_model::ntfs::read(opt.input)?;
+    let exclusion_rules = match opt.exclusion_rules {
+        Some(path) => navitia_model::transfers::read_exclusion_rules(path)?,
+        None => vec![],
+    };
+    let model = navitia_model::ntfs::read(opt.input.input)?;
     let mut collections = model.into_collections();
 
     info!("Generating transfers...");
@@ -79,19 +96,21 @@ fn run() -> Result<()> {
         opt.max_distance,
         opt.walking_speed,
         opt.waiting_time,
+        opt.max_duration,
+        &exclusion_rules,
     );
+    if let Some(path) = opt.rules {
+        let rules = navitia_model::transfers::read_transfer_rules(path)?;
+        navitia_model::transfers::apply_transfer_rules(&mut collections, &rules);
+    }
 
     let model = navitia_model::Model::new(collections)?;
-    navitia_model::ntfs::write(&model, opt.output)?;
+    opt.output.write_ntfs(&model)?;
     Ok(())
 }
 
 fn main() {
-    env_logger::init();
-    if let Err(err) = run() {
-        for cause in err.iter_chain() {
-            eprintln!("{}", cause);
-        }
-        std::process::exit(1);
-    }
+    let opt = Opt::from_args();
+    opt.log.init_logger();
+    cli::run(|| run(opt));
 }
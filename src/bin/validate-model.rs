@@ -0,0 +1,129 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+#[macro_use]
+extern crate failure;
+#[macro_use]
+extern crate log;
+extern crate navitia_model;
+extern crate serde_json;
+extern crate structopt;
+
+use failure::ResultExt;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+use navitia_model::cli;
+use navitia_model::validator::{Severity, ValidationReport};
+use navitia_model::{Model, Result};
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "validate-model",
+    about = "Check a dataset for semantic issues."
+)]
+struct Opt {
+    #[structopt(flatten)]
+    input: cli::InputOptions,
+
+    /// format of the input: ntfs, gtfs or netex
+    #[structopt(short = "f", long = "format", default_value = "ntfs")]
+    format: String,
+
+    #[structopt(flatten)]
+    read: cli::ReadOptions,
+
+    /// file in which the report is written, printed on the standard
+    /// output if not given
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    output: Option<PathBuf>,
+
+    /// write the report as json instead of text
+    #[structopt(long = "json")]
+    json: bool,
+
+    /// exit with an error when an issue of this severity or above is
+    /// found: info, warning or error
+    #[structopt(long = "fail-on", default_value = "error")]
+    fail_on: Severity,
+
+    #[structopt(flatten)]
+    log: cli::LogOptions,
+}
+
+fn read(opt: &Opt) -> Result<Model> {
+    let input = opt.input.input.clone();
+    let config_path = opt.read.config_path.clone();
+    let profile = &opt.read.profile;
+    match opt.format.as_str() {
+        "ntfs" => navitia_model::ntfs::read_with_profile(input, profile).map(|(m, _)| m),
+        "gtfs" => navitia_model::gtfs::read_with_profile(input, config_path, None, profile)
+            .and_then(|(m, _)| opt.read.add_prefix(m)),
+        "netex" => navitia_model::netex::read_with_profile(input, config_path, None, profile)
+            .and_then(|(m, _)| opt.read.add_prefix(m)),
+        format => bail!("unknown format {:?}, expected ntfs, gtfs or netex", format),
+    }
+}
+
+fn write_report<W: Write>(mut w: W, report: &ValidationReport, json: bool) -> Result<()> {
+    if json {
+        serde_json::to_writer_pretty(&mut w, report)?;
+        writeln!(w)?;
+    } else {
+        for issue in &report.issues {
+            writeln!(w, "{}", issue)?;
+        }
+    }
+    Ok(())
+}
+
+fn run(opt: Opt) -> Result<bool> {
+    info!("Launching validate-model...");
+
+    let model = read(&opt)?;
+    let report = model.validate();
+    info!(
+        "{} errors, {} warnings, {} infos",
+        report.count(Severity::Error),
+        report.count(Severity::Warning),
+        report.count(Severity::Info)
+    );
+    match opt.output {
+        Some(ref path) => {
+            let file = File::create(path).with_context(|_| format!("Error writing {:?}", path))?;
+            write_report(file, &report, opt.json)?;
+        }
+        None => write_report(io::stdout(), &report, opt.json)?,
+    }
+    Ok(report.max_severity().is_none_or(|s| s < opt.fail_on))
+}
+
+fn main() {
+    let opt = Opt::from_args();
+    opt.log.init_logger();
+    match run(opt) {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(2),
+        Err(err) => {
+            for cause in err.iter_chain() {
+                eprintln!("{}", cause);
+            }
+            std::process::exit(1);
+        }
+    }
+}
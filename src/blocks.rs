@@ -0,0 +1,82 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! Interlining of the vehicle journeys of a same block, operated in a
+//! row by the same vehicle.
+
+use collection::Collection;
+use model::Collections;
+use objects::{VehicleJourney, VehicleJourneyLink};
+use std::collections::BTreeMap;
+
+// Whether the vehicle journeys run on a same day.
+fn run_together(c: &Collections, vj: &VehicleJourney, other: &VehicleJourney) -> bool {
+    match (
+        c.calendars.get(&vj.service_id),
+        c.calendars.get(&other.service_id),
+    ) {
+        (Some(calendar), Some(other)) => !calendar.dates.is_disjoint(&other.dates),
+        _ => false,
+    }
+}
+
+/// Replaces the links between the vehicle journeys by the ones of
+/// their blocks, and returns their number.
+///
+/// A vehicle journey is followed by the first vehicle journey of its
+/// block departing after its arrival and running on one of its days.
+/// They are linked when the second one starts at the stop point where
+/// the first one ends, the travellers being able to stay seated.
+pub fn build_vehicle_journey_links(collections: &mut Collections) -> usize {
+    let mut links = vec![];
+    {
+        let c = &*collections;
+        let mut blocks: BTreeMap<&str, Vec<&VehicleJourney>> = BTreeMap::new();
+        for vj in c.vehicle_journeys.values() {
+            if let Some(ref block_id) = vj.block_id {
+                if !vj.stop_times.is_empty() {
+                    blocks.entry(block_id).or_default().push(vj);
+                }
+            }
+        }
+        for vjs in blocks.values_mut() {
+            vjs.sort_by_key(|vj| vj.stop_times[0].departure_time);
+            for (i, vj) in vjs.iter().enumerate() {
+                let last = &vj.stop_times[vj.stop_times.len() - 1];
+                let next = vjs[i + 1..].iter().find(|next| {
+                    next.stop_times[0].departure_time >= last.arrival_time
+                        && run_together(c, vj, next)
+                });
+                if let Some(next) = next {
+                    if next.stop_times[0].stop_point_idx == last.stop_point_idx {
+                        links.push(VehicleJourneyLink {
+                            from_vehicle_journey_id: vj.id.clone(),
+                            to_vehicle_journey_id: next.id.clone(),
+                            stop_point_id: c.stop_points[last.stop_point_idx].id.clone().into(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+    info!(
+        "{} vehicle journey links built from the blocks",
+        links.len()
+    );
+    let nb_links = links.len();
+    collections.vehicle_journey_links = Collection::new(links);
+    nb_links
+}
@@ -0,0 +1,188 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! Verification of the checksum of the downloaded archives.
+
+use hex;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+use Result;
+
+/// The expected checksum of a downloaded file, as hexadecimal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Checksum {
+    /// The SHA-256 digest.
+    Sha256(String),
+    /// The MD5 digest.
+    Md5(String),
+}
+
+impl Checksum {
+    fn algorithm(&self) -> &'static str {
+        match *self {
+            Checksum::Sha256(_) => "sha256",
+            Checksum::Md5(_) => "md5",
+        }
+    }
+
+    fn expected(&self) -> &str {
+        match *self {
+            Checksum::Sha256(ref e) | Checksum::Md5(ref e) => e,
+        }
+    }
+
+    /// Fails if the checksum of `content`, downloaded from `source`,
+    /// is not the expected one.
+    pub fn verify(&self, content: &[u8], source: &str) -> Result<()> {
+        let actual = match *self {
+            Checksum::Sha256(_) => hex::encode(Sha256::digest(content)),
+            Checksum::Md5(_) => hex::encode(md5(content)),
+        };
+        ensure!(
+            actual.eq_ignore_ascii_case(self.expected().trim()),
+            "{} checksum mismatch for {}: expected {}, got {}",
+            self.algorithm(),
+            source,
+            self.expected(),
+            actual
+        );
+        Ok(())
+    }
+}
+
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.algorithm(), self.expected())
+    }
+}
+
+/// Parses a checksum given as `sha256:<hex>` or `md5:<hex>`.
+impl FromStr for Checksum {
+    type Err = ::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (algorithm, digest) = match s.find(':') {
+            Some(i) => (&s[..i], &s[i + 1..]),
+            None => bail!("invalid checksum {:?}, expected sha256:<hex> or md5:<hex>", s),
+        };
+        let len = match algorithm.to_lowercase().as_str() {
+            "sha256" => 64,
+            "md5" => 32,
+            _ => bail!("unknown checksum algorithm {:?}, expected sha256 or md5", algorithm),
+        };
+        ensure!(
+            digest.len() == len && digest.chars().all(|c| c.is_ascii_hexdigit()),
+            "invalid {} digest {:?}",
+            algorithm,
+            digest
+        );
+        Ok(if len == 64 {
+            Checksum::Sha256(digest.to_string())
+        } else {
+            Checksum::Md5(digest.to_string())
+        })
+    }
+}
+
+// The MD5 digest of RFC 1321, still published along with many feeds.
+fn md5(content: &[u8]) -> [u8; 16] {
+    const SHIFTS: [u32; 64] = [
+        7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 5, 9, 14, 20, 5, 9, 14, 20, 5,
+        9, 14, 20, 5, 9, 14, 20, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 6, 10,
+        15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+    ];
+    let constants: Vec<u32> = (0..64)
+        .map(|i| ((i as f64 + 1.).sin().abs() * 4_294_967_296.) as u32)
+        .collect();
+
+    let mut message = content.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((content.len() as u64).wrapping_mul(8)).to_le_bytes());
+
+    let mut state: [u32; 4] = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476];
+    for chunk in message.chunks(64) {
+        let words: Vec<u32> = chunk
+            .chunks(4)
+            .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+            .collect();
+        let [mut a, mut b, mut c, mut d] = state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let f = f
+                .wrapping_add(a)
+                .wrapping_add(constants[i])
+                .wrapping_add(words[g]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(f.rotate_left(SHIFTS[i]));
+        }
+        state[0] = state[0].wrapping_add(a);
+        state[1] = state[1].wrapping_add(b);
+        state[2] = state[2].wrapping_add(c);
+        state[3] = state[3].wrapping_add(d);
+    }
+
+    let mut digest = [0; 16];
+    for (i, word) in state.iter().enumerate() {
+        digest[4 * i..4 * i + 4].copy_from_slice(&word.to_le_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn md5_digest() {
+        assert_eq!("d41d8cd98f00b204e9800998ecf8427e", hex::encode(md5(b"")));
+        assert_eq!(
+            "9e107d9d372bb6826bd81d3542a419d6",
+            hex::encode(md5(b"The quick brown fox jumps over the lazy dog"))
+        );
+        let long = vec![b'a'; 1000];
+        assert_eq!("cabe45dcc9ae5b66ba86600cca6b8ba8", hex::encode(md5(&long)));
+    }
+
+    #[test]
+    fn verify_checksum() {
+        let sha256: Checksum =
+            "sha256:E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855"
+                .parse()
+                .unwrap();
+        assert!(sha256.verify(b"", "gtfs.zip").is_ok());
+        assert!(sha256.verify(b"truncated", "gtfs.zip").is_err());
+        let md5: Checksum = "md5:d41d8cd98f00b204e9800998ecf8427e".parse().unwrap();
+        assert!(md5.verify(b"", "gtfs.zip").is_ok());
+        assert!(md5.verify(b"tampered", "gtfs.zip").is_err());
+
+        assert!("sha1:da39a3ee5e6b4b0d3255bfef95601890afd80709"
+            .parse::<Checksum>()
+            .is_err());
+        assert!("md5:d41d8cd9".parse::<Checksum>().is_err());
+        assert!("d41d8cd98f00b204e9800998ecf8427e".parse::<Checksum>().is_err());
+    }
+}
@@ -0,0 +1,210 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! Command line options shared by the binaries, to be flattened in
+//! their own options.
+//!
+//! ```no_run
+//! # #[macro_use] extern crate structopt;
+//! # extern crate navitia_model;
+//! use navitia_model::cli;
+//! use structopt::StructOpt;
+//!
+//! #[derive(Debug, StructOpt)]
+//! struct Opt {
+//!     #[structopt(flatten)]
+//!     input: cli::InputOptions,
+//!     #[structopt(flatten)]
+//!     output: cli::OutputOptions,
+//!     #[structopt(flatten)]
+//!     log: cli::LogOptions,
+//! }
+//!
+//! fn run(opt: Opt) -> navitia_model::Result<()> {
+//!     let model = navitia_model::ntfs::read(opt.input.input)?;
+//!     opt.output.write_ntfs(&model)
+//! }
+//!
+//! fn main() {
+//!     let opt = Opt::from_args();
+//!     opt.log.init_logger();
+//!     cli::run(|| run(opt));
+//! }
+//! ```
+
+use chrono::{self, NaiveDate};
+use env_logger;
+use model::Model;
+use ntfs;
+use objects::PrefixConfiguration;
+use progress::{self, Event, Operation, Stage};
+use report::ParseProfile;
+use std::path::PathBuf;
+use std::process;
+use Result;
+
+/// The input directory.
+#[derive(Debug, StructOpt)]
+pub struct InputOptions {
+    /// input directory.
+    #[structopt(short = "i", long = "input", parse(from_os_str), default_value = ".")]
+    pub input: PathBuf,
+}
+
+/// The output directory.
+#[derive(Debug, StructOpt)]
+pub struct OutputOptions {
+    /// output directory, or zip archive with a .zip extension
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    pub output: PathBuf,
+}
+
+impl OutputOptions {
+    /// Writes the model as an NTFS in the output directory, or in a
+    /// zip archive if the output has a `.zip` extension.
+    pub fn write_ntfs(&self, model: &Model) -> Result<()> {
+        let is_zip = self
+            .output
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"));
+        if is_zip {
+            ntfs::write_to_zip(model, &self.output)
+        } else {
+            ntfs::write(model, &self.output)
+        }
+    }
+}
+
+/// The options of the readers of the formats converted to an NTFS.
+#[derive(Debug, StructOpt)]
+pub struct ReadOptions {
+    /// config file
+    #[structopt(short = "c", long = "config", parse(from_os_str))]
+    pub config_path: Option<PathBuf>,
+
+    /// prefix
+    #[structopt(short = "p", long = "prefix")]
+    pub prefix: Option<String>,
+
+    /// separator between the prefix and the identifiers
+    #[structopt(long = "prefix-separator", default_value = ":")]
+    pub prefix_separator: String,
+
+    /// collection whose identifiers are not prefixed, as
+    /// physical_modes
+    #[structopt(long = "unprefixed-collection")]
+    pub unprefixed_collections: Vec<String>,
+
+    /// pattern of the identifiers not prefixed, where * matches any
+    /// sequence of characters
+    #[structopt(long = "unprefixed-id")]
+    pub unprefixed_ids: Vec<String>,
+
+    /// parse profile, strict to fail on the first invalid element
+    /// but for the optional ones, permissive to skip the invalid
+    /// elements
+    #[structopt(long = "profile", default_value = "strict")]
+    pub profile: ParseProfile,
+}
+
+impl ReadOptions {
+    /// Returns the configuration of the prefix of the identifiers,
+    /// `None` without prefix.
+    pub fn prefix_configuration(&self) -> Option<PrefixConfiguration> {
+        self.prefix.as_ref().map(|prefix| PrefixConfiguration {
+            separator: self.prefix_separator.clone(),
+            exempt_collections: self.unprefixed_collections.iter().cloned().collect(),
+            exempt_patterns: self.unprefixed_ids.clone(),
+            ..PrefixConfiguration::new(prefix.as_str())
+        })
+    }
+
+    /// Prefixes the identifiers of the model read without prefix, as
+    /// configured by the options.
+    pub fn add_prefix(&self, model: Model) -> Result<Model> {
+        match self.prefix_configuration() {
+            Some(prefix_conf) => {
+                let mut collections = model.into_collections();
+                collections.add_prefix(&prefix_conf)?;
+                Model::new(collections)
+            }
+            None => Ok(model),
+        }
+    }
+}
+
+/// The logging options.
+#[derive(Debug, StructOpt)]
+pub struct LogOptions {
+    /// log level, or filters as `navitia_model=debug`, overriding the
+    /// RUST_LOG environment variable
+    #[structopt(long = "log-level")]
+    pub log_level: Option<String>,
+
+    /// log the progress of the reading and writing of the files
+    #[structopt(long = "progress")]
+    pub progress: bool,
+}
+
+fn log_progress(event: &Event) {
+    let verb = match event.operation {
+        Operation::Read => "Read",
+        Operation::Write => "Wrote",
+    };
+    match (event.stage, event.size) {
+        (Stage::Started, _) => {}
+        (Stage::InProgress, Some(size)) if size > 0 => info!(
+            "{} {} MiB of {:?} ({}%)",
+            verb,
+            event.bytes >> 20,
+            event.path,
+            event.bytes * 100 / size
+        ),
+        (Stage::InProgress, _) => info!("{} {} MiB of {:?}", verb, event.bytes >> 20, event.path),
+        (Stage::Finished, _) => info!("{} {} lines of {:?}", verb, event.lines, event.path),
+    }
+}
+
+impl LogOptions {
+    /// Initializes the logger from the RUST_LOG environment variable
+    /// and the log level option, and the logging of the progress.
+    pub fn init_logger(&self) {
+        let mut builder = env_logger::Builder::from_default_env();
+        if let Some(ref log_level) = self.log_level {
+            builder.parse(log_level);
+        }
+        builder.init();
+        if self.progress {
+            progress::set_handler(log_progress);
+        }
+    }
+}
+
+/// Parses a date given as `YYYYMMDD`, as in the NTFS and GTFS files.
+pub fn parse_date(s: &str) -> ::std::result::Result<NaiveDate, chrono::ParseError> {
+    NaiveDate::parse_from_str(s, "%Y%m%d")
+}
+
+/// Runs the body of a binary, printing the chain of causes and
+/// exiting with an error code on failure.
+pub fn run<F: FnOnce() -> Result<()>>(f: F) {
+    if let Err(err) = f() {
+        for cause in err.iter_chain() {
+            eprintln!("{}", cause);
+        }
+        process::exit(1);
+    }
+}
@@ -17,15 +17,18 @@
 //! Collections of objects with typed indices and buildin identifier
 //! support.
 
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::borrow::Borrow;
 use std::cmp::Ordering;
 use std::collections::hash_map::Entry::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::iter;
 use std::marker::PhantomData;
 use std::ops;
 use std::result::Result as StdResult;
 use std::slice;
+use std::sync::Arc;
 use Result;
 
 /// An object that has a unique identifier.
@@ -60,6 +63,192 @@ impl<T> PartialOrd for Idx<T> {
     }
 }
 
+/// Typed identifier, referencing an object of a `CollectionWithId<T>`
+/// by its identifier without mixing up the identifiers of different
+/// types of objects.  The references between the objects, as the
+/// `route_id` of a vehicle journey, are typed identifiers: a
+/// `TypedId<Line>` can't be given as the route of a vehicle journey.
+///
+/// A typed identifier dereferences to its identifier, and is
+/// (de)serialized as this identifier.  Its identifier is shared by
+/// its clones, and by the other typed identifiers given to the same
+/// `Interner`.
+///
+/// # Examples
+///
+/// ```
+/// # use navitia_model::collection::*;
+/// # fn run() -> navitia_model::Result<()> {
+/// # #[derive(PartialEq, Debug)] struct Obj(&'static str);
+/// # impl Id<Obj> for Obj { fn id(&self) -> &str { self.0 } }
+/// let c = CollectionWithId::new(vec![Obj("foo"), Obj("bar")])?;
+/// let id: TypedId<Obj> = TypedId::new("bar");
+/// assert_eq!(c.get_typed(&id), Some(&Obj("bar")));
+/// assert_eq!(TypedId::of(&Obj("foo")).as_str(), "foo");
+/// # Ok(())
+/// # }
+/// # fn main() { run().unwrap() }
+/// ```
+#[derive(Derivative, Debug)]
+#[derivative(
+    Clone(bound = ""),
+    Default(bound = ""),
+    PartialEq(bound = ""),
+    Eq(bound = ""),
+    Hash(bound = "")
+)]
+pub struct TypedId<T>(Arc<str>, PhantomData<T>);
+
+impl<T> TypedId<T> {
+    /// Creates the typed identifier of an object of type `T`.
+    pub fn new<S: Into<Arc<str>>>(id: S) -> Self {
+        TypedId(id.into(), PhantomData)
+    }
+    /// Returns the identifier as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+    /// Converts the typed identifier into its identifier.
+    pub fn into_string(self) -> String {
+        self.0.to_string()
+    }
+    /// Returns the typed identifier of `object`, or of the object of
+    /// type `T` it references, as the route of a vehicle journey.
+    pub fn of<O: Id<T>>(object: &O) -> Self {
+        TypedId::new(object.id())
+    }
+}
+impl<T> Ord for TypedId<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+impl<T> PartialOrd for TypedId<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl<T> fmt::Display for TypedId<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+impl<T> ops::Deref for TypedId<T> {
+    type Target = str;
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+impl<T> Borrow<str> for TypedId<T> {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+impl<T> AsRef<str> for TypedId<T> {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+impl<T> From<String> for TypedId<T> {
+    fn from(id: String) -> Self {
+        TypedId::new(id)
+    }
+}
+impl<T> From<&str> for TypedId<T> {
+    fn from(id: &str) -> Self {
+        TypedId::new(id)
+    }
+}
+impl<T> From<TypedId<T>> for String {
+    fn from(id: TypedId<T>) -> Self {
+        id.into_string()
+    }
+}
+impl<T> PartialEq<str> for TypedId<T> {
+    fn eq(&self, other: &str) -> bool {
+        *self.0 == *other
+    }
+}
+impl<T> PartialEq<&str> for TypedId<T> {
+    fn eq(&self, other: &&str) -> bool {
+        *self.0 == **other
+    }
+}
+impl<T> PartialEq<String> for TypedId<T> {
+    fn eq(&self, other: &String) -> bool {
+        *self.0 == **other
+    }
+}
+impl<T> PartialEq<TypedId<T>> for str {
+    fn eq(&self, other: &TypedId<T>) -> bool {
+        *self == *other.0
+    }
+}
+impl<T> PartialEq<TypedId<T>> for &str {
+    fn eq(&self, other: &TypedId<T>) -> bool {
+        **self == *other.0
+    }
+}
+impl<T> PartialEq<TypedId<T>> for String {
+    fn eq(&self, other: &TypedId<T>) -> bool {
+        **self == *other.0
+    }
+}
+impl<T> Serialize for TypedId<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> StdResult<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+impl<'de, T> Deserialize<'de> for TypedId<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> StdResult<Self, D::Error> {
+        String::deserialize(deserializer).map(TypedId::new)
+    }
+}
+
+/// Interns strings: the identical strings given to an interner share
+/// a single allocation, as the route and calendar identifiers
+/// repeated by the vehicle journeys.
+///
+/// # Examples
+///
+/// ```
+/// # use navitia_model::collection::*;
+/// # struct Obj;
+/// let mut interner = Interner::default();
+/// let mut first: TypedId<Obj> = TypedId::new("foo");
+/// let mut second: TypedId<Obj> = TypedId::new("foo".to_string());
+/// interner.intern_id(&mut first);
+/// interner.intern_id(&mut second);
+/// assert_eq!(first.as_ptr(), second.as_ptr());
+/// assert!(::std::sync::Arc::ptr_eq(
+///     &interner.intern("bar"),
+///     &interner.intern("bar")
+/// ));
+/// ```
+#[derive(Debug, Default)]
+pub struct Interner(HashSet<Arc<str>>);
+
+impl Interner {
+    /// Returns the shared allocation of `s`.
+    pub fn intern(&mut self, s: &str) -> Arc<str> {
+        if let Some(interned) = self.0.get(s) {
+            return interned.clone();
+        }
+        let interned: Arc<str> = Arc::from(s);
+        self.0.insert(interned.clone());
+        interned
+    }
+    /// Makes `id` share the allocation of its identifier.
+    pub fn intern_id<T>(&mut self, id: &mut TypedId<T>) {
+        match self.0.get(&*id.0) {
+            Some(interned) => id.0 = interned.clone(),
+            None => {
+                self.0.insert(id.0.clone());
+            }
+        }
+    }
+}
+
 /// The `Collection` object looks like a `Map<Idx<T>, T>`, with opaque
 /// keys.  Then, you can easily store indices and don't mess up
 /// between different types of indices.
@@ -101,6 +290,19 @@ impl<T> Collection<T> {
         self.objects.len()
     }
 
+    /// Returns `true` if the `Collection` contains no object.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use navitia_model::collection::*;
+    /// let c: Collection<i32> = Collection::new(vec![]);
+    /// assert!(c.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
     /// Iterates over the `(Idx<T>, &T)` of the `Collection`.
     ///
     /// # Examples
@@ -224,6 +426,22 @@ impl<T> Collection<T> {
         }
         Ok(())
     }
+
+    /// Keeps only the objects for which `f` returns `true`.  The
+    /// indices of the kept objects may change.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use navitia_model::collection::*;
+    /// # #[derive(PartialEq, Debug)] struct Obj(&'static str);
+    /// let mut c = Collection::new(vec![Obj("foo"), Obj("bar"), Obj("baz")]);
+    /// c.retain(|o| o.0.starts_with('b'));
+    /// assert_eq!(c.values().collect::<Vec<_>>(), &[&Obj("bar"), &Obj("baz")]);
+    /// ```
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, f: F) {
+        self.objects.retain(f);
+    }
 }
 
 /// The type returned by `Collection::iter`.
@@ -447,6 +665,36 @@ impl<T: Id<T>> CollectionWithId<T> {
         }
         Ok(())
     }
+
+    /// Keeps only the objects for which `f` returns `true`.  The
+    /// indices of the kept objects may change.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use navitia_model::collection::*;
+    /// # fn run() -> navitia_model::Result<()> {
+    /// # #[derive(PartialEq, Debug)] struct Obj(&'static str);
+    /// # impl Id<Obj> for Obj { fn id(&self) -> &str { self.0 } }
+    /// let mut c = CollectionWithId::new(vec![Obj("foo"), Obj("bar"), Obj("baz")])?;
+    /// c.retain(|o| o.0.starts_with('b'));
+    /// assert_eq!(c.len(), 2);
+    /// assert!(c.get("foo").is_none());
+    /// assert_eq!(&c[c.get_idx("baz").unwrap()], &Obj("baz"));
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap() }
+    /// ```
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, f: F) {
+        self.collection.retain(f);
+        self.id_to_idx = self
+            .collection
+            .objects
+            .iter()
+            .enumerate()
+            .map(|(i, obj)| (obj.id().to_string(), Idx::new(i)))
+            .collect();
+    }
 }
 
 impl<T> CollectionWithId<T> {
@@ -492,6 +740,12 @@ impl<T> CollectionWithId<T> {
         self.get_idx(id).map(|idx| &self[idx])
     }
 
+    /// Returns a reference to the object corresponding to the typed
+    /// identifier, see `TypedId`.
+    pub fn get_typed(&self, id: &TypedId<T>) -> Option<&T> {
+        self.get(id.as_str())
+    }
+
     /// Converts `self` into a vector without clones or allocation.
     ///
     /// # Examples
@@ -16,10 +16,11 @@
 
 use chrono::{self, Datelike};
 use collection::*;
-use csv;
 use failure::ResultExt;
 use model::Collections;
 use objects::{self, Date, ExceptionType};
+use progress;
+use read_utils::{opt_csv_reader, FileHandler};
 use std::collections::BTreeSet;
 use std::path;
 use utils::*;
@@ -96,18 +97,18 @@ impl Calendar {
     }
 }
 
-fn manage_calendar_dates(
+fn manage_calendar_dates<H>(
     calendars: &mut CollectionWithId<objects::Calendar>,
-    path: &path::Path,
-) -> Result<()> {
+    file_handler: &mut H,
+) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
     let file = "calendar_dates.txt";
-    let path = path.join(file);
-    if !path.exists() {
-        info!("Skipping {}", file);
-    } else {
+    let (rdr, path) = opt_csv_reader(file_handler, file)?;
+    if let Some(mut rdr) = rdr {
         info!("Reading {}", file);
 
-        let mut rdr = csv::Reader::from_path(&path).with_context(ctx_from_path!(path))?;
         for calendar_date in rdr.deserialize() {
             let calendar_date: CalendarDate = calendar_date.with_context(ctx_from_path!(path))?;
 
@@ -134,22 +135,22 @@ fn manage_calendar_dates(
                 }
             });
         }
+    } else {
+        info!("Skipping {}", file);
     }
 
     Ok(())
 }
 
-pub fn manage_calendars(collections: &mut Collections, path: &path::Path) -> Result<()> {
+pub fn manage_calendars<H>(collections: &mut Collections, file_handler: &mut H) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
     let mut calendars: Vec<objects::Calendar> = vec![];
 
     let file = "calendar.txt";
-    let calendar_path = path.join(file);
-    if !calendar_path.exists() {
-        info!("Skipping {}", file);
-    } else {
+    if let (Some(mut rdr), calendar_path) = opt_csv_reader(&mut *file_handler, file)? {
         info!("Reading {}", file);
-        let mut rdr =
-            csv::Reader::from_path(&calendar_path).with_context(ctx_from_path!(calendar_path))?;
         for calendar in rdr.deserialize() {
             let calendar: Calendar = calendar.with_context(ctx_from_path!(calendar_path))?;
             calendars.push(objects::Calendar {
@@ -157,11 +158,173 @@ pub fn manage_calendars(collections: &mut Collections, path: &path::Path) -> Res
                 dates: calendar.get_valid_dates(),
             });
         }
+    } else {
+        info!("Skipping {}", file);
     }
 
     collections.calendars = CollectionWithId::new(calendars)?;
 
-    manage_calendar_dates(&mut collections.calendars, &path)?;
+    manage_calendar_dates(&mut collections.calendars, file_handler)?;
 
     Ok(())
 }
+
+// Finds the days of the week the calendar runs on over the period of
+// its dates, and the dates to add or remove from this weekly pattern.
+// Returns no weekly pattern when listing all the dates is shorter.
+fn compress_calendar(calendar: &objects::Calendar) -> (Option<Calendar>, Vec<CalendarDate>) {
+    let (start_date, end_date) = match (calendar.dates.iter().next(), calendar.dates.iter().last())
+    {
+        (Some(start_date), Some(end_date)) => (*start_date, *end_date),
+        _ => return (None, vec![]),
+    };
+    let period: Vec<Date> = (0..(end_date - start_date).num_days() + 1)
+        .map(|i| start_date + chrono::Duration::days(i))
+        .collect();
+
+    let mut nb_days = [0; 7];
+    let mut nb_active_days = [0; 7];
+    for date in &period {
+        let weekday = date.weekday().num_days_from_monday() as usize;
+        nb_days[weekday] += 1;
+        if calendar.dates.contains(date) {
+            nb_active_days[weekday] += 1;
+        }
+    }
+    let mut week = [false; 7];
+    for (weekday, active) in week.iter_mut().enumerate() {
+        *active = 2 * nb_active_days[weekday] > nb_days[weekday];
+    }
+
+    let exception = |date: Date, exception_type| CalendarDate {
+        service_id: calendar.id.clone(),
+        date,
+        exception_type,
+    };
+    let exceptions: Vec<CalendarDate> = period
+        .iter()
+        .filter_map(|date| {
+            let in_week = week[date.weekday().num_days_from_monday() as usize];
+            match (in_week, calendar.dates.contains(date)) {
+                (true, false) => Some(exception(*date, ExceptionType::Remove)),
+                (false, true) => Some(exception(*date, ExceptionType::Add)),
+                _ => None,
+            }
+        }).collect();
+    if exceptions.len() + 1 >= calendar.dates.len() {
+        let dates = calendar
+            .dates
+            .iter()
+            .map(|date| exception(*date, ExceptionType::Add))
+            .collect();
+        return (None, dates);
+    }
+
+    let weekly_pattern = Calendar {
+        id: calendar.id.clone(),
+        monday: week[0],
+        tuesday: week[1],
+        wednesday: week[2],
+        thursday: week[3],
+        friday: week[4],
+        saturday: week[5],
+        sunday: week[6],
+        start_date,
+        end_date,
+    };
+    (Some(weekly_pattern), exceptions)
+}
+
+/// Writes the calendars as weekly patterns in calendar.txt, with the
+/// dates not following them in calendar_dates.txt, or as a list of
+/// dates in calendar_dates.txt when it is shorter.
+pub fn write_calendar_and_calendar_dates(
+    path: &path::Path,
+    calendars: &CollectionWithId<objects::Calendar>,
+) -> Result<()> {
+    let mut weekly_patterns = vec![];
+    let mut calendar_dates = vec![];
+    for c in calendars.values() {
+        let (weekly_pattern, dates) = compress_calendar(c);
+        weekly_patterns.extend(weekly_pattern);
+        calendar_dates.extend(dates);
+    }
+
+    if !weekly_patterns.is_empty() {
+        info!("Writing calendar.txt");
+        let calendar_path = path.join("calendar.txt");
+        let mut wtr = progress::csv_writer(&calendar_path)?;
+        for weekly_pattern in weekly_patterns {
+            wtr.serialize(weekly_pattern)
+                .with_context(ctx_from_path!(calendar_path))?;
+        }
+        wtr.flush().with_context(ctx_from_path!(calendar_path))?;
+    }
+
+    info!("Writing calendar_dates.txt");
+    let calendar_dates_path = path.join("calendar_dates.txt");
+    let mut wtr = progress::csv_writer(&calendar_dates_path)?;
+    for calendar_date in calendar_dates {
+        wtr.serialize(calendar_date)
+            .with_context(ctx_from_path!(calendar_dates_path))?;
+    }
+    wtr.flush()
+        .with_context(ctx_from_path!(calendar_dates_path))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn compress_weekly_calendar() {
+        // the weekdays of 2018, but the 1st of May, and a Saturday
+        let mut dates: BTreeSet<Date> = (0..365)
+            .map(|i| NaiveDate::from_ymd(2018, 1, 1) + chrono::Duration::days(i))
+            .filter(|d| d.weekday().num_days_from_monday() < 5)
+            .collect();
+        dates.remove(&NaiveDate::from_ymd(2018, 5, 1));
+        dates.insert(NaiveDate::from_ymd(2018, 6, 16));
+        let calendar = objects::Calendar {
+            id: "c".to_string(),
+            dates,
+        };
+
+        let (weekly_pattern, exceptions) = compress_calendar(&calendar);
+        let weekly_pattern = weekly_pattern.unwrap();
+        assert!(weekly_pattern.monday && weekly_pattern.friday);
+        assert!(!weekly_pattern.saturday && !weekly_pattern.sunday);
+        assert_eq!(NaiveDate::from_ymd(2018, 1, 1), weekly_pattern.start_date);
+        assert_eq!(NaiveDate::from_ymd(2018, 12, 31), weekly_pattern.end_date);
+        let exceptions: Vec<_> = exceptions
+            .iter()
+            .map(|e| (e.date, e.exception_type.clone()))
+            .collect();
+        assert_eq!(
+            exceptions,
+            vec![
+                (NaiveDate::from_ymd(2018, 5, 1), ExceptionType::Remove),
+                (NaiveDate::from_ymd(2018, 6, 16), ExceptionType::Add),
+            ]
+        );
+    }
+
+    #[test]
+    fn keep_dates_of_short_calendar() {
+        let mut dates = BTreeSet::new();
+        dates.insert(NaiveDate::from_ymd(2018, 5, 1));
+        dates.insert(NaiveDate::from_ymd(2018, 5, 10));
+        let calendar = objects::Calendar {
+            id: "c".to_string(),
+            dates,
+        };
+
+        let (weekly_pattern, exceptions) = compress_calendar(&calendar);
+        assert!(weekly_pattern.is_none());
+        assert_eq!(2, exceptions.len());
+        assert!(exceptions.iter().all(|e| e.exception_type == ExceptionType::Add));
+    }
+}
@@ -0,0 +1,265 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! Consolidation of the lines of an imported model.
+//!
+//! The line consolidation rules are csv files with the
+//! `network_id,line_code,line_id` columns: all the lines of the
+//! network having the code are merged in a single line of identifier
+//! `line_id`, with the routes of all of them.  The merged line keeps
+//! the properties of the line already having this identifier, or else
+//! of the first one, and the identifiers of the other lines as object
+//! codes of the `original_id` system.
+//!
+//! The route consolidation merges the routes of a line having the same
+//! direction type and main destination, as the routes created for
+//! each direction of a GTFS route.
+
+use apply_rules::{read_rules, warning};
+use collection::CollectionWithId;
+use model::{Collections, Model};
+use ntfs::ORIGINAL_ID_SYSTEM;
+use objects::{Codes, CommentLinks, Line, ObjectType, Properties, Route};
+use report::Warning;
+use std::collections::HashMap;
+use std::path::Path;
+use Result;
+
+#[derive(Deserialize, Debug)]
+struct LineRule {
+    network_id: String,
+    line_code: String,
+    line_id: String,
+}
+
+fn push_unique<T: PartialEq>(values: &mut Vec<T>, value: T) {
+    if !values.contains(&value) {
+        values.push(value);
+    }
+}
+
+// Records that the line `old_id` is now `new_id`, as well as the lines
+// already merged in it.
+fn record(ids: &mut HashMap<String, String>, old_id: String, new_id: &str) {
+    for id in ids.values_mut().filter(|id| **id == old_id) {
+        *id = new_id.to_string();
+    }
+    ids.insert(old_id, new_id.to_string());
+}
+
+// Merges the codes, properties and comments of `object` in `target`,
+// keeping its identifier as an `original_id` code.
+fn merge_object<T>(target: &mut T, id: String, object: &T)
+where
+    T: Codes + Properties + CommentLinks,
+{
+    target
+        .codes_mut()
+        .push((ORIGINAL_ID_SYSTEM.to_string(), id));
+    for code in object.codes() {
+        push_unique(target.codes_mut(), code.clone());
+    }
+    for property in object.properties() {
+        push_unique(target.properties_mut(), property.clone());
+    }
+    for comment_link in object.comment_links() {
+        push_unique(target.comment_links_mut(), *comment_link);
+    }
+}
+
+// Merges the lines of the rule, recording the new identifier of each
+// merged line in `ids`.
+fn consolidate(
+    lines: &mut Vec<Line>,
+    rule: &LineRule,
+    ids: &mut HashMap<String, String>,
+) -> Result<()> {
+    let is_merged = |line: &Line| {
+        line.network_id == rule.network_id && line.code.as_ref() == Some(&rule.line_code)
+    };
+    ensure!(
+        lines.iter().any(&is_merged),
+        "no line with code {} in network {}",
+        rule.line_code,
+        rule.network_id
+    );
+    ensure!(
+        !lines
+            .iter()
+            .any(|line| line.id == rule.line_id && !is_merged(line)),
+        "line {} already exists",
+        rule.line_id
+    );
+    let (mut merged, others): (Vec<_>, Vec<_>) = lines.drain(..).partition(|l| is_merged(l));
+    let target_pos = merged
+        .iter()
+        .position(|line| line.id == rule.line_id)
+        .unwrap_or(0);
+    let mut target = merged.remove(target_pos);
+    if target.id != rule.line_id {
+        let old_id = ::std::mem::replace(&mut target.id, rule.line_id.clone());
+        target
+            .codes
+            .push((ORIGINAL_ID_SYSTEM.to_string(), old_id.clone()));
+        record(ids, old_id, &rule.line_id);
+    }
+    for line in merged {
+        merge_object(&mut target, line.id.clone(), &line);
+        record(ids, line.id, &rule.line_id);
+    }
+    *lines = others;
+    lines.push(target);
+    Ok(())
+}
+
+fn rewrite<S>(id: &mut S, ids: &HashMap<String, String>)
+where
+    S: AsRef<str> + From<String>,
+{
+    if let Some(new_id) = ids.get(id.as_ref()) {
+        *id = new_id.clone().into();
+    }
+}
+
+// Updates the references to the merged lines.
+fn rewrite_line_ids(c: &mut Collections, ids: &HashMap<String, String>) -> Result<()> {
+    let mut routes = c.routes.take();
+    for route in &mut routes {
+        rewrite(&mut route.line_id, ids);
+    }
+    c.routes = CollectionWithId::new(routes)?;
+    let mut line_groups = c.line_groups.take();
+    for line_group in &mut line_groups {
+        rewrite(&mut line_group.main_line_id, ids);
+    }
+    c.line_groups = CollectionWithId::new(line_groups)?;
+    for link in c.line_group_links.values_mut() {
+        rewrite(&mut link.line_id, ids);
+    }
+    for rel in c.grid_rel_calendar_line.values_mut() {
+        rewrite(&mut rel.line_id, ids);
+    }
+    for perimeter in c.ticket_use_perimeters.values_mut() {
+        if perimeter.object_type == ObjectType::Line {
+            rewrite(&mut perimeter.object_id, ids);
+        }
+    }
+    Ok(())
+}
+
+/// Merges the lines according to the line consolidation rules,
+/// moving their routes, and thus their vehicle journeys, to the
+/// consolidated lines.  The rules that cannot be applied, as the ones
+/// matching no line, are ignored and returned as warnings.  Fails if
+/// a rule file cannot be read.
+pub fn consolidate_lines<P: AsRef<Path>>(
+    model: Model,
+    rules_files: &[P],
+) -> Result<(Model, Vec<Warning>)> {
+    let mut c = model.into_collections();
+    let mut lines = c.lines.take();
+    let mut ids = HashMap::new();
+    let mut warnings = vec![];
+    for path in rules_files {
+        let path = path.as_ref();
+        let rules: Vec<LineRule> = read_rules(path)?;
+        for (i, rule) in rules.iter().enumerate() {
+            if let Err(e) = consolidate(&mut lines, rule, &mut ids) {
+                warnings.push(warning(path, i + 1, &e));
+            }
+        }
+    }
+    c.lines = CollectionWithId::new(lines)?;
+    rewrite_line_ids(&mut c, &ids)?;
+    Ok((Model::new(c)?, warnings))
+}
+
+// The stop area ending the most vehicle journeys of each route, the
+// one of lowest identifier in case of a tie.
+fn main_destinations(model: &Model) -> HashMap<String, String> {
+    let mut counts: HashMap<&str, HashMap<&str, usize>> = HashMap::new();
+    for vj in model.vehicle_journeys.values() {
+        if let Some(stop_time) = vj.stop_times.last() {
+            let stop_area_id = &model.stop_points[stop_time.stop_point_idx].stop_area_id;
+            *counts
+                .entry(&vj.route_id)
+                .or_default()
+                .entry(stop_area_id)
+                .or_insert(0) += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .filter_map(|(route_id, destinations)| {
+            destinations
+                .into_iter()
+                .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+                .map(|(destination, _)| (route_id.to_string(), destination.to_string()))
+        })
+        .collect()
+}
+
+/// Merges the routes of a same line having the same direction type
+/// and main destination, the stop area ending most of their vehicle
+/// journeys, in the first of them.  The `destination_id` of the routes
+/// is set to their main destination, and the merged routes are kept
+/// as `original_id` codes.  The routes without vehicle journey are
+/// left untouched.
+pub fn consolidate_routes(model: Model) -> Result<Model> {
+    let destinations = main_destinations(&model);
+    let mut c = model.into_collections();
+    let mut routes: Vec<Route> = vec![];
+    let mut groups = HashMap::new();
+    let mut ids = HashMap::new();
+    for mut route in c.routes.take() {
+        let destination = match destinations.get(&route.id) {
+            Some(destination) => destination,
+            None => {
+                routes.push(route);
+                continue;
+            }
+        };
+        route.destination_id = Some(destination.clone());
+        let key = (
+            route.line_id.clone(),
+            route.direction_type.clone(),
+            destination,
+        );
+        match groups.get(&key) {
+            Some(&pos) => {
+                let target: &mut Route = &mut routes[pos];
+                merge_object(target, route.id.clone(), &route);
+                ids.insert(route.id, target.id.clone());
+            }
+            None => {
+                groups.insert(key, routes.len());
+                routes.push(route);
+            }
+        }
+    }
+    c.routes = CollectionWithId::new(routes)?;
+    let mut vehicle_journeys = c.vehicle_journeys.take();
+    for vj in &mut vehicle_journeys {
+        rewrite(&mut vj.route_id, &ids);
+    }
+    c.vehicle_journeys = CollectionWithId::new(vehicle_journeys)?;
+    for perimeter in c.ticket_use_perimeters.values_mut() {
+        if perimeter.object_type == ObjectType::Route {
+            rewrite(&mut perimeter.object_id, &ids);
+        }
+    }
+    Model::new(c)
+}
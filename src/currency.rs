@@ -0,0 +1,280 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! Currencies and exact decimal amounts of the ticket prices, and
+//! the conversion of the amounts between currencies.
+
+use csv;
+use failure::ResultExt;
+use serde;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops;
+use std::path::Path;
+use std::str::FromStr;
+use {Error, Result};
+
+/// An [ISO 4217](https://en.wikipedia.org/wiki/ISO_4217) currency
+/// code, as `EUR`.
+///
+/// ```
+/// # use navitia_model::currency::Currency;
+/// let euro: Currency = "eur".parse().unwrap();
+/// assert_eq!("EUR", euro.to_string());
+/// assert!("EURO".parse::<Currency>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Currency(String);
+
+impl Currency {
+    /// Returns the code of the currency.
+    pub fn code(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for Currency {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let code = s.trim();
+        ensure!(
+            code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic()),
+            "invalid currency {:?}, expected a 3 letters code as EUR",
+            s
+        );
+        Ok(Currency(code.to_uppercase()))
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+// The number of units of an `Amount` in 1.
+const SCALE: i64 = 1_000_000;
+const DECIMALS: usize = 6;
+
+/// An exact decimal amount, with up to 6 decimal places.
+///
+/// ```
+/// # use navitia_model::currency::Amount;
+/// let price: Amount = "1.10".parse().unwrap();
+/// assert_eq!("3.3", (price + price + price).to_string());
+/// assert!("1.1234567".parse::<Amount>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Amount(i64);
+
+impl Amount {
+    /// Returns the amount of `cents` hundredths, as the prices given in
+    /// cents.
+    pub fn from_cents(cents: i64) -> Self {
+        Amount(cents * (SCALE / 100))
+    }
+
+    /// Multiplies the amount by `rate`, rounding half away from zero
+    /// to the 6th decimal place.
+    pub fn times(self, rate: Amount) -> Amount {
+        let product = i128::from(self.0) * i128::from(rate.0);
+        let half = i128::from(SCALE / 2) * product.signum();
+        Amount(((product + half) / i128::from(SCALE)) as i64)
+    }
+
+    /// Whether the amount is below zero.
+    pub fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+}
+
+impl ops::Add for Amount {
+    type Output = Amount;
+
+    fn add(self, other: Amount) -> Amount {
+        Amount(self.0 + other.0)
+    }
+}
+
+impl FromStr for Amount {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || format_err!("invalid amount {:?}", s);
+        let trimmed = s.trim();
+        let (negative, digits) = match trimmed.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, trimmed),
+        };
+        let (integer, fraction) = match digits.find('.') {
+            Some(i) if i + 1 < digits.len() => (&digits[..i], &digits[i + 1..]),
+            Some(_) => return Err(invalid()),
+            None => (digits, ""),
+        };
+        if integer.is_empty()
+            || fraction.len() > DECIMALS
+            || !integer.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+        let integer: i64 = integer.parse().map_err(|_| invalid())?;
+        let fraction: i64 = format!("{:0<width$}", fraction, width = DECIMALS)
+            .parse()
+            .map_err(|_| invalid())?;
+        let units = integer
+            .checked_mul(SCALE)
+            .and_then(|u| u.checked_add(fraction))
+            .ok_or_else(invalid)?;
+        Ok(Amount(if negative { -units } else { units }))
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let units = self.0.abs();
+        let fraction = format!("{:0width$}", units % SCALE, width = DECIMALS);
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() {
+            write!(f, "{}{}", sign, units / SCALE)
+        } else {
+            write!(f, "{}{}.{}", sign, units / SCALE, fraction)
+        }
+    }
+}
+
+macro_rules! impl_serde_from_str {
+    ($ty:ty, $expecting:expr) => {
+        impl serde::Serialize for $ty {
+            fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $ty {
+            fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                use serde::de::Error;
+                let s = String::deserialize(deserializer)?;
+                s.parse()
+                    .map_err(|e| D::Error::custom(format!("{}, expected {}", e, $expecting)))
+            }
+        }
+    };
+}
+
+impl_serde_from_str!(Currency, "a currency code");
+impl_serde_from_str!(Amount, "a decimal amount");
+
+#[derive(Deserialize, Debug)]
+struct ExchangeRate {
+    from_currency: Currency,
+    to_currency: Currency,
+    rate: Amount,
+}
+
+/// The rates converting the amounts from a currency to another.
+#[derive(Debug, Default, Clone)]
+pub struct ExchangeRates {
+    rates: HashMap<(Currency, Currency), Amount>,
+}
+
+impl ExchangeRates {
+    /// Reads the rates from a csv file with the `from_currency`,
+    /// `to_currency` and `rate` columns.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        info!("Reading exchange rates from {:?}", path);
+        let mut rdr = csv::Reader::from_path(path).with_context(ctx_from_path!(path))?;
+        let mut rates = ExchangeRates::default();
+        for rate in rdr.deserialize() {
+            let rate: ExchangeRate = rate.with_context(ctx_from_path!(path))?;
+            rates.insert(rate.from_currency, rate.to_currency, rate.rate)?;
+        }
+        Ok(rates)
+    }
+
+    /// Sets the rate converting the amounts in `from` to `to`.  Fails
+    /// if the rate is not positive.
+    pub fn insert(&mut self, from: Currency, to: Currency, rate: Amount) -> Result<()> {
+        ensure!(
+            rate > Amount::default(),
+            "invalid rate {} from {} to {}",
+            rate,
+            from,
+            to
+        );
+        self.rates.insert((from, to), rate);
+        Ok(())
+    }
+
+    /// Converts the amount in `from` to `to`.  Fails if there is no
+    /// rate from `from` to `to`.
+    ///
+    /// ```
+    /// # use navitia_model::currency::{Amount, Currency, ExchangeRates};
+    /// let mut rates = ExchangeRates::default();
+    /// let eur: Currency = "EUR".parse().unwrap();
+    /// let chf: Currency = "CHF".parse().unwrap();
+    /// rates.insert(eur.clone(), chf.clone(), "1.1".parse().unwrap()).unwrap();
+    /// let price: Amount = "2.5".parse().unwrap();
+    /// assert_eq!("2.75", rates.convert(price, &eur, &chf).unwrap().to_string());
+    /// assert!(rates.convert(price, &chf, &eur).is_err());
+    /// ```
+    pub fn convert(&self, amount: Amount, from: &Currency, to: &Currency) -> Result<Amount> {
+        if from == to {
+            return Ok(amount);
+        }
+        match self.rates.get(&(from.clone(), to.clone())) {
+            Some(&rate) => Ok(amount.times(rate)),
+            None => bail!("no exchange rate from {} to {}", from, to),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_amounts() {
+        let amount = |s: &str| s.parse::<Amount>().unwrap();
+        assert_eq!("1.5", amount("1.50").to_string());
+        assert_eq!("12", amount(" 12 ").to_string());
+        assert_eq!("0.000001", amount("0.000001").to_string());
+        assert_eq!("-3.25", amount("-3.25").to_string());
+        assert_eq!(amount("0.3"), amount("0.1") + amount("0.1") + amount("0.1"));
+        assert_eq!(Amount::from_cents(150), amount("1.5"));
+        for invalid in &["", ".5", "1.", "1,5", "1.0000001", "1e3", "--1", "99999999999999"] {
+            assert!(invalid.parse::<Amount>().is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn round_conversions() {
+        let amount = |s: &str| s.parse::<Amount>().unwrap();
+        assert_eq!(amount("0.333333"), amount("1").times(amount("0.333333")));
+        assert_eq!(amount("0.000001"), amount("0.000001").times(amount("0.5")));
+        assert_eq!(amount("-0.000001"), amount("-0.000001").times(amount("0.5")));
+        assert_eq!(amount("0"), amount("0.000001").times(amount("0.4")));
+    }
+}
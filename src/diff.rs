@@ -0,0 +1,220 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! Differences between two models, as between two successive
+//! versions of a feed.
+
+use collection::{CollectionWithId, Id};
+use model::Model;
+use objects::Date;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// The identifiers of the objects added, removed and modified in a
+/// collection.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CollectionDiff {
+    /// Name of the collection, as `stop_points`.
+    pub collection: String,
+    /// Identifiers of the objects only in the new model.
+    pub added: Vec<String>,
+    /// Identifiers of the objects only in the old model.
+    pub removed: Vec<String>,
+    /// Identifiers of the objects of both models which differ.
+    pub modified: Vec<String>,
+}
+
+impl CollectionDiff {
+    /// Returns `true` if the collection is the same in both models.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// The service dates of a line added and removed, a date being a
+/// service date of a line when one of its vehicle journeys runs on it.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ServiceDatesDiff {
+    /// Identifier of the line.
+    pub line_id: String,
+    /// Service dates only in the new model.
+    pub added: Vec<Date>,
+    /// Service dates only in the old model.
+    pub removed: Vec<Date>,
+}
+
+/// The differences between two models.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ModelDiff {
+    /// The collections with differences, in the order of the
+    /// `Collections` fields.
+    pub collections: Vec<CollectionDiff>,
+    /// The lines whose service dates changed, sorted by identifier.
+    pub service_dates: Vec<ServiceDatesDiff>,
+}
+
+impl ModelDiff {
+    /// Returns `true` if there is no difference between the models.
+    pub fn is_empty(&self) -> bool {
+        self.collections.is_empty() && self.service_dates.is_empty()
+    }
+
+    /// Returns a human readable summary of the differences.
+    pub fn summary(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for ModelDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "no difference");
+        }
+        for c in &self.collections {
+            writeln!(
+                f,
+                "{}: {} added, {} removed, {} modified",
+                c.collection,
+                c.added.len(),
+                c.removed.len(),
+                c.modified.len()
+            )?;
+        }
+        for d in &self.service_dates {
+            writeln!(
+                f,
+                "service dates of line {}: {} added, {} removed",
+                d.line_id,
+                d.added.len(),
+                d.removed.len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn diff_collection<T>(
+    name: &str,
+    old: &CollectionWithId<T>,
+    new: &CollectionWithId<T>,
+) -> CollectionDiff
+where
+    T: Id<T> + PartialEq,
+{
+    let mut diff = CollectionDiff {
+        collection: name.to_string(),
+        ..Default::default()
+    };
+    for old_obj in old.values() {
+        match new.get(old_obj.id()) {
+            None => diff.removed.push(old_obj.id().to_string()),
+            Some(new_obj) if new_obj != old_obj => diff.modified.push(old_obj.id().to_string()),
+            Some(_) => {}
+        }
+    }
+    diff.added = new
+        .values()
+        .filter(|new_obj| old.get(new_obj.id()).is_none())
+        .map(|new_obj| new_obj.id().to_string())
+        .collect();
+    diff
+}
+
+fn line_service_dates(model: &Model) -> BTreeMap<&str, BTreeSet<Date>> {
+    let mut service_dates = BTreeMap::new();
+    for vj in model.vehicle_journeys.values() {
+        let line_id = match model.routes.get(&vj.route_id) {
+            Some(route) => route.line_id.as_str(),
+            None => continue,
+        };
+        let dates = service_dates.entry(line_id).or_insert_with(BTreeSet::new);
+        if let Some(calendar) = model.calendars.get(&vj.service_id) {
+            dates.extend(calendar.dates.iter().cloned());
+        }
+    }
+    service_dates
+}
+
+fn diff_service_dates(old: &Model, new: &Model) -> Vec<ServiceDatesDiff> {
+    let old_dates = line_service_dates(old);
+    let new_dates = line_service_dates(new);
+    let no_dates = BTreeSet::new();
+    let line_ids: BTreeSet<_> = old_dates.keys().chain(new_dates.keys()).collect();
+    line_ids
+        .into_iter()
+        .map(|line_id| {
+            let old = old_dates.get(line_id).unwrap_or(&no_dates);
+            let new = new_dates.get(line_id).unwrap_or(&no_dates);
+            ServiceDatesDiff {
+                line_id: line_id.to_string(),
+                added: new.difference(old).cloned().collect(),
+                removed: old.difference(new).cloned().collect(),
+            }
+        })
+        .filter(|d| !d.added.is_empty() || !d.removed.is_empty())
+        .collect()
+}
+
+/// Computes the differences between the `old` and the `new` models.
+///
+/// The objects are compared by identifier in the collections of
+/// objects with an identifier, the other collections, as the
+/// transfers, being ignored.
+pub fn diff(old: &Model, new: &Model) -> ModelDiff {
+    let collections = vec![
+        diff_collection("contributors", &old.contributors, &new.contributors),
+        diff_collection("datasets", &old.datasets, &new.datasets),
+        diff_collection("networks", &old.networks, &new.networks),
+        diff_collection(
+            "commercial_modes",
+            &old.commercial_modes,
+            &new.commercial_modes,
+        ),
+        diff_collection("lines", &old.lines, &new.lines),
+        diff_collection("line_groups", &old.line_groups, &new.line_groups),
+        diff_collection("routes", &old.routes, &new.routes),
+        diff_collection(
+            "vehicle_journeys",
+            &old.vehicle_journeys,
+            &new.vehicle_journeys,
+        ),
+        diff_collection("physical_modes", &old.physical_modes, &new.physical_modes),
+        diff_collection("stop_areas", &old.stop_areas, &new.stop_areas),
+        diff_collection("stop_points", &old.stop_points, &new.stop_points),
+        diff_collection("entrances", &old.entrances, &new.entrances),
+        diff_collection("addresses", &old.addresses, &new.addresses),
+        diff_collection("calendars", &old.calendars, &new.calendars),
+        diff_collection("grid_calendars", &old.grid_calendars, &new.grid_calendars),
+        diff_collection("companies", &old.companies, &new.companies),
+        diff_collection("comments", &old.comments, &new.comments),
+        diff_collection("equipments", &old.equipments, &new.equipments),
+        diff_collection(
+            "trip_properties",
+            &old.trip_properties,
+            &new.trip_properties,
+        ),
+        diff_collection("geometries", &old.geometries, &new.geometries),
+        diff_collection("tickets", &old.tickets, &new.tickets),
+        diff_collection("ticket_uses", &old.ticket_uses, &new.ticket_uses),
+        diff_collection("fare_zones", &old.fare_zones, &new.fare_zones),
+        diff_collection("pathways", &old.pathways, &new.pathways),
+        diff_collection("levels", &old.levels, &new.levels),
+    ];
+    ModelDiff {
+        collections: collections.into_iter().filter(|c| !c.is_empty()).collect(),
+        service_dates: diff_service_dates(old, new),
+    }
+}
@@ -0,0 +1,732 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! Detection of the objects published several times by different
+//! contributors, typically after merging feeds, and merge of the
+//! duplicated stops.
+
+use collection::{Collection, CollectionWithId, Idx};
+use model::{rename_ids, Collections};
+use objects::{Codes, CommentLinks, Coord, Date, ObjectType, StopPoint, Time, VehicleJourney};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::mem;
+use Result;
+
+/// A vehicle journey being the same real-world service as another
+/// vehicle journey published by another contributor.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DuplicateVehicleJourney {
+    /// Identifier of the duplicated vehicle journey.
+    pub vehicle_journey_id: String,
+    /// Identifier of the vehicle journey it duplicates, the first one
+    /// found in the collection.
+    pub duplicate_of: String,
+}
+
+// A stop point is identified by its name and its coordinates rounded
+// to about a meter, as stop points of different feeds never share
+// their identifiers.
+type StopKey = (String, i64, i64);
+type VehicleJourneyKey = (Vec<(StopKey, Time, Time)>, BTreeSet<Date>);
+
+fn stop_key(stop_point: &StopPoint) -> StopKey {
+    (
+        stop_point.name.clone(),
+        (stop_point.coord.lon * 1e5).round() as i64,
+        (stop_point.coord.lat * 1e5).round() as i64,
+    )
+}
+
+fn vehicle_journey_key(c: &Collections, vj: &VehicleJourney) -> VehicleJourneyKey {
+    let stops = vj
+        .stop_times
+        .iter()
+        .map(|st| {
+            (
+                stop_key(&c.stop_points[st.stop_point_idx]),
+                st.arrival_time,
+                st.departure_time,
+            )
+        })
+        .collect();
+    let dates = c
+        .calendars
+        .get(&vj.service_id)
+        .map(|calendar| calendar.dates.clone())
+        .unwrap_or_default();
+    (stops, dates)
+}
+
+fn contributor_id<'a>(c: &'a Collections, vj: &VehicleJourney) -> Option<&'a str> {
+    c.datasets
+        .get(&vj.dataset_id)
+        .map(|dataset| dataset.contributor_id.as_str())
+}
+
+/// Finds the vehicle journeys serving the same stops at the same
+/// times on the same days as a vehicle journey of another
+/// contributor.
+///
+/// Vehicle journeys of the same contributor are never considered as
+/// duplicates.
+pub fn find_duplicate_vehicle_journeys(c: &Collections) -> Vec<DuplicateVehicleJourney> {
+    let mut references: HashMap<VehicleJourneyKey, Vec<&VehicleJourney>> = HashMap::new();
+    let mut duplicates = vec![];
+    for vj in c.vehicle_journeys.values() {
+        if vj.stop_times.is_empty() {
+            continue;
+        }
+        let contributor = contributor_id(c, vj);
+        let same_services = references.entry(vehicle_journey_key(c, vj)).or_default();
+        match same_services
+            .iter()
+            .find(|other| contributor_id(c, other) != contributor)
+        {
+            Some(other) => duplicates.push(DuplicateVehicleJourney {
+                vehicle_journey_id: vj.id.clone(),
+                duplicate_of: other.id.clone(),
+            }),
+            None => same_services.push(vj),
+        }
+    }
+    duplicates
+}
+
+/// Removes the duplicated vehicle journeys found by
+/// `find_duplicate_vehicle_journeys`, keeping only the first
+/// published service, and returns them.
+pub fn remove_duplicate_vehicle_journeys(
+    c: &mut Collections,
+) -> Result<Vec<DuplicateVehicleJourney>> {
+    let duplicates = find_duplicate_vehicle_journeys(c);
+    if duplicates.is_empty() {
+        return Ok(duplicates);
+    }
+    let to_remove: BTreeSet<_> = duplicates
+        .iter()
+        .map(|d| d.vehicle_journey_id.as_str())
+        .collect();
+    let vehicle_journeys = c
+        .vehicle_journeys
+        .take()
+        .into_iter()
+        .filter(|vj| !to_remove.contains(vj.id.as_str()))
+        .collect();
+    c.vehicle_journeys = CollectionWithId::new(vehicle_journeys)?;
+    let frequencies = mem::take(&mut c.frequencies)
+        .into_iter()
+        .filter(|f| !to_remove.contains(f.vehicle_journey_id.as_str()))
+        .collect();
+    c.frequencies = Collection::new(frequencies);
+    let vehicle_journey_links = mem::take(&mut c.vehicle_journey_links)
+        .into_iter()
+        .filter(|link| {
+            !to_remove.contains(link.from_vehicle_journey_id.as_str())
+                && !to_remove.contains(link.to_vehicle_journey_id.as_str())
+        })
+        .collect();
+    c.vehicle_journey_links = Collection::new(vehicle_journey_links);
+    let attributions = mem::take(&mut c.attributions)
+        .into_iter()
+        .filter(|a| {
+            !a.vehicle_journey_id
+                .as_ref()
+                .is_some_and(|id| to_remove.contains(id.as_str()))
+        })
+        .collect();
+    c.attributions = Collection::new(attributions);
+    let translations = mem::take(&mut c.translations)
+        .into_iter()
+        .filter(|t| {
+            t.object_type != ObjectType::VehicleJourney || !to_remove.contains(t.object_id.as_str())
+        })
+        .collect();
+    c.translations = Collection::new(translations);
+    Ok(duplicates)
+}
+
+/// The criteria for `dedupe_stops` to consider two stops as the
+/// same physical station.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StopMatching {
+    /// The max distance in meters between the stops.
+    pub max_distance: f64,
+    /// The min similarity of the names, from 0 (any name) to 1 (the
+    /// same name, ignoring the case and the punctuation).
+    pub min_name_similarity: f64,
+    /// Whether the stop points of the merged stop areas are also
+    /// merged.
+    pub merge_stop_points: bool,
+}
+
+impl Default for StopMatching {
+    fn default() -> Self {
+        StopMatching {
+            max_distance: 100.,
+            min_name_similarity: 0.8,
+            merge_stop_points: false,
+        }
+    }
+}
+
+/// A stop area or a stop point merged into another one by
+/// `dedupe_stops`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MergedStop {
+    /// Identifier of the removed stop.
+    pub id: String,
+    /// Identifier of the stop it has been merged into, the first one
+    /// found in the collection.
+    pub merged_into: String,
+}
+
+// The lowercased alphanumeric characters of a name.
+fn normalize_name(name: &str) -> Vec<char> {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+// 1 minus the Levenshtein distance between the names relative to the
+// longest one.
+fn name_similarity(a: &[char], b: &[char]) -> f64 {
+    let len = a.len().max(b.len());
+    if len == 0 {
+        return 1.;
+    }
+    let mut distances: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = distances[0];
+        distances[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = distances[j + 1];
+            distances[j + 1] = if ca == cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(distances[j])
+            };
+            diagonal = above;
+        }
+    }
+    1. - distances[b.len()] as f64 / len as f64
+}
+
+// A stop candidate to the merge: the group inside which the stops
+// can be merged, the source of the stop (stops of the same source are
+// never merged together), its id, name and coordinates.
+struct Candidate<'a> {
+    group: &'a str,
+    source: &'a str,
+    id: &'a str,
+    name: &'a str,
+    coord: &'a Coord,
+}
+
+// Finds the stops matching a previous stop of another source.
+fn find_matches<'a, I>(candidates: I, matching: &StopMatching) -> Vec<MergedStop>
+where
+    I: IntoIterator<Item = Candidate<'a>>,
+{
+    // The kept stops by group and by latitude in microdegrees.
+    type Kept<'a> = BTreeMap<i64, Vec<(usize, Candidate<'a>, Vec<char>)>>;
+    let mut kept: HashMap<&str, Kept> = HashMap::new();
+    let lat_key = |coord: &Coord| (coord.lat * 1e6).round() as i64;
+    // A degree of latitude is about 111 km.
+    let delta = (matching.max_distance / 111_000. * 1e6).ceil() as i64;
+    let mut merged = vec![];
+    for (position, candidate) in candidates.into_iter().enumerate() {
+        let name = normalize_name(candidate.name);
+        let group = kept.entry(candidate.group).or_default();
+        let key = lat_key(candidate.coord);
+        let found = group
+            .range(key - delta..=key + delta)
+            .flat_map(|(_, stops)| stops)
+            .filter(|(_, other, _)| other.source != candidate.source)
+            .filter(|(_, other, _)| {
+                other.coord.distance_to(candidate.coord) <= matching.max_distance
+            })
+            .filter(|(_, _, other_name)| {
+                name_similarity(&name, other_name) >= matching.min_name_similarity
+            })
+            .min_by_key(|(other_position, _, _)| *other_position)
+            .map(|(_, other, _)| other.id.to_string());
+        match found {
+            Some(merged_into) => merged.push(MergedStop {
+                id: candidate.id.to_string(),
+                merged_into,
+            }),
+            None => group
+                .entry(key)
+                .or_default()
+                .push((position, candidate, name)),
+        }
+    }
+    merged
+}
+
+fn rewrite<S>(id: &mut S, ids: &HashMap<String, String>)
+where
+    S: AsRef<str> + From<String>,
+{
+    if let Some(new_id) = ids.get(id.as_ref()) {
+        *id = new_id.clone().into();
+    }
+}
+
+fn extend_unique<T: PartialEq + Clone>(values: &mut Vec<T>, others: &[T]) {
+    for other in others {
+        if !values.contains(other) {
+            values.push(other.clone());
+        }
+    }
+}
+
+fn merge_links<T: Codes + CommentLinks>(stops: &mut [T], from: usize, to: usize) {
+    let codes = stops[from].codes().clone();
+    let comment_links = stops[from].comment_links().clone();
+    extend_unique(stops[to].codes_mut(), &codes);
+    extend_unique(stops[to].comment_links_mut(), &comment_links);
+}
+
+// Moves the translations of the merged stops to the kept ones, but
+// for the fields already translated in the same language.
+fn merge_translations(
+    c: &mut Collections,
+    object_type: &ObjectType,
+    ids: &HashMap<String, String>,
+) {
+    let translations: Vec<_> = mem::take(&mut c.translations)
+        .into_iter()
+        .collect();
+    let mut translated: BTreeSet<_> = translations
+        .iter()
+        .filter(|t| t.object_type == *object_type && !ids.contains_key(&t.object_id))
+        .map(|t| {
+            (
+                t.object_id.clone(),
+                t.field_name.clone(),
+                t.language.clone(),
+            )
+        })
+        .collect();
+    let translations = translations
+        .into_iter()
+        .filter_map(|mut t| {
+            if t.object_type != *object_type || !ids.contains_key(&t.object_id) {
+                return Some(t);
+            }
+            rewrite(&mut t.object_id, ids);
+            let key = (
+                t.object_id.clone(),
+                t.field_name.clone(),
+                t.language.clone(),
+            );
+            if translated.insert(key) {
+                Some(t)
+            } else {
+                None
+            }
+        })
+        .collect();
+    c.translations = Collection::new(translations);
+}
+
+fn merged_ids(merged: &[MergedStop]) -> HashMap<String, String> {
+    merged
+        .iter()
+        .map(|m| (m.id.clone(), m.merged_into.clone()))
+        .collect()
+}
+
+fn merge_stop_areas(c: &mut Collections, merged: &[MergedStop]) -> Result<()> {
+    let ids = merged_ids(merged);
+    let mut stop_areas = c.stop_areas.take();
+    let positions: HashMap<String, usize> = stop_areas
+        .iter()
+        .enumerate()
+        .map(|(i, sa)| (sa.id.clone(), i))
+        .collect();
+    for m in merged {
+        let (from, to) = (positions[&m.id], positions[&m.merged_into]);
+        merge_links(&mut stop_areas, from, to);
+        if stop_areas[to].equipment_id.is_none() {
+            stop_areas[to].equipment_id = stop_areas[from].equipment_id.clone();
+        }
+    }
+    stop_areas.retain(|sa| !ids.contains_key(&sa.id));
+    c.stop_areas = CollectionWithId::new(stop_areas)?;
+
+    // The merged stop areas being removed, the references to them are
+    // rewritten as for a renaming, once their translations merged.
+    merge_translations(c, &ObjectType::StopArea, &ids);
+    rename_ids(c, "stop_areas", &ids)
+}
+
+fn merge_stop_points(c: &mut Collections, merged: &[MergedStop]) -> Result<()> {
+    let ids = merged_ids(merged);
+    let old_ids: HashMap<Idx<StopPoint>, String> = c
+        .stop_points
+        .iter()
+        .map(|(idx, sp)| (idx, sp.id.clone()))
+        .collect();
+    let mut stop_points = c.stop_points.take();
+    let positions: HashMap<String, usize> = stop_points
+        .iter()
+        .enumerate()
+        .map(|(i, sp)| (sp.id.clone(), i))
+        .collect();
+    for m in merged {
+        let (from, to) = (positions[&m.id], positions[&m.merged_into]);
+        merge_links(&mut stop_points, from, to);
+        if stop_points[to].equipment_id.is_none() {
+            stop_points[to].equipment_id = stop_points[from].equipment_id.clone();
+        }
+    }
+    stop_points.retain(|sp| !ids.contains_key(&sp.id));
+    c.stop_points = CollectionWithId::new(stop_points)?;
+
+    let mut vehicle_journeys = c.vehicle_journeys.take();
+    for stop_time in vehicle_journeys
+        .iter_mut()
+        .flat_map(|vj| &mut vj.stop_times)
+    {
+        let id = &old_ids[&stop_time.stop_point_idx];
+        let id = ids.get(id).unwrap_or(id);
+        stop_time.stop_point_idx = c.stop_points.get_idx(id).unwrap();
+    }
+    c.vehicle_journeys = CollectionWithId::new(vehicle_journeys)?;
+    for transfer in c.transfers.values_mut() {
+        rewrite(&mut transfer.from_stop_id, &ids);
+        rewrite(&mut transfer.to_stop_id, &ids);
+    }
+    for link in c.vehicle_journey_links.values_mut() {
+        rewrite(&mut link.stop_point_id, &ids);
+    }
+    let mut pathways = c.pathways.take();
+    for pathway in &mut pathways {
+        rewrite(&mut pathway.from_stop_id, &ids);
+        rewrite(&mut pathway.to_stop_id, &ids);
+    }
+    c.pathways = CollectionWithId::new(pathways)?;
+    for admin_station in c.admin_stations.values_mut() {
+        rewrite(&mut admin_station.stop_id, &ids);
+    }
+    merge_translations(c, &ObjectType::StopPoint, &ids);
+    Ok(())
+}
+
+/// Merges the stop areas of different feeds being the same physical
+/// station, that is close enough and with similar names according to
+/// `matching`, into the first one found in the collection, and
+/// returns the merged stops: the stop areas, then the stop points if
+/// `matching.merge_stop_points` is set.
+///
+/// All the references to the merged stops are rewritten, and their
+/// codes, comments and equipments are moved to the kept stops.  The
+/// stop points of the same original stop area are never merged
+/// together.
+pub fn dedupe_stops(c: &mut Collections, matching: &StopMatching) -> Result<Vec<MergedStop>> {
+    let mut merged = find_matches(
+        c.stop_areas.values().map(|sa| Candidate {
+            group: "",
+            source: &sa.id,
+            id: &sa.id,
+            name: &sa.name,
+            coord: &sa.coord,
+        }),
+        matching,
+    );
+    let sources: HashMap<String, String> = c
+        .stop_points
+        .values()
+        .map(|sp| (sp.id.clone(), sp.stop_area_id.to_string()))
+        .collect();
+    merge_stop_areas(c, &merged)?;
+    if matching.merge_stop_points {
+        let merged_stop_points = find_matches(
+            c.stop_points.values().map(|sp| Candidate {
+                group: &sp.stop_area_id,
+                source: &sources[&sp.id],
+                id: &sp.id,
+                name: &sp.name,
+                coord: &sp.coord,
+            }),
+            matching,
+        );
+        merge_stop_points(c, &merged_stop_points)?;
+        merged.extend(merged_stop_points);
+    }
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use collection::Collection;
+    use objects::*;
+
+    fn stop_point(id: &str, name: &str, lon: f64, lat: f64) -> StopPoint {
+        StopPoint {
+            id: id.to_string(),
+            name: name.to_string(),
+            codes: KeysValues::default(),
+            object_properties: KeysValues::default(),
+            comment_links: CommentLinksT::default(),
+            visible: true,
+            coord: Coord { lon, lat },
+            stop_area_id: "sa".into(),
+            timezone: None,
+            geometry_id: None,
+            equipment_id: None,
+            fare_zone_id: None,
+            address_id: None,
+        }
+    }
+
+    fn vehicle_journey(
+        c: &Collections,
+        id: &str,
+        dataset_id: &str,
+        stops: &[(&str, &str)],
+    ) -> VehicleJourney {
+        let stop_times = stops
+            .iter()
+            .enumerate()
+            .map(|(i, &(sp_id, time))| StopTime {
+                stop_point_idx: c.stop_points.get_idx(sp_id).unwrap(),
+                sequence: i as u32,
+                arrival_time: time.parse().unwrap(),
+                departure_time: time.parse().unwrap(),
+                boarding_duration: 0,
+                alighting_duration: 0,
+                pickup_type: 0,
+                drop_off_type: 0,
+                datetime_estimated: false,
+                local_zone_id: None,
+                extras: None,
+            })
+            .collect();
+        VehicleJourney {
+            id: id.to_string(),
+            dataset_id: dataset_id.to_string(),
+            service_id: "c".into(),
+            stop_times,
+            ..Default::default()
+        }
+    }
+
+    fn make_collections() -> Collections {
+        let mut c = Collections {
+            datasets: CollectionWithId::new(vec![
+                Dataset::new("A:ds".to_string(), "A:contributor".to_string()),
+                Dataset::new("B:ds".to_string(), "B:contributor".to_string()),
+            ])
+            .unwrap(),
+            stop_points: CollectionWithId::new(vec![
+                stop_point("A:sp1", "Gare", 2.37, 48.84),
+                stop_point("A:sp2", "Mairie", 2.38, 48.85),
+                stop_point("B:sp1", "Gare", 2.370001, 48.840001),
+                stop_point("B:sp2", "Mairie", 2.380001, 48.850001),
+            ])
+            .unwrap(),
+            ..Default::default()
+        };
+        let vjs = vec![
+            vehicle_journey(
+                &c,
+                "A:vj1",
+                "A:ds",
+                &[("A:sp1", "10:00:00"), ("A:sp2", "10:10:00")],
+            ),
+            vehicle_journey(
+                &c,
+                "A:vj2",
+                "A:ds",
+                &[("A:sp1", "10:00:00"), ("A:sp2", "10:10:00")],
+            ),
+            vehicle_journey(
+                &c,
+                "B:vj1",
+                "B:ds",
+                &[("B:sp1", "10:00:00"), ("B:sp2", "10:10:00")],
+            ),
+            vehicle_journey(
+                &c,
+                "B:vj2",
+                "B:ds",
+                &[("B:sp1", "11:00:00"), ("B:sp2", "11:10:00")],
+            ),
+        ];
+        c.vehicle_journeys = CollectionWithId::new(vjs).unwrap();
+        c
+    }
+
+    #[test]
+    fn find_duplicates_between_contributors() {
+        let c = make_collections();
+        assert_eq!(
+            vec![DuplicateVehicleJourney {
+                vehicle_journey_id: "B:vj1".to_string(),
+                duplicate_of: "A:vj1".to_string(),
+            }],
+            find_duplicate_vehicle_journeys(&c)
+        );
+    }
+
+    #[test]
+    fn remove_duplicates() {
+        let mut c = make_collections();
+        let duplicates = remove_duplicate_vehicle_journeys(&mut c).unwrap();
+        assert_eq!(1, duplicates.len());
+        let mut ids: Vec<_> = c
+            .vehicle_journeys
+            .values()
+            .map(|vj| vj.id.as_str())
+            .collect();
+        ids.sort();
+        assert_eq!(vec!["A:vj1", "A:vj2", "B:vj2"], ids);
+    }
+
+    fn stop_area(id: &str, name: &str, lon: f64, lat: f64) -> StopArea {
+        StopArea {
+            id: id.to_string(),
+            name: name.to_string(),
+            codes: KeysValues::default(),
+            object_properties: KeysValues::default(),
+            comment_links: CommentLinksT::default(),
+            visible: true,
+            coord: Coord { lon, lat },
+            timezone: None,
+            geometry_id: None,
+            equipment_id: None,
+        }
+    }
+
+    #[test]
+    fn similar_names() {
+        let similarity = |a: &str, b: &str| name_similarity(&normalize_name(a), &normalize_name(b));
+        assert_eq!(1., similarity("Gare de Lyon", "GARE-DE-LYON"));
+        assert_eq!(0.9, similarity("Gare de Lyon", "Gare de Lyom"));
+        assert!(similarity("Gare de Lyon", "Mairie") < 0.5);
+    }
+
+    #[test]
+    fn dedupe_stop_areas_and_stop_points() {
+        let mut c = Collections {
+            stop_areas: CollectionWithId::new(vec![
+                stop_area("A:sa", "Gare de Lyon", 2.37, 48.84),
+                stop_area("B:sa1", "GARE-DE-LYON", 2.3701, 48.8401),
+                stop_area("B:sa2", "Gare de Lyon", 2.38, 48.85),
+            ])
+            .unwrap(),
+            ..Default::default()
+        };
+        let mut stop_points = vec![
+            stop_point("A:sp", "Gare de Lyon", 2.37, 48.84),
+            stop_point("B:sp1", "Gare de Lyon", 2.370001, 48.840001),
+            stop_point("B:sp2", "Gare de Lyon", 2.3702, 48.8402),
+        ];
+        stop_points[0].stop_area_id = "A:sa".into();
+        stop_points[1].stop_area_id = "B:sa1".into();
+        stop_points[2].stop_area_id = "B:sa1".into();
+        c.stop_points = CollectionWithId::new(stop_points).unwrap();
+        c.vehicle_journeys = CollectionWithId::new(vec![vehicle_journey(
+            &c,
+            "B:vj",
+            "B:ds",
+            &[("B:sp1", "10:00:00"), ("B:sp2", "10:10:00")],
+        )])
+        .unwrap();
+        c.transfers = Collection::new(vec![Transfer {
+            from_stop_id: "A:sp".to_string(),
+            to_stop_id: "B:sp1".to_string(),
+            min_transfer_time: None,
+            real_min_transfer_time: None,
+            equipment_id: None,
+        }]);
+        let matching = StopMatching {
+            max_distance: 20.,
+            merge_stop_points: true,
+            ..Default::default()
+        };
+
+        let merged = dedupe_stops(&mut c, &matching).unwrap();
+        let merged: Vec<_> = merged
+            .iter()
+            .map(|m| (m.id.as_str(), m.merged_into.as_str()))
+            .collect();
+        assert_eq!(vec![("B:sa1", "A:sa"), ("B:sp1", "A:sp")], merged);
+        let stop_areas: Vec<_> = c.stop_areas.values().map(|sa| sa.id.as_str()).collect();
+        assert_eq!(vec!["A:sa", "B:sa2"], stop_areas);
+        let stop_points: Vec<_> = c
+            .stop_points
+            .values()
+            .map(|sp| (sp.id.as_str(), sp.stop_area_id.as_str()))
+            .collect();
+        assert_eq!(vec![("A:sp", "A:sa"), ("B:sp2", "A:sa")], stop_points);
+        let vj = c.vehicle_journeys.get("B:vj").unwrap();
+        let stops: Vec<_> = vj
+            .stop_times
+            .iter()
+            .map(|st| c.stop_points[st.stop_point_idx].id.as_str())
+            .collect();
+        assert_eq!(vec!["A:sp", "B:sp2"], stops);
+        let transfer = c.transfers.values().next().unwrap();
+        assert_eq!("A:sp", transfer.from_stop_id);
+        assert_eq!("A:sp", transfer.to_stop_id);
+    }
+
+    #[test]
+    fn rewrite_destinations_of_merged_stop_areas() {
+        let route = |id: &str, destination_id: &str| Route {
+            id: id.to_string(),
+            name: id.to_string(),
+            direction_type: None,
+            codes: KeysValues::default(),
+            object_properties: KeysValues::default(),
+            comment_links: CommentLinksT::default(),
+            line_id: "l".into(),
+            geometry_id: None,
+            destination_id: Some(destination_id.to_string()),
+            continuous_pickup: None,
+            continuous_drop_off: None,
+        };
+        let mut c = Collections {
+            stop_areas: CollectionWithId::new(vec![
+                stop_area("A:sa", "Gare de Lyon", 2.37, 48.84),
+                stop_area("B:sa", "GARE-DE-LYON", 2.3701, 48.8401),
+            ])
+            .unwrap(),
+            routes: CollectionWithId::new(vec![route("A:r", "A:sa"), route("B:r", "B:sa")])
+                .unwrap(),
+            ..Default::default()
+        };
+        let matching = StopMatching {
+            max_distance: 20.,
+            ..Default::default()
+        };
+
+        let merged = dedupe_stops(&mut c, &matching).unwrap();
+        assert_eq!(1, merged.len());
+        let destinations: Vec<_> = c
+            .routes
+            .values()
+            .map(|r| (r.id.as_str(), r.destination_id.as_ref().unwrap().as_str()))
+            .collect();
+        assert_eq!(vec![("A:r", "A:sa"), ("B:r", "A:sa")], destinations);
+    }
+}
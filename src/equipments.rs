@@ -0,0 +1,196 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! Enrichment of the equipments of the stops from an external
+//! accessibility file, the transit feeds rarely describing more than
+//! the wheelchair boarding of their stops.
+
+use collection::CollectionWithId;
+use csv;
+use failure::ResultExt;
+use gtfs::read::EquipmentList;
+use model::Collections;
+use objects::{Availability, Equipment};
+use std::collections::HashMap;
+use std::path::Path;
+use utils::de_with_empty_or_invalid_default;
+use Result;
+
+/// The accessibility of a stop point or a stop area, the empty or
+/// missing columns leaving the current value of the stop unchanged.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct StopAccessibility {
+    /// Identifier of the stop point or stop area.
+    pub stop_id: String,
+    /// Whether the stop is accessible to wheelchairs.
+    #[serde(default, deserialize_with = "de_with_empty_or_invalid_default")]
+    pub wheelchair_boarding: Option<Availability>,
+    /// Whether the stop is sheltered.
+    #[serde(default, deserialize_with = "de_with_empty_or_invalid_default")]
+    pub sheltered: Option<Availability>,
+    /// Whether the stop has an elevator.
+    #[serde(default, deserialize_with = "de_with_empty_or_invalid_default")]
+    pub elevator: Option<Availability>,
+    /// Whether the stop has an escalator.
+    #[serde(default, deserialize_with = "de_with_empty_or_invalid_default")]
+    pub escalator: Option<Availability>,
+    /// Whether the stop has visual announcements.
+    #[serde(default, deserialize_with = "de_with_empty_or_invalid_default")]
+    pub visual_announcement: Option<Availability>,
+    /// Whether the stop has audible announcements.
+    #[serde(default, deserialize_with = "de_with_empty_or_invalid_default")]
+    pub audible_announcement: Option<Availability>,
+}
+
+impl StopAccessibility {
+    fn apply_to(&self, equipment: &mut Equipment) {
+        fn set(field: &mut Availability, value: &Option<Availability>) {
+            if let Some(value) = value {
+                *field = value.clone();
+            }
+        }
+        set(
+            &mut equipment.wheelchair_boarding,
+            &self.wheelchair_boarding,
+        );
+        set(&mut equipment.sheltered, &self.sheltered);
+        set(&mut equipment.elevator, &self.elevator);
+        set(&mut equipment.escalator, &self.escalator);
+        set(
+            &mut equipment.visual_announcement,
+            &self.visual_announcement,
+        );
+        set(
+            &mut equipment.audible_announcement,
+            &self.audible_announcement,
+        );
+    }
+}
+
+/// Reads the accessibility of the stops from a csv file with a
+/// `stop_id` column and the optional `wheelchair_boarding`,
+/// `sheltered`, `elevator`, `escalator`, `visual_announcement` and
+/// `audible_announcement` columns, valued as the NTFS equipments (`0`
+/// for no information, `1` for available and `2` for not available).
+pub fn read_accessibility<P: AsRef<Path>>(path: P) -> Result<Vec<StopAccessibility>> {
+    let path = path.as_ref();
+    info!("Reading {:?}", path);
+    let mut rdr = csv::Reader::from_path(path).with_context(ctx_from_path!(path))?;
+    let accessibilities = rdr
+        .deserialize()
+        .collect::<::std::result::Result<_, _>>()
+        .with_context(ctx_from_path!(path))?;
+    Ok(accessibilities)
+}
+
+fn no_equipment() -> Equipment {
+    Equipment {
+        id: String::new(),
+        wheelchair_boarding: Availability::InformationNotAvailable,
+        sheltered: Availability::InformationNotAvailable,
+        elevator: Availability::InformationNotAvailable,
+        escalator: Availability::InformationNotAvailable,
+        bike_accepted: Availability::InformationNotAvailable,
+        bike_depot: Availability::InformationNotAvailable,
+        visual_announcement: Availability::InformationNotAvailable,
+        audible_announcement: Availability::InformationNotAvailable,
+        appropriate_escort: Availability::InformationNotAvailable,
+        appropriate_signage: Availability::InformationNotAvailable,
+    }
+}
+
+/// Merges the accessibility of the stops into their equipments, and
+/// returns the number of stops enriched.
+///
+/// The given values override the ones of the current equipment of the
+/// stop, a stop identifier being first searched in the stop points,
+/// then in the stop areas.  The identical equipments are merged, the
+/// existing ones keeping their identifiers.
+pub fn enrich_equipments(
+    collections: &mut Collections,
+    accessibilities: &[StopAccessibility],
+) -> Result<usize> {
+    let mut equipment_list = EquipmentList::default();
+    let mut renamed = HashMap::new();
+    let mut equipments = HashMap::new();
+    for mut equipment in collections.equipments.take() {
+        let old_id = equipment.id.clone();
+        let new_id = equipment_list.insert(equipment.clone());
+        equipment.id = String::new();
+        equipments.entry(new_id.clone()).or_insert(equipment);
+        renamed.insert(old_id, new_id);
+    }
+
+    let mut stop_points = collections.stop_points.take();
+    let mut stop_areas = collections.stop_areas.take();
+    {
+        let rename = |equipment_id: &mut Option<String>| {
+            if let Some(new_id) = equipment_id.as_ref().and_then(|id| renamed.get(id)) {
+                *equipment_id = Some(new_id.clone());
+            }
+        };
+        for stop_point in &mut stop_points {
+            rename(&mut stop_point.equipment_id);
+        }
+        for stop_area in &mut stop_areas {
+            rename(&mut stop_area.equipment_id);
+        }
+        for transfer in collections.transfers.values_mut() {
+            rename(&mut transfer.equipment_id);
+        }
+    }
+
+    let sp_idx: HashMap<_, _> = stop_points
+        .iter()
+        .enumerate()
+        .map(|(i, sp)| (sp.id.clone(), i))
+        .collect();
+    let sa_idx: HashMap<_, _> = stop_areas
+        .iter()
+        .enumerate()
+        .map(|(i, sa)| (sa.id.clone(), i))
+        .collect();
+    let mut enriched = 0;
+    for accessibility in accessibilities {
+        let equipment_id = if let Some(&i) = sp_idx.get(&accessibility.stop_id) {
+            &mut stop_points[i].equipment_id
+        } else if let Some(&i) = sa_idx.get(&accessibility.stop_id) {
+            &mut stop_areas[i].equipment_id
+        } else {
+            warn!(
+                "accessibility of {} ignored, stop not found",
+                accessibility.stop_id
+            );
+            continue;
+        };
+        let mut equipment = equipment_id
+            .as_ref()
+            .and_then(|id| equipments.get(id))
+            .cloned()
+            .unwrap_or_else(no_equipment);
+        accessibility.apply_to(&mut equipment);
+        let new_id = equipment_list.push(equipment.clone());
+        equipments.entry(new_id.clone()).or_insert(equipment);
+        *equipment_id = Some(new_id);
+        enriched += 1;
+    }
+
+    collections.stop_points = CollectionWithId::new(stop_points)?;
+    collections.stop_areas = CollectionWithId::new(stop_areas)?;
+    collections.equipments = CollectionWithId::new(equipment_list.into_equipments())?;
+    info!("{} stops enriched with their accessibility", enriched);
+    Ok(enriched)
+}
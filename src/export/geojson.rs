@@ -0,0 +1,226 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! [GeoJSON](https://tools.ietf.org/html/rfc7946) export of the stops
+//! and of the routes of a `Model`, for their visual check in tools as
+//! QGIS or geojson.io.
+
+use failure::ResultExt;
+use geo_types::{Geometry as GeoGeometry, Point};
+use model::Model;
+use serde_json::{self, Map, Value};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+use Result;
+
+fn position(point: &Point<f64>) -> Value {
+    json!([point.x(), point.y()])
+}
+
+fn positions(points: &[Point<f64>]) -> Value {
+    Value::Array(points.iter().map(position).collect())
+}
+
+/// Converts a geometry of the model to a GeoJSON geometry.
+pub fn geometry(geo: &GeoGeometry<f64>) -> Value {
+    match *geo {
+        GeoGeometry::Point(ref p) => json!({"type": "Point", "coordinates": position(p)}),
+        GeoGeometry::Line(ref l) => json!({
+            "type": "LineString",
+            "coordinates": [position(&l.start), position(&l.end)],
+        }),
+        GeoGeometry::LineString(ref ls) => json!({
+            "type": "LineString",
+            "coordinates": positions(&ls.0),
+        }),
+        GeoGeometry::Polygon(ref p) => json!({
+            "type": "Polygon",
+            "coordinates": ::std::iter::once(&p.exterior)
+                .chain(&p.interiors)
+                .map(|r| positions(&r.0))
+                .collect::<Vec<_>>(),
+        }),
+        GeoGeometry::MultiPoint(ref mp) => json!({
+            "type": "MultiPoint",
+            "coordinates": positions(&mp.0),
+        }),
+        GeoGeometry::MultiLineString(ref mls) => json!({
+            "type": "MultiLineString",
+            "coordinates": mls.0.iter().map(|ls| positions(&ls.0)).collect::<Vec<_>>(),
+        }),
+        GeoGeometry::MultiPolygon(ref mp) => json!({
+            "type": "MultiPolygon",
+            "coordinates": mp
+                .0
+                .iter()
+                .map(|p| {
+                    ::std::iter::once(&p.exterior)
+                        .chain(&p.interiors)
+                        .map(|r| positions(&r.0))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>(),
+        }),
+        GeoGeometry::GeometryCollection(ref gc) => json!({
+            "type": "GeometryCollection",
+            "geometries": gc.0.iter().map(geometry).collect::<Vec<_>>(),
+        }),
+    }
+}
+
+fn feature(geometry: Value, properties: Map<String, Value>) -> Value {
+    json!({"type": "Feature", "geometry": geometry, "properties": properties})
+}
+
+fn feature_collection(features: Vec<Value>) -> Value {
+    json!({"type": "FeatureCollection", "features": features})
+}
+
+/// The stop areas and the stop points as a `FeatureCollection` of
+/// points, their `type` property being `stop_area` or `stop_point`.
+pub fn stops(model: &Model) -> Value {
+    let mut features = vec![];
+    for stop_area in model.stop_areas.values() {
+        let mut properties = Map::new();
+        properties.insert("type".into(), "stop_area".into());
+        properties.insert("id".into(), stop_area.id.clone().into());
+        properties.insert("name".into(), stop_area.name.clone().into());
+        let coord = json!([stop_area.coord.lon, stop_area.coord.lat]);
+        features.push(feature(
+            json!({"type": "Point", "coordinates": coord}),
+            properties,
+        ));
+    }
+    for stop_point in model.stop_points.values() {
+        let mut properties = Map::new();
+        properties.insert("type".into(), "stop_point".into());
+        properties.insert("id".into(), stop_point.id.clone().into());
+        properties.insert("name".into(), stop_point.name.clone().into());
+        properties.insert(
+            "stop_area_id".into(),
+            stop_point.stop_area_id.as_str().into(),
+        );
+        let coord = json!([stop_point.coord.lon, stop_point.coord.lat]);
+        features.push(feature(
+            json!({"type": "Point", "coordinates": coord}),
+            properties,
+        ));
+    }
+    feature_collection(features)
+}
+
+/// The routes as a `FeatureCollection` of their geometries, with the
+/// properties of their line as its `line_code`, its `line_color` and
+/// its `commercial_mode`.
+///
+/// The geometry of a route is its own one, or else the one of one of
+/// its vehicle journeys, or else the line between the stop points of
+/// its vehicle journey with the most stops.  The routes without
+/// vehicle journey nor geometry are not exported.
+pub fn routes(model: &Model) -> Value {
+    let mut vjs_by_route = HashMap::new();
+    for vj in model.vehicle_journeys.values() {
+        vjs_by_route
+            .entry(vj.route_id.as_str())
+            .or_insert_with(Vec::new)
+            .push(vj);
+    }
+    let mut features = vec![];
+    for route in model.routes.values() {
+        let vjs = vjs_by_route
+            .get(route.id.as_str())
+            .map_or(&[][..], |vjs| &vjs[..]);
+        let route_geometry = route
+            .geometry_id
+            .as_ref()
+            .or_else(|| vjs.iter().filter_map(|vj| vj.geometry_id.as_ref()).next())
+            .and_then(|id| model.geometries.get(id))
+            .map(|g| geometry(&g.geometry));
+        let route_geometry = match route_geometry {
+            Some(g) => g,
+            None => match vjs.iter().max_by_key(|vj| vj.stop_times.len()) {
+                Some(vj) => json!({
+                    "type": "LineString",
+                    "coordinates": vj
+                        .stop_times
+                        .iter()
+                        .map(|st| {
+                            let coord = &model.stop_points[st.stop_point_idx].coord;
+                            json!([coord.lon, coord.lat])
+                        })
+                        .collect::<Vec<_>>(),
+                }),
+                None => continue,
+            },
+        };
+        let mut properties = Map::new();
+        properties.insert("route_id".into(), route.id.clone().into());
+        properties.insert("route_name".into(), route.name.clone().into());
+        properties.insert("line_id".into(), route.line_id.as_str().into());
+        if let Some(line) = model.lines.get(&route.line_id) {
+            properties.insert("line_code".into(), line.code.clone().into());
+            properties.insert("line_name".into(), line.name.clone().into());
+            properties.insert(
+                "line_color".into(),
+                line.color.as_ref().map(|c| format!("#{}", c)).into(),
+            );
+            properties.insert("network_id".into(), line.network_id.as_str().into());
+            properties.insert(
+                "commercial_mode".into(),
+                line.commercial_mode_id.clone().into(),
+            );
+        }
+        features.push(feature(route_geometry, properties));
+    }
+    feature_collection(features)
+}
+
+fn write<P: AsRef<Path>>(value: &Value, path: P) -> Result<()> {
+    let path = path.as_ref();
+    info!("Writing {:?}", path);
+    let file = File::create(path).with_context(ctx_from_path!(path))?;
+    serde_json::to_writer(file, value).with_context(ctx_from_path!(path))?;
+    Ok(())
+}
+
+/// Writes the `stops` of the model in the GeoJSON file at `path`.
+pub fn write_stops<P: AsRef<Path>>(model: &Model, path: P) -> Result<()> {
+    write(&stops(model), path)
+}
+
+/// Writes the `routes` of the model in the GeoJSON file at `path`.
+pub fn write_routes<P: AsRef<Path>>(model: &Model, path: P) -> Result<()> {
+    write(&routes(model), path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo_types::{LineString, Point};
+
+    #[test]
+    fn line_string_geometry() {
+        let line_string = GeoGeometry::LineString(LineString(vec![
+            Point::new(2.37, 48.84),
+            Point::new(2.38, 48.85),
+        ]));
+        assert_eq!(
+            json!({"type": "LineString", "coordinates": [[2.37, 48.84], [2.38, 48.85]]}),
+            geometry(&line_string)
+        );
+    }
+}
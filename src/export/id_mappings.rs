@@ -0,0 +1,61 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! CSV export of the mappings between the identifiers of the source
+//! feed and the identifiers generated by the readers, to trace the
+//! objects of a `Model` back to their source.
+
+use collection::Collection;
+use csv;
+use failure::ResultExt;
+use model::Model;
+use objects::IdMapping;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use Result;
+
+/// Writes the id mappings as CSV in `writer`, with the header
+/// `collection,source_id,generated_id`, sorted by collection and
+/// source identifier.
+pub fn write<W: Write>(id_mappings: &Collection<IdMapping>, writer: W) -> Result<()> {
+    let mut id_mappings: Vec<_> = id_mappings.values().collect();
+    id_mappings.sort_by(|a, b| {
+        (&a.collection, &a.source_id, &a.generated_id).cmp(&(
+            &b.collection,
+            &b.source_id,
+            &b.generated_id,
+        ))
+    });
+    let mut wtr = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(writer);
+    wtr.write_record(["collection", "source_id", "generated_id"])?;
+    for id_mapping in id_mappings {
+        wtr.serialize(id_mapping)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Writes the id mappings of the model in the CSV file at `path`,
+/// like `write`.
+pub fn write_to_path<P: AsRef<Path>>(model: &Model, path: P) -> Result<()> {
+    let path = path.as_ref();
+    info!("Writing id mappings to {:?}", path);
+    let file = File::create(path).with_context(ctx_from_path!(path))?;
+    Ok(write(&model.id_mappings, BufWriter::new(file)).with_context(ctx_from_path!(path))?)
+}
@@ -0,0 +1,377 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! Export of the `Collections` as SQL statements, creating and
+//! filling the tables of a relational schema, as loaded by
+//! `psql -f`.
+//!
+//! The tables are named after the files of the NTFS, as `stop_points`
+//! or `trips`, and their columns after the fields of the objects, the
+//! fields of the nested objects being joined by `_`, as `coord_lon`,
+//! in the alphabetical order.  The type of a column is `BOOLEAN`,
+//! `BIGINT` or `DOUBLE PRECISION` when all its values are of this
+//! type, or else `TEXT`.  The objects not described by the fields
+//! of their collection are in their own tables:
+//!
+//! - `stop_times` (`trip_id`, `stop_sequence`, `stop_id`,
+//!   `arrival_time`, `departure_time`, `boarding_duration`,
+//!   `alighting_duration`, `pickup_type`, `drop_off_type`,
+//!   `datetime_estimated`, `local_zone_id`, `shape_dist_traveled`,
+//!   `continuous_pickup`, `continuous_drop_off`), the times being
+//!   given as `HH:MM:SS`;
+//! - `calendar_dates` (`service_id`, `date`), one row by date a
+//!   service runs;
+//! - `object_codes` (`object_type`, `object_id`, `object_system`,
+//!   `object_code`);
+//! - `feed_infos` (`feed_info_param`, `feed_info_value`).
+//!
+//! The empty tables are not created, and the existing tables are
+//! dropped first.
+
+use failure::ResultExt;
+use model::Collections;
+use objects::Codes;
+use serde::Serialize;
+use serde_json::{self, Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use Result;
+
+// The number of rows of an `INSERT` statement.
+const ROWS_BY_INSERT: usize = 1000;
+
+type Row = BTreeMap<String, Value>;
+
+fn flatten(prefix: &str, value: Value, row: &mut Row) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let key = if prefix.is_empty() {
+                    key
+                } else {
+                    format!("{}_{}", prefix, key)
+                };
+                flatten(&key, value, row);
+            }
+        }
+        value => {
+            row.insert(prefix.to_string(), value);
+        }
+    }
+}
+
+fn rows<'a, T, I>(objects: I) -> Result<Vec<Row>>
+where
+    T: Serialize + 'a,
+    I: IntoIterator<Item = &'a T>,
+{
+    objects
+        .into_iter()
+        .map(|object| {
+            let mut row = Row::new();
+            flatten("", serde_json::to_value(object)?, &mut row);
+            Ok(row)
+        })
+        .collect()
+}
+
+fn row(values: Map<String, Value>) -> Row {
+    values.into_iter().collect()
+}
+
+fn sql_type<'a, I: Iterator<Item = &'a Value>>(values: I) -> &'static str {
+    let mut values = values.filter(|v| !v.is_null()).peekable();
+    if values.peek().is_none() {
+        return "TEXT";
+    }
+    let (mut boolean, mut integer, mut number) = (true, true, true);
+    for value in values {
+        boolean &= value.is_boolean();
+        integer &= value.is_i64() || value.is_u64();
+        number &= value.is_number();
+    }
+    if boolean {
+        "BOOLEAN"
+    } else if integer {
+        "BIGINT"
+    } else if number {
+        "DOUBLE PRECISION"
+    } else {
+        "TEXT"
+    }
+}
+
+fn literal(value: &Value) -> String {
+    match *value {
+        Value::Null => "NULL".to_string(),
+        Value::Bool(b) => if b { "TRUE" } else { "FALSE" }.to_string(),
+        Value::Number(ref n) => n.to_string(),
+        Value::String(ref s) => format!("'{}'", s.replace('\'', "''")),
+        ref value => format!("'{}'", value.to_string().replace('\'', "''")),
+    }
+}
+
+fn write_table<W: Write>(writer: &mut W, table: &str, rows: &[Row]) -> Result<()> {
+    if rows.is_empty() {
+        return Ok(());
+    }
+    info!("Writing table {}", table);
+    let columns: BTreeSet<&str> = rows
+        .iter()
+        .flat_map(|row| row.keys().map(|k| k.as_str()))
+        .collect();
+    let definitions: Vec<_> = columns
+        .iter()
+        .map(|&column| {
+            let values = rows.iter().filter_map(|row| row.get(column));
+            format!("\"{}\" {}", column, sql_type(values))
+        })
+        .collect();
+    writeln!(writer, "DROP TABLE IF EXISTS \"{}\";", table)?;
+    writeln!(
+        writer,
+        "CREATE TABLE \"{}\" ({});",
+        table,
+        definitions.join(", ")
+    )?;
+    let names: Vec<_> = columns.iter().map(|c| format!("\"{}\"", c)).collect();
+    for chunk in rows.chunks(ROWS_BY_INSERT) {
+        writeln!(
+            writer,
+            "INSERT INTO \"{}\" ({}) VALUES",
+            table,
+            names.join(", ")
+        )?;
+        for (i, row) in chunk.iter().enumerate() {
+            let values: Vec<_> = columns
+                .iter()
+                .map(|&column| row.get(column).map_or_else(|| "NULL".to_string(), literal))
+                .collect();
+            let end = if i + 1 == chunk.len() { ";" } else { "," };
+            writeln!(writer, "({}){}", values.join(", "), end)?;
+        }
+    }
+    Ok(())
+}
+
+fn object_codes<'a, T, I>(object_type: &str, objects: I, codes: &mut Vec<Row>)
+where
+    T: Codes + 'a,
+    I: IntoIterator<Item = (&'a str, &'a T)>,
+{
+    for (id, object) in objects {
+        for (system, code) in object.codes() {
+            codes.push(row(json_map(json!({
+                "object_type": object_type,
+                "object_id": id,
+                "object_system": system,
+                "object_code": code,
+            }))));
+        }
+    }
+}
+
+fn json_map(value: Value) -> Map<String, Value> {
+    match value {
+        Value::Object(map) => map,
+        _ => Map::new(),
+    }
+}
+
+/// Writes the SQL statements creating and filling the tables of the
+/// collections.
+pub fn write<W: Write>(collections: &Collections, mut writer: W) -> Result<()> {
+    let c = collections;
+    let w = &mut writer;
+    writeln!(w, "BEGIN;")?;
+    write_table(w, "contributors", &rows(c.contributors.values())?)?;
+    write_table(w, "datasets", &rows(c.datasets.values())?)?;
+    write_table(w, "networks", &rows(c.networks.values())?)?;
+    write_table(w, "companies", &rows(c.companies.values())?)?;
+    write_table(w, "commercial_modes", &rows(c.commercial_modes.values())?)?;
+    write_table(w, "physical_modes", &rows(c.physical_modes.values())?)?;
+    write_table(w, "lines", &rows(c.lines.values())?)?;
+    write_table(w, "line_groups", &rows(c.line_groups.values())?)?;
+    write_table(w, "line_group_links", &rows(c.line_group_links.values())?)?;
+    write_table(w, "frequencies", &rows(c.frequencies.values())?)?;
+    write_table(
+        w,
+        "vehicle_journey_links",
+        &rows(c.vehicle_journey_links.values())?,
+    )?;
+    write_table(w, "routes", &rows(c.routes.values())?)?;
+    write_table(w, "trips", &rows(c.vehicle_journeys.values())?)?;
+    write_table(w, "stop_areas", &rows(c.stop_areas.values())?)?;
+    write_table(w, "stop_points", &rows(c.stop_points.values())?)?;
+    write_table(w, "entrances", &rows(c.entrances.values())?)?;
+    write_table(w, "addresses", &rows(c.addresses.values())?)?;
+    write_table(w, "comments", &rows(c.comments.values())?)?;
+    write_table(w, "grid_calendars", &rows(c.grid_calendars.values())?)?;
+    write_table(
+        w,
+        "grid_exception_dates",
+        &rows(c.grid_exception_dates.values())?,
+    )?;
+    write_table(w, "grid_periods", &rows(c.grid_periods.values())?)?;
+    write_table(
+        w,
+        "grid_rel_calendar_line",
+        &rows(c.grid_rel_calendar_line.values())?,
+    )?;
+    write_table(w, "equipments", &rows(c.equipments.values())?)?;
+    write_table(w, "transfers", &rows(c.transfers.values())?)?;
+    write_table(w, "trip_properties", &rows(c.trip_properties.values())?)?;
+    write_table(w, "geometries", &rows(c.geometries.values())?)?;
+    write_table(w, "admin_stations", &rows(c.admin_stations.values())?)?;
+    write_table(w, "tickets", &rows(c.tickets.values())?)?;
+    write_table(w, "ticket_prices", &rows(c.ticket_prices.values())?)?;
+    write_table(w, "od_rules", &rows(c.od_rules.values())?)?;
+    write_table(w, "ticket_uses", &rows(c.ticket_uses.values())?)?;
+    write_table(
+        w,
+        "ticket_use_perimeters",
+        &rows(c.ticket_use_perimeters.values())?,
+    )?;
+    write_table(
+        w,
+        "ticket_use_restrictions",
+        &rows(c.ticket_use_restrictions.values())?,
+    )?;
+    write_table(w, "fare_zones", &rows(c.fare_zones.values())?)?;
+    write_table(w, "pathways", &rows(c.pathways.values())?)?;
+    write_table(w, "levels", &rows(c.levels.values())?)?;
+    write_table(w, "attributions", &rows(c.attributions.values())?)?;
+    write_table(w, "translations", &rows(c.translations.values())?)?;
+    write_table(w, "id_mappings", &rows(c.id_mappings.values())?)?;
+
+    let mut stop_times = vec![];
+    for vj in c.vehicle_journeys.values() {
+        for st in &vj.stop_times {
+            stop_times.push(row(json_map(json!({
+                "trip_id": vj.id,
+                "stop_sequence": st.sequence,
+                "stop_id": c.stop_points[st.stop_point_idx].id,
+                "arrival_time": st.arrival_time,
+                "departure_time": st.departure_time,
+                "boarding_duration": st.boarding_duration,
+                "alighting_duration": st.alighting_duration,
+                "pickup_type": st.pickup_type,
+                "drop_off_type": st.drop_off_type,
+                "datetime_estimated": st.datetime_estimated,
+                "local_zone_id": st.local_zone_id,
+                "shape_dist_traveled": st.shape_dist_traveled(),
+                "continuous_pickup": st.continuous_pickup(),
+                "continuous_drop_off": st.continuous_drop_off(),
+            }))));
+        }
+    }
+    write_table(w, "stop_times", &stop_times)?;
+
+    let mut calendar_dates = vec![];
+    for calendar in c.calendars.values() {
+        for date in &calendar.dates {
+            calendar_dates.push(row(json_map(json!({
+                "service_id": calendar.id,
+                "date": date.format("%Y-%m-%d").to_string(),
+            }))));
+        }
+    }
+    write_table(w, "calendar_dates", &calendar_dates)?;
+
+    let mut codes = vec![];
+    object_codes(
+        "network",
+        c.networks.values().map(|o| (o.id.as_str(), o)),
+        &mut codes,
+    );
+    object_codes(
+        "line",
+        c.lines.values().map(|o| (o.id.as_str(), o)),
+        &mut codes,
+    );
+    object_codes(
+        "route",
+        c.routes.values().map(|o| (o.id.as_str(), o)),
+        &mut codes,
+    );
+    object_codes(
+        "trip",
+        c.vehicle_journeys.values().map(|o| (o.id.as_str(), o)),
+        &mut codes,
+    );
+    object_codes(
+        "stop_area",
+        c.stop_areas.values().map(|o| (o.id.as_str(), o)),
+        &mut codes,
+    );
+    object_codes(
+        "stop_point",
+        c.stop_points.values().map(|o| (o.id.as_str(), o)),
+        &mut codes,
+    );
+    write_table(w, "object_codes", &codes)?;
+
+    let feed_infos: Vec<_> = c
+        .feed_infos
+        .iter()
+        .collect::<BTreeMap<_, _>>()
+        .into_iter()
+        .map(|(param, value)| {
+            row(json_map(json!({
+                "feed_info_param": param,
+                "feed_info_value": value,
+            })))
+        })
+        .collect();
+    write_table(w, "feed_infos", &feed_infos)?;
+    writeln!(w, "COMMIT;")?;
+    Ok(())
+}
+
+/// Writes the SQL statements of the collections in the file at
+/// `path`, like `write`.
+pub fn write_to_path<P: AsRef<Path>>(collections: &Collections, path: P) -> Result<()> {
+    let path = path.as_ref();
+    info!("Writing SQL statements to {:?}", path);
+    let file = File::create(path).with_context(ctx_from_path!(path))?;
+    Ok(write(collections, BufWriter::new(file)).with_context(ctx_from_path!(path))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sql_literals() {
+        assert_eq!("NULL", literal(&Value::Null));
+        assert_eq!("TRUE", literal(&json!(true)));
+        assert_eq!("1.5", literal(&json!(1.5)));
+        assert_eq!("'l''Étoile'", literal(&json!("l'Étoile")));
+    }
+
+    #[test]
+    fn column_types() {
+        let values = [json!(1), Value::Null, json!(2)];
+        assert_eq!("BIGINT", sql_type(values.iter()));
+        let values = [json!(1), json!(2.5)];
+        assert_eq!("DOUBLE PRECISION", sql_type(values.iter()));
+        let values = [json!(1), json!("a")];
+        assert_eq!("TEXT", sql_type(values.iter()));
+        assert_eq!("TEXT", sql_type([Value::Null].iter()));
+    }
+}
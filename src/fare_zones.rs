@@ -0,0 +1,171 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! Assignment of the stop points to the fare zones containing them,
+//! for the datasets whose stops lack a fare zone.
+
+use geo_types::{Geometry as GeoGeometry, LineString, Polygon};
+use model::Collections;
+use objects::Coord;
+
+// Whether the point is inside the ring, by counting the crossings of
+// a ray going east from the point.
+fn ring_contains(ring: &LineString<f64>, coord: &Coord) -> bool {
+    let points = &ring.0;
+    let mut inside = false;
+    for (i, current) in points.iter().enumerate() {
+        let previous = &points[(i + points.len() - 1) % points.len()];
+        let (x1, y1) = (current.x(), current.y());
+        let (x2, y2) = (previous.x(), previous.y());
+        if (y1 > coord.lat) != (y2 > coord.lat)
+            && coord.lon < (x2 - x1) * (coord.lat - y1) / (y2 - y1) + x1
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+fn polygon_contains(polygon: &Polygon<f64>, coord: &Coord) -> bool {
+    ring_contains(&polygon.exterior, coord)
+        && !polygon.interiors.iter().any(|i| ring_contains(i, coord))
+}
+
+// Whether the point is inside the polygons of the geometry, the other
+// kinds of geometries containing no point.
+fn contains(geometry: &GeoGeometry<f64>, coord: &Coord) -> bool {
+    match *geometry {
+        GeoGeometry::Polygon(ref polygon) => polygon_contains(polygon, coord),
+        GeoGeometry::MultiPolygon(ref polygons) => {
+            polygons.0.iter().any(|p| polygon_contains(p, coord))
+        }
+        GeoGeometry::GeometryCollection(ref geometries) => {
+            geometries.0.iter().any(|g| contains(g, coord))
+        }
+        _ => false,
+    }
+}
+
+/// Sets the fare zone of the stop points without one to the first
+/// fare zone whose geometry contains them, and returns the number of
+/// stop points assigned to a zone.
+pub fn assign_fare_zones(collections: &mut Collections) -> usize {
+    let zones: Vec<_> = collections
+        .fare_zones
+        .values()
+        .filter_map(|zone| {
+            let geometry = collections.geometries.get(&zone.geometry_id);
+            if geometry.is_none() {
+                warn!(
+                    "fare zone {} ignored, geometry {} not found",
+                    zone.id, zone.geometry_id
+                );
+            }
+            geometry.map(|g| (zone.id.clone(), &g.geometry))
+        })
+        .collect();
+    let assignments: Vec<_> = collections
+        .stop_points
+        .iter()
+        .filter(|&(_, stop_point)| stop_point.fare_zone_id.is_none())
+        .filter_map(|(idx, stop_point)| {
+            zones
+                .iter()
+                .find(|&&(_, geometry)| contains(geometry, &stop_point.coord))
+                .map(|(zone_id, _)| (idx, zone_id.clone()))
+        })
+        .collect();
+    let assigned = assignments.len();
+    for (idx, zone_id) in assignments {
+        collections.stop_points.index_mut(idx).fare_zone_id = Some(zone_id);
+    }
+    info!("{} stop points assigned to a fare zone", assigned);
+    assigned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use collection::CollectionWithId;
+    use geo_types::Point;
+    use objects::{FareZone, Geometry, StopPoint};
+
+    fn square(x: f64, y: f64, size: f64) -> LineString<f64> {
+        LineString(vec![
+            Point::new(x, y),
+            Point::new(x + size, y),
+            Point::new(x + size, y + size),
+            Point::new(x, y + size),
+            Point::new(x, y),
+        ])
+    }
+
+    fn stop_point(id: &str, lon: f64, lat: f64, fare_zone_id: Option<&str>) -> StopPoint {
+        StopPoint {
+            id: id.to_string(),
+            name: id.to_string(),
+            codes: Default::default(),
+            object_properties: Default::default(),
+            comment_links: Default::default(),
+            visible: true,
+            coord: Coord { lon, lat },
+            stop_area_id: "SA".into(),
+            timezone: None,
+            geometry_id: None,
+            equipment_id: None,
+            fare_zone_id: fare_zone_id.map(|z| z.to_string()),
+            address_id: None,
+        }
+    }
+
+    #[test]
+    fn assign_zones() {
+        let mut collections = Collections {
+            geometries: CollectionWithId::new(vec![Geometry {
+                id: "geo:1".to_string(),
+                // A square with a hole in its middle.
+                geometry: GeoGeometry::Polygon(Polygon::new(
+                    square(0., 0., 3.),
+                    vec![square(1., 1., 1.)],
+                )),
+            }]).unwrap(),
+            fare_zones: CollectionWithId::new(vec![
+                FareZone {
+                    id: "1".to_string(),
+                    geometry_id: "geo:1".to_string(),
+                },
+                FareZone {
+                    id: "2".to_string(),
+                    geometry_id: "unknown".to_string(),
+                },
+            ]).unwrap(),
+            stop_points: CollectionWithId::new(vec![
+                stop_point("inside", 0.5, 0.5, None),
+                stop_point("hole", 1.5, 1.5, None),
+                stop_point("outside", 4., 0.5, None),
+                stop_point("zoned", 0.5, 2.5, Some("3")),
+            ]).unwrap(),
+            ..Default::default()
+        };
+
+        assert_eq!(1, assign_fare_zones(&mut collections));
+        let zone = |id| collections.stop_points.get(id).unwrap().fare_zone_id.clone();
+        assert_eq!(Some("1".to_string()), zone("inside"));
+        assert_eq!(None, zone("hole"));
+        assert_eq!(None, zone("outside"));
+        assert_eq!(Some("3".to_string()), zone("zoned"));
+    }
+}
@@ -0,0 +1,241 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! Extraction of a part of a model, restricted to some networks,
+//! lines or physical modes, to a period or to a geographical area, or
+//! without some networks or lines.
+
+use collection::{CollectionWithId, Id, Idx};
+use model::{Collections, Model};
+use objects::{Coord, Date, Line, StopPoint};
+use std::collections::BTreeSet;
+use std::str::FromStr;
+use {Error, Result};
+
+/// A geographical area delimited by its south-west and north-east
+/// corners.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoundingBox {
+    /// The south-west corner.
+    pub min: Coord,
+    /// The north-east corner.
+    pub max: Coord,
+}
+
+impl BoundingBox {
+    /// Returns `true` if the coordinates are inside the bounding box.
+    pub fn contains(&self, coord: &Coord) -> bool {
+        self.min.lon <= coord.lon
+            && coord.lon <= self.max.lon
+            && self.min.lat <= coord.lat
+            && coord.lat <= self.max.lat
+    }
+}
+
+impl FromStr for BoundingBox {
+    type Err = Error;
+
+    /// Parses a bounding box given as `min_lon,min_lat,max_lon,max_lat`.
+    ///
+    /// ```
+    /// # use navitia_model::filter::BoundingBox;
+    /// let bbox: BoundingBox = "2.2,48.8,2.5,48.9".parse().unwrap();
+    /// assert_eq!(2.5, bbox.max.lon);
+    /// assert!("2.2,48.8,2.5".parse::<BoundingBox>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self> {
+        let values = s
+            .split(',')
+            .map(|v| v.trim().parse::<f64>())
+            .collect::<::std::result::Result<Vec<_>, _>>()
+            .map_err(|e| format_err!("invalid bounding box {:?}: {}", s, e))?;
+        ensure!(
+            values.len() == 4,
+            "invalid bounding box {:?}: expected min_lon,min_lat,max_lon,max_lat",
+            s
+        );
+        let bbox = BoundingBox {
+            min: Coord {
+                lon: values[0],
+                lat: values[1],
+            },
+            max: Coord {
+                lon: values[2],
+                lat: values[3],
+            },
+        };
+        ensure!(
+            bbox.min.lon <= bbox.max.lon && bbox.min.lat <= bbox.max.lat,
+            "invalid bounding box {:?}: min corner above max corner",
+            s
+        );
+        Ok(bbox)
+    }
+}
+
+/// The restrictions applied by `filter`.  The default filter keeps
+/// everything.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    /// The networks to keep, all the networks if empty.
+    pub network_ids: BTreeSet<String>,
+    /// The lines to keep, all the lines if empty.
+    pub line_ids: BTreeSet<String>,
+    /// The networks to remove, as the network of a competitor in a
+    /// shared dataset.
+    pub excluded_network_ids: BTreeSet<String>,
+    /// The lines to remove.
+    pub excluded_line_ids: BTreeSet<String>,
+    /// The physical modes to keep, all the physical modes if empty.
+    pub physical_mode_ids: BTreeSet<String>,
+    /// The first and last days of the period to keep.
+    pub period: Option<(Date, Date)>,
+    /// The area in which the stop points are kept.
+    pub bounding_box: Option<BoundingBox>,
+}
+
+// Keeps the vehicle journeys whose line matches `f`.
+fn restrict_lines<F: Fn(&Line) -> bool>(c: &mut Collections, f: F) {
+    let routes = &c.routes;
+    let lines = &c.lines;
+    c.vehicle_journeys.retain(|vj| {
+        routes
+            .get(&vj.route_id)
+            .and_then(|route| lines.get(&route.line_id))
+            .is_some_and(&f)
+    });
+}
+
+fn restrict_physical_modes(c: &mut Collections, physical_mode_ids: &BTreeSet<String>) {
+    c.vehicle_journeys
+        .retain(|vj| physical_mode_ids.contains(&vj.physical_mode_id));
+}
+
+fn warn_unknown_ids<T>(collection: &CollectionWithId<T>, ids: &BTreeSet<String>, kind: &str)
+where
+    T: Id<T>,
+{
+    for id in ids {
+        if collection.get(id).is_none() {
+            warn!("{} {:?} not found", kind, id);
+        }
+    }
+}
+
+fn restrict_period(c: &mut Collections, start: Date, end: Date) -> Result<()> {
+    let mut calendars = c.calendars.take();
+    for calendar in &mut calendars {
+        calendar.dates = calendar.dates.range(start..=end).cloned().collect();
+    }
+    calendars.retain(|calendar| !calendar.dates.is_empty());
+    c.calendars = CollectionWithId::new(calendars)?;
+    let calendars = &c.calendars;
+    c.vehicle_journeys
+        .retain(|vj| calendars.get(&vj.service_id).is_some());
+    let mut datasets = c.datasets.take();
+    for dataset in &mut datasets {
+        dataset.start_date = ::std::cmp::max(dataset.start_date, start);
+        dataset.end_date = ::std::cmp::min(dataset.end_date, end);
+    }
+    c.datasets = CollectionWithId::new(datasets)?;
+    restrict_feed_date(c, "feed_start_date", |date| ::std::cmp::max(date, start));
+    restrict_feed_date(c, "feed_end_date", |date| ::std::cmp::min(date, end));
+    Ok(())
+}
+
+fn restrict_feed_date<F: Fn(Date) -> Date>(c: &mut Collections, key: &str, f: F) {
+    if let Some(value) = c.feed_infos.get_mut(key) {
+        match Date::parse_from_str(value, "%Y%m%d") {
+            Ok(date) => *value = f(date).format("%Y%m%d").to_string(),
+            Err(_) => warn!("invalid {} {:?} not restricted", key, value),
+        }
+    }
+}
+
+fn restrict_area(c: &mut Collections, bounding_box: &BoundingBox) -> Result<()> {
+    let inside: BTreeSet<Idx<StopPoint>> = c
+        .stop_points
+        .iter()
+        .filter(|&(_, sp)| bounding_box.contains(&sp.coord))
+        .map(|(idx, _)| idx)
+        .collect();
+    let mut vehicle_journeys = c.vehicle_journeys.take();
+    for vj in &mut vehicle_journeys {
+        vj.stop_times
+            .retain(|st| inside.contains(&st.stop_point_idx));
+    }
+    vehicle_journeys.retain(|vj| vj.stop_times.len() >= 2);
+    c.vehicle_journeys = CollectionWithId::new(vehicle_journeys)?;
+    Ok(())
+}
+
+/// Restricts the model to the given period, see `filter`.
+pub fn restrict_validity_period(model: Model, start: Date, end: Date) -> Result<Model> {
+    let period_filter = Filter {
+        period: Some((start, end)),
+        ..Default::default()
+    };
+    filter(model, &period_filter)
+}
+
+/// Returns the part of the model matching the filter.  The vehicle
+/// journeys not matching the filter are removed, as well as all the
+/// objects not used anymore by the remaining vehicle journeys.  The
+/// vehicle journeys of the excluded networks and lines are removed
+/// whatever the networks and lines to keep.
+///
+/// With a bounding box, the stop times outside of the box are removed
+/// and the vehicle journeys keeping less than 2 stop times are
+/// removed.  With a period, the dates of the calendars, the validity
+/// periods of the datasets and the feed dates of the feed infos are
+/// restricted to the period.
+///
+/// See `Collections::clean_up` for the objects removed with the
+/// vehicle journeys.
+pub fn filter(model: Model, filter: &Filter) -> Result<Model> {
+    let mut c = model.into_collections();
+    if !filter.network_ids.is_empty() {
+        warn_unknown_ids(&c.networks, &filter.network_ids, "network");
+        restrict_lines(&mut c, |line| filter.network_ids.contains(line.network_id.as_str()));
+    }
+    if !filter.line_ids.is_empty() {
+        warn_unknown_ids(&c.lines, &filter.line_ids, "line");
+        restrict_lines(&mut c, |line| filter.line_ids.contains(&line.id));
+    }
+    if !filter.excluded_network_ids.is_empty() {
+        warn_unknown_ids(&c.networks, &filter.excluded_network_ids, "network");
+        restrict_lines(&mut c, |line| {
+            !filter.excluded_network_ids.contains(line.network_id.as_str())
+        });
+    }
+    if !filter.excluded_line_ids.is_empty() {
+        warn_unknown_ids(&c.lines, &filter.excluded_line_ids, "line");
+        restrict_lines(&mut c, |line| !filter.excluded_line_ids.contains(&line.id));
+    }
+    if !filter.physical_mode_ids.is_empty() {
+        warn_unknown_ids(&c.physical_modes, &filter.physical_mode_ids, "physical mode");
+        restrict_physical_modes(&mut c, &filter.physical_mode_ids);
+    }
+    if let Some((start, end)) = filter.period {
+        ensure!(start <= end, "the period ends before {}", start);
+        restrict_period(&mut c, start, end)?;
+    }
+    if let Some(ref bounding_box) = filter.bounding_box {
+        restrict_area(&mut c, bounding_box)?;
+    }
+    c.clean_up()?;
+    Model::new(c)
+}
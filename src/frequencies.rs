@@ -0,0 +1,146 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! Expansion of the frequencies in explicit vehicle journeys, for the
+//! consumers that only understand timetables.
+
+use collection::{Collection, CollectionWithId};
+use model::Model;
+use objects::{Frequency, Time, VehicleJourney};
+use std::collections::HashMap;
+use std::mem;
+use Result;
+
+/// How `expand` converts the frequencies in vehicle journeys.
+#[derive(Debug, Clone)]
+pub struct ExpansionPolicy {
+    /// Whether the frequencies without exact times, whose runs only
+    /// approximately follow the headway, are expanded.  Otherwise
+    /// they are kept as frequencies, with their vehicle journey.
+    pub expand_inexact: bool,
+    /// The identifier of the generated vehicle journeys, in which
+    /// `{trip_id}` is replaced by the identifier of the expanded
+    /// vehicle journey, `{index}` by the rank of the run, starting at
+    /// 0, and `{time}` by its departure time as `HHMMSS`.
+    pub id_template: String,
+}
+
+impl Default for ExpansionPolicy {
+    fn default() -> Self {
+        ExpansionPolicy {
+            expand_inexact: true,
+            id_template: "{trip_id}:{index}".to_string(),
+        }
+    }
+}
+
+fn seconds(time: Time) -> u32 {
+    time.hours() * 3600 + time.minutes() * 60 + time.seconds()
+}
+
+// The runs of the vehicle journey, starting at each headway of its
+// frequencies.
+fn runs(
+    vj: &VehicleJourney,
+    frequencies: &[Frequency],
+    policy: &ExpansionPolicy,
+) -> Result<Vec<VehicleJourney>> {
+    let first = match vj.stop_times.first() {
+        Some(stop_time) => seconds(stop_time.departure_time),
+        None => return Ok(vec![]),
+    };
+    let mut runs = vec![];
+    for frequency in frequencies {
+        ensure!(
+            frequency.headway_secs > 0,
+            "null headway for the frequency of trip {}",
+            vj.id
+        );
+        let mut start = seconds(frequency.start_time);
+        while start < seconds(frequency.end_time) {
+            let shift = |time: Time| Time::new(0, 0, (seconds(time) + start).saturating_sub(first));
+            let departure = shift(vj.stop_times[0].departure_time);
+            let time = format!(
+                "{:02}{:02}{:02}",
+                departure.hours(),
+                departure.minutes(),
+                departure.seconds()
+            );
+            let mut run = vj.clone();
+            run.id = policy
+                .id_template
+                .replace("{trip_id}", &vj.id)
+                .replace("{index}", &runs.len().to_string())
+                .replace("{time}", &time);
+            for stop_time in &mut run.stop_times {
+                stop_time.arrival_time = shift(stop_time.arrival_time);
+                stop_time.departure_time = shift(stop_time.departure_time);
+            }
+            runs.push(run);
+            start += frequency.headway_secs;
+        }
+    }
+    Ok(runs)
+}
+
+/// Replaces the vehicle journeys having frequencies by one vehicle
+/// journey for each of their runs, the stop times being shifted from
+/// the ones of the first run.  The generated vehicle journeys keep the
+/// properties of the expanded one, and are identified according to
+/// the `policy`.  Fails if a frequency has a null headway or if a
+/// generated identifier already exists.
+pub fn expand(model: Model, policy: &ExpansionPolicy) -> Result<Model> {
+    let mut c = model.into_collections();
+    let mut expanded: HashMap<String, Vec<Frequency>> = HashMap::new();
+    let mut kept = vec![];
+    for frequency in mem::take(&mut c.frequencies) {
+        if frequency.exact_times || policy.expand_inexact {
+            expanded
+                .entry(frequency.vehicle_journey_id.clone())
+                .or_default()
+                .push(frequency);
+        } else {
+            kept.push(frequency);
+        }
+    }
+    let mut vehicle_journeys = vec![];
+    for vj in c.vehicle_journeys.take() {
+        match expanded.get_mut(&vj.id) {
+            Some(frequencies) => {
+                frequencies.sort_by_key(|f| f.start_time);
+                vehicle_journeys.extend(runs(&vj, frequencies, policy)?);
+            }
+            None => vehicle_journeys.push(vj),
+        }
+    }
+    info!(
+        "{} vehicle journeys after the expansion of the frequencies",
+        vehicle_journeys.len()
+    );
+    c.vehicle_journeys = CollectionWithId::new(vehicle_journeys)?;
+    c.frequencies = Collection::new(kept);
+    // The runs of a vehicle journey are not linked to the vehicle
+    // journeys of its block.
+    let vehicle_journey_links = mem::take(&mut c.vehicle_journey_links)
+        .into_iter()
+        .filter(|link| {
+            !expanded.contains_key(&link.from_vehicle_journey_id)
+                && !expanded.contains_key(&link.to_vehicle_journey_id)
+        })
+        .collect();
+    c.vehicle_journey_links = Collection::new(vehicle_journey_links);
+    Model::new(c)
+}
@@ -0,0 +1,526 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! Computation of the geometries of the routes and lines from the ones
+//! of their vehicle journeys, and utilities to simplify the shapes and
+//! to check the stop points against them.
+//!
+//! The distances are in meters, computed on a local equirectangular
+//! projection of the coordinates, precise enough at the scale of a
+//! trip.
+
+use collection::Idx;
+use geo_types::{Geometry as GeoGeometry, LineString, MultiLineString, Point};
+use model::Collections;
+use objects::{Coord, Geometry, Line, Route, VehicleJourney, EARTH_RADIUS};
+use report::Warning;
+use std::collections::{HashMap, HashSet};
+use Result;
+
+// A projection of the coordinates on a plane, in meters, around a
+// latitude.
+struct Projection {
+    cos_lat: f64,
+}
+
+impl Projection {
+    fn new(lat: f64) -> Self {
+        Projection {
+            cos_lat: lat.to_radians().cos(),
+        }
+    }
+
+    fn project(&self, lon: f64, lat: f64) -> (f64, f64) {
+        (
+            EARTH_RADIUS * self.cos_lat * lon.to_radians(),
+            EARTH_RADIUS * lat.to_radians(),
+        )
+    }
+
+    fn unproject(&self, (x, y): (f64, f64)) -> Coord {
+        Coord {
+            lon: (x / (EARTH_RADIUS * self.cos_lat)).to_degrees(),
+            lat: (y / EARTH_RADIUS).to_degrees(),
+        }
+    }
+}
+
+// The point of the segment `[a, b]` closest to `p`, and its squared
+// distance to `p`.
+fn closest_on_segment(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> ((f64, f64), f64) {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let sq_len = dx * dx + dy * dy;
+    let t = if sq_len == 0. {
+        0.
+    } else {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / sq_len).clamp(0., 1.)
+    };
+    let closest = (a.0 + t * dx, a.1 + t * dy);
+    let sq_dist = (p.0 - closest.0).powi(2) + (p.1 - closest.1).powi(2);
+    (closest, sq_dist)
+}
+
+// Marks the points to keep between the `first` and `last` kept points.
+fn douglas_peucker(points: &[(f64, f64)], sq_tolerance: f64, keep: &mut [bool]) {
+    let mut ranges = vec![(0, points.len() - 1)];
+    while let Some((first, last)) = ranges.pop() {
+        let farthest = (first + 1..last)
+            .map(|i| {
+                (
+                    i,
+                    closest_on_segment(points[i], points[first], points[last]).1,
+                )
+            })
+            .fold(None, |max: Option<(usize, f64)>, (i, d)| match max {
+                Some((_, max_d)) if max_d >= d => max,
+                _ => Some((i, d)),
+            });
+        if let Some((i, sq_dist)) = farthest {
+            if sq_dist > sq_tolerance {
+                keep[i] = true;
+                ranges.push((first, i));
+                ranges.push((i, last));
+            }
+        }
+    }
+}
+
+/// Simplifies the line string with the Douglas-Peucker algorithm,
+/// removing the points closer than `tolerance` meters to the
+/// simplified line.  The first and last points are always kept.
+pub fn simplify(line: &LineString<f64>, tolerance: f64) -> LineString<f64> {
+    if line.0.len() < 3 {
+        return line.clone();
+    }
+    let projection = Projection::new(line.0[0].y());
+    let points: Vec<_> = line
+        .0
+        .iter()
+        .map(|p| projection.project(p.x(), p.y()))
+        .collect();
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    douglas_peucker(&points, tolerance * tolerance, &mut keep);
+    let kept = line
+        .0
+        .iter()
+        .zip(keep)
+        .filter(|&(_, keep)| keep)
+        .map(|(p, _)| *p)
+        .collect();
+    LineString(kept)
+}
+
+/// Simplifies the line strings of the geometries, as in `simplify`,
+/// and returns the number of points removed.
+pub fn simplify_geometries(collections: &mut Collections, tolerance: f64) -> usize {
+    let mut removed = 0;
+    let mut simplify_line = |line: &mut LineString<f64>| {
+        let simplified = simplify(line, tolerance);
+        removed += line.0.len() - simplified.0.len();
+        *line = simplified;
+    };
+    let indexes: Vec<_> = collections.geometries.iter().map(|(idx, _)| idx).collect();
+    for idx in indexes {
+        match collections.geometries.index_mut(idx).geometry {
+            GeoGeometry::LineString(ref mut line) => simplify_line(line),
+            GeoGeometry::MultiLineString(ref mut lines) => {
+                lines.0.iter_mut().for_each(&mut simplify_line)
+            }
+            _ => {}
+        }
+    }
+    info!("{} points removed from the geometries", removed);
+    removed
+}
+
+/// Snaps the coordinate onto the line string, returning the closest
+/// point of the line and its distance in meters to the coordinate.
+/// Returns `None` if the line string has no point.
+pub fn snap(coord: &Coord, line: &LineString<f64>) -> Option<(Coord, f64)> {
+    let projection = Projection::new(coord.lat);
+    let p = projection.project(coord.lon, coord.lat);
+    let points: Vec<_> = line
+        .0
+        .iter()
+        .map(|p| projection.project(p.x(), p.y()))
+        .collect();
+    let closest = match points.len() {
+        0 => return None,
+        1 => closest_on_segment(p, points[0], points[0]),
+        _ => points
+            .windows(2)
+            .map(|s| closest_on_segment(p, s[0], s[1]))
+            .fold((points[0], f64::INFINITY), |min, c| {
+                if c.1 < min.1 {
+                    c
+                } else {
+                    min
+                }
+            }),
+    };
+    Some((projection.unproject(closest.0), closest.1.sqrt()))
+}
+
+// The position on the line of the point closest to `p`, as the index
+// of its segment and its fraction of the segment, not before the
+// `from` position.
+fn locate(points: &[(f64, f64)], p: (f64, f64), from: (usize, f64)) -> (usize, f64) {
+    let mut best = (from, f64::INFINITY);
+    for i in from.0..points.len() - 1 {
+        let a = if i == from.0 {
+            let (a, b) = (points[i], points[i + 1]);
+            (a.0 + from.1 * (b.0 - a.0), a.1 + from.1 * (b.1 - a.1))
+        } else {
+            points[i]
+        };
+        let (closest, sq_dist) = closest_on_segment(p, a, points[i + 1]);
+        if sq_dist < best.1 {
+            let (a, b) = (points[i], points[i + 1]);
+            let sq_len = (b.0 - a.0).powi(2) + (b.1 - a.1).powi(2);
+            let t = if sq_len == 0. {
+                0.
+            } else {
+                ((closest.0 - a.0).powi(2) + (closest.1 - a.1).powi(2)).sqrt() / sq_len.sqrt()
+            };
+            best = ((i, t), sq_dist);
+        }
+    }
+    best.0
+}
+
+/// The distances along the line string of the coordinates snapped on
+/// it in order, each one not before the previous one.  The distances
+/// are in meters from the first point of the line, or else
+/// interpolated from `point_distances`, the distances of the points of
+/// the line.  Returns `None` if the line has less than two points or
+/// if the number of distances doesn't match its number of points.
+pub fn distances_along(
+    line: &LineString<f64>,
+    point_distances: Option<&[f64]>,
+    coords: &[Coord],
+) -> Option<Vec<f64>> {
+    if line.0.len() < 2 || point_distances.is_some_and(|d| d.len() != line.0.len()) {
+        return None;
+    }
+    let projection = Projection::new(line.0[0].y());
+    let points: Vec<_> = line
+        .0
+        .iter()
+        .map(|p| projection.project(p.x(), p.y()))
+        .collect();
+    let cumulated = match point_distances {
+        Some(distances) => distances.to_vec(),
+        None => {
+            let mut cumulated = vec![0.];
+            for s in points.windows(2) {
+                let length = ((s[1].0 - s[0].0).powi(2) + (s[1].1 - s[0].1).powi(2)).sqrt();
+                let last = cumulated[cumulated.len() - 1];
+                cumulated.push(last + length);
+            }
+            cumulated
+        }
+    };
+    let mut position = (0, 0.);
+    let distances = coords
+        .iter()
+        .map(|coord| {
+            let p = projection.project(coord.lon, coord.lat);
+            position = locate(&points, p, position);
+            let (i, t) = position;
+            cumulated[i] + t * (cumulated[i + 1] - cumulated[i])
+        })
+        .collect();
+    Some(distances)
+}
+
+/// Sets the `shape_dist_traveled` of the stop times of the vehicle
+/// journeys having a line string shape and no distance on their stop
+/// times, as the distances in meters along the shape of their stop
+/// points.  Returns the number of vehicle journeys updated.
+pub fn compute_shape_dist_traveled(collections: &mut Collections) -> usize {
+    set_shape_dist_traveled(collections, &HashMap::new())
+}
+
+// Like `compute_shape_dist_traveled`, interpolating the distances of
+// the points of the shapes of `shape_distances` when given.
+pub(crate) fn set_shape_dist_traveled(
+    collections: &mut Collections,
+    shape_distances: &HashMap<String, Vec<f64>>,
+) -> usize {
+    let mut updates = vec![];
+    for (idx, vj) in collections.vehicle_journeys.iter() {
+        if vj
+            .stop_times
+            .iter()
+            .any(|st| st.shape_dist_traveled().is_some())
+        {
+            continue;
+        }
+        let geometry_id = match vj.geometry_id {
+            Some(ref id) => id,
+            None => continue,
+        };
+        let line = match find_line_string(collections, geometry_id) {
+            Some(line) => line,
+            None => continue,
+        };
+        let coords: Vec<_> = vj
+            .stop_times
+            .iter()
+            .map(|st| collections.stop_points[st.stop_point_idx].coord)
+            .collect();
+        let point_distances = shape_distances.get(geometry_id).map(|d| d.as_slice());
+        if let Some(distances) = distances_along(line, point_distances, &coords) {
+            updates.push((idx, distances));
+        }
+    }
+    let updated = updates.len();
+    for (idx, distances) in updates {
+        let mut vj = collections.vehicle_journeys.index_mut(idx);
+        for (st, distance) in vj.stop_times.iter_mut().zip(distances) {
+            st.set_shape_dist_traveled(Some(distance));
+        }
+    }
+    updated
+}
+
+/// Checks that the stop points of the vehicle journeys having a shape
+/// are within `max_distance` meters of it, returning a warning for
+/// each stop point farther from the shape of one of its vehicle
+/// journeys.
+pub fn check_stops_on_shapes(collections: &Collections, max_distance: f64) -> Vec<Warning> {
+    let mut checked = HashSet::new();
+    let mut warnings = vec![];
+    for vj in collections.vehicle_journeys.values() {
+        let geometry_id = match vj.geometry_id {
+            Some(ref id) => id,
+            None => continue,
+        };
+        let line = match find_line_string(collections, geometry_id) {
+            Some(line) => line,
+            None => continue,
+        };
+        for stop_time in &vj.stop_times {
+            if !checked.insert((stop_time.stop_point_idx, geometry_id)) {
+                continue;
+            }
+            let stop_point = &collections.stop_points[stop_time.stop_point_idx];
+            match snap(&stop_point.coord, line) {
+                Some((_, distance)) if distance > max_distance => warnings.push(Warning {
+                    file: "stop_times.txt".to_string(),
+                    line: None,
+                    object_id: Some(stop_point.id.clone()),
+                    issue: None,
+                    handling: None,
+                    message: format!(
+                        "stop point {} of vehicle journey {} is {:.0}m away from geometry {}",
+                        stop_point.id, vj.id, distance, geometry_id
+                    ),
+                }),
+                _ => {}
+            }
+        }
+    }
+    warnings
+}
+
+fn line_string(geometry: &GeoGeometry<f64>) -> Option<&LineString<f64>> {
+    match *geometry {
+        GeoGeometry::LineString(ref line_string) => Some(line_string),
+        _ => None,
+    }
+}
+
+// The line string of the geometry of identifier `id`, if any.
+fn find_line_string<'a>(c: &'a Collections, id: &str) -> Option<&'a LineString<f64>> {
+    c.geometries.get(id).and_then(|g| line_string(&g.geometry))
+}
+
+// The line string joining the stop points of the vehicle journey, if
+// it has at least two stop times.
+fn stops_line_string(c: &Collections, vj: &VehicleJourney) -> Option<LineString<f64>> {
+    if vj.stop_times.len() < 2 {
+        return None;
+    }
+    let points = vj
+        .stop_times
+        .iter()
+        .map(|st| {
+            let coord = &c.stop_points[st.stop_point_idx].coord;
+            Point::new(coord.lon, coord.lat)
+        })
+        .collect();
+    Some(LineString(points))
+}
+
+// The vehicle journey of each route having the most stop times, the
+// first of them in case of a tie.
+fn longest_variants(c: &Collections) -> HashMap<&str, &VehicleJourney> {
+    let mut variants: HashMap<&str, &VehicleJourney> = HashMap::new();
+    for vj in c.vehicle_journeys.values() {
+        let longest = variants.entry(&vj.route_id).or_insert(vj);
+        if vj.stop_times.len() > longest.stop_times.len() {
+            *longest = vj;
+        }
+    }
+    variants
+}
+
+/// Sets the geometry of the routes and lines without one.
+///
+/// The geometry of a route is the one of its longest vehicle journey,
+/// the one with the most stop times: its shape if it has one, or else
+/// a new `route:<route id>` geometry joining its stop points.  The
+/// geometry of a line is the geometry of its routes if they all share
+/// it, or else a new `line:<line id>` geometry merging them.  Fails
+/// if one of the new geometries already exists.
+pub fn build_route_geometries(collections: &mut Collections) -> Result<()> {
+    let mut new_geometries = vec![];
+    let mut route_geometries: Vec<(Idx<Route>, String)> = vec![];
+    let mut line_geometries: Vec<(Idx<Line>, String)> = vec![];
+    {
+        let c = &*collections;
+        let variants = longest_variants(c);
+        // The geometry of each route, existing or built.
+        let mut route_lines: HashMap<&str, (String, LineString<f64>)> = HashMap::new();
+        for (idx, route) in c.routes.iter() {
+            if let Some(ref id) = route.geometry_id {
+                if let Some(line) = find_line_string(c, id) {
+                    route_lines.insert(&route.id, (id.clone(), line.clone()));
+                }
+                continue;
+            }
+            let vj = match variants.get(route.id.as_str()) {
+                Some(vj) => vj,
+                None => continue,
+            };
+            let shape = vj
+                .geometry_id
+                .as_ref()
+                .and_then(|id| find_line_string(c, id).map(|line| (id.clone(), line.clone())));
+            let (id, line) = match shape {
+                Some(shape) => shape,
+                None => match stops_line_string(c, vj) {
+                    Some(line) => {
+                        let id = format!("route:{}", route.id);
+                        new_geometries.push(Geometry {
+                            id: id.clone(),
+                            geometry: GeoGeometry::LineString(line.clone()),
+                        });
+                        (id, line)
+                    }
+                    None => continue,
+                },
+            };
+            route_geometries.push((idx, id.clone()));
+            route_lines.insert(&route.id, (id, line));
+        }
+
+        let mut lines_routes: HashMap<&str, Vec<&(String, LineString<f64>)>> = HashMap::new();
+        for route in c.routes.values() {
+            if let Some(route_line) = route_lines.get(route.id.as_str()) {
+                let routes = lines_routes.entry(&route.line_id).or_default();
+                if !routes.iter().any(|(_, line)| *line == route_line.1) {
+                    routes.push(route_line);
+                }
+            }
+        }
+        for (idx, line) in c.lines.iter() {
+            if line.geometry_id.is_some() {
+                continue;
+            }
+            let id = match lines_routes.get(line.id.as_str()) {
+                Some(routes) if routes.len() == 1 => routes[0].0.clone(),
+                Some(routes) => {
+                    let id = format!("line:{}", line.id);
+                    let lines = routes.iter().map(|(_, l)| l.clone()).collect();
+                    new_geometries.push(Geometry {
+                        id: id.clone(),
+                        geometry: GeoGeometry::MultiLineString(MultiLineString(lines)),
+                    });
+                    id
+                }
+                None => continue,
+            };
+            line_geometries.push((idx, id));
+        }
+    }
+
+    info!(
+        "{} geometries built for {} routes and {} lines",
+        new_geometries.len(),
+        route_geometries.len(),
+        line_geometries.len()
+    );
+    for geometry in new_geometries {
+        collections.geometries.push(geometry)?;
+    }
+    for (idx, id) in route_geometries {
+        collections.routes.index_mut(idx).geometry_id = Some(id);
+    }
+    for (idx, id) in line_geometries {
+        collections.lines.index_mut(idx).geometry_id = Some(id);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simplify_removes_close_points() {
+        // About 1m north of the straight line, then about 111m.
+        let line = LineString(vec![
+            Point::new(2., 48.),
+            Point::new(2.001, 48.00001),
+            Point::new(2.002, 48.),
+            Point::new(2.003, 48.001),
+            Point::new(2.004, 48.),
+        ]);
+        let simplified = simplify(&line, 10.);
+        assert_eq!(
+            vec![
+                Point::new(2., 48.),
+                Point::new(2.002, 48.),
+                Point::new(2.003, 48.001),
+                Point::new(2.004, 48.),
+            ],
+            simplified.0
+        );
+        assert_eq!(line, simplify(&line, 0.5));
+    }
+
+    #[test]
+    fn snap_on_closest_segment() {
+        let line = LineString(vec![
+            Point::new(2., 48.),
+            Point::new(2.01, 48.),
+            Point::new(2.01, 48.01),
+        ]);
+        let coord = Coord {
+            lon: 2.005,
+            lat: 48.001,
+        };
+        let (snapped, distance) = snap(&coord, &line).unwrap();
+        assert!((snapped.lon - 2.005).abs() < 1e-9);
+        assert!((snapped.lat - 48.).abs() < 1e-9);
+        assert!((distance - 111.2).abs() < 0.1);
+        assert_eq!(None, snap(&coord, &LineString(vec![])).map(|(_, d)| d));
+    }
+}
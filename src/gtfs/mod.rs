@@ -16,23 +16,359 @@
 
 //! [GTFS](http://gtfs.org/) format management.
 
-mod read;
+pub(crate) mod read;
+mod write;
 
 use collection::CollectionWithId;
-use common_format::manage_calendars;
+use common_format::{self, manage_calendars};
+use failure::ResultExt;
+#[cfg(feature = "async")]
+use futures::Future;
+use geometries;
 use gtfs::read::EquipmentList;
 use model::{Collections, Model};
-use objects::Comment;
-use read_utils::add_prefix;
+use ntfs;
+use objects::{self, Comment, Time};
+#[cfg(feature = "parallel")]
+use rayon;
+#[cfg(feature = "parallel")]
+use read_utils::MemoryFileHandler;
+use read_utils::{self, add_prefix, FileHandler, InputHandler, ZipHandler};
+#[cfg(feature = "async")]
+use read_utils::{read_url_async, HttpOptions};
+use report::{Issues, ParseProfile, Warning};
+use std::collections::HashMap;
+use std::fs::File;
+#[cfg(feature = "parallel")]
+use std::io::Read;
 use std::path::Path;
+use utils::*;
+#[cfg(feature = "async")]
+use Error;
 use Result;
 
+// The columns of feed_info.txt, kept with the same name as keys of
+// the feed infos.
+const FEED_INFO_KEYS: [&str; 8] = [
+    "feed_publisher_name",
+    "feed_publisher_url",
+    "feed_lang",
+    "feed_start_date",
+    "feed_end_date",
+    "feed_version",
+    "feed_contact_email",
+    "feed_contact_url",
+];
+
+// The fields of `translations.txt` that can be translated, by table,
+// with the NTFS name of the field of the model they are read in.
+const TRANSLATED_FIELDS: [(&str, &str, &str); 6] = [
+    ("agency", "agency_name", "network_name"),
+    ("agency", "agency_url", "network_url"),
+    ("stops", "stop_name", "stop_name"),
+    ("routes", "route_short_name", "line_code"),
+    ("routes", "route_long_name", "line_name"),
+    ("trips", "trip_headsign", "trip_headsign"),
+];
+
+// A minimal dataset, completed or modified by the tests of the reader
+// and the writer.
+#[cfg(test)]
+const MINIMAL_GTFS: &[(&str, &str)] = &[
+    (
+        "agency.txt",
+        "agency_id,agency_name,agency_url,agency_timezone\n\
+         agency:1,Agency,http://example.com,Europe/Paris",
+    ),
+    (
+        "stops.txt",
+        "stop_id,stop_name,stop_lat,stop_lon,location_type,parent_station\n\
+         sa:1,Stop 1,48.1,2.1,1,\n\
+         sp:1,Stop 1,48.1,2.1,0,sa:1\n\
+         sa:2,Stop 2,48.2,2.2,1,\n\
+         sp:2,Stop 2,48.2,2.2,0,sa:2",
+    ),
+    (
+        "routes.txt",
+        "route_id,agency_id,route_short_name,route_long_name,route_type\n\
+         route:1,agency:1,1,Line 1,3",
+    ),
+    (
+        "trips.txt",
+        "route_id,service_id,trip_id,direction_id,shape_id\n\
+         route:1,service:1,trip:1,0,shape:1\n\
+         route:1,service:1,trip:2,1,",
+    ),
+    (
+        "stop_times.txt",
+        "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+         trip:1,08:00:00,08:00:00,sp:1,1\n\
+         trip:1,08:10:00,08:10:00,sp:2,2\n\
+         trip:2,09:00:00,09:00:00,sp:2,1\n\
+         trip:2,09:10:00,09:10:00,sp:1,2",
+    ),
+    (
+        "calendar_dates.txt",
+        "service_id,date,exception_type\n\
+         service:1,20180101,1",
+    ),
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Agency {
+    #[serde(rename = "agency_id")]
+    id: Option<String>,
+    #[serde(rename = "agency_name")]
+    name: String,
+    #[serde(rename = "agency_url")]
+    url: String,
+    #[serde(rename = "agency_timezone")]
+    timezone: Option<String>,
+    #[serde(rename = "agency_lang")]
+    lang: Option<String>,
+    #[serde(rename = "agency_phone")]
+    phone: Option<String>,
+    #[serde(rename = "agency_email")]
+    email: Option<String>,
+}
+
+#[derivative(Default)]
+#[derive(Derivative, Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+enum StopLocationType {
+    #[derivative(Default)]
+    #[serde(rename = "0")]
+    StopPoint,
+    #[serde(rename = "1")]
+    StopArea,
+    #[serde(rename = "2")]
+    StopEntrace,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Stop {
+    #[serde(rename = "stop_id")]
+    id: String,
+    #[serde(rename = "stop_code")]
+    code: Option<String>,
+    #[serde(rename = "stop_name")]
+    name: String,
+    #[serde(default, rename = "stop_desc")]
+    desc: String,
+    #[serde(rename = "stop_lon")]
+    lon: f64,
+    #[serde(rename = "stop_lat")]
+    lat: f64,
+    #[serde(rename = "zone_id")]
+    fare_zone_id: Option<String>,
+    #[serde(rename = "stop_url")]
+    url: Option<String>,
+    #[serde(default, deserialize_with = "de_with_empty_default")]
+    location_type: StopLocationType,
+    parent_station: Option<String>,
+    #[serde(rename = "stop_timezone")]
+    timezone: Option<String>,
+    #[serde(default)]
+    wheelchair_boarding: Option<String>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+enum RouteType {
+    #[allow(non_camel_case_types)]
+    Tramway_LightRail,
+    Metro,
+    Rail,
+    Bus,
+    Ferry,
+    CableCar,
+    #[allow(non_camel_case_types)]
+    Gondola_SuspendedCableCar,
+    Funicular,
+    Other(u16),
+}
+
+impl RouteType {
+    fn to_gtfs_value(&self) -> String {
+        match *self {
+            RouteType::Tramway_LightRail => "0".to_string(),
+            RouteType::Metro => "1".to_string(),
+            RouteType::Rail => "2".to_string(),
+            RouteType::Bus => "3".to_string(),
+            RouteType::Ferry => "4".to_string(),
+            RouteType::CableCar => "5".to_string(),
+            RouteType::Gondola_SuspendedCableCar => "6".to_string(),
+            RouteType::Funicular => "7".to_string(),
+            RouteType::Other(i) => i.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Route {
+    #[serde(rename = "route_id")]
+    id: String,
+    agency_id: Option<String>,
+    #[serde(rename = "route_short_name")]
+    short_name: String,
+    #[serde(rename = "route_long_name")]
+    long_name: String,
+    #[serde(rename = "route_desc")]
+    desc: Option<String>,
+    route_type: RouteType,
+    #[serde(rename = "route_url")]
+    url: Option<String>,
+    #[serde(rename = "route_color", default)]
+    color: Option<objects::Rgb>,
+    #[serde(rename = "route_text_color", default)]
+    text_color: Option<objects::Rgb>,
+    #[serde(rename = "route_sort_order")]
+    sort_order: Option<u32>,
+    #[serde(deserialize_with = "de_with_empty_or_invalid_default", default)]
+    continuous_pickup: Option<u8>,
+    #[serde(deserialize_with = "de_with_empty_or_invalid_default", default)]
+    continuous_drop_off: Option<u8>,
+}
+
+#[derive(Derivative)]
+#[derivative(Default(bound = ""))]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+enum DirectionType {
+    #[derivative(Default)]
+    #[serde(rename = "0")]
+    Forward,
+    #[serde(rename = "1")]
+    Backward,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Trip {
+    route_id: String,
+    service_id: String,
+    #[serde(rename = "trip_id")]
+    id: String,
+    #[serde(rename = "trip_headsign")]
+    headsign: Option<String>,
+    #[serde(rename = "trip_short_name")]
+    short_name: Option<String>,
+    #[serde(default, deserialize_with = "de_with_empty_default", rename = "direction_id")]
+    direction: DirectionType,
+    block_id: Option<String>,
+    shape_id: Option<String>,
+    #[serde(deserialize_with = "de_with_empty_default", default)]
+    wheelchair_accessible: u8,
+    #[serde(deserialize_with = "de_with_empty_default", default)]
+    bikes_allowed: u8,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct StopTime {
+    trip_id: String,
+    arrival_time: Option<Time>,
+    departure_time: Option<Time>,
+    stop_id: String,
+    stop_sequence: u32,
+    #[serde(deserialize_with = "de_with_empty_default", default)]
+    pickup_type: u8,
+    #[serde(deserialize_with = "de_with_empty_default", default)]
+    drop_off_type: u8,
+    #[serde(deserialize_with = "de_with_empty_or_invalid_default", default)]
+    shape_dist_traveled: Option<f64>,
+    #[serde(deserialize_with = "de_with_empty_or_invalid_default", default)]
+    timepoint: Option<u8>,
+    #[serde(deserialize_with = "de_with_empty_or_invalid_default", default)]
+    continuous_pickup: Option<u8>,
+    #[serde(deserialize_with = "de_with_empty_or_invalid_default", default)]
+    continuous_drop_off: Option<u8>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Shape {
+    #[serde(rename = "shape_id")]
+    id: String,
+    #[serde(rename = "shape_pt_lat")]
+    lat: f64,
+    #[serde(rename = "shape_pt_lon")]
+    lon: f64,
+    #[serde(rename = "shape_pt_sequence")]
+    sequence: u32,
+    #[serde(deserialize_with = "de_with_empty_or_invalid_default", default)]
+    shape_dist_traveled: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Derivative)]
+#[derivative(Default)]
+enum TransferType {
+    #[derivative(Default)]
+    #[serde(rename = "0")]
+    Recommended,
+    #[serde(rename = "1")]
+    Timed,
+    #[serde(rename = "2")]
+    WithTransferTime,
+    #[serde(rename = "3")]
+    NotPossible,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Transfer {
+    from_stop_id: String,
+    to_stop_id: String,
+    #[serde(deserialize_with = "de_with_empty_default")]
+    transfer_type: TransferType,
+    min_transfer_time: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Attribution {
+    attribution_id: Option<String>,
+    agency_id: Option<String>,
+    route_id: Option<String>,
+    trip_id: Option<String>,
+    organization_name: String,
+    #[serde(
+        default,
+        deserialize_with = "de_from_u8_with_empty_default",
+        serialize_with = "ser_from_bool"
+    )]
+    is_producer: bool,
+    #[serde(
+        default,
+        deserialize_with = "de_from_u8_with_empty_default",
+        serialize_with = "ser_from_bool"
+    )]
+    is_operator: bool,
+    #[serde(
+        default,
+        deserialize_with = "de_from_u8_with_empty_default",
+        serialize_with = "ser_from_bool"
+    )]
+    is_authority: bool,
+    attribution_url: Option<String>,
+    attribution_email: Option<String>,
+    attribution_phone: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Translation {
+    table_name: String,
+    field_name: String,
+    language: String,
+    translation: String,
+    record_id: Option<String>,
+    record_sub_id: Option<String>,
+    field_value: Option<String>,
+}
+
 /// Imports a `Model` from the [GTFS](http://gtfs.org/) files in the
-/// `path` directory.
+/// `path` directory, zip or tar.gz archive.
 ///
 /// The `config_path` argument allows you to give a path to a file
 /// containing a json representing the contributor and dataset used
-/// for this GTFS. If not given, default values will be created.
+/// for this GTFS. If not given, default values will be created.  Its
+/// optional `modes` list overrides the physical and commercial modes
+/// given to a `route_type`, as
+/// `{"route_type": "7", "physical_mode_id": "CableCar", "co2_emission": 2.5}`
+/// with optional `physical_mode_name`, `commercial_mode_id` and
+/// `commercial_mode_name`.
 ///
 /// The `prefix` argument is a string that will be prepended to every
 /// identifiers, allowing to namespace the dataset. By default, no
@@ -41,39 +377,327 @@ pub fn read<P>(path: P, config_path: Option<P>, prefix: Option<String>) -> Resul
 where
     P: AsRef<Path>,
 {
-    let mut collections = Collections::default();
-    let mut equipments = EquipmentList::default();
-    let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
+    read_with_warnings(path, config_path, prefix).map(|(model, _)| model)
+}
 
+/// Imports a `Model` from the [GTFS](http://gtfs.org/) files in the
+/// `path` directory, zip or tar.gz archive like `read`, also returning the elements that
+/// were skipped while reading.
+pub fn read_with_warnings<P>(
+    path: P,
+    config_path: Option<P>,
+    prefix: Option<String>,
+) -> Result<(Model, Vec<Warning>)>
+where
+    P: AsRef<Path>,
+{
+    read_with_profile(path, config_path, prefix, &ParseProfile::default())
+}
+
+/// Imports a `Model` from the [GTFS](http://gtfs.org/) files in the
+/// `path` directory, zip or tar.gz archive like `read`, handling the
+/// issues met according to `profile`, and returns the elements that
+/// were skipped or fixed while reading.
+pub fn read_with_profile<P>(
+    path: P,
+    config_path: Option<P>,
+    prefix: Option<String>,
+    profile: &ParseProfile,
+) -> Result<(Model, Vec<Warning>)>
+where
+    P: AsRef<Path>,
+{
+    let mut file_handler = InputHandler::from_path(path)?;
+    read_from_handler(&mut file_handler, config_path, prefix, profile)
+}
+
+/// Imports a `Model` from the [GTFS](http://gtfs.org/) files of the
+/// zip archive at `path`, like `read`, whatever the extension of the
+/// archive.
+pub fn read_from_zip<P>(path: P, config_path: Option<P>, prefix: Option<String>) -> Result<Model>
+where
+    P: AsRef<Path>,
+{
     let path = path.as_ref();
+    let file = File::open(path).with_context(ctx_from_path!(path))?;
+    let mut file_handler = ZipHandler::new(file, path)?;
+    read_from_handler(
+        &mut file_handler,
+        config_path,
+        prefix,
+        &ParseProfile::default(),
+    ).map(|(model, _)| model)
+}
+
+/// Imports a `Model` from the [GTFS](http://gtfs.org/) files of the
+/// zip archive downloaded from the url, like `read`.
+pub fn read_from_url<P>(url: &str, config_path: Option<P>, prefix: Option<String>) -> Result<Model>
+where
+    P: AsRef<Path>,
+{
+    let mut file_handler = InputHandler::from_url(url)?;
+    read_from_handler(
+        &mut file_handler,
+        config_path,
+        prefix,
+        &ParseProfile::default(),
+    ).map(|(model, _)| model)
+}
+
+/// Imports a `Model` from the [GTFS](http://gtfs.org/) files of the
+/// zip archive downloaded from the url, like `read_from_url`, without
+/// blocking the thread during the download.  The future must be run
+/// by a [tokio](https://tokio.rs) runtime.
+#[cfg(feature = "async")]
+pub fn read_from_url_async<P>(
+    url: &str,
+    config_path: Option<P>,
+    prefix: Option<String>,
+) -> impl Future<Item = Model, Error = Error> + Send
+where
+    P: AsRef<Path> + Send,
+{
+    let source = url.to_string();
+    read_url_async(url, &HttpOptions::default()).and_then(move |archive| {
+        let mut file_handler = ZipHandler::new(archive, source)?;
+        read_from_handler(
+            &mut file_handler,
+            config_path,
+            prefix,
+            &ParseProfile::default(),
+        ).map(|(model, _)| model)
+    })
+}
+
+// Reads the calendars, the stops and transfers, the shapes, and the
+// routes and trips, that do not depend on each other, and returns the
+// distances along the shapes.
+#[cfg(any(not(feature = "parallel"), test))]
+fn read_independent_files<H>(
+    file_handler: &mut H,
+    collections: &mut Collections,
+    comments: &mut CollectionWithId<Comment>,
+    equipments: &mut EquipmentList,
+    modes: &read::ModeMapping,
+    issues: &mut Issues,
+) -> Result<HashMap<String, Vec<f64>>>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    manage_calendars(collections, file_handler)?;
+    let (stop_areas, stop_points, entrances) =
+        read::read_stops(file_handler, comments, equipments, issues)?;
+    collections.transfers = read::read_transfers(file_handler, &stop_points, issues)?;
+    collections.stop_areas = stop_areas;
+    collections.stop_points = stop_points;
+    collections.entrances = entrances;
+    let shape_distances = read::manage_shapes(collections, file_handler, issues)?;
+    read::read_routes(file_handler, collections, modes, issues)?;
+    Ok(shape_distances)
+}
+
+// Loads the existing files among `names` in memory, to be read by
+// another thread.
+#[cfg(feature = "parallel")]
+fn load_files<H>(file_handler: &mut H, names: &[&str]) -> Result<MemoryFileHandler>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let mut files = MemoryFileHandler::new(file_handler.source_name());
+    for name in names {
+        if let (Some(mut reader), path) = file_handler.get_file_if_exists(name)? {
+            let mut content = vec![];
+            reader
+                .read_to_end(&mut content)
+                .with_context(ctx_from_path!(path))?;
+            files.insert(*name, content);
+        }
+    }
+    Ok(files)
+}
+
+// Like the sequential version, the files being loaded in memory and
+// read on several threads, the warnings being reported in the same
+// order.
+#[cfg(feature = "parallel")]
+fn read_independent_files_in_parallel<H>(
+    file_handler: &mut H,
+    collections: &mut Collections,
+    comments: &mut CollectionWithId<Comment>,
+    equipments: &mut EquipmentList,
+    modes: &read::ModeMapping,
+    issues: &mut Issues,
+) -> Result<HashMap<String, Vec<f64>>>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let mut calendar_files = load_files(file_handler, &["calendar.txt", "calendar_dates.txt"])?;
+    let mut stop_files = load_files(file_handler, &["stops.txt", "transfers.txt"])?;
+    let mut shape_files = load_files(file_handler, &["shapes.txt"])?;
+    let mut route_files = load_files(file_handler, &["routes.txt", "trips.txt"])?;
+    let mut stop_issues = issues.fork();
+    let mut shape_issues = issues.fork();
+    let mut route_issues = issues.fork();
 
-    manage_calendars(&mut collections, path)?;
+    let read_calendars = || -> Result<_> {
+        let mut c = Collections::default();
+        manage_calendars::<MemoryFileHandler>(&mut c, &mut calendar_files)?;
+        Ok(c.calendars)
+    };
+    let read_stops = || -> Result<_> {
+        let stops = read::read_stops::<MemoryFileHandler>(
+            &mut stop_files,
+            comments,
+            equipments,
+            &mut stop_issues,
+        )?;
+        let transfers =
+            read::read_transfers::<MemoryFileHandler>(&mut stop_files, &stops.1, &mut stop_issues)?;
+        Ok((stops, transfers))
+    };
+    let read_shapes = || -> Result<_> {
+        let mut c = Collections::default();
+        let shape_distances =
+            read::manage_shapes::<MemoryFileHandler>(&mut c, &mut shape_files, &mut shape_issues)?;
+        Ok((c.geometries, shape_distances))
+    };
+    let read_routes = || {
+        read::read_routes::<MemoryFileHandler>(
+            &mut route_files,
+            collections,
+            modes,
+            &mut route_issues,
+        )
+    };
+    let ((calendars, stops), (shapes, routes)) = rayon::join(
+        || rayon::join(read_calendars, read_stops),
+        || rayon::join(read_shapes, read_routes),
+    );
 
-    let (contributors, mut datasets) = read::read_config(config_path)?;
-    read::set_dataset_validity_period(&mut datasets, &collections.calendars)?;
+    collections.calendars = calendars?;
+    let ((stop_areas, stop_points, entrances), transfers) = stops?;
+    issues.append(stop_issues);
+    collections.stop_areas = stop_areas;
+    collections.stop_points = stop_points;
+    collections.entrances = entrances;
+    collections.transfers = transfers;
+    let (geometries, shape_distances) = shapes?;
+    issues.append(shape_issues);
+    collections.geometries = geometries;
+    routes?;
+    issues.append(route_issues);
+    Ok(shape_distances)
+}
 
+/// Imports a `Model` from the [GTFS](http://gtfs.org/) files given by
+/// the file handler like `read`, handling the issues met according to
+/// `profile`, and returns the elements that were skipped or fixed
+/// while reading.
+pub fn read_from_handler<H, P>(
+    file_handler: &mut H,
+    config_path: Option<P>,
+    prefix: Option<String>,
+    profile: &ParseProfile,
+) -> Result<(Model, Vec<Warning>)>
+where
+    for<'a> &'a mut H: FileHandler,
+    P: AsRef<Path>,
+{
+    info!("Loading GTFS from {}", file_handler.source_name());
+    let mut issues = Issues::new(profile.clone());
+    let mut collections = Collections::default();
+    let mut equipments = EquipmentList::default();
+    let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
+
+    read::read_feed_info(file_handler, &mut collections)?;
+
+    let (contributors, datasets, modes) = read::read_config(config_path)?;
     collections.contributors = contributors;
     collections.datasets = datasets;
 
-    let (networks, companies) = read::read_agency(path)?;
+    let (networks, companies) = read::read_agency(file_handler)?;
     collections.networks = networks;
     collections.companies = companies;
-    let (stop_areas, stop_points) = read::read_stops(path, &mut comments, &mut equipments)?;
-    collections.transfers = read::read_transfers(path, &stop_points)?;
-    collections.stop_areas = stop_areas;
-    collections.stop_points = stop_points;
 
-    read::manage_shapes(&mut collections, path)?;
+    #[cfg(not(feature = "parallel"))]
+    let read_files = read_independent_files;
+    #[cfg(feature = "parallel")]
+    let read_files = read_independent_files_in_parallel;
+    let shape_distances = read_files(
+        file_handler,
+        &mut collections,
+        &mut comments,
+        &mut equipments,
+        &modes,
+        &mut issues,
+    )?;
+    read::set_dataset_validity_period(&mut collections.datasets, &collections.calendars)?;
+    read::read_fares(file_handler, &mut collections, &mut issues)?;
+    collections.pathways = make_opt_collection_with_id(file_handler, "pathways.txt")?;
+    collections.levels = make_opt_collection_with_id(file_handler, "levels.txt")?;
 
-    read::read_routes(path, &mut collections)?;
     collections.equipments = CollectionWithId::new(equipments.into_equipments())?;
     collections.comments = comments;
-    read::manage_stop_times(&mut collections, path)?;
+    read::manage_stop_times(&mut collections, file_handler, &mut issues)?;
+    collections.frequencies = make_opt_collection(file_handler, "frequencies.txt")?;
+    geometries::set_shape_dist_traveled(&mut collections, &shape_distances);
+    read::read_attributions(file_handler, &mut collections, &mut issues)?;
+    read::read_translations(file_handler, &mut collections, &mut issues)?;
+
+    read_utils::add_id_mappings(&mut collections);
 
     //add prefixes
     if let Some(prefix) = prefix {
         add_prefix(prefix, &mut collections)?;
     }
 
-    Ok(Model::new(collections)?)
+    Ok((Model::new(collections)?, issues.into_warnings()))
+}
+
+/// The options of the GTFS export.
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    /// Whether the codes and comments of the objects, that GTFS has no
+    /// place for, are also written in the `object_codes.txt`,
+    /// `comments.txt` and `comment_links.txt` files of the NTFS.
+    pub extensions: bool,
+}
+
+/// Exports a `Model` to [GTFS](http://gtfs.org/) files in the given
+/// directory.  The directory must already exist.
+pub fn write<P: AsRef<Path>>(model: &Model, path: P) -> Result<()> {
+    write_with_options(model, path, &WriteOptions::default())
+}
+
+/// Exports a `Model` to [GTFS](http://gtfs.org/) files in the given
+/// directory like `write`, according to `options`.
+pub fn write_with_options<P: AsRef<Path>>(
+    model: &Model,
+    path: P,
+    options: &WriteOptions,
+) -> Result<()> {
+    let path = path.as_ref();
+    info!("Writing GTFS to {:?}", path);
+
+    write::write_feed_info(path, &model.feed_infos)?;
+    write::write_agencies(path, &model.networks)?;
+    write::write_stops(path, &model.stop_points, &model.stop_areas, &model.entrances)?;
+    write::write_routes(path, model)?;
+    let shape_ids = write::write_shapes(path, &model.vehicle_journeys, &model.geometries)?;
+    write::write_trips_and_stop_times(path, model, &shape_ids)?;
+    if !model.frequencies.is_empty() {
+        ntfs::write::write_collection(path, "frequencies.txt", &model.frequencies)?;
+    }
+    common_format::write_calendar_and_calendar_dates(path, &model.calendars)?;
+    write::write_transfers(path, &model.transfers)?;
+    write::write_opt_collection_with_id(path, "pathways.txt", &model.pathways)?;
+    write::write_opt_collection_with_id(path, "levels.txt", &model.levels)?;
+    write::write_attributions(path, &model.attributions)?;
+    write::write_translations(path, &model.translations)?;
+    if options.extensions {
+        ntfs::write::write_codes(path, model)?;
+        ntfs::write::write_comments(path, model)?;
+    }
+
+    Ok(())
 }
@@ -14,20 +14,29 @@
 // along with this program.  If not, see
 // <http://www.gnu.org/licenses/>.
 
+use super::{
+    Agency, Attribution, DirectionType, Route, RouteType, Shape, Stop, StopLocationType, StopTime,
+    Transfer, TransferType, Translation, Trip, FEED_INFO_KEYS, TRANSLATED_FIELDS,
+};
 use collection::{Collection, CollectionWithId, Id};
 use csv;
+use currency::{Amount, Currency};
 use failure::ResultExt;
 use geo_types::{LineString, Point};
 use model::Collections;
 use objects::{
-    self, Availability, CommentLinksT, Contributor, Coord, KeysValues, Time, TransportType,
+    self, Availability, CommentLinksT, Contributor, Coord, KeysValues, ObjectType, Time,
+    TransportType,
 };
-use read_utils;
-use std::collections::{BTreeSet, HashMap, HashSet};
+use read_utils::{self, csv_reader, opt_csv_reader, FileHandler};
+use report::{Issue, IssueHandling, Issues};
+use serde::de::DeserializeOwned;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs::File;
+use std::io::Read;
+use std::mem;
 use std::path;
 use std::result::Result as StdResult;
-use utils::*;
 use Result;
 extern crate serde_json;
 
@@ -35,23 +44,6 @@ fn default_agency_id() -> String {
     "default_agency_id".to_string()
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct Agency {
-    #[serde(rename = "agency_id")]
-    id: Option<String>,
-    #[serde(rename = "agency_name")]
-    name: String,
-    #[serde(rename = "agency_url")]
-    url: String,
-    #[serde(rename = "agency_timezone")]
-    timezone: Option<String>,
-    #[serde(rename = "agency_lang")]
-    lang: Option<String>,
-    #[serde(rename = "agency_phone")]
-    phone: Option<String>,
-    #[serde(rename = "agency_email")]
-    email: Option<String>,
-}
 impl From<Agency> for objects::Network {
     fn from(agency: Agency) -> objects::Network {
         objects::Network {
@@ -80,45 +72,6 @@ impl From<Agency> for objects::Company {
     }
 }
 
-#[derivative(Default)]
-#[derive(Derivative, Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
-pub enum StopLocationType {
-    #[derivative(Default)]
-    #[serde(rename = "0")]
-    StopPoint,
-    #[serde(rename = "1")]
-    StopArea,
-    #[serde(rename = "2")]
-    StopEntrace,
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct Stop {
-    #[serde(rename = "stop_id")]
-    id: String,
-    #[serde(rename = "stop_code")]
-    code: Option<String>,
-    #[serde(rename = "stop_name")]
-    name: String,
-    #[serde(default, rename = "stop_desc")]
-    desc: String,
-    #[serde(rename = "stop_lon")]
-    lon: f64,
-    #[serde(rename = "stop_lat")]
-    lat: f64,
-    #[serde(rename = "zone_id")]
-    fare_zone_id: Option<String>,
-    #[serde(rename = "stop_url")]
-    url: Option<String>,
-    #[serde(default, deserialize_with = "de_with_empty_default")]
-    location_type: StopLocationType,
-    parent_station: Option<String>,
-    #[serde(rename = "stop_timezone")]
-    timezone: Option<String>,
-    #[serde(default)]
-    wheelchair_boarding: Option<String>,
-}
-
 impl From<Stop> for objects::StopArea {
     fn from(stop: Stop) -> objects::StopArea {
         let mut stop_codes: Vec<(String, String)> = vec![];
@@ -158,43 +111,13 @@ impl From<Stop> for objects::StopPoint {
                 lon: stop.lon,
                 lat: stop.lat,
             },
-            stop_area_id: stop.parent_station.unwrap(),
+            stop_area_id: stop.parent_station.unwrap().into(),
             timezone: stop.timezone,
             visible: true,
             geometry_id: None,
             equipment_id: None,
-            fare_zone_id: None,
-        }
-    }
-}
-
-#[derive(Serialize, Debug, Clone, Eq, PartialEq, Hash)]
-enum RouteType {
-    #[allow(non_camel_case_types)]
-    Tramway_LightRail,
-    Metro,
-    Rail,
-    Bus,
-    Ferry,
-    CableCar,
-    #[allow(non_camel_case_types)]
-    Gondola_SuspendedCableCar,
-    Funicular,
-    Other(u16),
-}
-
-impl RouteType {
-    fn to_gtfs_value(&self) -> String {
-        match *self {
-            RouteType::Tramway_LightRail => "0".to_string(),
-            RouteType::Metro => "1".to_string(),
-            RouteType::Rail => "2".to_string(),
-            RouteType::Bus => "3".to_string(),
-            RouteType::Ferry => "4".to_string(),
-            RouteType::CableCar => "5".to_string(),
-            RouteType::Gondola_SuspendedCableCar => "6".to_string(),
-            RouteType::Funicular => "7".to_string(),
-            RouteType::Other(i) => i.to_string(),
+            fare_zone_id: stop.fare_zone_id,
+            address_id: None,
         }
     }
 }
@@ -224,28 +147,6 @@ impl<'de> ::serde::Deserialize<'de> for RouteType {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct Route {
-    #[serde(rename = "route_id")]
-    id: String,
-    agency_id: Option<String>,
-    #[serde(rename = "route_short_name")]
-    short_name: String,
-    #[serde(rename = "route_long_name")]
-    long_name: String,
-    #[serde(rename = "route_desc")]
-    desc: Option<String>,
-    route_type: RouteType,
-    #[serde(rename = "route_url")]
-    url: Option<String>,
-    #[serde(rename = "route_color", default)]
-    color: Option<objects::Rgb>,
-    #[serde(rename = "route_text_color", default)]
-    text_color: Option<objects::Rgb>,
-    #[serde(rename = "route_sort_order")]
-    sort_order: Option<u32>,
-}
-
 impl Id<Route> for Route {
     fn id(&self) -> &str {
         &self.id
@@ -272,57 +173,31 @@ impl Route {
     }
 }
 
-#[derive(Derivative)]
-#[derivative(Default(bound = ""))]
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
-enum DirectionType {
-    #[derivative(Default)]
-    #[serde(rename = "0")]
-    Forward,
-    #[serde(rename = "1")]
-    Backward,
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct Trip {
-    route_id: String,
-    service_id: String,
-    #[serde(rename = "trip_id")]
-    id: String,
-    #[serde(rename = "trip_headsign")]
-    headsign: Option<String>,
-    #[serde(rename = "trip_short_name")]
-    short_name: Option<String>,
-    #[serde(default, deserialize_with = "de_with_empty_default", rename = "direction_id")]
-    direction: DirectionType,
-    block_id: Option<String>,
-    shape_id: Option<String>,
-    #[serde(deserialize_with = "de_with_empty_default", default)]
-    wheelchair_accessible: u8,
-    #[serde(deserialize_with = "de_with_empty_default", default)]
-    bikes_allowed: u8,
-}
-
 impl Trip {
     fn to_ntfs_vehicle_journey(
         &self,
         routes: &CollectionWithId<Route>,
         dataset: &objects::Dataset,
         trip_property_id: &Option<String>,
+        modes: &ModeMapping,
     ) -> objects::VehicleJourney {
         let route = routes.get(&self.route_id).unwrap();
-        let physical_mode = get_physical_mode(&route.route_type);
+        let physical_mode = modes.physical_mode(&route.route_type);
 
         objects::VehicleJourney {
             id: self.id.clone(),
             codes: KeysValues::default(),
             object_properties: KeysValues::default(),
             comment_links: CommentLinksT::default(),
-            route_id: route.get_id_by_direction(&self.direction),
+            route_id: route.get_id_by_direction(&self.direction).into(),
             physical_mode_id: physical_mode.id,
             dataset_id: dataset.id.clone(),
-            service_id: self.service_id.clone(),
-            headsign: self.short_name.clone().or_else(|| self.headsign.clone()),
+            service_id: self.service_id.clone().into(),
+            headsign: self
+                .short_name
+                .as_ref()
+                .or(self.headsign.as_ref())
+                .map(|h| h.as_str().into()),
             block_id: self.block_id.clone(),
             company_id: route.agency_id.clone().unwrap_or_else(default_agency_id),
             trip_property_id: trip_property_id.clone(),
@@ -332,52 +207,50 @@ impl Trip {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct StopTime {
-    trip_id: String,
-    arrival_time: Time,
-    departure_time: Time,
-    stop_id: String,
-    stop_sequence: u32,
-    #[serde(deserialize_with = "de_with_empty_default", default)]
-    pickup_type: u8,
-    #[serde(deserialize_with = "de_with_empty_default", default)]
-    drop_off_type: u8,
-}
-
-#[derive(Deserialize, Debug)]
-pub struct Shape {
-    #[serde(rename = "shape_id")]
-    id: String,
-    #[serde(rename = "shape_pt_lat")]
-    lat: f64,
-    #[serde(rename = "shape_pt_lon")]
-    lon: f64,
-    #[serde(rename = "shape_pt_sequence")]
-    sequence: u32,
-}
-
-pub fn manage_shapes<P: AsRef<path::Path>>(collections: &mut Collections, path: P) -> Result<()> {
+/// Reads the shapes as line string geometries, and returns the
+/// `shape_dist_traveled` of the points of the shapes giving it for
+/// all their points.
+pub fn manage_shapes<H>(
+    collections: &mut Collections,
+    file_handler: &mut H,
+    issues: &mut Issues,
+) -> Result<HashMap<String, Vec<f64>>>
+where
+    for<'a> &'a mut H: FileHandler,
+{
     let file = "shapes.txt";
-    let path = path.as_ref().join(file);
-    if !path.exists() {
-        info!("Skipping {}", file);
-        return Ok(());
-    }
+    let mut rdr = match opt_csv_reader(file_handler, file)? {
+        (Some(rdr), _) => rdr,
+        (None, _) => {
+            info!("Skipping {}", file);
+            return Ok(HashMap::new());
+        }
+    };
 
     info!("Reading {}", file);
-    let mut rdr = csv::Reader::from_path(&path).with_context(ctx_from_path!(path))?;
-    let mut shapes: Vec<Shape> = rdr
-        .deserialize()
-        .collect::<StdResult<_, _>>()
-        .with_context(ctx_from_path!(path))?;
+    let mut shapes: Vec<Shape> = vec![];
+    for shape in rdr.deserialize() {
+        shapes.push(skip_fail!(shape, issues, Issue::InvalidRow, file));
+    }
 
     shapes.sort_unstable_by_key(|s| s.sequence);
     let mut map: HashMap<String, Vec<Point<f64>>> = HashMap::new();
+    let mut distances: HashMap<String, Option<Vec<f64>>> = HashMap::new();
     for s in &shapes {
         map.entry(s.id.clone())
             .or_insert_with(|| vec![])
             .push((s.lon, s.lat).into());
+        let shape_distances = distances
+            .entry(s.id.clone())
+            .or_insert_with(|| Some(vec![]));
+        match s.shape_dist_traveled {
+            Some(distance) => {
+                if let Some(ref mut shape_distances) = *shape_distances {
+                    shape_distances.push(distance);
+                }
+            }
+            None => *shape_distances = None,
+        }
     }
 
     collections.geometries = CollectionWithId::new(
@@ -392,72 +265,290 @@ pub fn manage_shapes<P: AsRef<path::Path>>(collections: &mut Collections, path:
             .collect(),
     )?;
 
-    Ok(())
+    Ok(distances
+        .into_iter()
+        .filter_map(|(id, distances)| distances.map(|d| (id, d)))
+        .collect())
+}
+
+// Deserializes a row of a csv file, returning the line of the row
+// along with the result.
+fn deserialize_record<T>(
+    headers: &csv::StringRecord,
+    record: csv::Result<csv::StringRecord>,
+) -> (Option<u64>, csv::Result<T>)
+where
+    T: DeserializeOwned,
+{
+    match record {
+        Ok(record) => (
+            record.position().map(|p| p.line()),
+            record.deserialize(Some(headers)),
+        ),
+        Err(e) => (e.position().map(|p| p.line()), Err(e)),
+    }
+}
+
+// Reads all the rows of a csv file with their line, handling the rows
+// that cannot be deserialized as `issue`.
+fn read_rows<T, R>(
+    mut rdr: csv::Reader<R>,
+    path: &path::Path,
+    file: &str,
+    issue: Issue,
+    issues: &mut Issues,
+) -> Result<Vec<(Option<u64>, T)>>
+where
+    T: DeserializeOwned,
+    R: Read,
+{
+    let headers = rdr.headers().with_context(ctx_from_path!(path))?.clone();
+    let mut rows = vec![];
+    for record in rdr.into_records() {
+        let (line, row) = deserialize_record(&headers, record);
+        let row = skip_fail!(row, issues, issue, file, line);
+        rows.push((line, row));
+    }
+    Ok(rows)
+}
+
+#[cfg(any(not(feature = "parallel"), test))]
+fn deserialize_stop_times<R: Read>(
+    mut rdr: csv::Reader<R>,
+) -> csv::Result<impl Iterator<Item = (Option<u64>, csv::Result<StopTime>)>> {
+    let headers = rdr.headers()?.clone();
+    Ok(rdr
+        .into_records()
+        .map(move |record| deserialize_record(&headers, record)))
+}
+
+// The number of rows of stop_times.txt read before being deserialized
+// by the threads of the parallel mode.
+#[cfg(feature = "parallel")]
+const STOP_TIMES_CHUNK_SIZE: usize = 100_000;
+
+// Like the sequential version, the rows being deserialized by chunks
+// on several threads, in the order of the file.
+#[cfg(feature = "parallel")]
+fn deserialize_stop_times_in_parallel<R: Read>(
+    mut rdr: csv::Reader<R>,
+    chunk_size: usize,
+) -> csv::Result<impl Iterator<Item = (Option<u64>, csv::Result<StopTime>)>> {
+    use rayon::prelude::*;
+    use std::iter;
+
+    let headers = rdr.headers()?.clone();
+    let mut records = rdr.into_records();
+    let chunks = iter::from_fn(move || {
+        let chunk: Vec<_> = records.by_ref().take(chunk_size).collect();
+        if chunk.is_empty() {
+            return None;
+        }
+        let stop_times: Vec<_> = chunk
+            .into_par_iter()
+            .map(|record| deserialize_record(&headers, record))
+            .collect();
+        Some(stop_times)
+    });
+    Ok(chunks.flatten())
+}
+
+fn seconds(time: Time) -> u32 {
+    time.hours() * 3600 + time.minutes() * 60 + time.seconds()
+}
+
+// Fills the times of the stop times without time, flagged `false`,
+// between two stop times with times, proportionally to their shape
+// distance when known for all of them, or else evenly.
+fn interpolate_times(stop_times: &mut [(objects::StopTime, bool)]) {
+    let known: Vec<_> = (0..stop_times.len())
+        .filter(|&i| stop_times[i].1)
+        .collect();
+    for w in known.windows(2) {
+        let (from, to) = (w[0], w[1]);
+        if to == from + 1 {
+            continue;
+        }
+        let start = seconds(stop_times[from].0.departure_time);
+        let duration = seconds(stop_times[to].0.arrival_time).saturating_sub(start);
+        let distances: Option<Vec<f64>> = stop_times[from..=to]
+            .iter()
+            .map(|(st, _)| st.shape_dist_traveled())
+            .collect();
+        for i in from + 1..to {
+            let ratio = match distances {
+                Some(ref d) if d[to - from] > d[0] => {
+                    (d[i - from] - d[0]) / (d[to - from] - d[0])
+                }
+                _ => (i - from) as f64 / (to - from) as f64,
+            };
+            let time = Time::new(0, 0, start + (f64::from(duration) * ratio).round() as u32);
+            stop_times[i].0.arrival_time = time;
+            stop_times[i].0.departure_time = time;
+        }
+    }
 }
 
-pub fn manage_stop_times<P: AsRef<path::Path>>(
+pub fn manage_stop_times<H>(
     collections: &mut Collections,
-    path: P,
-) -> Result<()> {
-    info!("Reading stop_times.txt");
-    let path = path.as_ref().join("stop_times.txt");
-    let mut rdr = csv::Reader::from_path(&path).with_context(ctx_from_path!(path))?;
-    for stop_time in rdr.deserialize() {
-        let stop_time: StopTime = stop_time.with_context(ctx_from_path!(path))?;
-        let stop_point_idx = collections
-            .stop_points
-            .get_idx(&stop_time.stop_id)
-            .ok_or_else(|| {
-                format_err!(
-                    "Problem reading {:?}: stop_id={:?} not found",
-                    path,
-                    stop_time.stop_id
-                )
-            })?;
-        let vj_idx = collections
-            .vehicle_journeys
-            .get_idx(&stop_time.trip_id)
-            .ok_or_else(|| {
-                format_err!(
-                    "Problem reading {:?}: trip_id={:?} not found",
-                    path,
-                    stop_time.trip_id
-                )
-            })?;
-        collections
-            .vehicle_journeys
-            .index_mut(vj_idx)
-            .stop_times
-            .push(objects::StopTime {
+    file_handler: &mut H,
+    issues: &mut Issues,
+) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let file = "stop_times.txt";
+    info!("Reading {}", file);
+    let (rdr, path) = csv_reader(file_handler, file)?;
+    #[cfg(not(feature = "parallel"))]
+    let stop_times = deserialize_stop_times(rdr);
+    #[cfg(feature = "parallel")]
+    let stop_times = deserialize_stop_times_in_parallel(rdr, STOP_TIMES_CHUNK_SIZE);
+    let stop_times = stop_times.with_context(ctx_from_path!(path))?;
+    let mut vehicle_journeys = collections.vehicle_journeys.take();
+    // The stop times are grouped by position of their vehicle journey
+    // while streaming, the rows of a trip usually following each other.
+    // Each stop time is flagged by whether it has a time in the file.
+    let mut vj_stop_times: Vec<Vec<(objects::StopTime, bool)>> =
+        (0..vehicle_journeys.len()).map(|_| vec![]).collect();
+    {
+        let vj_positions: HashMap<&str, usize> = vehicle_journeys
+            .iter()
+            .enumerate()
+            .map(|(pos, vj)| (vj.id.as_str(), pos))
+            .collect();
+        let mut last_vj_pos: Option<usize> = None;
+        for (line, stop_time) in stop_times {
+            let stop_time = skip_fail!(stop_time, issues, Issue::InvalidRow, file, line);
+            let stop_point_idx = skip_fail!(
+                collections
+                    .stop_points
+                    .get_idx(&stop_time.stop_id)
+                    .ok_or_else(|| format_err!(
+                        "Problem reading {:?}: stop_id={:?} not found",
+                        path,
+                        stop_time.stop_id
+                    )),
+                issues,
+                Issue::UnknownReference,
+                file,
+                line
+            );
+            let vj_pos = match last_vj_pos {
+                Some(pos) if vehicle_journeys[pos].id == stop_time.trip_id => pos,
+                _ => skip_fail!(
+                    vj_positions
+                        .get(stop_time.trip_id.as_str())
+                        .cloned()
+                        .ok_or_else(|| format_err!(
+                            "Problem reading {:?}: trip_id={:?} not found",
+                            path,
+                            stop_time.trip_id
+                        )),
+                    issues,
+                    Issue::UnknownReference,
+                    file,
+                    line
+                ),
+            };
+            last_vj_pos = Some(vj_pos);
+            // The stop times without time are estimated, to be
+            // interpolated.
+            let (arrival_time, departure_time, timed) =
+                match (stop_time.arrival_time, stop_time.departure_time) {
+                    (Some(arrival), Some(departure)) => (arrival, departure, true),
+                    (Some(time), None) | (None, Some(time)) => (time, time, true),
+                    (None, None) => (Time::new(0, 0, 0), Time::new(0, 0, 0), false),
+                };
+            let stop_time = objects::StopTime {
                 stop_point_idx,
                 sequence: stop_time.stop_sequence,
-                arrival_time: stop_time.arrival_time,
-                departure_time: stop_time.departure_time,
+                arrival_time,
+                departure_time,
                 boarding_duration: 0,
                 alighting_duration: 0,
                 pickup_type: stop_time.pickup_type,
                 drop_off_type: stop_time.drop_off_type,
-                datetime_estimated: false,
+                datetime_estimated: !timed || stop_time.timepoint == Some(0),
                 local_zone_id: None,
-            });
+                extras: objects::StopTimeExtras::boxed(
+                    stop_time.shape_dist_traveled,
+                    stop_time.continuous_pickup,
+                    stop_time.continuous_drop_off,
+                ),
+            };
+            vj_stop_times[vj_pos].push((stop_time, timed));
+        }
     }
-    let mut vehicle_journeys = collections.vehicle_journeys.take();
-    for vj in &mut vehicle_journeys {
-        vj.stop_times.sort_unstable_by_key(|st| st.sequence);
+    for (vj, mut stop_times) in vehicle_journeys.iter_mut().zip(vj_stop_times) {
+        if stop_times.windows(2).any(|w| w[0].0.sequence > w[1].0.sequence) {
+            stop_times.sort_unstable_by_key(|(st, _)| st.sequence);
+        }
+        let known = |&(_, timed): &(objects::StopTime, bool)| timed;
+        let first = stop_times.iter().position(known).unwrap_or(stop_times.len());
+        let last = stop_times.iter().rposition(known).map_or(0, |pos| pos + 1);
+        if first > 0 || last < stop_times.len() {
+            issues.handle_object(
+                Issue::InvalidRow,
+                file,
+                &vj.id,
+                format!(
+                    "stop times without time skipped at the ends of trip {}",
+                    vj.id
+                ),
+            )?;
+            stop_times.truncate(last);
+            stop_times.drain(..first.min(last));
+        }
+        interpolate_times(&mut stop_times);
+        vj.stop_times = stop_times.into_iter().map(|(st, _)| st).collect();
+        // the collected vector may keep the capacity of the grouped
+        // stop times
+        vj.stop_times.shrink_to_fit();
     }
     collections.vehicle_journeys = CollectionWithId::new(vehicle_journeys)?;
     Ok(())
 }
 
-pub fn read_agency<P: AsRef<path::Path>>(
-    path: P,
+/// Reads the publisher, language, validity period and version of the
+/// feed from `feed_info.txt` into the feed infos.
+pub fn read_feed_info<H>(file_handler: &mut H, collections: &mut Collections) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let file = "feed_info.txt";
+    let (mut rdr, path) = match opt_csv_reader(file_handler, file)? {
+        (Some(rdr), path) => (rdr, path),
+        (None, _) => {
+            info!("Skipping {}", file);
+            return Ok(());
+        }
+    };
+    info!("Reading {}", file);
+    let mut records = rdr.deserialize();
+    if let Some(record) = records.next() {
+        let record: HashMap<String, String> = record.with_context(ctx_from_path!(path))?;
+        collections.feed_infos.extend(record.into_iter().filter(|(key, value)| {
+            FEED_INFO_KEYS.contains(&key.as_str()) && !value.is_empty()
+        }));
+    }
+    if records.next().is_some() {
+        warn!("only the first row of {} is read", file);
+    }
+    Ok(())
+}
+
+pub fn read_agency<H>(
+    file_handler: &mut H,
 ) -> Result<(
     CollectionWithId<objects::Network>,
     CollectionWithId<objects::Company>,
-)> {
+)>
+where
+    for<'a> &'a mut H: FileHandler,
+{
     info!("Reading agency.txt");
-    let path = path.as_ref().join("agency.txt");
-    let mut rdr = csv::Reader::from_path(&path).with_context(ctx_from_path!(path))?;
+    let (mut rdr, path) = csv_reader(file_handler, "agency.txt")?;
     let gtfs_agencies: Vec<Agency> = rdr
         .deserialize()
         .collect::<StdResult<_, _>>()
@@ -517,7 +608,24 @@ impl EquipmentList {
     }
 
     pub fn push(&mut self, equipment: objects::Equipment) -> String {
-        let equipment_id = self.equipments.len().to_string();
+        if let Some(id) = self.equipments.get(&equipment) {
+            return id.clone();
+        }
+        // the identifiers kept by `insert` may not be numbers
+        let used: HashSet<_> = self.equipments.values().cloned().collect();
+        let equipment_id = (self.equipments.len()..)
+            .map(|i| i.to_string())
+            .find(|id| !used.contains(id))
+            .unwrap();
+        self.equipments.insert(equipment, equipment_id.clone());
+        equipment_id
+    }
+
+    /// Adds an equipment keeping its identifier, unless the same
+    /// equipment is already in the list, and returns the identifier of
+    /// the equipment in the list.
+    pub fn insert(&mut self, mut equipment: objects::Equipment) -> String {
+        let equipment_id = mem::replace(&mut equipment.id, String::new());
         let id = self.equipments.entry(equipment).or_insert(equipment_id);
         id.clone()
     }
@@ -551,29 +659,35 @@ fn get_equipment_id_and_populate_equipments(
         })
 }
 
-pub fn read_stops<P: AsRef<path::Path>>(
-    path: P,
+pub fn read_stops<H>(
+    file_handler: &mut H,
     comments: &mut CollectionWithId<objects::Comment>,
     equipments: &mut EquipmentList,
+    issues: &mut Issues,
 ) -> Result<(
     CollectionWithId<objects::StopArea>,
     CollectionWithId<objects::StopPoint>,
-)> {
-    info!("Reading stops.txt");
-    let path = path.as_ref().join("stops.txt");
-    let mut rdr = csv::Reader::from_path(&path).with_context(ctx_from_path!(path))?;
-    let gtfs_stops: Vec<Stop> = rdr
-        .deserialize()
-        .collect::<StdResult<_, _>>()
-        .with_context(ctx_from_path!(path))?;
+    CollectionWithId<objects::Entrance>,
+)>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let file = "stops.txt";
+    info!("Reading {}", file);
+    let (mut rdr, _) = csv_reader(file_handler, file)?;
+    let mut gtfs_stops: Vec<Stop> = vec![];
+    for stop in rdr.deserialize() {
+        gtfs_stops.push(skip_fail!(stop, issues, Issue::InvalidRow, file));
+    }
 
     let mut stop_areas = vec![];
     let mut stop_points = vec![];
+    let mut entrances = vec![];
     for mut stop in gtfs_stops {
-        let comment_links = manage_comment_from_stop(comments, &stop);
-        let equipment_id = get_equipment_id_and_populate_equipments(equipments, &stop);
         match stop.location_type {
             StopLocationType::StopPoint => {
+                let comment_links = manage_comment_from_stop(comments, &stop);
+                let equipment_id = get_equipment_id_and_populate_equipments(equipments, &stop);
                 if stop.parent_station.is_none() {
                     let mut new_stop_area = stop.clone();
                     new_stop_area.id = format!("Navitia:{}", new_stop_area.id);
@@ -587,94 +701,114 @@ pub fn read_stops<P: AsRef<path::Path>>(
                 stop_points.push(stop_point);
             }
             StopLocationType::StopArea => {
+                let comment_links = manage_comment_from_stop(comments, &stop);
+                let equipment_id = get_equipment_id_and_populate_equipments(equipments, &stop);
                 let mut stop_area = objects::StopArea::from(stop);
                 stop_area.comment_links = comment_links;
                 stop_area.equipment_id = equipment_id;
                 stop_areas.push(stop_area);
             }
-            StopLocationType::StopEntrace => warn!(
-                "stop location type {:?} not handled for the moment, skipping",
-                StopLocationType::StopEntrace
-            ),
+            StopLocationType::StopEntrace => {
+                let stop_area_id = skip_fail!(
+                    stop.parent_station.clone().ok_or_else(|| format_err!(
+                        "entrance {} without parent_station, skipping",
+                        stop.id
+                    )),
+                    issues,
+                    Issue::InvalidOptionalElement,
+                    file
+                );
+                entrances.push(objects::Entrance {
+                    id: stop.id,
+                    name: stop.name,
+                    coord: Coord {
+                        lon: stop.lon,
+                        lat: stop.lat,
+                    },
+                    stop_area_id: stop_area_id.into(),
+                    timezone: stop.timezone,
+                });
+            }
         }
     }
     let stoppoints = CollectionWithId::new(stop_points)?;
     let stopareas = CollectionWithId::new(stop_areas)?;
-    Ok((stopareas, stoppoints))
-}
-
-#[derive(Deserialize, Debug, Derivative)]
-#[derivative(Default)]
-enum TransferType {
-    #[derivative(Default)]
-    #[serde(rename = "0")]
-    Recommended,
-    #[serde(rename = "1")]
-    Timed,
-    #[serde(rename = "2")]
-    WithTransferTime,
-    #[serde(rename = "3")]
-    NotPossible,
+    let entrances = CollectionWithId::new(entrances)?;
+    Ok((stopareas, stoppoints, entrances))
 }
 
-#[derive(Deserialize, Debug)]
-pub struct Transfer {
-    from_stop_id: String,
-    to_stop_id: String,
-    #[serde(deserialize_with = "de_with_empty_default")]
-    transfer_type: TransferType,
-    min_transfer_time: Option<u32>,
-}
-
-pub fn read_transfers<P: AsRef<path::Path>>(
-    path: P,
+pub fn read_transfers<H>(
+    file_handler: &mut H,
     stop_points: &CollectionWithId<objects::StopPoint>,
-) -> Result<Collection<objects::Transfer>> {
+    issues: &mut Issues,
+) -> Result<Collection<objects::Transfer>>
+where
+    for<'a> &'a mut H: FileHandler,
+{
     let file = "transfers.txt";
-    let path = path.as_ref().join(file);
-    if !path.exists() {
-        info!("Skipping {}", file);
-        return Ok(Collection::new(vec![]));
-    }
+    let (mut rdr, path) = match opt_csv_reader(file_handler, file)? {
+        (Some(rdr), path) => (rdr, path),
+        (None, _) => {
+            info!("Skipping {}", file);
+            return Ok(Collection::new(vec![]));
+        }
+    };
     info!("Reading {}", file);
-    let mut rdr = csv::Reader::from_path(&path).with_context(ctx_from_path!(path))?;
     let mut transfers = vec![];
     for transfer in rdr.deserialize() {
-        let transfer: Transfer = transfer.with_context(ctx_from_path!(path))?;
-        let from_stop_point = skip_fail!(stop_points.get(&transfer.from_stop_id).ok_or_else(
-            || format_err!(
-                "Problem reading {:?}: from_stop_id={:?} not found",
-                path,
-                transfer.from_stop_id
-            )
-        ));
+        let transfer: Transfer = skip_fail!(transfer, issues, Issue::InvalidRow, file);
+        let from_stop_point = skip_fail!(
+            stop_points
+                .get(&transfer.from_stop_id)
+                .ok_or_else(|| format_err!(
+                    "Problem reading {:?}: from_stop_id={:?} not found",
+                    path,
+                    transfer.from_stop_id
+                )),
+            issues,
+            Issue::InvalidOptionalElement,
+            file
+        );
 
-        let to_stop_point = skip_fail!(stop_points.get(&transfer.to_stop_id).ok_or_else(
-            || {
-                format_err!(
+        let to_stop_point = skip_fail!(
+            stop_points
+                .get(&transfer.to_stop_id)
+                .ok_or_else(|| format_err!(
                     "Problem reading {:?}: to_stop_id={:?} not found",
                     path,
                     transfer.to_stop_id
-                )
-            }
-        ));
+                )),
+            issues,
+            Issue::InvalidOptionalElement,
+            file
+        );
 
-        let (min_transfer_time, real_min_transfer_time) = match transfer.transfer_type {
-            TransferType::Recommended => {
-                let distance = from_stop_point.coord.distance_to(&to_stop_point.coord);
-                let transfer_time = (distance / 0.785) as u32;
+        let recommended_times = || {
+            let distance = from_stop_point.coord.distance_to(&to_stop_point.coord);
+            let transfer_time = (distance / 0.785) as u32;
 
-                (Some(transfer_time), Some(transfer_time + 2 * 60))
-            }
+            (Some(transfer_time), Some(transfer_time + 2 * 60))
+        };
+        let (min_transfer_time, real_min_transfer_time) = match transfer.transfer_type {
+            TransferType::Recommended => recommended_times(),
             TransferType::Timed => (Some(0), Some(0)),
             TransferType::WithTransferTime => {
                 if transfer.min_transfer_time.is_none() {
-                    warn!(
-                        "The min_transfer_time between from_stop_id {} and to_stop_id {} is empty",
-                        from_stop_point.id, to_stop_point.id
-                    );
+                    let handling = issues.handle(
+                        Issue::MissingValue,
+                        file,
+                        format!(
+                            "The min_transfer_time between from_stop_id {} and to_stop_id {} is empty",
+                            from_stop_point.id, to_stop_point.id
+                        ),
+                    )?;
+                    match handling {
+                        IssueHandling::Fix => recommended_times(),
+                        _ => (None, None),
+                    }
+                } else {
+                    (transfer.min_transfer_time, transfer.min_transfer_time)
                 }
-                (transfer.min_transfer_time, transfer.min_transfer_time)
             }
             TransferType::NotPossible => (Some(86400), Some(86400)),
         };
@@ -691,15 +825,201 @@ pub fn read_transfers<P: AsRef<path::Path>>(
     Ok(Collection::new(transfers))
 }
 
+#[derive(Deserialize, Debug)]
+struct FareAttribute {
+    fare_id: String,
+    price: Amount,
+    currency_type: Currency,
+}
+
+#[derive(Deserialize, Debug)]
+struct FareRule {
+    fare_id: String,
+    origin_id: Option<String>,
+    destination_id: Option<String>,
+}
+
+/// Reads `fare_attributes.txt` and `fare_rules.txt` into tickets,
+/// valid during the dataset validity period, and OD rules between the
+/// stop areas of the origin and destination zones.  The fare rules
+/// without an origin or a destination zone give no OD rule.
+pub fn read_fares<H>(
+    file_handler: &mut H,
+    collections: &mut Collections,
+    issues: &mut Issues,
+) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let mut tickets = vec![];
+    let mut ticket_prices = vec![];
+    {
+        let file = "fare_attributes.txt";
+        let mut rdr = match opt_csv_reader(&mut *file_handler, file)? {
+            (Some(rdr), _) => rdr,
+            (None, _) => {
+                info!("Skipping {}", file);
+                return Ok(());
+            }
+        };
+        info!("Reading {}", file);
+        let (validity_start, validity_end) = match collections.datasets.values().next() {
+            Some(dataset) => (dataset.start_date, dataset.end_date),
+            None => bail!("no dataset to get the validity period of the fares"),
+        };
+        for fare_attribute in rdr.deserialize() {
+            let fare_attribute: FareAttribute =
+                skip_fail!(fare_attribute, issues, Issue::InvalidOptionalElement, file);
+            tickets.push(objects::Ticket {
+                id: fare_attribute.fare_id.clone(),
+                name: fare_attribute.fare_id.clone(),
+                comment: None,
+            });
+            ticket_prices.push(objects::TicketPrice {
+                ticket_id: fare_attribute.fare_id,
+                price: fare_attribute.price,
+                currency: fare_attribute.currency_type,
+                validity_start,
+                validity_end,
+            });
+        }
+    }
+    let tickets = CollectionWithId::new(tickets)?;
+
+    let mut zones: HashMap<&str, BTreeSet<&str>> = HashMap::new();
+    for stop_point in collections.stop_points.values() {
+        if let Some(ref zone) = stop_point.fare_zone_id {
+            zones
+                .entry(zone)
+                .or_default()
+                .insert(&stop_point.stop_area_id);
+        }
+    }
+    let mut od_rules = BTreeSet::new();
+    let file = "fare_rules.txt";
+    if let (Some(mut rdr), path) = opt_csv_reader(file_handler, file)? {
+        info!("Reading {}", file);
+        for fare_rule in rdr.deserialize() {
+            let fare_rule: FareRule =
+                skip_fail!(fare_rule, issues, Issue::InvalidOptionalElement, file);
+            skip_fail!(
+                tickets.get_idx(&fare_rule.fare_id).ok_or_else(|| format_err!(
+                    "Problem reading {:?}: fare_id={:?} not found",
+                    path,
+                    fare_rule.fare_id
+                )),
+                issues,
+                Issue::InvalidOptionalElement,
+                file
+            );
+            let (origin, destination) = match (fare_rule.origin_id, fare_rule.destination_id) {
+                (Some(origin), Some(destination)) => (origin, destination),
+                _ => continue,
+            };
+            let stop_areas = |zone: &str| zones.get(zone).into_iter().flat_map(|s| s.iter());
+            for origin_stop_area_id in stop_areas(&origin) {
+                for destination_stop_area_id in stop_areas(&destination) {
+                    od_rules.insert((
+                        fare_rule.fare_id.clone(),
+                        origin_stop_area_id.to_string(),
+                        destination_stop_area_id.to_string(),
+                    ));
+                }
+            }
+        }
+    }
+
+    collections.tickets = tickets;
+    collections.ticket_prices = Collection::new(ticket_prices);
+    collections.od_rules = Collection::new(
+        od_rules
+            .into_iter()
+            .map(|(ticket_id, origin_stop_area_id, destination_stop_area_id)| {
+                objects::ODRule {
+                    ticket_id,
+                    origin_stop_area_id,
+                    destination_stop_area_id,
+                }
+            })
+            .collect(),
+    );
+    Ok(())
+}
+
 #[derive(Deserialize, Debug)]
 struct Dataset {
     dataset_id: String,
 }
 
+// A mode of the config overriding the default modes of a route_type.
+#[derive(Deserialize, Debug, Clone)]
+struct ModeRule {
+    route_type: String,
+    physical_mode_id: Option<String>,
+    physical_mode_name: Option<String>,
+    commercial_mode_id: Option<String>,
+    commercial_mode_name: Option<String>,
+    co2_emission: Option<f32>,
+}
+
+/// The physical and commercial modes given to the routes according
+/// to their route_type, overridable by the `modes` of the config.
+#[derive(Debug, Default)]
+pub struct ModeMapping {
+    rules: HashMap<String, ModeRule>,
+}
+
+impl ModeMapping {
+    fn new(rules: Vec<ModeRule>) -> Result<Self> {
+        let mut mapping = ModeMapping::default();
+        for rule in rules {
+            let route_type = rule.route_type.trim().to_string();
+            ensure!(
+                !mapping.rules.contains_key(&route_type),
+                "route_type {} is mapped twice in the config modes",
+                route_type
+            );
+            mapping.rules.insert(route_type, rule);
+        }
+        Ok(mapping)
+    }
+
+    fn physical_mode(&self, route_type: &RouteType) -> objects::PhysicalMode {
+        let mut mode = get_physical_mode(route_type);
+        if let Some(rule) = self.rules.get(&route_type.to_gtfs_value()) {
+            if let Some(ref id) = rule.physical_mode_id {
+                mode.id = id.clone();
+                mode.name = rule.physical_mode_name.clone().unwrap_or_else(|| id.clone());
+            } else if let Some(ref name) = rule.physical_mode_name {
+                mode.name = name.clone();
+            }
+            if rule.co2_emission.is_some() {
+                mode.co2_emission = rule.co2_emission;
+            }
+        }
+        mode
+    }
+
+    fn commercial_mode(&self, route_type: &RouteType) -> objects::CommercialMode {
+        let mut mode = get_commercial_mode(route_type);
+        if let Some(rule) = self.rules.get(&route_type.to_gtfs_value()) {
+            if let Some(ref id) = rule.commercial_mode_id {
+                mode.id = id.clone();
+                mode.name = rule.commercial_mode_name.clone().unwrap_or_else(|| id.clone());
+            } else if let Some(ref name) = rule.commercial_mode_name {
+                mode.name = name.clone();
+            }
+        }
+        mode
+    }
+}
+
 #[derive(Deserialize, Debug)]
 struct Config {
     contributor: objects::Contributor,
     dataset: Dataset,
+    #[serde(default)]
+    modes: Vec<ModeRule>,
 }
 
 pub fn read_config<P: AsRef<path::Path>>(
@@ -707,9 +1027,11 @@ pub fn read_config<P: AsRef<path::Path>>(
 ) -> Result<(
     CollectionWithId<objects::Contributor>,
     CollectionWithId<objects::Dataset>,
+    ModeMapping,
 )> {
     let contributor;
     let dataset;
+    let mut modes = ModeMapping::default();
     if let Some(config_path) = config_path {
         let json_config_file = File::open(config_path)?;
         let config: Config = serde_json::from_reader(json_config_file)?;
@@ -717,6 +1039,7 @@ pub fn read_config<P: AsRef<path::Path>>(
 
         contributor = config.contributor;
         dataset = objects::Dataset::new(config.dataset.dataset_id, contributor.id.clone());
+        modes = ModeMapping::new(config.modes)?;
     } else {
         contributor = Contributor::default();
         dataset = objects::Dataset::default();
@@ -724,7 +1047,7 @@ pub fn read_config<P: AsRef<path::Path>>(
 
     let contributors = CollectionWithId::new(vec![contributor])?;
     let datasets = CollectionWithId::new(vec![dataset])?;
-    Ok((contributors, datasets))
+    Ok((contributors, datasets, modes))
 }
 
 fn get_commercial_mode_label(route_type: &RouteType) -> String {
@@ -788,17 +1111,21 @@ fn get_physical_mode(route_type: &RouteType) -> objects::PhysicalMode {
 
 fn get_modes_from_gtfs(
     gtfs_routes: &CollectionWithId<Route>,
+    modes: &ModeMapping,
 ) -> (Vec<objects::CommercialMode>, Vec<objects::PhysicalMode>) {
     let gtfs_mode_types: HashSet<RouteType> =
         gtfs_routes.values().map(|r| r.route_type.clone()).collect();
 
     let commercial_modes = gtfs_mode_types
         .iter()
-        .map(|mt| get_commercial_mode(mt))
+        .map(|mt| modes.commercial_mode(mt))
+        .map(|m| (m.id.clone(), m))
+        .collect::<BTreeMap<_, _>>()
+        .into_values()
         .collect();
     let physical_modes = gtfs_mode_types
         .iter()
-        .map(|mt| get_physical_mode(mt))
+        .map(|mt| modes.physical_mode(mt))
         .collect::<BTreeSet<_>>()
         .into_iter()
         .collect();
@@ -811,7 +1138,7 @@ fn get_route_with_smallest_name<'a>(routes: &'a [&Route]) -> &'a Route {
 
 type MapLineRoutes<'a> = HashMap<(Option<String>, String), Vec<&'a Route>>;
 
-fn map_line_routes(gtfs_routes: &CollectionWithId<Route>) -> MapLineRoutes {
+fn map_line_routes(gtfs_routes: &CollectionWithId<Route>) -> MapLineRoutes<'_> {
     let mut map = HashMap::new();
     for r in gtfs_routes.values() {
         map.entry(r.get_line_key())
@@ -821,7 +1148,11 @@ fn map_line_routes(gtfs_routes: &CollectionWithId<Route>) -> MapLineRoutes {
     map
 }
 
-fn make_lines(gtfs_trips: &[Trip], map_line_routes: &MapLineRoutes) -> Vec<objects::Line> {
+fn make_lines(
+    gtfs_trips: &[Trip],
+    map_line_routes: &MapLineRoutes,
+    modes: &ModeMapping,
+) -> Vec<objects::Line> {
     let mut lines = vec![];
 
     let line_code = |r: &Route| {
@@ -857,8 +1188,8 @@ fn make_lines(gtfs_trips: &[Trip], map_line_routes: &MapLineRoutes) -> Vec<objec
                 color: r.color.clone(),
                 text_color: r.text_color.clone(),
                 sort_order: r.sort_order,
-                network_id: line_agency(r),
-                commercial_mode_id: r.route_type.to_gtfs_value(),
+                network_id: line_agency(r).into(),
+                commercial_mode_id: modes.commercial_mode(&r.route_type).id,
                 geometry_id: None,
                 opening_time: None,
                 closing_time: None,
@@ -869,7 +1200,11 @@ fn make_lines(gtfs_trips: &[Trip], map_line_routes: &MapLineRoutes) -> Vec<objec
     lines
 }
 
-fn make_routes(gtfs_trips: &[Trip], map_line_routes: &MapLineRoutes) -> Vec<objects::Route> {
+fn make_routes(
+    gtfs_trips: &[Trip],
+    map_line_routes: &MapLineRoutes,
+    issues: &mut Issues,
+) -> Vec<objects::Route> {
     let mut routes = vec![];
 
     let get_direction_name = |d: &DirectionType| match *d {
@@ -885,7 +1220,10 @@ fn make_routes(gtfs_trips: &[Trip], map_line_routes: &MapLineRoutes) -> Vec<obje
                 route_directions.insert(&t.direction);
             }
             if route_directions.is_empty() {
-                warn!("Coudn't find trips for route_id {}", r.id);
+                issues.warn(
+                    "routes.txt",
+                    format!("Coudn't find trips for route_id {}", r.id),
+                );
             }
 
             for d in route_directions {
@@ -896,9 +1234,11 @@ fn make_routes(gtfs_trips: &[Trip], map_line_routes: &MapLineRoutes) -> Vec<obje
                     codes: KeysValues::default(),
                     object_properties: KeysValues::default(),
                     comment_links: CommentLinksT::default(),
-                    line_id: sr.id.clone(),
+                    line_id: sr.id.clone().into(),
                     geometry_id: None,
                     destination_id: None,
+                    continuous_pickup: r.continuous_pickup,
+                    continuous_drop_off: r.continuous_drop_off,
                 });
             }
         }
@@ -921,6 +1261,7 @@ fn make_ntfs_vehicle_journeys(
     gtfs_trips: &[Trip],
     routes: &CollectionWithId<Route>,
     datasets: &CollectionWithId<objects::Dataset>,
+    modes: &ModeMapping,
 ) -> Result<(Vec<objects::VehicleJourney>, Vec<objects::TripProperty>)> {
     // there always is one dataset from config or a default one
     let (_, dataset) = datasets.iter().next().unwrap();
@@ -956,49 +1297,308 @@ fn make_ntfs_vehicle_journeys(
             id_incr += 1;
         }
         for t in trips {
-            vehicle_journeys.push(t.to_ntfs_vehicle_journey(routes, dataset, &property_id));
+            vehicle_journeys.push(t.to_ntfs_vehicle_journey(routes, dataset, &property_id, modes));
         }
     }
 
     Ok((vehicle_journeys, trip_properties))
 }
 
-pub fn read_routes<P: AsRef<path::Path>>(path: P, collections: &mut Collections) -> Result<()> {
+pub fn read_routes<H>(
+    file_handler: &mut H,
+    collections: &mut Collections,
+    modes: &ModeMapping,
+    issues: &mut Issues,
+) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
     info!("Reading routes.txt");
-    let path = path.as_ref();
-    let routes_path = path.join("routes.txt");
-    let mut rdr = csv::Reader::from_path(&routes_path).with_context(ctx_from_path!(routes_path))?;
-    let gtfs_routes: Vec<Route> = rdr
-        .deserialize()
-        .collect::<StdResult<_, _>>()
-        .with_context(ctx_from_path!(routes_path))?;
+    let gtfs_routes: Vec<Route> = {
+        let (rdr, routes_path) = csv_reader(&mut *file_handler, "routes.txt")?;
+        read_rows(rdr, &routes_path, "routes.txt", Issue::InvalidRow, issues)?
+            .into_iter()
+            .map(|(_, route)| route)
+            .collect()
+    };
 
     let gtfs_routes_collection = CollectionWithId::new(gtfs_routes)?;
 
-    let (commercial_modes, physical_modes) = get_modes_from_gtfs(&gtfs_routes_collection);
+    let (commercial_modes, physical_modes) = get_modes_from_gtfs(&gtfs_routes_collection, modes);
     collections.commercial_modes = CollectionWithId::new(commercial_modes)?;
     collections.physical_modes = CollectionWithId::new(physical_modes)?;
 
-    let trips_path = path.join("trips.txt");
-    let mut rdr = csv::Reader::from_path(&trips_path).with_context(ctx_from_path!(trips_path))?;
-    let gtfs_trips: Vec<Trip> = rdr
-        .deserialize()
-        .collect::<StdResult<_, _>>()
-        .with_context(ctx_from_path!(trips_path))?;
+    let file = "trips.txt";
+    let (rdr, trips_path) = csv_reader(file_handler, file)?;
+    let mut gtfs_trips: Vec<Trip> = vec![];
+    for (line, trip) in read_rows::<Trip, _>(rdr, &trips_path, file, Issue::InvalidRow, issues)? {
+        skip_fail!(
+            gtfs_routes_collection.get_idx(&trip.route_id).ok_or_else(|| format_err!(
+                "Problem reading {:?}: route_id={:?} not found",
+                trips_path,
+                trip.route_id
+            )),
+            issues,
+            Issue::UnknownReference,
+            file,
+            line
+        );
+        gtfs_trips.push(trip);
+    }
 
     let map_line_routes = map_line_routes(&gtfs_routes_collection);
-    let lines = make_lines(&gtfs_trips, &map_line_routes);
+    let lines = make_lines(&gtfs_trips, &map_line_routes, modes);
     collections.lines = CollectionWithId::new(lines)?;
 
-    let routes = make_routes(&gtfs_trips, &map_line_routes);
+    let routes = make_routes(&gtfs_trips, &map_line_routes, issues);
     collections.routes = CollectionWithId::new(routes)?;
 
-    let (vehicle_journeys, trip_properties) =
-        make_ntfs_vehicle_journeys(&gtfs_trips, &gtfs_routes_collection, &collections.datasets)
-            .with_context(ctx_from_path!(trips_path))?;
+    let (vehicle_journeys, trip_properties) = make_ntfs_vehicle_journeys(
+        &gtfs_trips,
+        &gtfs_routes_collection,
+        &collections.datasets,
+        modes,
+    ).with_context(ctx_from_path!(trips_path))?;
     collections.vehicle_journeys = CollectionWithId::new(vehicle_journeys)?;
     collections.trip_properties = CollectionWithId::new(trip_properties)?;
 
+    let mut route_ids = BTreeSet::new();
+    for trip in &gtfs_trips {
+        let route = match gtfs_routes_collection.get(&trip.route_id) {
+            Some(route) => route,
+            None => continue,
+        };
+        let route_id = route.get_id_by_direction(&trip.direction);
+        if route_id != route.id && route_ids.insert(route_id.clone()) {
+            collections
+                .id_mappings
+                .push(objects::IdMapping::new("routes", &route.id, &route_id));
+        }
+    }
+    for vj in collections.vehicle_journeys.values() {
+        if let Some(ref trip_property_id) = vj.trip_property_id {
+            collections.id_mappings.push(objects::IdMapping::new(
+                "trip_properties",
+                &vj.id,
+                trip_property_id,
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// The identifier found by `find` for the optional reference `id` of
+// the `name` field.
+fn resolve<F, I>(id: &Option<String>, name: &str, path: &path::Path, find: F) -> Result<Option<I>>
+where
+    F: Fn(&str) -> Option<I>,
+{
+    match *id {
+        Some(ref id) => find(id).map(Some).ok_or_else(|| {
+            format_err!("Problem reading {:?}: {}={:?} not found", path, name, id)
+        }),
+        None => Ok(None),
+    }
+}
+
+/// Reads `attributions.txt` into the attributions of the dataset, the
+/// attributions of an agency, a route or a trip being given to the
+/// corresponding network, line or vehicle journey.
+pub fn read_attributions<H>(
+    file_handler: &mut H,
+    collections: &mut Collections,
+    issues: &mut Issues,
+) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let file = "attributions.txt";
+    let (rdr, path) = match opt_csv_reader(file_handler, file)? {
+        (Some(rdr), path) => (rdr, path),
+        (None, _) => {
+            info!("Skipping {}", file);
+            return Ok(());
+        }
+    };
+    info!("Reading {}", file);
+    // there always is one dataset from config or a default one
+    let dataset_id = collections.datasets.values().next().unwrap().id.clone();
+    let mut attributions = vec![];
+    let rows =
+        read_rows::<Attribution, _>(rdr, &path, file, Issue::InvalidOptionalElement, issues)?;
+    for (line, attribution) in rows {
+        let network_id = skip_fail!(
+            resolve(&attribution.agency_id, "agency_id", &path, |id| collections
+                .networks
+                .get(id)
+                .map(|n| n.id.clone().into())),
+            issues,
+            Issue::InvalidOptionalElement,
+            file,
+            line
+        );
+        // The routes of a trip are read with its direction.
+        let line_id = skip_fail!(
+            resolve(&attribution.route_id, "route_id", &path, |id| collections
+                .routes
+                .get(id)
+                .or_else(|| collections.routes.get(&format!("{}_R", id)))
+                .map(|r| r.line_id.clone())),
+            issues,
+            Issue::InvalidOptionalElement,
+            file,
+            line
+        );
+        let vehicle_journey_id = skip_fail!(
+            resolve(&attribution.trip_id, "trip_id", &path, |id| collections
+                .vehicle_journeys
+                .get(id)
+                .map(|vj| vj.id.clone())),
+            issues,
+            Issue::InvalidOptionalElement,
+            file,
+            line
+        );
+        attributions.push(objects::Attribution {
+            id: attribution.attribution_id,
+            dataset_id: dataset_id.clone(),
+            network_id,
+            line_id,
+            vehicle_journey_id,
+            organization_name: attribution.organization_name,
+            is_producer: attribution.is_producer,
+            is_operator: attribution.is_operator,
+            is_authority: attribution.is_authority,
+            url: attribution.attribution_url,
+            email: attribution.attribution_email,
+            phone: attribution.attribution_phone,
+        });
+    }
+    collections.attributions = Collection::new(attributions);
+    Ok(())
+}
+
+// The objects having the `field` of the model, with its value.
+fn translatable_values<'a>(
+    c: &'a Collections,
+    field: &str,
+) -> Vec<(ObjectType, &'a str, Option<&'a str>)> {
+    match field {
+        "network_name" => c
+            .networks
+            .values()
+            .map(|n| (ObjectType::Network, n.id.as_str(), Some(n.name.as_str())))
+            .collect(),
+        "network_url" => c
+            .networks
+            .values()
+            .map(|n| (ObjectType::Network, n.id.as_str(), n.url.as_deref()))
+            .collect(),
+        "stop_name" => c
+            .stop_points
+            .values()
+            .map(|sp| (ObjectType::StopPoint, sp.id.as_str(), Some(sp.name.as_str())))
+            .chain(
+                c.stop_areas
+                    .values()
+                    .map(|sa| (ObjectType::StopArea, sa.id.as_str(), Some(sa.name.as_str()))),
+            )
+            .collect(),
+        "line_code" => c
+            .lines
+            .values()
+            .map(|l| (ObjectType::Line, l.id.as_str(), l.code.as_deref()))
+            .collect(),
+        "line_name" => c
+            .lines
+            .values()
+            .map(|l| (ObjectType::Line, l.id.as_str(), Some(l.name.as_str())))
+            .collect(),
+        "trip_headsign" => c
+            .vehicle_journeys
+            .values()
+            .map(|vj| (ObjectType::VehicleJourney, vj.id.as_str(), vj.headsign.as_deref()))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Reads `translations.txt` into the translations of the objects.  A
+/// translation is given to the object of its `record_id`, or to all
+/// the objects of its table whose field is `field_value`.  The
+/// translations of the fields the model doesn't have are skipped.
+pub fn read_translations<H>(
+    file_handler: &mut H,
+    collections: &mut Collections,
+    issues: &mut Issues,
+) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let file = "translations.txt";
+    let (rdr, path) = match opt_csv_reader(file_handler, file)? {
+        (Some(rdr), path) => (rdr, path),
+        (None, _) => {
+            info!("Skipping {}", file);
+            return Ok(());
+        }
+    };
+    info!("Reading {}", file);
+    let mut translations = vec![];
+    let rows =
+        read_rows::<Translation, _>(rdr, &path, file, Issue::InvalidOptionalElement, issues)?;
+    for (line, translation) in rows {
+        let field_name = skip_fail!(
+            TRANSLATED_FIELDS
+                .iter()
+                .find(|&&(table, field, _)| {
+                    table == translation.table_name && field == translation.field_name
+                })
+                .map(|&(_, _, field_name)| field_name)
+                .ok_or_else(|| format_err!(
+                    "translation of {}.{} not supported",
+                    translation.table_name,
+                    translation.field_name
+                )),
+            issues,
+            Issue::Unsupported,
+            file,
+            line
+        );
+        let objects: Vec<_> = translatable_values(collections, field_name)
+            .into_iter()
+            .filter(
+                |&(_, id, value)| match (&translation.record_id, &translation.field_value) {
+                    (Some(record_id), _) => id == record_id,
+                    (None, Some(field_value)) => value == Some(field_value.as_str()),
+                    (None, None) => false,
+                },
+            )
+            .collect();
+        if objects.is_empty() {
+            issues.handle_at(
+                Issue::InvalidOptionalElement,
+                file,
+                line,
+                format!(
+                    "Problem reading {:?}: no object for the translation of {}.{}",
+                    path, translation.table_name, translation.field_name
+                ),
+            )?;
+            continue;
+        }
+        for (object_type, object_id, _) in objects {
+            translations.push(objects::Translation {
+                object_type,
+                object_id: object_id.to_string(),
+                field_name: field_name.to_string(),
+                language: translation.language.clone(),
+                translation: translation.translation.clone(),
+            });
+        }
+    }
+    collections.translations = Collection::new(translations);
     Ok(())
 }
 
@@ -1029,13 +1629,16 @@ mod tests {
     use collection::{Collection, CollectionWithId, Id};
     use common_format;
     use geo_types::{Geometry as GeoGeometry, LineString, Point};
-    use gtfs::add_prefix;
-    use gtfs::read::EquipmentList;
+    use gtfs::{self, add_prefix, MINIMAL_GTFS};
+    use gtfs::read::{EquipmentList, ModeMapping};
     use model::Collections;
     use objects::*;
-    use std::collections::BTreeSet;
+    use read_utils::{self, MemoryFileHandler, PathFileHandler};
+    use report::{Issue, IssueHandling, Issues, ParseProfile};
+    use std::collections::{BTreeSet, HashMap};
     use std::fs::File;
     use std::io::prelude::*;
+    use utils;
 
     fn create_file_with_content(temp_dir: &TempDir, file_name: &str, content: &str) {
         let file_path = temp_dir.path().join(file_name);
@@ -1069,7 +1672,8 @@ mod tests {
 
         test_in_tmp_dir(|ref tmp_dir| {
             create_file_with_content(&tmp_dir, "agency.txt", agency_content);
-            let (networks, companies) = super::read_agency(tmp_dir.path()).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            let (networks, companies) = super::read_agency(&mut handler).unwrap();
             assert_eq!(1, networks.len());
             let agency = networks.iter().next().unwrap().1;
             assert_eq!("default_agency_id", agency.id);
@@ -1084,7 +1688,8 @@ mod tests {
 
         test_in_tmp_dir(|ref tmp_dir| {
             create_file_with_content(&tmp_dir, "agency.txt", agency_content);
-            let (networks, companies) = super::read_agency(tmp_dir.path()).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            let (networks, companies) = super::read_agency(&mut handler).unwrap();
             assert_eq!(1, networks.len());
             assert_eq!(1, companies.len());
         });
@@ -1100,7 +1705,8 @@ mod tests {
 
         test_in_tmp_dir(|ref tmp_dir| {
             create_file_with_content(&tmp_dir, "agency.txt", agency_content);
-            let (networks, companies) = super::read_agency(tmp_dir.path()).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            let (networks, companies) = super::read_agency(&mut handler).unwrap();
             assert_eq!(1, networks.len());
             let network = networks.iter().next().unwrap().1;
             assert_eq!("id_1", network.id);
@@ -1117,7 +1723,8 @@ mod tests {
 
         test_in_tmp_dir(|ref tmp_dir| {
             create_file_with_content(&tmp_dir, "agency.txt", agency_content);
-            super::read_agency(tmp_dir.path()).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            super::read_agency(&mut handler).unwrap();
         });
     }
 
@@ -1130,8 +1737,14 @@ mod tests {
             create_file_with_content(&tmp_dir, "stops.txt", stops_content);
             let mut equipments = EquipmentList::default();
             let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
-            let (stop_areas, stop_points) =
-                super::read_stops(tmp_dir.path(), &mut comments, &mut equipments).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            let (stop_areas, stop_points, _) =
+                super::read_stops(
+                    &mut handler,
+                    &mut comments,
+                    &mut equipments,
+                    &mut Issues::default(),
+                ).unwrap();
             assert_eq!(1, stop_areas.len());
             assert_eq!(1, stop_points.len());
             let stop_area = stop_areas.iter().next().unwrap().1;
@@ -1143,6 +1756,33 @@ mod tests {
         });
     }
 
+    #[test]
+    fn read_stop_entrances() {
+        let stops_content = "stop_id,stop_name,stop_lat,stop_lon,location_type,parent_station\n\
+                             sa1,my stop area,0.1,1.2,1,\n\
+                             e1,my entrance,0.1,1.2,2,sa1\n\
+                             e2,my orphan entrance,0.1,1.2,2,";
+
+        test_in_tmp_dir(|ref tmp_dir| {
+            create_file_with_content(&tmp_dir, "stops.txt", stops_content);
+            let mut equipments = EquipmentList::default();
+            let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
+            let mut issues = Issues::new(ParseProfile::Permissive);
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            let (stop_areas, stop_points, entrances) =
+                super::read_stops(&mut handler, &mut comments, &mut equipments, &mut issues)
+                    .unwrap();
+            assert_eq!(1, stop_areas.len());
+            assert_eq!(0, stop_points.len());
+            assert_eq!(vec!["e1"], extract_ids(&entrances));
+            assert_eq!("sa1", entrances.get("e1").unwrap().stop_area_id);
+            let warnings = issues.into_warnings();
+            assert_eq!(1, warnings.len());
+            assert_eq!("stops.txt", warnings[0].file);
+            assert!(warnings[0].message.contains("e2"));
+        });
+    }
+
     #[test]
     fn stop_code_on_stops() {
         let stops_content =
@@ -1154,8 +1794,14 @@ mod tests {
             create_file_with_content(&tmp_dir, "stops.txt", stops_content);
             let mut equipments = EquipmentList::default();
             let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
-            let (stop_areas, stop_points) =
-                super::read_stops(tmp_dir.path(), &mut comments, &mut equipments).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            let (stop_areas, stop_points, _) =
+                super::read_stops(
+                    &mut handler,
+                    &mut comments,
+                    &mut equipments,
+                    &mut Issues::default(),
+                ).unwrap();
             //validate stop_point code
             assert_eq!(1, stop_points.len());
             let stop_point = stop_points.iter().next().unwrap().1;
@@ -1184,8 +1830,14 @@ mod tests {
             create_file_with_content(&tmp_dir, "stops.txt", stops_content);
             let mut equipments = EquipmentList::default();
             let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
-            let (stop_areas, _) =
-                super::read_stops(tmp_dir.path(), &mut comments, &mut equipments).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            let (stop_areas, _, _) =
+                super::read_stops(
+                    &mut handler,
+                    &mut comments,
+                    &mut equipments,
+                    &mut Issues::default(),
+                ).unwrap();
             //validate stop_area code
             assert_eq!(1, stop_areas.len());
             let stop_area = stop_areas.iter().next().unwrap().1;
@@ -1213,10 +1865,16 @@ mod tests {
             create_file_with_content(&tmp_dir, "routes.txt", routes_content);
             create_file_with_content(&tmp_dir, "trips.txt", trips_content);
             let mut collections = Collections::default();
-            let (contributors, datasets) = super::read_config(None::<&str>).unwrap();
+            let (contributors, datasets, _) = super::read_config(None::<&str>).unwrap();
             collections.contributors = contributors;
             collections.datasets = datasets;
-            super::read_routes(tmp_dir, &mut collections).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            super::read_routes(
+                &mut handler,
+                &mut collections,
+                &ModeMapping::default(),
+                &mut Issues::default(),
+            ).unwrap();
             assert_eq!(4, collections.lines.len());
             assert_eq!(2, collections.commercial_modes.len());
 
@@ -1249,6 +1907,65 @@ mod tests {
         });
     }
 
+    #[test]
+    fn gtfs_routes_with_config_modes() {
+        let routes_content = "route_id,agency_id,route_short_name,route_long_name,route_type\n\
+                              route_1,agency_1,1,My line 1,3\n\
+                              route_2,agency_1,2,My line 2,7";
+        let trips_content = "trip_id,route_id,service_id\n\
+                             1,route_1,service_1\n\
+                             2,route_2,service_1";
+        let config_content = r#"{
+            "contributor": {"contributor_id": "c", "contributor_name": "C"},
+            "dataset": {"dataset_id": "d"},
+            "modes": [{
+                "route_type": "7",
+                "physical_mode_id": "CableCar",
+                "physical_mode_name": "Cable car",
+                "commercial_mode_id": "CableCar",
+                "co2_emission": 2.5
+            }]
+        }"#;
+
+        test_in_tmp_dir(|tmp_dir| {
+            create_file_with_content(tmp_dir, "routes.txt", routes_content);
+            create_file_with_content(tmp_dir, "trips.txt", trips_content);
+            create_file_with_content(tmp_dir, "config.json", config_content);
+            let mut collections = Collections::default();
+            let (contributors, datasets, modes) =
+                super::read_config(Some(tmp_dir.path().join("config.json"))).unwrap();
+            collections.contributors = contributors;
+            collections.datasets = datasets;
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            super::read_routes(
+                &mut handler,
+                &mut collections,
+                &modes,
+                &mut Issues::default(),
+            ).unwrap();
+
+            assert_eq!(
+                extract_ids(&collections.physical_modes),
+                &["Bus", "CableCar"]
+            );
+            let cable_car = collections.physical_modes.get("CableCar").unwrap();
+            assert_eq!("Cable car", cable_car.name);
+            assert_eq!(Some(2.5), cable_car.co2_emission);
+            assert_eq!(
+                extract_ids(&collections.commercial_modes),
+                &["3", "CableCar"]
+            );
+            assert_eq!(
+                "CableCar",
+                collections.lines.get("route_2").unwrap().commercial_mode_id
+            );
+            assert_eq!(
+                "CableCar",
+                collections.vehicle_journeys.get("2").unwrap().physical_mode_id
+            );
+        });
+    }
+
     #[test]
     fn gtfs_routes_as_route() {
         let routes_content = "route_id,agency_id,route_short_name,route_long_name,route_type,route_color,route_text_color\n\
@@ -1270,10 +1987,16 @@ mod tests {
             create_file_with_content(&tmp_dir, "routes.txt", routes_content);
             create_file_with_content(&tmp_dir, "trips.txt", trips_content);
             let mut collections = Collections::default();
-            let (contributors, datasets) = super::read_config(None::<&str>).unwrap();
+            let (contributors, datasets, _) = super::read_config(None::<&str>).unwrap();
             collections.contributors = contributors;
             collections.datasets = datasets;
-            super::read_routes(tmp_dir, &mut collections).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            super::read_routes(
+                &mut handler,
+                &mut collections,
+                &ModeMapping::default(),
+                &mut Issues::default(),
+            ).unwrap();
 
             assert_eq!(3, collections.lines.len());
             assert_eq!(
@@ -1308,10 +2031,16 @@ mod tests {
             create_file_with_content(&tmp_dir, "routes.txt", routes_content);
             create_file_with_content(&tmp_dir, "trips.txt", trips_content);
             let mut collections = Collections::default();
-            let (contributors, datasets) = super::read_config(None::<&str>).unwrap();
+            let (contributors, datasets, _) = super::read_config(None::<&str>).unwrap();
             collections.contributors = contributors;
             collections.datasets = datasets;
-            super::read_routes(tmp_dir, &mut collections).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            super::read_routes(
+                &mut handler,
+                &mut collections,
+                &ModeMapping::default(),
+                &mut Issues::default(),
+            ).unwrap();
 
             assert_eq!(2, collections.lines.len());
 
@@ -1340,10 +2069,16 @@ mod tests {
             create_file_with_content(&tmp_dir, "routes.txt", routes_content);
             create_file_with_content(&tmp_dir, "trips.txt", trips_content);
             let mut collections = Collections::default();
-            let (contributors, datasets) = super::read_config(None::<&str>).unwrap();
+            let (contributors, datasets, _) = super::read_config(None::<&str>).unwrap();
             collections.contributors = contributors;
             collections.datasets = datasets;
-            super::read_routes(tmp_dir, &mut collections).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            super::read_routes(
+                &mut handler,
+                &mut collections,
+                &ModeMapping::default(),
+                &mut Issues::default(),
+            ).unwrap();
 
             assert_eq!(2, collections.lines.len());
             assert_eq!(extract_ids(&collections.lines), &["route_1", "route_3"]);
@@ -1373,10 +2108,16 @@ mod tests {
             create_file_with_content(&tmp_dir, "trips.txt", trips_content);
 
             let mut collections = Collections::default();
-            let (contributors, datasets) = super::read_config(None::<&str>).unwrap();
+            let (contributors, datasets, _) = super::read_config(None::<&str>).unwrap();
             collections.contributors = contributors;
             collections.datasets = datasets;
-            super::read_routes(tmp_dir, &mut collections).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            super::read_routes(
+                &mut handler,
+                &mut collections,
+                &ModeMapping::default(),
+                &mut Issues::default(),
+            ).unwrap();
             assert_eq!(1, collections.lines.len());
             assert_eq!(1, collections.routes.len());
         });
@@ -1412,19 +2153,31 @@ mod tests {
 
             let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
             let mut equipments = EquipmentList::default();
-            let (contributors, datasets) = super::read_config(None::<&str>).unwrap();
+            let (contributors, datasets, _) = super::read_config(None::<&str>).unwrap();
             collections.contributors = contributors;
             collections.datasets = datasets;
-            let (stop_areas, stop_points) =
-                super::read_stops(tmp_dir.path(), &mut comments, &mut equipments).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            let (stop_areas, stop_points, _) =
+                super::read_stops(
+                    &mut handler,
+                    &mut comments,
+                    &mut equipments,
+                    &mut Issues::default(),
+                ).unwrap();
             collections.stop_areas = stop_areas;
             collections.stop_points = stop_points;
-            let (networks, companies) = super::read_agency(tmp_dir.path()).unwrap();
+            let (networks, companies) = super::read_agency(&mut handler).unwrap();
             collections.networks = networks;
             collections.companies = companies;
             collections.comments = comments;
-            super::read_routes(tmp_dir, &mut collections).unwrap();
-
+            super::read_routes(
+                &mut handler,
+                &mut collections,
+                &ModeMapping::default(),
+                &mut Issues::default(),
+            ).unwrap();
+
+            read_utils::add_id_mappings(&mut collections);
             add_prefix("my_prefix".to_string(), &mut collections).unwrap();
 
             assert_eq!(
@@ -1463,6 +2216,21 @@ mod tests {
                 vec!["my_prefix:1"],
                 extract_ids(&collections.trip_properties)
             );
+            assert_eq!(
+                vec![
+                    ("routes", "route_2", "my_prefix:route_2_R"),
+                    ("stop_areas", "sp:01", "my_prefix:Navitia:sp:01"),
+                    ("trip_properties", "2", "my_prefix:1"),
+                ],
+                extract(
+                    |m| (
+                        m.collection.as_str(),
+                        m.source_id.as_str(),
+                        m.generated_id.as_str()
+                    ),
+                    &collections.id_mappings,
+                )
+            );
 
             let comment_vec = collections.comments.into_vec();
 
@@ -1490,11 +2258,17 @@ mod tests {
             create_file_with_content(&tmp_dir, "trips.txt", trips_content);
 
             let mut collections = Collections::default();
-            let (contributors, datasets) = super::read_config(None::<&str>).unwrap();
+            let (contributors, datasets, _) = super::read_config(None::<&str>).unwrap();
             collections.contributors = contributors;
             collections.datasets = datasets;
 
-            super::read_routes(tmp_dir, &mut collections).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            super::read_routes(
+                &mut handler,
+                &mut collections,
+                &ModeMapping::default(),
+                &mut Issues::default(),
+            ).unwrap();
             assert_eq!(3, collections.lines.len());
             assert_eq!(3, collections.routes.len());
             assert_eq!(3, collections.vehicle_journeys.len());
@@ -1518,11 +2292,17 @@ mod tests {
             create_file_with_content(&tmp_dir, "trips.txt", trips_content);
 
             let mut collections = Collections::default();
-            let (contributors, datasets) = super::read_config(None::<&str>).unwrap();
+            let (contributors, datasets, _) = super::read_config(None::<&str>).unwrap();
             collections.contributors = contributors;
             collections.datasets = datasets;
 
-            super::read_routes(tmp_dir, &mut collections).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            super::read_routes(
+                &mut handler,
+                &mut collections,
+                &ModeMapping::default(),
+                &mut Issues::default(),
+            ).unwrap();
             assert_eq!(3, collections.lines.len());
             assert_eq!(3, collections.routes.len());
 
@@ -1551,11 +2331,17 @@ mod tests {
             create_file_with_content(&tmp_dir, "trips.txt", trips_content);
 
             let mut collections = Collections::default();
-            let (contributors, datasets) = super::read_config(None::<&str>).unwrap();
+            let (contributors, datasets, _) = super::read_config(None::<&str>).unwrap();
             collections.contributors = contributors;
             collections.datasets = datasets;
 
-            super::read_routes(tmp_dir, &mut collections).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            super::read_routes(
+                &mut handler,
+                &mut collections,
+                &ModeMapping::default(),
+                &mut Issues::default(),
+            ).unwrap();
             assert_eq!(2, collections.vehicle_journeys.len());
             assert_eq!(0, collections.trip_properties.len());
             for vj in collections.vehicle_journeys.values() {
@@ -1599,8 +2385,14 @@ mod tests {
 
             let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
             let mut equipments = EquipmentList::default();
-            let (stop_areas, stop_points) =
-                super::read_stops(tmp_dir.path(), &mut comments, &mut equipments).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            let (stop_areas, stop_points, _) =
+                super::read_stops(
+                    &mut handler,
+                    &mut comments,
+                    &mut equipments,
+                    &mut Issues::default(),
+                ).unwrap();
             let equipments_collection =
                 CollectionWithId::new(equipments.into_equipments()).unwrap();
             assert_eq!(2, stop_areas.len());
@@ -1663,8 +2455,14 @@ mod tests {
 
             let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
             let mut equipments = EquipmentList::default();
-            let (_, stop_points) =
-                super::read_stops(tmp_dir.path(), &mut comments, &mut equipments).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            let (_, stop_points, _) =
+                super::read_stops(
+                    &mut handler,
+                    &mut comments,
+                    &mut equipments,
+                    &mut Issues::default(),
+                ).unwrap();
             let equipments_collection =
                 CollectionWithId::new(equipments.into_equipments()).unwrap();
             assert_eq!(2, stop_points.len());
@@ -1724,18 +2522,30 @@ mod tests {
             create_file_with_content(&tmp_dir, "stops.txt", stops_content);
 
             let mut collections = Collections::default();
-            let (contributors, datasets) = super::read_config(None::<&str>).unwrap();
+            let (contributors, datasets, _) = super::read_config(None::<&str>).unwrap();
             collections.contributors = contributors;
             collections.datasets = datasets;
 
             let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
             let mut equipments = EquipmentList::default();
-            let (_, stop_points) =
-                super::read_stops(&tmp_dir, &mut comments, &mut equipments).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            let (_, stop_points, _) =
+                super::read_stops(
+                    &mut handler,
+                    &mut comments,
+                    &mut equipments,
+                    &mut Issues::default(),
+                ).unwrap();
             collections.stop_points = stop_points;
 
-            super::read_routes(&tmp_dir, &mut collections).unwrap();
-            super::manage_stop_times(&mut collections, &tmp_dir).unwrap();
+            super::read_routes(
+                &mut handler,
+                &mut collections,
+                &ModeMapping::default(),
+                &mut Issues::default(),
+            ).unwrap();
+            super::manage_stop_times(&mut collections, &mut handler, &mut Issues::default())
+                .unwrap();
 
             assert_eq!(
                 collections.vehicle_journeys.into_vec()[0].stop_times,
@@ -1751,6 +2561,7 @@ mod tests {
                         drop_off_type: 0,
                         datetime_estimated: false,
                         local_zone_id: None,
+                        extras: None,
                     },
                     StopTime {
                         stop_point_idx: collections.stop_points.get_idx("sp:02").unwrap(),
@@ -1763,12 +2574,157 @@ mod tests {
                         drop_off_type: 1,
                         datetime_estimated: false,
                         local_zone_id: None,
+                        extras: None,
                     },
                 ]
             );
         });
     }
 
+    #[test]
+    fn gtfs_interleaved_stop_times() {
+        let routes_content = "route_id,agency_id,route_short_name,route_long_name,route_type\n\
+                              route_1,agency_1,1,My line 1,3";
+        let stops_content = "stop_id,stop_name,stop_lat,stop_lon,location_type,parent_station\n\
+                             sp:01,my stop point name 1,0.1,1.2,0,\n\
+                             sp:02,my stop point name 2,0.2,1.5,0,";
+        let trips_content = "trip_id,route_id,service_id\n\
+                             1,route_1,service_1\n\
+                             2,route_1,service_1\n\
+                             3,route_1,service_1";
+        let stop_times_content = "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+                                  2,07:00:00,07:00:00,sp:01,1\n\
+                                  1,06:10:00,06:10:00,sp:02,2\n\
+                                  1,06:00:00,06:00:00,sp:01,1\n\
+                                  unknown,06:00:00,06:00:00,sp:01,1\n\
+                                  2,07:10:00,07:10:00,sp:02,2";
+
+        test_in_tmp_dir(|tmp_dir| {
+            create_file_with_content(tmp_dir, "routes.txt", routes_content);
+            create_file_with_content(tmp_dir, "trips.txt", trips_content);
+            create_file_with_content(tmp_dir, "stop_times.txt", stop_times_content);
+            create_file_with_content(tmp_dir, "stops.txt", stops_content);
+
+            let mut collections = Collections::default();
+            let (contributors, datasets, _) = super::read_config(None::<&str>).unwrap();
+            collections.contributors = contributors;
+            collections.datasets = datasets;
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            let (_, stop_points, _) = super::read_stops(
+                &mut handler,
+                &mut CollectionWithId::default(),
+                &mut EquipmentList::default(),
+                &mut Issues::default(),
+            ).unwrap();
+            collections.stop_points = stop_points;
+            super::read_routes(
+                &mut handler,
+                &mut collections,
+                &ModeMapping::default(),
+                &mut Issues::default(),
+            ).unwrap();
+            let mut issues = Issues::new(ParseProfile::Permissive);
+            super::manage_stop_times(&mut collections, &mut handler, &mut issues).unwrap();
+
+            assert_eq!(1, issues.into_warnings().len());
+            let sequences = |id: &str| -> Vec<(u32, Time)> {
+                collections
+                    .vehicle_journeys
+                    .get(id)
+                    .unwrap()
+                    .stop_times
+                    .iter()
+                    .map(|st| (st.sequence, st.arrival_time))
+                    .collect()
+            };
+            assert_eq!(
+                vec![(1, Time::new(6, 0, 0)), (2, Time::new(6, 10, 0))],
+                sequences("1")
+            );
+            assert_eq!(
+                vec![(1, Time::new(7, 0, 0)), (2, Time::new(7, 10, 0))],
+                sequences("2")
+            );
+            assert!(sequences("3").is_empty());
+        });
+    }
+
+    #[test]
+    fn gtfs_interpolated_stop_times() {
+        let routes_content = "route_id,agency_id,route_short_name,route_long_name,route_type\n\
+                              route_1,agency_1,1,My line 1,3";
+        let stops_content = "stop_id,stop_name,stop_lat,stop_lon,location_type,parent_station\n\
+                             sp:01,my stop point name 1,0.1,1.2,0,\n\
+                             sp:02,my stop point name 2,0.2,1.5,0,";
+        let trips_content = "trip_id,route_id,service_id\n\
+                             1,route_1,service_1\n\
+                             2,route_1,service_1\n\
+                             3,route_1,service_1";
+        let stop_times_content =
+            "trip_id,arrival_time,departure_time,stop_id,stop_sequence,shape_dist_traveled\n\
+             1,06:00:00,06:00:00,sp:01,1,\n\
+             1,,,sp:02,2,\n\
+             1,,,sp:01,3,\n\
+             1,06:30:00,06:30:00,sp:02,4,\n\
+             2,07:00:00,07:00:00,sp:01,1,0\n\
+             2,,,sp:02,2,1\n\
+             2,07:40:00,07:40:00,sp:01,3,4\n\
+             3,08:00:00,08:00:00,sp:01,1,\n\
+             3,08:10:00,08:10:00,sp:02,2,\n\
+             3,,,sp:01,3,";
+
+        test_in_tmp_dir(|tmp_dir| {
+            create_file_with_content(tmp_dir, "routes.txt", routes_content);
+            create_file_with_content(tmp_dir, "trips.txt", trips_content);
+            create_file_with_content(tmp_dir, "stop_times.txt", stop_times_content);
+            create_file_with_content(tmp_dir, "stops.txt", stops_content);
+
+            let mut collections = Collections::default();
+            let (contributors, datasets, _) = super::read_config(None::<&str>).unwrap();
+            collections.contributors = contributors;
+            collections.datasets = datasets;
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            let (_, stop_points, _) = super::read_stops(
+                &mut handler,
+                &mut CollectionWithId::default(),
+                &mut EquipmentList::default(),
+                &mut Issues::default(),
+            ).unwrap();
+            collections.stop_points = stop_points;
+            super::read_routes(
+                &mut handler,
+                &mut collections,
+                &ModeMapping::default(),
+                &mut Issues::default(),
+            ).unwrap();
+            let mut issues = Issues::new(ParseProfile::Permissive);
+            super::manage_stop_times(&mut collections, &mut handler, &mut issues).unwrap();
+
+            assert_eq!(1, issues.into_warnings().len());
+            let times = |id: &str| -> Vec<(Time, bool)> {
+                collections
+                    .vehicle_journeys
+                    .get(id)
+                    .unwrap()
+                    .stop_times
+                    .iter()
+                    .map(|st| (st.departure_time, st.datetime_estimated))
+                    .collect()
+            };
+            assert_eq!(
+                vec![
+                    (Time::new(6, 0, 0), false),
+                    (Time::new(6, 10, 0), true),
+                    (Time::new(6, 20, 0), true),
+                    (Time::new(6, 30, 0), false),
+                ],
+                times("1")
+            );
+            assert_eq!((Time::new(7, 10, 0), true), times("2")[1]);
+            assert_eq!(2, times("3").len());
+        });
+    }
+
     #[test]
     fn read_tranfers() {
         let stops_content = "stop_id,stop_name,stop_lat,stop_lon,location_type,parent_station,wheelchair_boarding\n\
@@ -1793,10 +2749,18 @@ mod tests {
 
             let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
             let mut equipments = EquipmentList::default();
-            let (_, stop_points) =
-                super::read_stops(tmp_dir.path(), &mut comments, &mut equipments).unwrap();
-
-            let transfers = super::read_transfers(tmp_dir.path(), &stop_points).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            let (_, stop_points, _) =
+                super::read_stops(
+                    &mut handler,
+                    &mut comments,
+                    &mut equipments,
+                    &mut Issues::default(),
+                ).unwrap();
+
+            let transfers =
+                super::read_transfers(&mut handler, &stop_points, &mut Issues::default())
+                    .unwrap();
             assert_eq!(
                 transfers.values().collect::<Vec<_>>(),
                 vec![
@@ -1868,6 +2832,79 @@ mod tests {
         });
     }
 
+    #[test]
+    fn fix_missing_transfer_time() {
+        let stops_content = "stop_id,stop_name,stop_lat,stop_lon,location_type,parent_station,wheelchair_boarding\n\
+                             sp:01,my stop point name 1,48.857332,2.346331,0,,1\n\
+                             sp:02,my stop point name 2,48.858195,2.347448,0,,1";
+
+        let transfers_content = "from_stop_id,to_stop_id,transfer_type,min_transfer_time\n\
+                                 sp:01,sp:02,2,";
+
+        test_in_tmp_dir(|ref tmp_dir| {
+            create_file_with_content(&tmp_dir, "stops.txt", stops_content);
+            create_file_with_content(&tmp_dir, "transfers.txt", transfers_content);
+
+            let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
+            let mut equipments = EquipmentList::default();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            let (_, stop_points, _) =
+                super::read_stops(
+                    &mut handler,
+                    &mut comments,
+                    &mut equipments,
+                    &mut Issues::default(),
+                ).unwrap();
+
+            let mut handlings = HashMap::new();
+            handlings.insert(Issue::MissingValue, IssueHandling::Fix);
+            let mut issues = Issues::new(ParseProfile::Custom(handlings));
+            let transfers =
+                super::read_transfers(&mut handler, &stop_points, &mut issues).unwrap();
+            let transfer = transfers.values().next().unwrap();
+            assert_eq!(Some(160), transfer.min_transfer_time);
+            assert_eq!(Some(280), transfer.real_min_transfer_time);
+            assert_eq!(1, issues.into_warnings().len());
+
+            let mut issues = Issues::new(ParseProfile::Strict);
+            let transfers =
+                super::read_transfers(&mut handler, &stop_points, &mut issues).unwrap();
+            let transfer = transfers.values().next().unwrap();
+            assert_eq!(None, transfer.min_transfer_time);
+            assert_eq!(None, transfer.real_min_transfer_time);
+        });
+    }
+
+    #[test]
+    fn skip_transfers_between_unknown_stops_by_default() {
+        let stops_content = "stop_id,stop_name,stop_lat,stop_lon,location_type,parent_station\n\
+                             sp:01,my stop point name 1,48.857332,2.346331,0,\n\
+                             sp:02,my stop point name 2,48.858195,2.347448,0,";
+
+        let transfers_content = "from_stop_id,to_stop_id,transfer_type,min_transfer_time\n\
+                                 sp:01,sp:02,2,60\n\
+                                 sp:01,sp:unknown,2,60";
+
+        test_in_tmp_dir(|tmp_dir| {
+            create_file_with_content(tmp_dir, "stops.txt", stops_content);
+            create_file_with_content(tmp_dir, "transfers.txt", transfers_content);
+
+            let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
+            let mut equipments = EquipmentList::default();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            let mut issues = Issues::default();
+            let (_, stop_points, _) =
+                super::read_stops(&mut handler, &mut comments, &mut equipments, &mut issues)
+                    .unwrap();
+            let transfers =
+                super::read_transfers(&mut handler, &stop_points, &mut issues).unwrap();
+            assert_eq!(1, transfers.len());
+            let warnings = issues.into_warnings();
+            assert_eq!(1, warnings.len());
+            assert_eq!(Some(Issue::InvalidOptionalElement), warnings[0].issue);
+        });
+    }
+
     #[test]
     fn gtfs_with_calendars_and_no_calendar_dates() {
         let content = "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
@@ -1878,7 +2915,8 @@ mod tests {
             create_file_with_content(&tmp_dir, "calendar.txt", content);
 
             let mut collections = Collections::default();
-            common_format::manage_calendars(&mut collections, tmp_dir.as_ref()).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            common_format::manage_calendars(&mut collections, &mut handler).unwrap();
 
             let mut dates = BTreeSet::new();
             dates.insert(chrono::NaiveDate::from_ymd(2018, 5, 5));
@@ -1910,7 +2948,8 @@ mod tests {
             create_file_with_content(&tmp_dir, "calendar_dates.txt", content);
 
             let mut collections = Collections::default();
-            common_format::manage_calendars(&mut collections, tmp_dir.as_ref()).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            common_format::manage_calendars(&mut collections, &mut handler).unwrap();
 
             let mut dates = BTreeSet::new();
             dates.insert(chrono::NaiveDate::from_ymd(2018, 2, 12));
@@ -1940,7 +2979,8 @@ mod tests {
             create_file_with_content(&tmp_dir, "calendar_dates.txt", calendar_dates_content);
 
             let mut collections = Collections::default();
-            common_format::manage_calendars(&mut collections, tmp_dir.as_ref()).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            common_format::manage_calendars(&mut collections, &mut handler).unwrap();
 
             let mut dates = BTreeSet::new();
             dates.insert(chrono::NaiveDate::from_ymd(2018, 5, 6));
@@ -1975,9 +3015,10 @@ mod tests {
             create_file_with_content(&tmp_dir, "calendar_dates.txt", calendar_dates_content);
 
             let mut collections = Collections::default();
-            let (_, mut datasets) = super::read_config(None::<&str>).unwrap();
+            let (_, mut datasets, _) = super::read_config(None::<&str>).unwrap();
 
-            common_format::manage_calendars(&mut collections, tmp_dir.as_ref()).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            common_format::manage_calendars(&mut collections, &mut handler).unwrap();
             super::set_dataset_validity_period(&mut datasets, &collections.calendars).unwrap();
 
             assert_eq!(
@@ -2005,9 +3046,10 @@ mod tests {
             create_file_with_content(&tmp_dir, "calendar.txt", calendars_content);
 
             let mut collections = Collections::default();
-            let (_, mut datasets) = super::read_config(None::<&str>).unwrap();
+            let (_, mut datasets, _) = super::read_config(None::<&str>).unwrap();
 
-            common_format::manage_calendars(&mut collections, tmp_dir.as_ref()).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            common_format::manage_calendars(&mut collections, &mut handler).unwrap();
             super::set_dataset_validity_period(&mut datasets, &collections.calendars).unwrap();
 
             assert_eq!(
@@ -2037,7 +3079,9 @@ mod tests {
             create_file_with_content(&tmp_dir, "shapes.txt", shapes_content);
 
             let mut collections = Collections::default();
-            super::manage_shapes(&mut collections, tmp_dir.as_ref()).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            super::manage_shapes(&mut collections, &mut handler, &mut Issues::default())
+                .unwrap();
             let mut geometries = collections.geometries.into_vec();
             geometries.sort_unstable_by_key(|s| s.id.clone());
 
@@ -2064,12 +3108,87 @@ mod tests {
     fn read_shapes_with_no_shapes_file() {
         test_in_tmp_dir(|ref tmp_dir| {
             let mut collections = Collections::default();
-            super::manage_shapes(&mut collections, tmp_dir.as_ref()).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            super::manage_shapes(&mut collections, &mut handler, &mut Issues::default())
+                .unwrap();
             let geometries = collections.geometries.into_vec();
             assert_eq!(geometries, vec![]);
         });
     }
 
+    #[test]
+    fn read_fares() {
+        let stops_content =
+            "stop_id,stop_name,stop_lat,stop_lon,location_type,parent_station,zone_id\n\
+             sa:1,Stop 1,48.1,2.1,1,,\n\
+             sp:1,Stop 1,48.1,2.1,0,sa:1,zone:1\n\
+             sa:2,Stop 2,48.2,2.2,1,,\n\
+             sp:2,Stop 2,48.2,2.2,0,sa:2,zone:2\n\
+             sp:3,Stop 3,48.2,2.2,0,sa:2,zone:2";
+        let fare_attributes_content =
+            "fare_id,price,currency_type,payment_method,transfers\n\
+             fare:1,1.50,EUR,0,\n\
+             fare:2,2,eur,0,0\n\
+             fare:3,free,EUR,0,0";
+        let fare_rules_content = "fare_id,route_id,origin_id,destination_id,contains_id\n\
+                                  fare:1,,zone:1,zone:2,\n\
+                                  fare:1,,zone:2,zone:1,\n\
+                                  fare:2,,zone:1,,\n\
+                                  fare:3,,zone:1,zone:2,\n\
+                                  fare:4,,zone:1,zone:2,";
+
+        test_in_tmp_dir(|tmp_dir| {
+            create_file_with_content(tmp_dir, "stops.txt", stops_content);
+            create_file_with_content(tmp_dir, "fare_attributes.txt", fare_attributes_content);
+            create_file_with_content(tmp_dir, "fare_rules.txt", fare_rules_content);
+
+            let mut collections = Collections::default();
+            let (_, datasets, _) = super::read_config(None::<&str>).unwrap();
+            collections.datasets = datasets;
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            let mut issues = Issues::new(ParseProfile::Permissive);
+            let (stop_areas, stop_points, _) = super::read_stops(
+                &mut handler,
+                &mut CollectionWithId::default(),
+                &mut EquipmentList::default(),
+                &mut issues,
+            ).unwrap();
+            collections.stop_areas = stop_areas;
+            collections.stop_points = stop_points;
+            super::read_fares(&mut handler, &mut collections, &mut issues).unwrap();
+
+            assert_eq!(vec!["fare:1", "fare:2"], extract_ids(&collections.tickets));
+            let prices: Vec<_> = collections
+                .ticket_prices
+                .values()
+                .map(|p| (p.price.to_string(), p.currency.to_string()))
+                .collect();
+            assert_eq!(
+                vec![
+                    ("1.5".to_string(), "EUR".to_string()),
+                    ("2".to_string(), "EUR".to_string()),
+                ],
+                prices
+            );
+            let od_rules: Vec<_> = collections
+                .od_rules
+                .values()
+                .map(|r| {
+                    (
+                        r.ticket_id.as_str(),
+                        r.origin_stop_area_id.as_str(),
+                        r.destination_stop_area_id.as_str(),
+                    )
+                })
+                .collect();
+            assert_eq!(
+                vec![("fare:1", "sa:1", "sa:2"), ("fare:1", "sa:2", "sa:1")],
+                od_rules
+            );
+            assert_eq!(3, issues.into_warnings().len());
+        });
+    }
+
     #[test]
     fn deduplicate_funicular_physical_mode() {
         let routes_content = "route_id,agency_id,route_short_name,route_long_name,route_desc,route_type,route_url,route_color,route_text_color\n\
@@ -2088,11 +3207,17 @@ mod tests {
             create_file_with_content(&tmp_dir, "trips.txt", trips_content);
 
             let mut collections = Collections::default();
-            let (contributors, datasets) = super::read_config(None::<&str>).unwrap();
+            let (contributors, datasets, _) = super::read_config(None::<&str>).unwrap();
             collections.contributors = contributors;
             collections.datasets = datasets;
 
-            super::read_routes(tmp_dir, &mut collections).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            super::read_routes(
+                &mut handler,
+                &mut collections,
+                &ModeMapping::default(),
+                &mut Issues::default(),
+            ).unwrap();
             // physical mode file should contain only two modes (5,6,7 => funicular 2 => train)
             assert_eq!(4, collections.lines.len());
             assert_eq!(4, collections.commercial_modes.len());
@@ -2112,8 +3237,14 @@ mod tests {
             create_file_with_content(&tmp_dir, "stops.txt", stops_content);
             let mut equipments = EquipmentList::default();
             let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
-            let (stop_areas, stop_points) =
-                super::read_stops(tmp_dir.path(), &mut comments, &mut equipments).unwrap();
+            let mut handler = PathFileHandler::new(tmp_dir.path());
+            let (stop_areas, stop_points, _) =
+                super::read_stops(
+                    &mut handler,
+                    &mut comments,
+                    &mut equipments,
+                    &mut Issues::default(),
+                ).unwrap();
             assert_eq!(1, stop_points.len());
             assert_eq!(1, stop_areas.len());
             let stop_area = stop_areas.iter().next().unwrap().1;
@@ -2122,4 +3253,231 @@ mod tests {
             assert_eq!("stop:1", stop_point.id);
         });
     }
+
+    #[cfg(feature = "parallel")]
+    fn sorted_json<T: ::serde::Serialize>(c: &Collection<T>) -> Vec<String> {
+        let mut objects: Vec<_> = c
+            .values()
+            .map(|o| ::serde_json::to_string(o).unwrap())
+            .collect();
+        objects.sort();
+        objects
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn deserialize_stop_times_in_parallel() {
+        let content = "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+                       1,06:00:00,06:00:00,sp:01,1\n\
+                       1,invalid,06:06:27,sp:02,2\n\
+                       2,06:06:27,06:06:27,sp:02,1\n\
+                       2,06:07:00,06:07:00,sp:03,2\n\
+                       3,06:08:00,06:08:00,sp:03,1";
+
+        let rdr = || ::csv::Reader::from_reader(content.as_bytes());
+        let debug = |(line, stop_time)| format!("{:?} {:?}", line, stop_time);
+        let sequential: Vec<_> = super::deserialize_stop_times(rdr())
+            .unwrap()
+            .map(debug)
+            .collect();
+        let parallel: Vec<_> = super::deserialize_stop_times_in_parallel(rdr(), 2)
+            .unwrap()
+            .map(debug)
+            .collect();
+        assert_eq!(5, parallel.len());
+        assert_eq!(sequential, parallel);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn read_independent_files_in_parallel() {
+        let calendar_content = "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+                                1,1,1,1,1,1,0,0,20180501,20180508";
+        let stops_content = "stop_id,stop_name,stop_lat,stop_lon,location_type,parent_station\n\
+                             sa:01,my stop area,48.85,2.35,1,\n\
+                             sp:01,my stop point,48.85,2.35,0,sa:01\n\
+                             sp:02,my other stop point,48.86,2.36,0,\n\
+                             sp:03,invalid stop point,invalid,2.36,0,";
+        let transfers_content = "from_stop_id,to_stop_id,transfer_type,min_transfer_time\n\
+                                 sp:01,sp:02,2,60\n\
+                                 sp:01,sp:03,2,60";
+        let shapes_content = "shape_id,shape_pt_lat,shape_pt_lon,shape_pt_sequence\n\
+                              shape:1,48.85,2.35,1\n\
+                              shape:1,48.86,2.36,2\n\
+                              shape:2,48.86,2.36,1\n\
+                              shape:2,48.85,2.35,2";
+        let routes_content = "route_id,route_short_name,route_long_name,route_type\n\
+                              route:1,1,my route,3";
+        let trips_content = "route_id,service_id,trip_id,shape_id\n\
+                             route:1,1,trip:1,shape:1\n\
+                             route:1,1,trip:2,shape:2\n\
+                             unknown,1,trip:3,";
+
+        test_in_tmp_dir(|tmp_dir| {
+            create_file_with_content(tmp_dir, "calendar.txt", calendar_content);
+            create_file_with_content(tmp_dir, "stops.txt", stops_content);
+            create_file_with_content(tmp_dir, "transfers.txt", transfers_content);
+            create_file_with_content(tmp_dir, "shapes.txt", shapes_content);
+            create_file_with_content(tmp_dir, "routes.txt", routes_content);
+            create_file_with_content(tmp_dir, "trips.txt", trips_content);
+
+            let read = |parallel: bool| {
+                let mut handler = PathFileHandler::new(tmp_dir.path());
+                let (_, datasets, modes) = super::read_config(None::<&str>).unwrap();
+                let mut collections = Collections {
+                    datasets,
+                    ..Default::default()
+                };
+                let mut comments = CollectionWithId::default();
+                let mut equipments = EquipmentList::default();
+                let mut issues = Issues::new(ParseProfile::Permissive);
+                let shape_distances = if parallel {
+                    ::gtfs::read_independent_files_in_parallel(
+                        &mut handler,
+                        &mut collections,
+                        &mut comments,
+                        &mut equipments,
+                        &modes,
+                        &mut issues,
+                    )
+                } else {
+                    ::gtfs::read_independent_files(
+                        &mut handler,
+                        &mut collections,
+                        &mut comments,
+                        &mut equipments,
+                        &modes,
+                        &mut issues,
+                    )
+                }.unwrap();
+                (collections, shape_distances, issues.into_warnings())
+            };
+            let (sequential, sequential_distances, sequential_warnings) = read(false);
+            let (parallel, parallel_distances, parallel_warnings) = read(true);
+
+            assert_eq!(3, parallel_warnings.len());
+            assert_eq!(sequential_warnings, parallel_warnings);
+            assert_eq!(sequential_distances, parallel_distances);
+            assert_eq!(
+                extract_ids(&sequential.geometries),
+                extract_ids(&parallel.geometries)
+            );
+            macro_rules! assert_same {
+                ($($collection:ident),*) => {
+                    $(assert_eq!(
+                        sorted_json(&sequential.$collection),
+                        sorted_json(&parallel.$collection)
+                    );)*
+                };
+            }
+            assert_same!(
+                calendars,
+                stop_areas,
+                stop_points,
+                entrances,
+                transfers,
+                commercial_modes,
+                physical_modes,
+                lines,
+                routes,
+                vehicle_journeys,
+                id_mappings
+            );
+        });
+    }
+
+    #[test]
+    fn read_gtfs_from_zip() {
+        let input_dir = TempDir::new("navitia_model_tests").expect("create temp dir");
+        for &(name, content) in MINIMAL_GTFS {
+            create_file_with_content(&input_dir, name, content);
+        }
+        let archive_dir = TempDir::new("navitia_model_tests").expect("create temp dir");
+        let archive = archive_dir.path().join("gtfs.archive");
+        utils::zip_to(input_dir.path(), &archive).unwrap();
+
+        let model = gtfs::read_from_zip(&archive, None, None).unwrap();
+        assert_eq!(2, model.stop_points.len());
+        assert_eq!(2, model.vehicle_journeys.len());
+        assert!(gtfs::read_from_zip(input_dir.path(), None, None).is_err());
+    }
+
+    #[test]
+    fn read_gtfs_from_memory() {
+        let mut handler = MemoryFileHandler::new("memory");
+        for &(name, content) in MINIMAL_GTFS {
+            handler.insert(name, content);
+        }
+
+        let (model, warnings) =
+            gtfs::read_from_handler(&mut handler, None::<&str>, None, &ParseProfile::default())
+                .unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(2, model.stop_areas.len());
+        assert_eq!(1, model.lines.len());
+        assert_eq!(2, model.vehicle_journeys.len());
+    }
+
+    // Replaces the trips and stop times of the minimal GTFS by invalid
+    // rows and unknown references.
+    const MESSY_GTFS: &[(&str, &str)] = &[
+        (
+            "trips.txt",
+            "route_id,service_id,trip_id,direction_id\n\
+             route:1,service:1,trip:1,0\n\
+             unknown_route,service:1,trip:2,0\n\
+             route:1,service:1,trip:3,invalid_direction",
+        ),
+        (
+            "stop_times.txt",
+            "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+             trip:1,08:00:00,08:00:00,sp:1,1\n\
+             trip:1,08:10:00,08:10:00,unknown_stop,2\n\
+             trip:1,invalid_time,08:20:00,sp:2,3\n\
+             trip:2,09:00:00,09:00:00,sp:2,1",
+        ),
+    ];
+
+    #[test]
+    fn read_messy_gtfs_leniently() {
+        let mut handler = MemoryFileHandler::new("memory");
+        for &(name, content) in MINIMAL_GTFS.iter().chain(MESSY_GTFS) {
+            handler.insert(name, content);
+        }
+
+        let (model, warnings) =
+            gtfs::read_from_handler(&mut handler, None::<&str>, None, &ParseProfile::Permissive)
+                .unwrap();
+        let trip_ids: Vec<_> = model.vehicle_journeys.values().map(|vj| &vj.id).collect();
+        assert_eq!(vec!["trip:1"], trip_ids);
+        assert_eq!(1, model.vehicle_journeys.get("trip:1").unwrap().stop_times.len());
+        let lines: Vec<_> = warnings
+            .iter()
+            .map(|w| (w.file.as_str(), w.line))
+            .collect();
+        assert_eq!(
+            vec![
+                ("trips.txt", Some(4)),
+                ("trips.txt", Some(3)),
+                ("stop_times.txt", Some(3)),
+                ("stop_times.txt", Some(4)),
+                ("stop_times.txt", Some(5)),
+            ],
+            lines
+        );
+
+        let strict =
+            gtfs::read_from_handler(&mut handler, None::<&str>, None, &ParseProfile::Strict);
+        assert!(strict.is_err());
+    }
+
+    #[test]
+    fn read_messy_gtfs_strictly_by_default() {
+        test_in_tmp_dir(|tmp_dir| {
+            for &(name, content) in MINIMAL_GTFS.iter().chain(MESSY_GTFS) {
+                create_file_with_content(tmp_dir, name, content);
+            }
+            assert!(gtfs::read(tmp_dir.path(), None, None).is_err());
+        });
+    }
 }
@@ -0,0 +1,934 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+use super::{
+    Agency, Attribution, DirectionType, Route, RouteType, Shape, Stop, StopLocationType, StopTime,
+    Transfer, TransferType, Translation, Trip, FEED_INFO_KEYS, TRANSLATED_FIELDS,
+};
+use collection::{Collection, CollectionWithId, Id};
+use failure::ResultExt;
+use geo_types::Geometry as GeoGeometry;
+use model::Collections;
+use objects::{self, Availability, Geometry, ObjectType, VehicleJourney};
+use progress;
+use serde;
+use std::collections::{BTreeSet, HashMap};
+use std::path;
+use std::result::Result as StdResult;
+use Result;
+
+impl serde::Serialize for RouteType {
+    fn serialize<S>(&self, serializer: S) -> StdResult<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_gtfs_value())
+    }
+}
+
+fn get_route_type(physical_mode_id: &str) -> RouteType {
+    match physical_mode_id {
+        "RailShuttle" | "Tramway" => RouteType::Tramway_LightRail,
+        "Metro" => RouteType::Metro,
+        "Train" | "LocalTrain" | "LongDistanceTrain" | "RapidTransit" => RouteType::Rail,
+        "Ferry" | "Boat" => RouteType::Ferry,
+        "SuspendedCableCar" => RouteType::Gondola_SuspendedCableCar,
+        "Funicular" => RouteType::Funicular,
+        _ => RouteType::Bus,
+    }
+}
+
+fn get_stop_code(codes: &objects::KeysValues) -> Option<String> {
+    codes
+        .iter()
+        .find(|(system, _)| system == "gtfs_stop_code")
+        .map(|(_, code)| code.clone())
+}
+
+/// Writes `feed_info.txt` from the feed infos, unless none of its
+/// columns is given.
+pub fn write_feed_info(path: &path::Path, feed_infos: &HashMap<String, String>) -> Result<()> {
+    let (keys, values): (Vec<&str>, Vec<&str>) = FEED_INFO_KEYS
+        .iter()
+        .filter_map(|&key| feed_infos.get(key).map(|value| (key, value.as_str())))
+        .unzip();
+    if keys.is_empty() {
+        return Ok(());
+    }
+    info!("Writing feed_info.txt");
+    let path = path.join("feed_info.txt");
+    let mut wtr = progress::csv_writer(&path)?;
+    wtr.write_record(&keys).with_context(ctx_from_path!(path))?;
+    wtr.write_record(&values).with_context(ctx_from_path!(path))?;
+    wtr.flush().with_context(ctx_from_path!(path))?;
+
+    Ok(())
+}
+
+pub fn write_agencies(
+    path: &path::Path,
+    networks: &CollectionWithId<objects::Network>,
+) -> Result<()> {
+    info!("Writing agency.txt");
+    let path = path.join("agency.txt");
+    let mut wtr = progress::csv_writer(&path)?;
+    for n in networks.values() {
+        wtr.serialize(Agency {
+            id: Some(n.id.clone()),
+            name: n.name.clone(),
+            url: n.url.clone().unwrap_or_default(),
+            timezone: n.timezone.clone(),
+            lang: n.lang.clone(),
+            phone: n.phone.clone(),
+            email: None,
+        }).with_context(ctx_from_path!(path))?;
+    }
+    wtr.flush().with_context(ctx_from_path!(path))?;
+
+    Ok(())
+}
+
+pub fn write_stops(
+    path: &path::Path,
+    stop_points: &CollectionWithId<objects::StopPoint>,
+    stop_areas: &CollectionWithId<objects::StopArea>,
+    entrances: &CollectionWithId<objects::Entrance>,
+) -> Result<()> {
+    info!("Writing stops.txt");
+    let path = path.join("stops.txt");
+    let mut wtr = progress::csv_writer(&path)?;
+    for sa in stop_areas.values() {
+        wtr.serialize(Stop {
+            id: sa.id.clone(),
+            code: get_stop_code(&sa.codes),
+            name: sa.name.clone(),
+            desc: "".to_string(),
+            lon: sa.coord.lon,
+            lat: sa.coord.lat,
+            fare_zone_id: None,
+            url: None,
+            location_type: StopLocationType::StopArea,
+            parent_station: None,
+            timezone: sa.timezone.clone(),
+            wheelchair_boarding: None,
+        }).with_context(ctx_from_path!(path))?;
+    }
+    for sp in stop_points.values() {
+        wtr.serialize(Stop {
+            id: sp.id.clone(),
+            code: get_stop_code(&sp.codes),
+            name: sp.name.clone(),
+            desc: "".to_string(),
+            lon: sp.coord.lon,
+            lat: sp.coord.lat,
+            fare_zone_id: sp.fare_zone_id.clone(),
+            url: None,
+            location_type: StopLocationType::StopPoint,
+            parent_station: Some(sp.stop_area_id.to_string()),
+            timezone: sp.timezone.clone(),
+            wheelchair_boarding: None,
+        }).with_context(ctx_from_path!(path))?;
+    }
+    for e in entrances.values() {
+        wtr.serialize(Stop {
+            id: e.id.clone(),
+            code: None,
+            name: e.name.clone(),
+            desc: "".to_string(),
+            lon: e.coord.lon,
+            lat: e.coord.lat,
+            fare_zone_id: None,
+            url: None,
+            location_type: StopLocationType::StopEntrace,
+            parent_station: Some(e.stop_area_id.to_string()),
+            timezone: e.timezone.clone(),
+            wheelchair_boarding: None,
+        }).with_context(ctx_from_path!(path))?;
+    }
+    wtr.flush().with_context(ctx_from_path!(path))?;
+
+    Ok(())
+}
+
+/// The continuous pickup and drop off of the lines, given by their
+/// routes, `None` when their routes don't share the same value.  A
+/// route with another value than its line gives it on its stop times.
+fn line_continuous_types(collections: &Collections) -> HashMap<&str, (Option<u8>, Option<u8>)> {
+    let mut line_routes: HashMap<&str, Vec<&objects::Route>> = HashMap::new();
+    for route in collections.routes.values() {
+        line_routes.entry(&route.line_id).or_default().push(route);
+    }
+    line_routes
+        .into_iter()
+        .map(|(line_id, routes)| {
+            let shared = |value: fn(&objects::Route) -> Option<u8>| {
+                let first = value(routes[0]);
+                if routes.iter().all(|r| value(r) == first) {
+                    first
+                } else {
+                    None
+                }
+            };
+            let types = (
+                shared(|r| r.continuous_pickup),
+                shared(|r| r.continuous_drop_off),
+            );
+            (line_id, types)
+        })
+        .collect()
+}
+
+pub fn write_routes(path: &path::Path, collections: &Collections) -> Result<()> {
+    info!("Writing routes.txt");
+    let mut physical_modes: HashMap<&str, &str> = HashMap::new();
+    for vj in collections.vehicle_journeys.values() {
+        if let Some(route) = collections.routes.get(&vj.route_id) {
+            physical_modes
+                .entry(&route.line_id)
+                .or_insert(&vj.physical_mode_id);
+        }
+    }
+
+    let continuous_types = line_continuous_types(collections);
+
+    let path = path.join("routes.txt");
+    let mut wtr = progress::csv_writer(&path)?;
+    for l in collections.lines.values() {
+        let physical_mode_id = physical_modes.get(l.id.as_str()).cloned().unwrap_or("Bus");
+        let (continuous_pickup, continuous_drop_off) = continuous_types
+            .get(l.id.as_str())
+            .cloned()
+            .unwrap_or_default();
+        wtr.serialize(Route {
+            id: l.id.clone(),
+            agency_id: Some(l.network_id.to_string()),
+            short_name: l.code.clone().unwrap_or_default(),
+            long_name: l.name.clone(),
+            desc: None,
+            route_type: get_route_type(physical_mode_id),
+            url: None,
+            color: l.color.clone(),
+            text_color: l.text_color.clone(),
+            sort_order: l.sort_order,
+            continuous_pickup,
+            continuous_drop_off,
+        }).with_context(ctx_from_path!(path))?;
+    }
+    wtr.flush().with_context(ctx_from_path!(path))?;
+
+    Ok(())
+}
+
+fn get_availability(availability: &Availability) -> u8 {
+    match *availability {
+        Availability::InformationNotAvailable => 0,
+        Availability::Available => 1,
+        Availability::NotAvailable => 2,
+    }
+}
+
+pub fn write_trips_and_stop_times(
+    path: &path::Path,
+    collections: &Collections,
+    shape_ids: &BTreeSet<&str>,
+) -> Result<()> {
+    info!("Writing trips.txt and stop_times.txt");
+    let trip_path = path.join("trips.txt");
+    let stop_times_path = path.join("stop_times.txt");
+    let mut vj_wtr = progress::csv_writer(&trip_path)?;
+    let mut st_wtr = progress::csv_writer(&stop_times_path)?;
+    let continuous_types = line_continuous_types(collections);
+    for vj in collections.vehicle_journeys.values() {
+        let route = collections.routes.get(&vj.route_id).ok_or_else(|| {
+            format_err!(
+                "route_id={:?} of trip_id={:?} not found",
+                vj.route_id,
+                vj.id
+            )
+        })?;
+        let direction = match route.direction_type.as_deref() {
+            Some("backward") | Some("anticlockwise") | Some("inbound") => {
+                DirectionType::Backward
+            }
+            _ => DirectionType::Forward,
+        };
+        let (line_pickup, line_drop_off) = continuous_types
+            .get(route.line_id.as_str())
+            .cloned()
+            .unwrap_or_default();
+        let route_pickup = route.continuous_pickup.filter(|_| line_pickup.is_none());
+        let route_drop_off = route.continuous_drop_off.filter(|_| line_drop_off.is_none());
+        let (wheelchair_accessible, bikes_allowed) = vj
+            .trip_property_id
+            .as_ref()
+            .and_then(|id| collections.trip_properties.get(id))
+            .map(|tp| {
+                (
+                    get_availability(&tp.wheelchair_accessible),
+                    get_availability(&tp.bike_accepted),
+                )
+            })
+            .unwrap_or_default();
+        vj_wtr
+            .serialize(Trip {
+                route_id: route.line_id.to_string(),
+                service_id: vj.service_id.to_string(),
+                id: vj.id.clone(),
+                headsign: vj.headsign.as_ref().map(|h| h.to_string()),
+                short_name: None,
+                direction,
+                block_id: vj.block_id.clone(),
+                shape_id: vj
+                    .geometry_id
+                    .clone()
+                    .filter(|id| shape_ids.contains(id.as_str())),
+                wheelchair_accessible,
+                bikes_allowed,
+            })
+            .with_context(ctx_from_path!(trip_path))?;
+
+        for st in &vj.stop_times {
+            st_wtr
+                .serialize(StopTime {
+                    trip_id: vj.id.clone(),
+                    arrival_time: Some(st.arrival_time),
+                    departure_time: Some(st.departure_time),
+                    stop_id: collections.stop_points[st.stop_point_idx].id.clone(),
+                    stop_sequence: st.sequence,
+                    pickup_type: st.pickup_type,
+                    drop_off_type: st.drop_off_type,
+                    shape_dist_traveled: st.shape_dist_traveled(),
+                    timepoint: Some(if st.datetime_estimated { 0 } else { 1 }),
+                    continuous_pickup: st.continuous_pickup().or(route_pickup),
+                    continuous_drop_off: st.continuous_drop_off().or(route_drop_off),
+                })
+                .with_context(ctx_from_path!(stop_times_path))?;
+        }
+    }
+    st_wtr
+        .flush()
+        .with_context(ctx_from_path!(stop_times_path))?;
+    vj_wtr.flush().with_context(ctx_from_path!(trip_path))?;
+
+    Ok(())
+}
+
+/// Writes `transfers.txt`, GTFS having a single transfer time: the
+/// real one, including the time needed to reach the vehicle, is
+/// written when given.
+pub fn write_transfers(path: &path::Path, transfers: &Collection<objects::Transfer>) -> Result<()> {
+    info!("Writing transfers.txt");
+    let path = path.join("transfers.txt");
+    let mut wtr = progress::csv_writer(&path)?;
+    for t in transfers.values() {
+        let min_transfer_time = t.real_min_transfer_time.or(t.min_transfer_time);
+        let transfer_type = match min_transfer_time {
+            Some(_) => TransferType::WithTransferTime,
+            None => TransferType::Recommended,
+        };
+        wtr.serialize(Transfer {
+            from_stop_id: t.from_stop_id.clone(),
+            to_stop_id: t.to_stop_id.clone(),
+            transfer_type,
+            min_transfer_time,
+        }).with_context(ctx_from_path!(path))?;
+    }
+    wtr.flush().with_context(ctx_from_path!(path))?;
+
+    Ok(())
+}
+
+/// Writes `attributions.txt`, unless there is no attribution, the
+/// attributions of a network, a line or a vehicle journey being given
+/// to the corresponding agency, route or trip.
+pub fn write_attributions(
+    path: &path::Path,
+    attributions: &Collection<objects::Attribution>,
+) -> Result<()> {
+    if attributions.is_empty() {
+        return Ok(());
+    }
+    info!("Writing attributions.txt");
+    let path = path.join("attributions.txt");
+    let mut wtr = progress::csv_writer(&path)?;
+    for a in attributions.values() {
+        wtr.serialize(Attribution {
+            attribution_id: a.id.clone(),
+            agency_id: a.network_id.clone().map(String::from),
+            route_id: a.line_id.clone().map(String::from),
+            trip_id: a.vehicle_journey_id.clone(),
+            organization_name: a.organization_name.clone(),
+            is_producer: a.is_producer,
+            is_operator: a.is_operator,
+            is_authority: a.is_authority,
+            attribution_url: a.url.clone(),
+            attribution_email: a.email.clone(),
+            attribution_phone: a.phone.clone(),
+        }).with_context(ctx_from_path!(path))?;
+    }
+    wtr.flush().with_context(ctx_from_path!(path))?;
+
+    Ok(())
+}
+
+/// Writes `translations.txt`, unless there is no translation.  The
+/// translations of the objects and fields GTFS doesn't have are
+/// skipped.
+pub fn write_translations(
+    path: &path::Path,
+    translations: &Collection<objects::Translation>,
+) -> Result<()> {
+    let gtfs_field = |t: &objects::Translation| {
+        let table = match t.object_type {
+            ObjectType::Network => "agency",
+            ObjectType::StopPoint | ObjectType::StopArea => "stops",
+            ObjectType::Line => "routes",
+            ObjectType::VehicleJourney => "trips",
+            _ => return None,
+        };
+        TRANSLATED_FIELDS
+            .iter()
+            .find(|&&(tbl, _, field_name)| tbl == table && field_name == t.field_name)
+            .map(|&(table, field, _)| (table, field))
+    };
+    let mut translations = translations
+        .values()
+        .filter_map(|t| gtfs_field(t).map(|field| (t, field)))
+        .peekable();
+    if translations.peek().is_none() {
+        return Ok(());
+    }
+    info!("Writing translations.txt");
+    let path = path.join("translations.txt");
+    let mut wtr = progress::csv_writer(&path)?;
+    for (t, (table, field)) in translations {
+        wtr.serialize(Translation {
+            table_name: table.to_string(),
+            field_name: field.to_string(),
+            language: t.language.clone(),
+            translation: t.translation.clone(),
+            record_id: Some(t.object_id.clone()),
+            record_sub_id: None,
+            field_value: None,
+        }).with_context(ctx_from_path!(path))?;
+    }
+    wtr.flush().with_context(ctx_from_path!(path))?;
+
+    Ok(())
+}
+
+/// Writes the collection in `file`, unless it is empty as the
+/// optional files.
+pub fn write_opt_collection_with_id<T>(
+    path: &path::Path,
+    file: &str,
+    collection: &CollectionWithId<T>,
+) -> Result<()>
+where
+    T: Id<T>,
+    T: serde::Serialize,
+{
+    if collection.is_empty() {
+        return Ok(());
+    }
+    info!("Writing {}", file);
+    let path = path.join(file);
+    let mut wtr = progress::csv_writer(&path)?;
+    for obj in collection.values() {
+        wtr.serialize(obj).with_context(ctx_from_path!(path))?;
+    }
+    wtr.flush().with_context(ctx_from_path!(path))?;
+
+    Ok(())
+}
+
+/// Writes in `shapes.txt` the line string geometries of the vehicle
+/// journeys, and returns the ids of the written shapes.  The other
+/// geometries can't be represented in GTFS and are skipped.
+pub fn write_shapes<'a>(
+    path: &path::Path,
+    vehicle_journeys: &'a CollectionWithId<VehicleJourney>,
+    geometries: &CollectionWithId<Geometry>,
+) -> Result<BTreeSet<&'a str>> {
+    let mut shape_ids = BTreeSet::new();
+    let geometry_ids: BTreeSet<&str> = vehicle_journeys
+        .values()
+        .filter_map(|vj| vj.geometry_id.as_deref())
+        .collect();
+    if geometry_ids.is_empty() {
+        return Ok(shape_ids);
+    }
+
+    info!("Writing shapes.txt");
+    let path = path.join("shapes.txt");
+    let mut wtr = progress::csv_writer(&path)?;
+    for id in geometry_ids {
+        let linestring = match geometries.get(id).map(|g| &g.geometry) {
+            Some(GeoGeometry::LineString(linestring)) => linestring,
+            Some(_) => {
+                warn!("Geometry {} is not a line string, it won't be written as a shape", id);
+                continue;
+            }
+            None => continue,
+        };
+        for (sequence, point) in linestring.0.iter().enumerate() {
+            wtr.serialize(Shape {
+                id: id.to_string(),
+                lat: point.y(),
+                lon: point.x(),
+                sequence: sequence as u32,
+                shape_dist_traveled: None,
+            }).with_context(ctx_from_path!(path))?;
+        }
+        shape_ids.insert(id);
+    }
+    wtr.flush().with_context(ctx_from_path!(path))?;
+
+    Ok(shape_ids)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempdir;
+    use self::tempdir::TempDir;
+    use geo_types::{Geometry as GeoGeometry, LineString, Point};
+    use gtfs::{self, MINIMAL_GTFS};
+    use model::Model;
+    use objects::{Availability, Coord, Entrance, Geometry, Level, PathwayMode};
+    use std::fs::File;
+    use std::io::prelude::*;
+
+    fn create_file_with_content(temp_dir: &TempDir, file_name: &str, content: &str) {
+        let file_path = temp_dir.path().join(file_name);
+        let mut f = File::create(&file_path).unwrap();
+        f.write_all(content.as_bytes()).unwrap();
+    }
+
+    // Reads the minimal GTFS completed with `files`, writes it and
+    // reads it back.
+    fn round_trip(files: &[(&str, &str)]) -> Model {
+        let input_dir = TempDir::new("navitia_model_tests").expect("create temp dir");
+        for &(name, content) in MINIMAL_GTFS.iter().chain(files) {
+            create_file_with_content(&input_dir, name, content);
+        }
+        let model = gtfs::read(input_dir.path(), None, None).unwrap();
+
+        let output_dir = TempDir::new("navitia_model_tests").expect("create temp dir");
+        gtfs::write(&model, output_dir.path()).unwrap();
+        gtfs::read(output_dir.path(), None, None).unwrap()
+    }
+
+    #[test]
+    fn shapes_round_trip() {
+        let model = round_trip(&[(
+            "shapes.txt",
+            "shape_id,shape_pt_lat,shape_pt_lon,shape_pt_sequence\n\
+             shape:1,48.1,2.1,1\n\
+             shape:1,48.15,2.15,2\n\
+             shape:1,48.2,2.2,3\n\
+             unused_shape,48.2,2.2,1",
+        )]);
+
+        assert_eq!(
+            model.geometries.values().collect::<Vec<_>>(),
+            vec![&Geometry {
+                id: "shape:1".to_string(),
+                geometry: GeoGeometry::LineString(LineString(vec![
+                    Point::new(2.1, 48.1),
+                    Point::new(2.15, 48.15),
+                    Point::new(2.2, 48.2),
+                ])),
+            }]
+        );
+        assert_eq!(
+            Some("shape:1".to_string()),
+            model.vehicle_journeys.get("trip:1").unwrap().geometry_id
+        );
+        assert_eq!(None, model.vehicle_journeys.get("trip:2").unwrap().geometry_id);
+    }
+
+    fn distances(model: &Model, trip_id: &str) -> Vec<f64> {
+        let vj = model.vehicle_journeys.get(trip_id).unwrap();
+        vj.stop_times
+            .iter()
+            .map(|st| st.shape_dist_traveled().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn shape_dist_traveled_round_trip() {
+        let model = round_trip(&[
+            (
+                "shapes.txt",
+                "shape_id,shape_pt_lat,shape_pt_lon,shape_pt_sequence,shape_dist_traveled\n\
+                 shape:1,48.1,2.1,1,0\n\
+                 shape:1,48.15,2.15,2,5\n\
+                 shape:1,48.2,2.2,3,10",
+            ),
+            (
+                "stop_times.txt",
+                "trip_id,arrival_time,departure_time,stop_id,stop_sequence,shape_dist_traveled\n\
+                 trip:1,08:00:00,08:00:00,sp:1,1,\n\
+                 trip:1,08:10:00,08:10:00,sp:2,2,\n\
+                 trip:2,09:00:00,09:00:00,sp:2,1,0.5\n\
+                 trip:2,09:10:00,09:10:00,sp:1,2,12.5",
+            ),
+        ]);
+
+        // Interpolated from the distances of the shape.
+        let trip_1 = distances(&model, "trip:1");
+        assert_eq!(0., trip_1[0]);
+        assert!((trip_1[1] - 10.).abs() < 1e-6);
+        assert_eq!(vec![0.5, 12.5], distances(&model, "trip:2"));
+    }
+
+    #[test]
+    fn timepoint_round_trip() {
+        let model = round_trip(&[(
+            "stop_times.txt",
+            "trip_id,arrival_time,departure_time,stop_id,stop_sequence,timepoint\n\
+             trip:1,08:00:00,08:00:00,sp:1,1,1\n\
+             trip:1,08:10:00,08:10:00,sp:2,2,0\n\
+             trip:2,09:00:00,09:00:00,sp:2,1,\n\
+             trip:2,09:10:00,09:10:00,sp:1,2,",
+        )]);
+
+        let estimated = |id| -> Vec<bool> {
+            let vj = model.vehicle_journeys.get(id).unwrap();
+            vj.stop_times.iter().map(|st| st.datetime_estimated).collect()
+        };
+        assert_eq!(vec![false, true], estimated("trip:1"));
+        assert_eq!(vec![false, false], estimated("trip:2"));
+    }
+
+    #[test]
+    fn continuous_types_round_trip() {
+        let model = round_trip(&[
+            (
+                "routes.txt",
+                "route_id,agency_id,route_short_name,route_long_name,route_type,\
+                 continuous_pickup,continuous_drop_off\n\
+                 route:1,agency:1,1,Line 1,3,2,1\n\
+                 route:2,agency:1,1,Line 1,3,0,1",
+            ),
+            (
+                "trips.txt",
+                "route_id,service_id,trip_id,direction_id\n\
+                 route:1,service:1,trip:1,0\n\
+                 route:2,service:1,trip:2,0",
+            ),
+            (
+                "stop_times.txt",
+                "trip_id,arrival_time,departure_time,stop_id,stop_sequence,continuous_drop_off\n\
+                 trip:1,08:00:00,08:00:00,sp:1,1,3\n\
+                 trip:1,08:10:00,08:10:00,sp:2,2,\n\
+                 trip:2,09:00:00,09:00:00,sp:2,1,\n\
+                 trip:2,09:10:00,09:10:00,sp:1,2,",
+            ),
+        ]);
+
+        // The drop off shared by the routes of the line is kept on
+        // them, the pickup they disagree on is moved to the stop times.
+        assert!(model
+            .routes
+            .values()
+            .all(|r| r.continuous_pickup.is_none() && r.continuous_drop_off == Some(1)));
+        let types = |id| -> Vec<(Option<u8>, Option<u8>)> {
+            let vj = model.vehicle_journeys.get(id).unwrap();
+            vj.stop_times
+                .iter()
+                .map(|st| (st.continuous_pickup(), st.continuous_drop_off()))
+                .collect()
+        };
+        assert_eq!(vec![(Some(2), Some(3)), (Some(2), None)], types("trip:1"));
+        assert_eq!(vec![(Some(0), None), (Some(0), None)], types("trip:2"));
+    }
+
+    #[test]
+    fn trip_properties_round_trip() {
+        let model = round_trip(&[(
+            "trips.txt",
+            "route_id,service_id,trip_id,direction_id,wheelchair_accessible,bikes_allowed\n\
+             route:1,service:1,trip:1,0,1,2\n\
+             route:1,service:1,trip:2,1,,",
+        )]);
+
+        let availabilities = |id| {
+            let vj = model.vehicle_journeys.get(id).unwrap();
+            vj.trip_property_id.as_ref().map(|tp_id| {
+                let tp = model.trip_properties.get(tp_id).unwrap();
+                (tp.wheelchair_accessible.clone(), tp.bike_accepted.clone())
+            })
+        };
+        assert_eq!(
+            Some((Availability::Available, Availability::NotAvailable)),
+            availabilities("trip:1")
+        );
+        assert_eq!(None, availabilities("trip:2"));
+    }
+
+    #[test]
+    fn transfers_round_trip() {
+        let model = round_trip(&[(
+            "transfers.txt",
+            "from_stop_id,to_stop_id,transfer_type,min_transfer_time\n\
+             sp:1,sp:2,0,\n\
+             sp:2,sp:1,2,300",
+        )]);
+
+        let times: Vec<_> = model
+            .transfers
+            .values()
+            .map(|t| (t.from_stop_id.as_str(), t.real_min_transfer_time))
+            .collect();
+        // The recommended transfer keeps its time with the walking
+        // buffer of the first reading.
+        assert_eq!(vec![("sp:1", Some(17_148)), ("sp:2", Some(300))], times);
+    }
+
+    #[test]
+    fn zone_id_round_trip() {
+        let model = round_trip(&[(
+            "stops.txt",
+            "stop_id,stop_name,stop_lat,stop_lon,location_type,parent_station,zone_id\n\
+             sa:1,Stop 1,48.1,2.1,1,,\n\
+             sp:1,Stop 1,48.1,2.1,0,sa:1,zone:1\n\
+             sa:2,Stop 2,48.2,2.2,1,,\n\
+             sp:2,Stop 2,48.2,2.2,0,sa:2,",
+        )]);
+
+        let zone = |id| model.stop_points.get(id).unwrap().fare_zone_id.clone();
+        assert_eq!(Some("zone:1".to_string()), zone("sp:1"));
+        assert_eq!(None, zone("sp:2"));
+    }
+
+    #[test]
+    fn attributions_round_trip() {
+        let model = round_trip(&[(
+            "attributions.txt",
+            "attribution_id,agency_id,route_id,trip_id,organization_name,is_producer,\
+             is_operator,is_authority,attribution_url\n\
+             attr:1,,,,Open Data,1,,,http://example.com\n\
+             attr:2,agency:1,,,Operator,0,1,0,\n\
+             attr:3,,route:1,,Authority,,,1,\n\
+             attr:4,,,trip:2,Operator,,1,,",
+        )]);
+
+        let attributions: Vec<_> = model
+            .attributions
+            .values()
+            .map(|a| {
+                (
+                    a.id.as_ref().unwrap().as_str(),
+                    a.network_id.as_ref().map(|id| id.as_str()),
+                    a.line_id.as_ref().map(|id| id.as_str()),
+                    a.vehicle_journey_id.as_deref(),
+                    (a.is_producer, a.is_operator, a.is_authority),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                ("attr:1", None, None, None, (true, false, false)),
+                ("attr:2", Some("agency:1"), None, None, (false, true, false)),
+                ("attr:3", None, Some("route:1"), None, (false, false, true)),
+                ("attr:4", None, None, Some("trip:2"), (false, true, false)),
+            ],
+            attributions
+        );
+        let attribution = model.attributions.values().next().unwrap();
+        assert_eq!("default_dataset", attribution.dataset_id);
+        assert_eq!(Some("http://example.com"), attribution.url.as_deref());
+    }
+
+    #[test]
+    fn translations_round_trip() {
+        let model = round_trip(&[(
+            "translations.txt",
+            "table_name,field_name,language,translation,record_id,record_sub_id,field_value\n\
+             stops,stop_name,nl,Halte 1,sp:1,,\n\
+             stops,stop_name,de,Haltestelle 2,,,Stop 2\n\
+             routes,route_long_name,fr,Ligne 1,route:1,,\n\
+             stop_times,stop_headsign,fr,Gare,trip:1,1,",
+        )]);
+
+        let mut translations: Vec<_> = model
+            .translations
+            .values()
+            .map(|t| {
+                (
+                    t.object_type.as_str(),
+                    t.object_id.as_str(),
+                    t.field_name.as_str(),
+                    t.language.as_str(),
+                    t.translation.as_str(),
+                )
+            })
+            .collect();
+        translations.sort();
+        assert_eq!(
+            vec![
+                ("line", "route:1", "line_name", "fr", "Ligne 1"),
+                ("stop_area", "sa:2", "stop_name", "de", "Haltestelle 2"),
+                ("stop_point", "sp:1", "stop_name", "nl", "Halte 1"),
+                ("stop_point", "sp:2", "stop_name", "de", "Haltestelle 2"),
+            ],
+            translations
+        );
+    }
+
+    #[test]
+    fn shape_dist_traveled_computed_from_shape() {
+        let model = round_trip(&[(
+            "shapes.txt",
+            "shape_id,shape_pt_lat,shape_pt_lon,shape_pt_sequence\n\
+             shape:1,48.1,2.1,1\n\
+             shape:1,48.15,2.15,2\n\
+             shape:1,48.2,2.2,3",
+        )]);
+
+        let trip_1 = distances(&model, "trip:1");
+        assert_eq!(0., trip_1[0]);
+        assert!(13_300. < trip_1[1] && trip_1[1] < 13_450.);
+        let trip_2 = model.vehicle_journeys.get("trip:2").unwrap();
+        assert!(trip_2
+            .stop_times
+            .iter()
+            .all(|st| st.shape_dist_traveled().is_none()));
+    }
+
+    #[test]
+    fn write_codes_and_comments_as_extensions() {
+        let input_dir = TempDir::new("navitia_model_tests").expect("create temp dir");
+        for &(name, content) in MINIMAL_GTFS {
+            create_file_with_content(&input_dir, name, content);
+        }
+        create_file_with_content(
+            &input_dir,
+            "stops.txt",
+            "stop_id,stop_code,stop_name,stop_desc,stop_lat,stop_lon,location_type,parent_station\n\
+             sa:1,,Stop 1,,48.1,2.1,1,\n\
+             sp:1,1234,Stop 1,Platform A,48.1,2.1,0,sa:1\n\
+             sa:2,,Stop 2,,48.2,2.2,1,\n\
+             sp:2,,Stop 2,,48.2,2.2,0,sa:2",
+        );
+        let model = gtfs::read(input_dir.path(), None, None).unwrap();
+
+        let output_dir = TempDir::new("navitia_model_tests").expect("create temp dir");
+        gtfs::write(&model, output_dir.path()).unwrap();
+        assert!(!output_dir.path().join("object_codes.txt").exists());
+        assert!(!output_dir.path().join("comments.txt").exists());
+
+        let options = gtfs::WriteOptions { extensions: true };
+        gtfs::write_with_options(&model, output_dir.path(), &options).unwrap();
+        let read_file = |name: &str| {
+            let mut content = String::new();
+            File::open(output_dir.path().join(name))
+                .unwrap()
+                .read_to_string(&mut content)
+                .unwrap();
+            content
+        };
+        assert!(
+            read_file("object_codes.txt").contains("stop_point,sp:1,gtfs_stop_code,1234")
+        );
+        assert!(read_file("comments.txt").contains("Platform A"));
+        assert!(read_file("comment_links.txt").contains("sp:1,stop_point,"));
+    }
+
+    #[test]
+    fn feed_info_round_trip() {
+        let model = round_trip(&[(
+            "feed_info.txt",
+            "feed_publisher_name,feed_publisher_url,feed_lang,feed_start_date,feed_end_date,\
+             feed_version,feed_extension\n\
+             Publisher,http://example.com,fr,20180101,20181231,1.0,unknown",
+        )]);
+
+        let mut feed_infos: Vec<_> = model
+            .feed_infos
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        feed_infos.sort();
+        assert_eq!(
+            vec![
+                ("feed_end_date", "20181231"),
+                ("feed_lang", "fr"),
+                ("feed_publisher_name", "Publisher"),
+                ("feed_publisher_url", "http://example.com"),
+                ("feed_start_date", "20180101"),
+                ("feed_version", "1.0"),
+            ],
+            feed_infos
+        );
+    }
+
+    #[test]
+    fn entrances_round_trip() {
+        let stops = MINIMAL_GTFS[1].1.to_string() + "\ne:1,Entrance 1,48.11,2.11,2,sa:1";
+        let model = round_trip(&[("stops.txt", &stops)]);
+
+        assert_eq!(
+            vec![&Entrance {
+                id: "e:1".to_string(),
+                name: "Entrance 1".to_string(),
+                coord: Coord {
+                    lon: 2.11,
+                    lat: 48.11,
+                },
+                stop_area_id: "sa:1".into(),
+                timezone: None,
+            }],
+            model.entrances.values().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn pathways_and_levels_round_trip() {
+        let model = round_trip(&[
+            (
+                "pathways.txt",
+                "pathway_id,from_stop_id,to_stop_id,pathway_mode,is_bidirectional,stair_count\n\
+                 pw:1,sp:1,sa:1,2,1,12\n\
+                 pw:2,sa:1,sp:1,5,0,",
+            ),
+            (
+                "levels.txt",
+                "level_id,level_index,level_name\n\
+                 level:0,0,Street\n\
+                 level:-1,-1,",
+            ),
+        ]);
+
+        let stairs = model.pathways.get("pw:1").unwrap();
+        assert_eq!(PathwayMode::Stairs, stairs.pathway_mode);
+        assert!(stairs.is_bidirectional);
+        assert_eq!(Some(12), stairs.stair_count);
+        let elevator = model.pathways.get("pw:2").unwrap();
+        assert_eq!(PathwayMode::Elevator, elevator.pathway_mode);
+        assert!(!elevator.is_bidirectional);
+        assert_eq!(None, elevator.stair_count);
+        assert_eq!(
+            &Level {
+                id: "level:-1".to_string(),
+                level_index: -1.,
+                level_name: None,
+            },
+            model.levels.get("level:-1").unwrap()
+        );
+    }
+}
@@ -0,0 +1,356 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! Decoding of the protobuf messages of a GTFS-RT feed, limited to
+//! the fields of the trip updates and of the alerts.
+
+use Result;
+
+// A field of a message, by its wire type, the fixed size fields
+// being unused by GTFS-RT.
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+fn varint(bytes: &[u8], pos: &mut usize) -> Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes
+            .get(*pos)
+            .ok_or_else(|| format_err!("truncated protobuf varint"))?;
+        *pos += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    bail!("invalid protobuf varint")
+}
+
+fn take<'a>(bytes: &'a [u8], pos: &mut usize, len: usize) -> Result<&'a [u8]> {
+    let end = pos
+        .checked_add(len)
+        .filter(|&end| end <= bytes.len())
+        .ok_or_else(|| format_err!("truncated protobuf field"))?;
+    let value = &bytes[*pos..end];
+    *pos = end;
+    Ok(value)
+}
+
+// The fields of a message, by their number, in their order.
+fn fields(bytes: &[u8]) -> Result<Vec<(u64, Value<'_>)>> {
+    let mut fields = vec![];
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let key = varint(bytes, &mut pos)?;
+        let value = match key & 7 {
+            0 => Value::Varint(varint(bytes, &mut pos)?),
+            1 => take(bytes, &mut pos, 8).map(|_| Value::Fixed)?,
+            2 => {
+                let len = varint(bytes, &mut pos)? as usize;
+                Value::Bytes(take(bytes, &mut pos, len)?)
+            }
+            5 => take(bytes, &mut pos, 4).map(|_| Value::Fixed)?,
+            wire_type => bail!("unsupported protobuf wire type {}", wire_type),
+        };
+        fields.push((key >> 3, value));
+    }
+    Ok(fields)
+}
+
+fn as_varint(value: &Value) -> Result<u64> {
+    match *value {
+        Value::Varint(v) => Ok(v),
+        _ => bail!("invalid protobuf field, expected a varint"),
+    }
+}
+
+fn as_bytes<'a>(value: &Value<'a>) -> Result<&'a [u8]> {
+    match *value {
+        Value::Bytes(b) => Ok(b),
+        _ => bail!("invalid protobuf field, expected a length-delimited field"),
+    }
+}
+
+fn as_string(value: &Value) -> Result<String> {
+    Ok(String::from_utf8(as_bytes(value)?.to_vec())?)
+}
+
+// The `int32` and `int64` fields, the negative values being encoded
+// as 64 bits two's complement.
+fn as_int(value: &Value) -> Result<i64> {
+    as_varint(value).map(|v| v as i64)
+}
+
+/// A `FeedMessage`, the root of the feed.
+#[derive(Debug, Default)]
+pub struct FeedMessage {
+    /// The time of the feed, in POSIX time.
+    pub timestamp: Option<u64>,
+    pub entities: Vec<FeedEntity>,
+}
+
+#[derive(Debug, Default)]
+pub struct FeedEntity {
+    pub id: String,
+    pub is_deleted: bool,
+    pub trip_update: Option<TripUpdate>,
+    pub alert: Option<Alert>,
+}
+
+#[derive(Debug, Default)]
+pub struct TripDescriptor {
+    pub trip_id: Option<String>,
+    pub route_id: Option<String>,
+    pub start_date: Option<String>,
+    /// 0 scheduled, 1 added, 2 unscheduled, 3 canceled.
+    pub schedule_relationship: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct TripUpdate {
+    pub trip: TripDescriptor,
+    pub stop_time_updates: Vec<StopTimeUpdate>,
+    pub delay: Option<i64>,
+}
+
+#[derive(Debug, Default)]
+pub struct StopTimeEvent {
+    pub delay: Option<i64>,
+    /// The absolute time, in POSIX time.
+    pub time: Option<i64>,
+}
+
+#[derive(Debug, Default)]
+pub struct StopTimeUpdate {
+    pub stop_sequence: Option<u32>,
+    pub stop_id: Option<String>,
+    pub arrival: Option<StopTimeEvent>,
+    pub departure: Option<StopTimeEvent>,
+    /// 0 scheduled, 1 skipped, 2 no data.
+    pub schedule_relationship: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct EntitySelector {
+    pub agency_id: Option<String>,
+    pub route_id: Option<String>,
+    pub trip: Option<TripDescriptor>,
+    pub stop_id: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Alert {
+    /// The start and end of the periods, in POSIX time.
+    pub active_periods: Vec<(Option<u64>, Option<u64>)>,
+    pub informed_entities: Vec<EntitySelector>,
+    pub cause: Option<u64>,
+    pub effect: Option<u64>,
+    pub url: Option<String>,
+    pub header_text: Option<String>,
+    pub description_text: Option<String>,
+}
+
+/// Decodes a `FeedMessage`.
+pub fn decode(bytes: &[u8]) -> Result<FeedMessage> {
+    let mut feed = FeedMessage::default();
+    for (number, value) in fields(bytes)? {
+        match number {
+            1 => {
+                for (number, value) in fields(as_bytes(&value)?)? {
+                    if number == 3 {
+                        feed.timestamp = Some(as_varint(&value)?);
+                    }
+                }
+            }
+            2 => feed.entities.push(entity(as_bytes(&value)?)?),
+            _ => {}
+        }
+    }
+    Ok(feed)
+}
+
+fn entity(bytes: &[u8]) -> Result<FeedEntity> {
+    let mut entity = FeedEntity::default();
+    for (number, value) in fields(bytes)? {
+        match number {
+            1 => entity.id = as_string(&value)?,
+            2 => entity.is_deleted = as_varint(&value)? != 0,
+            3 => entity.trip_update = Some(trip_update(as_bytes(&value)?)?),
+            5 => entity.alert = Some(alert(as_bytes(&value)?)?),
+            _ => {}
+        }
+    }
+    Ok(entity)
+}
+
+fn trip_descriptor(bytes: &[u8]) -> Result<TripDescriptor> {
+    let mut trip = TripDescriptor::default();
+    for (number, value) in fields(bytes)? {
+        match number {
+            1 => trip.trip_id = Some(as_string(&value)?),
+            3 => trip.start_date = Some(as_string(&value)?),
+            4 => trip.schedule_relationship = as_varint(&value)?,
+            5 => trip.route_id = Some(as_string(&value)?),
+            _ => {}
+        }
+    }
+    Ok(trip)
+}
+
+fn trip_update(bytes: &[u8]) -> Result<TripUpdate> {
+    let mut update = TripUpdate::default();
+    for (number, value) in fields(bytes)? {
+        match number {
+            1 => update.trip = trip_descriptor(as_bytes(&value)?)?,
+            2 => update
+                .stop_time_updates
+                .push(stop_time_update(as_bytes(&value)?)?),
+            5 => update.delay = Some(as_int(&value)?),
+            _ => {}
+        }
+    }
+    Ok(update)
+}
+
+fn stop_time_event(bytes: &[u8]) -> Result<StopTimeEvent> {
+    let mut event = StopTimeEvent::default();
+    for (number, value) in fields(bytes)? {
+        match number {
+            1 => event.delay = Some(as_int(&value)?),
+            2 => event.time = Some(as_int(&value)?),
+            _ => {}
+        }
+    }
+    Ok(event)
+}
+
+fn stop_time_update(bytes: &[u8]) -> Result<StopTimeUpdate> {
+    let mut update = StopTimeUpdate::default();
+    for (number, value) in fields(bytes)? {
+        match number {
+            1 => update.stop_sequence = Some(as_varint(&value)? as u32),
+            2 => update.arrival = Some(stop_time_event(as_bytes(&value)?)?),
+            3 => update.departure = Some(stop_time_event(as_bytes(&value)?)?),
+            4 => update.stop_id = Some(as_string(&value)?),
+            5 => update.schedule_relationship = as_varint(&value)?,
+            _ => {}
+        }
+    }
+    Ok(update)
+}
+
+fn entity_selector(bytes: &[u8]) -> Result<EntitySelector> {
+    let mut selector = EntitySelector::default();
+    for (number, value) in fields(bytes)? {
+        match number {
+            1 => selector.agency_id = Some(as_string(&value)?),
+            2 => selector.route_id = Some(as_string(&value)?),
+            4 => selector.trip = Some(trip_descriptor(as_bytes(&value)?)?),
+            5 => selector.stop_id = Some(as_string(&value)?),
+            _ => {}
+        }
+    }
+    Ok(selector)
+}
+
+// The text of a `TranslatedString`, the one without language if any,
+// or else the first one.
+fn translated_string(bytes: &[u8]) -> Result<Option<String>> {
+    let mut texts = vec![];
+    for (number, value) in fields(bytes)? {
+        if number != 1 {
+            continue;
+        }
+        let mut text = None;
+        let mut language = None;
+        for (number, value) in fields(as_bytes(&value)?)? {
+            match number {
+                1 => text = Some(as_string(&value)?),
+                2 => language = Some(as_string(&value)?),
+                _ => {}
+            }
+        }
+        if let Some(text) = text {
+            texts.push((language.is_some(), text));
+        }
+    }
+    texts.sort_by_key(|&(has_language, _)| has_language);
+    Ok(texts.into_iter().next().map(|(_, text)| text))
+}
+
+fn alert(bytes: &[u8]) -> Result<Alert> {
+    let mut alert = Alert::default();
+    for (number, value) in fields(bytes)? {
+        match number {
+            1 => {
+                let mut period = (None, None);
+                for (number, value) in fields(as_bytes(&value)?)? {
+                    match number {
+                        1 => period.0 = Some(as_varint(&value)?),
+                        2 => period.1 = Some(as_varint(&value)?),
+                        _ => {}
+                    }
+                }
+                alert.active_periods.push(period);
+            }
+            5 => alert
+                .informed_entities
+                .push(entity_selector(as_bytes(&value)?)?),
+            6 => alert.cause = Some(as_varint(&value)?),
+            7 => alert.effect = Some(as_varint(&value)?),
+            8 => alert.url = translated_string(as_bytes(&value)?)?,
+            10 => alert.header_text = translated_string(as_bytes(&value)?)?,
+            11 => alert.description_text = translated_string(as_bytes(&value)?)?,
+            _ => {}
+        }
+    }
+    Ok(alert)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_trip_update() {
+        // a trip update of the trip "T1" with a delay of -30 seconds
+        // at the stop sequence 2
+        let bytes = [
+            0x0a, 0x02, 0x18, 0x64, // header, timestamp 100
+            0x12, 0x1e, // entity
+            0x0a, 0x01, b'1', // id
+            0x1a, 0x19, // trip update
+            0x0a, 0x04, 0x0a, 0x02, b'T', b'1', // trip
+            0x12, 0x11, 0x08, 0x02, // stop time update, sequence 2
+            0x12, 0x0b, 0x08, 0xe2, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01, 0x28,
+            0x01, // skipped
+        ];
+        let feed = decode(&bytes).unwrap();
+        assert_eq!(Some(100), feed.timestamp);
+        let update = feed.entities[0].trip_update.as_ref().unwrap();
+        assert_eq!(Some("T1".to_string()), update.trip.trip_id);
+        let stop_time_update = &update.stop_time_updates[0];
+        assert_eq!(Some(2), stop_time_update.stop_sequence);
+        assert_eq!(1, stop_time_update.schedule_relationship);
+        let arrival = stop_time_update.arrival.as_ref().unwrap();
+        assert_eq!(Some(-30), arrival.delay);
+
+        assert!(decode(&bytes[..10]).is_err());
+    }
+}
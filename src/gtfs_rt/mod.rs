@@ -0,0 +1,397 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! [GTFS-Realtime](https://developers.google.com/transit/gtfs-realtime/)
+//! feeds, resolved against the vehicle journeys and the stop points
+//! of a `Model`.
+
+mod feed;
+
+use chrono::{FixedOffset, NaiveDate, TimeZone};
+use collection::Idx;
+use failure::ResultExt;
+use model::Model;
+use objects::{Date, Line, Network, StopPoint, Time, VehicleJourney};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use Result;
+
+/// The state of a vehicle journey given by the feed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TripStatus {
+    /// The vehicle journey runs, with the updated stop times.
+    Scheduled,
+    /// The vehicle journey does not run on the date.
+    Cancelled,
+    /// The vehicle journey is not in the model.
+    Added,
+}
+
+/// A stop time of an updated vehicle journey.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdatedStopTime {
+    /// The stop point of the stop time.
+    pub stop_point_idx: Idx<StopPoint>,
+    /// The sequence of the stop time in the vehicle journey.
+    pub sequence: u32,
+    /// The arrival time, delays included.
+    pub arrival_time: Time,
+    /// The departure time, delays included.
+    pub departure_time: Time,
+    /// Whether the vehicle does not stop.
+    pub skipped: bool,
+}
+
+/// The real time state of a vehicle journey on a date.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TripUpdate {
+    /// The vehicle journey of the model, `None` for an added trip.
+    pub vehicle_journey_idx: Option<Idx<VehicleJourney>>,
+    /// The id of the trip in the feed.
+    pub trip_id: String,
+    /// The date of the trip.
+    pub date: Date,
+    /// Whether the trip runs, is cancelled or added.
+    pub status: TripStatus,
+    /// The stop times of the trip, empty for a cancelled trip.
+    pub stop_times: Vec<UpdatedStopTime>,
+}
+
+/// A service alert, with the objects of the model it concerns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Disruption {
+    /// The id of the feed entity.
+    pub id: String,
+    /// The cause, as `STRIKE`.
+    pub cause: Option<String>,
+    /// The effect, as `NO_SERVICE`.
+    pub effect: Option<String>,
+    /// The header of the alert.
+    pub header: Option<String>,
+    /// The description of the alert.
+    pub description: Option<String>,
+    /// The url of more information.
+    pub url: Option<String>,
+    /// The periods of the alert, as POSIX times, a missing bound
+    /// meaning an infinite period.
+    pub periods: Vec<(Option<u64>, Option<u64>)>,
+    /// The networks concerned, given by their agency.
+    pub networks: Vec<Idx<Network>>,
+    /// The lines concerned, given by their route.
+    pub lines: Vec<Idx<Line>>,
+    /// The vehicle journeys concerned.
+    pub vehicle_journeys: Vec<Idx<VehicleJourney>>,
+    /// The stop points concerned.
+    pub stop_points: Vec<Idx<StopPoint>>,
+}
+
+/// The updates of a GTFS-RT feed, resolved against a model.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RealtimeUpdates {
+    /// The time of the feed, as POSIX time.
+    pub timestamp: Option<u64>,
+    /// The updated, cancelled and added vehicle journeys.
+    pub trip_updates: Vec<TripUpdate>,
+    /// The service alerts.
+    pub disruptions: Vec<Disruption>,
+}
+
+const CAUSES: &[&str] = &[
+    "UNKNOWN_CAUSE",
+    "OTHER_CAUSE",
+    "TECHNICAL_PROBLEM",
+    "STRIKE",
+    "DEMONSTRATION",
+    "ACCIDENT",
+    "HOLIDAY",
+    "WEATHER",
+    "MAINTENANCE",
+    "CONSTRUCTION",
+    "POLICE_ACTIVITY",
+    "MEDICAL_EMERGENCY",
+];
+
+const EFFECTS: &[&str] = &[
+    "NO_SERVICE",
+    "REDUCED_SERVICE",
+    "SIGNIFICANT_DELAYS",
+    "DETOUR",
+    "ADDITIONAL_SERVICE",
+    "MODIFIED_SERVICE",
+    "OTHER_EFFECT",
+    "UNKNOWN_EFFECT",
+    "STOP_MOVED",
+    "NO_EFFECT",
+    "ACCESSIBILITY_ISSUE",
+];
+
+// The name of a value of the enumerations, numbered from 1.
+fn enum_name(names: &[&str], value: Option<u64>) -> Option<String> {
+    value
+        .and_then(|v| (v as usize).checked_sub(1))
+        .and_then(|i| names.get(i))
+        .map(|name| name.to_string())
+}
+
+fn seconds(time: Time) -> i64 {
+    i64::from(time.hours() * 3600 + time.minutes() * 60 + time.seconds())
+}
+
+// The time of the seconds since the midnight of the trip date, the
+// times before midnight being brought back to midnight.
+fn time(seconds: i64) -> Time {
+    Time::new(0, 0, seconds.max(0) as u32)
+}
+
+/// Reads the GTFS-RT feed at `path`, like `read`.
+pub fn read_from_path<P: AsRef<Path>>(
+    model: &Model,
+    path: P,
+    utc_offset: FixedOffset,
+) -> Result<RealtimeUpdates> {
+    let path = path.as_ref();
+    info!("Reading GTFS-RT feed from {:?}", path);
+    let mut content = vec![];
+    File::open(path)
+        .and_then(|mut f| f.read_to_end(&mut content))
+        .with_context(ctx_from_path!(path))?;
+    Ok(read(model, &content, utc_offset).with_context(ctx_from_path!(path))?)
+}
+
+/// Reads the trip updates and the alerts of the GTFS-RT protobuf feed
+/// `content`, resolving them against the vehicle journeys and the
+/// stop points of `model` by their id.
+///
+/// The trips of the feed without start date are taken on the date of
+/// the feed, and the absolute times of the feed are converted to
+/// local times with `utc_offset`.  As in GTFS-RT, the delay of a stop
+/// is propagated to the following stops without update.  The
+/// unknown trips and stops are skipped with a warning.
+pub fn read(model: &Model, content: &[u8], utc_offset: FixedOffset) -> Result<RealtimeUpdates> {
+    let feed = feed::decode(content)?;
+    let mut updates = RealtimeUpdates {
+        timestamp: feed.timestamp,
+        ..Default::default()
+    };
+    let feed_date = feed
+        .timestamp
+        .map(|t| utc_offset.timestamp(t as i64, 0).naive_local().date());
+    for entity in feed.entities.iter().filter(|e| !e.is_deleted) {
+        if let Some(ref trip_update) = entity.trip_update {
+            match resolve_trip_update(model, trip_update, feed_date, utc_offset)
+                .with_context(|_| format!("Error reading the trip update {:?}", entity.id))?
+            {
+                Some(update) => updates.trip_updates.push(update),
+                None => warn!("The trip update {:?} is ignored", entity.id),
+            }
+        }
+        if let Some(ref alert) = entity.alert {
+            updates
+                .disruptions
+                .push(resolve_alert(model, &entity.id, alert));
+        }
+    }
+    Ok(updates)
+}
+
+fn resolve_trip_update(
+    model: &Model,
+    trip_update: &feed::TripUpdate,
+    feed_date: Option<Date>,
+    utc_offset: FixedOffset,
+) -> Result<Option<TripUpdate>> {
+    let trip = &trip_update.trip;
+    let trip_id = match trip.trip_id {
+        Some(ref trip_id) => trip_id.clone(),
+        None => {
+            warn!("A trip update has no trip_id");
+            return Ok(None);
+        }
+    };
+    let date = match trip.start_date {
+        Some(ref date) => NaiveDate::parse_from_str(date, "%Y%m%d")
+            .with_context(|_| format!("invalid start_date {:?}", date))?,
+        None => feed_date.ok_or_else(|| format_err!("no start_date nor feed timestamp"))?,
+    };
+    // the POSIX time of the midnight of the date
+    let midnight = utc_offset
+        .from_local_date(&date)
+        .single()
+        .map(|d| d.and_hms(0, 0, 0).timestamp())
+        .ok_or_else(|| format_err!("invalid date {}", date))?;
+    let mut update = TripUpdate {
+        vehicle_journey_idx: None,
+        trip_id: trip_id.clone(),
+        date,
+        status: TripStatus::Scheduled,
+        stop_times: vec![],
+    };
+
+    // the trips added by the feed
+    if trip.schedule_relationship == 1 || trip.schedule_relationship == 2 {
+        update.status = TripStatus::Added;
+        for stu in &trip_update.stop_time_updates {
+            let stop_point_idx = match stu
+                .stop_id
+                .as_ref()
+                .and_then(|id| model.stop_points.get_idx(id))
+            {
+                Some(idx) => idx,
+                None => {
+                    warn!(
+                        "Unknown stop {:?} of the added trip {:?}",
+                        stu.stop_id, trip_id
+                    );
+                    continue;
+                }
+            };
+            let absolute = |event: &Option<feed::StopTimeEvent>| {
+                event.as_ref().and_then(|e| e.time).map(|t| t - midnight)
+            };
+            let arrival = absolute(&stu.arrival).or_else(|| absolute(&stu.departure));
+            let departure = absolute(&stu.departure).or(arrival);
+            let (arrival, departure) = match (arrival, departure) {
+                (Some(arrival), Some(departure)) => (arrival, departure),
+                _ => bail!("no time at the stop {:?} of an added trip", stu.stop_id),
+            };
+            update.stop_times.push(UpdatedStopTime {
+                stop_point_idx,
+                sequence: stu.stop_sequence.unwrap_or(update.stop_times.len() as u32),
+                arrival_time: time(arrival),
+                departure_time: time(departure),
+                skipped: stu.schedule_relationship == 1,
+            });
+        }
+        return Ok(Some(update));
+    }
+
+    let vj_idx = match model.vehicle_journeys.get_idx(&trip_id) {
+        Some(idx) => idx,
+        None => {
+            warn!("The trip {:?} is not found", trip_id);
+            return Ok(None);
+        }
+    };
+    let vj = &model.vehicle_journeys[vj_idx];
+    let runs = model
+        .calendars
+        .get(&vj.service_id)
+        .is_some_and(|c| c.dates.contains(&date));
+    if !runs {
+        warn!("The trip {:?} does not run on {}", trip_id, date);
+        return Ok(None);
+    }
+    update.vehicle_journey_idx = Some(vj_idx);
+    if trip.schedule_relationship == 3 {
+        update.status = TripStatus::Cancelled;
+        return Ok(Some(update));
+    }
+
+    let mut stus = trip_update.stop_time_updates.iter().peekable();
+    let mut delay = trip_update.delay.unwrap_or(0);
+    for st in &vj.stop_times {
+        let stop_id = &model.stop_points[st.stop_point_idx].id;
+        let matches = |stu: &&feed::StopTimeUpdate| match stu.stop_sequence {
+            Some(sequence) => sequence == st.sequence,
+            None => stu.stop_id.as_ref() == Some(stop_id),
+        };
+        let mut skipped = false;
+        let (arrival_delay, departure_delay) = match stus.next_if(matches) {
+            Some(stu) if stu.schedule_relationship == 2 => {
+                delay = 0;
+                (0, 0)
+            }
+            Some(stu) => {
+                skipped = stu.schedule_relationship == 1;
+                let event_delay = |event: &Option<feed::StopTimeEvent>, scheduled: Time| {
+                    event.as_ref().and_then(|e| {
+                        e.delay
+                            .or_else(|| e.time.map(|t| t - midnight - seconds(scheduled)))
+                    })
+                };
+                let arrival_delay = event_delay(&stu.arrival, st.arrival_time).unwrap_or(delay);
+                delay = event_delay(&stu.departure, st.departure_time).unwrap_or(arrival_delay);
+                (arrival_delay, delay)
+            }
+            None => (delay, delay),
+        };
+        update.stop_times.push(UpdatedStopTime {
+            stop_point_idx: st.stop_point_idx,
+            sequence: st.sequence,
+            arrival_time: time(seconds(st.arrival_time) + arrival_delay),
+            departure_time: time(seconds(st.departure_time) + departure_delay),
+            skipped,
+        });
+    }
+    for stu in stus {
+        warn!(
+            "The stop {:?} (sequence {:?}) of the trip {:?} is not found",
+            stu.stop_id, stu.stop_sequence, trip_id
+        );
+    }
+    Ok(Some(update))
+}
+
+fn resolve_alert(model: &Model, id: &str, alert: &feed::Alert) -> Disruption {
+    let mut disruption = Disruption {
+        id: id.to_string(),
+        cause: enum_name(CAUSES, alert.cause),
+        effect: enum_name(EFFECTS, alert.effect),
+        header: alert.header_text.clone(),
+        description: alert.description_text.clone(),
+        url: alert.url.clone(),
+        periods: alert.active_periods.clone(),
+        networks: vec![],
+        lines: vec![],
+        vehicle_journeys: vec![],
+        stop_points: vec![],
+    };
+    for entity in &alert.informed_entities {
+        if let Some(ref agency_id) = entity.agency_id {
+            match model.networks.get_idx(agency_id) {
+                Some(idx) => disruption.networks.push(idx),
+                None => warn!("Unknown agency {:?} in the alert {:?}", agency_id, id),
+            }
+        }
+        if let Some(ref route_id) = entity.route_id {
+            // the GTFS routes are the lines of the model
+            let line_idx = model.lines.get_idx(route_id).or_else(|| {
+                model
+                    .routes
+                    .get(route_id)
+                    .and_then(|r| model.lines.get_idx(&r.line_id))
+            });
+            match line_idx {
+                Some(idx) => disruption.lines.push(idx),
+                None => warn!("Unknown route {:?} in the alert {:?}", route_id, id),
+            }
+        }
+        if let Some(trip_id) = entity.trip.as_ref().and_then(|t| t.trip_id.as_ref()) {
+            match model.vehicle_journeys.get_idx(trip_id) {
+                Some(idx) => disruption.vehicle_journeys.push(idx),
+                None => warn!("Unknown trip {:?} in the alert {:?}", trip_id, id),
+            }
+        }
+        if let Some(ref stop_id) = entity.stop_id {
+            match model.stop_points.get_idx(stop_id) {
+                Some(idx) => disruption.stop_points.push(idx),
+                None => warn!("Unknown stop {:?} in the alert {:?}", stop_id, id),
+            }
+        }
+    }
+    disruption
+}
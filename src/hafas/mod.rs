@@ -0,0 +1,87 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! [HAFAS](https://opentransportdata.swiss/en/cookbook/hafas-rohdaten-format-hrdf/)
+//! raw data format management, the HAFAS Rohdaten of the Swiss and
+//! German national timetables.
+
+mod read;
+
+use self::read::HafasReader;
+use collection::CollectionWithId;
+use model::Model;
+use read_utils::{self, FileHandler, InputHandler};
+use std::path::Path;
+use Result;
+
+/// Imports a `Model` from the HAFAS raw data files, as `BAHNHOF` and
+/// `FPLAN`, of the `path` directory, zip or tar.gz archive.
+///
+/// The stations of `BAHNHOF`, placed by `BFKOORD_WGS`, become stop
+/// points, each one in its own stop area.  The journeys of `FPLAN`
+/// become vehicle journeys, repeated as many times as their cycle
+/// gives, running on the days of their `BITFELD` bit field from the
+/// first day of `ECKDATEN`.  Their lines are given by their `*L`
+/// line, or else by their `ZUGART` category, and their companies by
+/// the administrations of `BETRIEB`.  Only the first category and
+/// bit field of a journey are used.
+///
+/// The `config_path` argument allows you to give a path to a file
+/// containing a json representing the contributor and dataset used
+/// for these files. If not given, default values will be created.
+///
+/// The `prefix` argument is a string that will be prepended to every
+/// identifiers, allowing to namespace the dataset. By default, no
+/// prefix will be added to the identifiers.
+pub fn read<P>(path: P, config_path: Option<P>, prefix: Option<String>) -> Result<Model>
+where
+    P: AsRef<Path>,
+{
+    let mut file_handler = InputHandler::from_path(path)?;
+    read_from_handler(&mut file_handler, config_path, prefix)
+}
+
+/// Imports a `Model` from the HAFAS raw data files given by the file
+/// handler, like `read`.
+pub fn read_from_handler<H, P>(
+    file_handler: &mut H,
+    config_path: Option<P>,
+    prefix: Option<String>,
+) -> Result<Model>
+where
+    for<'a> &'a mut H: FileHandler,
+    P: AsRef<Path>,
+{
+    info!("Loading HAFAS from {}", file_handler.source_name());
+    let (contributor, mut dataset) = read_utils::read_config(config_path)?;
+    let mut reader = HafasReader::new(file_handler, &dataset.id)?;
+    reader.read_stations(file_handler)?;
+    reader.read_fplan(file_handler)?;
+    let mut collections = reader.collections;
+
+    let vp = read_utils::get_validity_period(&collections.calendars)
+        .ok_or_else(|| format_err!("No valid calendar in HAFAS data"))?;
+    dataset.start_date = vp.start_date;
+    dataset.end_date = vp.end_date;
+    dataset.system = Some("HAFAS".to_string());
+    collections.contributors = CollectionWithId::new(vec![contributor])?;
+    collections.datasets = CollectionWithId::new(vec![dataset])?;
+    read_utils::add_id_mappings(&mut collections);
+    if let Some(prefix) = prefix {
+        read_utils::add_prefix(prefix, &mut collections)?;
+    }
+    Model::new(collections)
+}
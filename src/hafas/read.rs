@@ -0,0 +1,648 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+use chrono::{self, NaiveDate};
+use failure::ResultExt;
+use model::Collections;
+use objects::{self, CommentLinksT, Coord, Date, KeysValues, StopTime, Time};
+use read_utils::FileHandler;
+use std::collections::{BTreeSet, HashMap};
+use std::io::Read;
+use std::path::PathBuf;
+use Result;
+
+// The bit field of the journeys running every day of the timetable.
+const EVERY_DAY: &str = "000000";
+
+// A stop of a journey, with its arrival and departure times in
+// seconds, and whether the passengers may alight and board.
+struct JourneyStop {
+    stop_id: String,
+    arrival: Option<(u32, bool)>,
+    departure: Option<(u32, bool)>,
+}
+
+// A journey of `FPLAN`, from its `*Z` line to the next one.
+#[derive(Default)]
+struct Journey {
+    number: String,
+    administration: String,
+    repetitions: u32,
+    interval: u32,
+    category: Option<String>,
+    bitfield: Option<String>,
+    line: Option<String>,
+    direction: Option<String>,
+    stops: Vec<JourneyStop>,
+}
+
+// The lines of the file without their `%` comments, `None` if the
+// file does not exist.
+fn read_lines<H>(file_handler: &mut H, name: &str) -> Result<Option<(Vec<String>, PathBuf)>>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let (reader, path) = file_handler.get_file_if_exists(name)?;
+    let mut reader = match reader {
+        Some(reader) => reader,
+        None => return Ok(None),
+    };
+    info!("Reading {}", name);
+    let mut content = vec![];
+    reader
+        .read_to_end(&mut content)
+        .with_context(ctx_from_path!(path))?;
+    // the older files are encoded in ISO-8859-1, whose bytes are the
+    // code points of the characters
+    let content = String::from_utf8(content)
+        .unwrap_or_else(|e| e.into_bytes().into_iter().map(char::from).collect());
+    let lines = content
+        .lines()
+        .map(|line| line.split('%').next().unwrap_or_default().to_string())
+        .collect();
+    Ok(Some((lines, path)))
+}
+
+fn read_required_lines<H>(file_handler: &mut H, name: &str) -> Result<(Vec<String>, PathBuf)>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    read_lines(file_handler, name)?.ok_or_else(|| format_err!("file {:?} not found", name))
+}
+
+// The trimmed text of the `start` to `end` columns of the line,
+// counted from 1 as in the format specification.
+fn column(line: &str, start: usize, end: usize) -> String {
+    line.chars()
+        .skip(start - 1)
+        .take(end + 1 - start)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+// Parses a `HHHMM` time as seconds, a leading `-` meaning that the
+// passengers may not alight or board.
+fn parse_time(time: &str) -> Result<Option<(u32, bool)>> {
+    if time.is_empty() {
+        return Ok(None);
+    }
+    let (allowed, digits) = match time.strip_prefix('-') {
+        Some(digits) => (false, digits),
+        None => (true, time),
+    };
+    let value: u32 = digits
+        .parse()
+        .map_err(|_| format_err!("invalid time {:?}", time))?;
+    ensure!(value % 100 < 60, "invalid time {:?}", time);
+    Ok(Some(((value / 100 * 60 + value % 100) * 60, allowed)))
+}
+
+// The days of the bit field given in hexadecimal, the first bit
+// being the first day of the timetable.
+fn bitfield_dates(bitfield: &str, start: Date, end: Date) -> Result<BTreeSet<Date>> {
+    let mut dates = BTreeSet::new();
+    for (i, c) in bitfield.chars().enumerate() {
+        let digit = c
+            .to_digit(16)
+            .ok_or_else(|| format_err!("invalid bit field {:?}", bitfield))?;
+        for bit in 0..4 {
+            if digit & (8 >> bit) != 0 {
+                let date = start + chrono::Duration::days(4 * i as i64 + bit);
+                if date <= end {
+                    dates.insert(date);
+                }
+            }
+        }
+    }
+    Ok(dates)
+}
+
+// The physical mode of a product class of `ZUGART`, as numbered by
+// the Swiss timetable.
+fn physical_mode(class: &str) -> (&'static str, &'static str) {
+    match class {
+        "0" | "1" | "2" => ("LongDistanceTrain", "Long distance train"),
+        "3" | "8" => ("LocalTrain", "Local train"),
+        "4" => ("Ferry", "Ferry"),
+        "5" => ("RapidTransit", "Rapid transit"),
+        "7" => ("SuspendedCableCar", "Suspended cable car"),
+        "9" => ("Tramway", "Tramway"),
+        _ => ("Bus", "Bus"),
+    }
+}
+
+pub struct HafasReader {
+    dataset_id: String,
+    start_date: Date,
+    end_date: Date,
+    bitfields: HashMap<String, String>,
+    // the physical modes of the categories
+    categories: HashMap<String, (&'static str, &'static str)>,
+    // the names of the companies, by their administration
+    companies: HashMap<String, String>,
+    pub collections: Collections,
+}
+
+impl HafasReader {
+    /// Reads the timetable period, the bit fields, the categories
+    /// and the companies.
+    pub fn new<H>(file_handler: &mut H, dataset_id: &str) -> Result<Self>
+    where
+        for<'a> &'a mut H: FileHandler,
+    {
+        let (lines, path) = read_required_lines(file_handler, "ECKDATEN")?;
+        let dates = lines
+            .iter()
+            .filter(|line| !line.starts_with('*') && !line.trim().is_empty())
+            .take(2)
+            .map(|line| {
+                let date = column(line, 1, 10);
+                NaiveDate::parse_from_str(&date, "%d.%m.%Y")
+                    .with_context(|_| format!("invalid date {:?} in {:?}", date, path))
+            })
+            .collect::<::std::result::Result<Vec<_>, _>>()?;
+        ensure!(dates.len() == 2, "no timetable period in {:?}", path);
+
+        let mut bitfields = HashMap::new();
+        if let Some((lines, _)) = read_lines(file_handler, "BITFELD")? {
+            for line in lines.iter().filter(|l| !l.trim().is_empty()) {
+                bitfields.insert(column(line, 1, 6), column(line, 8, 103));
+            }
+        }
+
+        let mut categories = HashMap::new();
+        let (lines, _) = read_required_lines(file_handler, "ZUGART")?;
+        // the texts of the categories follow their definitions
+        for line in lines.iter().take_while(|l| !l.starts_with('<')) {
+            let code = column(line, 1, 3);
+            if !code.is_empty() {
+                categories.insert(code, physical_mode(&column(line, 5, 6)));
+            }
+        }
+
+        let mut names = HashMap::new();
+        let mut administrations = vec![];
+        let betrieb = match read_lines(file_handler, "BETRIEB_DE")? {
+            Some(betrieb) => Some(betrieb),
+            None => read_lines(file_handler, "BETRIEB")?,
+        };
+        for line in betrieb.map(|(lines, _)| lines).unwrap_or_default() {
+            let operator = column(&line, 1, 5);
+            let rest = line.chars().skip(6).collect::<String>();
+            let rest = rest.trim();
+            if let Some(codes) = rest.strip_prefix(':') {
+                for code in codes.split_whitespace() {
+                    administrations.push((code.to_string(), operator.clone()));
+                }
+            } else {
+                // the short name `K`, the abbreviation `L` and the
+                // long name `V`, each one quoted
+                let parts: Vec<_> = rest.split('"').collect();
+                let mut name = None;
+                for pair in parts.chunks(2).filter(|p| p.len() == 2) {
+                    match pair[0].trim() {
+                        "V" => name = Some(pair[1].to_string()),
+                        "K" | "L" if name.is_none() => name = Some(pair[1].to_string()),
+                        _ => {}
+                    }
+                }
+                if let Some(name) = name {
+                    names.insert(operator, name);
+                }
+            }
+        }
+        let companies = administrations
+            .into_iter()
+            .filter_map(|(code, operator)| names.get(&operator).map(|n| (code, n.clone())))
+            .collect();
+
+        Ok(HafasReader {
+            dataset_id: dataset_id.to_string(),
+            start_date: dates[0],
+            end_date: dates[1],
+            bitfields,
+            categories,
+            companies,
+            collections: Collections::default(),
+        })
+    }
+
+    /// Reads the stations of `BAHNHOF` as stop points, each one in its
+    /// own stop area, placed by `BFKOORD_WGS`.
+    pub fn read_stations<H>(&mut self, file_handler: &mut H) -> Result<()>
+    where
+        for<'a> &'a mut H: FileHandler,
+    {
+        let mut coords = HashMap::new();
+        if let Some((lines, path)) = read_lines(file_handler, "BFKOORD_WGS")? {
+            for line in lines {
+                let fields: Vec<_> = line.split_whitespace().collect();
+                if fields.len() < 3 {
+                    continue;
+                }
+                let coordinate = |s: &str| {
+                    s.parse::<f64>()
+                        .with_context(|_| format!("invalid coordinate {:?} in {:?}", s, path))
+                };
+                let coord = Coord {
+                    lon: coordinate(fields[1])?,
+                    lat: coordinate(fields[2])?,
+                };
+                coords.insert(fields[0].to_string(), coord);
+            }
+        }
+        let (lines, _) = read_required_lines(file_handler, "BAHNHOF")?;
+        for line in lines {
+            let id = column(&line, 1, 7);
+            if id.is_empty() {
+                continue;
+            }
+            // the other names follow the `$` of the official one
+            let name = column(&line, 13, 62);
+            let name = name
+                .split('$')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+            let coord = coords.get(&id).cloned().unwrap_or_else(|| {
+                warn!("The station {:?} has no coordinates", id);
+                Coord { lon: 0., lat: 0. }
+            });
+            let stop_area_id = format!("Navitia:{}", id);
+            self.collections.stop_areas.push(objects::StopArea {
+                id: stop_area_id.clone(),
+                name: name.clone(),
+                codes: KeysValues::default(),
+                object_properties: KeysValues::default(),
+                comment_links: CommentLinksT::default(),
+                visible: true,
+                coord,
+                timezone: None,
+                geometry_id: None,
+                equipment_id: None,
+            })?;
+            self.collections.stop_points.push(objects::StopPoint {
+                id,
+                name,
+                codes: KeysValues::default(),
+                object_properties: KeysValues::default(),
+                comment_links: CommentLinksT::default(),
+                visible: true,
+                coord,
+                stop_area_id: stop_area_id.into(),
+                timezone: None,
+                geometry_id: None,
+                equipment_id: None,
+                fare_zone_id: None,
+                address_id: None,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Reads the journeys of `FPLAN` as vehicle journeys.
+    pub fn read_fplan<H>(&mut self, file_handler: &mut H) -> Result<()>
+    where
+        for<'a> &'a mut H: FileHandler,
+    {
+        let (lines, path) = read_required_lines(file_handler, "FPLAN")?;
+        let mut journey = None;
+        for (i, line) in lines.iter().enumerate() {
+            self.read_fplan_line(line, &mut journey)
+                .with_context(|_| format!("Error reading line {} of {:?}", i + 1, path))?;
+        }
+        if let Some(journey) = journey {
+            self.add_journey(journey)?;
+        }
+        Ok(())
+    }
+
+    fn read_fplan_line(&mut self, line: &str, journey: &mut Option<Journey>) -> Result<()> {
+        if line.starts_with("*Z") {
+            if let Some(journey) = journey.take() {
+                self.add_journey(journey)?;
+            }
+            let number = |start, end| -> Result<u32> {
+                let value = column(line, start, end);
+                if value.is_empty() {
+                    return Ok(0);
+                }
+                value
+                    .parse()
+                    .map_err(|_| format_err!("invalid number {:?}", value))
+            };
+            *journey = Some(Journey {
+                number: column(line, 4, 9),
+                administration: column(line, 11, 16),
+                repetitions: number(23, 25)?,
+                interval: number(27, 29)?,
+                ..Default::default()
+            });
+            return Ok(());
+        }
+        if line.trim().is_empty() {
+            return Ok(());
+        }
+        let journey = journey
+            .as_mut()
+            .ok_or_else(|| format_err!("no *Z line before {:?}", line))?;
+        if line.starts_with("*G") {
+            if journey.category.is_none() {
+                journey.category = Some(column(line, 4, 6));
+            }
+        } else if line.starts_with("*A VE") {
+            if journey.bitfield.is_none() {
+                journey.bitfield = Some(column(line, 23, 28));
+            }
+        } else if line.starts_with("*L") {
+            journey.line = Some(column(line, 4, 11));
+        } else if line.starts_with("*R") {
+            journey.direction = Some(column(line, 4, 4));
+        } else if !line.starts_with('*') {
+            let arrival = parse_time(&column(line, 30, 35))?;
+            let departure = parse_time(&column(line, 37, 42))?;
+            // the stations passed through have no times
+            if arrival.is_some() || departure.is_some() {
+                journey.stops.push(JourneyStop {
+                    stop_id: column(line, 1, 7),
+                    arrival,
+                    departure,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn add_journey(&mut self, journey: Journey) -> Result<()> {
+        let base_id = format!("{}:{}", journey.administration, journey.number);
+        if journey.stops.len() < 2 {
+            warn!(
+                "The journey {:?} has less than 2 stops, it is ignored",
+                base_id
+            );
+            return Ok(());
+        }
+        let service_id = match journey.bitfield {
+            Some(ref bitfield) if !bitfield.is_empty() => bitfield.clone(),
+            _ => EVERY_DAY.to_string(),
+        };
+        if !self.calendar(&service_id)? {
+            warn!("The journey {:?} never runs, it is ignored", base_id);
+            return Ok(());
+        }
+        let category = journey
+            .category
+            .clone()
+            .ok_or_else(|| format_err!("The journey {:?} has no category", base_id))?;
+        let physical_mode_id = self.modes(&category)?;
+        let company_id = self.company(&journey.administration)?;
+        let route_id = self.route(&journey, &category, &company_id)?;
+
+        let mut stops = vec![];
+        for stop in &journey.stops {
+            let stop_point_idx = self
+                .collections
+                .stop_points
+                .get_idx(&stop.stop_id)
+                .ok_or_else(|| format_err!("The station {:?} is not found", stop.stop_id))?;
+            stops.push((stop_point_idx, stop));
+        }
+
+        // the journey is repeated every `interval` minutes
+        for repetition in 0..=journey.repetitions {
+            let mut id = base_id.clone();
+            let mut n = 0;
+            while self.collections.vehicle_journeys.get(&id).is_some() {
+                n += 1;
+                id = format!("{}:{}", base_id, n);
+            }
+            let offset = repetition * journey.interval * 60;
+            let stop_times = stops
+                .iter()
+                .enumerate()
+                .map(|(i, &(stop_point_idx, stop))| {
+                    let arrival = stop.arrival.or(stop.departure).unwrap_or_default();
+                    let departure = stop.departure.or(stop.arrival).unwrap_or_default();
+                    StopTime {
+                        stop_point_idx,
+                        sequence: i as u32,
+                        arrival_time: Time::new(0, 0, arrival.0 + offset),
+                        departure_time: Time::new(0, 0, departure.0 + offset),
+                        boarding_duration: 0,
+                        alighting_duration: 0,
+                        pickup_type: if stop.departure.is_none_or(|d| d.1) {
+                            0
+                        } else {
+                            1
+                        },
+                        drop_off_type: if stop.arrival.is_none_or(|a| a.1) {
+                            0
+                        } else {
+                            1
+                        },
+                        datetime_estimated: false,
+                        local_zone_id: None,
+                        extras: None,
+                    }
+                })
+                .collect();
+            self.collections
+                .vehicle_journeys
+                .push(objects::VehicleJourney {
+                    id,
+                    route_id: route_id.clone().into(),
+                    physical_mode_id: physical_mode_id.clone(),
+                    dataset_id: self.dataset_id.clone(),
+                    service_id: service_id.clone().into(),
+                    company_id: company_id.clone(),
+                    stop_times,
+                    ..Default::default()
+                })?;
+        }
+        Ok(())
+    }
+
+    // Creates the calendar of the bit field if needed, and returns
+    // whether it has any date.
+    fn calendar(&mut self, bitfield: &str) -> Result<bool> {
+        if let Some(calendar) = self.collections.calendars.get(bitfield) {
+            return Ok(!calendar.dates.is_empty());
+        }
+        let dates = if bitfield == EVERY_DAY {
+            let days = (self.end_date - self.start_date).num_days();
+            (0..=days)
+                .map(|i| self.start_date + chrono::Duration::days(i))
+                .collect()
+        } else {
+            let hexadecimal = self
+                .bitfields
+                .get(bitfield)
+                .ok_or_else(|| format_err!("The bit field {:?} is not found", bitfield))?;
+            bitfield_dates(hexadecimal, self.start_date, self.end_date)?
+        };
+        let has_dates = !dates.is_empty();
+        self.collections.calendars.push(objects::Calendar {
+            id: bitfield.to_string(),
+            dates,
+        })?;
+        Ok(has_dates)
+    }
+
+    // Creates the modes of the category if needed, and returns the id
+    // of the physical mode.
+    fn modes(&mut self, category: &str) -> Result<String> {
+        let (id, name) = self
+            .categories
+            .get(category)
+            .cloned()
+            .unwrap_or_else(|| physical_mode(""));
+        if self.collections.physical_modes.get(id).is_none() {
+            self.collections
+                .physical_modes
+                .push(objects::PhysicalMode {
+                    id: id.to_string(),
+                    name: name.to_string(),
+                    co2_emission: None,
+                })?;
+        }
+        if self.collections.commercial_modes.get(category).is_none() {
+            self.collections
+                .commercial_modes
+                .push(objects::CommercialMode {
+                    id: category.to_string(),
+                    name: category.to_string(),
+                })?;
+        }
+        Ok(id.to_string())
+    }
+
+    // Creates the company and the network of the administration if
+    // needed, and returns their id.
+    fn company(&mut self, administration: &str) -> Result<String> {
+        if self.collections.companies.get(administration).is_none() {
+            let name = self
+                .companies
+                .get(administration)
+                .cloned()
+                .unwrap_or_else(|| administration.to_string());
+            self.collections.companies.push(objects::Company {
+                id: administration.to_string(),
+                name: name.clone(),
+                ..Default::default()
+            })?;
+            self.collections.networks.push(objects::Network {
+                id: administration.to_string(),
+                name,
+                url: None,
+                codes: KeysValues::default(),
+                timezone: Some("Europe/Zurich".to_string()),
+                lang: None,
+                phone: None,
+                address: None,
+                sort_order: None,
+            })?;
+        }
+        Ok(administration.to_string())
+    }
+
+    // Creates the line and the route of the journey if needed, the
+    // route being given by the first and last stops, and returns the
+    // id of the route.
+    fn route(&mut self, journey: &Journey, category: &str, company_id: &str) -> Result<String> {
+        let code = journey.line.clone().unwrap_or_else(|| category.to_string());
+        let line_id = format!("{}:{}", company_id, code);
+        if self.collections.lines.get(&line_id).is_none() {
+            self.collections.lines.push(objects::Line {
+                id: line_id.clone(),
+                code: Some(code.clone()),
+                codes: KeysValues::default(),
+                object_properties: KeysValues::default(),
+                comment_links: CommentLinksT::default(),
+                name: code,
+                forward_name: None,
+                forward_direction: None,
+                backward_name: None,
+                backward_direction: None,
+                color: None,
+                text_color: None,
+                sort_order: None,
+                network_id: company_id.into(),
+                commercial_mode_id: category.to_string(),
+                geometry_id: None,
+                opening_time: None,
+                closing_time: None,
+            })?;
+        }
+        let first = &journey.stops[0].stop_id;
+        let last = &journey.stops[journey.stops.len() - 1].stop_id;
+        let route_id = format!("{}:{}:{}", line_id, first, last);
+        if self.collections.routes.get(&route_id).is_none() {
+            let name = self
+                .collections
+                .stop_points
+                .get(last)
+                .map(|sp| sp.name.clone())
+                .unwrap_or_default();
+            self.collections.routes.push(objects::Route {
+                id: route_id.clone(),
+                name,
+                direction_type: match journey.direction.as_deref() {
+                    Some("H") => Some("forward".to_string()),
+                    Some("R") => Some("backward".to_string()),
+                    _ => None,
+                },
+                codes: KeysValues::default(),
+                object_properties: KeysValues::default(),
+                comment_links: CommentLinksT::default(),
+                line_id: line_id.into(),
+                geometry_id: None,
+                destination_id: None,
+                continuous_pickup: None,
+                continuous_drop_off: None,
+            })?;
+        }
+        Ok(route_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn times() {
+        assert_eq!(Some((28_800, true)), parse_time("00800").unwrap());
+        assert_eq!(Some((91_920, false)), parse_time("-02532").unwrap());
+        assert_eq!(None, parse_time("").unwrap());
+        assert!(parse_time("00870").is_err());
+        assert!(parse_time("8h00").is_err());
+    }
+
+    #[test]
+    fn bitfields() {
+        let start = Date::from_ymd(2018, 6, 1);
+        let end = Date::from_ymd(2018, 6, 5);
+        let dates: Vec<_> = bitfield_dates("A3", start, end)
+            .unwrap()
+            .into_iter()
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(vec!["2018-06-01", "2018-06-03"], dates);
+        assert!(bitfield_dates("G0", start, end).is_err());
+    }
+}
@@ -20,40 +20,87 @@
 
 #![deny(missing_docs)]
 
+extern crate bincode;
 extern crate chrono;
 extern crate csv;
+extern crate env_logger;
 #[macro_use]
 extern crate derivative;
 #[macro_use]
 extern crate failure;
+extern crate flate2;
+#[cfg(feature = "async")]
+extern crate futures;
 #[macro_use]
 extern crate get_corresponding_derive;
 #[macro_use]
 extern crate log;
+extern crate reqwest;
 extern crate serde;
+#[cfg(feature = "parallel")]
+extern crate rayon;
+#[macro_use]
+extern crate structopt;
+extern crate tar;
+#[cfg(test)]
+extern crate tempdir;
+#[cfg(feature = "async")]
+extern crate tokio;
 extern crate walkdir;
 extern crate zip;
 #[macro_use]
 extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
 extern crate geo_types;
+extern crate hex;
+#[cfg(feature = "s3")]
+extern crate hmac;
+extern crate sha2;
 extern crate wkt;
 
 #[macro_use]
 pub(crate) mod utils;
+pub mod apply_rules;
+pub mod blocks;
+mod checksum;
+pub mod cli;
 pub mod collection;
+pub mod currency;
 pub(crate) mod common_format;
+pub mod consolidation;
+pub mod diff;
+pub mod duplicates;
+pub mod equipments;
+pub mod export;
+pub mod fare_zones;
+pub mod filter;
+pub mod frequencies;
+pub mod geometries;
 pub mod gtfs;
+pub mod gtfs_rt;
+pub mod hafas;
+pub mod merge;
 pub mod model;
 pub mod netex;
 pub mod ntfs;
 pub mod objects;
-mod read_utils;
+pub mod overnight;
+pub mod progress;
+pub mod read_utils;
 pub mod relations;
+pub mod report;
+pub mod statistics;
+pub mod siri;
 pub mod transfers;
+pub mod transxchange;
+pub mod validator;
+pub mod vdv452;
 /// The error type used by the crate.
 pub type Error = failure::Error;
 
 /// The corresponding result type used by the crate.
 pub type Result<T> = std::result::Result<T, Error>;
 
+pub use merge::merge;
 pub use model::Model;
@@ -0,0 +1,127 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! Merge of several NTFS datasets into one, as the datasets of the
+//! operators of a region.
+
+use duplicates::{self, StopMatching};
+use failure::ResultExt;
+use model::{Collections, MergeStrategies, MergeStrategy, Model};
+use ntfs;
+use objects::PrefixConfiguration;
+use std::collections::BTreeSet;
+use std::path::Path;
+use Result;
+
+/// The options of `merge`.
+#[derive(Debug, Clone)]
+pub struct MergeOptions {
+    /// The prefix of the identifiers of each dataset, in the order of
+    /// the merged datasets, `None` to keep the identifiers of a
+    /// dataset.  Empty to prefix none of them.
+    pub prefixes: Vec<Option<String>>,
+    /// The separator between the prefixes and the identifiers.
+    pub prefix_separator: String,
+    /// The collections whose identifiers are shared by the datasets,
+    /// and thus never prefixed, as `physical_modes`.
+    pub unprefixed_collections: BTreeSet<String>,
+    /// How the identifier collisions are handled, after the
+    /// prefixing.  The calendars, the geometries and the fare zones
+    /// are never prefixed (see `Collections::add_prefix`), their
+    /// collisions being handled by their strategies.
+    pub strategies: MergeStrategies,
+    /// The criteria of the stops of several datasets merged as the
+    /// same station, `None` to keep all the stops.
+    pub stop_matching: Option<StopMatching>,
+    /// Whether the trips published by several datasets are removed,
+    /// the one of the first dataset being kept.
+    pub remove_duplicate_trips: bool,
+}
+
+/// The physical and commercial modes are shared by the datasets,
+/// their first occurrence being kept, as well as the first occurrence
+/// of the contributors publishing several datasets.  Any other
+/// identifier collision fails.
+impl Default for MergeOptions {
+    fn default() -> Self {
+        let mut strategies = MergeStrategies::default();
+        for collection in &["contributors", "commercial_modes", "physical_modes"] {
+            strategies
+                .collections
+                .insert(collection.to_string(), MergeStrategy::KeepFirst);
+        }
+        MergeOptions {
+            prefixes: vec![],
+            prefix_separator: ":".to_string(),
+            unprefixed_collections: ["commercial_modes", "physical_modes"]
+                .iter()
+                .map(|c| c.to_string())
+                .collect(),
+            strategies,
+            stop_matching: None,
+            remove_duplicate_trips: false,
+        }
+    }
+}
+
+/// Reads the NTFS datasets of the `paths` directories, prefixes the
+/// identifiers of each one with its prefix and merges them into a
+/// single `Model`, in the order of the paths.
+///
+/// The stops and the trips found in several datasets are then
+/// deduplicated as configured by the options, the duplicate trips
+/// being logged.
+pub fn merge<P: AsRef<Path>>(paths: &[P], options: &MergeOptions) -> Result<Model> {
+    ensure!(
+        options.prefixes.is_empty() || options.prefixes.len() == paths.len(),
+        "{} prefixes given for {} datasets",
+        options.prefixes.len(),
+        paths.len()
+    );
+    let mut collections = Collections::default();
+    for (i, path) in paths.iter().enumerate() {
+        let path = path.as_ref();
+        let mut dataset = ntfs::read(path)?.into_collections();
+        if let Some(Some(prefix)) = options.prefixes.get(i) {
+            let prefix_conf = PrefixConfiguration {
+                separator: options.prefix_separator.clone(),
+                exempt_collections: options.unprefixed_collections.clone(),
+                ..PrefixConfiguration::new(prefix.as_str())
+            };
+            dataset.add_prefix(&prefix_conf)?;
+        }
+        collections
+            .merge_with(dataset, &options.strategies)
+            .with_context(|_| format!("Error merging {:?}", path))?;
+    }
+    if let Some(ref matching) = options.stop_matching {
+        for merged in duplicates::dedupe_stops(&mut collections, matching)? {
+            info!("stop {} merged into stop {}", merged.id, merged.merged_into);
+        }
+    }
+    let duplicates = if options.remove_duplicate_trips {
+        duplicates::remove_duplicate_vehicle_journeys(&mut collections)?
+    } else {
+        duplicates::find_duplicate_vehicle_journeys(&collections)
+    };
+    for duplicate in &duplicates {
+        warn!(
+            "trip {} is a duplicate of trip {}",
+            duplicate.vehicle_journey_id, duplicate.duplicate_of
+        );
+    }
+    Model::new(collections)
+}
@@ -16,12 +16,28 @@
 
 //! Definition of the navitia transit model.
 
-use collection::{Collection, CollectionWithId, Idx};
+use bincode;
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime};
+use collection::{Collection, CollectionWithId, Id, Idx, Interner};
+use currency::{Currency, ExchangeRates};
+use failure::ResultExt;
 use objects::*;
 use relations::{IdxSet, ManyToMany, OneToMany, Relation};
-use std::collections::{BTreeMap, HashMap};
+use statistics::{self, Statistics};
+use std::borrow::Borrow;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::mem;
 use std::ops;
+use std::path::Path;
 use std::result::Result as StdResult;
+use std::str::FromStr;
+use utils::{add_prefix_to_collection, add_prefix_to_collection_with_id};
+use validator::{self, ValidationReport};
 use {Error, Result};
 
 /// The set of collections representing the model.
@@ -31,16 +47,27 @@ use {Error, Result};
 pub struct Collections {
     pub contributors: CollectionWithId<Contributor>,
     pub datasets: CollectionWithId<Dataset>,
+    pub attributions: Collection<Attribution>,
     pub networks: CollectionWithId<Network>,
     pub commercial_modes: CollectionWithId<CommercialMode>,
     pub lines: CollectionWithId<Line>,
+    pub line_groups: CollectionWithId<LineGroup>,
+    pub line_group_links: Collection<LineGroupLink>,
     pub routes: CollectionWithId<Route>,
     pub vehicle_journeys: CollectionWithId<VehicleJourney>,
+    pub frequencies: Collection<Frequency>,
+    pub vehicle_journey_links: Collection<VehicleJourneyLink>,
     pub physical_modes: CollectionWithId<PhysicalMode>,
     pub stop_areas: CollectionWithId<StopArea>,
     pub stop_points: CollectionWithId<StopPoint>,
+    pub entrances: CollectionWithId<Entrance>,
+    pub addresses: CollectionWithId<Address>,
     pub feed_infos: HashMap<String, String>,
     pub calendars: CollectionWithId<Calendar>,
+    pub grid_calendars: CollectionWithId<GridCalendar>,
+    pub grid_exception_dates: Collection<GridExceptionDate>,
+    pub grid_periods: Collection<GridPeriod>,
+    pub grid_rel_calendar_line: Collection<GridRelCalendarLine>,
     pub companies: CollectionWithId<Company>,
     pub comments: CollectionWithId<Comment>,
     pub equipments: CollectionWithId<Equipment>,
@@ -48,56 +75,1223 @@ pub struct Collections {
     pub trip_properties: CollectionWithId<TripProperty>,
     pub geometries: CollectionWithId<Geometry>,
     pub admin_stations: Collection<AdminStation>,
+    pub tickets: CollectionWithId<Ticket>,
+    pub ticket_prices: Collection<TicketPrice>,
+    pub od_rules: Collection<ODRule>,
+    pub ticket_uses: CollectionWithId<TicketUse>,
+    pub ticket_use_perimeters: Collection<TicketUsePerimeter>,
+    pub ticket_use_restrictions: Collection<TicketUseRestriction>,
+    pub fare_zones: CollectionWithId<FareZone>,
+    pub pathways: CollectionWithId<Pathway>,
+    pub levels: CollectionWithId<Level>,
+    pub translations: Collection<Translation>,
+    pub id_mappings: Collection<IdMapping>,
+}
+
+/// How `Collections::merge_with` handles an object whose identifier
+/// is already in the collections.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// Fails on the first identifier collision.
+    #[default]
+    Fail,
+    /// Keeps the object already in the collections.
+    KeepFirst,
+    /// Replaces the object already in the collections by the merged
+    /// one.
+    KeepSecond,
+    /// Keeps both objects, the identifier of the merged one being
+    /// followed by the suffix, as well as all its references.
+    RenameWithSuffix(String),
+}
+
+/// Parses a strategy given as `fail`, `keep-first`, `keep-second` or
+/// `rename:<suffix>`.
+impl FromStr for MergeStrategy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "fail" => MergeStrategy::Fail,
+            "keep-first" => MergeStrategy::KeepFirst,
+            "keep-second" => MergeStrategy::KeepSecond,
+            _ if s.starts_with("rename:") && s.len() > "rename:".len() => {
+                MergeStrategy::RenameWithSuffix(s["rename:".len()..].to_string())
+            }
+            _ => bail!(
+                "invalid merge strategy {:?}, expected fail, keep-first, \
+                 keep-second or rename:<suffix>",
+                s
+            ),
+        })
+    }
+}
+
+impl fmt::Display for MergeStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MergeStrategy::Fail => f.write_str("fail"),
+            MergeStrategy::KeepFirst => f.write_str("keep-first"),
+            MergeStrategy::KeepSecond => f.write_str("keep-second"),
+            MergeStrategy::RenameWithSuffix(ref suffix) => write!(f, "rename:{}", suffix),
+        }
+    }
+}
+
+/// The names of the collections of objects with an identifier, in
+/// which the identifiers may collide during a merge.
+pub const COLLECTIONS_WITH_ID: &[&str] = &[
+    "contributors",
+    "datasets",
+    "networks",
+    "commercial_modes",
+    "lines",
+    "line_groups",
+    "routes",
+    "vehicle_journeys",
+    "physical_modes",
+    "stop_areas",
+    "stop_points",
+    "entrances",
+    "addresses",
+    "calendars",
+    "grid_calendars",
+    "companies",
+    "comments",
+    "equipments",
+    "trip_properties",
+    "geometries",
+    "tickets",
+    "ticket_uses",
+    "fare_zones",
+    "pathways",
+    "levels",
+];
+
+/// The `MergeStrategy` of each collection for
+/// `Collections::merge_with`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MergeStrategies {
+    /// The strategy of the collections without a specific one.
+    pub default: MergeStrategy,
+    /// The strategies by collection name, as `stop_areas` (see
+    /// `COLLECTIONS_WITH_ID`).
+    pub collections: HashMap<String, MergeStrategy>,
+}
+
+impl MergeStrategies {
+    /// Returns the strategy of the collection named `collection`.
+    pub fn get(&self, collection: &str) -> &MergeStrategy {
+        self.collections.get(collection).unwrap_or(&self.default)
+    }
+
+    /// Sets the strategy of the collection named `collection`.  Fails
+    /// if there is no such collection of objects with an identifier.
+    pub fn insert(&mut self, collection: &str, strategy: MergeStrategy) -> Result<()> {
+        ensure!(
+            COLLECTIONS_WITH_ID.contains(&collection),
+            "unknown collection {:?}, expected one of {}",
+            collection,
+            COLLECTIONS_WITH_ID.join(", ")
+        );
+        self.collections.insert(collection.to_string(), strategy);
+        Ok(())
+    }
+}
+
+// Applies `f` on each object of the collection, that may change the
+// identifiers.
+fn update<T: Id<T>, F: FnMut(&mut T)>(collection: &mut CollectionWithId<T>, f: F) -> Result<()> {
+    let mut objects = collection.take();
+    objects.iter_mut().for_each(f);
+    *collection = CollectionWithId::new(objects)?;
+    Ok(())
+}
+
+fn rewrite<S>(id: &mut S, ids: &HashMap<String, String>)
+where
+    S: AsRef<str> + From<String>,
+{
+    if let Some(new_id) = ids.get(id.as_ref()) {
+        *id = new_id.clone().into();
+    }
+}
+
+fn rewrite_opt<S>(id: &mut Option<S>, ids: &HashMap<String, String>)
+where
+    S: AsRef<str> + From<String>,
+{
+    if let Some(new_id) = id.as_ref().and_then(|id| ids.get(id.as_ref())) {
+        *id = Some(new_id.clone().into());
+    }
+}
+
+// The identifiers of `second` already in `first`, with their suffixed
+// version.
+fn rewrite_perimeters(
+    perimeters: &mut Collection<TicketUsePerimeter>,
+    object_type: &ObjectType,
+    ids: &HashMap<String, String>,
+) {
+    for perimeter in perimeters.values_mut() {
+        if perimeter.object_type == *object_type {
+            rewrite(&mut perimeter.object_id, ids);
+        }
+    }
+}
+
+fn rewrite_translations(
+    translations: &mut Collection<Translation>,
+    object_type: &ObjectType,
+    ids: &HashMap<String, String>,
+) {
+    for translation in translations.values_mut() {
+        if translation.object_type == *object_type {
+            rewrite(&mut translation.object_id, ids);
+        }
+    }
+}
+
+fn rewrite_id_mappings(
+    id_mappings: &mut Collection<IdMapping>,
+    collection: &str,
+    ids: &HashMap<String, String>,
+) {
+    for id_mapping in id_mappings.values_mut() {
+        if id_mapping.collection == collection {
+            rewrite(&mut id_mapping.generated_id, ids);
+        }
+    }
+}
+
+fn rewrite_restrictions(
+    restrictions: &mut Collection<TicketUseRestriction>,
+    restriction_type: RestrictionType,
+    ids: &HashMap<String, String>,
+) {
+    for restriction in restrictions.values_mut() {
+        if restriction.restriction_type == restriction_type {
+            rewrite(&mut restriction.use_origin, ids);
+            rewrite(&mut restriction.use_destination, ids);
+        }
+    }
+}
+
+macro_rules! rename_in {
+    ($collection:expr, $ids:expr) => {
+        update(&mut $collection, |o| rewrite(&mut o.id, $ids))?;
+    };
+}
+
+// Renames the objects of the collection `name` of `c` (see
+// `COLLECTIONS_WITH_ID`) whose identifier is a key of `ids` to the
+// associated value, and all the references to them.
+pub(crate) fn rename_ids(
+    c: &mut Collections,
+    name: &str,
+    ids: &HashMap<String, String>,
+) -> Result<()> {
+    match name {
+        "contributors" => {
+            rename_in!(c.contributors, ids);
+            update(&mut c.datasets, |o| rewrite(&mut o.contributor_id, ids))?;
+        }
+        "datasets" => {
+            rename_in!(c.datasets, ids);
+            update(&mut c.vehicle_journeys, |o| rewrite(&mut o.dataset_id, ids))?;
+            for attribution in c.attributions.values_mut() {
+                rewrite(&mut attribution.dataset_id, ids);
+            }
+        }
+        "networks" => {
+            rename_in!(c.networks, ids);
+            update(&mut c.lines, |o| rewrite(&mut o.network_id, ids))?;
+            rewrite_perimeters(&mut c.ticket_use_perimeters, &ObjectType::Network, ids);
+            for attribution in c.attributions.values_mut() {
+                rewrite_opt(&mut attribution.network_id, ids);
+            }
+            rewrite_translations(&mut c.translations, &ObjectType::Network, ids);
+        }
+        "commercial_modes" => {
+            rename_in!(c.commercial_modes, ids);
+            update(&mut c.lines, |o| rewrite(&mut o.commercial_mode_id, ids))?;
+        }
+        "lines" => {
+            rename_in!(c.lines, ids);
+            update(&mut c.routes, |o| rewrite(&mut o.line_id, ids))?;
+            rewrite_perimeters(&mut c.ticket_use_perimeters, &ObjectType::Line, ids);
+            for attribution in c.attributions.values_mut() {
+                rewrite_opt(&mut attribution.line_id, ids);
+            }
+            rewrite_translations(&mut c.translations, &ObjectType::Line, ids);
+            for rel in c.grid_rel_calendar_line.values_mut() {
+                rewrite(&mut rel.line_id, ids);
+            }
+            update(&mut c.line_groups, |o| rewrite(&mut o.main_line_id, ids))?;
+            for link in c.line_group_links.values_mut() {
+                rewrite(&mut link.line_id, ids);
+            }
+        }
+        "line_groups" => {
+            rename_in!(c.line_groups, ids);
+            rewrite_translations(&mut c.translations, &ObjectType::LineGroup, ids);
+            for link in c.line_group_links.values_mut() {
+                rewrite(&mut link.line_group_id, ids);
+            }
+        }
+        "routes" => {
+            rename_in!(c.routes, ids);
+            update(&mut c.vehicle_journeys, |o| rewrite(&mut o.route_id, ids))?;
+            rewrite_translations(&mut c.translations, &ObjectType::Route, ids);
+            rewrite_id_mappings(&mut c.id_mappings, name, ids);
+        }
+        "vehicle_journeys" => {
+            rename_in!(c.vehicle_journeys, ids);
+            for frequency in c.frequencies.values_mut() {
+                rewrite(&mut frequency.vehicle_journey_id, ids);
+            }
+            for link in c.vehicle_journey_links.values_mut() {
+                rewrite(&mut link.from_vehicle_journey_id, ids);
+                rewrite(&mut link.to_vehicle_journey_id, ids);
+            }
+            for attribution in c.attributions.values_mut() {
+                rewrite_opt(&mut attribution.vehicle_journey_id, ids);
+            }
+            rewrite_translations(&mut c.translations, &ObjectType::VehicleJourney, ids);
+        }
+        "physical_modes" => {
+            rename_in!(c.physical_modes, ids);
+            update(&mut c.vehicle_journeys, |o| {
+                rewrite(&mut o.physical_mode_id, ids)
+            })?;
+        }
+        "stop_areas" => {
+            rename_in!(c.stop_areas, ids);
+            update(&mut c.stop_points, |o| rewrite(&mut o.stop_area_id, ids))?;
+            update(&mut c.entrances, |o| rewrite(&mut o.stop_area_id, ids))?;
+            update(&mut c.routes, |o| rewrite_opt(&mut o.destination_id, ids))?;
+            for od_rule in c.od_rules.values_mut() {
+                rewrite(&mut od_rule.origin_stop_area_id, ids);
+                rewrite(&mut od_rule.destination_stop_area_id, ids);
+            }
+            rewrite_restrictions(
+                &mut c.ticket_use_restrictions,
+                RestrictionType::OriginDestination,
+                ids,
+            );
+            for admin_station in c.admin_stations.values_mut() {
+                rewrite(&mut admin_station.stop_id, ids);
+            }
+            rewrite_translations(&mut c.translations, &ObjectType::StopArea, ids);
+            rewrite_id_mappings(&mut c.id_mappings, name, ids);
+        }
+        "stop_points" => {
+            rename_in!(c.stop_points, ids);
+            for transfer in c.transfers.values_mut() {
+                rewrite(&mut transfer.from_stop_id, ids);
+                rewrite(&mut transfer.to_stop_id, ids);
+            }
+            for link in c.vehicle_journey_links.values_mut() {
+                rewrite(&mut link.stop_point_id, ids);
+            }
+            update(&mut c.pathways, |o| {
+                rewrite(&mut o.from_stop_id, ids);
+                rewrite(&mut o.to_stop_id, ids);
+            })?;
+            for admin_station in c.admin_stations.values_mut() {
+                rewrite(&mut admin_station.stop_id, ids);
+            }
+            rewrite_translations(&mut c.translations, &ObjectType::StopPoint, ids);
+        }
+        "addresses" => {
+            rename_in!(c.addresses, ids);
+            update(&mut c.stop_points, |o| rewrite_opt(&mut o.address_id, ids))?;
+        }
+        "entrances" => {
+            rename_in!(c.entrances, ids);
+            update(&mut c.pathways, |o| {
+                rewrite(&mut o.from_stop_id, ids);
+                rewrite(&mut o.to_stop_id, ids);
+            })?;
+        }
+        "calendars" => {
+            rename_in!(c.calendars, ids);
+            update(&mut c.vehicle_journeys, |o| rewrite(&mut o.service_id, ids))?;
+        }
+        "grid_calendars" => {
+            rename_in!(c.grid_calendars, ids);
+            for exception_date in c.grid_exception_dates.values_mut() {
+                rewrite(&mut exception_date.grid_calendar_id, ids);
+            }
+            for period in c.grid_periods.values_mut() {
+                rewrite(&mut period.grid_calendar_id, ids);
+            }
+            for rel in c.grid_rel_calendar_line.values_mut() {
+                rewrite(&mut rel.grid_calendar_id, ids);
+            }
+        }
+        "companies" => {
+            rename_in!(c.companies, ids);
+            update(&mut c.vehicle_journeys, |o| rewrite(&mut o.company_id, ids))?;
+        }
+        "comments" => {
+            // The comments are referred to by their indices.
+            rename_in!(c.comments, ids);
+        }
+        "equipments" => {
+            rename_in!(c.equipments, ids);
+            update(&mut c.stop_areas, |o| rewrite_opt(&mut o.equipment_id, ids))?;
+            update(&mut c.stop_points, |o| {
+                rewrite_opt(&mut o.equipment_id, ids)
+            })?;
+            for transfer in c.transfers.values_mut() {
+                rewrite_opt(&mut transfer.equipment_id, ids);
+            }
+            rewrite_id_mappings(&mut c.id_mappings, name, ids);
+        }
+        "trip_properties" => {
+            rename_in!(c.trip_properties, ids);
+            update(&mut c.vehicle_journeys, |o| {
+                rewrite_opt(&mut o.trip_property_id, ids)
+            })?;
+            rewrite_id_mappings(&mut c.id_mappings, name, ids);
+        }
+        "geometries" => {
+            rename_in!(c.geometries, ids);
+            update(&mut c.lines, |o| rewrite_opt(&mut o.geometry_id, ids))?;
+            update(&mut c.routes, |o| rewrite_opt(&mut o.geometry_id, ids))?;
+            update(&mut c.vehicle_journeys, |o| {
+                rewrite_opt(&mut o.geometry_id, ids)
+            })?;
+            update(&mut c.stop_areas, |o| rewrite_opt(&mut o.geometry_id, ids))?;
+            update(&mut c.stop_points, |o| rewrite_opt(&mut o.geometry_id, ids))?;
+            update(&mut c.fare_zones, |o| rewrite(&mut o.geometry_id, ids))?;
+        }
+        "tickets" => {
+            rename_in!(c.tickets, ids);
+            for ticket_price in c.ticket_prices.values_mut() {
+                rewrite(&mut ticket_price.ticket_id, ids);
+            }
+            for od_rule in c.od_rules.values_mut() {
+                rewrite(&mut od_rule.ticket_id, ids);
+            }
+            update(&mut c.ticket_uses, |o| rewrite(&mut o.ticket_id, ids))?;
+        }
+        "ticket_uses" => {
+            rename_in!(c.ticket_uses, ids);
+            for perimeter in c.ticket_use_perimeters.values_mut() {
+                rewrite(&mut perimeter.ticket_use_id, ids);
+            }
+            for restriction in c.ticket_use_restrictions.values_mut() {
+                rewrite(&mut restriction.ticket_use_id, ids);
+            }
+        }
+        "fare_zones" => {
+            rename_in!(c.fare_zones, ids);
+            update(&mut c.stop_points, |o| {
+                rewrite_opt(&mut o.fare_zone_id, ids)
+            })?;
+            rewrite_restrictions(&mut c.ticket_use_restrictions, RestrictionType::Zone, ids);
+        }
+        "pathways" => {
+            rename_in!(c.pathways, ids);
+        }
+        "levels" => {
+            rename_in!(c.levels, ids);
+        }
+        _ => bail!("unknown collection {:?}", name),
+    }
+    Ok(())
+}
+
+fn collisions<T: Id<T>>(
+    first: &CollectionWithId<T>,
+    second: &CollectionWithId<T>,
+    suffix: &str,
+) -> HashMap<String, String> {
+    second
+        .values()
+        .map(|o| o.id())
+        .filter(|id| first.get(id).is_some())
+        .map(|id| (id.to_string(), format!("{}{}", id, suffix)))
+        .collect()
+}
+
+fn first_collision_in<'a, T: Id<T>>(
+    first: &CollectionWithId<T>,
+    second: &'a CollectionWithId<T>,
+) -> Option<&'a str> {
+    second
+        .values()
+        .map(|o| o.id())
+        .find(|id| first.get(id).is_some())
+}
+
+fn merge_collection<T: Id<T>>(
+    first: &mut CollectionWithId<T>,
+    second: CollectionWithId<T>,
+    strategy: &MergeStrategy,
+) -> Result<()> {
+    match *strategy {
+        // The colliding objects have already been renamed.
+        MergeStrategy::Fail | MergeStrategy::RenameWithSuffix(_) => first.merge(second),
+        MergeStrategy::KeepFirst => {
+            for object in second {
+                if first.get(object.id()).is_none() {
+                    first.push(object)?;
+                }
+            }
+            Ok(())
+        }
+        MergeStrategy::KeepSecond => {
+            // The objects are replaced in place, keeping the indices
+            // of the first collection valid.
+            let mut objects = first.take();
+            let positions: HashMap<String, usize> = objects
+                .iter()
+                .enumerate()
+                .map(|(i, o)| (o.id().to_string(), i))
+                .collect();
+            for object in second {
+                match positions.get(object.id()) {
+                    Some(&i) => objects[i] = object,
+                    None => objects.push(object),
+                }
+            }
+            *first = CollectionWithId::new(objects)?;
+            Ok(())
+        }
+    }
+}
+
+macro_rules! first_collision {
+    ($self:ident, $other:ident, $name:ident, $($collection:ident),*) => {{
+        $(if $name == stringify!($collection) {
+            return first_collision_in(&$self.$collection, &$other.$collection);
+        })*
+        None
+    }};
+}
+
+macro_rules! collisions {
+    ($self:ident, $other:ident, $name:ident, $suffix:ident, $($collection:ident),*) => {{
+        $(if $name == stringify!($collection) {
+            collisions(&$self.$collection, &$other.$collection, $suffix)
+        } else)* {
+            bail!("unknown collection {:?}", $name)
+        }
+    }};
+}
+
+macro_rules! merge_collections {
+    ($self:ident, $other:ident, $strategies:ident, $($name:ident),*) => {
+        $(merge_collection(&mut $self.$name, $other.$name, $strategies.get(stringify!($name)))?;)*
+    };
 }
 
 impl Collections {
     /// Merge the `Collections` parameter into the current `Collections` by consecutively merging
     /// each collections representing the model.  Fails in case of id collision.
     pub fn merge(&mut self, c: Collections) -> Result<()> {
-        let Collections {
+        self.merge_with(c, &MergeStrategies::default())
+    }
+
+    /// Merge the `Collections` parameter into the current
+    /// `Collections`, handling the identifier collisions of each
+    /// collection with its strategy.
+    ///
+    /// The objects of the merged collections referring to a removed
+    /// object refer to the kept object with the same identifier.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use navitia_model::collection::CollectionWithId;
+    /// # use navitia_model::model::*;
+    /// # use navitia_model::objects::Company;
+    /// # fn run() -> navitia_model::Result<()> {
+    /// let company = |name: &str| Company {
+    ///     id: "TGC".into(),
+    ///     name: name.into(),
+    ///     ..Default::default()
+    /// };
+    /// let mut base = Collections::default();
+    /// base.companies = CollectionWithId::new(vec![company("The Great Company")])?;
+    /// let mut patch = Collections::default();
+    /// patch.companies = CollectionWithId::new(vec![company("The Good Company")])?;
+    /// let strategies = MergeStrategies {
+    ///     default: MergeStrategy::KeepSecond,
+    ///     ..Default::default()
+    /// };
+    /// base.merge_with(patch, &strategies)?;
+    /// assert_eq!("The Good Company", base.companies.get("TGC").unwrap().name);
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap() }
+    /// ```
+    pub fn merge_with(&mut self, mut c: Collections, strategies: &MergeStrategies) -> Result<()> {
+        for name in COLLECTIONS_WITH_ID {
+            if *strategies.get(name) == MergeStrategy::Fail {
+                if let Some(id) = self.first_collision(&c, name) {
+                    bail!("{} already found", id);
+                }
+            }
+        }
+        for name in COLLECTIONS_WITH_ID {
+            if let MergeStrategy::RenameWithSuffix(ref suffix) = *strategies.get(name) {
+                self.rename_collisions(&mut c, name, suffix)?;
+            }
+        }
+
+        // The indices of the merged collections are only valid in
+        // them.
+        let comment_ids: HashMap<Idx<Comment>, String> = c
+            .comments
+            .iter()
+            .map(|(idx, comment)| (idx, comment.id.clone()))
+            .collect();
+        let stop_point_ids: HashMap<Idx<StopPoint>, String> = c
+            .stop_points
+            .iter()
+            .map(|(idx, stop_point)| (idx, stop_point.id.clone()))
+            .collect();
+        merge_collections!(self, c, strategies, comments);
+        {
+            let comments = &self.comments;
+            let comment_idx = |idx: &mut Idx<Comment>| {
+                *idx = comments.get_idx(&comment_ids[idx]).unwrap();
+            };
+            update(&mut c.lines, |o| {
+                o.comment_links.iter_mut().for_each(comment_idx)
+            })?;
+            update(&mut c.line_groups, |o| {
+                o.comment_links.iter_mut().for_each(comment_idx)
+            })?;
+            update(&mut c.routes, |o| {
+                o.comment_links.iter_mut().for_each(comment_idx)
+            })?;
+            update(&mut c.vehicle_journeys, |o| {
+                o.comment_links.iter_mut().for_each(comment_idx)
+            })?;
+            update(&mut c.stop_areas, |o| {
+                o.comment_links.iter_mut().for_each(comment_idx)
+            })?;
+            update(&mut c.stop_points, |o| {
+                o.comment_links.iter_mut().for_each(comment_idx)
+            })?;
+        }
+        merge_collections!(self, c, strategies, stop_points);
+        {
+            let stop_points = &self.stop_points;
+            update(&mut c.vehicle_journeys, |vj| {
+                for stop_time in &mut vj.stop_times {
+                    let id = &stop_point_ids[&stop_time.stop_point_idx];
+                    stop_time.stop_point_idx = stop_points.get_idx(id).unwrap();
+                }
+            })?;
+        }
+
+        merge_collections!(
+            self,
+            c,
+            strategies,
             contributors,
             datasets,
             networks,
             commercial_modes,
             lines,
+            line_groups,
+            routes,
+            vehicle_journeys,
+            physical_modes,
+            stop_areas,
+            entrances,
+            addresses,
+            calendars,
+            grid_calendars,
+            companies,
+            equipments,
+            trip_properties,
+            geometries,
+            tickets,
+            ticket_uses,
+            fare_zones,
+            pathways,
+            levels
+        );
+        self.feed_infos.extend(c.feed_infos);
+        self.transfers.merge(c.transfers)?;
+        self.admin_stations.merge(c.admin_stations)?;
+        self.ticket_prices.merge(c.ticket_prices)?;
+        self.od_rules.merge(c.od_rules)?;
+        self.ticket_use_perimeters.merge(c.ticket_use_perimeters)?;
+        self.ticket_use_restrictions
+            .merge(c.ticket_use_restrictions)?;
+        self.line_group_links.merge(c.line_group_links)?;
+        self.frequencies.merge(c.frequencies)?;
+        self.vehicle_journey_links.merge(c.vehicle_journey_links)?;
+        self.attributions.merge(c.attributions)?;
+        self.translations.merge(c.translations)?;
+        self.id_mappings.merge(c.id_mappings)?;
+        self.grid_exception_dates.merge(c.grid_exception_dates)?;
+        self.grid_periods.merge(c.grid_periods)?;
+        self.grid_rel_calendar_line
+            .merge(c.grid_rel_calendar_line)?;
+        Ok(())
+    }
+
+    // The first identifier of the collection `name` of `c` already in
+    // `self`.
+    fn first_collision<'a>(&self, c: &'a Collections, name: &str) -> Option<&'a str> {
+        first_collision!(
+            self,
+            c,
+            name,
+            contributors,
+            datasets,
+            networks,
+            commercial_modes,
+            lines,
+            line_groups,
+            routes,
+            vehicle_journeys,
+            physical_modes,
+            stop_areas,
+            stop_points,
+            entrances,
+            addresses,
+            calendars,
+            grid_calendars,
+            companies,
+            comments,
+            equipments,
+            trip_properties,
+            geometries,
+            tickets,
+            ticket_uses,
+            fare_zones,
+            pathways,
+            levels
+        )
+    }
+
+    // Renames the objects of the collection `name` of `c` whose
+    // identifier is already in `self`, and all their references.
+    fn rename_collisions(&self, c: &mut Collections, name: &str, suffix: &str) -> Result<()> {
+        let ids = collisions!(
+            self,
+            c,
+            name,
+            suffix,
+            contributors,
+            datasets,
+            networks,
+            commercial_modes,
+            lines,
+            line_groups,
             routes,
             vehicle_journeys,
             physical_modes,
             stop_areas,
             stop_points,
-            feed_infos,
+            addresses,
+            entrances,
             calendars,
+            grid_calendars,
             companies,
             comments,
             equipments,
-            transfers,
             trip_properties,
             geometries,
-            admin_stations,
-        } = c;
-        self.contributors.merge(contributors)?;
-        self.datasets.merge(datasets)?;
-        self.networks.merge(networks)?;
-        self.commercial_modes.merge(commercial_modes)?;
-        self.lines.merge(lines)?;
-        self.routes.merge(routes)?;
-        self.vehicle_journeys.merge(vehicle_journeys)?;
-        self.physical_modes.merge(physical_modes)?;
-        self.stop_areas.merge(stop_areas)?;
-        self.stop_points.merge(stop_points)?;
-        self.feed_infos.extend(feed_infos);
-        self.calendars.merge(calendars)?;
-        self.companies.merge(companies)?;
-        self.comments.merge(comments)?;
-        self.equipments.merge(equipments)?;
-        self.transfers.merge(transfers)?;
-        self.trip_properties.merge(trip_properties)?;
-        self.geometries.merge(geometries)?;
-        self.admin_stations.merge(admin_stations)?;
+            tickets,
+            ticket_uses,
+            fare_zones,
+            pathways,
+            levels
+        );
+        rename_ids(c, name, &ids)
+    }
+
+    /// Converts all the ticket prices to `currency` with the exchange
+    /// `rates`, as before merging fare sources using different
+    /// currencies.  Fails, leaving the prices unchanged, if a rate is
+    /// missing.
+    pub fn convert_ticket_prices(
+        &mut self,
+        rates: &ExchangeRates,
+        currency: &Currency,
+    ) -> Result<()> {
+        let prices = self
+            .ticket_prices
+            .values()
+            .map(|p| rates.convert(p.price, &p.currency, currency))
+            .collect::<Result<Vec<_>>>()?;
+        for (ticket_price, price) in self.ticket_prices.values_mut().zip(prices) {
+            ticket_price.price = price;
+            ticket_price.currency = currency.clone();
+        }
+        Ok(())
+    }
+
+    /// Returns the tickets of the OD rules, and of the uses
+    /// restricted to an OD, from the stop area `origin_id` to the
+    /// stop area `destination_id`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use navitia_model::collection::{Collection, CollectionWithId};
+    /// # use navitia_model::model::Collections;
+    /// # use navitia_model::objects::{ODRule, Ticket};
+    /// let collections = Collections {
+    ///     tickets: CollectionWithId::new(vec![Ticket {
+    ///         id: "single".into(),
+    ///         name: "Single".into(),
+    ///         comment: None,
+    ///     }]).unwrap(),
+    ///     od_rules: Collection::new(vec![ODRule {
+    ///         ticket_id: "single".into(),
+    ///         origin_stop_area_id: "SA:1".into(),
+    ///         destination_stop_area_id: "SA:2".into(),
+    ///     }]),
+    ///     ..Default::default()
+    /// };
+    /// let tickets = collections.od_tickets("SA:1", "SA:2");
+    /// assert_eq!(vec!["single"], tickets.iter().map(|t| t.id.as_str()).collect::<Vec<_>>());
+    /// assert!(collections.od_tickets("SA:2", "SA:1").is_empty());
+    /// ```
+    pub fn od_tickets(&self, origin_id: &str, destination_id: &str) -> Vec<&Ticket> {
+        let use_ticket_ids = self
+            .ticket_use_restrictions
+            .values()
+            .filter(|r| r.restriction_type == RestrictionType::OriginDestination)
+            .filter(|r| r.use_origin == origin_id && r.use_destination == destination_id)
+            .filter_map(|r| self.ticket_uses.get(&r.ticket_use_id))
+            .map(|ticket_use| &ticket_use.ticket_id);
+        let mut ticket_ids: Vec<&String> = self
+            .od_rules
+            .values()
+            .filter(|r| r.origin_stop_area_id == origin_id)
+            .filter(|r| r.destination_stop_area_id == destination_id)
+            .map(|r| &r.ticket_id)
+            .chain(use_ticket_ids)
+            .collect();
+        ticket_ids.sort();
+        ticket_ids.dedup();
+        ticket_ids
+            .into_iter()
+            .filter_map(|id| self.tickets.get(id))
+            .collect()
+    }
+
+    /// Whether the object of type `object_type` identified by `id`
+    /// exists, the stop times having no identifier.
+    pub fn contains_object(&self, object_type: &ObjectType, id: &str) -> bool {
+        match *object_type {
+            ObjectType::StopArea => self.stop_areas.get_idx(id).is_some(),
+            ObjectType::StopPoint => self.stop_points.get_idx(id).is_some(),
+            ObjectType::Network => self.networks.get_idx(id).is_some(),
+            ObjectType::Line => self.lines.get_idx(id).is_some(),
+            ObjectType::Route => self.routes.get_idx(id).is_some(),
+            ObjectType::VehicleJourney => self.vehicle_journeys.get_idx(id).is_some(),
+            ObjectType::StopTime => false,
+            ObjectType::LineGroup => self.line_groups.get_idx(id).is_some(),
+        }
+    }
+
+    /// Prefixes the identifiers of the objects and of their
+    /// references as configured by `prefix_conf`.  Fails if an
+    /// exempt collection is not a collection of objects with an
+    /// identifier.
+    ///
+    /// The physical modes, the calendars, the geometries and the fare
+    /// zones are never prefixed.
+    pub fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) -> Result<()> {
+        for collection in &prefix_conf.exempt_collections {
+            ensure!(
+                COLLECTIONS_WITH_ID.contains(&collection.as_str()),
+                "unknown collection {:?}, expected one of {}",
+                collection,
+                COLLECTIONS_WITH_ID.join(", ")
+            );
+        }
+        match prefix_conf.replaced_prefix {
+            Some(ref replaced_prefix) => info!(
+                "Replacing prefix \"{}\" by \"{}{}\"",
+                replaced_prefix, prefix_conf.prefix, prefix_conf.separator
+            ),
+            None => info!(
+                "Adding prefix: \"{}{}\"",
+                prefix_conf.prefix, prefix_conf.separator
+            ),
+        }
+        add_prefix_to_collection_with_id(&mut self.commercial_modes, prefix_conf)?;
+        add_prefix_to_collection_with_id(&mut self.networks, prefix_conf)?;
+        add_prefix_to_collection_with_id(&mut self.companies, prefix_conf)?;
+        add_prefix_to_collection_with_id(&mut self.stop_points, prefix_conf)?;
+        add_prefix_to_collection_with_id(&mut self.stop_areas, prefix_conf)?;
+        add_prefix_to_collection_with_id(&mut self.entrances, prefix_conf)?;
+        add_prefix_to_collection_with_id(&mut self.addresses, prefix_conf)?;
+        add_prefix_to_collection(&mut self.transfers, prefix_conf);
+        add_prefix_to_collection_with_id(&mut self.routes, prefix_conf)?;
+        add_prefix_to_collection_with_id(&mut self.lines, prefix_conf)?;
+        add_prefix_to_collection_with_id(&mut self.line_groups, prefix_conf)?;
+        add_prefix_to_collection(&mut self.line_group_links, prefix_conf);
+        add_prefix_to_collection(&mut self.frequencies, prefix_conf);
+        add_prefix_to_collection(&mut self.vehicle_journey_links, prefix_conf);
+        add_prefix_to_collection_with_id(&mut self.contributors, prefix_conf)?;
+        add_prefix_to_collection_with_id(&mut self.datasets, prefix_conf)?;
+        add_prefix_to_collection(&mut self.attributions, prefix_conf);
+        add_prefix_to_collection_with_id(&mut self.vehicle_journeys, prefix_conf)?;
+        add_prefix_to_collection_with_id(&mut self.trip_properties, prefix_conf)?;
+        add_prefix_to_collection_with_id(&mut self.equipments, prefix_conf)?;
+        add_prefix_to_collection_with_id(&mut self.comments, prefix_conf)?;
+        add_prefix_to_collection_with_id(&mut self.tickets, prefix_conf)?;
+        add_prefix_to_collection(&mut self.ticket_prices, prefix_conf);
+        add_prefix_to_collection(&mut self.od_rules, prefix_conf);
+        add_prefix_to_collection_with_id(&mut self.ticket_uses, prefix_conf)?;
+        add_prefix_to_collection(&mut self.ticket_use_perimeters, prefix_conf);
+        add_prefix_to_collection(&mut self.ticket_use_restrictions, prefix_conf);
+        add_prefix_to_collection_with_id(&mut self.grid_calendars, prefix_conf)?;
+        add_prefix_to_collection(&mut self.grid_exception_dates, prefix_conf);
+        add_prefix_to_collection(&mut self.grid_periods, prefix_conf);
+        add_prefix_to_collection(&mut self.grid_rel_calendar_line, prefix_conf);
+        add_prefix_to_collection_with_id(&mut self.pathways, prefix_conf)?;
+        add_prefix_to_collection_with_id(&mut self.levels, prefix_conf)?;
+        add_prefix_to_collection(&mut self.translations, prefix_conf);
+        add_prefix_to_collection(&mut self.id_mappings, prefix_conf);
+        Ok(())
+    }
+
+    /// Removes the prefix of `prefix_conf`, followed by its separator,
+    /// from the identifiers of the objects and of their references,
+    /// the identifiers without it being left as is.
+    pub fn remove_prefix(&mut self, prefix_conf: &PrefixConfiguration) -> Result<()> {
+        let no_prefix = PrefixConfiguration {
+            separator: String::new(),
+            ..PrefixConfiguration::new("")
+        };
+        self.replace_prefix(prefix_conf, &no_prefix)
+    }
+
+    /// Replaces the prefix of `old_prefix_conf`, followed by its
+    /// separator, by the one of `new_prefix_conf` in the identifiers
+    /// of the objects and of their references, the identifiers without
+    /// it being left as is.
+    ///
+    /// The identifiers exempt from the new prefix only lose the old
+    /// one.
+    pub fn replace_prefix(
+        &mut self,
+        old_prefix_conf: &PrefixConfiguration,
+        new_prefix_conf: &PrefixConfiguration,
+    ) -> Result<()> {
+        let prefix_conf = PrefixConfiguration {
+            replaced_prefix: Some(format!(
+                "{}{}",
+                old_prefix_conf.prefix, old_prefix_conf.separator
+            )),
+            ..new_prefix_conf.clone()
+        };
+        self.add_prefix(&prefix_conf)
+    }
+
+    /// Removes the vehicle journeys for which `f` returns `true` and
+    /// returns their number.  The objects they were using are kept
+    /// until `clean_up` is called.
+    pub fn remove_vehicle_journeys<F>(&mut self, mut f: F) -> usize
+    where
+        F: FnMut(&VehicleJourney) -> bool,
+    {
+        let len = self.vehicle_journeys.len();
+        self.vehicle_journeys.retain(|vj| !f(vj));
+        len - self.vehicle_journeys.len()
+    }
+
+    /// Removes the objects that are not used anymore by the vehicle
+    /// journeys, as the stop points they don't stop at, their routes,
+    /// lines, networks, calendars, companies or modes, and then the
+    /// comments, geometries and equipments that are not referenced
+    /// anymore.
+    pub fn clean_up(&mut self) -> Result<()> {
+        let mut route_ids = BTreeSet::new();
+        let mut physical_mode_ids = BTreeSet::new();
+        let mut dataset_ids = BTreeSet::new();
+        let mut calendar_ids = BTreeSet::new();
+        let mut company_ids = BTreeSet::new();
+        let mut trip_property_ids = BTreeSet::new();
+        let mut geometry_ids = BTreeSet::new();
+        let mut stop_point_idxs = BTreeSet::new();
+        for vj in self.vehicle_journeys.values() {
+            route_ids.insert(vj.route_id.clone());
+            physical_mode_ids.insert(vj.physical_mode_id.clone());
+            dataset_ids.insert(vj.dataset_id.clone());
+            calendar_ids.insert(vj.service_id.clone());
+            company_ids.insert(vj.company_id.clone());
+            trip_property_ids.extend(vj.trip_property_id.clone());
+            geometry_ids.extend(vj.geometry_id.clone());
+            stop_point_idxs.extend(vj.stop_times.iter().map(|st| st.stop_point_idx));
+        }
+
+        // the stop times reference the stop points by index, they must
+        // be updated when the stop points are removed
+        let stop_point_ids: HashMap<Idx<StopPoint>, String> = stop_point_idxs
+            .into_iter()
+            .map(|idx| (idx, self.stop_points[idx].id.clone()))
+            .collect();
+        {
+            let used: BTreeSet<&str> = stop_point_ids.values().map(|id| id.as_str()).collect();
+            self.stop_points.retain(|sp| used.contains(sp.id.as_str()));
+        }
+        {
+            let stop_points = &self.stop_points;
+            update(&mut self.vehicle_journeys, |vj| {
+                for st in &mut vj.stop_times {
+                    let id = &stop_point_ids[&st.stop_point_idx];
+                    st.stop_point_idx = stop_points.get_idx(id).unwrap();
+                }
+            })?;
+        }
+
+        let stop_area_ids: BTreeSet<_> = self
+            .stop_points
+            .values()
+            .map(|sp| sp.stop_area_id.clone())
+            .collect();
+        self.stop_areas.retain(|sa| stop_area_ids.contains(sa.id.as_str()));
+        self.entrances
+            .retain(|e| stop_area_ids.contains(&e.stop_area_id));
+
+        self.routes.retain(|route| route_ids.contains(route.id.as_str()));
+        update(&mut self.routes, |route| {
+            keep_if_used(&mut route.destination_id, &stop_area_ids)
+        })?;
+        let line_ids: BTreeSet<_> = self.routes.values().map(|r| r.line_id.clone()).collect();
+        self.lines.retain(|line| line_ids.contains(line.id.as_str()));
+        let network_ids: BTreeSet<_> = self.lines.values().map(|l| l.network_id.clone()).collect();
+        self.networks
+            .retain(|network| network_ids.contains(network.id.as_str()));
+        let commercial_mode_ids: BTreeSet<_> = self
+            .lines
+            .values()
+            .map(|l| l.commercial_mode_id.clone())
+            .collect();
+        self.commercial_modes
+            .retain(|mode| commercial_mode_ids.contains(&mode.id));
+        self.physical_modes
+            .retain(|mode| physical_mode_ids.contains(&mode.id));
+        self.datasets
+            .retain(|dataset| dataset_ids.contains(&dataset.id));
+        let contributor_ids: BTreeSet<_> = self
+            .datasets
+            .values()
+            .map(|d| d.contributor_id.clone())
+            .collect();
+        self.contributors
+            .retain(|contributor| contributor_ids.contains(&contributor.id));
+        self.calendars
+            .retain(|calendar| calendar_ids.contains(calendar.id.as_str()));
+        self.companies
+            .retain(|company| company_ids.contains(&company.id));
+        self.trip_properties
+            .retain(|trip_property| trip_property_ids.contains(&trip_property.id));
+
+        let stop_points = &self.stop_points;
+        let vehicle_journeys = &self.vehicle_journeys;
+        self.transfers.retain(|t| {
+            stop_points.get(&t.from_stop_id).is_some() && stop_points.get(&t.to_stop_id).is_some()
+        });
+        self.frequencies
+            .retain(|f| vehicle_journeys.get(&f.vehicle_journey_id).is_some());
+        self.vehicle_journey_links.retain(|link| {
+            vehicle_journeys
+                .get(&link.from_vehicle_journey_id)
+                .is_some()
+                && vehicle_journeys.get(&link.to_vehicle_journey_id).is_some()
+                && stop_points.get(&link.stop_point_id).is_some()
+        });
+        self.admin_stations
+            .retain(|a| stop_area_ids.contains(a.stop_id.as_str()));
+        let lines = &self.lines;
+        self.grid_rel_calendar_line
+            .retain(|rel| lines.get(&rel.line_id).is_some());
+        self.line_groups
+            .retain(|group| line_ids.contains(group.main_line_id.as_str()));
+        let line_groups = &self.line_groups;
+        self.line_group_links.retain(|link| {
+            line_groups.get(&link.line_group_id).is_some() && line_ids.contains(&link.line_id)
+        });
+        let datasets = &self.datasets;
+        let networks = &self.networks;
+        self.attributions.retain(|a| {
+            datasets.get(&a.dataset_id).is_some()
+                && a.network_id.iter().all(|id| networks.get(id).is_some())
+                && a.line_id.iter().all(|id| lines.get(id).is_some())
+                && a.vehicle_journey_id
+                    .iter()
+                    .all(|id| vehicle_journeys.get(id).is_some())
+        });
+        let translations = mem::take(&mut self.translations)
+            .into_iter()
+            .filter(|t| self.contains_object(&t.object_type, &t.object_id))
+            .collect();
+        self.translations = Collection::new(translations);
+
+        geometry_ids.extend(self.lines.values().filter_map(|l| l.geometry_id.clone()));
+        geometry_ids.extend(self.routes.values().filter_map(|r| r.geometry_id.clone()));
+        geometry_ids.extend(
+            self.stop_areas
+                .values()
+                .filter_map(|s| s.geometry_id.clone()),
+        );
+        geometry_ids.extend(
+            self.stop_points
+                .values()
+                .filter_map(|s| s.geometry_id.clone()),
+        );
+        self.geometries
+            .retain(|geometry| geometry_ids.contains(&geometry.id));
+
+        let mut equipment_ids = BTreeSet::new();
+        equipment_ids.extend(
+            self.stop_areas
+                .values()
+                .filter_map(|s| s.equipment_id.clone()),
+        );
+        equipment_ids.extend(
+            self.stop_points
+                .values()
+                .filter_map(|s| s.equipment_id.clone()),
+        );
+        equipment_ids.extend(
+            self.transfers
+                .values()
+                .filter_map(|t| t.equipment_id.clone()),
+        );
+        self.equipments
+            .retain(|equipment| equipment_ids.contains(&equipment.id));
+
+        self.clean_up_comments()?;
+
+        let stop_areas = &self.stop_areas;
+        let routes = &self.routes;
+        let equipments = &self.equipments;
+        let trip_properties = &self.trip_properties;
+        self.id_mappings.retain(|m| match m.collection.as_str() {
+            "stop_areas" => stop_areas.get(&m.generated_id).is_some(),
+            "routes" => routes.get(&m.generated_id).is_some(),
+            "equipments" => equipments.get(&m.generated_id).is_some(),
+            "trip_properties" => trip_properties.get(&m.generated_id).is_some(),
+            _ => true,
+        });
+        Ok(())
+    }
+
+    // Removes the comments linked to no object, the links of the
+    // objects being updated as they refer to the comments by index.
+    fn clean_up_comments(&mut self) -> Result<()> {
+        let mut comment_idxs = BTreeSet::new();
+        comment_idxs.extend(self.lines.values().flat_map(|o| o.comment_links.iter()));
+        comment_idxs.extend(
+            self.line_groups
+                .values()
+                .flat_map(|o| o.comment_links.iter()),
+        );
+        comment_idxs.extend(self.routes.values().flat_map(|o| o.comment_links.iter()));
+        comment_idxs.extend(
+            self.vehicle_journeys
+                .values()
+                .flat_map(|o| o.comment_links.iter()),
+        );
+        comment_idxs.extend(
+            self.stop_areas
+                .values()
+                .flat_map(|o| o.comment_links.iter()),
+        );
+        comment_idxs.extend(
+            self.stop_points
+                .values()
+                .flat_map(|o| o.comment_links.iter()),
+        );
+        let comment_ids: HashMap<Idx<Comment>, String> = comment_idxs
+            .into_iter()
+            .map(|&idx| (idx, self.comments[idx].id.clone()))
+            .collect();
+        if comment_ids.len() == self.comments.len() {
+            return Ok(());
+        }
+        {
+            let used: BTreeSet<&str> = comment_ids.values().map(|id| id.as_str()).collect();
+            self.comments.retain(|c| used.contains(c.id.as_str()));
+        }
+        let comments = &self.comments;
+        let comment_idx = |idx: &mut Idx<Comment>| {
+            *idx = comments.get_idx(&comment_ids[idx]).unwrap();
+        };
+        update(&mut self.lines, |o| {
+            o.comment_links.iter_mut().for_each(comment_idx)
+        })?;
+        update(&mut self.line_groups, |o| {
+            o.comment_links.iter_mut().for_each(comment_idx)
+        })?;
+        update(&mut self.routes, |o| {
+            o.comment_links.iter_mut().for_each(comment_idx)
+        })?;
+        update(&mut self.vehicle_journeys, |o| {
+            o.comment_links.iter_mut().for_each(comment_idx)
+        })?;
+        update(&mut self.stop_areas, |o| {
+            o.comment_links.iter_mut().for_each(comment_idx)
+        })?;
+        update(&mut self.stop_points, |o| {
+            o.comment_links.iter_mut().for_each(comment_idx)
+        })?;
+        Ok(())
+    }
+
+    /// Shares the allocation of the identical references and headsigns
+    /// of the objects, the routes and calendars of the vehicle journeys
+    /// being repeated by most of them on big datasets.
+    pub fn intern_strings(&mut self) -> Result<()> {
+        let mut interner = Interner::default();
+        update(&mut self.lines, |o| interner.intern_id(&mut o.network_id))?;
+        update(&mut self.routes, |o| interner.intern_id(&mut o.line_id))?;
+        update(&mut self.vehicle_journeys, |o| {
+            interner.intern_id(&mut o.route_id);
+            interner.intern_id(&mut o.service_id);
+            if let Some(headsign) = o.headsign.as_mut() {
+                *headsign = interner.intern(headsign);
+            }
+        })?;
+        update(&mut self.stop_points, |o| interner.intern_id(&mut o.stop_area_id))?;
+        update(&mut self.entrances, |o| interner.intern_id(&mut o.stop_area_id))?;
+        for link in self.vehicle_journey_links.values_mut() {
+            interner.intern_id(&mut link.stop_point_id);
+        }
+        for link in self.line_group_links.values_mut() {
+            interner.intern_id(&mut link.line_id);
+        }
+        for rel in self.grid_rel_calendar_line.values_mut() {
+            interner.intern_id(&mut rel.line_id);
+        }
+        for attribution in self.attributions.values_mut() {
+            if let Some(ref mut id) = attribution.network_id {
+                interner.intern_id(id);
+            }
+            if let Some(ref mut id) = attribution.line_id {
+                interner.intern_id(id);
+            }
+        }
         Ok(())
     }
 }
 
+fn keep_if_used<T: Borrow<str> + Ord>(id: &mut Option<String>, used: &BTreeSet<T>) {
+    if id.as_ref().is_some_and(|id| !used.contains(id.as_str())) {
+        *id = None;
+    }
+}
+
 /// The navitia transit model.
 #[derive(GetCorresponding)]
 pub struct Model {
@@ -132,8 +1326,9 @@ pub struct Model {
 }
 
 impl Model {
-    /// Constructs a model from the given `Collections`.  Fails in
-    /// case of incoherence, as invalid external references.
+    /// Constructs a model from the given `Collections`, interning
+    /// their strings.  Fails in case of incoherence, as invalid
+    /// external references.
     ///
     /// # Examples
     ///
@@ -161,7 +1356,8 @@ impl Model {
     /// }]);
     /// assert!(Model::new(collections).is_err());
     /// ```
-    pub fn new(c: Collections) -> Result<Self> {
+    pub fn new(mut c: Collections) -> Result<Self> {
+        c.intern_strings()?;
         let forward_vj_to_sp = c
             .vehicle_journeys
             .iter()
@@ -185,6 +1381,168 @@ impl Model {
                 Ok((idx, stop_points))
             })
             .collect::<StdResult<BTreeMap<_, _>, Error>>()?;
+        for price in c.ticket_prices.values() {
+            ensure!(
+                c.tickets.get_idx(&price.ticket_id).is_some(),
+                "Invalid id: ticket_price.ticket_id={:?}",
+                price.ticket_id
+            );
+            ensure!(
+                !price.price.is_negative(),
+                "Invalid price {} for ticket {:?}",
+                price.price,
+                price.ticket_id
+            );
+        }
+        for od_rule in c.od_rules.values() {
+            ensure!(
+                c.tickets.get_idx(&od_rule.ticket_id).is_some(),
+                "Invalid id: od_rule.ticket_id={:?}",
+                od_rule.ticket_id
+            );
+        }
+        for frequency in c.frequencies.values() {
+            ensure!(
+                c.vehicle_journeys
+                    .get_idx(&frequency.vehicle_journey_id)
+                    .is_some(),
+                "Invalid id: frequency.trip_id={:?}",
+                frequency.vehicle_journey_id
+            );
+        }
+        for link in c.vehicle_journey_links.values() {
+            for vj_id in &[&link.from_vehicle_journey_id, &link.to_vehicle_journey_id] {
+                ensure!(
+                    c.vehicle_journeys.get_idx(vj_id).is_some(),
+                    "Invalid id: vehicle_journey_link.trip_id={:?}",
+                    vj_id
+                );
+            }
+            ensure!(
+                c.stop_points.get_idx(&link.stop_point_id).is_some(),
+                "Invalid id: vehicle_journey_link.stop_id={:?}",
+                link.stop_point_id
+            );
+        }
+        for line_group in c.line_groups.values() {
+            ensure!(
+                c.lines.get_idx(&line_group.main_line_id).is_some(),
+                "Invalid id: line_group.main_line_id={:?}",
+                line_group.main_line_id
+            );
+        }
+        for link in c.line_group_links.values() {
+            ensure!(
+                c.line_groups.get_idx(&link.line_group_id).is_some(),
+                "Invalid id: line_group_link.line_group_id={:?}",
+                link.line_group_id
+            );
+            ensure!(
+                c.lines.get_idx(&link.line_id).is_some(),
+                "Invalid id: line_group_link.line_id={:?}",
+                link.line_id
+            );
+        }
+        for attribution in c.attributions.values() {
+            ensure!(
+                c.datasets.get_idx(&attribution.dataset_id).is_some(),
+                "Invalid id: attribution.dataset_id={:?}",
+                attribution.dataset_id
+            );
+            if let Some(ref network_id) = attribution.network_id {
+                ensure!(
+                    c.networks.get_idx(network_id).is_some(),
+                    "Invalid id: attribution.network_id={:?}",
+                    network_id
+                );
+            }
+            if let Some(ref line_id) = attribution.line_id {
+                ensure!(
+                    c.lines.get_idx(line_id).is_some(),
+                    "Invalid id: attribution.line_id={:?}",
+                    line_id
+                );
+            }
+            if let Some(ref vj_id) = attribution.vehicle_journey_id {
+                ensure!(
+                    c.vehicle_journeys.get_idx(vj_id).is_some(),
+                    "Invalid id: attribution.trip_id={:?}",
+                    vj_id
+                );
+            }
+        }
+        for exception_date in c.grid_exception_dates.values() {
+            ensure!(
+                c.grid_calendars
+                    .get_idx(&exception_date.grid_calendar_id)
+                    .is_some(),
+                "Invalid id: grid_exception_date.grid_calendar_id={:?}",
+                exception_date.grid_calendar_id
+            );
+        }
+        for period in c.grid_periods.values() {
+            ensure!(
+                c.grid_calendars.get_idx(&period.grid_calendar_id).is_some(),
+                "Invalid id: grid_period.grid_calendar_id={:?}",
+                period.grid_calendar_id
+            );
+        }
+        for rel in c.grid_rel_calendar_line.values() {
+            ensure!(
+                c.grid_calendars.get_idx(&rel.grid_calendar_id).is_some(),
+                "Invalid id: grid_rel_calendar_line.grid_calendar_id={:?}",
+                rel.grid_calendar_id
+            );
+            ensure!(
+                c.lines.get_idx(&rel.line_id).is_some(),
+                "Invalid id: grid_rel_calendar_line.line_id={:?}",
+                rel.line_id
+            );
+        }
+        for ticket_use in c.ticket_uses.values() {
+            ensure!(
+                c.tickets.get_idx(&ticket_use.ticket_id).is_some(),
+                "Invalid id: ticket_use.ticket_id={:?}",
+                ticket_use.ticket_id
+            );
+        }
+        for perimeter in c.ticket_use_perimeters.values() {
+            ensure!(
+                c.ticket_uses.get_idx(&perimeter.ticket_use_id).is_some(),
+                "Invalid id: ticket_use_perimeter.ticket_use_id={:?}",
+                perimeter.ticket_use_id
+            );
+        }
+        for restriction in c.ticket_use_restrictions.values() {
+            ensure!(
+                c.ticket_uses.get_idx(&restriction.ticket_use_id).is_some(),
+                "Invalid id: ticket_use_restriction.ticket_use_id={:?}",
+                restriction.ticket_use_id
+            );
+        }
+        for stop_point in c.stop_points.values() {
+            if let Some(ref address_id) = stop_point.address_id {
+                ensure!(
+                    c.addresses.get_idx(address_id).is_some(),
+                    "Invalid id: stop_point.address_id={:?}",
+                    address_id
+                );
+            }
+        }
+        for entrance in c.entrances.values() {
+            ensure!(
+                c.stop_areas.get_idx(&entrance.stop_area_id).is_some(),
+                "Invalid id: entrance.stop_area_id={:?}",
+                entrance.stop_area_id
+            );
+        }
+        for fare_zone in c.fare_zones.values() {
+            ensure!(
+                c.geometries.get_idx(&fare_zone.geometry_id).is_some(),
+                "Invalid id: fare_zone.geometry_id={:?}",
+                fare_zone.geometry_id
+            );
+        }
         let vehicle_journeys_to_stop_points = ManyToMany::from_forward(forward_vj_to_sp);
         let routes_to_vehicle_journeys =
             OneToMany::new(&c.routes, &c.vehicle_journeys, "routes_to_vehicle_journeys")?;
@@ -279,6 +1637,197 @@ impl Model {
     pub fn into_collections(self) -> Collections {
         self.collections
     }
+
+    /// Applies `f` to the collections of the model.  The relations
+    /// are rebuilt, and the collections checked as by `new`, only if
+    /// `f` changed the identifiers or the references of the objects,
+    /// other changes, as fixing a name or adding a comment, being
+    /// cheap on huge datasets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use navitia_model::model::*;
+    /// # fn run() -> navitia_model::Result<()> {
+    /// let model = Model::new(Collections::default())?;
+    /// let model = model.apply(|c| {
+    ///     c.feed_infos.insert("feed_publisher_name".into(), "me".into());
+    ///     Ok(())
+    /// })?;
+    /// assert_eq!("me", model.feed_infos["feed_publisher_name"]);
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap() }
+    /// ```
+    pub fn apply<F>(mut self, f: F) -> Result<Self>
+    where
+        F: FnOnce(&mut Collections) -> Result<()>,
+    {
+        let structure = structure_hash(&self.collections);
+        f(&mut self.collections)?;
+        if structure_hash(&self.collections) == structure {
+            Ok(self)
+        } else {
+            debug!("Rebuilding the relations of the model");
+            Model::new(self.collections)
+        }
+    }
+
+    /// Checks the model for semantic issues, see
+    /// `validator::validate`.
+    pub fn validate(&self) -> ValidationReport {
+        validator::validate(&self.collections)
+    }
+
+    /// Computes the statistics of the model, see
+    /// `statistics::statistics`.
+    pub fn statistics(&self) -> Statistics {
+        statistics::statistics(&self.collections)
+    }
+
+    /// Returns the stop points served by the vehicle journeys of the
+    /// line, ordered by index.
+    pub fn stop_points_of_line<'a>(
+        &'a self,
+        idx: Idx<Line>,
+    ) -> impl Iterator<Item = (Idx<StopPoint>, &'a StopPoint)> + 'a {
+        objects(&self.stop_points, self.get_corresponding_from_idx(idx))
+    }
+
+    /// Returns the stop areas of the stop points served by the
+    /// vehicle journeys of the line, ordered by index.
+    pub fn stop_areas_of_line<'a>(
+        &'a self,
+        idx: Idx<Line>,
+    ) -> impl Iterator<Item = (Idx<StopArea>, &'a StopArea)> + 'a {
+        objects(&self.stop_areas, self.get_corresponding_from_idx(idx))
+    }
+
+    /// Returns the vehicle journeys of the routes of the line,
+    /// ordered by index.
+    pub fn vehicle_journeys_of_line<'a>(
+        &'a self,
+        idx: Idx<Line>,
+    ) -> impl Iterator<Item = (Idx<VehicleJourney>, &'a VehicleJourney)> + 'a {
+        objects(&self.vehicle_journeys, self.get_corresponding_from_idx(idx))
+    }
+
+    /// Returns the lines of the vehicle journeys serving the stop
+    /// point, ordered by index.
+    pub fn lines_of_stop_point<'a>(
+        &'a self,
+        idx: Idx<StopPoint>,
+    ) -> impl Iterator<Item = (Idx<Line>, &'a Line)> + 'a {
+        objects(&self.lines, self.get_corresponding_from_idx(idx))
+    }
+
+    /// Returns the lines of the vehicle journeys serving the stop
+    /// points of the stop area, ordered by index.
+    pub fn lines_of_stop_area<'a>(
+        &'a self,
+        idx: Idx<StopArea>,
+    ) -> impl Iterator<Item = (Idx<Line>, &'a Line)> + 'a {
+        objects(&self.lines, self.get_corresponding_from_idx(idx))
+    }
+
+    /// Returns the vehicle journeys running on `date`, their calendar
+    /// containing it.
+    pub fn vehicle_journeys_on_date<'a>(
+        &'a self,
+        date: Date,
+    ) -> impl Iterator<Item = (Idx<VehicleJourney>, &'a VehicleJourney)> + 'a {
+        self.vehicle_journeys_active_between(date, date)
+    }
+
+    /// Returns the vehicle journeys running on at least one day
+    /// between `start` and `end`, both included, the days being the
+    /// dates of their calendar.
+    pub fn vehicle_journeys_active_between<'a>(
+        &'a self,
+        start: Date,
+        end: Date,
+    ) -> impl Iterator<Item = (Idx<VehicleJourney>, &'a VehicleJourney)> + 'a {
+        let calendars = &self.calendars;
+        self.vehicle_journeys.iter().filter(move |&(_, vj)| {
+            calendars
+                .get(&vj.service_id)
+                .is_some_and(|calendar| calendar.is_active_between(start, end))
+        })
+    }
+
+    /// Returns the `limit` first departures from the stop point at or
+    /// after `datetime`, ordered by time.  The last stop times of the
+    /// vehicle journeys and the stop times without pickup are not
+    /// departures.
+    pub fn next_departures(
+        &self,
+        stop_point_idx: Idx<StopPoint>,
+        datetime: NaiveDateTime,
+        limit: usize,
+    ) -> Vec<Departure> {
+        let vj_idxs: IdxSet<VehicleJourney> = self.get_corresponding_from_idx(stop_point_idx);
+        let mut departures = vec![];
+        for vj_idx in vj_idxs {
+            let vj = &self.vehicle_journeys[vj_idx];
+            let calendar = match self.calendars.get(&vj.service_id) {
+                Some(calendar) => calendar,
+                None => continue,
+            };
+            let last = vj.stop_times.len().saturating_sub(1);
+            for (stop_time_idx, st) in vj.stop_times.iter().enumerate() {
+                if st.stop_point_idx != stop_point_idx
+                    || st.pickup_type == 1
+                    || stop_time_idx == last
+                {
+                    continue;
+                }
+                // the departure times after midnight are on the days
+                // following the dates of the calendar
+                let offset = Duration::seconds(i64::from(time_to_seconds(st.departure_time)));
+                let first_date = (datetime - offset).date();
+                departures.extend(
+                    calendar
+                        .dates
+                        .range(first_date..)
+                        .map(|date| date.and_hms(0, 0, 0) + offset)
+                        .filter(|departure| *departure >= datetime)
+                        .take(limit)
+                        .map(|datetime| Departure {
+                            datetime,
+                            vehicle_journey_idx: vj_idx,
+                            stop_time_idx,
+                        }),
+                );
+            }
+        }
+        departures.sort_by_key(|d| (d.datetime, d.vehicle_journey_idx));
+        departures.truncate(limit);
+        departures
+    }
+}
+
+fn time_to_seconds(time: Time) -> u32 {
+    time.hours() * 3600 + time.minutes() * 60 + time.seconds()
+}
+
+/// A departure from a stop point, as returned by
+/// `Model::next_departures`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Departure {
+    /// The date and time of the departure.
+    pub datetime: NaiveDateTime,
+    /// The index of the departing vehicle journey.
+    pub vehicle_journey_idx: Idx<VehicleJourney>,
+    /// The position of the stop time in the stop times of the vehicle
+    /// journey.
+    pub stop_time_idx: usize,
+}
+
+fn objects<'a, T>(
+    collection: &'a CollectionWithId<T>,
+    idxs: IdxSet<T>,
+) -> impl Iterator<Item = (Idx<T>, &'a T)> + 'a {
+    idxs.into_iter().map(move |idx| (idx, &collection[idx]))
 }
 impl ::serde::Serialize for Model {
     fn serialize<S>(&self, serializer: S) -> StdResult<S::Ok, S::Error>
@@ -298,9 +1847,361 @@ impl<'de> ::serde::Deserialize<'de> for Model {
             .and_then(|o| Model::new(o).map_err(D::Error::custom))
     }
 }
+// Hashes the identifiers and the references of the collections read
+// by `Model::new`, to know whether the relations of a model must be
+// rebuilt.
+fn structure_hash(c: &Collections) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    macro_rules! hash {
+        ($collection:expr, | $o:ident | $fields:expr) => {
+            $collection.len().hash(&mut hasher);
+            for $o in $collection.values() {
+                $fields.hash(&mut hasher);
+            }
+        };
+    }
+    hash!(c.contributors, |o| &o.id);
+    hash!(c.datasets, |o| (&o.id, &o.contributor_id));
+    hash!(c.attributions, |o| (
+        &o.dataset_id,
+        &o.network_id,
+        &o.line_id,
+        &o.vehicle_journey_id
+    ));
+    hash!(c.networks, |o| &o.id);
+    hash!(c.commercial_modes, |o| &o.id);
+    hash!(c.lines, |o| (&o.id, &o.network_id, &o.commercial_mode_id));
+    hash!(c.line_groups, |o| (&o.id, &o.main_line_id));
+    hash!(c.line_group_links, |o| (&o.line_group_id, &o.line_id));
+    hash!(c.routes, |o| (&o.id, &o.line_id));
+    hash!(c.vehicle_journeys, |o| (
+        &o.id,
+        &o.route_id,
+        &o.physical_mode_id,
+        &o.dataset_id,
+        &o.company_id
+    ));
+    for vj in c.vehicle_journeys.values() {
+        vj.stop_times.len().hash(&mut hasher);
+        for st in &vj.stop_times {
+            st.stop_point_idx.hash(&mut hasher);
+        }
+    }
+    hash!(c.frequencies, |o| &o.vehicle_journey_id);
+    hash!(c.vehicle_journey_links, |o| (
+        &o.from_vehicle_journey_id,
+        &o.to_vehicle_journey_id,
+        &o.stop_point_id
+    ));
+    hash!(c.physical_modes, |o| &o.id);
+    hash!(c.stop_areas, |o| &o.id);
+    hash!(c.stop_points, |o| (&o.id, &o.stop_area_id, &o.address_id));
+    hash!(c.entrances, |o| (&o.id, &o.stop_area_id));
+    hash!(c.addresses, |o| &o.id);
+    hash!(c.grid_calendars, |o| &o.id);
+    hash!(c.grid_exception_dates, |o| &o.grid_calendar_id);
+    hash!(c.grid_periods, |o| &o.grid_calendar_id);
+    hash!(c.grid_rel_calendar_line, |o| (
+        &o.grid_calendar_id,
+        &o.line_id
+    ));
+    hash!(c.companies, |o| &o.id);
+    hash!(c.transfers, |o| (&o.from_stop_id, &o.to_stop_id));
+    hash!(c.geometries, |o| &o.id);
+    hash!(c.tickets, |o| &o.id);
+    hash!(c.ticket_prices, |o| (&o.ticket_id, o.price.is_negative()));
+    hash!(c.od_rules, |o| &o.ticket_id);
+    hash!(c.ticket_uses, |o| (&o.id, &o.ticket_id));
+    hash!(c.ticket_use_perimeters, |o| &o.ticket_use_id);
+    hash!(c.ticket_use_restrictions, |o| &o.ticket_use_id);
+    hash!(c.fare_zones, |o| (&o.id, &o.geometry_id));
+    hasher.finish()
+}
+
 impl ops::Deref for Model {
     type Target = Collections;
     fn deref(&self) -> &Self::Target {
         &self.collections
     }
 }
+
+/// Magic bytes opening the files written by `save_binary`.
+const BINARY_MAGIC: [u8; 4] = *b"NTMB";
+
+/// Version of the binary format, to be increased each time the
+/// serialized objects change.
+const BINARY_VERSION: u32 = 8;
+
+/// The fields of an object not serialized with it.
+#[derive(Serialize, Deserialize, Default)]
+struct ObjectExtras {
+    codes: KeysValues,
+    object_properties: KeysValues,
+    comment_ids: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryStopTime {
+    stop_point_id: String,
+    sequence: u32,
+    arrival_time: Time,
+    departure_time: Time,
+    boarding_duration: u16,
+    alighting_duration: u16,
+    pickup_type: u8,
+    drop_off_type: u8,
+    datetime_estimated: bool,
+    local_zone_id: Option<u16>,
+    shape_dist_traveled: Option<f64>,
+    continuous_pickup: Option<u8>,
+    continuous_drop_off: Option<u8>,
+}
+
+/// Everything the serialization of the `Collections` leaves out.
+#[derive(Serialize, Deserialize, Default)]
+struct BinaryExtras {
+    network_codes: Vec<KeysValues>,
+    lines: Vec<ObjectExtras>,
+    line_group_comment_ids: Vec<Vec<String>>,
+    routes: Vec<ObjectExtras>,
+    vehicle_journeys: Vec<ObjectExtras>,
+    stop_areas: Vec<ObjectExtras>,
+    stop_points: Vec<ObjectExtras>,
+    stop_times: Vec<Vec<BinaryStopTime>>,
+    calendar_dates: Vec<Vec<i32>>,
+}
+
+fn comment_ids(links: &CommentLinksT, comments: &CollectionWithId<Comment>) -> Vec<String> {
+    links.iter().map(|&idx| comments[idx].id.clone()).collect()
+}
+
+fn comment_links(ids: &[String], comments: &CollectionWithId<Comment>) -> Result<CommentLinksT> {
+    ids.iter()
+        .map(|id| {
+            comments
+                .get_idx(id)
+                .ok_or_else(|| format_err!("comment {} not found", id))
+        })
+        .collect()
+}
+
+fn object_extras<T>(
+    collection: &CollectionWithId<T>,
+    comments: &CollectionWithId<Comment>,
+) -> Vec<ObjectExtras>
+where
+    T: Id<T> + Codes + Properties + CommentLinks,
+{
+    collection
+        .values()
+        .map(|obj| ObjectExtras {
+            codes: obj.codes().clone(),
+            object_properties: obj.properties().clone(),
+            comment_ids: comment_ids(obj.comment_links(), comments),
+        })
+        .collect()
+}
+
+fn set_object_extras<T>(
+    collection: &mut CollectionWithId<T>,
+    extras: Vec<ObjectExtras>,
+    comments: &CollectionWithId<Comment>,
+) -> Result<()>
+where
+    T: Id<T> + Codes + Properties + CommentLinks,
+{
+    let mut objects = collection.take();
+    ensure!(
+        objects.len() == extras.len(),
+        "inconsistent binary model: {} objects for {} extras",
+        objects.len(),
+        extras.len()
+    );
+    for (obj, extras) in objects.iter_mut().zip(extras) {
+        *obj.codes_mut() = extras.codes;
+        *obj.properties_mut() = extras.object_properties;
+        *obj.comment_links_mut() = comment_links(&extras.comment_ids, comments)?;
+    }
+    *collection = CollectionWithId::new(objects)?;
+    Ok(())
+}
+
+impl BinaryExtras {
+    fn from_collections(c: &Collections) -> Self {
+        BinaryExtras {
+            network_codes: c.networks.values().map(|n| n.codes.clone()).collect(),
+            lines: object_extras(&c.lines, &c.comments),
+            line_group_comment_ids: c
+                .line_groups
+                .values()
+                .map(|group| comment_ids(&group.comment_links, &c.comments))
+                .collect(),
+            routes: object_extras(&c.routes, &c.comments),
+            vehicle_journeys: object_extras(&c.vehicle_journeys, &c.comments),
+            stop_areas: object_extras(&c.stop_areas, &c.comments),
+            stop_points: object_extras(&c.stop_points, &c.comments),
+            stop_times: c
+                .vehicle_journeys
+                .values()
+                .map(|vj| {
+                    vj.stop_times
+                        .iter()
+                        .map(|st| BinaryStopTime {
+                            stop_point_id: c.stop_points[st.stop_point_idx].id.clone(),
+                            sequence: st.sequence,
+                            arrival_time: st.arrival_time,
+                            departure_time: st.departure_time,
+                            boarding_duration: st.boarding_duration,
+                            alighting_duration: st.alighting_duration,
+                            pickup_type: st.pickup_type,
+                            drop_off_type: st.drop_off_type,
+                            datetime_estimated: st.datetime_estimated,
+                            local_zone_id: st.local_zone_id,
+                            shape_dist_traveled: st.shape_dist_traveled(),
+                            continuous_pickup: st.continuous_pickup(),
+                            continuous_drop_off: st.continuous_drop_off(),
+                        })
+                        .collect()
+                })
+                .collect(),
+            calendar_dates: c
+                .calendars
+                .values()
+                .map(|cal| cal.dates.iter().map(|d| d.num_days_from_ce()).collect())
+                .collect(),
+        }
+    }
+
+    fn apply(self, c: &mut Collections) -> Result<()> {
+        let mut networks = c.networks.take();
+        ensure!(
+            networks.len() == self.network_codes.len(),
+            "inconsistent binary model: {} networks for {} codes",
+            networks.len(),
+            self.network_codes.len()
+        );
+        for (network, codes) in networks.iter_mut().zip(self.network_codes) {
+            network.codes = codes;
+        }
+        c.networks = CollectionWithId::new(networks)?;
+        set_object_extras(&mut c.lines, self.lines, &c.comments)?;
+        let mut line_groups = c.line_groups.take();
+        ensure!(
+            line_groups.len() == self.line_group_comment_ids.len(),
+            "inconsistent binary model: {} line groups for {} comment lists",
+            line_groups.len(),
+            self.line_group_comment_ids.len()
+        );
+        for (group, ids) in line_groups.iter_mut().zip(self.line_group_comment_ids) {
+            group.comment_links = comment_links(&ids, &c.comments)?;
+        }
+        c.line_groups = CollectionWithId::new(line_groups)?;
+        set_object_extras(&mut c.routes, self.routes, &c.comments)?;
+        set_object_extras(&mut c.vehicle_journeys, self.vehicle_journeys, &c.comments)?;
+        set_object_extras(&mut c.stop_areas, self.stop_areas, &c.comments)?;
+        set_object_extras(&mut c.stop_points, self.stop_points, &c.comments)?;
+
+        let mut vehicle_journeys = c.vehicle_journeys.take();
+        ensure!(
+            vehicle_journeys.len() == self.stop_times.len(),
+            "inconsistent binary model: {} vehicle journeys for {} stop time lists",
+            vehicle_journeys.len(),
+            self.stop_times.len()
+        );
+        for (vj, stop_times) in vehicle_journeys.iter_mut().zip(self.stop_times) {
+            for st in stop_times {
+                let stop_point_idx = c
+                    .stop_points
+                    .get_idx(&st.stop_point_id)
+                    .ok_or_else(|| format_err!("stop point {} not found", st.stop_point_id))?;
+                vj.stop_times.push(StopTime {
+                    stop_point_idx,
+                    sequence: st.sequence,
+                    arrival_time: st.arrival_time,
+                    departure_time: st.departure_time,
+                    boarding_duration: st.boarding_duration,
+                    alighting_duration: st.alighting_duration,
+                    pickup_type: st.pickup_type,
+                    drop_off_type: st.drop_off_type,
+                    datetime_estimated: st.datetime_estimated,
+                    local_zone_id: st.local_zone_id,
+                    extras: StopTimeExtras::boxed(
+                        st.shape_dist_traveled,
+                        st.continuous_pickup,
+                        st.continuous_drop_off,
+                    ),
+                });
+            }
+        }
+        c.vehicle_journeys = CollectionWithId::new(vehicle_journeys)?;
+
+        let mut calendars = c.calendars.take();
+        ensure!(
+            calendars.len() == self.calendar_dates.len(),
+            "inconsistent binary model: {} calendars for {} date lists",
+            calendars.len(),
+            self.calendar_dates.len()
+        );
+        for (cal, days) in calendars.iter_mut().zip(self.calendar_dates) {
+            cal.dates = days
+                .into_iter()
+                .map(|d| {
+                    NaiveDate::from_num_days_from_ce_opt(d)
+                        .ok_or_else(|| format_err!("invalid date {} in binary model", d))
+                })
+                .collect::<Result<BTreeSet<_>>>()?;
+        }
+        c.calendars = CollectionWithId::new(calendars)?;
+        Ok(())
+    }
+}
+
+/// Writes the model in a compact binary form, that `read_binary` loads
+/// back much faster than the original data, without any parsing nor
+/// relation building.
+///
+/// The data starts with a versioned header, a model written by
+/// another version of the binary format being refused when read.
+pub fn write_binary<W: Write>(model: &Model, mut writer: W) -> Result<()> {
+    bincode::serialize_into(&mut writer, &(BINARY_MAGIC, BINARY_VERSION))?;
+    bincode::serialize_into(&mut writer, &model.collections)?;
+    bincode::serialize_into(
+        &mut writer,
+        &BinaryExtras::from_collections(&model.collections),
+    )?;
+    Ok(())
+}
+
+/// Reads a model written by `write_binary`.
+pub fn read_binary<R: Read>(mut reader: R) -> Result<Model> {
+    let (magic, version): ([u8; 4], u32) =
+        bincode::deserialize_from(&mut reader).context("not a binary model")?;
+    ensure!(magic == BINARY_MAGIC, "not a binary model");
+    ensure!(
+        version == BINARY_VERSION,
+        "binary model of version {} while version {} is expected, it must be generated again",
+        version,
+        BINARY_VERSION
+    );
+    let mut collections: Collections = bincode::deserialize_from(&mut reader)?;
+    let extras: BinaryExtras = bincode::deserialize_from(&mut reader)?;
+    extras.apply(&mut collections)?;
+    Model::new(collections)
+}
+
+/// Saves the model to the file `path`, see `write_binary`.
+pub fn save_binary<P: AsRef<Path>>(model: &Model, path: P) -> Result<()> {
+    let path = path.as_ref();
+    let file = File::create(path).with_context(ctx_from_path!(path))?;
+    let mut writer = BufWriter::new(file);
+    write_binary(model, &mut writer).with_context(ctx_from_path!(path))?;
+    writer.flush().with_context(ctx_from_path!(path))?;
+    Ok(())
+}
+
+/// Loads the model saved by `save_binary` to the file `path`.
+pub fn load_binary<P: AsRef<Path>>(path: P) -> Result<Model> {
+    let path = path.as_ref();
+    let file = File::open(path).with_context(ctx_from_path!(path))?;
+    Ok(read_binary(BufReader::new(file)).with_context(ctx_from_path!(path))?)
+}
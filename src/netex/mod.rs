@@ -20,73 +20,121 @@ mod read;
 
 use self::read::NetexReader;
 use collection::CollectionWithId;
+use failure::ResultExt;
 use model::Model;
-use read_utils;
+use read_utils::{self, FileHandler, InputHandler};
+use report::{ParseProfile, Warning};
 use std::fs;
+use std::mem;
 use std::path::Path;
 use Result;
 extern crate tempdir;
-extern crate zip;
+
+// Whether the extension of `path` is `extension`, whatever its case.
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+}
+
+// Reads the xml files given by the file handler, by the order of their
+// names.
+fn read_files<H>(netex_reader: &mut NetexReader, file_handler: &mut H) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    for name in file_handler.file_names()? {
+        if has_extension(Path::new(&name), "xml") {
+            let (file, path) = file_handler.get_file(&name)?;
+            netex_reader
+                .read_netex_file(&name, file)
+                .with_context(ctx_from_path!(path))?;
+        } else {
+            info!("Netex read : skipping file {:?}", name);
+        }
+    }
+    Ok(())
+}
 
 /// Imports a `Model` from one or several [Netex](http://netex-cen.eu/) files.
-/// The `path` can be a single file, a directory or a zip file.
+/// The `path` can be a single file, a directory, a zip or a tar.gz
+/// archive, the xml files of a directory or an archive being read by
+/// the order of their names.
 /// Refers to the [Netex Github repo](https://github.com/NeTEx-CEN/NeTEx/)
-/// for details.
+/// for details.  The names given in other languages by the
+/// `alternativeTexts` of the stops, networks, lines and routes are
+/// read as their translations.
 ///
 /// The `config_path` argument allows you to give a path to a file
 /// containing a json representing the contributor and dataset used
 /// for this Netex file. If not given, default values will be created.
+/// The validity period of the dataset is the one of the read calendars.
 ///
 /// The `prefix` argument is a string that will be prepended to every
 /// identifiers, allowing to namespace the dataset. By default, no
 /// prefix will be added to the identifiers.
 pub fn read<P>(path: P, config_path: Option<P>, prefix: Option<String>) -> Result<Model>
+where
+    P: AsRef<Path>,
+{
+    read_with_profile(path, config_path, prefix, &ParseProfile::default()).map(|(model, _)| model)
+}
+
+/// Imports a `Model` from one or several [Netex](http://netex-cen.eu/)
+/// files like `read`, handling the issues met according to `profile`,
+/// and returns the elements that were skipped or fixed while reading.
+/// The service journeys without route, stopping at an unknown stop or
+/// without time at a stop are the issues handled.
+pub fn read_with_profile<P>(
+    path: P,
+    config_path: Option<P>,
+    prefix: Option<String>,
+    profile: &ParseProfile,
+) -> Result<(Model, Vec<Warning>)>
 where
     P: AsRef<Path>,
 {
     let path = path.as_ref();
-    info!("Reading Netex data from {:?}", path);
-    println!("Reading Netex data from {:?}", path);
-    let mut netex_reader = NetexReader::default();
-    if path.is_file() {
-        match path.extension().and_then(|ext| ext.to_str()) {
-            Some("zip") => {
-                let zip_file = fs::File::open(path)?;
-                let mut zip = zip::ZipArchive::new(zip_file)?;
-                for i in 0..zip.len() {
-                    let mut file = zip.by_index(i)?;
-                    match file.sanitized_name().extension() {
-                        Some(ext) if ext == "xml" => {
-                            netex_reader.read_netex_file(file)?;
-                        }
-                        _ => {
-                            info!(
-                                "Netex read : skipping file in ZIP : {:?}",
-                                file.sanitized_name()
-                            );
-                        }
-                    }
-                }
-            }
-            Some("xml") => netex_reader.read_netex_file(fs::File::open(path)?)?,
-            _ => bail!("Provided netex file should be xml or zip : {:?}", path),
-        };
-    } else {
-        for entry in fs::read_dir(path)? {
-            let file = entry?;
-            if file.path().extension().map_or(false, |ext| ext == "xml") {
-                let file = fs::File::open(file.path())?;
-                netex_reader.read_netex_file(file)?;
-            } else {
-                info!(
-                    "Netex read : skipping file in directory : {:?}",
-                    file.file_name()
-                );
-            }
-        }
-    };
+    if path.is_file() && has_extension(path, "xml") {
+        info!("Reading Netex data from {:?}", path);
+        let mut netex_reader = NetexReader::new(profile);
+        let file = fs::File::open(path).with_context(ctx_from_path!(path))?;
+        let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        netex_reader
+            .read_netex_file(&name, file)
+            .with_context(ctx_from_path!(path))?;
+        return build_model(netex_reader, config_path, prefix);
+    }
+    let mut file_handler = InputHandler::from_path(path)?;
+    read_from_handler(&mut file_handler, config_path, prefix, profile)
+}
+
+/// Imports a `Model` from the xml files given by the file handler, by
+/// the order of their names, like `read_with_profile`.
+pub fn read_from_handler<H, P>(
+    file_handler: &mut H,
+    config_path: Option<P>,
+    prefix: Option<String>,
+    profile: &ParseProfile,
+) -> Result<(Model, Vec<Warning>)>
+where
+    for<'a> &'a mut H: FileHandler,
+    P: AsRef<Path>,
+{
+    info!("Reading Netex data from {}", file_handler.source_name());
+    let mut netex_reader = NetexReader::new(profile);
+    read_files(&mut netex_reader, file_handler)?;
+    build_model(netex_reader, config_path, prefix)
+}
 
+fn build_model<P: AsRef<Path>>(
+    mut netex_reader: NetexReader,
+    config_path: Option<P>,
+    prefix: Option<String>,
+) -> Result<(Model, Vec<Warning>)> {
     let (contributor, mut dataset) = read_utils::read_config(config_path)?;
+    netex_reader.finalize(&dataset.id)?;
+
     let vp = read_utils::get_validity_period(&netex_reader.collections.calendars);
     let vp = match vp {
         None => bail!("No valid calendar in Netex Data"),
@@ -98,10 +146,13 @@ where
 
     netex_reader.collections.contributors = CollectionWithId::new(vec![contributor])?;
     netex_reader.collections.datasets = CollectionWithId::new(vec![dataset])?;
+    read_utils::add_id_mappings(&mut netex_reader.collections);
+
     //add prefixes
     if let Some(prefix) = prefix {
         read_utils::add_prefix(prefix, &mut netex_reader.collections)?;
     }
 
-    Ok(Model::new(netex_reader.collections)?)
+    let collections = mem::take(&mut netex_reader.collections);
+    Ok((Model::new(collections)?, netex_reader.into_warnings()))
 }
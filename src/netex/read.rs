@@ -14,9 +14,18 @@
 // along with this program.  If not, see
 // <http://www.gnu.org/licenses/>.
 
+use chrono::{self, Datelike};
+use collection::Idx;
+use gtfs::read::EquipmentList;
 use model::Collections;
-use objects;
+use objects::{
+    self, Availability, CommentLinksT, Coord, Date, KeysValues, ObjectType, StopPoint, StopTime,
+    Time,
+};
+use report::{Issue, Issues, ParseProfile, Warning};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::io::Read;
+use std::mem;
 use Result;
 
 extern crate minidom;
@@ -24,29 +33,321 @@ extern crate serde_json;
 use self::minidom::Element;
 use failure::ResultExt;
 
-// type RoutePointId = String;
-// type StopPointId = String;
-// type RoutePointMapping = HashMap<RoutePointId, StopPointId>;
-// type RouteLineMap = HashMap<String, String>;
+type RoutePointId = String;
+type StopPointId = String;
+type RoutePointMapping = HashMap<RoutePointId, StopPointId>;
+type RouteLineMap = HashMap<String, String>;
+
+// A stop point of the timetables, converted to a navitia stop point
+// once the quays of all the frames are known.
+struct ScheduledStopPoint {
+    name: String,
+    name_translations: Vec<(String, String)>,
+    coord: Option<Coord>,
+}
+
+// A stop of a journey pattern, referenced by the passing times.
+struct PatternStop {
+    stop_point_ref: String,
+    for_boarding: bool,
+    for_alighting: bool,
+}
+
+struct JourneyPattern {
+    route_id: Option<String>,
+    stops: HashMap<String, PatternStop>,
+}
+
+// The times at a stop of a service journey, the stop being a stop
+// point in a journey pattern or a scheduled stop point.
+struct PassingTime {
+    stop_ref: String,
+    arrival: Option<Time>,
+    departure: Option<Time>,
+}
+
+// A service journey, converted to a vehicle journey once the journey
+// patterns, stop points and day types of all the frames are known.
+struct ServiceJourney {
+    id: String,
+    // the file in which the journey was read
+    file: String,
+    day_types: Vec<String>,
+    route_id: Option<String>,
+    journey_pattern_id: Option<String>,
+    operator_id: Option<String>,
+    passing_times: Vec<PassingTime>,
+}
 
 #[derive(Default)]
 struct NetexContext {
+    // the file being read
+    file: String,
     namespace: String,
     first_operator_id: String,
-    // network_id: String,
-    // routepoint_mapping: RoutePointMapping,
-    // route_line_map: RouteLineMap,
-    // route_mode_map: HashMap<String, String>,
-    // journeypattern_route_map: HashMap<String, String>,
+    network_id: String,
+    routepoint_mapping: RoutePointMapping,
+    route_line_map: RouteLineMap,
+    route_mode_map: HashMap<String, String>,
+    scheduled_stop_points: BTreeMap<String, ScheduledStopPoint>,
+    // the quay or stop place assigned to each scheduled stop point
+    stop_assignments: HashMap<String, String>,
+    journey_patterns: HashMap<String, JourneyPattern>,
+    // the days of week of each day type, every day if empty
+    day_type_days: HashMap<String, Vec<chrono::Weekday>>,
+    day_type_dates: HashMap<String, BTreeSet<Date>>,
+    service_journeys: Vec<ServiceJourney>,
+    equipments: EquipmentList,
+}
+
+// The text of the child `name` of `node`.
+fn child_text(node: &Element, name: &str, ns: &str) -> Option<String> {
+    node.get_child(name, ns)
+        .map(|n| n.text().trim().to_string())
+}
+
+// The `ref` attribute of the child `name` of `node`.
+fn child_ref<'a>(node: &'a Element, name: &str, ns: &str) -> Option<&'a str> {
+    node.get_child(name, ns).and_then(|n| n.attr("ref"))
+}
+
+fn node_id(node: &Element) -> Result<&str> {
+    node.attr("id")
+        .ok_or_else(|| format_err!("A '{}' node doesn't have an 'id' property.", node.name()))
+}
+
+// The children `name` of the list `list` of `node`, as the `Line`s
+// of `lines`.
+fn list_items<'a>(
+    node: &'a Element,
+    list: &str,
+    name: &'a str,
+    ns: &str,
+) -> impl Iterator<Item = &'a Element> {
+    node.get_child(list, ns)
+        .into_iter()
+        .flat_map(|l| l.children())
+        .filter(move |n| n.name() == name)
+}
+
+// The translations of the `Name` of `node` by language, given by its
+// `alternativeTexts` as
+// `<AlternativeText attributeName="Name"><Text lang="fr">`.
+fn name_translations(node: &Element, ns: &str) -> Vec<(String, String)> {
+    list_items(node, "alternativeTexts", "AlternativeText", ns)
+        .filter(|text| match text.attr("attributeName") {
+            Some(attribute) => attribute == "Name",
+            None => true,
+        })
+        .filter_map(|text| text.get_child("Text", ns))
+        .filter_map(|text| {
+            let lang = text.attr("lang")?;
+            Some((lang.to_string(), text.text().trim().to_string()))
+        })
+        .collect()
+}
+
+// The coordinates of the `Location` of `node`.
+fn location(node: &Element, ns: &str) -> Result<Option<Coord>> {
+    let location = match node.get_child("Location", ns) {
+        Some(location) => location,
+        None => return Ok(None),
+    };
+    let coordinate = |name: &str| -> Result<f64> {
+        let value = child_text(location, name, ns)
+            .ok_or_else(|| format_err!("A 'Location' node doesn't have a '{}'", name))?;
+        Ok(value
+            .parse::<f64>()
+            .with_context(|_| format!("invalid {} {:?}", name, value))?)
+    };
+    Ok(Some(Coord {
+        lon: coordinate("Longitude")?,
+        lat: coordinate("Latitude")?,
+    }))
+}
+
+// The coordinates of the `Centroid` of `node`.
+fn centroid(node: &Element, ns: &str) -> Result<Option<Coord>> {
+    match node.get_child("Centroid", ns) {
+        Some(centroid) => location(centroid, ns),
+        None => Ok(None),
+    }
+}
+
+// The equipment described by the `AccessibilityAssessment` of `node`,
+// `None` if it gives no information.
+fn equipment(node: &Element, ns: &str) -> Option<objects::Equipment> {
+    let assessment = node.get_child("AccessibilityAssessment", ns)?;
+    let limitation = assessment
+        .get_child("limitations", ns)
+        .and_then(|l| l.get_child("AccessibilityLimitation", ns));
+    let availability = |name| match limitation.and_then(|l| child_text(l, name, ns)) {
+        Some(ref value) if value == "true" => Availability::Available,
+        Some(ref value) if value == "false" => Availability::NotAvailable,
+        _ => Availability::InformationNotAvailable,
+    };
+    let mut wheelchair_boarding = availability("WheelchairAccess");
+    if wheelchair_boarding == Availability::InformationNotAvailable {
+        wheelchair_boarding = match child_text(assessment, "MobilityImpairedAccess", ns) {
+            Some(ref value) if value == "true" => Availability::Available,
+            Some(ref value) if value == "false" => Availability::NotAvailable,
+            _ => Availability::InformationNotAvailable,
+        };
+    }
+    let equipment = objects::Equipment {
+        id: "".to_string(),
+        wheelchair_boarding,
+        sheltered: Availability::InformationNotAvailable,
+        elevator: Availability::InformationNotAvailable,
+        escalator: Availability::InformationNotAvailable,
+        bike_accepted: Availability::InformationNotAvailable,
+        bike_depot: Availability::InformationNotAvailable,
+        visual_announcement: availability("VisualSignsAvailable"),
+        audible_announcement: availability("AudibleSignalsAvailable"),
+        appropriate_escort: Availability::InformationNotAvailable,
+        appropriate_signage: Availability::InformationNotAvailable,
+    };
+    let unknown = [
+        &equipment.wheelchair_boarding,
+        &equipment.visual_announcement,
+        &equipment.audible_announcement,
+    ]
+    .iter()
+    .all(|a| **a == Availability::InformationNotAvailable);
+    if unknown {
+        None
+    } else {
+        Some(equipment)
+    }
+}
+
+// Parses a NeTEx date, as `2009-08-31` or `2016-04-03T00:00:00`.
+fn parse_date(value: &str) -> Result<Date> {
+    let date = value.get(..10).unwrap_or(value);
+    Ok(Date::parse_from_str(date, "%Y-%m-%d")
+        .with_context(|_| format!("invalid date {:?}", value))?)
+}
+
+// The time `name` of `node`, shifted by the number of days of its
+// `offset_name` child.
+fn child_time(node: &Element, name: &str, offset_name: &str, ns: &str) -> Result<Option<Time>> {
+    let time = match child_text(node, name, ns) {
+        Some(time) => time,
+        None => return Ok(None),
+    };
+    let parsed = time
+        .parse::<Time>()
+        .with_context(|_| format!("invalid time {:?}", time))?;
+    let days = match child_text(node, offset_name, ns) {
+        Some(offset) => offset
+            .parse::<u32>()
+            .with_context(|_| format!("invalid day offset {:?}", offset))?,
+        None => 0,
+    };
+    Ok(Some(Time::new(
+        parsed.hours() + 24 * days,
+        parsed.minutes(),
+        parsed.seconds(),
+    )))
+}
+
+// The days of a NeTEx `DaysOfWeek`, as `Monday Tuesday`.
+fn days_of_week(value: &str) -> Vec<chrono::Weekday> {
+    use self::chrono::Weekday::*;
+    value
+        .split_whitespace()
+        .flat_map(|day| match day {
+            "Monday" => vec![Mon],
+            "Tuesday" => vec![Tue],
+            "Wednesday" => vec![Wed],
+            "Thursday" => vec![Thu],
+            "Friday" => vec![Fri],
+            "Saturday" => vec![Sat],
+            "Sunday" => vec![Sun],
+            "Weekdays" => vec![Mon, Tue, Wed, Thu, Fri],
+            "Weekend" => vec![Sat, Sun],
+            "Everyday" => vec![Mon, Tue, Wed, Thu, Fri, Sat, Sun],
+            _ => vec![],
+        })
+        .collect()
+}
+
+// The dates from `from` to `to` on one of `days`, every day if empty.
+fn dates_between(from: Date, to: Date, days: &[chrono::Weekday]) -> BTreeSet<Date> {
+    let duration = to - from;
+    (0..duration.num_days() + 1)
+        .map(|i| from + chrono::Duration::days(i))
+        .filter(|d| days.is_empty() || days.contains(&d.weekday()))
+        .collect()
+}
+
+// The physical and commercial modes of a NeTEx `TransportMode`.
+fn modes(transport_mode: &str) -> (objects::PhysicalMode, objects::CommercialMode) {
+    let (id, name) = match transport_mode {
+        "air" => ("Air", "Airplane"),
+        "coach" => ("Coach", "Coach"),
+        "ferry" | "water" => ("Ferry", "Ferry"),
+        "metro" => ("Metro", "Metro"),
+        "rail" => ("Train", "Train"),
+        "tram" => ("Tramway", "Tramway"),
+        "funicular" | "cableway" | "telecabin" | "lift" => ("Funicular", "Funicular"),
+        _ => ("Bus", "Bus"),
+    };
+    (
+        objects::PhysicalMode {
+            id: id.to_string(),
+            name: name.to_string(),
+            co2_emission: None,
+        },
+        objects::CommercialMode {
+            id: id.to_string(),
+            name: name.to_string(),
+        },
+    )
 }
 
 #[derive(Default)]
 pub struct NetexReader {
     context: NetexContext,
+    issues: Issues,
     pub collections: Collections,
 }
 impl NetexReader {
-    pub fn read_netex_file<R: Read>(&mut self, mut file: R) -> Result<()> {
+    /// A reader handling the issues met according to `profile`.
+    pub fn new(profile: &ParseProfile) -> Self {
+        NetexReader {
+            issues: Issues::new(profile.clone()),
+            ..Default::default()
+        }
+    }
+
+    /// The elements that were skipped or fixed while reading.
+    pub fn into_warnings(self) -> Vec<Warning> {
+        self.issues.into_warnings()
+    }
+
+    // Adds the translations of the field `field_name` of an object.
+    fn push_translations(
+        &mut self,
+        object_type: &ObjectType,
+        object_id: &str,
+        field_name: &str,
+        translations: Vec<(String, String)>,
+    ) {
+        for (language, translation) in translations {
+            self.collections.translations.push(objects::Translation {
+                object_type: object_type.clone(),
+                object_id: object_id.to_string(),
+                field_name: field_name.to_string(),
+                language,
+                translation,
+            });
+        }
+    }
+
+    /// Reads the NeTEx file named `name`.
+    pub fn read_netex_file<R: Read>(&mut self, name: &str, mut file: R) -> Result<()> {
+        self.context.file = name.to_string();
         let mut file_content = "".to_string();
         file.read_to_string(&mut file_content)?;
         let root: Element = file_content.parse()?;
@@ -76,11 +377,11 @@ impl NetexReader {
             .children()
         {
             match frame.name() {
-                // "SiteFrame" => self.read_site_frame(&frame),
-                // "ServiceFrame" => self.read_service_frame(&frame),
-                // "ServiceCalendarFrame" => self.read_service_calendar_frame(&frame),
-                // "TimetableFrame" => self.read_time_table_frame(&frame),
-                "ResourceFrame" => self.read_resource_frame(&frame),
+                "SiteFrame" => self.read_site_frame(frame),
+                "ServiceFrame" => self.read_service_frame(frame),
+                "ServiceCalendarFrame" => self.read_service_calendar_frame(frame),
+                "TimetableFrame" => self.read_time_table_frame(frame),
+                "ResourceFrame" => self.read_resource_frame(frame),
                 _ => Ok(()),
             }?
         }
@@ -95,10 +396,724 @@ impl NetexReader {
         let organisations = resource_frame.get_child("organisations", &self.context.namespace);
         match organisations {
             None => Ok(()),
-            Some(orgs) => self.read_organisations(&orgs),
+            Some(orgs) => self.read_organisations(orgs),
         }
     }
 
+    fn read_site_frame(&mut self, site_frame: &Element) -> Result<()> {
+        let ns = self.context.namespace.clone();
+        for stop_place in list_items(site_frame, "stopPlaces", "StopPlace", &ns) {
+            self.read_stop_place(stop_place)?;
+        }
+        Ok(())
+    }
+
+    // Reads a stop place as a stop area and its quays as its stop
+    // points.
+    fn read_stop_place(&mut self, stop_place: &Element) -> Result<()> {
+        let ns = self.context.namespace.clone();
+        let ns = ns.as_str();
+        let id = node_id(stop_place)?;
+        if self.collections.stop_areas.get(id).is_some() {
+            return Ok(());
+        }
+        let name = child_text(stop_place, "Name", ns)
+            .or_else(|| {
+                stop_place
+                    .get_child("TopographicPlaceView", ns)
+                    .and_then(|view| child_text(view, "Name", ns))
+            })
+            .unwrap_or_default();
+        let quays = list_items(stop_place, "quays", "Quay", ns)
+            .map(|quay| Ok((quay, centroid(quay, ns)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let coord = match centroid(stop_place, ns)? {
+            Some(coord) => coord,
+            None => {
+                // the barycenter of the quays
+                let coords: Vec<_> = quays.iter().filter_map(|&(_, coord)| coord).collect();
+                let len = coords.len().max(1) as f64;
+                Coord {
+                    lon: coords.iter().map(|c| c.lon).sum::<f64>() / len,
+                    lat: coords.iter().map(|c| c.lat).sum::<f64>() / len,
+                }
+            }
+        };
+        let equipment_id = equipment(stop_place, ns).map(|e| self.context.equipments.push(e));
+        self.collections.stop_areas.push(objects::StopArea {
+            id: id.to_string(),
+            name: name.clone(),
+            codes: KeysValues::default(),
+            object_properties: KeysValues::default(),
+            comment_links: CommentLinksT::default(),
+            visible: true,
+            coord,
+            timezone: None,
+            geometry_id: None,
+            equipment_id: equipment_id.clone(),
+        })?;
+        let translations = name_translations(stop_place, ns);
+        self.push_translations(&ObjectType::StopArea, id, "stop_name", translations);
+        for (quay, quay_coord) in quays {
+            let quay_id = node_id(quay)?;
+            if self.collections.stop_points.get(quay_id).is_some() {
+                continue;
+            }
+            let quay_equipment_id = match equipment(quay, ns) {
+                Some(equipment) => Some(self.context.equipments.push(equipment)),
+                None => equipment_id.clone(),
+            };
+            self.collections.stop_points.push(objects::StopPoint {
+                id: quay_id.to_string(),
+                name: child_text(quay, "Name", ns)
+                    .or_else(|| child_text(quay, "Label", ns))
+                    .unwrap_or_else(|| name.clone()),
+                codes: KeysValues::default(),
+                object_properties: KeysValues::default(),
+                comment_links: CommentLinksT::default(),
+                visible: true,
+                coord: quay_coord.unwrap_or(coord),
+                stop_area_id: id.into(),
+                timezone: None,
+                geometry_id: None,
+                equipment_id: quay_equipment_id,
+                fare_zone_id: None,
+                address_id: None,
+            })?;
+            let translations = name_translations(quay, ns);
+            self.push_translations(&ObjectType::StopPoint, quay_id, "stop_name", translations);
+        }
+        Ok(())
+    }
+
+    fn read_service_frame(&mut self, service_frame: &Element) -> Result<()> {
+        let ns = self.context.namespace.clone();
+        let ns = ns.as_str();
+        if let Some(network) = service_frame.get_child("Network", ns) {
+            self.read_network(network)?;
+        }
+        let directions = list_items(service_frame, "directions", "Direction", ns)
+            .map(|direction| {
+                let direction_type = child_text(direction, "DirectionType", ns);
+                Ok((node_id(direction)?.to_string(), direction_type))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+        let mut route_point_coords = HashMap::new();
+        for route_point in list_items(service_frame, "routePoints", "RoutePoint", ns) {
+            let id = node_id(route_point)?;
+            let stop_point_ref = list_items(route_point, "projections", "PointProjection", ns)
+                .filter_map(|projection| {
+                    child_ref(projection, "ProjectToPointRef", ns)
+                        .or_else(|| child_ref(projection, "ProjectedPointRef", ns))
+                })
+                .next();
+            if let Some(stop_point_ref) = stop_point_ref {
+                self.context
+                    .routepoint_mapping
+                    .insert(id.to_string(), stop_point_ref.to_string());
+                if let Some(coord) = location(route_point, ns)? {
+                    route_point_coords.insert(stop_point_ref, coord);
+                }
+            }
+        }
+        for stop_point in list_items(
+            service_frame,
+            "scheduledStopPoints",
+            "ScheduledStopPoint",
+            ns,
+        ) {
+            let id = node_id(stop_point)?;
+            let coord = location(stop_point, ns)?.or_else(|| route_point_coords.get(id).cloned());
+            self.context.scheduled_stop_points.insert(
+                id.to_string(),
+                ScheduledStopPoint {
+                    name: child_text(stop_point, "Name", ns).unwrap_or_default(),
+                    name_translations: name_translations(stop_point, ns),
+                    coord,
+                },
+            );
+        }
+        for assignment in list_items(
+            service_frame,
+            "stopAssignments",
+            "PassengerStopAssignment",
+            ns,
+        ) {
+            let stop_point_ref = child_ref(assignment, "ScheduledStopPointRef", ns);
+            let stop_ref = child_ref(assignment, "QuayRef", ns)
+                .or_else(|| child_ref(assignment, "StopPlaceRef", ns));
+            if let (Some(stop_point_ref), Some(stop_ref)) = (stop_point_ref, stop_ref) {
+                self.context
+                    .stop_assignments
+                    .insert(stop_point_ref.to_string(), stop_ref.to_string());
+            }
+        }
+        let mut line_modes = HashMap::new();
+        for line in list_items(service_frame, "lines", "Line", ns) {
+            let (line_id, physical_mode_id) = self.read_line(line)?;
+            line_modes.insert(line_id, physical_mode_id);
+        }
+        for route in list_items(service_frame, "routes", "Route", ns) {
+            let id = node_id(route)?;
+            let line_id = child_ref(route, "LineRef", ns)
+                .or_else(|| self.context.route_line_map.get(id).map(|l| l.as_str()))
+                .ok_or_else(|| format_err!("The Route {:?} doesn't belong to any Line", id))?
+                .to_string();
+            if let Some(physical_mode_id) = line_modes.get(&line_id) {
+                self.context
+                    .route_mode_map
+                    .insert(id.to_string(), physical_mode_id.clone());
+            }
+            let direction_type = child_text(route, "DirectionType", ns).or_else(|| {
+                child_ref(route, "DirectionRef", ns)
+                    .and_then(|d| directions.get(d).cloned())
+                    .and_then(|d| d)
+            });
+            self.context
+                .route_line_map
+                .insert(id.to_string(), line_id.clone());
+            self.collections.routes.push(objects::Route {
+                id: id.to_string(),
+                name: child_text(route, "Name", ns).unwrap_or_default(),
+                direction_type,
+                codes: KeysValues::default(),
+                object_properties: KeysValues::default(),
+                comment_links: CommentLinksT::default(),
+                line_id: line_id.into(),
+                geometry_id: None,
+                destination_id: None,
+                continuous_pickup: None,
+                continuous_drop_off: None,
+            })?;
+            let translations = name_translations(route, ns);
+            self.push_translations(&ObjectType::Route, id, "route_name", translations);
+        }
+        for pattern in ["journeyPatterns", "servicePatterns"]
+            .iter()
+            .filter_map(|list| service_frame.get_child(list, ns))
+            .flat_map(|list| list.children())
+        {
+            let stops = list_items(pattern, "pointsInSequence", "StopPointInJourneyPattern", ns)
+                .map(|stop| {
+                    let id = node_id(stop)?;
+                    let stop_point_ref =
+                        child_ref(stop, "ScheduledStopPointRef", ns).ok_or_else(|| {
+                            format_err!("The StopPointInJourneyPattern {:?} has no stop", id)
+                        })?;
+                    let allowed = |name| child_text(stop, name, ns).is_none_or(|a| a != "false");
+                    let stop = PatternStop {
+                        stop_point_ref: stop_point_ref.to_string(),
+                        for_boarding: allowed("ForBoarding"),
+                        for_alighting: allowed("ForAlighting"),
+                    };
+                    Ok((id.to_string(), stop))
+                })
+                .collect::<Result<_>>()?;
+            self.context.journey_patterns.insert(
+                node_id(pattern)?.to_string(),
+                JourneyPattern {
+                    route_id: child_ref(pattern, "RouteRef", ns).map(|r| r.to_string()),
+                    stops,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    // Reads the dates of the day types, given by dates or operating
+    // periods, or by their days of week during the service calendar.
+    fn read_service_calendar_frame(&mut self, frame: &Element) -> Result<()> {
+        let ns = self.context.namespace.clone();
+        let ns = ns.as_str();
+        for day_type in list_items(frame, "dayTypes", "DayType", ns) {
+            let days = list_items(day_type, "properties", "PropertyOfDay", ns)
+                .filter_map(|property| child_text(property, "DaysOfWeek", ns))
+                .flat_map(|days| days_of_week(&days))
+                .collect();
+            let id = node_id(day_type)?.to_string();
+            self.context.day_type_dates.entry(id.clone()).or_default();
+            self.context.day_type_days.insert(id, days);
+        }
+        let periods = list_items(frame, "operatingPeriods", "OperatingPeriod", ns)
+            .map(|period| {
+                let id = node_id(period)?;
+                let date = |name| -> Result<Date> {
+                    let date = child_text(period, name, ns).ok_or_else(|| {
+                        format_err!("The OperatingPeriod {:?} doesn't have a {}", id, name)
+                    })?;
+                    parse_date(&date)
+                };
+                Ok((id.to_string(), (date("FromDate")?, date("ToDate")?)))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+        let mut assigned = HashSet::new();
+        for assignment in list_items(frame, "dayTypeAssignments", "DayTypeAssignment", ns) {
+            let day_type_id = match child_ref(assignment, "DayTypeRef", ns) {
+                Some(day_type_id) => day_type_id,
+                None => continue,
+            };
+            let days = self
+                .context
+                .day_type_days
+                .get(day_type_id)
+                .cloned()
+                .unwrap_or_default();
+            let dates = if let Some(date) = child_text(assignment, "Date", ns) {
+                let mut dates = BTreeSet::new();
+                dates.insert(parse_date(&date)?);
+                dates
+            } else if let Some(period) = child_ref(assignment, "OperatingPeriodRef", ns) {
+                let &(from, to) = periods
+                    .get(period)
+                    .ok_or_else(|| format_err!("The OperatingPeriod {:?} is not found", period))?;
+                dates_between(from, to, &days)
+            } else {
+                continue;
+            };
+            assigned.insert(day_type_id.to_string());
+            let day_type_dates = self
+                .context
+                .day_type_dates
+                .entry(day_type_id.to_string())
+                .or_default();
+            if child_text(assignment, "isAvailable", ns).is_none_or(|a| a != "false") {
+                day_type_dates.extend(dates);
+            } else {
+                for date in &dates {
+                    day_type_dates.remove(date);
+                }
+            }
+        }
+        let calendar = match frame.get_child("ServiceCalendar", ns) {
+            Some(calendar) => calendar,
+            None => return Ok(()),
+        };
+        let from = child_text(calendar, "FromDate", ns);
+        let to = child_text(calendar, "ToDate", ns);
+        if let (Some(from), Some(to)) = (from, to) {
+            let (from, to) = (parse_date(&from)?, parse_date(&to)?);
+            for day_type in list_items(frame, "dayTypes", "DayType", ns) {
+                let id = node_id(day_type)?;
+                let days = &self.context.day_type_days[id];
+                if assigned.contains(id) || days.is_empty() {
+                    continue;
+                }
+                let dates = dates_between(from, to, days);
+                if let Some(day_type_dates) = self.context.day_type_dates.get_mut(id) {
+                    day_type_dates.extend(dates);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn read_time_table_frame(&mut self, frame: &Element) -> Result<()> {
+        let ns = self.context.namespace.clone();
+        let ns = ns.as_str();
+        for journey in list_items(frame, "vehicleJourneys", "ServiceJourney", ns) {
+            let id = node_id(journey)?;
+            let no_stop =
+                || format_err!("A passing time of the ServiceJourney {:?} has no stop", id);
+            let mut passing_times = vec![];
+            for passing_time in list_items(journey, "passingTimes", "TimetabledPassingTime", ns) {
+                let stop_ref = child_ref(passing_time, "StopPointInJourneyPatternRef", ns)
+                    .or_else(|| child_ref(passing_time, "ScheduledStopPointRef", ns))
+                    .ok_or_else(no_stop)?;
+                passing_times.push(PassingTime {
+                    stop_ref: stop_ref.to_string(),
+                    arrival: child_time(passing_time, "ArrivalTime", "ArrivalDayOffset", ns)?,
+                    departure: child_time(passing_time, "DepartureTime", "DepartureDayOffset", ns)?,
+                });
+            }
+            for call in list_items(journey, "calls", "Call", ns) {
+                let stop_ref = child_ref(call, "ScheduledStopPointRef", ns).ok_or_else(no_stop)?;
+                let time = |name| match call.get_child(name, ns) {
+                    Some(event) => child_time(event, "Time", "DayOffset", ns),
+                    None => Ok(None),
+                };
+                passing_times.push(PassingTime {
+                    stop_ref: stop_ref.to_string(),
+                    arrival: time("Arrival")?,
+                    departure: time("Departure")?,
+                });
+            }
+            let journey_pattern_id = child_ref(journey, "JourneyPatternRef", ns)
+                .or_else(|| child_ref(journey, "ServiceJourneyPatternRef", ns));
+            self.context.service_journeys.push(ServiceJourney {
+                id: id.to_string(),
+                file: self.context.file.clone(),
+                day_types: list_items(journey, "dayTypes", "DayTypeRef", ns)
+                    .filter_map(|day_type| day_type.attr("ref"))
+                    .map(|day_type| day_type.to_string())
+                    .collect(),
+                route_id: child_ref(journey, "RouteRef", ns).map(|r| r.to_string()),
+                journey_pattern_id: journey_pattern_id.map(|j| j.to_string()),
+                operator_id: child_ref(journey, "OperatorRef", ns).map(|o| o.to_string()),
+                passing_times,
+            });
+        }
+        Ok(())
+    }
+
+    fn read_network(&mut self, network: &Element) -> Result<()> {
+        let id = node_id(network)?;
+        self.context.network_id = id.to_string();
+        if self.collections.networks.get(id).is_none() {
+            self.collections.networks.push(objects::Network {
+                id: id.to_string(),
+                name: child_text(network, "Name", &self.context.namespace).unwrap_or_default(),
+                url: None,
+                codes: KeysValues::default(),
+                timezone: None,
+                lang: None,
+                phone: None,
+                address: None,
+                sort_order: None,
+            })?;
+            let translations = name_translations(network, &self.context.namespace);
+            self.push_translations(&ObjectType::Network, id, "network_name", translations);
+        }
+        Ok(())
+    }
+
+    // Reads a line and returns its id with its physical mode.
+    fn read_line(&mut self, line: &Element) -> Result<(String, String)> {
+        let ns = self.context.namespace.clone();
+        let ns = ns.as_str();
+        let id = node_id(line)?.to_string();
+        let transport_mode = child_text(line, "TransportMode", ns).unwrap_or_default();
+        let (physical_mode, commercial_mode) = modes(&transport_mode);
+        let physical_mode_id = physical_mode.id.clone();
+        let commercial_mode_id = commercial_mode.id.clone();
+        if self
+            .collections
+            .physical_modes
+            .get(&physical_mode.id)
+            .is_none()
+        {
+            self.collections.physical_modes.push(physical_mode)?;
+        }
+        if self
+            .collections
+            .commercial_modes
+            .get(&commercial_mode.id)
+            .is_none()
+        {
+            self.collections.commercial_modes.push(commercial_mode)?;
+        }
+        for route_ref in list_items(line, "routes", "RouteRef", ns) {
+            if let Some(route_id) = route_ref.attr("ref") {
+                self.context
+                    .route_line_map
+                    .insert(route_id.to_string(), id.clone());
+            }
+        }
+        let network_id = match child_ref(line, "RepresentedByGroupRef", ns) {
+            Some(group) if self.collections.networks.get(group).is_some() => group.to_string(),
+            _ => self.network_id()?,
+        };
+        self.collections.lines.push(objects::Line {
+            id: id.clone(),
+            code: child_text(line, "PublicCode", ns),
+            codes: KeysValues::default(),
+            object_properties: KeysValues::default(),
+            comment_links: CommentLinksT::default(),
+            name: child_text(line, "Name", ns).unwrap_or_default(),
+            forward_name: None,
+            forward_direction: None,
+            backward_name: None,
+            backward_direction: None,
+            color: None,
+            text_color: None,
+            sort_order: None,
+            network_id: network_id.into(),
+            commercial_mode_id,
+            geometry_id: None,
+            opening_time: None,
+            closing_time: None,
+        })?;
+        let translations = name_translations(line, ns);
+        self.push_translations(&ObjectType::Line, &id, "line_name", translations);
+        Ok((id, physical_mode_id))
+    }
+
+    // The network of the current frame, a default one being created
+    // if none has been read.
+    fn network_id(&mut self) -> Result<String> {
+        if self.context.network_id.is_empty() {
+            self.context.network_id = "default_network".to_string();
+            if self.collections.networks.get("default_network").is_none() {
+                self.collections.networks.push(objects::Network {
+                    id: "default_network".to_string(),
+                    name: "Default network".to_string(),
+                    url: None,
+                    codes: KeysValues::default(),
+                    timezone: None,
+                    lang: None,
+                    phone: None,
+                    address: None,
+                    sort_order: None,
+                })?;
+            }
+        }
+        Ok(self.context.network_id.clone())
+    }
+
+    /// Converts the scheduled stop points of all the read files to
+    /// stop points, the ones assigned to a known quay being replaced
+    /// by it, then the service journeys to vehicle journeys of the
+    /// dataset `dataset_id`.
+    pub fn finalize(&mut self, dataset_id: &str) -> Result<()> {
+        let equipments = mem::take(&mut self.context.equipments);
+        for equipment in equipments.into_equipments() {
+            self.collections.equipments.push(equipment)?;
+        }
+        let scheduled_stop_points = mem::take(&mut self.context.scheduled_stop_points);
+        for (id, scheduled_stop_point) in scheduled_stop_points {
+            let assigned = self.context.stop_assignments.get(&id).cloned();
+            if let Some(ref quay_id) = assigned {
+                if self.collections.stop_points.get(quay_id).is_some() {
+                    continue;
+                }
+            }
+            let coord = scheduled_stop_point
+                .coord
+                .unwrap_or(Coord { lon: 0., lat: 0. });
+            let stop_area_id = match assigned {
+                Some(ref stop_place_id)
+                    if self.collections.stop_areas.get(stop_place_id).is_some() =>
+                {
+                    stop_place_id.clone()
+                }
+                _ => {
+                    let stop_area_id = format!("Navitia:{}", id);
+                    self.collections.stop_areas.push(objects::StopArea {
+                        id: stop_area_id.clone(),
+                        name: scheduled_stop_point.name.clone(),
+                        codes: KeysValues::default(),
+                        object_properties: KeysValues::default(),
+                        comment_links: CommentLinksT::default(),
+                        visible: true,
+                        coord,
+                        timezone: None,
+                        geometry_id: None,
+                        equipment_id: None,
+                    })?;
+                    self.push_translations(
+                        &ObjectType::StopArea,
+                        &stop_area_id,
+                        "stop_name",
+                        scheduled_stop_point.name_translations.clone(),
+                    );
+                    stop_area_id
+                }
+            };
+            self.push_translations(
+                &ObjectType::StopPoint,
+                &id,
+                "stop_name",
+                scheduled_stop_point.name_translations,
+            );
+            self.collections.stop_points.push(objects::StopPoint {
+                id,
+                name: scheduled_stop_point.name,
+                codes: KeysValues::default(),
+                object_properties: KeysValues::default(),
+                comment_links: CommentLinksT::default(),
+                visible: true,
+                coord,
+                stop_area_id: stop_area_id.into(),
+                timezone: None,
+                geometry_id: None,
+                equipment_id: None,
+                fare_zone_id: None,
+                address_id: None,
+            })?;
+        }
+        let service_journeys = mem::take(&mut self.context.service_journeys);
+        for journey in service_journeys {
+            self.read_vehicle_journey(journey, dataset_id)
+                .with_context(|_| "Converting a ServiceJourney")?;
+        }
+        Ok(())
+    }
+
+    fn read_vehicle_journey(&mut self, journey: ServiceJourney, dataset_id: &str) -> Result<()> {
+        let service_id = match self.calendar_id(&journey.day_types)? {
+            Some(service_id) => service_id,
+            None => {
+                let message = format!("ServiceJourney {:?} ignored, it never runs", journey.id);
+                self.issues.warn(&journey.file, message);
+                return Ok(());
+            }
+        };
+        let pattern = journey
+            .journey_pattern_id
+            .as_ref()
+            .and_then(|id| self.context.journey_patterns.get(id));
+        let route_id = match journey
+            .route_id
+            .as_ref()
+            .or_else(|| pattern.and_then(|p| p.route_id.as_ref()))
+        {
+            Some(route_id) => route_id.clone(),
+            None => {
+                self.issues.handle_object(
+                    Issue::InvalidRow,
+                    &journey.file,
+                    &journey.id,
+                    format!("The ServiceJourney {:?} has no route", journey.id),
+                )?;
+                return Ok(());
+            }
+        };
+        let mut stop_times = vec![];
+        for (sequence, passing_time) in journey.passing_times.iter().enumerate() {
+            let stop = pattern.and_then(|p| p.stops.get(&passing_time.stop_ref));
+            let stop_point_ref = stop.map_or(passing_time.stop_ref.as_str(), |s| {
+                s.stop_point_ref.as_str()
+            });
+            let stop_point_idx = match self.stop_point_idx(stop_point_ref) {
+                Some(stop_point_idx) => stop_point_idx,
+                None => {
+                    self.issues.handle_object(
+                        Issue::UnknownReference,
+                        &journey.file,
+                        &journey.id,
+                        format!(
+                            "The ServiceJourney {:?} stops at the unknown {:?}",
+                            journey.id, stop_point_ref
+                        ),
+                    )?;
+                    return Ok(());
+                }
+            };
+            let (arrival_time, departure_time) =
+                match (passing_time.arrival, passing_time.departure) {
+                    (Some(arrival), Some(departure)) => (arrival, departure),
+                    (Some(time), None) | (None, Some(time)) => (time, time),
+                    (None, None) => {
+                        self.issues.handle_object(
+                            Issue::InvalidRow,
+                            &journey.file,
+                            &journey.id,
+                            format!(
+                                "The ServiceJourney {:?} has no time at {:?}",
+                                journey.id, stop_point_ref
+                            ),
+                        )?;
+                        return Ok(());
+                    }
+                };
+            let (pickup_type, drop_off_type) = match stop {
+                Some(stop) => (u8::from(!stop.for_boarding), u8::from(!stop.for_alighting)),
+                None => (0, 0),
+            };
+            stop_times.push(StopTime {
+                stop_point_idx,
+                sequence: sequence as u32,
+                arrival_time,
+                departure_time,
+                boarding_duration: 0,
+                alighting_duration: 0,
+                pickup_type,
+                drop_off_type,
+                datetime_estimated: false,
+                local_zone_id: None,
+                extras: None,
+            });
+        }
+        let physical_mode_id = match self.context.route_mode_map.get(&route_id) {
+            Some(physical_mode_id) => physical_mode_id.clone(),
+            None => {
+                let (physical_mode, _) = modes("bus");
+                let physical_mode_id = physical_mode.id.clone();
+                if self
+                    .collections
+                    .physical_modes
+                    .get(&physical_mode_id)
+                    .is_none()
+                {
+                    self.collections.physical_modes.push(physical_mode)?;
+                }
+                physical_mode_id
+            }
+        };
+        let company_id = match journey.operator_id {
+            Some(ref company_id) if self.collections.companies.get(company_id).is_some() => {
+                company_id.clone()
+            }
+            _ => self.company_id()?,
+        };
+        self.collections
+            .vehicle_journeys
+            .push(objects::VehicleJourney {
+                id: journey.id,
+                route_id: route_id.into(),
+                physical_mode_id,
+                dataset_id: dataset_id.to_string(),
+                service_id: service_id.into(),
+                company_id,
+                stop_times,
+                ..Default::default()
+            })?;
+        Ok(())
+    }
+
+    // The calendar of the union of the dates of `day_types`, created
+    // if needed, `None` if they have no date.
+    fn calendar_id(&mut self, day_types: &[String]) -> Result<Option<String>> {
+        let id = day_types.join("+");
+        if self.collections.calendars.get(&id).is_some() {
+            return Ok(Some(id));
+        }
+        let dates: BTreeSet<Date> = day_types
+            .iter()
+            .filter_map(|day_type| self.context.day_type_dates.get(day_type))
+            .flat_map(|dates| dates.iter().cloned())
+            .collect();
+        if dates.is_empty() {
+            return Ok(None);
+        }
+        self.collections.calendars.push(objects::Calendar {
+            id: id.clone(),
+            dates,
+        })?;
+        Ok(Some(id))
+    }
+
+    // The stop point of a scheduled stop point, or of the route point
+    // projected on it, its assigned quay if known.
+    fn stop_point_idx(&self, id: &str) -> Option<Idx<StopPoint>> {
+        let id = self
+            .context
+            .routepoint_mapping
+            .get(id)
+            .map_or(id, |id| id.as_str());
+        let stop_points = &self.collections.stop_points;
+        self.context
+            .stop_assignments
+            .get(id)
+            .and_then(|quay_id| stop_points.get_idx(quay_id))
+            .or_else(|| stop_points.get_idx(id))
+    }
+
+    // The operator of the read files, a default company being created
+    // if none has been read.
+    fn company_id(&mut self) -> Result<String> {
+        if self.context.first_operator_id.is_empty() {
+            self.context.first_operator_id = objects::Company::default().id;
+        }
+        let id = self.context.first_operator_id.clone();
+        if self.collections.companies.get(&id).is_none() {
+            self.collections.companies.push(objects::Company {
+                id: id.clone(),
+                ..Default::default()
+            })?;
+        }
+        Ok(id)
+    }
+
     fn read_organisations(&mut self, organisations: &Element) -> Result<()> {
         let companies = organisations
             .children()
@@ -142,6 +1157,8 @@ impl NetexReader {
 mod tests {
     extern crate minidom;
     use self::minidom::Element;
+    use objects::{Availability, Time};
+    use report::{Issue, ParseProfile};
 
     #[test]
     fn test_read_organisations_empty() {
@@ -200,4 +1217,400 @@ mod tests {
         assert!(netex_reader.read_organisations(&organisations).is_err());
         assert_eq!(netex_reader.collections.companies.len(), 0);
     }
+
+    #[test]
+    fn test_read_service_frame() {
+        let mut netex_reader = super::NetexReader::default();
+        let netex = r#"<PublicationDelivery xmlns="http://www.netex.org.uk/netex">
+            <dataObjects><CompositeFrame id="cf"><frames>
+                <ServiceFrame id="sf">
+                    <Network id="net"><Name>Network</Name></Network>
+                    <directions>
+                        <Direction id="dir"><DirectionType>inbound</DirectionType></Direction>
+                    </directions>
+                    <routePoints>
+                        <RoutePoint id="rp1">
+                            <Location>
+                                <Longitude>2.37</Longitude><Latitude>48.84</Latitude>
+                            </Location>
+                            <projections><PointProjection id="pp1">
+                                <ProjectToPointRef ref="ssp1"/>
+                            </PointProjection></projections>
+                        </RoutePoint>
+                    </routePoints>
+                    <routes>
+                        <Route id="route1"><Name>Outbound</Name><LineRef ref="line1"/>
+                            <DirectionType>outbound</DirectionType></Route>
+                        <Route id="route2"><Name>Inbound</Name><DirectionRef ref="dir"/></Route>
+                    </routes>
+                    <lines>
+                        <Line id="line1"><Name>Line 1</Name><TransportMode>tram</TransportMode>
+                            <PublicCode>1</PublicCode>
+                            <routes><RouteRef ref="route2"/></routes>
+                        </Line>
+                    </lines>
+                    <scheduledStopPoints>
+                        <ScheduledStopPoint id="ssp1"><Name>Gare</Name></ScheduledStopPoint>
+                        <ScheduledStopPoint id="ssp2"><Name>Mairie</Name></ScheduledStopPoint>
+                    </scheduledStopPoints>
+                    <stopAssignments>
+                        <PassengerStopAssignment id="psa1">
+                            <ScheduledStopPointRef ref="ssp1"/><QuayRef ref="quay1"/>
+                        </PassengerStopAssignment>
+                    </stopAssignments>
+                </ServiceFrame>
+            </frames></CompositeFrame></dataObjects>
+        </PublicationDelivery>"#;
+        netex_reader
+            .read_netex_file("netex.xml", netex.as_bytes())
+            .unwrap();
+        netex_reader.finalize("default_dataset").unwrap();
+        let collections = &netex_reader.collections;
+
+        let line = collections.lines.get("line1").unwrap();
+        assert_eq!("Line 1", line.name);
+        assert_eq!(Some("1".to_string()), line.code);
+        assert_eq!("net", line.network_id);
+        assert_eq!("Tramway", line.commercial_mode_id);
+        assert_eq!(1, collections.physical_modes.len());
+        let routes: Vec<_> = collections
+            .routes
+            .values()
+            .map(|r| (r.id.as_str(), r.line_id.as_str(), r.direction_type.clone()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("route1", "line1", Some("outbound".to_string())),
+                ("route2", "line1", Some("inbound".to_string())),
+            ],
+            routes
+        );
+        assert_eq!("Tramway", netex_reader.context.route_mode_map["route2"]);
+
+        let stop_point = collections.stop_points.get("ssp1").unwrap();
+        assert_eq!("Gare", stop_point.name);
+        assert_eq!(2.37, stop_point.coord.lon);
+        assert_eq!("Navitia:ssp1", stop_point.stop_area_id);
+        assert_eq!(2, collections.stop_areas.len());
+        assert_eq!("quay1", netex_reader.context.stop_assignments["ssp1"]);
+    }
+
+    #[test]
+    fn test_read_time_table_frame() {
+        let mut netex_reader = super::NetexReader::default();
+        let netex = r#"<PublicationDelivery xmlns="http://www.netex.org.uk/netex">
+            <dataObjects><CompositeFrame id="cf"><frames>
+                <ServiceFrame id="sf">
+                    <lines><Line id="line1"><Name>Line 1</Name></Line></lines>
+                    <routes><Route id="route1"><LineRef ref="line1"/></Route></routes>
+                    <scheduledStopPoints>
+                        <ScheduledStopPoint id="ssp1"><Name>Gare</Name></ScheduledStopPoint>
+                        <ScheduledStopPoint id="ssp2"><Name>Mairie</Name></ScheduledStopPoint>
+                    </scheduledStopPoints>
+                    <journeyPatterns>
+                        <JourneyPattern id="jp1"><RouteRef ref="route1"/>
+                            <pointsInSequence>
+                                <StopPointInJourneyPattern id="spjp1" order="1">
+                                    <ScheduledStopPointRef ref="ssp1"/>
+                                    <ForAlighting>false</ForAlighting>
+                                </StopPointInJourneyPattern>
+                                <StopPointInJourneyPattern id="spjp2" order="2">
+                                    <ScheduledStopPointRef ref="ssp2"/>
+                                    <ForBoarding>false</ForBoarding>
+                                </StopPointInJourneyPattern>
+                            </pointsInSequence>
+                        </JourneyPattern>
+                    </journeyPatterns>
+                </ServiceFrame>
+                <ServiceCalendarFrame id="scf">
+                    <dayTypes>
+                        <DayType id="weekend"><properties><PropertyOfDay>
+                            <DaysOfWeek>Saturday Sunday</DaysOfWeek>
+                        </PropertyOfDay></properties></DayType>
+                        <DayType id="holiday"/>
+                    </dayTypes>
+                    <operatingPeriods>
+                        <OperatingPeriod id="may">
+                            <FromDate>2018-05-01T00:00:00</FromDate>
+                            <ToDate>2018-05-13T00:00:00</ToDate>
+                        </OperatingPeriod>
+                    </operatingPeriods>
+                    <dayTypeAssignments>
+                        <DayTypeAssignment id="a1">
+                            <OperatingPeriodRef ref="may"/><DayTypeRef ref="weekend"/>
+                        </DayTypeAssignment>
+                        <DayTypeAssignment id="a2">
+                            <Date>2018-05-06</Date><DayTypeRef ref="weekend"/>
+                            <isAvailable>false</isAvailable>
+                        </DayTypeAssignment>
+                        <DayTypeAssignment id="a3">
+                            <Date>2018-05-08</Date><DayTypeRef ref="holiday"/>
+                        </DayTypeAssignment>
+                    </dayTypeAssignments>
+                </ServiceCalendarFrame>
+                <TimetableFrame id="tf">
+                    <vehicleJourneys>
+                        <ServiceJourney id="sj1">
+                            <dayTypes>
+                                <DayTypeRef ref="weekend"/><DayTypeRef ref="holiday"/>
+                            </dayTypes>
+                            <JourneyPatternRef ref="jp1"/>
+                            <passingTimes>
+                                <TimetabledPassingTime>
+                                    <StopPointInJourneyPatternRef ref="spjp1"/>
+                                    <DepartureTime>23:50:00</DepartureTime>
+                                </TimetabledPassingTime>
+                                <TimetabledPassingTime>
+                                    <StopPointInJourneyPatternRef ref="spjp2"/>
+                                    <ArrivalTime>00:10:00</ArrivalTime>
+                                    <ArrivalDayOffset>1</ArrivalDayOffset>
+                                </TimetabledPassingTime>
+                            </passingTimes>
+                        </ServiceJourney>
+                    </vehicleJourneys>
+                </TimetableFrame>
+            </frames></CompositeFrame></dataObjects>
+        </PublicationDelivery>"#;
+        netex_reader
+            .read_netex_file("netex.xml", netex.as_bytes())
+            .unwrap();
+        netex_reader.finalize("default_dataset").unwrap();
+        let collections = &netex_reader.collections;
+
+        let calendar = collections.calendars.get("weekend+holiday").unwrap();
+        let dates: Vec<_> = calendar.dates.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            vec!["2018-05-05", "2018-05-08", "2018-05-12", "2018-05-13"],
+            dates
+        );
+
+        let vj = collections.vehicle_journeys.get("sj1").unwrap();
+        assert_eq!("route1", vj.route_id);
+        assert_eq!("weekend+holiday", vj.service_id);
+        assert_eq!("default_dataset", vj.dataset_id);
+        assert_eq!("default_company", vj.company_id);
+        assert_eq!("Bus", vj.physical_mode_id);
+        let stop_times: Vec<_> = vj
+            .stop_times
+            .iter()
+            .map(|st| {
+                (
+                    collections.stop_points[st.stop_point_idx].id.as_str(),
+                    st.arrival_time,
+                    st.departure_time,
+                    st.pickup_type,
+                    st.drop_off_type,
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                ("ssp1", Time::new(23, 50, 0), Time::new(23, 50, 0), 0, 1),
+                ("ssp2", Time::new(24, 10, 0), Time::new(24, 10, 0), 1, 0),
+            ],
+            stop_times
+        );
+    }
+
+    #[test]
+    fn test_journeys_at_unknown_stops() {
+        let netex = r#"<PublicationDelivery xmlns="http://www.netex.org.uk/netex">
+            <dataObjects><CompositeFrame id="cf"><frames>
+                <ServiceFrame id="sf">
+                    <lines><Line id="line1"><Name>Line 1</Name></Line></lines>
+                    <routes><Route id="route1"><LineRef ref="line1"/></Route></routes>
+                    <scheduledStopPoints>
+                        <ScheduledStopPoint id="ssp1"><Name>Gare</Name></ScheduledStopPoint>
+                    </scheduledStopPoints>
+                </ServiceFrame>
+                <ServiceCalendarFrame id="scf">
+                    <dayTypeAssignments>
+                        <DayTypeAssignment id="a1">
+                            <Date>2018-05-08</Date><DayTypeRef ref="holiday"/>
+                        </DayTypeAssignment>
+                    </dayTypeAssignments>
+                </ServiceCalendarFrame>
+                <TimetableFrame id="tf">
+                    <vehicleJourneys>
+                        <ServiceJourney id="sj1">
+                            <dayTypes><DayTypeRef ref="holiday"/></dayTypes>
+                            <RouteRef ref="route1"/>
+                            <passingTimes>
+                                <TimetabledPassingTime>
+                                    <ScheduledStopPointRef ref="ssp1"/>
+                                    <DepartureTime>08:00:00</DepartureTime>
+                                </TimetabledPassingTime>
+                            </passingTimes>
+                        </ServiceJourney>
+                        <ServiceJourney id="sj2">
+                            <dayTypes><DayTypeRef ref="holiday"/></dayTypes>
+                            <RouteRef ref="route1"/>
+                            <passingTimes>
+                                <TimetabledPassingTime>
+                                    <ScheduledStopPointRef ref="unknown"/>
+                                    <DepartureTime>09:00:00</DepartureTime>
+                                </TimetabledPassingTime>
+                            </passingTimes>
+                        </ServiceJourney>
+                    </vehicleJourneys>
+                </TimetableFrame>
+            </frames></CompositeFrame></dataObjects>
+        </PublicationDelivery>"#;
+
+        let mut netex_reader = super::NetexReader::default();
+        netex_reader
+            .read_netex_file("netex.xml", netex.as_bytes())
+            .unwrap();
+        assert!(netex_reader.finalize("default_dataset").is_err());
+
+        let mut netex_reader = super::NetexReader::new(&ParseProfile::Permissive);
+        netex_reader
+            .read_netex_file("netex.xml", netex.as_bytes())
+            .unwrap();
+        netex_reader.finalize("default_dataset").unwrap();
+        let vj_ids: Vec<_> = netex_reader
+            .collections
+            .vehicle_journeys
+            .values()
+            .map(|vj| vj.id.as_str())
+            .collect();
+        assert_eq!(vec!["sj1"], vj_ids);
+        let warnings = netex_reader.into_warnings();
+        assert_eq!(1, warnings.len());
+        assert_eq!("netex.xml", warnings[0].file);
+        assert_eq!(Some("sj2".to_string()), warnings[0].object_id);
+        assert_eq!(Some(Issue::UnknownReference), warnings[0].issue);
+    }
+
+    #[test]
+    fn test_read_site_frame() {
+        let mut netex_reader = super::NetexReader::default();
+        let netex = r#"<PublicationDelivery xmlns="http://www.netex.org.uk/netex">
+            <dataObjects><CompositeFrame id="cf"><frames>
+                <SiteFrame id="sf">
+                    <stopPlaces>
+                        <StopPlace id="sp1">
+                            <Name>Gare</Name>
+                            <AccessibilityAssessment id="aa1">
+                                <MobilityImpairedAccess>true</MobilityImpairedAccess>
+                            </AccessibilityAssessment>
+                            <quays>
+                                <Quay id="quay1">
+                                    <Centroid><Location>
+                                        <Longitude>2.0</Longitude><Latitude>48.0</Latitude>
+                                    </Location></Centroid>
+                                </Quay>
+                                <Quay id="quay2">
+                                    <Name>Gare quai 2</Name>
+                                    <Centroid><Location>
+                                        <Longitude>3.0</Longitude><Latitude>49.0</Latitude>
+                                    </Location></Centroid>
+                                    <AccessibilityAssessment id="aa2">
+                                        <MobilityImpairedAccess>partial</MobilityImpairedAccess>
+                                        <limitations><AccessibilityLimitation>
+                                            <WheelchairAccess>false</WheelchairAccess>
+                                            <AudibleSignalsAvailable>true</AudibleSignalsAvailable>
+                                        </AccessibilityLimitation></limitations>
+                                    </AccessibilityAssessment>
+                                </Quay>
+                            </quays>
+                        </StopPlace>
+                    </stopPlaces>
+                </SiteFrame>
+            </frames></CompositeFrame></dataObjects>
+        </PublicationDelivery>"#;
+        netex_reader
+            .read_netex_file("netex.xml", netex.as_bytes())
+            .unwrap();
+        netex_reader.finalize("default_dataset").unwrap();
+        let collections = &netex_reader.collections;
+
+        let stop_area = collections.stop_areas.get("sp1").unwrap();
+        assert_eq!("Gare", stop_area.name);
+        assert_eq!(2.5, stop_area.coord.lon);
+        assert_eq!(48.5, stop_area.coord.lat);
+        let quay1 = collections.stop_points.get("quay1").unwrap();
+        assert_eq!("Gare", quay1.name);
+        assert_eq!("sp1", quay1.stop_area_id);
+        assert_eq!(stop_area.equipment_id, quay1.equipment_id);
+        let quay2 = collections.stop_points.get("quay2").unwrap();
+        assert_eq!("Gare quai 2", quay2.name);
+
+        let equipment = |id: &Option<String>| {
+            let equipment = collections.equipments.get(id.as_ref().unwrap()).unwrap();
+            (
+                &equipment.wheelchair_boarding,
+                &equipment.audible_announcement,
+            )
+        };
+        assert_eq!(
+            (
+                &Availability::Available,
+                &Availability::InformationNotAvailable
+            ),
+            equipment(&stop_area.equipment_id)
+        );
+        assert_eq!(
+            (&Availability::NotAvailable, &Availability::Available),
+            equipment(&quay2.equipment_id)
+        );
+    }
+
+    #[test]
+    fn test_read_name_translations() {
+        let mut netex_reader = super::NetexReader::default();
+        let netex = r#"<PublicationDelivery xmlns="http://www.netex.org.uk/netex">
+            <dataObjects><CompositeFrame id="cf"><frames>
+                <SiteFrame id="sf">
+                    <stopPlaces>
+                        <StopPlace id="sp1">
+                            <Name>Brussel-Centraal</Name>
+                            <alternativeTexts>
+                                <AlternativeText attributeName="Name">
+                                    <Text lang="fr">Bruxelles-Central</Text>
+                                </AlternativeText>
+                                <AlternativeText attributeName="ShortName">
+                                    <Text lang="fr">Central</Text>
+                                </AlternativeText>
+                            </alternativeTexts>
+                            <quays>
+                                <Quay id="quay1">
+                                    <Name>Perron 1</Name>
+                                    <alternativeTexts><AlternativeText>
+                                        <Text lang="de">Bahnsteig 1</Text>
+                                    </AlternativeText></alternativeTexts>
+                                </Quay>
+                            </quays>
+                        </StopPlace>
+                    </stopPlaces>
+                </SiteFrame>
+            </frames></CompositeFrame></dataObjects>
+        </PublicationDelivery>"#;
+        netex_reader
+            .read_netex_file("netex.xml", netex.as_bytes())
+            .unwrap();
+        netex_reader.finalize("default_dataset").unwrap();
+
+        let translations: Vec<_> = netex_reader
+            .collections
+            .translations
+            .values()
+            .map(|t| {
+                (
+                    t.object_type.as_str(),
+                    t.object_id.as_str(),
+                    t.field_name.as_str(),
+                    t.language.as_str(),
+                    t.translation.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                ("stop_area", "sp1", "stop_name", "fr", "Bruxelles-Central"),
+                ("stop_point", "quay1", "stop_name", "de", "Bahnsteig 1"),
+            ],
+            translations
+        );
+    }
 }
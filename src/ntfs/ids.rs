@@ -0,0 +1,361 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! Checks and sanitization of the identifiers written in an NTFS.
+
+use collection::{Collection, CollectionWithId, Id};
+use failure::ResultExt;
+use model::{rename_ids, Collections, Model};
+use objects::*;
+use std::collections::{HashMap, HashSet};
+use Result;
+
+/// Maximum number of characters of an NTFS identifier.
+pub const MAX_ID_LENGTH: usize = 255;
+
+/// Object code system used to keep the original identifier of a
+/// sanitized object.
+pub const ORIGINAL_ID_SYSTEM: &str = "original_id";
+
+const FORBIDDEN_CHARS: &[char] = &['"', ';'];
+const REPLACEMENT_CHAR: char = '_';
+
+fn is_forbidden(c: char) -> bool {
+    c.is_control() || FORBIDDEN_CHARS.contains(&c)
+}
+
+/// Checks that an identifier respects the NTFS constraints: it must
+/// not be empty, must not contain control characters, `"` or `;` and
+/// must not be longer than `MAX_ID_LENGTH` characters.
+pub fn check_id(id: &str) -> Result<()> {
+    ensure!(!id.is_empty(), "empty identifier");
+    ensure!(
+        id.chars().count() <= MAX_ID_LENGTH,
+        "identifier {:?} is longer than {} characters",
+        id,
+        MAX_ID_LENGTH
+    );
+    if let Some(c) = id.chars().find(|c| is_forbidden(*c)) {
+        bail!("identifier {:?} contains forbidden character {:?}", id, c);
+    }
+    Ok(())
+}
+
+/// Returns an identifier respecting the NTFS constraints, built by
+/// replacing the forbidden characters and truncating the given one.
+/// An empty identifier is replaced by `_`.
+pub fn sanitize_id(id: &str) -> String {
+    if id.is_empty() {
+        return REPLACEMENT_CHAR.to_string();
+    }
+    id.chars()
+        .map(|c| if is_forbidden(c) { REPLACEMENT_CHAR } else { c })
+        .take(MAX_ID_LENGTH)
+        .collect()
+}
+
+// `id`, or `id` followed by the first numeric suffix giving an
+// identifier that is not in `used`, truncated to respect
+// `MAX_ID_LENGTH`.
+fn unused_id(id: &str, used: &HashSet<String>) -> String {
+    if !used.contains(id) {
+        return id.to_string();
+    }
+    (1..)
+        .map(|n| {
+            let suffix = format!("{}{}", REPLACEMENT_CHAR, n);
+            let prefix: String = id.chars().take(MAX_ID_LENGTH - suffix.len()).collect();
+            prefix + &suffix
+        })
+        .find(|new_id| !used.contains(new_id))
+        .unwrap()
+}
+
+fn check_collection_with_id<T: Id<T>>(file: &str, collection: &CollectionWithId<T>) -> Result<()> {
+    for obj in collection.values() {
+        check_id(obj.id()).with_context(|_| format!("invalid identifier in {}", file))?;
+    }
+    Ok(())
+}
+
+/// Checks the identifiers of all the objects of the collections
+/// against the NTFS constraints.
+pub fn check_ids(c: &Collections) -> Result<()> {
+    check_collection_with_id("contributors.txt", &c.contributors)?;
+    check_collection_with_id("datasets.txt", &c.datasets)?;
+    check_collection_with_id("networks.txt", &c.networks)?;
+    check_collection_with_id("commercial_modes.txt", &c.commercial_modes)?;
+    check_collection_with_id("lines.txt", &c.lines)?;
+    check_collection_with_id("line_groups.txt", &c.line_groups)?;
+    check_collection_with_id("routes.txt", &c.routes)?;
+    check_collection_with_id("trips.txt", &c.vehicle_journeys)?;
+    check_collection_with_id("physical_modes.txt", &c.physical_modes)?;
+    check_collection_with_id("stops.txt", &c.stop_areas)?;
+    check_collection_with_id("stops.txt", &c.stop_points)?;
+    check_collection_with_id("stops.txt", &c.entrances)?;
+    check_collection_with_id("addresses.txt", &c.addresses)?;
+    check_collection_with_id("calendar_dates.txt", &c.calendars)?;
+    check_collection_with_id("grid_calendars.txt", &c.grid_calendars)?;
+    check_collection_with_id("companies.txt", &c.companies)?;
+    check_collection_with_id("comments.txt", &c.comments)?;
+    check_collection_with_id("equipments.txt", &c.equipments)?;
+    check_collection_with_id("trip_properties.txt", &c.trip_properties)?;
+    check_collection_with_id("geometries.txt", &c.geometries)?;
+    check_collection_with_id("tickets.txt", &c.tickets)?;
+    check_collection_with_id("ticket_uses.txt", &c.ticket_uses)?;
+    check_collection_with_id("fare_zones.txt", &c.fare_zones)?;
+    Ok(())
+}
+
+// The identifiers of `collection` that do not respect the NTFS
+// constraints, with their sanitized version.  The sanitized
+// identifiers colliding with an identifier of the collection, or with
+// a previously sanitized one, get a numeric suffix.
+fn invalid_ids<T: Id<T>>(collection: &CollectionWithId<T>) -> HashMap<String, String> {
+    let mut used: HashSet<String> = collection.values().map(|o| o.id().to_string()).collect();
+    let mut ids = HashMap::new();
+    for id in collection.values().map(|o| o.id()) {
+        if check_id(id).is_ok() {
+            continue;
+        }
+        let new_id = unused_id(&sanitize_id(id), &used);
+        used.insert(new_id.clone());
+        ids.insert(id.to_string(), new_id);
+    }
+    ids
+}
+
+fn keep_original_codes<T: Id<T> + Codes>(
+    collection: &mut CollectionWithId<T>,
+    ids: &HashMap<String, String>,
+) {
+    for (old_id, new_id) in ids {
+        if let Some(mut obj) = collection.get_mut(new_id) {
+            obj.codes_mut()
+                .push((ORIGINAL_ID_SYSTEM.to_string(), old_id.clone()));
+        }
+    }
+}
+
+fn keep_original_id_mappings(
+    id_mappings: &mut Collection<IdMapping>,
+    collection: &str,
+    ids: &HashMap<String, String>,
+) {
+    let mut ids: Vec<_> = ids.iter().collect();
+    ids.sort();
+    for (old_id, new_id) in ids {
+        id_mappings.push(IdMapping::new(collection, old_id, new_id));
+    }
+}
+
+// Sanitizes the identifiers of the collections, keeping the original
+// ones as object codes.
+macro_rules! sanitize_keeping_codes {
+    ($c:ident, $($collection:ident),*) => {
+        $({
+            let ids = invalid_ids(&$c.$collection);
+            rename_ids(&mut $c, stringify!($collection), &ids)?;
+            keep_original_codes(&mut $c.$collection, &ids);
+        })*
+    };
+}
+
+// Sanitizes the identifiers of the collections, keeping the original
+// ones as id mappings.
+macro_rules! sanitize_keeping_id_mappings {
+    ($c:ident, $($collection:ident),*) => {
+        $({
+            let ids = invalid_ids(&$c.$collection);
+            rename_ids(&mut $c, stringify!($collection), &ids)?;
+            keep_original_id_mappings(&mut $c.id_mappings, stringify!($collection), &ids);
+        })*
+    };
+}
+
+/// Replaces the identifiers that do not respect the NTFS constraints
+/// by sanitized ones, in all the collections checked by `check_ids`,
+/// updating the references to them.  A numeric suffix is added to the
+/// sanitized identifiers that would collide with another identifier
+/// of the same collection.
+///
+/// The original identifiers of the networks, lines, routes, vehicle
+/// journeys, stop areas and stop points are kept as object codes with
+/// the `ORIGINAL_ID_SYSTEM` system, the ones of the other objects as
+/// id mappings.
+pub fn sanitize_ids(model: Model) -> Result<Model> {
+    let mut c = model.into_collections();
+
+    sanitize_keeping_codes!(
+        c,
+        networks,
+        lines,
+        routes,
+        vehicle_journeys,
+        stop_areas,
+        stop_points
+    );
+    sanitize_keeping_id_mappings!(
+        c,
+        contributors,
+        datasets,
+        commercial_modes,
+        line_groups,
+        physical_modes,
+        entrances,
+        addresses,
+        calendars,
+        grid_calendars,
+        companies,
+        comments,
+        equipments,
+        trip_properties,
+        geometries,
+        tickets,
+        ticket_uses,
+        fare_zones
+    );
+
+    Model::new(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_valid_id() {
+        assert!(check_id("OIF:SA:8739322").is_ok());
+        assert!(check_id(&"a".repeat(MAX_ID_LENGTH)).is_ok());
+    }
+
+    #[test]
+    fn check_invalid_id() {
+        assert!(check_id("").is_err());
+        assert!(check_id("stop;1").is_err());
+        assert!(check_id("stop\"1").is_err());
+        assert!(check_id("stop\n1").is_err());
+        assert!(check_id(&"a".repeat(MAX_ID_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn sanitize() {
+        assert_eq!("stop_1", sanitize_id("stop;1"));
+        assert_eq!("stop__1", sanitize_id("stop\"\t1"));
+        assert_eq!(MAX_ID_LENGTH, sanitize_id(&"é".repeat(300)).chars().count());
+        assert!(check_id(&sanitize_id("a\"b;c\nd")).is_ok());
+        assert_eq!("_", sanitize_id(""));
+    }
+
+    #[test]
+    fn sanitize_colliding_ids() {
+        let long_id = |end: &str| format!("{}{}", "a".repeat(MAX_ID_LENGTH), end);
+        let contributors = CollectionWithId::new(
+            vec![
+                "a;b",
+                "a_b",
+                "a\"b",
+                "",
+                long_id("1").as_str(),
+                long_id("2").as_str(),
+            ]
+            .into_iter()
+            .map(|id| Contributor {
+                id: id.to_string(),
+                ..Default::default()
+            })
+            .collect(),
+        )
+        .unwrap();
+        let ids = invalid_ids(&contributors);
+        assert_eq!(5, ids.len());
+        assert_eq!("a_b_1", ids["a;b"]);
+        assert_eq!("a_b_2", ids["a\"b"]);
+        assert_eq!("_", ids[""]);
+        assert_eq!("a".repeat(MAX_ID_LENGTH), ids[&long_id("1")]);
+        assert_eq!(
+            format!("{}_1", "a".repeat(MAX_ID_LENGTH - 2)),
+            ids[&long_id("2")]
+        );
+        let new_ids: HashSet<_> = ids.values().collect();
+        assert_eq!(5, new_ids.len());
+        assert!(new_ids.iter().all(|id| check_id(id).is_ok()));
+    }
+
+    #[test]
+    fn sanitize_model_ids() {
+        let stop_areas = CollectionWithId::new(vec![StopArea {
+            id: "sa;1".to_string(),
+            name: "sa 1".to_string(),
+            codes: KeysValues::default(),
+            object_properties: KeysValues::default(),
+            comment_links: CommentLinksT::default(),
+            visible: true,
+            coord: Coord { lon: 0., lat: 0. },
+            timezone: None,
+            geometry_id: None,
+            equipment_id: None,
+        }])
+        .unwrap();
+        let stop_points = CollectionWithId::new(vec![StopPoint {
+            id: "sp;1".to_string(),
+            name: "sp 1".to_string(),
+            codes: KeysValues::default(),
+            object_properties: KeysValues::default(),
+            comment_links: CommentLinksT::default(),
+            visible: true,
+            coord: Coord { lon: 0., lat: 0. },
+            stop_area_id: "sa;1".into(),
+            timezone: None,
+            geometry_id: None,
+            equipment_id: None,
+            fare_zone_id: None,
+            address_id: None,
+        }])
+        .unwrap();
+        let transfers = ::collection::Collection::new(vec![Transfer {
+            from_stop_id: "sp;1".to_string(),
+            to_stop_id: "sp;1".to_string(),
+            min_transfer_time: None,
+            real_min_transfer_time: None,
+            equipment_id: None,
+        }]);
+        let collections = Collections {
+            stop_areas,
+            stop_points,
+            transfers,
+            ..Default::default()
+        };
+        let model = Model::new(collections).unwrap();
+        assert!(check_ids(&model).is_err());
+
+        let model = sanitize_ids(model).unwrap();
+        assert!(check_ids(&model).is_ok());
+        let stop_point = model.stop_points.get("sp_1").unwrap();
+        assert_eq!("sa_1", stop_point.stop_area_id);
+        assert_eq!(
+            vec![(ORIGINAL_ID_SYSTEM.to_string(), "sp;1".to_string())],
+            stop_point.codes
+        );
+        assert_eq!(
+            vec![(ORIGINAL_ID_SYSTEM.to_string(), "sa;1".to_string())],
+            model.stop_areas.get("sa_1").unwrap().codes
+        );
+        let transfer = model.transfers.values().next().unwrap();
+        assert_eq!("sp_1", transfer.from_stop_id);
+        assert_eq!("sp_1", transfer.to_stop_id);
+    }
+}
@@ -17,18 +17,29 @@
 //! [NTFS](https://github.com/CanalTP/navitia/blob/dev/documentation/ntfs/ntfs_fr.md)
 //! format management.
 
+mod ids;
 mod read;
-mod write;
+pub(crate) mod write;
 
 use common_format;
+#[cfg(feature = "async")]
+use futures::Future;
 use model::{Collections, Model};
 use objects::*;
+use read_utils::{FileHandler, InputHandler};
+#[cfg(feature = "async")]
+use read_utils::{read_url_async, HttpOptions, ZipHandler};
+use report::{Issues, ParseProfile, Warning};
 use std::path;
 use utils::*;
+#[cfg(feature = "async")]
+use Error;
 use Result;
 extern crate tempdir;
 use self::tempdir::TempDir;
 
+pub use self::ids::{check_ids, sanitize_ids, MAX_ID_LENGTH, ORIGINAL_ID_SYSTEM};
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct StopTime {
     stop_id: String,
@@ -47,6 +58,9 @@ struct StopTime {
     #[serde(default, deserialize_with = "de_from_u8", serialize_with = "ser_from_bool")]
     datetime_estimated: bool,
     local_zone_id: Option<u16>,
+    shape_dist_traveled: Option<f64>,
+    continuous_pickup: Option<u8>,
+    continuous_drop_off: Option<u8>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -73,6 +87,7 @@ struct Stop {
     timezone: Option<String>,
     geometry_id: Option<String>,
     equipment_id: Option<String>,
+    address_id: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -104,46 +119,124 @@ fn default_visible() -> bool {
 
 /// Imports a `Model` from the
 /// [NTFS](https://github.com/CanalTP/navitia/blob/dev/documentation/ntfs/ntfs_fr.md)
-/// files in the given directory.
+/// files in the given directory, zip or tar.gz archive.
 pub fn read<P: AsRef<path::Path>>(path: P) -> Result<Model> {
-    let path = path.as_ref();
-    info!("Loading NTFS from {:?}", path);
-    let mut collections = Collections::default();
-    collections.contributors = make_collection_with_id(path, "contributors.txt")?;
-    collections.datasets = make_collection_with_id(path, "datasets.txt")?;
-    collections.commercial_modes = make_collection_with_id(path, "commercial_modes.txt")?;
-    collections.networks = make_collection_with_id(path, "networks.txt")?;
-    collections.lines = make_collection_with_id(path, "lines.txt")?;
-    collections.routes = make_collection_with_id(path, "routes.txt")?;
-    collections.vehicle_journeys = make_collection_with_id(path, "trips.txt")?;
-    collections.physical_modes = make_collection_with_id(path, "physical_modes.txt")?;
-    collections.companies = make_collection_with_id(path, "companies.txt")?;
-    collections.equipments = make_opt_collection_with_id(path, "equipments.txt")?;
-    collections.trip_properties = make_opt_collection_with_id(path, "trip_properties.txt")?;
-    collections.comments = make_opt_collection_with_id(path, "comments.txt")?;
-    collections.transfers = make_opt_collection(path, "transfers.txt")?;
-    collections.admin_stations = make_opt_collection(path, "admin_stations.txt")?;
-    common_format::manage_calendars(&mut collections, path)?;
-    read::manage_geometries(&mut collections, path)?;
-    read::manage_feed_infos(&mut collections, path)?;
-    read::manage_stops(&mut collections, path)?;
-    read::manage_stop_times(&mut collections, path)?;
-    read::manage_codes(&mut collections, path)?;
-    read::manage_comments(&mut collections, path)?;
-    read::manage_object_properties(&mut collections, path)?;
+    read_with_warnings(path).map(|(model, _)| model)
+}
+
+/// Imports a `Model` from the
+/// [NTFS](https://github.com/CanalTP/navitia/blob/dev/documentation/ntfs/ntfs_fr.md)
+/// files in the given directory, zip or tar.gz archive, also
+/// returning the elements that were skipped while reading.
+pub fn read_with_warnings<P: AsRef<path::Path>>(path: P) -> Result<(Model, Vec<Warning>)> {
+    read_with_profile(path, &ParseProfile::default())
+}
+
+/// Imports a `Model` from the
+/// [NTFS](https://github.com/CanalTP/navitia/blob/dev/documentation/ntfs/ntfs_fr.md)
+/// files in the given directory, zip or tar.gz archive, handling the
+/// issues met according to `profile`, and returns the elements that
+/// were skipped or fixed while reading.
+pub fn read_with_profile<P: AsRef<path::Path>>(
+    path: P,
+    profile: &ParseProfile,
+) -> Result<(Model, Vec<Warning>)> {
+    read_from_handler(&mut InputHandler::from_path(path)?, profile)
+}
+
+/// Imports a `Model` from the
+/// [NTFS](https://github.com/CanalTP/navitia/blob/dev/documentation/ntfs/ntfs_fr.md)
+/// files of the zip archive downloaded from the url.
+pub fn read_from_url(url: &str) -> Result<Model> {
+    let mut file_handler = InputHandler::from_url(url)?;
+    read_from_handler(&mut file_handler, &ParseProfile::default()).map(|(model, _)| model)
+}
+
+/// Imports a `Model` from the
+/// [NTFS](https://github.com/CanalTP/navitia/blob/dev/documentation/ntfs/ntfs_fr.md)
+/// files of the zip archive downloaded from the url, like
+/// `read_from_url`, without blocking the thread during the download.
+/// The future must be run by a [tokio](https://tokio.rs) runtime.
+#[cfg(feature = "async")]
+pub fn read_from_url_async(url: &str) -> impl Future<Item = Model, Error = Error> + Send {
+    let source = url.to_string();
+    read_url_async(url, &HttpOptions::default()).and_then(move |archive| {
+        let mut file_handler = ZipHandler::new(archive, source)?;
+        read_from_handler(&mut file_handler, &ParseProfile::default()).map(|(model, _)| model)
+    })
+}
+
+/// Imports a `Model` from the
+/// [NTFS](https://github.com/CanalTP/navitia/blob/dev/documentation/ntfs/ntfs_fr.md)
+/// files given by the file handler, handling the issues met according
+/// to `profile`, and returns the elements that were skipped or fixed
+/// while reading.
+pub fn read_from_handler<H>(
+    file_handler: &mut H,
+    profile: &ParseProfile,
+) -> Result<(Model, Vec<Warning>)>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let mut issues = Issues::new(profile.clone());
+    info!("Loading NTFS from {}", file_handler.source_name());
+    let mut collections = Collections {
+        contributors: make_collection_with_id(file_handler, "contributors.txt")?,
+        datasets: make_collection_with_id(file_handler, "datasets.txt")?,
+        attributions: make_opt_collection(file_handler, "attributions.txt")?,
+        commercial_modes: make_collection_with_id(file_handler, "commercial_modes.txt")?,
+        networks: make_collection_with_id(file_handler, "networks.txt")?,
+        lines: make_collection_with_id(file_handler, "lines.txt")?,
+        line_groups: make_opt_collection_with_id(file_handler, "line_groups.txt")?,
+        line_group_links: make_opt_collection(file_handler, "line_group_links.txt")?,
+        routes: make_collection_with_id(file_handler, "routes.txt")?,
+        vehicle_journeys: make_collection_with_id(file_handler, "trips.txt")?,
+        frequencies: make_opt_collection(file_handler, "frequencies.txt")?,
+        physical_modes: make_collection_with_id(file_handler, "physical_modes.txt")?,
+        companies: make_collection_with_id(file_handler, "companies.txt")?,
+        equipments: make_opt_collection_with_id(file_handler, "equipments.txt")?,
+        trip_properties: make_opt_collection_with_id(file_handler, "trip_properties.txt")?,
+        comments: make_opt_collection_with_id(file_handler, "comments.txt")?,
+        transfers: make_opt_collection(file_handler, "transfers.txt")?,
+        admin_stations: make_opt_collection(file_handler, "admin_stations.txt")?,
+        translations: make_opt_collection(file_handler, "translations.txt")?,
+        addresses: make_opt_collection_with_id(file_handler, "addresses.txt")?,
+        grid_calendars: make_opt_collection_with_id(file_handler, "grid_calendars.txt")?,
+        grid_exception_dates: make_opt_collection(file_handler, "grid_exception_dates.txt")?,
+        grid_periods: make_opt_collection(file_handler, "grid_periods.txt")?,
+        grid_rel_calendar_line: make_opt_collection(file_handler, "grid_rel_calendar_line.txt")?,
+        tickets: make_opt_collection_with_id(file_handler, "tickets.txt")?,
+        ticket_prices: make_opt_collection(file_handler, "ticket_prices.txt")?,
+        ticket_uses: make_opt_collection_with_id(file_handler, "ticket_uses.txt")?,
+        ticket_use_perimeters: make_opt_collection(file_handler, "ticket_use_perimeters.txt")?,
+        ticket_use_restrictions: make_opt_collection(file_handler, "ticket_use_restrictions.txt")?,
+        fare_zones: make_opt_collection_with_id(file_handler, "fare_zones.txt")?,
+        ..Default::default()
+    };
+    common_format::manage_calendars(&mut collections, file_handler)?;
+    read::manage_geometries(&mut collections, file_handler, &mut issues)?;
+    read::manage_feed_infos(&mut collections, file_handler)?;
+    read::manage_stops(&mut collections, file_handler, &mut issues)?;
+    read::manage_stop_times(&mut collections, file_handler, &mut issues)?;
+    read::manage_codes(&mut collections, file_handler)?;
+    read::manage_comments(&mut collections, file_handler, &mut issues)?;
+    read::manage_object_properties(&mut collections, file_handler)?;
     info!("Indexing");
     let res = Model::new(collections)?;
     info!("Loading NTFS done");
-    Ok(res)
+    Ok((res, issues.into_warnings()))
 }
 
 /// Exports a `Model` to the
 /// [NTFS](https://github.com/CanalTP/navitia/blob/dev/documentation/ntfs/ntfs_fr.md)
-/// files in the given directory.
+/// files in the given directory.  Fails if an identifier does not
+/// respect the NTFS constraints, see `sanitize_ids` to fix them.
 pub fn write<P: AsRef<path::Path>>(model: &Model, path: P) -> Result<()> {
     let path = path.as_ref();
     info!("Writing NTFS to {:?}", path);
 
+    check_ids(model)?;
+
     write::write_feed_infos(path, &model.feed_infos)?;
     write::write_collection_with_id(path, "contributors.txt", &model.contributors)?;
     write::write_collection_with_id(path, "datasets.txt", &model.datasets)?;
@@ -151,23 +244,44 @@ pub fn write<P: AsRef<path::Path>>(model: &Model, path: P) -> Result<()> {
     write::write_collection_with_id(path, "commercial_modes.txt", &model.commercial_modes)?;
     write::write_collection_with_id(path, "companies.txt", &model.companies)?;
     write::write_collection_with_id(path, "lines.txt", &model.lines)?;
+    if !model.line_groups.is_empty() {
+        write::write_collection_with_id(path, "line_groups.txt", &model.line_groups)?;
+        write::write_collection(path, "line_group_links.txt", &model.line_group_links)?;
+    }
     write::write_collection_with_id(path, "physical_modes.txt", &model.physical_modes)?;
     write::write_collection_with_id(path, "equipments.txt", &model.equipments)?;
     write::write_collection_with_id(path, "routes.txt", &model.routes)?;
     write::write_collection_with_id(path, "trip_properties.txt", &model.trip_properties)?;
     write::write_collection_with_id(path, "geometries.txt", &model.geometries)?;
+    if !model.fare_zones.is_empty() {
+        write::write_collection_with_id(path, "fare_zones.txt", &model.fare_zones)?;
+    }
     write::write_collection(path, "transfers.txt", &model.transfers)?;
     write::write_collection(path, "admin_stations.txt", &model.admin_stations)?;
+    if !model.addresses.is_empty() {
+        write::write_collection_with_id(path, "addresses.txt", &model.addresses)?;
+    }
     write::write_vehicle_journeys_and_stop_times(
         path,
         &model.vehicle_journeys,
         &model.stop_points,
     )?;
-    write::write_calendar_dates(path, &model.calendars)?;
-    write::write_stops(path, &model.stop_points, &model.stop_areas)?;
+    if !model.frequencies.is_empty() {
+        write::write_collection(path, "frequencies.txt", &model.frequencies)?;
+    }
+    if !model.attributions.is_empty() {
+        write::write_collection(path, "attributions.txt", &model.attributions)?;
+    }
+    if !model.translations.is_empty() {
+        write::write_collection(path, "translations.txt", &model.translations)?;
+    }
+    common_format::write_calendar_and_calendar_dates(path, &model.calendars)?;
+    write::write_stops(path, &model.stop_points, &model.stop_areas, &model.entrances)?;
     write::write_comments(path, model)?;
     write::write_codes(path, model)?;
     write::write_object_properties(path, model)?;
+    write::write_grid_calendars(path, model)?;
+    write::write_fares(path, model)?;
 
     Ok(())
 }
@@ -196,9 +310,12 @@ mod tests {
     use common_format;
     use geo_types::{Geometry as GeoGeometry, LineString, Point};
     use objects::*;
+    use read_utils::PathFileHandler;
+    use report::Issues;
     use serde;
     use std::collections::HashMap;
     use std::fmt::Debug;
+    use std::fs;
     use std::path;
     use utils::*;
 
@@ -222,7 +339,8 @@ mod tests {
         let collection = CollectionWithId::new(objects).unwrap();
         ser_deser_in_tmp_dir(|path| {
             write::write_collection_with_id(path, "file.txt", &collection).unwrap();
-            let des_collection = make_collection_with_id(path, "file.txt").unwrap();
+            let mut handler = PathFileHandler::new(path);
+            let des_collection = make_collection_with_id(&mut handler, "file.txt").unwrap();
             assert_eq!(des_collection, collection);
         });
     }
@@ -235,7 +353,8 @@ mod tests {
         let collection = Collection::new(objects);
         ser_deser_in_tmp_dir(|path| {
             write::write_collection(path, "file.txt", &collection).unwrap();
-            let des_collection = make_opt_collection(path, "file.txt").unwrap();
+            let mut handler = PathFileHandler::new(path);
+            let des_collection = make_opt_collection(&mut handler, "file.txt").unwrap();
             assert_eq!(des_collection, collection);
         });
     }
@@ -249,7 +368,8 @@ mod tests {
 
         ser_deser_in_tmp_dir(|path| {
             write::write_feed_infos(path, &feed_infos).unwrap();
-            read::manage_feed_infos(&mut collections, path).unwrap();
+            let mut handler = PathFileHandler::new(path);
+            read::manage_feed_infos(&mut collections, &mut handler).unwrap();
         });
         assert_eq!(collections.feed_infos.len(), 2);
         assert_eq!(collections.feed_infos, feed_infos);
@@ -344,7 +464,7 @@ mod tests {
                     blue: 45,
                 }),
                 sort_order: Some(1342),
-                network_id: "OIF:829".to_string(),
+                network_id: "OIF:829".into(),
                 commercial_mode_id: "bus".to_string(),
                 geometry_id: Some("Geometry:Line:Relation:6883353".to_string()),
                 opening_time: Some(Time::new(9, 0, 0)),
@@ -364,7 +484,7 @@ mod tests {
                 color: None,
                 text_color: None,
                 sort_order: None,
-                network_id: "OIF:829".to_string(),
+                network_id: "OIF:829".into(),
                 commercial_mode_id: "bus".to_string(),
                 geometry_id: None,
                 opening_time: None,
@@ -399,9 +519,11 @@ mod tests {
                 codes: KeysValues::default(),
                 object_properties: KeysValues::default(),
                 comment_links: CommentLinksT::default(),
-                line_id: "OIF:002002002:BDEOIF829".to_string(),
+                line_id: "OIF:002002002:BDEOIF829".into(),
                 geometry_id: Some("Geometry:Line:Relation:6883353".to_string()),
                 destination_id: Some("OIF,OIF:SA:4:126".to_string()),
+                continuous_pickup: None,
+                continuous_drop_off: None,
             },
             Route {
                 id: "OIF:002002002:CEN".to_string(),
@@ -410,9 +532,11 @@ mod tests {
                 codes: KeysValues::default(),
                 object_properties: KeysValues::default(),
                 comment_links: CommentLinksT::default(),
-                line_id: "OIF:002002002:BDEOIF829".to_string(),
+                line_id: "OIF:002002002:BDEOIF829".into(),
                 geometry_id: None,
                 destination_id: None,
+                continuous_pickup: None,
+                continuous_drop_off: None,
             },
         ]);
     }
@@ -431,11 +555,12 @@ mod tests {
                     lon: 2.073034,
                     lat: 48.799115,
                 },
-                stop_area_id: "OIF:SA:8739322".to_string(),
+                stop_area_id: "OIF:SA:8739322".into(),
                 timezone: Some("Europe/Paris".to_string()),
                 geometry_id: None,
                 equipment_id: None,
                 fare_zone_id: Some("1".to_string()),
+                address_id: None,
             },
             StopPoint {
                 id: "OIF:SP:36:2127".to_string(),
@@ -448,11 +573,12 @@ mod tests {
                     lon: 2.073407,
                     lat: 48.800598,
                 },
-                stop_area_id: "OIF:SA:2:1468".to_string(),
+                stop_area_id: "OIF:SA:2:1468".into(),
                 timezone: Some("Europe/Paris".to_string()),
                 geometry_id: None,
                 equipment_id: None,
                 fare_zone_id: None,
+                address_id: None,
             },
         ]).unwrap();
         let vehicle_journeys = CollectionWithId::new(vec![
@@ -461,11 +587,11 @@ mod tests {
                 codes: KeysValues::default(),
                 object_properties: KeysValues::default(),
                 comment_links: CommentLinksT::default(),
-                route_id: "OIF:078078001:1".to_string(),
+                route_id: "OIF:078078001:1".into(),
                 physical_mode_id: "Bus".to_string(),
                 dataset_id: "OIF:0".to_string(),
-                service_id: "2".to_string(),
-                headsign: Some("2005".to_string()),
+                service_id: "2".into(),
+                headsign: Some("2005".into()),
                 block_id: Some("PLOI".to_string()),
                 company_id: "OIF:743".to_string(),
                 trip_property_id: Some("0".to_string()),
@@ -482,6 +608,7 @@ mod tests {
                         drop_off_type: 1,
                         datetime_estimated: false,
                         local_zone_id: None,
+                        extras: None,
                     },
                     StopTime {
                         stop_point_idx: stop_points.get_idx("OIF:SP:36:2127").unwrap(),
@@ -494,6 +621,7 @@ mod tests {
                         drop_off_type: 0,
                         datetime_estimated: false,
                         local_zone_id: None,
+                        extras: None,
                     },
                 ],
             },
@@ -502,10 +630,10 @@ mod tests {
                 codes: KeysValues::default(),
                 object_properties: KeysValues::default(),
                 comment_links: CommentLinksT::default(),
-                route_id: "OIF:800:TER".to_string(),
+                route_id: "OIF:800:TER".into(),
                 physical_mode_id: "Bus".to_string(),
                 dataset_id: "OIF:0".to_string(),
-                service_id: "2".to_string(),
+                service_id: "2".into(),
                 headsign: None,
                 block_id: None,
                 company_id: "OIF:743".to_string(),
@@ -520,11 +648,13 @@ mod tests {
                 .unwrap();
 
             let mut collections = Collections::default();
+            let mut handler = PathFileHandler::new(path);
             collections.vehicle_journeys =
-                make_collection_with_id::<VehicleJourney>(path, "trips.txt").unwrap();
+                make_collection_with_id::<VehicleJourney, _>(&mut handler, "trips.txt").unwrap();
             collections.stop_points = stop_points;
 
-            read::manage_stop_times(&mut collections, path).unwrap();
+            read::manage_stop_times(&mut collections, &mut handler, &mut Issues::default())
+                .unwrap();
             assert_eq!(collections.vehicle_journeys, vehicle_journeys);
         });
     }
@@ -631,10 +761,11 @@ mod tests {
         ]).unwrap();
 
         ser_deser_in_tmp_dir(|path| {
-            write::write_calendar_dates(path, &calendars).unwrap();
+            common_format::write_calendar_and_calendar_dates(path, &calendars).unwrap();
 
             let mut collections = Collections::default();
-            common_format::manage_calendars(&mut collections, path).unwrap();
+            let mut handler = PathFileHandler::new(path);
+            common_format::manage_calendars(&mut collections, &mut handler).unwrap();
 
             assert_eq!(collections.calendars, calendars);
         });
@@ -657,8 +788,9 @@ mod tests {
                 timezone: Some("Europe/Paris".to_string()),
                 geometry_id: Some("geometry_1".to_string()),
                 equipment_id: Some("equipment_1".to_string()),
-                stop_area_id: "sa_1".to_string(),
+                stop_area_id: "sa_1".into(),
                 fare_zone_id: Some("1".to_string()),
+                address_id: Some("A1".to_string()),
             },
             // stop point with no parent station
             StopPoint {
@@ -675,8 +807,9 @@ mod tests {
                 timezone: None,
                 geometry_id: None,
                 equipment_id: None,
-                stop_area_id: "Navitia:sa_2".to_string(),
+                stop_area_id: "Navitia:sa_2".into(),
                 fare_zone_id: None,
+                address_id: None,
             },
         ]).unwrap();
 
@@ -713,14 +846,27 @@ mod tests {
             },
         ]).unwrap();
 
+        let entrances = CollectionWithId::new(vec![Entrance {
+            id: "entrance_1".to_string(),
+            name: "entrance_name_1".to_string(),
+            coord: Coord {
+                lon: 2.073134,
+                lat: 48.799215,
+            },
+            stop_area_id: "sa_1".into(),
+            timezone: None,
+        }]).unwrap();
+
         ser_deser_in_tmp_dir(|path| {
-            write::write_stops(path, &stop_points, &stop_areas).unwrap();
+            write::write_stops(path, &stop_points, &stop_areas, &entrances).unwrap();
 
             let mut collections = Collections::default();
-            read::manage_stops(&mut collections, path).unwrap();
+            let mut handler = PathFileHandler::new(path);
+            read::manage_stops(&mut collections, &mut handler, &mut Issues::default()).unwrap();
 
             assert_eq!(collections.stop_points, stop_points);
             assert_eq!(collections.stop_areas, stop_areas);
+            assert_eq!(collections.entrances, entrances);
         });
     }
 
@@ -765,8 +911,9 @@ mod tests {
             timezone: None,
             geometry_id: None,
             equipment_id: None,
-            stop_area_id: "sa_1".to_string(),
+            stop_area_id: "sa_1".into(),
             fare_zone_id: None,
+            address_id: None,
         }]).unwrap();
 
         let stop_areas = CollectionWithId::new(vec![StopArea {
@@ -802,7 +949,7 @@ mod tests {
             color: None,
             text_color: None,
             sort_order: None,
-            network_id: "OIF:829".to_string(),
+            network_id: "OIF:829".into(),
             commercial_mode_id: "bus".to_string(),
             geometry_id: None,
             opening_time: None,
@@ -819,9 +966,11 @@ mod tests {
             ],
             object_properties: vec![("prop_name:4".to_string(), "prop_value:4".to_string())],
             comment_links: vec![comments.get_idx("c:3").unwrap()],
-            line_id: "OIF:002002002:BDEOIF829".to_string(),
+            line_id: "OIF:002002002:BDEOIF829".into(),
             geometry_id: None,
             destination_id: None,
+            continuous_pickup: None,
+            continuous_drop_off: None,
         }]).unwrap();
 
         let vehicle_journeys = CollectionWithId::new(vec![VehicleJourney {
@@ -829,10 +978,10 @@ mod tests {
             codes: vec![("object_system:6".to_string(), "object_code:6".to_string())],
             object_properties: vec![("prop_name:6".to_string(), "prop_value:6".to_string())],
             comment_links: CommentLinksT::default(),
-            route_id: "OIF:800:TER".to_string(),
+            route_id: "OIF:800:TER".into(),
             physical_mode_id: "Bus".to_string(),
             dataset_id: "OIF:0".to_string(),
-            service_id: "2".to_string(),
+            service_id: "2".into(),
             headsign: None,
             block_id: None,
             company_id: "OIF:743".to_string(),
@@ -853,7 +1002,15 @@ mod tests {
             codes: KeysValues::default(),
         }]).unwrap();
 
+        let line_groups = CollectionWithId::new(vec![LineGroup {
+            id: "LG:1".to_string(),
+            name: "Line 3 and its branches".to_string(),
+            main_line_id: "OIF:002002003:3OIF829".to_string(),
+            comment_links: vec![comments.get_idx("c:3").unwrap()],
+        }]).unwrap();
+
         ser_collections.comments = comments;
+        ser_collections.line_groups = line_groups;
         ser_collections.stop_areas = stop_areas;
         ser_collections.stop_points = stop_points;
         ser_collections.lines = lines;
@@ -863,10 +1020,13 @@ mod tests {
 
         ser_deser_in_tmp_dir(|path| {
             write::write_collection_with_id(path, "lines.txt", &ser_collections.lines).unwrap();
+            write::write_collection_with_id(path, "line_groups.txt", &ser_collections.line_groups)
+                .unwrap();
             write::write_stops(
                 path,
                 &ser_collections.stop_points,
                 &ser_collections.stop_areas,
+                &ser_collections.entrances,
             ).unwrap();
             write::write_collection_with_id(path, "routes.txt", &ser_collections.routes).unwrap();
             write::write_collection_with_id(path, "trips.txt", &ser_collections.vehicle_journeys)
@@ -878,18 +1038,25 @@ mod tests {
             write::write_object_properties(path, &ser_collections).unwrap();
 
             let mut des_collections = Collections::default();
-            des_collections.lines = make_collection_with_id(path, "lines.txt").unwrap();
-            des_collections.routes = make_collection_with_id(path, "routes.txt").unwrap();
-            des_collections.vehicle_journeys = make_collection_with_id(path, "trips.txt").unwrap();
-            des_collections.networks = make_collection_with_id(path, "networks.txt").unwrap();
-            read::manage_stops(&mut des_collections, path).unwrap();
-            read::manage_comments(&mut des_collections, path).unwrap();
-            read::manage_codes(&mut des_collections, path).unwrap();
-            read::manage_object_properties(&mut des_collections, path).unwrap();
+            let mut handler = PathFileHandler::new(path);
+            des_collections.lines = make_collection_with_id(&mut handler, "lines.txt").unwrap();
+            des_collections.line_groups =
+                make_collection_with_id(&mut handler, "line_groups.txt").unwrap();
+            des_collections.routes = make_collection_with_id(&mut handler, "routes.txt").unwrap();
+            des_collections.vehicle_journeys =
+                make_collection_with_id(&mut handler, "trips.txt").unwrap();
+            des_collections.networks =
+                make_collection_with_id(&mut handler, "networks.txt").unwrap();
+            let mut issues = Issues::default();
+            read::manage_stops(&mut des_collections, &mut handler, &mut issues).unwrap();
+            read::manage_comments(&mut des_collections, &mut handler, &mut issues).unwrap();
+            read::manage_codes(&mut des_collections, &mut handler).unwrap();
+            read::manage_object_properties(&mut des_collections, &mut handler).unwrap();
 
             assert_eq!(ser_collections.comments, des_collections.comments);
 
             // test comment links
+            assert_eq!(ser_collections.line_groups, des_collections.line_groups);
             assert_eq!(
                 ser_collections
                     .lines
@@ -1078,6 +1245,196 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn fares_serialization() {
+        let collections = Collections {
+            tickets: CollectionWithId::new(vec![Ticket {
+                id: "T1".to_string(),
+                name: "Single".to_string(),
+                comment: None,
+            }]).unwrap(),
+            ticket_prices: Collection::new(vec![TicketPrice {
+                ticket_id: "T1".to_string(),
+                price: "1.50".parse().unwrap(),
+                currency: "EUR".parse().unwrap(),
+                validity_start: chrono::NaiveDate::from_ymd(2018, 1, 1),
+                validity_end: chrono::NaiveDate::from_ymd(2018, 12, 31),
+            }]),
+            od_rules: Collection::new(vec![
+                ODRule {
+                    ticket_id: "T1".to_string(),
+                    origin_stop_area_id: "SA:1".to_string(),
+                    destination_stop_area_id: "SA:2".to_string(),
+                },
+                ODRule {
+                    ticket_id: "T1".to_string(),
+                    origin_stop_area_id: "SA:2".to_string(),
+                    destination_stop_area_id: "SA:1".to_string(),
+                },
+            ]),
+            ..Default::default()
+        };
+
+        ser_deser_in_tmp_dir(|path| {
+            write::write_fares(path, &collections).unwrap();
+            let read = |file| fs::read_to_string(path.join(file)).unwrap();
+            assert_eq!(
+                "ticket_id,ticket_name,ticket_comment\nT1,Single,\n",
+                read("tickets.txt")
+            );
+            assert_eq!(
+                "ticket_id,ticket_price,ticket_currency,ticket_validity_start,\
+                 ticket_validity_end\nT1,1.5,EUR,20180101,20181231\n",
+                read("ticket_prices.txt")
+            );
+            assert_eq!(
+                "ticket_use_id,ticket_id,max_transfers,boarding_time_limit,\
+                 alighting_time_limit\nT1,T1,,,\n",
+                read("ticket_uses.txt")
+            );
+            assert_eq!(
+                "ticket_use_id,restriction_type,use_origin,use_destination\n\
+                 T1,OD,SA:1,SA:2\n\
+                 T1,OD,SA:2,SA:1\n",
+                read("ticket_use_restrictions.txt")
+            );
+        });
+
+        ser_deser_in_tmp_dir(|path| {
+            write::write_fares(path, &Collections::default()).unwrap();
+            assert!(!path.join("tickets.txt").exists());
+        });
+    }
+
+    #[test]
+    fn addresses_serialization_deserialization() {
+        test_serialize_deserialize_collection_with_id(vec![
+            Address {
+                id: "A1".to_string(),
+                street_name: "Rue de Rivoli".to_string(),
+                house_number: Some("20 bis".to_string()),
+                admin_level_8_id: Some("admin:75056".to_string()),
+                admin_level_9_id: None,
+                admin_level_10_id: None,
+            },
+            Address {
+                id: "A2".to_string(),
+                street_name: "Place de la Concorde".to_string(),
+                house_number: None,
+                admin_level_8_id: None,
+                admin_level_9_id: None,
+                admin_level_10_id: None,
+            },
+        ]);
+    }
+
+    #[test]
+    fn grid_calendars_serialization_deserialization() {
+        test_serialize_deserialize_collection_with_id(vec![GridCalendar {
+            id: "GC1".to_string(),
+            name: "Weekdays".to_string(),
+            monday: true,
+            tuesday: true,
+            wednesday: true,
+            thursday: true,
+            friday: true,
+            saturday: false,
+            sunday: false,
+        }]);
+        test_serialize_deserialize_collection(vec![
+            GridExceptionDate {
+                grid_calendar_id: "GC1".to_string(),
+                date: chrono::NaiveDate::from_ymd(2018, 5, 1),
+                included: false,
+            },
+            GridExceptionDate {
+                grid_calendar_id: "GC1".to_string(),
+                date: chrono::NaiveDate::from_ymd(2018, 5, 5),
+                included: true,
+            },
+        ]);
+        test_serialize_deserialize_collection(vec![GridPeriod {
+            grid_calendar_id: "GC1".to_string(),
+            start_date: chrono::NaiveDate::from_ymd(2018, 1, 1),
+            end_date: chrono::NaiveDate::from_ymd(2018, 6, 30),
+        }]);
+        test_serialize_deserialize_collection(vec![
+            GridRelCalendarLine {
+                grid_calendar_id: "GC1".to_string(),
+                line_id: "L1".into(),
+                line_external_code: Some("Line 1".to_string()),
+            },
+            GridRelCalendarLine {
+                grid_calendar_id: "GC1".to_string(),
+                line_id: "L2".into(),
+                line_external_code: None,
+            },
+        ]);
+    }
+
+    #[test]
+    fn ticket_uses_serialization_deserialization() {
+        let collections = Collections {
+            tickets: CollectionWithId::new(vec![
+                Ticket {
+                    id: "T1".to_string(),
+                    name: "Zonal".to_string(),
+                    comment: None,
+                },
+                Ticket {
+                    id: "T2".to_string(),
+                    name: "Single".to_string(),
+                    comment: None,
+                },
+            ]).unwrap(),
+            ticket_uses: CollectionWithId::new(vec![TicketUse {
+                id: "TU1".to_string(),
+                ticket_id: "T1".to_string(),
+                max_transfers: Some(2),
+                boarding_time_limit: Some(3600),
+                alighting_time_limit: None,
+            }]).unwrap(),
+            ticket_use_perimeters: Collection::new(vec![TicketUsePerimeter {
+                ticket_use_id: "TU1".to_string(),
+                object_type: ObjectType::Network,
+                object_id: "N1".to_string(),
+                perimeter_action: PerimeterAction::Included,
+            }]),
+            ticket_use_restrictions: Collection::new(vec![TicketUseRestriction {
+                ticket_use_id: "TU1".to_string(),
+                restriction_type: RestrictionType::Zone,
+                use_origin: "1".to_string(),
+                use_destination: "2".to_string(),
+            }]),
+            od_rules: Collection::new(vec![ODRule {
+                ticket_id: "T2".to_string(),
+                origin_stop_area_id: "SA:1".to_string(),
+                destination_stop_area_id: "SA:2".to_string(),
+            }]),
+            ..Default::default()
+        };
+
+        ser_deser_in_tmp_dir(|path| {
+            write::write_fares(path, &collections).unwrap();
+            let mut handler = PathFileHandler::new(path);
+            let ticket_uses: CollectionWithId<TicketUse> =
+                make_opt_collection_with_id(&mut handler, "ticket_uses.txt").unwrap();
+            assert_eq!(
+                vec!["TU1", "T2"],
+                ticket_uses.values().map(|u| u.id.as_str()).collect::<Vec<_>>()
+            );
+            assert_eq!(Some(2), ticket_uses.get("TU1").unwrap().max_transfers);
+            let perimeters = make_opt_collection(&mut handler, "ticket_use_perimeters.txt");
+            assert_eq!(collections.ticket_use_perimeters, perimeters.unwrap());
+            assert_eq!(
+                "ticket_use_id,restriction_type,use_origin,use_destination\n\
+                 TU1,zone,1,2\n\
+                 T2,OD,SA:1,SA:2\n",
+                fs::read_to_string(path.join("ticket_use_restrictions.txt")).unwrap()
+            );
+        });
+    }
+
     #[test]
     fn admin_stations_serialization_deserialization() {
         test_serialize_deserialize_collection(vec![
@@ -1098,4 +1455,24 @@ mod tests {
             },
         ]);
     }
+
+    #[test]
+    fn translations_serialization_deserialization() {
+        test_serialize_deserialize_collection(vec![
+            Translation {
+                object_type: ObjectType::StopArea,
+                object_id: "OIF:SA:8727100".to_string(),
+                field_name: "stop_name".to_string(),
+                language: "nl".to_string(),
+                translation: "Parijs-Noord".to_string(),
+            },
+            Translation {
+                object_type: ObjectType::VehicleJourney,
+                object_id: "OIF:87604986-1_11595-1".to_string(),
+                field_name: "trip_headsign".to_string(),
+                language: "de".to_string(),
+                translation: "Paris Nordbahnhof".to_string(),
+            },
+        ]);
+    }
 }
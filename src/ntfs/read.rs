@@ -14,14 +14,13 @@
 // along with this program.  If not, see
 // <http://www.gnu.org/licenses/>.
 
-use csv;
-use std::path;
-
 use super::{Code, CommentLink, ObjectProperty, Stop, StopTime};
 use collection::*;
 use failure::ResultExt;
 use model::Collections;
 use objects::*;
+use read_utils::{csv_reader, opt_csv_reader, FileHandler};
+use report::{Issue, Issues};
 use utils::make_collection_with_id;
 use Result;
 
@@ -59,67 +58,122 @@ impl From<Stop> for StopPoint {
                 lon: stop.lon,
                 lat: stop.lat,
             },
-            stop_area_id,
+            stop_area_id: stop_area_id.into(),
             timezone: stop.timezone,
             geometry_id: stop.geometry_id,
             equipment_id: stop.equipment_id,
             fare_zone_id: stop.fare_zone_id,
+            address_id: stop.address_id,
         }
     }
 }
 
-pub fn manage_stops(collections: &mut Collections, path: &path::Path) -> Result<()> {
+pub fn manage_stops<H>(
+    collections: &mut Collections,
+    file_handler: &mut H,
+    issues: &mut Issues,
+) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
     info!("Reading stops.txt");
-    let path = path.join("stops.txt");
-    let mut rdr = csv::Reader::from_path(&path).with_context(ctx_from_path!(path))?;
+    let (mut rdr, _) = csv_reader(file_handler, "stops.txt")?;
     let mut stop_areas = vec![];
     let mut stop_points = vec![];
+    let mut entrances = vec![];
     for stop in rdr.deserialize() {
-        let stop: Stop = stop.with_context(ctx_from_path!(path))?;
+        let stop: Stop = skip_fail!(stop, issues, Issue::InvalidRow, "stops.txt");
         match stop.location_type {
             0 => {
                 if stop.parent_station.is_none() {
                     let mut new_stop_area = stop.clone();
                     new_stop_area.id = format!("Navitia:{}", new_stop_area.id);
+                    collections.id_mappings.push(IdMapping::new(
+                        "stop_areas",
+                        &stop.id,
+                        &new_stop_area.id,
+                    ));
                     stop_areas.push(StopArea::from(new_stop_area));
                 }
                 stop_points.push(StopPoint::from(stop));
             }
             1 => stop_areas.push(StopArea::from(stop)),
-            i => warn!("stop.location_type = {} not yet supported, skipping.", i),
+            3 => {
+                let stop_area_id = skip_fail!(
+                    stop.parent_station.clone().ok_or_else(|| format_err!(
+                        "entrance {} without parent_station, skipping",
+                        stop.id
+                    )),
+                    issues,
+                    Issue::InvalidOptionalElement,
+                    "stops.txt"
+                );
+                entrances.push(Entrance {
+                    id: stop.id,
+                    name: stop.name,
+                    coord: Coord {
+                        lon: stop.lon,
+                        lat: stop.lat,
+                    },
+                    stop_area_id: stop_area_id.into(),
+                    timezone: stop.timezone,
+                });
+            }
+            i => {
+                issues.handle_object(
+                    Issue::Unsupported,
+                    "stops.txt",
+                    &stop.id,
+                    format!("stop.location_type = {} not yet supported, skipping.", i),
+                )?;
+            }
         }
     }
     collections.stop_areas = CollectionWithId::new(stop_areas)?;
     collections.stop_points = CollectionWithId::new(stop_points)?;
+    collections.entrances = CollectionWithId::new(entrances)?;
     Ok(())
 }
 
-pub fn manage_stop_times(collections: &mut Collections, path: &path::Path) -> Result<()> {
-    info!("Reading stop_times.txt");
-    let path = path.join("stop_times.txt");
-    let mut rdr = csv::Reader::from_path(&path).with_context(ctx_from_path!(path))?;
+pub fn manage_stop_times<H>(
+    collections: &mut Collections,
+    file_handler: &mut H,
+    issues: &mut Issues,
+) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let file = "stop_times.txt";
+    info!("Reading {}", file);
+    let (mut rdr, path) = csv_reader(file_handler, file)?;
     for stop_time in rdr.deserialize() {
-        let stop_time: StopTime = stop_time.with_context(ctx_from_path!(path))?;
-        let stop_point_idx = collections
-            .stop_points
-            .get_idx(&stop_time.stop_id)
-            .ok_or_else(|| {
-                format_err!(
+        let stop_time: StopTime = skip_fail!(stop_time, issues, Issue::InvalidRow, file);
+        let stop_point_idx = skip_fail!(
+            collections
+                .stop_points
+                .get_idx(&stop_time.stop_id)
+                .ok_or_else(|| format_err!(
                     "Problem reading {:?}: stop_id={:?} not found",
                     path,
                     stop_time.stop_id
-                )
-            })?;
-        let vj_idx = collections
-            .vehicle_journeys
-            .get_idx(&stop_time.trip_id)
-            .ok_or_else(|| {
-                format_err!(
+                )),
+            issues,
+            Issue::UnknownReference,
+            file
+        );
+        let vj_idx = skip_fail!(
+            collections
+                .vehicle_journeys
+                .get_idx(&stop_time.trip_id)
+                .ok_or_else(|| format_err!(
                     "Problem reading {:?}: trip_id={:?} not found",
                     path,
-                    stop_time.stop_id
-                )
-            })?;
+                    stop_time.trip_id
+                )),
+            issues,
+            Issue::UnknownReference,
+            file
+        );
         collections
             .vehicle_journeys
             .index_mut(vj_idx)
@@ -135,11 +189,19 @@ pub fn manage_stop_times(collections: &mut Collections, path: &path::Path) -> Re
                 drop_off_type: stop_time.drop_off_type,
                 datetime_estimated: stop_time.datetime_estimated,
                 local_zone_id: stop_time.local_zone_id,
+                extras: StopTimeExtras::boxed(
+                    stop_time.shape_dist_traveled,
+                    stop_time.continuous_pickup,
+                    stop_time.continuous_drop_off,
+                ),
             });
     }
     let mut vehicle_journeys = collections.vehicle_journeys.take();
     for vj in &mut vehicle_journeys {
         vj.stop_times.sort_unstable_by_key(|st| st.sequence);
+        // the stop times were pushed one by one, their spare capacity
+        // is significant on big datasets
+        vj.stop_times.shrink_to_fit();
     }
     collections.vehicle_journeys = CollectionWithId::new(vehicle_journeys)?;
     Ok(())
@@ -172,15 +234,19 @@ where
     insert_code_with_idx(collection, idx, code);
 }
 
-pub fn manage_codes(collections: &mut Collections, path: &path::Path) -> Result<()> {
+pub fn manage_codes<H>(collections: &mut Collections, file_handler: &mut H) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
     let file = "object_codes.txt";
-    if !path.join(file).exists() {
-        info!("Skipping {}", file);
-        return Ok(());
-    }
+    let (mut rdr, path) = match opt_csv_reader(file_handler, file)? {
+        (Some(rdr), path) => (rdr, path),
+        (None, _) => {
+            info!("Skipping {}", file);
+            return Ok(());
+        }
+    };
     info!("Reading {}", file);
-    let path = path.join(file);
-    let mut rdr = csv::Reader::from_path(&path).with_context(ctx_from_path!(path))?;
     for code in rdr.deserialize() {
         let code: Code = code.with_context(ctx_from_path!(path))?;
         match code.object_type {
@@ -208,10 +274,12 @@ struct FeedInfo {
     info_value: String,
 }
 
-pub fn manage_feed_infos(collections: &mut Collections, path: &path::Path) -> Result<()> {
+pub fn manage_feed_infos<H>(collections: &mut Collections, file_handler: &mut H) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
     info!("Reading feed_infos.txt");
-    let path = path.join("feed_infos.txt");
-    let mut rdr = csv::Reader::from_path(&path).with_context(ctx_from_path!(path))?;
+    let (mut rdr, path) = csv_reader(file_handler, "feed_infos.txt")?;
     collections.feed_infos.clear();
     for feed_info in rdr.deserialize() {
         let feed_info: FeedInfo = feed_info.with_context(ctx_from_path!(path))?;
@@ -261,12 +329,18 @@ where
     Ok(())
 }
 
-pub fn manage_comments(collections: &mut Collections, path: &path::Path) -> Result<()> {
-    if path.join("comments.txt").exists() {
-        collections.comments = make_collection_with_id(path, "comments.txt")?;
+pub fn manage_comments<H>(
+    collections: &mut Collections,
+    file_handler: &mut H,
+    issues: &mut Issues,
+) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    if file_handler.get_file_if_exists("comments.txt")?.0.is_some() {
+        collections.comments = make_collection_with_id(file_handler, "comments.txt")?;
 
-        let path = path.join("comment_links.txt");
-        if let Ok(mut rdr) = csv::Reader::from_path(&path) {
+        if let (Some(mut rdr), path) = opt_csv_reader(file_handler, "comment_links.txt")? {
             info!("Reading comment_links.txt");
             for comment_link in rdr.deserialize() {
                 let comment_link: CommentLink = comment_link.with_context(ctx_from_path!(path))?;
@@ -296,8 +370,18 @@ pub fn manage_comments(collections: &mut Collections, path: &path::Path) -> Resu
                         &collections.comments,
                         &comment_link,
                     )?,
-                    ObjectType::StopTime => warn!("comments are not added to StopTime yet"),
-                    ObjectType::LineGroup => warn!("line_groups.txt is not parsed yet"),
+                    ObjectType::StopTime => {
+                        issues.handle(
+                            Issue::Unsupported,
+                            "comment_links.txt",
+                            "comments are not added to StopTime yet",
+                        )?;
+                    }
+                    ObjectType::LineGroup => insert_comment_link(
+                        &mut collections.line_groups,
+                        &collections.comments,
+                        &comment_link,
+                    )?,
                     _ => bail!(
                         "comment does not support {}",
                         comment_link.object_type.as_str()
@@ -330,15 +414,22 @@ where
     ));
 }
 
-pub fn manage_object_properties(collections: &mut Collections, path: &path::Path) -> Result<()> {
+pub fn manage_object_properties<H>(
+    collections: &mut Collections,
+    file_handler: &mut H,
+) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
     let file = "object_properties.txt";
-    let path = path.join(file);
-    if !path.exists() {
-        info!("Skipping {}", file);
-        return Ok(());
-    }
+    let (mut rdr, path) = match opt_csv_reader(file_handler, file)? {
+        (Some(rdr), path) => (rdr, path),
+        (None, _) => {
+            info!("Skipping {}", file);
+            return Ok(());
+        }
+    };
     info!("Reading {}", file);
-    let mut rdr = csv::Reader::from_path(&path).with_context(ctx_from_path!(path))?;
     for obj_prop in rdr.deserialize() {
         let obj_prop: ObjectProperty = obj_prop.with_context(ctx_from_path!(path))?;
         match obj_prop.object_type {
@@ -359,20 +450,29 @@ pub fn manage_object_properties(collections: &mut Collections, path: &path::Path
     Ok(())
 }
 
-pub fn manage_geometries(collections: &mut Collections, path: &path::Path) -> Result<()> {
+pub fn manage_geometries<H>(
+    collections: &mut Collections,
+    file_handler: &mut H,
+    issues: &mut Issues,
+) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
     let file = "geometries.txt";
-    let path = path.join(file);
-    if !path.exists() {
-        info!("Skipping {}", file);
-        return Ok(());
-    }
+    let mut rdr = match opt_csv_reader(file_handler, file)? {
+        (Some(rdr), _) => rdr,
+        (None, _) => {
+            info!("Skipping {}", file);
+            return Ok(());
+        }
+    };
 
     info!("Reading {}", file);
 
     let mut geometries: Vec<Geometry> = vec![];
-    let mut rdr = csv::Reader::from_path(&path).with_context(ctx_from_path!(path))?;
     for geometry in rdr.deserialize() {
-        let geometry: Geometry = skip_fail!(geometry);
+        let geometry: Geometry =
+            skip_fail!(geometry, issues, Issue::InvalidOptionalElement, file);
         geometries.push(geometry)
     }
 
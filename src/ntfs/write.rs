@@ -16,19 +16,19 @@
 
 use super::{Code, CommentLink, ObjectProperty, Result, Stop, StopTime};
 use collection::{Collection, CollectionWithId, Id};
-use common_format::CalendarDate;
 use csv;
 use failure::ResultExt;
 use model::Collections;
 use objects::*;
+use progress;
 use serde;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path;
 
 pub fn write_feed_infos(path: &path::Path, feed_infos: &HashMap<String, String>) -> Result<()> {
     info!("Writing feed_infos.txt");
     let path = path.join("feed_infos.txt");
-    let mut wtr = csv::Writer::from_path(&path).with_context(ctx_from_path!(path))?;
+    let mut wtr = progress::csv_writer(&path)?;
     wtr.write_record(&["feed_info_param", "feed_info_value"])
         .with_context(ctx_from_path!(path))?;
     for feed_info in feed_infos {
@@ -46,9 +46,8 @@ pub fn write_vehicle_journeys_and_stop_times(
     info!("Writing trips.txt and stop_times.txt");
     let trip_path = path.join("trips.txt");
     let stop_times_path = path.join("stop_times.txt");
-    let mut vj_wtr = csv::Writer::from_path(&trip_path).with_context(ctx_from_path!(trip_path))?;
-    let mut st_wtr =
-        csv::Writer::from_path(&stop_times_path).with_context(ctx_from_path!(stop_times_path))?;
+    let mut vj_wtr = progress::csv_writer(&trip_path)?;
+    let mut st_wtr = progress::csv_writer(&stop_times_path)?;
     for vj in vehicle_journeys.values() {
         vj_wtr
             .serialize(vj)
@@ -68,9 +67,12 @@ pub fn write_vehicle_journeys_and_stop_times(
                     drop_off_type: st.drop_off_type,
                     datetime_estimated: st.datetime_estimated,
                     local_zone_id: st.local_zone_id,
+                    shape_dist_traveled: st.shape_dist_traveled(),
+                    continuous_pickup: st.continuous_pickup(),
+                    continuous_drop_off: st.continuous_drop_off(),
                     // TODO: Add headsign and stop_time_ids
                 })
-                .with_context(ctx_from_path!(st_wtr))?;
+                .with_context(ctx_from_path!(stop_times_path))?;
         }
     }
     st_wtr
@@ -92,7 +94,7 @@ where
 {
     info!("Writing {}", file);
     let path = path.join(file);
-    let mut wtr = csv::Writer::from_path(&path).with_context(ctx_from_path!(path))?;
+    let mut wtr = progress::csv_writer(&path)?;
     for obj in collection.values() {
         wtr.serialize(obj).with_context(ctx_from_path!(path))?;
     }
@@ -107,7 +109,7 @@ where
 {
     info!("Writing {}", file);
     let path = path.join(file);
-    let mut wtr = csv::Writer::from_path(&path).with_context(ctx_from_path!(path))?;
+    let mut wtr = progress::csv_writer(&path)?;
     for obj in collection.values() {
         wtr.serialize(obj).with_context(ctx_from_path!(path))?;
     }
@@ -116,37 +118,15 @@ where
     Ok(())
 }
 
-pub fn write_calendar_dates(
-    path: &path::Path,
-    calendars: &CollectionWithId<Calendar>,
-) -> Result<()> {
-    info!("Writing calendar_dates.txt");
-    let calendar_dates_path = path.join("calendar_dates.txt");
-    let mut wtr = csv::Writer::from_path(&calendar_dates_path)
-        .with_context(ctx_from_path!(calendar_dates_path))?;
-    for c in calendars.values() {
-        for d in &c.dates {
-            wtr.serialize(CalendarDate {
-                service_id: c.id.clone(),
-                date: *d,
-                exception_type: ExceptionType::Add,
-            }).with_context(ctx_from_path!(calendar_dates_path))?;
-        }
-    }
-    wtr.flush()
-        .with_context(ctx_from_path!(calendar_dates_path))?;
-
-    Ok(())
-}
-
 pub fn write_stops(
     path: &path::Path,
     stop_points: &CollectionWithId<StopPoint>,
     stop_areas: &CollectionWithId<StopArea>,
+    entrances: &CollectionWithId<Entrance>,
 ) -> Result<()> {
     info!("Writing stops.txt");
     let path = path.join("stops.txt");
-    let mut wtr = csv::Writer::from_path(&path).with_context(ctx_from_path!(path))?;
+    let mut wtr = progress::csv_writer(&path)?;
     for st in stop_points.values() {
         wtr.serialize(Stop {
             id: st.id.clone(),
@@ -160,6 +140,7 @@ pub fn write_stops(
             timezone: st.timezone.clone(),
             equipment_id: st.equipment_id.clone(),
             geometry_id: st.geometry_id.clone(),
+            address_id: st.address_id.clone(),
         }).with_context(ctx_from_path!(path))?;
     }
 
@@ -176,6 +157,24 @@ pub fn write_stops(
             timezone: sa.timezone.clone(),
             equipment_id: sa.equipment_id.clone(),
             geometry_id: sa.geometry_id.clone(),
+            address_id: None,
+        }).with_context(ctx_from_path!(path))?;
+    }
+
+    for e in entrances.values() {
+        wtr.serialize(Stop {
+            id: e.id.clone(),
+            visible: true,
+            name: e.name.clone(),
+            lat: e.coord.lat,
+            lon: e.coord.lon,
+            fare_zone_id: None,
+            location_type: 3,
+            parent_station: Some(e.stop_area_id.to_string()),
+            timezone: e.timezone.clone(),
+            equipment_id: None,
+            geometry_id: None,
+            address_id: None,
         }).with_context(ctx_from_path!(path))?;
     }
     wtr.flush().with_context(ctx_from_path!(path))?;
@@ -211,10 +210,8 @@ pub fn write_comments(path: &path::Path, collections: &Collections) -> Result<()
     let comments_path = path.join("comments.txt");
     let comment_links_path = path.join("comment_links.txt");
 
-    let mut c_wtr =
-        csv::Writer::from_path(&comments_path).with_context(ctx_from_path!(comments_path))?;
-    let mut cl_wtr = csv::Writer::from_path(&comment_links_path)
-        .with_context(ctx_from_path!(comment_links_path))?;
+    let mut c_wtr = progress::csv_writer(&comments_path)?;
+    let mut cl_wtr = progress::csv_writer(&comment_links_path)?;
     for c in collections.comments.values() {
         c_wtr
             .serialize(c)
@@ -251,7 +248,13 @@ pub fn write_comments(path: &path::Path, collections: &Collections) -> Result<()
         &collections.comments,
         &comment_links_path,
     )?;
-    // TODO: add stop_times and line_groups
+    write_comment_links_from_collection_with_id(
+        &mut cl_wtr,
+        &collections.line_groups,
+        &collections.comments,
+        &comment_links_path,
+    )?;
+    // TODO: add stop_times
 
     cl_wtr
         .flush()
@@ -289,7 +292,7 @@ pub fn write_codes(path: &path::Path, collections: &Collections) -> Result<()> {
 
     let path = path.join("object_codes.txt");
 
-    let mut wtr = csv::Writer::from_path(&path).with_context(ctx_from_path!(path))?;
+    let mut wtr = progress::csv_writer(&path)?;
     write_codes_from_collection_with_id(&mut wtr, &collections.stop_areas, &path)?;
     write_codes_from_collection_with_id(&mut wtr, &collections.stop_points, &path)?;
     write_codes_from_collection_with_id(&mut wtr, &collections.networks, &path)?;
@@ -330,7 +333,7 @@ pub fn write_object_properties(path: &path::Path, collections: &Collections) ->
 
     let path = path.join("object_properties.txt");
 
-    let mut wtr = csv::Writer::from_path(&path).with_context(ctx_from_path!(path))?;
+    let mut wtr = progress::csv_writer(&path)?;
     write_object_properties_from_collection_with_id(&mut wtr, &collections.stop_areas, &path)?;
     write_object_properties_from_collection_with_id(&mut wtr, &collections.stop_points, &path)?;
     write_object_properties_from_collection_with_id(&mut wtr, &collections.lines, &path)?;
@@ -345,3 +348,80 @@ pub fn write_object_properties(path: &path::Path, collections: &Collections) ->
 
     Ok(())
 }
+
+pub fn write_grid_calendars(path: &path::Path, collections: &Collections) -> Result<()> {
+    if collections.grid_calendars.is_empty() {
+        return Ok(());
+    }
+    write_collection_with_id(path, "grid_calendars.txt", &collections.grid_calendars)?;
+    write_collection(
+        path,
+        "grid_exception_dates.txt",
+        &collections.grid_exception_dates,
+    )?;
+    write_collection(path, "grid_periods.txt", &collections.grid_periods)?;
+    write_collection(
+        path,
+        "grid_rel_calendar_line.txt",
+        &collections.grid_rel_calendar_line,
+    )?;
+    Ok(())
+}
+
+pub fn write_fares(path: &path::Path, collections: &Collections) -> Result<()> {
+    if collections.tickets.is_empty() {
+        return Ok(());
+    }
+    write_collection_with_id(path, "tickets.txt", &collections.tickets)?;
+    write_collection(path, "ticket_prices.txt", &collections.ticket_prices)?;
+
+    // The tickets without any use, as the ones of the GTFS fares, get
+    // a single use restricted to their OD rules.
+    let used_ticket_ids: HashSet<&str> = collections
+        .ticket_uses
+        .values()
+        .map(|ticket_use| ticket_use.ticket_id.as_str())
+        .collect();
+    let mut ticket_uses: Vec<_> = collections.ticket_uses.values().cloned().collect();
+    let mut restrictions: Vec<_> = collections
+        .ticket_use_restrictions
+        .values()
+        .cloned()
+        .collect();
+    for ticket in collections.tickets.values() {
+        if !used_ticket_ids.contains(ticket.id.as_str()) {
+            ticket_uses.push(TicketUse {
+                id: ticket.id.clone(),
+                ticket_id: ticket.id.clone(),
+                max_transfers: None,
+                boarding_time_limit: None,
+                alighting_time_limit: None,
+            });
+        }
+    }
+    for od_rule in collections.od_rules.values() {
+        if !used_ticket_ids.contains(od_rule.ticket_id.as_str()) {
+            restrictions.push(TicketUseRestriction {
+                ticket_use_id: od_rule.ticket_id.clone(),
+                restriction_type: RestrictionType::OriginDestination,
+                use_origin: od_rule.origin_stop_area_id.clone(),
+                use_destination: od_rule.destination_stop_area_id.clone(),
+            });
+        }
+    }
+    write_collection(path, "ticket_uses.txt", &Collection::new(ticket_uses))?;
+    write_collection(
+        path,
+        "ticket_use_restrictions.txt",
+        &Collection::new(restrictions),
+    )?;
+    if !collections.ticket_use_perimeters.is_empty() {
+        write_collection(
+            path,
+            "ticket_use_perimeters.txt",
+            &collections.ticket_use_perimeters,
+        )?;
+    }
+
+    Ok(())
+}
@@ -19,19 +19,109 @@
 #![allow(missing_docs)]
 
 use chrono;
-use collection::{Id, Idx};
+use collection::{Id, Idx, TypedId};
+use currency::{Amount, Currency};
 use geo_types::Geometry as GeoGeometry;
 use std::cmp::Ordering;
 use std::collections::BTreeSet;
 use std::hash::{Hash, Hasher};
 use std::str::FromStr;
+use std::sync::Arc;
 use utils::*;
 
 pub trait AddPrefix {
-    fn add_prefix(&mut self, prefix: &str);
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration);
+}
+
+/// How `AddPrefix` prefixes the identifiers of the objects and of
+/// their references.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrefixConfiguration {
+    /// The prefix, as `my_dataset`.
+    pub prefix: String,
+    /// The separator between the prefix and the identifiers, `:` by
+    /// default.
+    pub separator: String,
+    /// The collections whose identifiers are not prefixed, by name as
+    /// `physical_modes` (see `model::COLLECTIONS_WITH_ID`).
+    pub exempt_collections: BTreeSet<String>,
+    /// The patterns of the identifiers not prefixed, whatever their
+    /// collection, where `*` matches any sequence of characters.
+    pub exempt_patterns: Vec<String>,
+    /// The prefix, separator included, replaced by the prefix, as
+    /// `old:`, the identifiers without it being left as is.  `None`
+    /// to prefix all the identifiers.
+    pub replaced_prefix: Option<String>,
+}
+
+impl PrefixConfiguration {
+    /// Creates a configuration prefixing all the identifiers with
+    /// `prefix` followed by `:`.
+    pub fn new<S: Into<String>>(prefix: S) -> Self {
+        PrefixConfiguration {
+            prefix: prefix.into(),
+            separator: ":".to_string(),
+            exempt_collections: BTreeSet::new(),
+            exempt_patterns: vec![],
+            replaced_prefix: None,
+        }
+    }
+
+    /// Returns the prefixed identifier `id` of an object of the
+    /// collection named `collection`, `id` being left as is if it
+    /// lacks the replaced prefix.
+    pub fn prefix(&self, collection: &str, id: &str) -> String {
+        let id = match self.replaced_prefix {
+            Some(ref replaced_prefix) => match id.strip_prefix(replaced_prefix.as_str()) {
+                Some(id) => id,
+                None => return id.to_string(),
+            },
+            None => id,
+        };
+        let exempt = self.exempt_collections.contains(collection)
+            || self
+                .exempt_patterns
+                .iter()
+                .any(|pattern| matches_pattern(pattern, id));
+        if exempt {
+            id.to_string()
+        } else {
+            format!("{}{}{}", self.prefix, self.separator, id)
+        }
+    }
+
+    /// Returns the prefixed identifier `id` of an object of type
+    /// `object_type`.
+    pub fn prefix_object(&self, object_type: &ObjectType, id: &str) -> String {
+        self.prefix(object_type.collection_name(), id)
+    }
+}
+
+// Whether `id` matches `pattern`, where `*` matches any sequence of
+// characters.
+fn matches_pattern(pattern: &str, id: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    if !id.starts_with(first) {
+        return false;
+    }
+    let mut rest = &id[first.len()..];
+    let parts: Vec<_> = parts.collect();
+    match parts.split_last() {
+        None => rest.is_empty(),
+        Some((last, middle)) => {
+            for part in middle {
+                match rest.find(part) {
+                    Some(i) => rest = &rest[i + part.len()..],
+                    None => return false,
+                }
+            }
+            rest.ends_with(last)
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum ObjectType {
     StopArea,
@@ -62,6 +152,21 @@ impl ObjectType {
             ObjectType::LineGroup => "line_group",
         }
     }
+
+    /// Returns the name of the collection of the objects of this type,
+    /// as `stop_areas`.
+    pub fn collection_name(&self) -> &'static str {
+        match *self {
+            ObjectType::StopArea => "stop_areas",
+            ObjectType::StopPoint => "stop_points",
+            ObjectType::Network => "networks",
+            ObjectType::Line => "lines",
+            ObjectType::Route => "routes",
+            ObjectType::VehicleJourney => "vehicle_journeys",
+            ObjectType::StopTime => "stop_times",
+            ObjectType::LineGroup => "line_groups",
+        }
+    }
 }
 
 // We use a Vec here for memory efficiency.  Other possible types can
@@ -139,8 +244,8 @@ impl Id<Contributor> for Contributor {
     }
 }
 impl AddPrefix for Contributor {
-    fn add_prefix(&mut self, prefix: &str) {
-        self.id = prefix.to_string() + &self.id;
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.id = prefix_conf.prefix("contributors", &self.id);
     }
 }
 
@@ -261,9 +366,59 @@ impl Id<Contributor> for Dataset {
     }
 }
 impl AddPrefix for Dataset {
-    fn add_prefix(&mut self, prefix: &str) {
-        self.id = prefix.to_string() + &self.id;
-        self.contributor_id = prefix.to_string() + &self.contributor_id;
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.id = prefix_conf.prefix("datasets", &self.id);
+        self.contributor_id = prefix_conf.prefix("contributors", &self.contributor_id);
+    }
+}
+
+/// An organization to credit for the data of a dataset, or only of
+/// one of its networks, lines or vehicle journeys.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Attribution {
+    #[serde(rename = "attribution_id")]
+    pub id: Option<String>,
+    pub dataset_id: String,
+    pub network_id: Option<TypedId<Network>>,
+    pub line_id: Option<TypedId<Line>>,
+    #[serde(rename = "trip_id")]
+    pub vehicle_journey_id: Option<String>,
+    pub organization_name: String,
+    #[serde(default, deserialize_with = "de_from_u8", serialize_with = "ser_from_bool")]
+    pub is_producer: bool,
+    #[serde(default, deserialize_with = "de_from_u8", serialize_with = "ser_from_bool")]
+    pub is_operator: bool,
+    #[serde(default, deserialize_with = "de_from_u8", serialize_with = "ser_from_bool")]
+    pub is_authority: bool,
+    #[serde(rename = "attribution_url")]
+    pub url: Option<String>,
+    #[serde(rename = "attribution_email")]
+    pub email: Option<String>,
+    #[serde(rename = "attribution_phone")]
+    pub phone: Option<String>,
+}
+
+impl Id<Dataset> for Attribution {
+    fn id(&self) -> &str {
+        &self.dataset_id
+    }
+}
+
+impl AddPrefix for Attribution {
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.dataset_id = prefix_conf.prefix("datasets", &self.dataset_id);
+        if let Some(ref mut id) = self.id {
+            *id = prefix_conf.prefix("attributions", id);
+        }
+        if let Some(ref mut network_id) = self.network_id {
+            *network_id = prefix_conf.prefix("networks", network_id).into();
+        }
+        if let Some(ref mut line_id) = self.line_id {
+            *line_id = prefix_conf.prefix("lines", line_id).into();
+        }
+        if let Some(ref mut vehicle_journey_id) = self.vehicle_journey_id {
+            *vehicle_journey_id = prefix_conf.prefix("vehicle_journeys", vehicle_journey_id);
+        }
     }
 }
 
@@ -280,8 +435,8 @@ impl Id<CommercialMode> for CommercialMode {
     }
 }
 impl AddPrefix for CommercialMode {
-    fn add_prefix(&mut self, prefix: &str) {
-        self.id = prefix.to_string() + &self.id;
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.id = prefix_conf.prefix("commercial_modes", &self.id);
     }
 }
 
@@ -361,8 +516,8 @@ impl GetObjectType for Network {
 }
 
 impl AddPrefix for Network {
-    fn add_prefix(&mut self, prefix: &str) {
-        self.id = prefix.to_string() + &self.id;
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.id = prefix_conf.prefix("networks", &self.id);
     }
 }
 
@@ -480,7 +635,7 @@ pub struct Line {
     pub text_color: Option<Rgb>,
     #[serde(rename = "line_sort_order")]
     pub sort_order: Option<u32>,
-    pub network_id: String,
+    pub network_id: TypedId<Network>,
     pub commercial_mode_id: String,
     pub geometry_id: Option<String>,
     #[serde(rename = "line_opening_time")]
@@ -505,10 +660,10 @@ impl Id<CommercialMode> for Line {
     }
 }
 impl AddPrefix for Line {
-    fn add_prefix(&mut self, prefix: &str) {
-        self.id = prefix.to_string() + &self.id;
-        self.network_id = prefix.to_string() + &self.network_id;
-        self.commercial_mode_id = prefix.to_string() + &self.commercial_mode_id;
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.id = prefix_conf.prefix("lines", &self.id);
+        self.network_id = prefix_conf.prefix("networks", &self.network_id).into();
+        self.commercial_mode_id = prefix_conf.prefix("commercial_modes", &self.commercial_mode_id);
     }
 }
 impl_codes!(Line);
@@ -521,6 +676,64 @@ impl GetObjectType for Line {
     }
 }
 
+/// A commercial group of lines, presented under its main line.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct LineGroup {
+    #[serde(rename = "line_group_id")]
+    pub id: String,
+    #[serde(rename = "line_group_name")]
+    pub name: String,
+    pub main_line_id: String,
+    #[serde(skip)]
+    pub comment_links: CommentLinksT,
+}
+
+impl Id<LineGroup> for LineGroup {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl AddPrefix for LineGroup {
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.id = prefix_conf.prefix("line_groups", &self.id);
+        self.main_line_id = prefix_conf.prefix("lines", &self.main_line_id);
+    }
+}
+impl_comment_links!(LineGroup);
+
+impl GetObjectType for LineGroup {
+    fn get_object_type() -> ObjectType {
+        ObjectType::LineGroup
+    }
+}
+
+/// The membership of a line in a line group.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct LineGroupLink {
+    pub line_group_id: String,
+    pub line_id: TypedId<Line>,
+}
+
+impl Id<LineGroup> for LineGroupLink {
+    fn id(&self) -> &str {
+        &self.line_group_id
+    }
+}
+
+impl Id<Line> for LineGroupLink {
+    fn id(&self) -> &str {
+        &self.line_id
+    }
+}
+
+impl AddPrefix for LineGroupLink {
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.line_group_id = prefix_conf.prefix("line_groups", &self.line_group_id);
+        self.line_id = prefix_conf.prefix("lines", &self.line_id).into();
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct Route {
     #[serde(rename = "route_id")]
@@ -534,9 +747,11 @@ pub struct Route {
     pub object_properties: KeysValues,
     #[serde(skip)]
     pub comment_links: CommentLinksT,
-    pub line_id: String,
+    pub line_id: TypedId<Line>,
     pub geometry_id: Option<String>,
     pub destination_id: Option<String>,
+    pub continuous_pickup: Option<u8>,
+    pub continuous_drop_off: Option<u8>,
 }
 impl Id<Route> for Route {
     fn id(&self) -> &str {
@@ -549,9 +764,9 @@ impl Id<Line> for Route {
     }
 }
 impl AddPrefix for Route {
-    fn add_prefix(&mut self, prefix: &str) {
-        self.id = prefix.to_string() + &self.id;
-        self.line_id = prefix.to_string() + &self.line_id;
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.id = prefix_conf.prefix("routes", &self.id);
+        self.line_id = prefix_conf.prefix("lines", &self.line_id).into();
     }
 }
 impl_codes!(Route);
@@ -564,7 +779,7 @@ impl GetObjectType for Route {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct VehicleJourney {
     #[serde(rename = "trip_id")]
     pub id: String,
@@ -574,12 +789,12 @@ pub struct VehicleJourney {
     pub object_properties: KeysValues,
     #[serde(skip)]
     pub comment_links: CommentLinksT,
-    pub route_id: String,
+    pub route_id: TypedId<Route>,
     pub physical_mode_id: String,
     pub dataset_id: String,
-    pub service_id: String,
+    pub service_id: TypedId<Calendar>,
     #[serde(rename = "trip_headsign")]
-    pub headsign: Option<String>,
+    pub headsign: Option<Arc<str>>,
     pub block_id: Option<String>,
     pub company_id: String,
     pub trip_property_id: Option<String>,
@@ -594,10 +809,10 @@ impl Default for VehicleJourney {
             codes: KeysValues::default(),
             object_properties: KeysValues::default(),
             comment_links: CommentLinksT::default(),
-            route_id: "default_route".to_string(),
+            route_id: "default_route".into(),
             physical_mode_id: "default_physical_mode".to_string(),
             dataset_id: "default_dataset".to_string(),
-            service_id: "".to_string(),
+            service_id: "".into(),
             headsign: None,
             block_id: None,
             company_id: "".to_string(),
@@ -632,16 +847,21 @@ impl Id<Company> for VehicleJourney {
         &self.company_id
     }
 }
+impl Id<Calendar> for VehicleJourney {
+    fn id(&self) -> &str {
+        &self.service_id
+    }
+}
 impl AddPrefix for VehicleJourney {
-    fn add_prefix(&mut self, prefix: &str) {
-        self.id = prefix.to_string() + &self.id;
-        self.route_id = prefix.to_string() + &self.route_id;
-        self.dataset_id = prefix.to_string() + &self.dataset_id;
-        self.company_id = prefix.to_string() + &self.company_id;
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.id = prefix_conf.prefix("vehicle_journeys", &self.id);
+        self.route_id = prefix_conf.prefix("routes", &self.route_id).into();
+        self.dataset_id = prefix_conf.prefix("datasets", &self.dataset_id);
+        self.company_id = prefix_conf.prefix("companies", &self.company_id);
         self.trip_property_id = self
             .trip_property_id
             .as_ref()
-            .map(|id| prefix.to_string() + id);
+            .map(|id| prefix_conf.prefix("trip_properties", id));
     }
 }
 impl_codes!(VehicleJourney);
@@ -681,7 +901,7 @@ impl Error for TimeError {
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct Time(u32);
 impl Time {
     pub fn new(h: u32, m: u32, s: u32) -> Time {
@@ -752,7 +972,42 @@ impl<'de> ::serde::Deserialize<'de> for Time {
     }
 }
 
-#[derive(Debug, PartialEq)]
+/// The properties of a stop time that are rarely given, boxed apart
+/// to keep the stop times small.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct StopTimeExtras {
+    /// The distance traveled from the first stop time along the
+    /// geometry of the vehicle journey.
+    pub shape_dist_traveled: Option<f64>,
+    /// Whether the riders can board anywhere between this stop time
+    /// and the next one, with the values of `pickup_type`.
+    pub continuous_pickup: Option<u8>,
+    /// Whether the riders can alight anywhere between this stop time
+    /// and the next one, with the values of `drop_off_type`.
+    pub continuous_drop_off: Option<u8>,
+}
+
+impl StopTimeExtras {
+    /// Returns the boxed extras, `None` if none of them is given.
+    pub fn boxed(
+        shape_dist_traveled: Option<f64>,
+        continuous_pickup: Option<u8>,
+        continuous_drop_off: Option<u8>,
+    ) -> Option<Box<Self>> {
+        let extras = StopTimeExtras {
+            shape_dist_traveled,
+            continuous_pickup,
+            continuous_drop_off,
+        };
+        if extras == StopTimeExtras::default() {
+            None
+        } else {
+            Some(Box::new(extras))
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct StopTime {
     pub stop_point_idx: Idx<StopPoint>,
     pub sequence: u32,
@@ -764,6 +1019,28 @@ pub struct StopTime {
     pub drop_off_type: u8,
     pub datetime_estimated: bool,
     pub local_zone_id: Option<u16>,
+    pub extras: Option<Box<StopTimeExtras>>,
+}
+
+impl StopTime {
+    pub fn shape_dist_traveled(&self) -> Option<f64> {
+        self.extras.as_ref().and_then(|e| e.shape_dist_traveled)
+    }
+
+    pub fn set_shape_dist_traveled(&mut self, shape_dist_traveled: Option<f64>) {
+        match self.extras {
+            Some(ref mut extras) => extras.shape_dist_traveled = shape_dist_traveled,
+            None => self.extras = StopTimeExtras::boxed(shape_dist_traveled, None, None),
+        }
+    }
+
+    pub fn continuous_pickup(&self) -> Option<u8> {
+        self.extras.as_ref().and_then(|e| e.continuous_pickup)
+    }
+
+    pub fn continuous_drop_off(&self) -> Option<u8> {
+        self.extras.as_ref().and_then(|e| e.continuous_drop_off)
+    }
 }
 
 impl GetObjectType for StopTime {
@@ -772,6 +1049,53 @@ impl GetObjectType for StopTime {
     }
 }
 
+/// A vehicle journey repeated every `headway_secs` seconds between
+/// `start_time` and `end_time`, its stop times giving the times of
+/// the first run.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Frequency {
+    #[serde(rename = "trip_id")]
+    pub vehicle_journey_id: String,
+    pub start_time: Time,
+    pub end_time: Time,
+    pub headway_secs: u32,
+    #[serde(
+        default,
+        deserialize_with = "de_from_u8_with_empty_default",
+        serialize_with = "ser_from_bool"
+    )]
+    pub exact_times: bool,
+}
+
+impl AddPrefix for Frequency {
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.vehicle_journey_id = prefix_conf.prefix("vehicle_journeys", &self.vehicle_journey_id);
+    }
+}
+
+/// A stay-seated transfer between two vehicle journeys operated in a
+/// row by the same vehicle, the second one starting at the stop point
+/// where the first one ends.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct VehicleJourneyLink {
+    #[serde(rename = "from_trip_id")]
+    pub from_vehicle_journey_id: String,
+    #[serde(rename = "to_trip_id")]
+    pub to_vehicle_journey_id: String,
+    #[serde(rename = "stop_id")]
+    pub stop_point_id: TypedId<StopPoint>,
+}
+
+impl AddPrefix for VehicleJourneyLink {
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.from_vehicle_journey_id =
+            prefix_conf.prefix("vehicle_journeys", &self.from_vehicle_journey_id);
+        self.to_vehicle_journey_id =
+            prefix_conf.prefix("vehicle_journeys", &self.to_vehicle_journey_id);
+        self.stop_point_id = prefix_conf.prefix("stop_points", &self.stop_point_id).into();
+    }
+}
+
 #[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
 pub struct Coord {
     pub lon: f64,
@@ -779,7 +1103,7 @@ pub struct Coord {
 }
 
 // Mean Earth radius in meters
-const EARTH_RADIUS: f64 = 6_371_000.0;
+pub(crate) const EARTH_RADIUS: f64 = 6_371_000.0;
 
 impl Coord {
     /// Calculate the orthodromic distance in meters
@@ -879,11 +1203,10 @@ impl Id<StopArea> for StopArea {
     }
 }
 impl AddPrefix for StopArea {
-    fn add_prefix(&mut self, prefix: &str) {
-        self.id = prefix.to_string() + &self.id;
-        let equipment_id_opt = self.equipment_id.clone();
-        if let Some(equipment_id) = equipment_id_opt {
-            self.equipment_id = Some(prefix.to_string() + &equipment_id);
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.id = prefix_conf.prefix("stop_areas", &self.id);
+        if let Some(ref mut equipment_id) = self.equipment_id {
+            *equipment_id = prefix_conf.prefix("equipments", equipment_id);
         }
     }
 }
@@ -909,11 +1232,12 @@ pub struct StopPoint {
     pub comment_links: CommentLinksT,
     pub visible: bool,
     pub coord: Coord,
-    pub stop_area_id: String,
+    pub stop_area_id: TypedId<StopArea>,
     pub timezone: Option<String>,
     pub geometry_id: Option<String>,
     pub equipment_id: Option<String>,
     pub fare_zone_id: Option<String>,
+    pub address_id: Option<String>,
 }
 
 impl Id<StopPoint> for StopPoint {
@@ -927,12 +1251,14 @@ impl Id<StopArea> for StopPoint {
     }
 }
 impl AddPrefix for StopPoint {
-    fn add_prefix(&mut self, prefix: &str) {
-        self.id = prefix.to_string() + &self.id;
-        self.stop_area_id = prefix.to_string() + &self.stop_area_id;
-        let equipment_id_opt = self.equipment_id.clone();
-        if let Some(equipment_id) = equipment_id_opt {
-            self.equipment_id = Some(prefix.to_string() + &equipment_id);
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.id = prefix_conf.prefix("stop_points", &self.id);
+        self.stop_area_id = prefix_conf.prefix("stop_areas", &self.stop_area_id).into();
+        if let Some(ref mut equipment_id) = self.equipment_id {
+            *equipment_id = prefix_conf.prefix("equipments", equipment_id);
+        }
+        if let Some(ref mut address_id) = self.address_id {
+            *address_id = prefix_conf.prefix("addresses", address_id);
         }
     }
 }
@@ -946,6 +1272,58 @@ impl GetObjectType for StopPoint {
     }
 }
 
+/// The street and house number of stop points, with the
+/// administrative regions containing it.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Address {
+    #[serde(rename = "address_id")]
+    pub id: String,
+    pub street_name: String,
+    pub house_number: Option<String>,
+    pub admin_level_8_id: Option<String>,
+    pub admin_level_9_id: Option<String>,
+    pub admin_level_10_id: Option<String>,
+}
+
+impl Id<Address> for Address {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl AddPrefix for Address {
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.id = prefix_conf.prefix("addresses", &self.id);
+    }
+}
+
+/// An entrance or exit of a stop area.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Entrance {
+    pub id: String,
+    pub name: String,
+    pub coord: Coord,
+    pub stop_area_id: TypedId<StopArea>,
+    pub timezone: Option<String>,
+}
+
+impl Id<Entrance> for Entrance {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+impl Id<StopArea> for Entrance {
+    fn id(&self) -> &str {
+        self.stop_area_id.as_str()
+    }
+}
+impl AddPrefix for Entrance {
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.id = prefix_conf.prefix("entrances", &self.id);
+        self.stop_area_id = prefix_conf.prefix("stop_areas", &self.stop_area_id).into();
+    }
+}
+
 pub type Date = chrono::NaiveDate;
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -975,6 +1353,136 @@ impl Calendar {
             dates: BTreeSet::new(),
         }
     }
+
+    /// Whether the calendar is active on `date`.
+    pub fn is_active_on(&self, date: Date) -> bool {
+        self.dates.contains(&date)
+    }
+
+    /// Whether the calendar is active on at least one day between
+    /// `start` and `end`, both included.
+    pub fn is_active_between(&self, start: Date, end: Date) -> bool {
+        start <= end && self.dates.range(start..=end).next().is_some()
+    }
+}
+
+/// A seasonal calendar of lines, as shown in the timetables, running
+/// on some days of the week during its periods.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct GridCalendar {
+    #[serde(rename = "grid_calendar_id")]
+    pub id: String,
+    pub name: String,
+    #[serde(deserialize_with = "de_from_u8", serialize_with = "ser_from_bool")]
+    pub monday: bool,
+    #[serde(deserialize_with = "de_from_u8", serialize_with = "ser_from_bool")]
+    pub tuesday: bool,
+    #[serde(deserialize_with = "de_from_u8", serialize_with = "ser_from_bool")]
+    pub wednesday: bool,
+    #[serde(deserialize_with = "de_from_u8", serialize_with = "ser_from_bool")]
+    pub thursday: bool,
+    #[serde(deserialize_with = "de_from_u8", serialize_with = "ser_from_bool")]
+    pub friday: bool,
+    #[serde(deserialize_with = "de_from_u8", serialize_with = "ser_from_bool")]
+    pub saturday: bool,
+    #[serde(deserialize_with = "de_from_u8", serialize_with = "ser_from_bool")]
+    pub sunday: bool,
+}
+
+impl Id<GridCalendar> for GridCalendar {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl AddPrefix for GridCalendar {
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.id = prefix_conf.prefix("grid_calendars", &self.id);
+    }
+}
+
+/// A date added to, or removed from, a grid calendar.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct GridExceptionDate {
+    pub grid_calendar_id: String,
+    #[serde(
+        deserialize_with = "de_from_date_string",
+        serialize_with = "ser_from_naive_date"
+    )]
+    pub date: Date,
+    /// Whether the date is added (`1`) or removed (`0`).
+    #[serde(
+        rename = "type",
+        deserialize_with = "de_from_u8",
+        serialize_with = "ser_from_bool"
+    )]
+    pub included: bool,
+}
+
+impl Id<GridCalendar> for GridExceptionDate {
+    fn id(&self) -> &str {
+        &self.grid_calendar_id
+    }
+}
+
+impl AddPrefix for GridExceptionDate {
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.grid_calendar_id = prefix_conf.prefix("grid_calendars", &self.grid_calendar_id);
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct GridPeriod {
+    pub grid_calendar_id: String,
+    #[serde(
+        deserialize_with = "de_from_date_string",
+        serialize_with = "ser_from_naive_date"
+    )]
+    pub start_date: Date,
+    #[serde(
+        deserialize_with = "de_from_date_string",
+        serialize_with = "ser_from_naive_date"
+    )]
+    pub end_date: Date,
+}
+
+impl Id<GridCalendar> for GridPeriod {
+    fn id(&self) -> &str {
+        &self.grid_calendar_id
+    }
+}
+
+impl AddPrefix for GridPeriod {
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.grid_calendar_id = prefix_conf.prefix("grid_calendars", &self.grid_calendar_id);
+    }
+}
+
+/// Links a grid calendar to a line.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct GridRelCalendarLine {
+    pub grid_calendar_id: String,
+    pub line_id: TypedId<Line>,
+    pub line_external_code: Option<String>,
+}
+
+impl Id<GridCalendar> for GridRelCalendarLine {
+    fn id(&self) -> &str {
+        &self.grid_calendar_id
+    }
+}
+
+impl Id<Line> for GridRelCalendarLine {
+    fn id(&self) -> &str {
+        &self.line_id
+    }
+}
+
+impl AddPrefix for GridRelCalendarLine {
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.grid_calendar_id = prefix_conf.prefix("grid_calendars", &self.grid_calendar_id);
+        self.line_id = prefix_conf.prefix("lines", &self.line_id).into();
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -1011,8 +1519,8 @@ impl Default for Company {
     }
 }
 impl AddPrefix for Company {
-    fn add_prefix(&mut self, prefix: &str) {
-        self.id = prefix.to_string() + &self.id;
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.id = prefix_conf.prefix("companies", &self.id);
     }
 }
 
@@ -1047,12 +1555,12 @@ impl Id<Comment> for Comment {
 }
 
 impl AddPrefix for Comment {
-    fn add_prefix(&mut self, prefix: &str) {
-        self.id = prefix.to_string() + &self.id;
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.id = prefix_conf.prefix("comments", &self.id);
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Derivative, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Debug, Derivative, PartialEq, Eq, Hash, Clone)]
 #[derivative(Default)]
 pub enum Availability {
     #[derivative(Default)]
@@ -1064,7 +1572,7 @@ pub enum Availability {
     NotAvailable,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
 pub struct Equipment {
     #[serde(rename = "equipment_id")]
     pub id: String,
@@ -1097,8 +1605,8 @@ impl Id<Equipment> for Equipment {
 }
 
 impl AddPrefix for Equipment {
-    fn add_prefix(&mut self, prefix: &str) {
-        self.id = prefix.to_string() + &self.id;
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.id = prefix_conf.prefix("equipments", &self.id);
     }
 }
 
@@ -1112,9 +1620,9 @@ pub struct Transfer {
 }
 
 impl AddPrefix for Transfer {
-    fn add_prefix(&mut self, prefix: &str) {
-        self.from_stop_id = prefix.to_string() + &self.from_stop_id;
-        self.to_stop_id = prefix.to_string() + &self.to_stop_id;
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.from_stop_id = prefix_conf.prefix("stop_points", &self.from_stop_id);
+        self.to_stop_id = prefix_conf.prefix("stop_points", &self.to_stop_id);
     }
 }
 
@@ -1159,8 +1667,8 @@ impl Id<TripProperty> for TripProperty {
 }
 
 impl AddPrefix for TripProperty {
-    fn add_prefix(&mut self, prefix: &str) {
-        self.id = prefix.to_string() + &self.id;
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.id = prefix_conf.prefix("trip_properties", &self.id);
     }
 }
 
@@ -1185,11 +1693,395 @@ pub struct AdminStation {
     pub stop_id: String,
 }
 
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Ticket {
+    #[serde(rename = "ticket_id")]
+    pub id: String,
+    #[serde(rename = "ticket_name")]
+    pub name: String,
+    #[serde(rename = "ticket_comment")]
+    pub comment: Option<String>,
+}
+
+impl Id<Ticket> for Ticket {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl AddPrefix for Ticket {
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.id = prefix_conf.prefix("tickets", &self.id);
+    }
+}
+
+/// The price of a ticket during its validity period.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct TicketPrice {
+    pub ticket_id: String,
+    #[serde(rename = "ticket_price")]
+    pub price: Amount,
+    #[serde(rename = "ticket_currency")]
+    pub currency: Currency,
+    #[serde(
+        rename = "ticket_validity_start",
+        deserialize_with = "de_from_date_string",
+        serialize_with = "ser_from_naive_date"
+    )]
+    pub validity_start: Date,
+    #[serde(
+        rename = "ticket_validity_end",
+        deserialize_with = "de_from_date_string",
+        serialize_with = "ser_from_naive_date"
+    )]
+    pub validity_end: Date,
+}
+
+impl Id<Ticket> for TicketPrice {
+    fn id(&self) -> &str {
+        &self.ticket_id
+    }
+}
+
+impl AddPrefix for TicketPrice {
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.ticket_id = prefix_conf.prefix("tickets", &self.ticket_id);
+    }
+}
+
+/// A fare zone delimited by the polygons of a geometry, for the
+/// zonal fares, stored in the `fare_zones.txt` file of the NTFS.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct FareZone {
+    #[serde(rename = "fare_zone_id")]
+    pub id: String,
+    pub geometry_id: String,
+}
+
+impl Id<FareZone> for FareZone {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Id<Geometry> for FareZone {
+    fn id(&self) -> &str {
+        self.geometry_id.as_str()
+    }
+}
+
+/// The ticket to use for a trip from a stop area to another.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ODRule {
+    pub ticket_id: String,
+    pub origin_stop_area_id: String,
+    pub destination_stop_area_id: String,
+}
+
+impl Id<Ticket> for ODRule {
+    fn id(&self) -> &str {
+        &self.ticket_id
+    }
+}
+
+impl AddPrefix for ODRule {
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.ticket_id = prefix_conf.prefix("tickets", &self.ticket_id);
+        self.origin_stop_area_id = prefix_conf.prefix("stop_areas", &self.origin_stop_area_id);
+        self.destination_stop_area_id =
+            prefix_conf.prefix("stop_areas", &self.destination_stop_area_id);
+    }
+}
+
+/// A use of a ticket, limiting the number of transfers and the
+/// durations, in seconds, from the first boarding to the last
+/// boarding and alighting.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct TicketUse {
+    #[serde(rename = "ticket_use_id")]
+    pub id: String,
+    pub ticket_id: String,
+    pub max_transfers: Option<u32>,
+    pub boarding_time_limit: Option<u32>,
+    pub alighting_time_limit: Option<u32>,
+}
+
+impl Id<TicketUse> for TicketUse {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Id<Ticket> for TicketUse {
+    fn id(&self) -> &str {
+        self.ticket_id.as_str()
+    }
+}
+
+impl AddPrefix for TicketUse {
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.id = prefix_conf.prefix("ticket_uses", &self.id);
+        self.ticket_id = prefix_conf.prefix("tickets", &self.ticket_id);
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PerimeterAction {
+    #[serde(rename = "1")]
+    Included,
+    #[serde(rename = "2")]
+    Excluded,
+}
+
+/// A network or a line on which a ticket use is valid, or not.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct TicketUsePerimeter {
+    pub ticket_use_id: String,
+    pub object_type: ObjectType,
+    pub object_id: String,
+    pub perimeter_action: PerimeterAction,
+}
+
+impl Id<TicketUse> for TicketUsePerimeter {
+    fn id(&self) -> &str {
+        &self.ticket_use_id
+    }
+}
+
+impl AddPrefix for TicketUsePerimeter {
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.ticket_use_id = prefix_conf.prefix("ticket_uses", &self.ticket_use_id);
+        self.object_id = prefix_conf.prefix_object(&self.object_type, &self.object_id);
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RestrictionType {
+    #[serde(rename = "zone")]
+    Zone,
+    #[serde(rename = "OD")]
+    OriginDestination,
+}
+
+/// Restricts a ticket use to the trips from a fare zone to another,
+/// or from a stop area to another.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct TicketUseRestriction {
+    pub ticket_use_id: String,
+    pub restriction_type: RestrictionType,
+    pub use_origin: String,
+    pub use_destination: String,
+}
+
+impl Id<TicketUse> for TicketUseRestriction {
+    fn id(&self) -> &str {
+        &self.ticket_use_id
+    }
+}
+
+impl AddPrefix for TicketUseRestriction {
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.ticket_use_id = prefix_conf.prefix("ticket_uses", &self.ticket_use_id);
+        // the fare zones are not prefixed
+        if self.restriction_type == RestrictionType::OriginDestination {
+            self.use_origin = prefix_conf.prefix("stop_areas", &self.use_origin);
+            self.use_destination = prefix_conf.prefix("stop_areas", &self.use_destination);
+        }
+    }
+}
+
+/// How a pathway between two locations of a station is traversed.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PathwayMode {
+    #[serde(rename = "1")]
+    Walkway,
+    #[serde(rename = "2")]
+    Stairs,
+    #[serde(rename = "3")]
+    MovingSidewalk,
+    #[serde(rename = "4")]
+    Escalator,
+    #[serde(rename = "5")]
+    Elevator,
+    #[serde(rename = "6")]
+    FareGate,
+    #[serde(rename = "7")]
+    ExitGate,
+}
+
+/// A pathway linking two locations of a station, as a stop point, a
+/// stop area or an entrance.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Pathway {
+    #[serde(rename = "pathway_id")]
+    pub id: String,
+    pub from_stop_id: String,
+    pub to_stop_id: String,
+    pub pathway_mode: PathwayMode,
+    #[serde(deserialize_with = "de_from_u8", serialize_with = "ser_from_bool")]
+    pub is_bidirectional: bool,
+    pub length: Option<f64>,
+    pub traversal_time: Option<u32>,
+    pub stair_count: Option<i32>,
+    pub max_slope: Option<f64>,
+    pub min_width: Option<f64>,
+    pub signposted_as: Option<String>,
+    pub reversed_signposted_as: Option<String>,
+}
+
+impl Id<Pathway> for Pathway {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl AddPrefix for Pathway {
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.id = prefix_conf.prefix("pathways", &self.id);
+        self.from_stop_id = prefix_conf.prefix("stop_points", &self.from_stop_id);
+        self.to_stop_id = prefix_conf.prefix("stop_points", &self.to_stop_id);
+    }
+}
+
+/// A level of a station, the ground level having the index 0.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Level {
+    #[serde(rename = "level_id")]
+    pub id: String,
+    pub level_index: f64,
+    pub level_name: Option<String>,
+}
+
+impl Id<Level> for Level {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl AddPrefix for Level {
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.id = prefix_conf.prefix("levels", &self.id);
+    }
+}
+
+/// The translation in `language` of the `field_name` field of an
+/// object, the field being named as the column of the NTFS, as
+/// `stop_name` or `line_name`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Translation {
+    pub object_type: ObjectType,
+    pub object_id: String,
+    pub field_name: String,
+    pub language: String,
+    pub translation: String,
+}
+
+impl AddPrefix for Translation {
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.object_id = prefix_conf.prefix_object(&self.object_type, &self.object_id);
+    }
+}
+
+/// An identifier generated by a reader, to trace the object back to
+/// the one of the source feed it was generated from, as a stop area
+/// generated for a stop point without one.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct IdMapping {
+    /// The name of the collection of the object with the generated
+    /// identifier, as `stop_areas`.
+    pub collection: String,
+    /// The identifier in the source feed of the object the identifier
+    /// was generated from.
+    pub source_id: String,
+    /// The generated identifier.
+    pub generated_id: String,
+}
+
+impl IdMapping {
+    pub fn new(collection: &str, source_id: &str, generated_id: &str) -> Self {
+        IdMapping {
+            collection: collection.to_string(),
+            source_id: source_id.to_string(),
+            generated_id: generated_id.to_string(),
+        }
+    }
+}
+
+impl AddPrefix for IdMapping {
+    fn add_prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        // the source identifiers are the ones of the source feed
+        self.generated_id = prefix_conf.prefix(&self.collection, &self.generated_id);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use collection::{CollectionWithId, TypedId};
     extern crate serde_json;
 
+    #[test]
+    fn stop_time_size() {
+        // the stop times are most of the memory of a model, their rare
+        // properties being boxed apart
+        assert_eq!(40, ::std::mem::size_of::<StopTime>());
+        assert_eq!(None, StopTimeExtras::boxed(None, None, None));
+        let extras = StopTimeExtras::boxed(Some(1.5), None, None).unwrap();
+        assert_eq!(Some(1.5), extras.shape_dist_traveled);
+    }
+
+    #[test]
+    fn typed_references() {
+        let vj = VehicleJourney {
+            id: "vj".to_string(),
+            route_id: "route".into(),
+            service_id: "week".into(),
+            ..Default::default()
+        };
+        let route_id: TypedId<Route> = TypedId::of(&vj);
+        assert_eq!("route", route_id.as_str());
+        assert_eq!("week", TypedId::<Calendar>::of(&vj).to_string());
+        assert_eq!(TypedId::new("vj"), TypedId::<VehicleJourney>::of(&vj));
+
+        let calendars = CollectionWithId::new(vec![Calendar::new("week".to_string())]).unwrap();
+        assert!(calendars.get_typed(&vj.service_id).is_some());
+        assert!(calendars.get_typed(&TypedId::of(&vj)).is_some());
+        assert_eq!("\"route\"", serde_json::to_string(&vj.route_id).unwrap());
+        let line_id: TypedId<Line> = serde_json::from_str("\"line\"").unwrap();
+        assert_eq!("line", line_id);
+    }
+
+    #[test]
+    fn id_patterns() {
+        assert!(matches_pattern("GDL", "GDL"));
+        assert!(!matches_pattern("GDL", "GDLM"));
+        assert!(matches_pattern("GDL*", "GDLM"));
+        assert!(matches_pattern("*M", "GDLM"));
+        assert!(matches_pattern("G*L*", "GDLM"));
+        assert!(matches_pattern("*", ""));
+        assert!(!matches_pattern("G*M*", "GDL"));
+        assert!(!matches_pattern("GD*DM", "GDM"));
+    }
+
+    #[test]
+    fn prefix_configuration() {
+        let mut prefix_conf = PrefixConfiguration::new("pfx");
+        assert_eq!("pfx:GDLM", prefix_conf.prefix("stop_points", "GDLM"));
+
+        prefix_conf.separator = "_".to_string();
+        prefix_conf
+            .exempt_collections
+            .insert("stop_areas".to_string());
+        prefix_conf.exempt_patterns.push("NAT*".to_string());
+        assert_eq!("pfx_GDLM", prefix_conf.prefix("stop_points", "GDLM"));
+        assert_eq!("GDL", prefix_conf.prefix("stop_areas", "GDL"));
+        assert_eq!(
+            "GDL",
+            prefix_conf.prefix_object(&ObjectType::StopArea, "GDL")
+        );
+        assert_eq!("NATM", prefix_conf.prefix("stop_points", "NATM"));
+    }
+
     #[test]
     fn rgb_serialization() {
         let white = Rgb {
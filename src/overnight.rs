@@ -0,0 +1,193 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! Normalization of the vehicle journeys running after midnight, whose
+//! times are at or after 24:00, for the consumers refusing them.
+
+use chrono::Duration;
+use collection::CollectionWithId;
+use model::Collections;
+use objects::{Calendar, Time, VehicleJourney};
+use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
+use {Error, Result};
+
+const DAY: u32 = 24 * 60 * 60;
+
+/// What `normalize_overnight` does with the vehicle journeys whose
+/// stop times are at or after 24:00.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Derivative)]
+#[derivative(Default)]
+pub enum OvernightPolicy {
+    /// The vehicle journeys are kept as they are.
+    #[derivative(Default)]
+    Keep,
+    /// The vehicle journeys starting at or after 24:00 are moved to
+    /// the following days, their times being shifted back by as many
+    /// days, so that all the vehicle journeys start before 24:00.
+    Shift,
+    /// The vehicle journeys are shifted, then split at each midnight:
+    /// the stop times from the first one arriving at or after 24:00
+    /// form a vehicle journey of the next day, identified as
+    /// `<id>:<number of days>`.  A vehicle journey is not split when
+    /// one of its parts would have less than two stop times.
+    Split,
+}
+
+impl FromStr for OvernightPolicy {
+    type Err = Error;
+
+    /// Parses the `keep`, `shift` and `split` policies.
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "keep" => Ok(OvernightPolicy::Keep),
+            "shift" => Ok(OvernightPolicy::Shift),
+            "split" => Ok(OvernightPolicy::Split),
+            _ => bail!(
+                "unknown overnight policy {:?}, expected keep, shift or split",
+                s
+            ),
+        }
+    }
+}
+
+fn seconds(time: Time) -> u32 {
+    time.hours() * 3600 + time.minutes() * 60 + time.seconds()
+}
+
+fn shift_back(vj: &mut VehicleJourney, days: u32) {
+    let shift = |time: Time| Time::new(0, 0, seconds(time).saturating_sub(days * DAY));
+    for stop_time in &mut vj.stop_times {
+        stop_time.arrival_time = shift(stop_time.arrival_time);
+        stop_time.departure_time = shift(stop_time.departure_time);
+    }
+}
+
+// The identifier of the calendar `service_id` shifted by `days` days,
+// created if needed.
+fn shifted_calendar(
+    calendars: &mut CollectionWithId<Calendar>,
+    service_id: &str,
+    days: u32,
+) -> Result<String> {
+    let id = format!("{}:+{}", service_id, days);
+    if calendars.get(&id).is_none() {
+        let dates: BTreeSet<_> = match calendars.get(service_id) {
+            Some(calendar) => calendar
+                .dates
+                .iter()
+                .map(|date| *date + Duration::days(i64::from(days)))
+                .collect(),
+            None => bail!("calendar {} not found", service_id),
+        };
+        calendars.push(Calendar {
+            id: id.clone(),
+            dates,
+        })?;
+    }
+    Ok(id)
+}
+
+// Splits the vehicle journey, moved from `service_id` by `days` days,
+// at each midnight.
+fn split(
+    mut vj: VehicleJourney,
+    calendars: &mut CollectionWithId<Calendar>,
+    service_id: &str,
+    days: u32,
+) -> Result<Vec<VehicleJourney>> {
+    let mut parts = vec![];
+    let mut part_days = 0;
+    loop {
+        let len = vj.stop_times.len();
+        match vj
+            .stop_times
+            .iter()
+            .position(|st| seconds(st.arrival_time) >= DAY)
+        {
+            Some(pos) if pos >= 2 && len - pos >= 2 => {
+                part_days += 1;
+                let stop_times = vj.stop_times.split_off(pos);
+                let mut next = vj.clone();
+                next.stop_times = stop_times;
+                shift_back(&mut next, 1);
+                next.id = format!("{}:{}", parts.first().unwrap_or(&vj).id, part_days);
+                next.service_id = shifted_calendar(calendars, service_id, days + part_days)?.into();
+                parts.push(vj);
+                vj = next;
+            }
+            _ => break,
+        }
+    }
+    parts.push(vj);
+    Ok(parts)
+}
+
+/// Normalizes the vehicle journeys whose stop times are at or after
+/// 24:00 according to the `policy`, the moved vehicle journeys running
+/// on new calendars, identified as `<calendar id>:+<number of days>`.
+/// The vehicle journeys having frequencies are left untouched.
+/// Returns the number of vehicle journeys moved or split.
+pub fn normalize_overnight(
+    collections: &mut Collections,
+    policy: OvernightPolicy,
+) -> Result<usize> {
+    if policy == OvernightPolicy::Keep {
+        return Ok(0);
+    }
+    let with_frequencies: BTreeSet<_> = collections
+        .frequencies
+        .values()
+        .map(|f| f.vehicle_journey_id.clone())
+        .collect();
+    let mut vehicle_journeys = vec![];
+    let mut last_parts = HashMap::new();
+    let mut normalized = 0;
+    for mut vj in collections.vehicle_journeys.take() {
+        let days = match vj.stop_times.first() {
+            Some(st) if !with_frequencies.contains(&vj.id) => seconds(st.departure_time) / DAY,
+            _ => {
+                vehicle_journeys.push(vj);
+                continue;
+            }
+        };
+        let service_id = vj.service_id.clone();
+        if days > 0 {
+            shift_back(&mut vj, days);
+            vj.service_id = shifted_calendar(&mut collections.calendars, &service_id, days)?.into();
+        }
+        let parts = match policy {
+            OvernightPolicy::Split => split(vj, &mut collections.calendars, &service_id, days)?,
+            _ => vec![vj],
+        };
+        if days > 0 || parts.len() > 1 {
+            normalized += 1;
+        }
+        if parts.len() > 1 {
+            last_parts.insert(parts[0].id.clone(), parts[parts.len() - 1].id.clone());
+        }
+        vehicle_journeys.extend(parts);
+    }
+    collections.vehicle_journeys = CollectionWithId::new(vehicle_journeys)?;
+    // The vehicle journeys following a split one follow its last part.
+    for link in collections.vehicle_journey_links.values_mut() {
+        if let Some(id) = last_parts.get(&link.from_vehicle_journey_id) {
+            link.from_vehicle_journey_id = id.clone();
+        }
+    }
+    info!("{} vehicle journeys normalized after midnight", normalized);
+    Ok(normalized)
+}
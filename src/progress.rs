@@ -0,0 +1,246 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! Progress reporting of the reading and writing of the files.
+//!
+//! As for the logs, a single handler is set for the whole program,
+//! receiving the events of all the readers and writers.  An
+//! [indicatif](https://docs.rs/indicatif) progress bar can be driven
+//! by a handler as:
+//!
+//! ```ignore
+//! let bar = indicatif::ProgressBar::new(0);
+//! navitia_model::progress::set_handler(move |event: &Event| match event.stage {
+//!     Stage::Started => {
+//!         bar.reset();
+//!         bar.set_length(event.size.unwrap_or(0));
+//!         bar.set_message(&event.path.display().to_string());
+//!     }
+//!     Stage::InProgress => bar.set_position(event.bytes),
+//!     Stage::Finished => bar.finish(),
+//! });
+//! ```
+
+use csv;
+use failure::ResultExt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use Result;
+
+// Number of bytes between two `InProgress` events of a file.
+const REPORT_INTERVAL: u64 = 1 << 20;
+
+type Handler = Box<dyn Fn(&Event) + Send + Sync>;
+
+static HANDLER: RwLock<Option<Handler>> = RwLock::new(None);
+
+/// Whether a file is read or written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// The file is read.
+    Read,
+    /// The file is written.
+    Write,
+}
+
+/// The stage of the processing of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// The file is opened.
+    Started,
+    /// Some bytes have been processed since the last event.
+    InProgress,
+    /// The file is closed.
+    Finished,
+}
+
+/// An event of the processing of a file.
+#[derive(Debug, Clone, Copy)]
+pub struct Event<'a> {
+    /// The path of the file.
+    pub path: &'a Path,
+    /// Whether the file is read or written.
+    pub operation: Operation,
+    /// The stage of the processing.
+    pub stage: Stage,
+    /// The size of the file read if known, `None` when writing.
+    pub size: Option<u64>,
+    /// The number of bytes processed so far.
+    pub bytes: u64,
+    /// The number of lines processed so far, header included.
+    pub lines: u64,
+}
+
+/// Sets the handler receiving the events of all the readers and
+/// writers, replacing the previous one.
+pub fn set_handler<F: Fn(&Event) + Send + Sync + 'static>(handler: F) {
+    *HANDLER.write().unwrap() = Some(Box::new(handler));
+}
+
+/// Removes the handler, the events being ignored.
+pub fn clear_handler() {
+    *HANDLER.write().unwrap() = None;
+}
+
+fn notify(event: &Event) {
+    if let Some(ref handler) = *HANDLER.read().unwrap() {
+        handler(event);
+    }
+}
+
+struct Counter {
+    path: PathBuf,
+    operation: Operation,
+    size: Option<u64>,
+    bytes: u64,
+    lines: u64,
+    reported: u64,
+}
+
+impl Counter {
+    fn new(path: &Path, operation: Operation, size: Option<u64>) -> Self {
+        let counter = Counter {
+            path: path.to_path_buf(),
+            operation,
+            size,
+            bytes: 0,
+            lines: 0,
+            reported: 0,
+        };
+        counter.notify(Stage::Started);
+        counter
+    }
+
+    fn notify(&self, stage: Stage) {
+        notify(&Event {
+            path: &self.path,
+            operation: self.operation,
+            stage,
+            size: self.size,
+            bytes: self.bytes,
+            lines: self.lines,
+        });
+    }
+
+    fn count(&mut self, buf: &[u8]) {
+        self.bytes += buf.len() as u64;
+        self.lines += buf.iter().filter(|&&b| b == b'\n').count() as u64;
+        if self.bytes - self.reported >= REPORT_INTERVAL {
+            self.reported = self.bytes;
+            self.notify(Stage::InProgress);
+        }
+    }
+}
+
+impl Drop for Counter {
+    fn drop(&mut self) {
+        self.notify(Stage::Finished);
+    }
+}
+
+/// A reader reporting its progress, the `Finished` event being sent
+/// when it is dropped.
+pub struct ProgressReader<R> {
+    inner: R,
+    counter: Counter,
+}
+
+impl<R: Read> ProgressReader<R> {
+    /// Wraps the reader of the file at `path`, of `size` bytes if
+    /// known.
+    pub fn new<P: AsRef<Path>>(inner: R, path: P, size: Option<u64>) -> Self {
+        ProgressReader {
+            inner,
+            counter: Counter::new(path.as_ref(), Operation::Read, size),
+        }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.counter.count(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// A writer reporting its progress, the `Finished` event being sent
+/// when it is dropped.
+pub struct ProgressWriter<W> {
+    inner: W,
+    counter: Counter,
+}
+
+impl<W: Write> ProgressWriter<W> {
+    /// Wraps the writer of the file at `path`.
+    pub fn new<P: AsRef<Path>>(inner: W, path: P) -> Self {
+        ProgressWriter {
+            inner,
+            counter: Counter::new(path.as_ref(), Operation::Write, None),
+        }
+    }
+}
+
+impl<W: Write> Write for ProgressWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.counter.count(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+pub(crate) fn csv_writer(path: &Path) -> Result<csv::Writer<ProgressWriter<File>>> {
+    let file = File::create(path).with_context(ctx_from_path!(path))?;
+    Ok(csv::Writer::from_writer(ProgressWriter::new(file, path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn report_reading() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let handler_events = Arc::clone(&events);
+        set_handler(move |event: &Event| {
+            if event.path == Path::new("memory/stops.txt") {
+                handler_events
+                    .lock()
+                    .unwrap()
+                    .push((event.stage, event.bytes, event.lines));
+            }
+        });
+        let content = vec![b'\n'; (REPORT_INTERVAL + 10) as usize];
+        let mut reader = ProgressReader::new(content.as_slice(), "memory/stops.txt", None);
+        let mut buf = vec![];
+        reader.read_to_end(&mut buf).unwrap();
+        drop(reader);
+        clear_handler();
+
+        let events = events.lock().unwrap();
+        assert_eq!(Some(&(Stage::Started, 0, 0)), events.first());
+        assert!(events.iter().any(|e| e.0 == Stage::InProgress));
+        let total = REPORT_INTERVAL + 10;
+        assert_eq!(Some(&(Stage::Finished, total, total)), events.last());
+    }
+}
@@ -14,13 +14,38 @@
 // along with this program.  If not, see
 // <http://www.gnu.org/licenses/>.
 
+//! Helpers shared by the readers, and the handlers giving access to
+//! the files of a directory or an archive.
+
+pub use checksum::Checksum;
 use collection::CollectionWithId;
+use csv;
+use failure::ResultExt;
+use flate2::read::GzDecoder;
+#[cfg(feature = "async")]
+use futures::future::{self, Loop};
+#[cfg(feature = "async")]
+use futures::{Future, Stream};
+use hex;
 use model::Collections;
-use objects::{self, Contributor};
-use std::collections::BTreeSet;
-use std::fs::File;
-use std::path;
-use utils::{add_prefix_to_collection, add_prefix_to_collection_with_id};
+use objects::{self, Contributor, PrefixConfiguration};
+use progress::ProgressReader;
+use reqwest::{self, header, StatusCode};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::{Cursor, Read, Seek};
+use std::path::{self, Path, PathBuf};
+use std::thread;
+#[cfg(feature = "async")]
+use std::time::Instant;
+use std::time::Duration;
+use tar;
+#[cfg(feature = "async")]
+use tokio::timer::Delay;
+use zip;
+#[cfg(feature = "async")]
+use Error;
 use Result;
 extern crate serde_json;
 
@@ -35,7 +60,7 @@ struct Config {
     dataset: ConfigDataset,
 }
 
-pub fn read_config<P: AsRef<path::Path>>(
+pub(crate) fn read_config<P: AsRef<path::Path>>(
     config_path: Option<P>,
 ) -> Result<(objects::Contributor, objects::Dataset)> {
     let contributor;
@@ -54,28 +79,45 @@ pub fn read_config<P: AsRef<path::Path>>(
     Ok((contributor, dataset))
 }
 
-pub fn add_prefix(prefix: String, collections: &mut Collections) -> Result<()> {
-    let prefix = prefix + ":";
-    info!("Adding prefix: \"{}\"", &prefix);
-    add_prefix_to_collection_with_id(&mut collections.commercial_modes, &prefix)?;
-    add_prefix_to_collection_with_id(&mut collections.networks, &prefix)?;
-    add_prefix_to_collection_with_id(&mut collections.companies, &prefix)?;
-    add_prefix_to_collection_with_id(&mut collections.stop_points, &prefix)?;
-    add_prefix_to_collection_with_id(&mut collections.stop_areas, &prefix)?;
-    add_prefix_to_collection(&mut collections.transfers, &prefix);
-    add_prefix_to_collection_with_id(&mut collections.routes, &prefix)?;
-    add_prefix_to_collection_with_id(&mut collections.lines, &prefix)?;
-    add_prefix_to_collection_with_id(&mut collections.contributors, &prefix)?;
-    add_prefix_to_collection_with_id(&mut collections.datasets, &prefix)?;
-    add_prefix_to_collection_with_id(&mut collections.vehicle_journeys, &prefix)?;
-    add_prefix_to_collection_with_id(&mut collections.trip_properties, &prefix)?;
-    add_prefix_to_collection_with_id(&mut collections.equipments, &prefix)?;
-    add_prefix_to_collection_with_id(&mut collections.comments, &prefix)?;
-
-    Ok(())
-}
-
-pub fn get_validity_period(
+pub(crate) fn add_prefix(prefix: String, collections: &mut Collections) -> Result<()> {
+    collections.add_prefix(&PrefixConfiguration::new(prefix))
+}
+
+/// Records the identifiers of the stop areas generated for the stop
+/// points without one, named `Navitia:<stop point id>`, and of the
+/// equipments generated from the accessibility of the stops.
+pub(crate) fn add_id_mappings(collections: &mut Collections) {
+    let mut id_mappings = vec![];
+    for stop_point in collections.stop_points.values() {
+        if stop_point.stop_area_id == format!("Navitia:{}", stop_point.id) {
+            id_mappings.push(objects::IdMapping::new(
+                "stop_areas",
+                &stop_point.id,
+                &stop_point.stop_area_id,
+            ));
+        }
+    }
+    let stops = collections
+        .stop_points
+        .values()
+        .map(|sp| (&sp.id, &sp.equipment_id))
+        .chain(
+            collections
+                .stop_areas
+                .values()
+                .map(|sa| (&sa.id, &sa.equipment_id)),
+        );
+    for (stop_id, equipment_id) in stops {
+        if let Some(ref equipment_id) = *equipment_id {
+            id_mappings.push(objects::IdMapping::new("equipments", stop_id, equipment_id));
+        }
+    }
+    for id_mapping in id_mappings {
+        collections.id_mappings.push(id_mapping);
+    }
+}
+
+pub(crate) fn get_validity_period(
     calendars: &CollectionWithId<objects::Calendar>,
 ) -> Option<objects::ValidityPeriod> {
     let dates = calendars.values().fold(BTreeSet::new(), |acc, c| {
@@ -91,3 +133,1681 @@ pub fn get_validity_period(
         end_date: *dates.iter().next_back().unwrap(),
     })
 }
+
+/// Gives access to the files of a dataset by their name, whatever
+/// the dataset is stored in.
+///
+/// The trait is implemented on a mutable reference of the handler so
+/// that the readers can borrow it, as the files of a zip archive.
+pub trait FileHandler
+where
+    Self: ::std::marker::Sized,
+{
+    /// The reader of a file.
+    type Reader: Read;
+
+    /// Returns the reader of the file, `None` if the file does not
+    /// exist, and the path of the file for the error messages.
+    fn get_file_if_exists(self, name: &str) -> Result<(Option<Self::Reader>, PathBuf)>;
+
+    /// Returns the reader of the file and its path, fails if the file
+    /// does not exist.
+    fn get_file(self, name: &str) -> Result<(Self::Reader, PathBuf)> {
+        let (reader, path) = self.get_file_if_exists(name)?;
+        match reader {
+            Some(reader) => Ok((reader, path)),
+            None => bail!("file {:?} not found", path),
+        }
+    }
+
+    /// Like `get_file_if_exists`, with the size of the file if known,
+    /// reported by the progress events.
+    fn get_file_with_size(
+        self,
+        name: &str,
+    ) -> Result<(Option<Self::Reader>, PathBuf, Option<u64>)> {
+        let (reader, path) = self.get_file_if_exists(name)?;
+        Ok((reader, path, None))
+    }
+
+    /// Returns the name of the source, as the directory or the
+    /// archive, for the logs.
+    fn source_name(&self) -> &str;
+
+    /// Returns the sorted names of the files, for the formats whose
+    /// files are not known by their names, as the xml files of Netex.
+    /// The names of the files of an archive are lowercased, as they
+    /// are looked up.  Fails if the files can't be listed.
+    fn file_names(self) -> Result<Vec<String>> {
+        bail!("The files of {} can't be listed", self.source_name())
+    }
+}
+
+/// Gives access to the files of a directory.
+///
+/// A file missing from the directory is read from its gzipped version
+/// if any, as `stops.txt.gz` for `stops.txt`.
+pub struct PathFileHandler {
+    base_path: PathBuf,
+    source_name: String,
+}
+
+impl PathFileHandler {
+    /// Constructs a handler on the files of the directory.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        let base_path = path.as_ref().to_path_buf();
+        let source_name = base_path.display().to_string();
+        PathFileHandler {
+            base_path,
+            source_name,
+        }
+    }
+}
+
+impl FileHandler for &mut PathFileHandler {
+    type Reader = Box<dyn Read>;
+
+    fn get_file_if_exists(self, name: &str) -> Result<(Option<Self::Reader>, PathBuf)> {
+        let (reader, path, _) = self.get_file_with_size(name)?;
+        Ok((reader, path))
+    }
+
+    fn get_file_with_size(
+        self,
+        name: &str,
+    ) -> Result<(Option<Self::Reader>, PathBuf, Option<u64>)> {
+        let path = self.base_path.join(name);
+        if path.exists() {
+            let file = File::open(&path).with_context(ctx_from_path!(path))?;
+            let size = file.metadata().ok().map(|m| m.len());
+            return Ok((Some(Box::new(file)), path, size));
+        }
+        let gz_path = self.base_path.join(format!("{}.gz", name));
+        if gz_path.exists() {
+            let file = File::open(&gz_path).with_context(ctx_from_path!(gz_path))?;
+            // the size of the decompressed file is unknown
+            return Ok((Some(Box::new(GzDecoder::new(file))), gz_path, None));
+        }
+        Ok((None, path, None))
+    }
+
+    fn source_name(&self) -> &str {
+        &self.source_name
+    }
+
+    fn file_names(self) -> Result<Vec<String>> {
+        let base_path = &self.base_path;
+        let mut names = vec![];
+        for entry in fs::read_dir(base_path).with_context(ctx_from_path!(base_path))? {
+            let path = entry.with_context(ctx_from_path!(base_path))?.path();
+            if path.is_file() {
+                names.extend(path.file_name().map(|n| n.to_string_lossy().into_owned()));
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+}
+
+/// Gives access to the files of a zip archive.
+///
+/// The files are looked up case-insensitively, at the root of the
+/// archive or inside its single top-level directory, as in the
+/// archives containing `feed/stops.txt`.  Unlike in a directory, a
+/// missing file is not read from its gzipped version.
+pub struct ZipHandler<R: Read + Seek> {
+    archive: zip::ZipArchive<R>,
+    index: BTreeMap<String, usize>,
+    archive_path: PathBuf,
+    source_name: String,
+}
+
+impl<R: Read + Seek> ZipHandler<R> {
+    /// Constructs a handler on the zip archive read from `r`,
+    /// `archive_path` being used in the error messages.
+    pub fn new<P: AsRef<Path>>(r: R, archive_path: P) -> Result<Self> {
+        let archive_path = archive_path.as_ref().to_path_buf();
+        let mut archive = zip::ZipArchive::new(r).with_context(ctx_from_path!(archive_path))?;
+        let index = zip_index(&mut archive).with_context(ctx_from_path!(archive_path))?;
+        let source_name = archive_path.display().to_string();
+        Ok(ZipHandler {
+            archive,
+            index,
+            archive_path,
+            source_name,
+        })
+    }
+}
+
+// Indexes the files of the zip archive by their position.
+fn zip_index<R: Read + Seek>(archive: &mut zip::ZipArchive<R>) -> Result<BTreeMap<String, usize>> {
+    let mut files = vec![];
+    for i in 0..archive.len() {
+        files.push((archive.by_index(i)?.name().to_string(), i));
+    }
+    archive_index(files)
+}
+
+// Indexes the files of an archive by their lowercased name, relative
+// to the single top-level directory containing all the files if any.
+// The metadata added by macOS in `__MACOSX` is ignored, and the files
+// whose names only differ by their case are ambiguous.
+fn archive_index<T>(files: Vec<(String, T)>) -> Result<BTreeMap<String, T>> {
+    let files: Vec<_> = files
+        .into_iter()
+        .filter(|(name, _)| !name.ends_with('/') && !name.starts_with("__MACOSX/"))
+        .collect();
+    let top_dirs: BTreeSet<_> = files
+        .iter()
+        .map(|(name, _)| name.split('/').next().unwrap_or("").to_string())
+        .collect();
+    let all_in_dir = files.iter().all(|(name, _)| name.contains('/'));
+    let prefix = match top_dirs.iter().next() {
+        Some(dir) if top_dirs.len() == 1 && all_in_dir => format!("{}/", dir),
+        _ => String::new(),
+    };
+    let mut index = BTreeMap::new();
+    for (name, file) in files {
+        let relative = &name[prefix.len()..];
+        if relative.contains('/') {
+            continue;
+        }
+        if index.insert(relative.to_lowercase(), file).is_some() {
+            bail!("several files of the archive are named {}", relative.to_lowercase());
+        }
+    }
+    Ok(index)
+}
+
+impl<'a, R: Read + Seek> FileHandler for &'a mut ZipHandler<R> {
+    type Reader = zip::read::ZipFile<'a>;
+
+    fn get_file_if_exists(self, name: &str) -> Result<(Option<Self::Reader>, PathBuf)> {
+        let path = self.archive_path.join(name);
+        match self.index.get(&name.to_lowercase()) {
+            Some(&i) => {
+                let file = self.archive.by_index(i).with_context(ctx_from_path!(path))?;
+                Ok((Some(file), path))
+            }
+            None => Ok((None, path)),
+        }
+    }
+
+    fn get_file_with_size(
+        self,
+        name: &str,
+    ) -> Result<(Option<Self::Reader>, PathBuf, Option<u64>)> {
+        let (file, path) = self.get_file_if_exists(name)?;
+        let size = file.as_ref().map(|f| f.size());
+        Ok((file, path, size))
+    }
+
+    fn source_name(&self) -> &str {
+        &self.source_name
+    }
+
+    fn file_names(self) -> Result<Vec<String>> {
+        Ok(self.index.keys().cloned().collect())
+    }
+}
+
+/// Gives access to the files of a gzipped tar archive.
+///
+/// As a tar archive can only be read sequentially, the files are
+/// decompressed in memory when the handler is constructed.  The files
+/// are looked up as in a [`ZipHandler`](struct.ZipHandler.html),
+/// case-insensitively, at the root of the archive or inside its single
+/// top-level directory, a missing file not being read from its gzipped
+/// version.
+pub struct TarGzHandler {
+    index: BTreeMap<String, Vec<u8>>,
+    archive_path: PathBuf,
+    source_name: String,
+}
+
+impl TarGzHandler {
+    /// Constructs a handler on the tar.gz archive read from `r`,
+    /// `archive_path` being used in the error messages.
+    pub fn new<R: Read, P: AsRef<Path>>(r: R, archive_path: P) -> Result<Self> {
+        let archive_path = archive_path.as_ref().to_path_buf();
+        let mut archive = tar::Archive::new(GzDecoder::new(r));
+        let mut files = vec![];
+        for entry in archive
+            .entries()
+            .with_context(ctx_from_path!(archive_path))?
+        {
+            let mut entry = entry.with_context(ctx_from_path!(archive_path))?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let name = entry
+                .path()
+                .with_context(ctx_from_path!(archive_path))?
+                .to_string_lossy()
+                .trim_start_matches("./")
+                .to_string();
+            let mut content = vec![];
+            entry
+                .read_to_end(&mut content)
+                .with_context(ctx_from_path!(archive_path))?;
+            files.push((name, content));
+        }
+        let index = archive_index(files).with_context(ctx_from_path!(archive_path))?;
+        let source_name = archive_path.display().to_string();
+        Ok(TarGzHandler {
+            index,
+            archive_path,
+            source_name,
+        })
+    }
+}
+
+impl<'a> FileHandler for &'a mut TarGzHandler {
+    type Reader = &'a [u8];
+
+    fn get_file_if_exists(self, name: &str) -> Result<(Option<Self::Reader>, PathBuf)> {
+        let path = self.archive_path.join(name);
+        let reader = self
+            .index
+            .get(&name.to_lowercase())
+            .map(|content| content.as_slice());
+        Ok((reader, path))
+    }
+
+    fn get_file_with_size(
+        self,
+        name: &str,
+    ) -> Result<(Option<Self::Reader>, PathBuf, Option<u64>)> {
+        let (reader, path) = self.get_file_if_exists(name)?;
+        let size = reader.map(|r| r.len() as u64);
+        Ok((reader, path, size))
+    }
+
+    fn source_name(&self) -> &str {
+        &self.source_name
+    }
+    fn file_names(self) -> Result<Vec<String>> {
+        Ok(self.index.keys().cloned().collect())
+    }
+}
+
+/// Gives access to files held in memory, as a dataset received over
+/// the network or built by a test.
+pub struct MemoryFileHandler {
+    files: BTreeMap<String, Vec<u8>>,
+    source_name: String,
+}
+
+impl MemoryFileHandler {
+    /// Constructs a handler without any file, `source_name` being
+    /// used in the logs and as the directory of the files in the
+    /// error messages.
+    pub fn new<S: Into<String>>(source_name: S) -> Self {
+        MemoryFileHandler {
+            files: BTreeMap::new(),
+            source_name: source_name.into(),
+        }
+    }
+
+    /// Adds a file, replacing the file of the same name if any.
+    pub fn insert<N: Into<String>, C: Into<Vec<u8>>>(&mut self, name: N, content: C) {
+        self.files.insert(name.into(), content.into());
+    }
+}
+
+impl<'a> FileHandler for &'a mut MemoryFileHandler {
+    type Reader = &'a [u8];
+
+    fn get_file_if_exists(self, name: &str) -> Result<(Option<Self::Reader>, PathBuf)> {
+        let path = Path::new(&self.source_name).join(name);
+        let reader = self.files.get(name).map(|content| content.as_slice());
+        Ok((reader, path))
+    }
+
+    fn get_file_with_size(
+        self,
+        name: &str,
+    ) -> Result<(Option<Self::Reader>, PathBuf, Option<u64>)> {
+        let (reader, path) = self.get_file_if_exists(name)?;
+        let size = reader.map(|r| r.len() as u64);
+        Ok((reader, path, size))
+    }
+
+    fn source_name(&self) -> &str {
+        &self.source_name
+    }
+    fn file_names(self) -> Result<Vec<String>> {
+        Ok(self.files.keys().cloned().collect())
+    }
+}
+
+/// Gives access to the files of a dataset stored in a directory, its
+/// files being possibly gzipped, a zip or a tar.gz archive, downloaded
+/// as a zip archive, or held in memory.  Only the files of a directory
+/// can be gzipped, not the ones of an archive.
+pub enum InputHandler {
+    /// The files of a directory.
+    Directory(PathFileHandler),
+    /// The files of a zip archive.
+    Zip(ZipHandler<File>),
+    /// The files of a downloaded zip archive.
+    DownloadedZip(ZipHandler<Cursor<Vec<u8>>>),
+    /// The files of a tar.gz archive.
+    TarGz(TarGzHandler),
+    /// The files held in memory.
+    Memory(MemoryFileHandler),
+}
+
+impl InputHandler {
+    /// Constructs the handler of the files of a directory, or of an
+    /// archive with a `.zip`, `.tar.gz` or `.tgz` extension.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if path.is_dir() {
+            return Ok(InputHandler::Directory(PathFileHandler::new(path)));
+        }
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let file = File::open(path).with_context(ctx_from_path!(path))?;
+        if name.ends_with(".zip") {
+            Ok(InputHandler::Zip(ZipHandler::new(file, path)?))
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Ok(InputHandler::TarGz(TarGzHandler::new(file, path)?))
+        } else {
+            bail!(
+                "{:?} is neither a directory nor a zip or tar.gz archive",
+                path
+            )
+        }
+    }
+
+    /// Constructs the handler of the files of the zip archive
+    /// downloaded from the url.
+    pub fn from_url(url: &str) -> Result<Self> {
+        Self::from_url_with_options(url, &HttpOptions::default())
+    }
+
+    /// Constructs the handler of the files of the zip archive
+    /// downloaded from the url with the given options, as the
+    /// authentication or the expected checksum.
+    pub fn from_url_with_options(url: &str, options: &HttpOptions) -> Result<Self> {
+        let archive = read_url_with_options(url, options)?;
+        Ok(InputHandler::DownloadedZip(ZipHandler::new(archive, url)?))
+    }
+}
+
+impl<'a> FileHandler for &'a mut InputHandler {
+    type Reader = Box<dyn Read + 'a>;
+
+    fn get_file_if_exists(self, name: &str) -> Result<(Option<Self::Reader>, PathBuf)> {
+        fn boxed<'a, R: Read + 'a>(
+            (reader, path): (Option<R>, PathBuf),
+        ) -> (Option<Box<dyn Read + 'a>>, PathBuf) {
+            (reader.map(|r| Box::new(r) as Box<dyn Read>), path)
+        }
+        Ok(match *self {
+            InputHandler::Directory(ref mut h) => boxed(h.get_file_if_exists(name)?),
+            InputHandler::Zip(ref mut h) => boxed(h.get_file_if_exists(name)?),
+            InputHandler::DownloadedZip(ref mut h) => boxed(h.get_file_if_exists(name)?),
+            InputHandler::TarGz(ref mut h) => boxed(h.get_file_if_exists(name)?),
+            InputHandler::Memory(ref mut h) => boxed(h.get_file_if_exists(name)?),
+        })
+    }
+
+    fn get_file_with_size(
+        self,
+        name: &str,
+    ) -> Result<(Option<Self::Reader>, PathBuf, Option<u64>)> {
+        fn boxed<'a, R: Read + 'a>(
+            (reader, path, size): (Option<R>, PathBuf, Option<u64>),
+        ) -> (Option<Box<dyn Read + 'a>>, PathBuf, Option<u64>) {
+            (reader.map(|r| Box::new(r) as Box<dyn Read>), path, size)
+        }
+        Ok(match *self {
+            InputHandler::Directory(ref mut h) => boxed(h.get_file_with_size(name)?),
+            InputHandler::Zip(ref mut h) => boxed(h.get_file_with_size(name)?),
+            InputHandler::DownloadedZip(ref mut h) => boxed(h.get_file_with_size(name)?),
+            InputHandler::TarGz(ref mut h) => boxed(h.get_file_with_size(name)?),
+            InputHandler::Memory(ref mut h) => boxed(h.get_file_with_size(name)?),
+        })
+    }
+
+    fn source_name(&self) -> &str {
+        match **self {
+            InputHandler::Directory(ref h) => &h.source_name,
+            InputHandler::Zip(ref h) => &h.source_name,
+            InputHandler::DownloadedZip(ref h) => &h.source_name,
+            InputHandler::TarGz(ref h) => &h.source_name,
+            InputHandler::Memory(ref h) => &h.source_name,
+        }
+    }
+
+    fn file_names(self) -> Result<Vec<String>> {
+        match *self {
+            InputHandler::Directory(ref mut h) => h.file_names(),
+            InputHandler::Zip(ref mut h) => h.file_names(),
+            InputHandler::DownloadedZip(ref mut h) => h.file_names(),
+            InputHandler::TarGz(ref mut h) => h.file_names(),
+            InputHandler::Memory(ref mut h) => h.file_names(),
+        }
+    }
+}
+
+// The csv reader of a file, reporting its progress.
+pub(crate) type CsvReader<R> = csv::Reader<ProgressReader<R>>;
+
+// Returns a csv reader of the file, reporting its progress, and the
+// path of the file.
+pub(crate) fn csv_reader<H: FileHandler>(
+    file_handler: H,
+    name: &str,
+) -> Result<(CsvReader<H::Reader>, PathBuf)> {
+    let (reader, path, size) = file_handler.get_file_with_size(name)?;
+    let reader = match reader {
+        Some(reader) => reader,
+        None => bail!("file {:?} not found", path),
+    };
+    let reader = csv::Reader::from_reader(ProgressReader::new(reader, &path, size));
+    Ok((reader, path))
+}
+
+// Like `csv_reader`, `None` being returned if the file does not exist.
+pub(crate) fn opt_csv_reader<H: FileHandler>(
+    file_handler: H,
+    name: &str,
+) -> Result<(Option<CsvReader<H::Reader>>, PathBuf)> {
+    let (reader, path, size) = file_handler.get_file_with_size(name)?;
+    let reader = reader.map(|r| csv::Reader::from_reader(ProgressReader::new(r, &path, size)));
+    Ok((reader, path))
+}
+
+/// The authentication of the HTTP requests.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HttpAuth {
+    /// Basic authentication, with an optional password.
+    Basic {
+        /// The user name.
+        username: String,
+        /// The password.
+        password: Option<String>,
+    },
+    /// Authentication with a bearer token.
+    Bearer(String),
+}
+
+/// The options of the HTTP downloads.
+#[derive(Debug, Clone)]
+pub struct HttpOptions {
+    /// The authentication sent with the requests.
+    pub auth: Option<HttpAuth>,
+    /// The timeout of each attempt, `None` for no timeout.
+    pub timeout: Option<Duration>,
+    /// The number of attempts after the first failed one.  Only the
+    /// connection errors, the timeouts and the server errors are
+    /// retried.
+    pub retries: u32,
+    /// The delay before the first retry, doubled after each attempt.
+    pub retry_delay: Duration,
+    /// The url of the proxy used for all the requests, as
+    /// `http://proxy.example.com:3128`.
+    pub proxy: Option<String>,
+    /// The expected checksum of the downloaded content, the download
+    /// failing if it does not match.
+    pub checksum: Option<Checksum>,
+    /// The directory caching the downloaded files, `None` to disable
+    /// the cache.  A cached file is given again when the server
+    /// answers that it is not modified, according to its `ETag` or
+    /// `Last-Modified` header.  A file is only cached once its
+    /// checksum is verified.
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl Default for HttpOptions {
+    fn default() -> Self {
+        HttpOptions {
+            auth: None,
+            timeout: Some(Duration::from_secs(30)),
+            retries: 0,
+            retry_delay: Duration::from_secs(1),
+            proxy: None,
+            checksum: None,
+            cache_dir: None,
+        }
+    }
+}
+
+impl HttpOptions {
+    fn client(&self) -> Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder().timeout(self.timeout);
+        if let Some(ref proxy) = self.proxy {
+            builder = builder.proxy(
+                reqwest::Proxy::all(proxy.as_str())
+                    .with_context(|_| format!("Error configuring the proxy {}", proxy))?,
+            );
+        }
+        Ok(builder.build()?)
+    }
+}
+
+// The validators of a cached file, stored along with it.
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct CacheEntry {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+// A downloaded file, `None` if not modified since it was cached.
+struct Downloaded {
+    content: Option<Vec<u8>>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+// The files caching the downloads of a url, named after the hash of
+// the url.
+struct Cache {
+    url: String,
+    data_path: PathBuf,
+    entry_path: PathBuf,
+}
+
+impl Cache {
+    fn new(cache_dir: &Path, url: &str) -> Self {
+        let name = hex::encode(Sha256::digest(url.as_bytes()));
+        Cache {
+            url: url.to_string(),
+            data_path: cache_dir.join(format!("{}.data", name)),
+            entry_path: cache_dir.join(format!("{}.json", name)),
+        }
+    }
+
+    fn entry(&self) -> Option<CacheEntry> {
+        if !self.data_path.exists() {
+            return None;
+        }
+        let file = File::open(&self.entry_path).ok()?;
+        serde_json::from_reader(file)
+            .map_err(|e| warn!("Ignoring the cache entry {:?}: {}", self.entry_path, e))
+            .ok()
+    }
+
+    fn load(&self) -> Result<Vec<u8>> {
+        info!("{} not modified, reading {:?}", self.url, self.data_path);
+        Ok(fs::read(&self.data_path).with_context(ctx_from_path!(self.data_path))?)
+    }
+
+    fn store(
+        &self,
+        content: &[u8],
+        etag: Option<String>,
+        last_modified: Option<String>,
+    ) -> Result<()> {
+        if etag.is_none() && last_modified.is_none() {
+            return Ok(());
+        }
+        if let Some(dir) = self.data_path.parent() {
+            fs::create_dir_all(dir).with_context(ctx_from_path!(dir))?;
+        }
+        fs::write(&self.data_path, content).with_context(ctx_from_path!(self.data_path))?;
+        let entry = CacheEntry {
+            url: self.url.clone(),
+            etag,
+            last_modified,
+        };
+        let file = File::create(&self.entry_path).with_context(ctx_from_path!(self.entry_path))?;
+        serde_json::to_writer(file, &entry)?;
+        Ok(())
+    }
+}
+
+// The content of a download, read from the cache if not modified,
+// verified against the expected checksum, and cached if downloaded.
+fn downloaded_content(
+    downloaded: Downloaded,
+    url: &str,
+    options: &HttpOptions,
+    cache: Option<&Cache>,
+) -> Result<Vec<u8>> {
+    let (content, is_new) = match downloaded.content {
+        Some(content) => (content, true),
+        // The download is only conditional with a cache entry.
+        None => (cache.expect("not modified without cache").load()?, false),
+    };
+    if let Some(ref checksum) = options.checksum {
+        checksum.verify(&content, url)?;
+    }
+    if let (true, Some(cache)) = (is_new, cache) {
+        cache.store(&content, downloaded.etag, downloaded.last_modified)?;
+    }
+    Ok(content)
+}
+
+// The `ETag` or `Last-Modified` header of a response.
+fn header_value(headers: &header::HeaderMap, name: header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string())
+}
+
+fn download(
+    request: reqwest::RequestBuilder,
+    cached: Option<&CacheEntry>,
+) -> reqwest::Result<Downloaded> {
+    let mut request = request;
+    if let Some(entry) = cached {
+        if let Some(ref etag) = entry.etag {
+            request = request.header(header::IF_NONE_MATCH, etag.as_str());
+        }
+        if let Some(ref last_modified) = entry.last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified.as_str());
+        }
+    }
+    let response = request.send()?;
+    let not_modified = cached.is_some() && response.status() == StatusCode::NOT_MODIFIED;
+    let mut response = response.error_for_status()?;
+    let etag = header_value(response.headers(), header::ETAG);
+    let last_modified = header_value(response.headers(), header::LAST_MODIFIED);
+    let content = if not_modified {
+        None
+    } else {
+        let mut content = vec![];
+        response.copy_to(&mut content)?;
+        Some(content)
+    };
+    Ok(Downloaded {
+        content,
+        etag,
+        last_modified,
+    })
+}
+
+fn is_transient(e: &reqwest::Error) -> bool {
+    match e.status() {
+        Some(status) => status.is_server_error(),
+        None => e.is_http() || e.is_timeout(),
+    }
+}
+
+// Sends the request built by `build` until it succeeds or the
+// retries are exhausted.
+fn download_with_retries<F>(url: &str, options: &HttpOptions, build: F) -> Result<Cursor<Vec<u8>>>
+where
+    F: Fn(&reqwest::Client) -> reqwest::RequestBuilder,
+{
+    match download_if_exists(url, options, &[StatusCode::NOT_FOUND], build)? {
+        Some(content) => Ok(content),
+        None => bail!("Error downloading {}: not found", url),
+    }
+}
+
+// Like `download_with_retries`, `None` if the server answers with one
+// of the `missing` statuses.
+fn download_if_exists<F>(
+    url: &str,
+    options: &HttpOptions,
+    missing: &[StatusCode],
+    build: F,
+) -> Result<Option<Cursor<Vec<u8>>>>
+where
+    F: Fn(&reqwest::Client) -> reqwest::RequestBuilder,
+{
+    let client = options.client()?;
+    let cache = options.cache_dir.as_ref().map(|dir| Cache::new(dir, url));
+    let cached = cache.as_ref().and_then(|c| c.entry());
+    let mut delay = options.retry_delay;
+    let mut attempt = 0;
+    info!("Downloading {}", url);
+    loop {
+        match download(build(&client), cached.as_ref()) {
+            Ok(downloaded) => {
+                let content = downloaded_content(downloaded, url, options, cache.as_ref())?;
+                return Ok(Some(Cursor::new(content)));
+            }
+            Err(ref e) if e.status().is_some_and(|s| missing.contains(&s)) => return Ok(None),
+            Err(ref e) if attempt < options.retries && is_transient(e) => {
+                attempt += 1;
+                warn!(
+                    "Error downloading {}: {}, attempt {} of {} in {:?}",
+                    url, e, attempt, options.retries, delay
+                );
+                thread::sleep(delay);
+                delay *= 2;
+            }
+            Err(e) => Err(e).with_context(|_| format!("Error downloading {}", url))?,
+        }
+    }
+}
+
+/// Downloads the content of the url, as a zip archive to give to a
+/// [`ZipHandler`](struct.ZipHandler.html).
+pub fn read_url(url: &str) -> Result<Cursor<Vec<u8>>> {
+    read_url_with_options(url, &HttpOptions::default())
+}
+
+/// Downloads the content of the url with the given authentication,
+/// timeout, retries, proxy and checksum.
+pub fn read_url_with_options(url: &str, options: &HttpOptions) -> Result<Cursor<Vec<u8>>> {
+    download_with_retries(url, options, |client| {
+        let request = client.get(url);
+        match options.auth {
+            Some(HttpAuth::Basic {
+                ref username,
+                ref password,
+            }) => request.basic_auth(username, password.as_ref()),
+            Some(HttpAuth::Bearer(ref token)) => request.bearer_auth(token),
+            None => request,
+        }
+    })
+}
+
+#[cfg(feature = "async")]
+impl HttpOptions {
+    fn async_client(&self) -> Result<reqwest::async::Client> {
+        let mut builder = reqwest::async::Client::builder();
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(ref proxy) = self.proxy {
+            builder = builder.proxy(
+                reqwest::Proxy::all(proxy.as_str())
+                    .with_context(|_| format!("Error configuring the proxy {}", proxy))?,
+            );
+        }
+        Ok(builder.build()?)
+    }
+
+    fn async_request(
+        &self,
+        client: &reqwest::async::Client,
+        url: &str,
+    ) -> reqwest::async::RequestBuilder {
+        let request = client.get(url);
+        match self.auth {
+            Some(HttpAuth::Basic {
+                ref username,
+                ref password,
+            }) => request.basic_auth(username, password.as_ref()),
+            Some(HttpAuth::Bearer(ref token)) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+fn download_async(
+    request: reqwest::async::RequestBuilder,
+    cached: Option<&CacheEntry>,
+) -> impl Future<Item = Downloaded, Error = reqwest::Error> {
+    let mut request = request;
+    if let Some(entry) = cached {
+        if let Some(ref etag) = entry.etag {
+            request = request.header(header::IF_NONE_MATCH, etag.as_str());
+        }
+        if let Some(ref last_modified) = entry.last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified.as_str());
+        }
+    }
+    let conditional = cached.is_some();
+    request
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(move |response| {
+            let etag = header_value(response.headers(), header::ETAG);
+            let last_modified = header_value(response.headers(), header::LAST_MODIFIED);
+            let content = if conditional && response.status() == StatusCode::NOT_MODIFIED {
+                future::Either::A(future::ok(None))
+            } else {
+                future::Either::B(response.into_body().concat2().map(|body| Some(body.to_vec())))
+            };
+            content.map(|content| Downloaded {
+                content,
+                etag,
+                last_modified,
+            })
+        })
+}
+
+#[cfg(feature = "async")]
+type Attempt = Box<dyn Future<Item = Loop<Downloaded, (u32, Duration)>, Error = Error> + Send>;
+
+/// Downloads the content of the url like
+/// [`read_url_with_options`](fn.read_url_with_options.html), without
+/// blocking the thread.  The future must be run by a
+/// [tokio](https://tokio.rs) runtime.
+#[cfg(feature = "async")]
+pub fn read_url_async(
+    url: &str,
+    options: &HttpOptions,
+) -> impl Future<Item = Cursor<Vec<u8>>, Error = Error> + Send {
+    let url = url.to_string();
+    let options = options.clone();
+    let cache = options.cache_dir.as_ref().map(|dir| Cache::new(dir, &url));
+    let cached = cache.as_ref().and_then(|c| c.entry());
+    let source = url.clone();
+    let download_options = options.clone();
+    future::result(options.async_client()).and_then(move |client| {
+        info!("Downloading {}", url);
+        let first = (0, options.retry_delay);
+        future::loop_fn(first, move |(attempt, delay)| {
+            let url = url.clone();
+            let retries = options.retries;
+            let request = options.async_request(&client, &url);
+            download_async(request, cached.as_ref()).then(move |res| -> Attempt {
+                match res {
+                    Ok(downloaded) => Box::new(future::ok(Loop::Break(downloaded))),
+                    Err(ref e) if attempt < retries && is_transient(e) => {
+                        warn!(
+                            "Error downloading {}: {}, attempt {} of {} in {:?}",
+                            url,
+                            e,
+                            attempt + 1,
+                            retries,
+                            delay
+                        );
+                        Box::new(
+                            Delay::new(Instant::now() + delay)
+                                .map(move |_| Loop::Continue((attempt + 1, delay * 2)))
+                                .map_err(Error::from),
+                        )
+                    }
+                    Err(e) => Box::new(future::err(
+                        Error::from(e).context(format!("Error downloading {}", url)).into(),
+                    )),
+                }
+            })
+        })
+    }).and_then(move |downloaded| {
+        let content = downloaded_content(downloaded, &source, &download_options, cache.as_ref())?;
+        Ok(Cursor::new(content))
+    })
+}
+
+/// The options of the downloads from an S3-compatible object
+/// storage.
+#[cfg(feature = "s3")]
+#[derive(Debug, Clone)]
+pub struct S3Options {
+    /// The url of the storage, as `https://s3.amazonaws.com`.  The
+    /// objects are requested with the path style,
+    /// `{endpoint}/{bucket}/{key}`.
+    pub endpoint: String,
+    /// The region of the bucket, used to sign the requests.
+    pub region: String,
+    /// The access key and the secret key.  The requests are not
+    /// signed without credentials, for the public buckets.
+    pub credentials: Option<(String, String)>,
+    /// Whether an object answered with `403 Forbidden` is considered
+    /// missing, as with `404 Not Found`.  AWS answers 403 for the
+    /// missing keys when the `s3:ListBucket` permission is not granted,
+    /// a real permission error being then reported as a missing file.
+    pub forbidden_as_missing: bool,
+    /// The timeout, retries, proxy and checksum of the downloads.  The
+    /// authentication is ignored, the requests being signed with the
+    /// credentials.  The checksum is the one of the archive given to
+    /// [`read_s3`](fn.read_s3.html), see
+    /// [`S3FileHandler::set_checksum`](struct.S3FileHandler.html#method.set_checksum)
+    /// for the files of a prefix.
+    pub http: HttpOptions,
+}
+
+#[cfg(feature = "s3")]
+impl Default for S3Options {
+    fn default() -> Self {
+        S3Options {
+            endpoint: "https://s3.amazonaws.com".to_string(),
+            region: "us-east-1".to_string(),
+            credentials: None,
+            forbidden_as_missing: true,
+            http: HttpOptions::default(),
+        }
+    }
+}
+
+#[cfg(feature = "s3")]
+impl S3Options {
+    /// Constructs the options from the `AWS_ENDPOINT_URL`,
+    /// `AWS_REGION` (or `AWS_DEFAULT_REGION`), `AWS_ACCESS_KEY_ID` and
+    /// `AWS_SECRET_ACCESS_KEY` environment variables, the defaults
+    /// being used for the missing ones.
+    pub fn from_env() -> Self {
+        use std::env::var;
+        let default = S3Options::default();
+        let region = var("AWS_REGION").or_else(|_| var("AWS_DEFAULT_REGION"));
+        S3Options {
+            endpoint: var("AWS_ENDPOINT_URL").unwrap_or(default.endpoint),
+            region: region.unwrap_or(default.region),
+            credentials: var("AWS_ACCESS_KEY_ID")
+                .and_then(|access_key| Ok((access_key, var("AWS_SECRET_ACCESS_KEY")?)))
+                .ok(),
+            forbidden_as_missing: default.forbidden_as_missing,
+            http: default.http,
+        }
+    }
+}
+
+// Splits an `s3://bucket/key` url in the bucket and the key.
+#[cfg(feature = "s3")]
+fn parse_s3_url(url: &str) -> Result<(&str, &str)> {
+    let path = match url.find("://") {
+        Some(i) if &url[..i] == "s3" => &url[i + 3..],
+        _ => bail!("{} is not an s3://bucket/key url", url),
+    };
+    match path.find('/') {
+        Some(i) if i > 0 && i + 1 < path.len() => Ok((&path[..i], &path[i + 1..])),
+        _ => bail!("{} is not an s3://bucket/key url", url),
+    }
+}
+
+#[cfg(feature = "s3")]
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    use hmac::{Hmac, Mac};
+    let mut mac = Hmac::<sha2::Sha256>::new_from_slice(key).expect("any key size is valid");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+// The key signing the requests of a day, as defined by the AWS
+// signature version 4.
+#[cfg(feature = "s3")]
+fn signing_key(secret_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac_sha256(format!("AWS4{}", secret_key).as_bytes(), date);
+    let key = hmac_sha256(&key, region);
+    let key = hmac_sha256(&key, service);
+    hmac_sha256(&key, "aws4_request")
+}
+
+// Percent-encodes the key of an object, keeping the slashes.
+#[cfg(feature = "s3")]
+fn encode_key(key: &str) -> String {
+    key.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+// Returns the headers of a GET request signed with the AWS signature
+// version 4.
+#[cfg(feature = "s3")]
+fn sign_request(
+    host: &str,
+    path: &str,
+    region: &str,
+    credentials: &(String, String),
+    now: chrono::DateTime<chrono::Utc>,
+) -> Vec<(&'static str, String)> {
+    let (ref access_key, ref secret_key) = *credentials;
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let payload_hash = "UNSIGNED-PAYLOAD";
+    let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+    let canonical_request = format!(
+        "GET\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+        path, host, payload_hash, amz_date, signed_headers, payload_hash
+    );
+    let scope = format!("{}/{}/s3/aws4_request", date, region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+    let signature = hex::encode(hmac_sha256(
+        &signing_key(secret_key, &date, region, "s3"),
+        &string_to_sign,
+    ));
+    let authorization = format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        access_key, scope, signed_headers, signature
+    );
+    vec![
+        ("x-amz-content-sha256", payload_hash.to_string()),
+        ("x-amz-date", amz_date),
+        ("authorization", authorization),
+    ]
+}
+
+// Downloads the object `key` of the bucket, `None` if it does not
+// exist (or is forbidden with `forbidden_as_missing`), `url` being
+// used in the logs and the error messages.
+#[cfg(feature = "s3")]
+fn download_s3_object(
+    url: &str,
+    bucket: &str,
+    key: &str,
+    options: &S3Options,
+) -> Result<Option<Cursor<Vec<u8>>>> {
+    let endpoint = reqwest::Url::parse(&options.endpoint)
+        .with_context(|_| format!("Invalid endpoint {}", options.endpoint))?;
+    let host = match (endpoint.host_str(), endpoint.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        (None, _) => bail!("Invalid endpoint {}", options.endpoint),
+    };
+    let path = format!(
+        "{}/{}/{}",
+        endpoint.path().trim_end_matches('/'),
+        bucket,
+        encode_key(key)
+    );
+    let http_url = format!("{}://{}{}", endpoint.scheme(), host, path);
+    let missing: &[StatusCode] = if options.forbidden_as_missing {
+        &[StatusCode::NOT_FOUND, StatusCode::FORBIDDEN]
+    } else {
+        &[StatusCode::NOT_FOUND]
+    };
+    download_if_exists(url, &options.http, missing, |client| {
+        let mut request = client.get(&http_url);
+        if let Some(ref credentials) = options.credentials {
+            let headers = sign_request(
+                &host,
+                &path,
+                &options.region,
+                credentials,
+                chrono::Utc::now(),
+            );
+            for (name, value) in headers {
+                request = request.header(name, value);
+            }
+        }
+        request
+    })
+}
+
+/// Downloads an object of an S3-compatible storage given by an
+/// `s3://bucket/key` url, as a zip archive to give to a
+/// [`ZipHandler`](struct.ZipHandler.html).
+#[cfg(feature = "s3")]
+pub fn read_s3(url: &str, options: &S3Options) -> Result<Cursor<Vec<u8>>> {
+    let (bucket, key) = parse_s3_url(url)?;
+    match download_s3_object(url, bucket, key, options)? {
+        Some(content) => Ok(content),
+        None => bail!("Error downloading {}: not found", url),
+    }
+}
+
+/// Gives access to the files stored as the objects of an
+/// S3-compatible storage sharing a key prefix, as
+/// `s3://bucket/gtfs/stops.txt` for the files of `s3://bucket/gtfs`.
+///
+/// Each file is downloaded in memory when requested, without any
+/// temporary file.  The checksum of the options is ignored, each file
+/// being verified against its own checksum, given by
+/// [`set_checksum`](#method.set_checksum).
+#[cfg(feature = "s3")]
+pub struct S3FileHandler {
+    bucket: String,
+    prefix: String,
+    options: S3Options,
+    checksums: BTreeMap<String, Checksum>,
+    source_name: String,
+}
+
+#[cfg(feature = "s3")]
+impl S3FileHandler {
+    /// Constructs a handler on the objects of the `s3://bucket` or
+    /// `s3://bucket/prefix` url.
+    pub fn new(url: &str, options: S3Options) -> Result<Self> {
+        let path = match url.find("://") {
+            Some(i) if &url[..i] == "s3" => url[i + 3..].trim_end_matches('/'),
+            _ => bail!("{} is not an s3://bucket/prefix url", url),
+        };
+        let (bucket, prefix) = match path.find('/') {
+            Some(i) => (&path[..i], format!("{}/", &path[i + 1..])),
+            None => (path, String::new()),
+        };
+        ensure!(!bucket.is_empty(), "{} is not an s3://bucket/prefix url", url);
+        Ok(S3FileHandler {
+            bucket: bucket.to_string(),
+            prefix,
+            options,
+            checksums: BTreeMap::new(),
+            source_name: format!("s3://{}", path),
+        })
+    }
+
+    /// Sets the expected checksum of the file `name`, verified when
+    /// the file is downloaded.
+    pub fn set_checksum(&mut self, name: &str, checksum: Checksum) {
+        self.checksums.insert(name.to_string(), checksum);
+    }
+}
+
+#[cfg(feature = "s3")]
+impl FileHandler for &mut S3FileHandler {
+    type Reader = Cursor<Vec<u8>>;
+
+    fn get_file_if_exists(self, name: &str) -> Result<(Option<Self::Reader>, PathBuf)> {
+        let path = Path::new(&self.source_name).join(name);
+        let key = format!("{}{}", self.prefix, name);
+        let url = format!("s3://{}/{}", self.bucket, key);
+        let mut options = self.options.clone();
+        options.http.checksum = self.checksums.get(name).cloned();
+        let reader = download_s3_object(&url, &self.bucket, &key, &options)?;
+        Ok((reader, path))
+    }
+
+    fn get_file_with_size(
+        self,
+        name: &str,
+    ) -> Result<(Option<Self::Reader>, PathBuf, Option<u64>)> {
+        let (reader, path) = self.get_file_if_exists(name)?;
+        let size = reader.as_ref().map(|r| r.get_ref().len() as u64);
+        Ok((reader, path, size))
+    }
+
+    fn source_name(&self) -> &str {
+        &self.source_name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use tempdir::TempDir;
+
+    fn read_to_string<R: Read>(mut r: R) -> String {
+        let mut s = String::new();
+        r.read_to_string(&mut s).unwrap();
+        s
+    }
+
+    fn tar_gz(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(vec![], Compression::default()));
+        for &(name, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, name, content.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn path_file_handler() {
+        let tmp_dir = TempDir::new("navitia_model_tests").unwrap();
+        File::create(tmp_dir.path().join("stops.txt"))
+            .unwrap()
+            .write_all(b"stop_id")
+            .unwrap();
+        let mut handler = PathFileHandler::new(tmp_dir.path());
+
+        let (reader, path) = handler.get_file("stops.txt").unwrap();
+        assert_eq!("stop_id", read_to_string(reader));
+        assert_eq!(tmp_dir.path().join("stops.txt"), path);
+        assert_eq!(Some(7), handler.get_file_with_size("stops.txt").unwrap().2);
+        assert!(handler
+            .get_file_if_exists("routes.txt")
+            .unwrap()
+            .0
+            .is_none());
+        assert!(handler.get_file("routes.txt").is_err());
+
+        fs::create_dir(tmp_dir.path().join("feed")).unwrap();
+        assert_eq!(vec!["stops.txt"], handler.file_names().unwrap());
+    }
+
+    #[test]
+    fn path_file_handler_gzipped_file() {
+        let tmp_dir = TempDir::new("navitia_model_tests").unwrap();
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(b"route_id").unwrap();
+        File::create(tmp_dir.path().join("routes.txt.gz"))
+            .unwrap()
+            .write_all(&encoder.finish().unwrap())
+            .unwrap();
+        let mut handler = PathFileHandler::new(tmp_dir.path());
+
+        let (reader, path, size) = handler.get_file_with_size("routes.txt").unwrap();
+        assert_eq!("route_id", read_to_string(reader.unwrap()));
+        assert_eq!(tmp_dir.path().join("routes.txt.gz"), path);
+        assert_eq!(None, size);
+    }
+
+    #[test]
+    fn zip_handler() {
+        let mut zip = zip::ZipWriter::new(Cursor::new(vec![]));
+        zip.start_file("stops.txt", zip::write::FileOptions::default())
+            .unwrap();
+        zip.write_all(b"stop_id").unwrap();
+        let archive = zip.finish().unwrap();
+        let mut handler = ZipHandler::new(archive, "gtfs.zip").unwrap();
+
+        let (reader, path) = handler.get_file("stops.txt").unwrap();
+        assert_eq!("stop_id", read_to_string(reader));
+        assert_eq!(Path::new("gtfs.zip/stops.txt"), path);
+        assert_eq!(Some(7), handler.get_file_with_size("stops.txt").unwrap().2);
+        assert!(handler
+            .get_file_if_exists("routes.txt")
+            .unwrap()
+            .0
+            .is_none());
+    }
+
+    fn zip(files: &[&str]) -> Cursor<Vec<u8>> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(vec![]));
+        for name in files {
+            zip.start_file(*name, zip::write::FileOptions::default())
+                .unwrap();
+            zip.write_all(name.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap()
+    }
+
+    #[test]
+    fn zip_handler_lookup() {
+        let archive = zip(&["feed/Stops.txt", "feed/routes.txt", "__MACOSX/feed/._stops.txt"]);
+        let mut handler = ZipHandler::new(archive, "gtfs.zip").unwrap();
+        let (reader, path) = handler.get_file("stops.txt").unwrap();
+        assert_eq!("feed/Stops.txt", read_to_string(reader));
+        assert_eq!(Path::new("gtfs.zip/stops.txt"), path);
+        let (reader, _) = handler.get_file("routes.txt").unwrap();
+        assert_eq!("feed/routes.txt", read_to_string(reader));
+        assert_eq!(vec!["routes.txt", "stops.txt"], handler.file_names().unwrap());
+
+        // The files of several directories are ambiguous.
+        let archive = zip(&["a/stops.txt", "b/routes.txt"]);
+        let mut handler = ZipHandler::new(archive, "gtfs.zip").unwrap();
+        assert!(handler.get_file("stops.txt").is_err());
+
+        // The files at the root are preferred to the nested ones.
+        let archive = zip(&["stops.txt", "feed/stops.txt"]);
+        let mut handler = ZipHandler::new(archive, "gtfs.zip").unwrap();
+        let (reader, _) = handler.get_file("stops.txt").unwrap();
+        assert_eq!("stops.txt", read_to_string(reader));
+
+        // The files whose names only differ by their case are ambiguous.
+        let archive = zip(&["stops.txt", "STOPS.txt"]);
+        assert!(ZipHandler::new(archive, "gtfs.zip").is_err());
+    }
+
+    #[test]
+    fn tar_gz_handler() {
+        let archive = tar_gz(&[
+            ("./gtfs/Stops.txt", "stop_id"),
+            ("gtfs/routes.txt", "route_id"),
+            ("gtfs/trips.txt.gz", "trip_id"),
+            ("__MACOSX/gtfs/._stops.txt", ""),
+        ]);
+        let mut handler = TarGzHandler::new(archive.as_slice(), "gtfs.tar.gz").unwrap();
+
+        let (reader, path) = handler.get_file("stops.txt").unwrap();
+        assert_eq!("stop_id", read_to_string(reader));
+        assert_eq!(Path::new("gtfs.tar.gz/stops.txt"), path);
+        let (reader, _) = handler.get_file("routes.txt").unwrap();
+        assert_eq!("route_id", read_to_string(reader));
+        assert!(handler.get_file_if_exists("trips.txt").unwrap().0.is_none());
+        assert!(handler.get_file("trips.txt").is_err());
+
+        // The files of several directories are ambiguous.
+        let archive = tar_gz(&[("a/stops.txt", "stop_id"), ("b/routes.txt", "route_id")]);
+        let mut handler = TarGzHandler::new(archive.as_slice(), "gtfs.tar.gz").unwrap();
+        assert!(handler.get_file("stops.txt").is_err());
+
+        // The files whose names only differ by their case too.
+        let archive = tar_gz(&[("stops.txt", "stop_id"), ("STOPS.txt", "stop_id")]);
+        assert!(TarGzHandler::new(archive.as_slice(), "gtfs.tar.gz").is_err());
+    }
+
+    #[test]
+    fn memory_file_handler() {
+        let mut handler = MemoryFileHandler::new("memory");
+        handler.insert("stops.txt", "stop_id");
+        handler.insert("routes.txt", b"route_id".to_vec());
+
+        let (reader, path) = handler.get_file("stops.txt").unwrap();
+        assert_eq!("stop_id", read_to_string(reader));
+        assert_eq!(Path::new("memory/stops.txt"), path);
+        let (reader, _) = handler.get_file("routes.txt").unwrap();
+        assert_eq!("route_id", read_to_string(reader));
+        assert_eq!(Some(8), handler.get_file_with_size("routes.txt").unwrap().2);
+        assert!(handler.get_file_if_exists("trips.txt").unwrap().0.is_none());
+        assert!(handler.get_file("trips.txt").is_err());
+        assert_eq!(vec!["routes.txt", "stops.txt"], handler.file_names().unwrap());
+    }
+
+    #[test]
+    fn invalid_tar_gz() {
+        assert!(TarGzHandler::new(&b"not an archive"[..], "gtfs.tar.gz").is_err());
+    }
+
+    // Serves the responses to the successive requests, returning the
+    // url of the server and the headers of the requests.
+    fn serve(responses: Vec<&'static str>) -> (String, thread::JoinHandle<Vec<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/gtfs.zip", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let mut requests = vec![];
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let headers = BufReader::new(stream.try_clone().unwrap())
+                    .lines()
+                    .map(|l| l.unwrap())
+                    .take_while(|l| !l.is_empty())
+                    .collect();
+                requests.push(headers);
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn read_url_with_auth_and_retries() {
+        let (url, handle) = serve(vec![
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 7\r\nConnection: close\r\n\r\nstop_id",
+        ]);
+        let options = HttpOptions {
+            auth: Some(HttpAuth::Bearer("secret".to_string())),
+            retries: 2,
+            retry_delay: Duration::from_millis(10),
+            ..Default::default()
+        };
+        let content = read_url_with_options(&url, &options).unwrap();
+        assert_eq!("stop_id", read_to_string(content));
+
+        let requests = handle.join().unwrap();
+        assert_eq!(2, requests.len());
+        for headers in requests {
+            assert!(headers
+                .iter()
+                .any(|h| h.to_lowercase() == "authorization: bearer secret"));
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn read_url_async_with_auth_and_retries() {
+        let (url, handle) = serve(vec![
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 7\r\nConnection: close\r\n\r\nstop_id",
+        ]);
+        let options = HttpOptions {
+            auth: Some(HttpAuth::Basic {
+                username: "user".to_string(),
+                password: None,
+            }),
+            retries: 1,
+            retry_delay: Duration::from_millis(10),
+            ..Default::default()
+        };
+        let mut runtime = ::tokio::runtime::Runtime::new().unwrap();
+        let content = runtime.block_on(read_url_async(&url, &options)).unwrap();
+        assert_eq!("stop_id", read_to_string(content));
+
+        let requests = handle.join().unwrap();
+        assert_eq!(2, requests.len());
+        for headers in requests {
+            assert!(headers
+                .iter()
+                .any(|h| h.to_lowercase().starts_with("authorization: basic ")));
+        }
+    }
+
+    #[test]
+    fn read_url_with_checksum() {
+        let response = "HTTP/1.1 200 OK\r\nContent-Length: 7\r\nConnection: close\r\n\r\nstop_id";
+        let (url, handle) = serve(vec![response, response]);
+        let mut options = HttpOptions {
+            checksum: Some(Checksum::Md5("fc1d4d0a6b2b7ff52e8a2e6d0fa8e1b5".to_string())),
+            ..Default::default()
+        };
+        let error = read_url_with_options(&url, &options).unwrap_err();
+        assert!(error.to_string().contains("checksum mismatch"));
+        options.checksum = Some(Checksum::Md5("91455da77df32a590b337af9b68d5d7f".to_string()));
+        let content = read_url_with_options(&url, &options).unwrap();
+        assert_eq!("stop_id", read_to_string(content));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn read_url_with_cache() {
+        let (url, handle) = serve(vec![
+            concat!(
+                "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\n",
+                "Content-Length: 7\r\nConnection: close\r\n\r\nstop_id"
+            ),
+            "HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n",
+        ]);
+        let cache_dir = TempDir::new("navitia_model_tests").unwrap();
+        let options = HttpOptions {
+            cache_dir: Some(cache_dir.path().join("cache")),
+            ..Default::default()
+        };
+        let content = read_url_with_options(&url, &options).unwrap();
+        assert_eq!("stop_id", read_to_string(content));
+        let content = read_url_with_options(&url, &options).unwrap();
+        assert_eq!("stop_id", read_to_string(content));
+
+        let requests = handle.join().unwrap();
+        let conditional = |headers: &Vec<String>| {
+            headers
+                .iter()
+                .any(|h| h.to_lowercase() == "if-none-match: \"v1\"")
+        };
+        assert!(!conditional(&requests[0]));
+        assert!(conditional(&requests[1]));
+    }
+
+    #[test]
+    fn read_url_with_cache_and_checksum_mismatch() {
+        let response = concat!(
+            "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\n",
+            "Content-Length: 7\r\nConnection: close\r\n\r\nstop_id"
+        );
+        let (url, handle) = serve(vec![response, response]);
+        let cache_dir = TempDir::new("navitia_model_tests").unwrap();
+        let options = HttpOptions {
+            checksum: Some(Checksum::Md5("fc1d4d0a6b2b7ff52e8a2e6d0fa8e1b5".to_string())),
+            cache_dir: Some(cache_dir.path().join("cache")),
+            ..Default::default()
+        };
+        assert!(read_url_with_options(&url, &options).is_err());
+        assert!(read_url_with_options(&url, &options).is_err());
+
+        // the content not matching the checksum is never cached
+        let requests = handle.join().unwrap();
+        assert!(requests.iter().all(|headers| !headers
+            .iter()
+            .any(|h| h.to_lowercase().starts_with("if-none-match"))));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn read_url_async_with_cache() {
+        let (url, handle) = serve(vec![
+            concat!(
+                "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\n",
+                "Content-Length: 7\r\nConnection: close\r\n\r\nstop_id"
+            ),
+            "HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n",
+        ]);
+        let cache_dir = TempDir::new("navitia_model_tests").unwrap();
+        let options = HttpOptions {
+            cache_dir: Some(cache_dir.path().join("cache")),
+            ..Default::default()
+        };
+        let mut runtime = ::tokio::runtime::Runtime::new().unwrap();
+        let content = runtime.block_on(read_url_async(&url, &options)).unwrap();
+        assert_eq!("stop_id", read_to_string(content));
+        let content = runtime.block_on(read_url_async(&url, &options)).unwrap();
+        assert_eq!("stop_id", read_to_string(content));
+
+        let requests = handle.join().unwrap();
+        assert!(requests[1]
+            .iter()
+            .any(|h| h.to_lowercase() == "if-none-match: \"v1\""));
+    }
+
+    #[test]
+    fn read_url_client_error_not_retried() {
+        let (url, handle) = serve(vec![
+            "HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ]);
+        let options = HttpOptions {
+            retries: 2,
+            retry_delay: Duration::from_millis(10),
+            ..Default::default()
+        };
+        assert!(read_url_with_options(&url, &options).is_err());
+        assert_eq!(1, handle.join().unwrap().len());
+    }
+
+    #[cfg(feature = "s3")]
+    #[test]
+    fn s3_url() {
+        assert_eq!(
+            ("bucket", "gtfs/2018.zip"),
+            parse_s3_url("s3://bucket/gtfs/2018.zip").unwrap()
+        );
+        assert!(parse_s3_url("https://bucket/gtfs.zip").is_err());
+        assert!(parse_s3_url("s3://bucket/").is_err());
+        assert_eq!("gtfs/data%20set%2B.zip", encode_key("gtfs/data set+.zip"));
+    }
+
+    #[cfg(feature = "s3")]
+    #[test]
+    fn s3_signing_key() {
+        // example of the AWS documentation
+        let key = signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+        assert_eq!(
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d",
+            hex::encode(key)
+        );
+    }
+
+    #[cfg(feature = "s3")]
+    #[test]
+    fn read_s3_signed() {
+        let (url, handle) = serve(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 7\r\nConnection: close\r\n\r\nstop_id",
+        ]);
+        let options = S3Options {
+            endpoint: url.trim_end_matches("/gtfs.zip").to_string(),
+            credentials: Some(("AKID".to_string(), "secret".to_string())),
+            ..Default::default()
+        };
+        let content = read_s3("s3://bucket/gtfs.zip", &options).unwrap();
+        assert_eq!("stop_id", read_to_string(content));
+
+        let requests = handle.join().unwrap();
+        assert!(requests[0][0].starts_with("GET /bucket/gtfs.zip "));
+        assert!(requests[0].iter().any(|h| h
+            .to_lowercase()
+            .starts_with("authorization: aws4-hmac-sha256 credential=akid/")));
+    }
+
+    #[cfg(feature = "s3")]
+    #[test]
+    fn s3_file_handler() {
+        let (url, handle) = serve(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 7\r\nConnection: close\r\n\r\nstop_id",
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ]);
+        let options = S3Options {
+            endpoint: url.trim_end_matches("/gtfs.zip").to_string(),
+            ..Default::default()
+        };
+        let mut handler = S3FileHandler::new("s3://bucket/gtfs/", options).unwrap();
+        assert_eq!("s3://bucket/gtfs", handler.source_name);
+
+        let (reader, path, size) = handler.get_file_with_size("stops.txt").unwrap();
+        assert_eq!("stop_id", read_to_string(reader.unwrap()));
+        assert_eq!(Path::new("s3://bucket/gtfs/stops.txt"), path);
+        assert_eq!(Some(7), size);
+        assert!(handler
+            .get_file_if_exists("transfers.txt")
+            .unwrap()
+            .0
+            .is_none());
+        assert!(handler
+            .get_file_if_exists("pathways.txt")
+            .unwrap()
+            .0
+            .is_none());
+
+        let requests = handle.join().unwrap();
+        assert!(requests[0][0].starts_with("GET /bucket/gtfs/stops.txt "));
+        assert!(requests[1][0].starts_with("GET /bucket/gtfs/transfers.txt "));
+        assert!(requests[2][0].starts_with("GET /bucket/gtfs/pathways.txt "));
+        assert!(S3FileHandler::new("https://bucket/gtfs", S3Options::default()).is_err());
+    }
+
+    #[cfg(feature = "s3")]
+    #[test]
+    fn s3_file_handler_forbidden() {
+        let (url, _handle) = serve(vec![
+            "HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ]);
+        let options = S3Options {
+            endpoint: url.trim_end_matches("/gtfs.zip").to_string(),
+            forbidden_as_missing: false,
+            ..Default::default()
+        };
+        let mut handler = S3FileHandler::new("s3://bucket/gtfs", options).unwrap();
+        let error = handler.get_file_if_exists("transfers.txt").unwrap_err();
+        assert!(format!("{:?}", error).contains("403"));
+    }
+
+    #[cfg(feature = "s3")]
+    #[test]
+    fn s3_file_handler_checksums() {
+        let (url, _handle) = serve(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 7\r\nConnection: close\r\n\r\nstop_id",
+            "HTTP/1.1 200 OK\r\nContent-Length: 9\r\nConnection: close\r\n\r\nagency_id",
+            "HTTP/1.1 200 OK\r\nContent-Length: 8\r\nConnection: close\r\n\r\nroute_id",
+        ]);
+        let mut options = S3Options {
+            endpoint: url.trim_end_matches("/gtfs.zip").to_string(),
+            ..Default::default()
+        };
+        // the checksum of an archive, not checked for the files
+        options.http.checksum = Some(Checksum::Md5("fc1d4d0a6b2b7ff52e8a2e6d0fa8e1b5".to_string()));
+        let mut handler = S3FileHandler::new("s3://bucket/gtfs", options).unwrap();
+        handler.set_checksum(
+            "stops.txt",
+            Checksum::Md5("91455da77df32a590b337af9b68d5d7f".to_string()),
+        );
+        handler.set_checksum(
+            "routes.txt",
+            Checksum::Md5("fc1d4d0a6b2b7ff52e8a2e6d0fa8e1b5".to_string()),
+        );
+
+        let (reader, _) = handler.get_file_if_exists("stops.txt").unwrap();
+        assert_eq!("stop_id", read_to_string(reader.unwrap()));
+        let (reader, _) = handler.get_file_if_exists("agency.txt").unwrap();
+        assert_eq!("agency_id", read_to_string(reader.unwrap()));
+        let error = handler.get_file_if_exists("routes.txt").unwrap_err();
+        assert!(error.to_string().contains("checksum mismatch"));
+    }
+
+    #[cfg(feature = "s3")]
+    #[test]
+    fn s3_file_handler_without_prefix() {
+        let handler = S3FileHandler::new("s3://bucket", S3Options::default()).unwrap();
+        assert_eq!("", handler.prefix);
+        assert_eq!("bucket", handler.bucket);
+    }
+}
@@ -0,0 +1,362 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! Handling and reporting of the issues met while reading a dataset.
+
+use failure::ResultExt;
+use serde_json;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::str::FromStr;
+use {Error, Result};
+
+/// An element skipped or ignored while reading a dataset.  The
+/// warnings serialize as structured entries, to be analyzed outside
+/// of the logs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Warning {
+    /// The file in which the element was found.
+    pub file: String,
+    /// The line of the file at which the element was found, when
+    /// known.
+    pub line: Option<u64>,
+    /// The identifier of the object concerned, when known.
+    pub object_id: Option<String>,
+    /// The kind of issue met, `None` for the warnings that never abort
+    /// the reading.
+    pub issue: Option<Issue>,
+    /// How the issue was handled, `None` for the warnings that never
+    /// abort the reading.
+    pub handling: Option<IssueHandling>,
+    /// The reason why the element was skipped or ignored.
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.file, line, self.message),
+            None => write!(f, "{}: {}", self.file, self.message),
+        }
+    }
+}
+
+/// The kinds of issues a reader can meet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Issue {
+    /// A row that cannot be parsed.
+    InvalidRow,
+    /// A reference to an object that does not exist.
+    UnknownReference,
+    /// An element that the reader does not support yet.
+    Unsupported,
+    /// A missing value that can be deduced from the other data.
+    MissingValue,
+    /// An invalid row, or a reference to an object that does not
+    /// exist, in an optional element that no other object depends on,
+    /// as a transfer, a geometry, an entrance, a fare, an attribution
+    /// or a translation.
+    InvalidOptionalElement,
+}
+
+/// What a reader does when meeting an issue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueHandling {
+    /// The reading fails.
+    Abort,
+    /// The element is skipped, or the missing value left empty, and a
+    /// warning is reported.
+    Skip,
+    /// The element is fixed when possible, skipped otherwise, and a
+    /// warning is reported.
+    Fix,
+}
+
+/// How strictly a dataset is read.  The profile is understood by the
+/// GTFS, NTFS and NeTEx readers.
+#[derive(Debug, Clone, PartialEq, Eq, Derivative)]
+#[derivative(Default)]
+pub enum ParseProfile {
+    /// Invalid rows and unknown references abort the reading, the
+    /// invalid optional elements and the unsupported ones are skipped
+    /// and the missing values left empty.
+    #[derivative(Default)]
+    Strict,
+    /// Invalid elements are skipped and missing values are left empty.
+    Permissive,
+    /// Custom handling per kind of issue, the issues not listed are
+    /// handled as in the `Permissive` profile.
+    Custom(HashMap<Issue, IssueHandling>),
+}
+
+impl ParseProfile {
+    /// Returns the handling of the given kind of issue.
+    ///
+    /// ```
+    /// # use navitia_model::report::*;
+    /// let mut handlings = ::std::collections::HashMap::new();
+    /// handlings.insert(Issue::InvalidRow, IssueHandling::Abort);
+    /// handlings.insert(Issue::MissingValue, IssueHandling::Fix);
+    /// let profile = ParseProfile::Custom(handlings);
+    /// assert_eq!(IssueHandling::Abort, profile.handling(Issue::InvalidRow));
+    /// assert_eq!(IssueHandling::Fix, profile.handling(Issue::MissingValue));
+    /// assert_eq!(IssueHandling::Skip, profile.handling(Issue::Unsupported));
+    /// ```
+    pub fn handling(&self, issue: Issue) -> IssueHandling {
+        match *self {
+            ParseProfile::Strict => match issue {
+                Issue::InvalidRow | Issue::UnknownReference => IssueHandling::Abort,
+                Issue::InvalidOptionalElement | Issue::Unsupported | Issue::MissingValue => {
+                    IssueHandling::Skip
+                }
+            },
+            ParseProfile::Permissive => IssueHandling::Skip,
+            ParseProfile::Custom(ref handlings) => handlings
+                .get(&issue)
+                .cloned()
+                .unwrap_or_else(|| ParseProfile::Permissive.handling(issue)),
+        }
+    }
+}
+
+impl FromStr for ParseProfile {
+    type Err = Error;
+
+    /// Parses the `strict` and `permissive` profiles.
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "strict" => Ok(ParseProfile::Strict),
+            "permissive" => Ok(ParseProfile::Permissive),
+            _ => bail!("unknown profile {:?}, expected strict or permissive", s),
+        }
+    }
+}
+
+/// Applies a `ParseProfile` to the issues met by a reader and
+/// collects the resulting warnings.
+#[derive(Default)]
+pub(crate) struct Issues {
+    profile: ParseProfile,
+    warnings: Vec<Warning>,
+}
+
+impl Issues {
+    pub fn new(profile: ParseProfile) -> Self {
+        Issues {
+            profile,
+            warnings: vec![],
+        }
+    }
+
+    /// Fails if the issue must abort the reading, otherwise logs and
+    /// records a warning and returns how the issue must be handled.
+    pub fn handle<S: Into<String>>(
+        &mut self,
+        issue: Issue,
+        file: &str,
+        message: S,
+    ) -> Result<IssueHandling> {
+        self.handle_at(issue, file, None, message)
+    }
+
+    /// Like `handle`, for an issue met at the given line of the file.
+    pub fn handle_at<S: Into<String>>(
+        &mut self,
+        issue: Issue,
+        file: &str,
+        line: Option<u64>,
+        message: S,
+    ) -> Result<IssueHandling> {
+        self.handle_warning(
+            issue,
+            Warning {
+                file: file.to_string(),
+                line,
+                object_id: None,
+                issue: Some(issue),
+                handling: None,
+                message: message.into(),
+            },
+        )
+    }
+
+    /// Like `handle`, for an issue concerning the object identified
+    /// by `object_id`.
+    pub fn handle_object<S: Into<String>>(
+        &mut self,
+        issue: Issue,
+        file: &str,
+        object_id: &str,
+        message: S,
+    ) -> Result<IssueHandling> {
+        self.handle_warning(
+            issue,
+            Warning {
+                file: file.to_string(),
+                line: None,
+                object_id: Some(object_id.to_string()),
+                issue: Some(issue),
+                handling: None,
+                message: message.into(),
+            },
+        )
+    }
+
+    fn handle_warning(&mut self, issue: Issue, mut warning: Warning) -> Result<IssueHandling> {
+        let handling = self.profile.handling(issue);
+        if handling == IssueHandling::Abort {
+            bail!("{}", warning);
+        }
+        warning.handling = Some(handling);
+        self.push(warning);
+        Ok(handling)
+    }
+
+    /// Logs and records a warning that never aborts the reading.
+    pub fn warn<S: Into<String>>(&mut self, file: &str, message: S) {
+        self.push(Warning {
+            file: file.to_string(),
+            line: None,
+            object_id: None,
+            issue: None,
+            handling: None,
+            message: message.into(),
+        });
+    }
+
+    fn push(&mut self, warning: Warning) {
+        warn!("{}", warning);
+        self.warnings.push(warning);
+    }
+
+    /// Returns a collector with the same profile, for a reader
+    /// running on another thread.
+    #[cfg(feature = "parallel")]
+    pub fn fork(&self) -> Self {
+        Issues::new(self.profile.clone())
+    }
+
+    /// Appends the warnings collected by `other`.
+    #[cfg(feature = "parallel")]
+    pub fn append(&mut self, other: Issues) {
+        self.warnings.extend(other.warnings);
+    }
+
+    pub fn into_warnings(self) -> Vec<Warning> {
+        self.warnings
+    }
+}
+
+/// Writes the warnings as a JSON array in the file at `path`, for the
+/// quality dashboards.
+pub fn write_warnings<P: AsRef<Path>>(warnings: &[Warning], path: P) -> Result<()> {
+    let path = path.as_ref();
+    info!("Writing {} warnings to {:?}", warnings.len(), path);
+    let file = File::create(path).with_context(ctx_from_path!(path))?;
+    serde_json::to_writer_pretty(BufWriter::new(file), warnings)
+        .with_context(ctx_from_path!(path))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permissive_issues_are_reported() {
+        let mut issues = Issues::new(ParseProfile::Permissive);
+        let handling = issues
+            .handle(
+                Issue::Unsupported,
+                "stops.txt",
+                "location_type 3 not supported",
+            ).unwrap();
+        assert_eq!(IssueHandling::Skip, handling);
+        let warnings = issues.into_warnings();
+        assert_eq!(1, warnings.len());
+        assert_eq!(
+            "stops.txt: location_type 3 not supported",
+            warnings[0].to_string()
+        );
+    }
+
+    #[test]
+    fn strict_issues_abort() {
+        let mut issues = Issues::new(ParseProfile::Strict);
+        assert!(
+            issues
+                .handle(Issue::InvalidRow, "transfers.txt", "no time")
+                .is_err()
+        );
+        assert_eq!(
+            IssueHandling::Skip,
+            issues
+                .handle(Issue::MissingValue, "transfers.txt", "no time")
+                .unwrap()
+        );
+        assert_eq!(
+            IssueHandling::Skip,
+            issues
+                .handle(Issue::InvalidOptionalElement, "transfers.txt", "unknown stop")
+                .unwrap()
+        );
+        issues.warn("routes.txt", "no trip");
+        assert_eq!(3, issues.into_warnings().len());
+    }
+
+    #[test]
+    fn structured_warnings() {
+        let mut issues = Issues::new(ParseProfile::Permissive);
+        issues
+            .handle_object(Issue::InvalidRow, "stop_times.txt", "trip:1", "no time")
+            .unwrap();
+        issues.warn("routes.txt", "no trip");
+        let warnings = issues.into_warnings();
+        assert_eq!(
+            json!([
+                {
+                    "file": "stop_times.txt",
+                    "line": null,
+                    "object_id": "trip:1",
+                    "issue": "invalid_row",
+                    "handling": "skip",
+                    "message": "no time",
+                },
+                {
+                    "file": "routes.txt",
+                    "line": null,
+                    "object_id": null,
+                    "issue": null,
+                    "handling": null,
+                    "message": "no trip",
+                },
+            ]),
+            serde_json::to_value(&warnings).unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_profile() {
+        assert_eq!(ParseProfile::Strict, "strict".parse().unwrap());
+        assert_eq!(ParseProfile::Permissive, "permissive".parse().unwrap());
+        assert!("lenient".parse::<ParseProfile>().is_err());
+    }
+}
@@ -0,0 +1,368 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! [SIRI](http://www.transmodel-cen.eu/standards/siri/) Estimated
+//! Timetable and Situation Exchange deliveries, resolved against the
+//! vehicle journeys and the stop points of a `Model` as the GTFS-RT
+//! feeds are.
+
+use chrono::{DateTime, FixedOffset};
+use failure::ResultExt;
+use gtfs_rt::{Disruption, RealtimeUpdates, TripStatus, TripUpdate, UpdatedStopTime};
+use model::Model;
+use objects::{Date, Time};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use Result;
+
+extern crate minidom;
+use self::minidom::Element;
+
+// A call of an estimated vehicle journey, with its best known times.
+struct Call {
+    stop_id: String,
+    arrival: Option<DateTime<FixedOffset>>,
+    departure: Option<DateTime<FixedOffset>>,
+    cancelled: bool,
+}
+
+fn child_text(node: &Element, name: &str, ns: &str) -> Option<String> {
+    node.get_child(name, ns)
+        .map(|n| n.text().trim().to_string())
+}
+
+// The elements named `name` under `node`, whatever their depth.
+fn descendants<'a>(node: &'a Element, name: &str) -> Vec<&'a Element> {
+    let mut elements = vec![];
+    for child in node.children() {
+        if child.name() == name {
+            elements.push(child);
+        } else {
+            elements.extend(descendants(child, name));
+        }
+    }
+    elements
+}
+
+fn parse_datetime(value: &str) -> Result<DateTime<FixedOffset>> {
+    Ok(DateTime::parse_from_rfc3339(value)
+        .with_context(|_| format!("invalid date and time {:?}", value))?)
+}
+
+fn datetime(node: &Element, names: &[&str], ns: &str) -> Result<Option<DateTime<FixedOffset>>> {
+    match names
+        .iter()
+        .filter_map(|name| child_text(node, name, ns))
+        .next()
+    {
+        Some(value) => parse_datetime(&value).map(Some),
+        None => Ok(None),
+    }
+}
+
+fn seconds(time: Time) -> i64 {
+    i64::from(time.hours() * 3600 + time.minutes() * 60 + time.seconds())
+}
+
+// The seconds of the time since the midnight of the date, in the
+// offset of the time.
+fn seconds_since(date: Date, time: DateTime<FixedOffset>) -> i64 {
+    (time.naive_local() - date.and_hms(0, 0, 0)).num_seconds()
+}
+
+fn time(seconds: i64) -> Time {
+    Time::new(0, 0, seconds.max(0) as u32)
+}
+
+/// Reads the SIRI delivery at `path`, like `read`.
+pub fn read_from_path<P: AsRef<Path>>(model: &Model, path: P) -> Result<RealtimeUpdates> {
+    let path = path.as_ref();
+    info!("Reading SIRI delivery from {:?}", path);
+    let file = File::open(path).with_context(ctx_from_path!(path))?;
+    Ok(read(model, file).with_context(ctx_from_path!(path))?)
+}
+
+/// Reads the estimated vehicle journeys and the situations of a SIRI
+/// service delivery, resolving them against the vehicle journeys, the
+/// lines, the networks and the stop points of `model` by their id.
+///
+/// The date of a journey is given by its `DataFrameRef`, or else by
+/// the `ResponseTimestamp` of the delivery.  The calls are matched to
+/// the stop times by their stop point, in order, and the delay of a
+/// call is propagated to the following stops without call.  The
+/// unknown journeys and stops are skipped with a warning.
+pub fn read<R: Read>(model: &Model, mut reader: R) -> Result<RealtimeUpdates> {
+    let mut content = String::new();
+    reader.read_to_string(&mut content)?;
+    let root: Element = content.parse()?;
+    let ns = root.ns().unwrap_or_default();
+    let ns = ns.as_str();
+
+    let timestamp = match descendants(&root, "ResponseTimestamp").first() {
+        Some(node) => Some(parse_datetime(node.text().trim())?),
+        None => None,
+    };
+    let mut updates = RealtimeUpdates {
+        timestamp: timestamp.map(|t| t.timestamp() as u64),
+        ..Default::default()
+    };
+    let default_date = timestamp.map(|t| t.naive_local().date());
+    for journey in descendants(&root, "EstimatedVehicleJourney") {
+        match read_journey(model, journey, default_date, ns)
+            .with_context(|_| "Error reading an EstimatedVehicleJourney")?
+        {
+            Some(update) => updates.trip_updates.push(update),
+            None => warn!("An EstimatedVehicleJourney is ignored"),
+        }
+    }
+    for situation in descendants(&root, "PtSituationElement") {
+        updates
+            .disruptions
+            .push(read_situation(model, situation, ns)?);
+    }
+    Ok(updates)
+}
+
+fn read_calls(journey: &Element, ns: &str) -> Result<Vec<Call>> {
+    let mut calls = vec![];
+    let nodes = descendants(journey, "RecordedCall")
+        .into_iter()
+        .chain(descendants(journey, "EstimatedCall"));
+    for node in nodes {
+        let stop_id = match child_text(node, "StopPointRef", ns) {
+            Some(stop_id) => stop_id,
+            None => bail!("A call has no StopPointRef"),
+        };
+        let status = |name| child_text(node, name, ns).as_deref() == Some("cancelled");
+        calls.push(Call {
+            arrival: datetime(
+                node,
+                &[
+                    "ActualArrivalTime",
+                    "ExpectedArrivalTime",
+                    "AimedArrivalTime",
+                ],
+                ns,
+            )?,
+            departure: datetime(
+                node,
+                &[
+                    "ActualDepartureTime",
+                    "ExpectedDepartureTime",
+                    "AimedDepartureTime",
+                ],
+                ns,
+            )?,
+            cancelled: child_text(node, "Cancellation", ns).as_deref() == Some("true")
+                || status("ArrivalStatus")
+                || status("DepartureStatus"),
+            stop_id,
+        });
+    }
+    Ok(calls)
+}
+
+fn read_journey(
+    model: &Model,
+    journey: &Element,
+    default_date: Option<Date>,
+    ns: &str,
+) -> Result<Option<TripUpdate>> {
+    let framed = journey.get_child("FramedVehicleJourneyRef", ns);
+    let trip_id = framed
+        .and_then(|f| child_text(f, "DatedVehicleJourneyRef", ns))
+        .or_else(|| child_text(journey, "DatedVehicleJourneyRef", ns))
+        .or_else(|| child_text(journey, "EstimatedVehicleJourneyCode", ns));
+    let trip_id = match trip_id {
+        Some(trip_id) => trip_id,
+        None => {
+            warn!("An EstimatedVehicleJourney has no reference");
+            return Ok(None);
+        }
+    };
+    let date = match framed.and_then(|f| child_text(f, "DataFrameRef", ns)) {
+        Some(date) => Date::parse_from_str(&date, "%Y-%m-%d")
+            .with_context(|_| format!("invalid DataFrameRef {:?}", date))?,
+        None => default_date.ok_or_else(|| format_err!("no DataFrameRef nor ResponseTimestamp"))?,
+    };
+    let calls = read_calls(journey, ns)?;
+    let mut update = TripUpdate {
+        vehicle_journey_idx: None,
+        trip_id: trip_id.clone(),
+        date,
+        status: TripStatus::Scheduled,
+        stop_times: vec![],
+    };
+
+    if child_text(journey, "ExtraJourney", ns).as_deref() == Some("true") {
+        update.status = TripStatus::Added;
+        for call in &calls {
+            let stop_point_idx = match model.stop_points.get_idx(&call.stop_id) {
+                Some(idx) => idx,
+                None => {
+                    warn!(
+                        "Unknown stop {:?} of the journey {:?}",
+                        call.stop_id, trip_id
+                    );
+                    continue;
+                }
+            };
+            let (arrival, departure) = match (call.arrival.or(call.departure), call.departure) {
+                (Some(arrival), departure) => (arrival, departure.unwrap_or(arrival)),
+                _ => bail!("no time at the stop {:?} of an extra journey", call.stop_id),
+            };
+            update.stop_times.push(UpdatedStopTime {
+                stop_point_idx,
+                sequence: update.stop_times.len() as u32,
+                arrival_time: time(seconds_since(date, arrival)),
+                departure_time: time(seconds_since(date, departure)),
+                skipped: call.cancelled,
+            });
+        }
+        return Ok(Some(update));
+    }
+
+    let vj_idx = match model.vehicle_journeys.get_idx(&trip_id) {
+        Some(idx) => idx,
+        None => {
+            warn!("The journey {:?} is not found", trip_id);
+            return Ok(None);
+        }
+    };
+    let vj = &model.vehicle_journeys[vj_idx];
+    let runs = model
+        .calendars
+        .get(&vj.service_id)
+        .is_some_and(|c| c.dates.contains(&date));
+    if !runs {
+        warn!("The journey {:?} does not run on {}", trip_id, date);
+        return Ok(None);
+    }
+    update.vehicle_journey_idx = Some(vj_idx);
+    if child_text(journey, "Cancellation", ns).as_deref() == Some("true") {
+        update.status = TripStatus::Cancelled;
+        return Ok(Some(update));
+    }
+
+    let mut calls = calls.iter().peekable();
+    let mut delay = 0;
+    for st in &vj.stop_times {
+        let stop_id = &model.stop_points[st.stop_point_idx].id;
+        let mut skipped = false;
+        let (arrival_delay, departure_delay) = match calls.next_if(|c| &c.stop_id == stop_id) {
+            Some(call) => {
+                skipped = call.cancelled;
+                let arrival_delay = call
+                    .arrival
+                    .map_or(delay, |t| seconds_since(date, t) - seconds(st.arrival_time));
+                delay = call.departure.map_or(arrival_delay, |t| {
+                    seconds_since(date, t) - seconds(st.departure_time)
+                });
+                (arrival_delay, delay)
+            }
+            None => (delay, delay),
+        };
+        update.stop_times.push(UpdatedStopTime {
+            stop_point_idx: st.stop_point_idx,
+            sequence: st.sequence,
+            arrival_time: time(seconds(st.arrival_time) + arrival_delay),
+            departure_time: time(seconds(st.departure_time) + departure_delay),
+            skipped,
+        });
+    }
+    for call in calls {
+        warn!(
+            "The stop {:?} of the journey {:?} is not found",
+            call.stop_id, trip_id
+        );
+    }
+    Ok(Some(update))
+}
+
+fn read_situation(model: &Model, situation: &Element, ns: &str) -> Result<Disruption> {
+    let id = child_text(situation, "SituationNumber", ns).unwrap_or_default();
+    let mut periods = vec![];
+    for period in descendants(situation, "ValidityPeriod") {
+        let bound = |name| -> Result<Option<u64>> {
+            Ok(datetime(period, &[name], ns)?.map(|t| t.timestamp() as u64))
+        };
+        periods.push((bound("StartTime")?, bound("EndTime")?));
+    }
+    let text = |name| {
+        descendants(situation, name)
+            .first()
+            .map(|n| n.text().trim().to_string())
+    };
+    let mut disruption = Disruption {
+        cause: situation
+            .children()
+            .find(|c| c.name().ends_with("Reason"))
+            .map(|c| c.text().trim().to_string()),
+        effect: text("Condition"),
+        header: child_text(situation, "Summary", ns),
+        description: child_text(situation, "Description", ns),
+        url: text("Uri"),
+        periods,
+        networks: vec![],
+        lines: vec![],
+        vehicle_journeys: vec![],
+        stop_points: vec![],
+        id,
+    };
+    let affects = match situation.get_child("Affects", ns) {
+        Some(affects) => affects,
+        None => return Ok(disruption),
+    };
+    let refs = |name| descendants(affects, name).into_iter().map(|n| n.text());
+    for network_id in refs("NetworkRef") {
+        match model.networks.get_idx(network_id.trim()) {
+            Some(idx) => disruption.networks.push(idx),
+            None => warn!(
+                "Unknown network {:?} in the situation {:?}",
+                network_id, disruption.id
+            ),
+        }
+    }
+    for line_id in refs("LineRef") {
+        match model.lines.get_idx(line_id.trim()) {
+            Some(idx) => disruption.lines.push(idx),
+            None => warn!(
+                "Unknown line {:?} in the situation {:?}",
+                line_id, disruption.id
+            ),
+        }
+    }
+    for vj_id in refs("DatedVehicleJourneyRef").chain(refs("VehicleJourneyRef")) {
+        match model.vehicle_journeys.get_idx(vj_id.trim()) {
+            Some(idx) => disruption.vehicle_journeys.push(idx),
+            None => warn!(
+                "Unknown journey {:?} in the situation {:?}",
+                vj_id, disruption.id
+            ),
+        }
+    }
+    for stop_id in refs("StopPointRef") {
+        match model.stop_points.get_idx(stop_id.trim()) {
+            Some(idx) => disruption.stop_points.push(idx),
+            None => warn!(
+                "Unknown stop {:?} in the situation {:?}",
+                stop_id, disruption.id
+            ),
+        }
+    }
+    Ok(disruption)
+}
@@ -0,0 +1,182 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! Statistics of a model, to follow the health of a feed.
+
+use model::Collections;
+use objects::Date;
+use read_utils;
+use std::collections::{BTreeMap, BTreeSet};
+use utils::ser_from_naive_date;
+
+/// A period of dates, both included.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Period {
+    /// The first date of the period.
+    #[serde(serialize_with = "ser_from_naive_date")]
+    pub start_date: Date,
+    /// The last date of the period.
+    #[serde(serialize_with = "ser_from_naive_date")]
+    pub end_date: Date,
+}
+
+/// The number of stop times served on a date.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DayStopTimes {
+    /// The date.
+    #[serde(serialize_with = "ser_from_naive_date")]
+    pub date: Date,
+    /// The number of stop times of the vehicle journeys running on
+    /// the date.
+    pub stop_times: usize,
+}
+
+/// The bounding box of the stop points.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Extent {
+    /// The westernmost longitude.
+    pub min_lon: f64,
+    /// The southernmost latitude.
+    pub min_lat: f64,
+    /// The easternmost longitude.
+    pub max_lon: f64,
+    /// The northernmost latitude.
+    pub max_lat: f64,
+}
+
+/// The statistics of a model.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Statistics {
+    /// The number of objects of each collection, by collection name.
+    pub counts: BTreeMap<String, usize>,
+    /// The period from the first to the last date of the calendars,
+    /// `None` without dates.
+    pub validity_period: Option<Period>,
+    /// The number of dates on which at least one vehicle journey
+    /// runs.
+    pub service_days: usize,
+    /// The number of stop times served on each service day, sorted by
+    /// date.
+    pub stop_times_per_day: Vec<DayStopTimes>,
+    /// The identifiers of the physical modes of the vehicle journeys,
+    /// sorted.
+    pub modes: Vec<String>,
+    /// The bounding box of the stop points, the ones at 0,0 being
+    /// ignored, `None` without stop points.
+    pub extent: Option<Extent>,
+}
+
+fn counts(c: &Collections) -> BTreeMap<String, usize> {
+    let counts = vec![
+        ("contributors", c.contributors.len()),
+        ("datasets", c.datasets.len()),
+        ("attributions", c.attributions.len()),
+        ("networks", c.networks.len()),
+        ("commercial_modes", c.commercial_modes.len()),
+        ("lines", c.lines.len()),
+        ("line_groups", c.line_groups.len()),
+        ("routes", c.routes.len()),
+        ("vehicle_journeys", c.vehicle_journeys.len()),
+        (
+            "stop_times",
+            c.vehicle_journeys
+                .values()
+                .map(|vj| vj.stop_times.len())
+                .sum(),
+        ),
+        ("frequencies", c.frequencies.len()),
+        ("physical_modes", c.physical_modes.len()),
+        ("stop_areas", c.stop_areas.len()),
+        ("stop_points", c.stop_points.len()),
+        ("entrances", c.entrances.len()),
+        ("addresses", c.addresses.len()),
+        ("calendars", c.calendars.len()),
+        ("companies", c.companies.len()),
+        ("comments", c.comments.len()),
+        ("equipments", c.equipments.len()),
+        ("transfers", c.transfers.len()),
+        ("trip_properties", c.trip_properties.len()),
+        ("geometries", c.geometries.len()),
+        ("tickets", c.tickets.len()),
+        ("fare_zones", c.fare_zones.len()),
+        ("pathways", c.pathways.len()),
+        ("levels", c.levels.len()),
+        ("translations", c.translations.len()),
+    ];
+    counts
+        .into_iter()
+        .map(|(name, count)| (name.to_string(), count))
+        .collect()
+}
+
+fn stop_times_per_day(c: &Collections) -> Vec<DayStopTimes> {
+    let mut per_day = BTreeMap::new();
+    for vj in c.vehicle_journeys.values() {
+        if let Some(calendar) = c.calendars.get(&vj.service_id) {
+            for date in &calendar.dates {
+                *per_day.entry(*date).or_insert(0) += vj.stop_times.len();
+            }
+        }
+    }
+    per_day
+        .into_iter()
+        .map(|(date, stop_times)| DayStopTimes { date, stop_times })
+        .collect()
+}
+
+fn extent(c: &Collections) -> Option<Extent> {
+    c.stop_points
+        .values()
+        .map(|sp| &sp.coord)
+        .filter(|coord| coord.lon != 0. || coord.lat != 0.)
+        .fold(None, |extent, coord| {
+            Some(match extent {
+                None => Extent {
+                    min_lon: coord.lon,
+                    min_lat: coord.lat,
+                    max_lon: coord.lon,
+                    max_lat: coord.lat,
+                },
+                Some(e) => Extent {
+                    min_lon: e.min_lon.min(coord.lon),
+                    min_lat: e.min_lat.min(coord.lat),
+                    max_lon: e.max_lon.max(coord.lon),
+                    max_lat: e.max_lat.max(coord.lat),
+                },
+            })
+        })
+}
+
+/// Computes the statistics of the collections.
+pub fn statistics(c: &Collections) -> Statistics {
+    let stop_times_per_day = stop_times_per_day(c);
+    let modes: BTreeSet<_> = c
+        .vehicle_journeys
+        .values()
+        .map(|vj| vj.physical_mode_id.clone())
+        .collect();
+    Statistics {
+        counts: counts(c),
+        validity_period: read_utils::get_validity_period(&c.calendars).map(|vp| Period {
+            start_date: vp.start_date,
+            end_date: vp.end_date,
+        }),
+        service_days: stop_times_per_day.len(),
+        stop_times_per_day,
+        modes: modes.into_iter().collect(),
+        extent: extent(c),
+    }
+}
@@ -18,8 +18,36 @@
 //! format management.
 
 use collection::{Collection, CollectionWithId, Idx};
+use csv;
+use failure::ResultExt;
+use model::Collections;
 use objects::{StopPoint, Transfer};
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::mem;
+use std::path::Path;
+use Result;
+
+/// A pair of stop points between which no transfer must be generated.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+pub struct ExclusionRule {
+    /// Identifier of the stop point the transfer starts from.
+    pub from_stop_id: String,
+    /// Identifier of the stop point the transfer goes to.
+    pub to_stop_id: String,
+}
+
+/// Reads the exclusion rules from a csv file with the `from_stop_id`
+/// and `to_stop_id` columns.
+pub fn read_exclusion_rules<P: AsRef<Path>>(path: P) -> Result<Vec<ExclusionRule>> {
+    let path = path.as_ref();
+    info!("Reading {:?}", path);
+    let mut rdr = csv::Reader::from_path(path).with_context(ctx_from_path!(path))?;
+    let rules = rdr
+        .deserialize()
+        .collect::<::std::result::Result<_, _>>()
+        .with_context(ctx_from_path!(path))?;
+    Ok(rules)
+}
 
 fn make_transfers_set(
     transfers: &Collection<Transfer>,
@@ -42,14 +70,36 @@ fn make_transfers_set(
 /// in meters to compute the tranfer.
 ///
 /// The `walking_speed` argument is the walking speed in meters per second.
+///
+/// The `max_duration` argument, when given, is the max walking time
+/// in seconds of the generated transfers.
+///
+/// No transfer is generated for the pairs of stop points of the
+/// `exclusion_rules`.
 pub fn generates_transfers(
     transfers: &mut Collection<Transfer>,
     stop_points: &CollectionWithId<StopPoint>,
     max_distance: f64,
     walking_speed: f64,
     waiting_time: u32,
+    max_duration: Option<u32>,
+    exclusion_rules: &[ExclusionRule],
 ) {
-    let transfers_set = make_transfers_set(&transfers, &stop_points);
+    let mut transfers_set = make_transfers_set(transfers, stop_points);
+    for rule in exclusion_rules {
+        match (
+            stop_points.get_idx(&rule.from_stop_id),
+            stop_points.get_idx(&rule.to_stop_id),
+        ) {
+            (Some(from_idx), Some(to_idx)) => {
+                transfers_set.insert((from_idx, to_idx));
+            }
+            _ => warn!(
+                "exclusion rule from {} to {} ignored, stop point not found",
+                rule.from_stop_id, rule.to_stop_id
+            ),
+        }
+    }
     let sq_max_distance = max_distance * max_distance;
     for (idx1, sp1) in stop_points {
         let approx = sp1.coord.approx();
@@ -62,6 +112,9 @@ pub fn generates_transfers(
                 continue;
             }
             let transfer_time = (sq_distance.sqrt() / walking_speed) as u32;
+            if max_duration.is_some_and(|max_duration| transfer_time > max_duration) {
+                continue;
+            }
             transfers.push(Transfer {
                 from_stop_id: sp1.id.clone(),
                 to_stop_id: sp2.id.clone(),
@@ -73,40 +126,122 @@ pub fn generates_transfers(
     }
 }
 
+/// The kind of objects a `TransferRule` applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferRuleObject {
+    /// The physical modes of the vehicle journeys serving the stop
+    /// points.
+    PhysicalMode,
+    /// The networks of the lines serving the stop points.
+    Network,
+}
+
+/// A rule applying to the transfers between the stop points served
+/// by two physical modes or two networks, in both directions.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TransferRule {
+    /// The kind of objects of `from_id` and `to_id`.
+    pub object_type: TransferRuleObject,
+    /// The physical mode or network at one end of the transfers.
+    pub from_id: String,
+    /// The physical mode or network at the other end of the
+    /// transfers.
+    pub to_id: String,
+    /// The minimum transfer time in seconds, the transfers being
+    /// removed if not given.
+    pub min_transfer_time: Option<u32>,
+}
+
+/// Reads the transfer rules from a csv file with the `object_type`
+/// (`physical_mode` or `network`), `from_id`, `to_id` and
+/// `min_transfer_time` columns.
+pub fn read_transfer_rules<P: AsRef<Path>>(path: P) -> Result<Vec<TransferRule>> {
+    let path = path.as_ref();
+    info!("Reading {:?}", path);
+    let mut rdr = csv::Reader::from_path(path).with_context(ctx_from_path!(path))?;
+    let rules = rdr
+        .deserialize()
+        .collect::<::std::result::Result<_, _>>()
+        .with_context(ctx_from_path!(path))?;
+    Ok(rules)
+}
+
+// The physical modes and networks serving each stop point.
+fn stop_point_services(
+    c: &Collections,
+) -> HashMap<(Idx<StopPoint>, TransferRuleObject), BTreeSet<&str>> {
+    let mut services: HashMap<_, BTreeSet<&str>> = HashMap::new();
+    for vj in c.vehicle_journeys.values() {
+        let network_id = c
+            .routes
+            .get(&vj.route_id)
+            .and_then(|route| c.lines.get(&route.line_id))
+            .map(|line| line.network_id.as_str());
+        for st in &vj.stop_times {
+            services
+                .entry((st.stop_point_idx, TransferRuleObject::PhysicalMode))
+                .or_default()
+                .insert(vj.physical_mode_id.as_str());
+            if let Some(network_id) = network_id {
+                services
+                    .entry((st.stop_point_idx, TransferRuleObject::Network))
+                    .or_default()
+                    .insert(network_id);
+            }
+        }
+    }
+    services
+}
+
+/// Applies the rules to the transfers.  The transfers matching a rule
+/// without minimum transfer time are removed, the others have their
+/// transfer times raised to at least the minimum of the matching
+/// rules.
+pub fn apply_transfer_rules(c: &mut Collections, rules: &[TransferRule]) {
+    let transfers = mem::take(&mut c.transfers);
+    let services = stop_point_services(c);
+    let serves = |stop_id: &str, object_type: TransferRuleObject, id: &str| {
+        c.stop_points
+            .get_idx(stop_id)
+            .and_then(|idx| services.get(&(idx, object_type)))
+            .is_some_and(|ids| ids.contains(id))
+    };
+    let matches = |transfer: &Transfer, rule: &TransferRule| {
+        let (from, to) = (&transfer.from_stop_id, &transfer.to_stop_id);
+        let t = rule.object_type;
+        (serves(from, t, &rule.from_id) && serves(to, t, &rule.to_id))
+            || (serves(from, t, &rule.to_id) && serves(to, t, &rule.from_id))
+    };
+    let transfers = transfers
+        .into_iter()
+        .filter_map(|mut transfer| {
+            let matching_rules: Vec<_> = rules
+                .iter()
+                .filter(|rule| matches(&transfer, rule))
+                .collect();
+            for rule in matching_rules {
+                let min_transfer_time = rule.min_transfer_time?;
+                for time in &mut [
+                    &mut transfer.min_transfer_time,
+                    &mut transfer.real_min_transfer_time,
+                ] {
+                    **time = Some(time.map_or(min_transfer_time, |t| t.max(min_transfer_time)));
+                }
+            }
+            Some(transfer)
+        })
+        .collect();
+    c.transfers = Collection::new(transfers);
+}
+
 #[cfg(test)]
 mod tests {
     use collection::{Collection, CollectionWithId};
     use objects::*;
 
-    #[test]
-    //                    206m
-    // sp_1 *--------------------------------* sp_3
-    //       \                        ______/
-    //        \                  ____/
-    //   65m   \           _____/   146m
-    //          \    _____/
-    //           \__/
-    //           sp_2
-    //
-    fn test_generates_transfers() {
-        let mut transfers = Collection::new(vec![
-            Transfer {
-                from_stop_id: "sp_1".to_string(),
-                to_stop_id: "sp_2".to_string(),
-                min_transfer_time: Some(50),
-                real_min_transfer_time: Some(60),
-                equipment_id: None,
-            },
-            Transfer {
-                from_stop_id: "sp_1".to_string(),
-                to_stop_id: "sp_3".to_string(),
-                min_transfer_time: Some(200),
-                real_min_transfer_time: Some(210),
-                equipment_id: None,
-            },
-        ]);
-
-        let stop_points = CollectionWithId::new(vec![
+    fn make_stop_points() -> CollectionWithId<StopPoint> {
+        CollectionWithId::new(vec![
             StopPoint {
                 id: "sp_1".to_string(),
                 name: "sp_name_1".to_string(),
@@ -121,8 +256,9 @@ mod tests {
                 timezone: None,
                 geometry_id: None,
                 equipment_id: None,
-                stop_area_id: "sa_1".to_string(),
+                stop_area_id: "sa_1".into(),
                 fare_zone_id: None,
+                address_id: None,
             },
             StopPoint {
                 id: "sp_2".to_string(),
@@ -138,8 +274,9 @@ mod tests {
                 timezone: None,
                 geometry_id: None,
                 equipment_id: None,
-                stop_area_id: "sa_1".to_string(),
+                stop_area_id: "sa_1".into(),
                 fare_zone_id: None,
+                address_id: None,
             },
             StopPoint {
                 id: "sp_3".to_string(),
@@ -155,12 +292,44 @@ mod tests {
                 timezone: None,
                 geometry_id: None,
                 equipment_id: None,
-                stop_area_id: "sa_1".to_string(),
+                stop_area_id: "sa_1".into(),
                 fare_zone_id: None,
+                address_id: None,
             },
-        ]).unwrap();
+        ]).unwrap()
+    }
 
-        super::generates_transfers(&mut transfers, &stop_points, 100.0, 0.785, 120);
+    #[test]
+    //                    206m
+    // sp_1 *--------------------------------* sp_3
+    //       \                        ______/
+    //        \                  ____/
+    //   65m   \           _____/   146m
+    //          \    _____/
+    //           \__/
+    //           sp_2
+    //
+    fn test_generates_transfers() {
+        let mut transfers = Collection::new(vec![
+            Transfer {
+                from_stop_id: "sp_1".to_string(),
+                to_stop_id: "sp_2".to_string(),
+                min_transfer_time: Some(50),
+                real_min_transfer_time: Some(60),
+                equipment_id: None,
+            },
+            Transfer {
+                from_stop_id: "sp_1".to_string(),
+                to_stop_id: "sp_3".to_string(),
+                min_transfer_time: Some(200),
+                real_min_transfer_time: Some(210),
+                equipment_id: None,
+            },
+        ]);
+
+        let stop_points = make_stop_points();
+
+        super::generates_transfers(&mut transfers, &stop_points, 100.0, 0.785, 120, None, &[]);
         let transfers = transfers.values().collect::<Vec<_>>();
 
         //we keep the 2 first existing transfers
@@ -215,4 +384,113 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_generates_transfers_with_exclusion_rules() {
+        let mut transfers = Collection::default();
+        let stop_points = make_stop_points();
+        let exclusion_rules = vec![
+            super::ExclusionRule {
+                from_stop_id: "sp_1".to_string(),
+                to_stop_id: "sp_2".to_string(),
+            },
+            super::ExclusionRule {
+                from_stop_id: "sp_1".to_string(),
+                to_stop_id: "unknown".to_string(),
+            },
+        ];
+
+        super::generates_transfers(
+            &mut transfers,
+            &stop_points,
+            100.0,
+            0.785,
+            120,
+            None,
+            &exclusion_rules,
+        );
+        let pairs: Vec<_> = transfers
+            .values()
+            .map(|t| (t.from_stop_id.as_str(), t.to_stop_id.as_str()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("sp_1", "sp_1"),
+                ("sp_2", "sp_1"),
+                ("sp_2", "sp_2"),
+                ("sp_3", "sp_3"),
+            ],
+            pairs
+        );
+    }
+
+    #[test]
+    fn generates_transfers_within_max_duration() {
+        let mut transfers = Collection::new(vec![]);
+        let stop_points = make_stop_points();
+
+        super::generates_transfers(&mut transfers, &stop_points, 100.0, 0.785, 120, Some(60), &[]);
+        let pairs: Vec<_> = transfers
+            .values()
+            .map(|t| (t.from_stop_id.as_str(), t.to_stop_id.as_str()))
+            .collect();
+        assert_eq!(
+            vec![("sp_1", "sp_1"), ("sp_2", "sp_2"), ("sp_3", "sp_3")],
+            pairs
+        );
+    }
+
+    #[test]
+    fn apply_transfer_rules() {
+        let mut collections = ::ntfs::read("fixtures/minimal_ntfs/")
+            .unwrap()
+            .into_collections();
+        let transfer = |from: &str, to: &str| Transfer {
+            from_stop_id: from.to_string(),
+            to_stop_id: to.to_string(),
+            min_transfer_time: Some(60),
+            real_min_transfer_time: Some(180),
+            equipment_id: None,
+        };
+        collections.transfers = Collection::new(vec![
+            transfer("GDLM", "NATR"),
+            transfer("GDLB", "MTPB"),
+            transfer("CHAM", "CDGM"),
+        ]);
+        let rules = vec![
+            super::TransferRule {
+                object_type: super::TransferRuleObject::PhysicalMode,
+                from_id: "RapidTransit".to_string(),
+                to_id: "Metro".to_string(),
+                min_transfer_time: Some(120),
+            },
+            super::TransferRule {
+                object_type: super::TransferRuleObject::PhysicalMode,
+                from_id: "Bus".to_string(),
+                to_id: "Bus".to_string(),
+                min_transfer_time: None,
+            },
+        ];
+        super::apply_transfer_rules(&mut collections, &rules);
+
+        let transfers: Vec<_> = collections
+            .transfers
+            .values()
+            .map(|t| {
+                (
+                    t.from_stop_id.as_str(),
+                    t.to_stop_id.as_str(),
+                    t.min_transfer_time,
+                    t.real_min_transfer_time,
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                ("GDLM", "NATR", Some(120), Some(180)),
+                ("CHAM", "CDGM", Some(60), Some(180)),
+            ],
+            transfers
+        );
+    }
 }
@@ -0,0 +1,170 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! [TransXChange](https://www.gov.uk/government/collections/transxchange)
+//! format management, the UK standard for the exchange of the bus
+//! routes and timetables.
+
+mod read;
+
+use self::read::TransXChangeReader;
+use collection::CollectionWithId;
+use failure::ResultExt;
+use model::Model;
+use read_utils::{self, FileHandler, PathFileHandler, ZipHandler};
+use std::fs;
+use std::path::Path;
+use Result;
+
+// Whether the extension of `path` is `xml`, whatever its case.
+fn is_xml(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("xml"))
+}
+
+// Reads the xml files given by the file handler, by the order of their
+// names.
+fn read_files<H>(reader: &mut TransXChangeReader, file_handler: &mut H) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    for name in file_handler.file_names()? {
+        if is_xml(Path::new(&name)) {
+            let (file, path) = file_handler.get_file(&name)?;
+            reader.read_file(file).with_context(ctx_from_path!(path))?;
+        } else {
+            info!("TransXChange read : skipping file {:?}", name);
+        }
+    }
+    Ok(())
+}
+
+fn build_model<P: AsRef<Path>>(
+    read_files: impl FnOnce(&mut TransXChangeReader) -> Result<()>,
+    config_path: Option<P>,
+    prefix: Option<String>,
+) -> Result<Model> {
+    let (contributor, mut dataset) = read_utils::read_config(config_path)?;
+    let mut reader = TransXChangeReader::new(&dataset.id);
+    read_files(&mut reader)?;
+    let mut collections = reader.collections;
+
+    let vp = read_utils::get_validity_period(&collections.calendars)
+        .ok_or_else(|| format_err!("No valid calendar in TransXChange data"))?;
+    dataset.start_date = vp.start_date;
+    dataset.end_date = vp.end_date;
+    dataset.system = Some("TransXChange".to_string());
+    collections.contributors = CollectionWithId::new(vec![contributor])?;
+    collections.datasets = CollectionWithId::new(vec![dataset])?;
+    read_utils::add_id_mappings(&mut collections);
+    if let Some(prefix) = prefix {
+        read_utils::add_prefix(prefix, &mut collections)?;
+    }
+    Model::new(collections)
+}
+
+/// Imports a `Model` from the
+/// [TransXChange](https://www.gov.uk/government/collections/transxchange)
+/// files of the `path` directory, or from the single xml file `path`.
+/// The files of a directory are read by the order of their names.
+///
+/// The NaPTAN stop points referenced by the files become stop points,
+/// each one in its own stop area, the services become lines and their
+/// operators companies and networks.  The bank holidays of the
+/// operating profiles are not handled.
+///
+/// The `config_path` argument allows you to give a path to a file
+/// containing a json representing the contributor and dataset used
+/// for these files. If not given, default values will be created.
+///
+/// The `prefix` argument is a string that will be prepended to every
+/// identifiers, allowing to namespace the dataset. By default, no
+/// prefix will be added to the identifiers.
+pub fn read_from_path<P: AsRef<Path>>(
+    path: P,
+    config_path: Option<P>,
+    prefix: Option<String>,
+) -> Result<Model> {
+    let path = path.as_ref();
+    if path.is_dir() {
+        return read_from_handler(&mut PathFileHandler::new(path), config_path, prefix);
+    }
+    info!("Reading TransXChange data from {:?}", path);
+    build_model(
+        |reader| {
+            if is_xml(path) {
+                let file = fs::File::open(path).with_context(ctx_from_path!(path))?;
+                reader.read_file(file).with_context(ctx_from_path!(path))?;
+            }
+            Ok(())
+        },
+        config_path,
+        prefix,
+    )
+}
+
+/// Imports a `Model` from a zip archive of
+/// [TransXChange](https://www.gov.uk/government/collections/transxchange)
+/// files, as `read_from_path` does for a directory.
+pub fn read_from_zip<P: AsRef<Path>>(
+    path: P,
+    config_path: Option<P>,
+    prefix: Option<String>,
+) -> Result<Model> {
+    let path = path.as_ref();
+    let file = fs::File::open(path).with_context(ctx_from_path!(path))?;
+    let mut file_handler = ZipHandler::new(file, path)?;
+    read_from_handler(&mut file_handler, config_path, prefix)
+}
+
+/// Imports a `Model` from a zip archive of
+/// [TransXChange](https://www.gov.uk/government/collections/transxchange)
+/// files downloaded from `url`, as `read_from_zip` does for a local
+/// archive.
+pub fn read_from_url<P: AsRef<Path>>(
+    url: &str,
+    config_path: Option<P>,
+    prefix: Option<String>,
+) -> Result<Model> {
+    let archive = read_utils::read_url(url)?;
+    let mut file_handler = ZipHandler::new(archive, url)?;
+    read_from_handler(&mut file_handler, config_path, prefix)
+}
+
+/// Imports a `Model` from the
+/// [TransXChange](https://www.gov.uk/government/collections/transxchange)
+/// xml files given by the file handler, by the order of their names,
+/// as `read_from_path` does for a directory.
+pub fn read_from_handler<H, P>(
+    file_handler: &mut H,
+    config_path: Option<P>,
+    prefix: Option<String>,
+) -> Result<Model>
+where
+    for<'a> &'a mut H: FileHandler,
+    P: AsRef<Path>,
+{
+    info!(
+        "Reading TransXChange data from {}",
+        file_handler.source_name()
+    );
+    build_model(
+        |reader| read_files(reader, file_handler),
+        config_path,
+        prefix,
+    )
+}
@@ -0,0 +1,778 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+use chrono::{self, Datelike};
+use failure::ResultExt;
+use model::Collections;
+use objects::{self, CommentLinksT, Coord, Date, KeysValues, StopTime, Time};
+use std::collections::{BTreeSet, HashMap};
+use std::io::Read;
+use Result;
+
+extern crate minidom;
+use self::minidom::Element;
+
+// A stop of a journey pattern timing link.
+struct LinkStop {
+    stop_point_ref: String,
+    activity: Option<String>,
+    wait_time: u32,
+}
+
+struct TimingLink {
+    from: LinkStop,
+    to: LinkStop,
+    run_time: u32,
+}
+
+struct JourneyPattern {
+    direction: String,
+    section_refs: Vec<String>,
+}
+
+// The elements of a service used by its vehicle journeys.
+struct Service<'a> {
+    node: &'a Element,
+    period: (Date, Date),
+    company_id: String,
+    physical_mode_id: String,
+    // the navitia ids of the lines of the service, by their id
+    line_ids: HashMap<&'a str, String>,
+}
+
+// The text of the child `name` of `node`.
+fn child_text(node: &Element, name: &str, ns: &str) -> Option<String> {
+    node.get_child(name, ns)
+        .map(|n| n.text().trim().to_string())
+}
+
+// The children `name` of the list `list` of `node`, as the
+// `Operator`s of `Operators`.
+fn list_items<'a>(
+    node: &'a Element,
+    list: &str,
+    name: &'a str,
+    ns: &str,
+) -> impl Iterator<Item = &'a Element> {
+    node.get_child(list, ns)
+        .into_iter()
+        .flat_map(|l| l.children())
+        .filter(move |n| n.name() == name)
+}
+
+fn required_text(node: &Element, name: &str, ns: &str) -> Result<String> {
+    child_text(node, name, ns)
+        .ok_or_else(|| format_err!("A '{}' node doesn't have a '{}'", node.name(), name))
+}
+
+fn parse_date(value: &str) -> Result<Date> {
+    Ok(Date::parse_from_str(value, "%Y-%m-%d")
+        .with_context(|_| format!("invalid date {:?}", value))?)
+}
+
+// The number of seconds of an ISO 8601 duration, as `PT1H2M30S`.
+fn parse_duration(value: &str) -> Result<u32> {
+    let invalid = || format_err!("invalid duration {:?}", value);
+    let time = value.strip_prefix("PT").ok_or_else(invalid)?;
+    let mut seconds = 0;
+    let mut number = String::new();
+    for c in time.chars() {
+        let unit = match c {
+            'H' => 3600,
+            'M' => 60,
+            'S' => 1,
+            _ if c.is_ascii_digit() => {
+                number.push(c);
+                continue;
+            }
+            _ => return Err(invalid()),
+        };
+        let n: u32 = number.parse().map_err(|_| invalid())?;
+        seconds += n * unit;
+        number.clear();
+    }
+    if !number.is_empty() {
+        return Err(invalid());
+    }
+    Ok(seconds)
+}
+
+fn seconds(time: Time) -> u32 {
+    time.hours() * 3600 + time.minutes() * 60 + time.seconds()
+}
+
+// The days of the `DaysOfWeek` of an `OperatingProfile`, as
+// `<MondayToFriday/>`.
+fn days_of_week(days_of_week: &Element) -> Vec<chrono::Weekday> {
+    use self::chrono::Weekday::*;
+    let all = all_days();
+    let mut days: Vec<chrono::Weekday> = days_of_week
+        .children()
+        .flat_map(|day| match day.name() {
+            "Monday" => vec![Mon],
+            "Tuesday" => vec![Tue],
+            "Wednesday" => vec![Wed],
+            "Thursday" => vec![Thu],
+            "Friday" => vec![Fri],
+            "Saturday" => vec![Sat],
+            "Sunday" => vec![Sun],
+            "MondayToFriday" => vec![Mon, Tue, Wed, Thu, Fri],
+            "MondayToSaturday" => vec![Mon, Tue, Wed, Thu, Fri, Sat],
+            "MondayToSunday" => all.clone(),
+            "Weekend" => vec![Sat, Sun],
+            name if name.starts_with("Not") => {
+                let excluded = match &name[3..] {
+                    "Monday" => Mon,
+                    "Tuesday" => Tue,
+                    "Wednesday" => Wed,
+                    "Thursday" => Thu,
+                    "Friday" => Fri,
+                    "Saturday" => Sat,
+                    _ => Sun,
+                };
+                all.iter().cloned().filter(|d| *d != excluded).collect()
+            }
+            _ => vec![],
+        })
+        .collect();
+    days.sort_by_key(|d| d.num_days_from_monday());
+    days.dedup();
+    days
+}
+
+// The dates from `from` to `to` on one of `days`.
+fn dates_between(from: Date, to: Date, days: &[chrono::Weekday]) -> BTreeSet<Date> {
+    let duration = to - from;
+    (0..duration.num_days() + 1)
+        .map(|i| from + chrono::Duration::days(i))
+        .filter(|d| days.contains(&d.weekday()))
+        .collect()
+}
+
+// The dates of the `DateRange`s of the `list` of the special days of
+// `profile`.
+fn special_days(profile: &Element, list: &str, ns: &str) -> Result<BTreeSet<Date>> {
+    let mut dates = BTreeSet::new();
+    let special_days = match profile.get_child("SpecialDaysOperation", ns) {
+        Some(special_days) => special_days,
+        None => return Ok(dates),
+    };
+    for range in list_items(special_days, list, "DateRange", ns) {
+        let start_date = parse_date(&required_text(range, "StartDate", ns)?)?;
+        let end_date = match child_text(range, "EndDate", ns) {
+            Some(end_date) => parse_date(&end_date)?,
+            None => start_date,
+        };
+        dates.extend(dates_between(start_date, end_date, &all_days()));
+    }
+    Ok(dates)
+}
+
+fn all_days() -> Vec<chrono::Weekday> {
+    use self::chrono::Weekday::*;
+    vec![Mon, Tue, Wed, Thu, Fri, Sat, Sun]
+}
+
+// The dates of `period` on which an `OperatingProfile` runs, every day
+// without profile.  The bank holidays are not handled.
+fn operating_dates(
+    profile: Option<&Element>,
+    period: (Date, Date),
+    ns: &str,
+) -> Result<BTreeSet<Date>> {
+    let profile = match profile {
+        Some(profile) => profile,
+        None => return Ok(dates_between(period.0, period.1, &all_days())),
+    };
+    let days = profile
+        .get_child("RegularDayType", ns)
+        .and_then(|day_type| day_type.get_child("DaysOfWeek", ns))
+        .map(days_of_week)
+        .unwrap_or_default();
+    let mut dates = dates_between(period.0, period.1, &days);
+    dates.extend(
+        special_days(profile, "DaysOfOperation", ns)?
+            .into_iter()
+            .filter(|d| *d >= period.0 && *d <= period.1),
+    );
+    for date in special_days(profile, "DaysOfNonOperation", ns)? {
+        dates.remove(&date);
+    }
+    Ok(dates)
+}
+
+// The physical and commercial modes of a TransXChange `Mode`.
+fn modes(mode: &str) -> (objects::PhysicalMode, objects::CommercialMode) {
+    let (id, name) = match mode {
+        "air" => ("Air", "Airplane"),
+        "coach" => ("Coach", "Coach"),
+        "ferry" => ("Ferry", "Ferry"),
+        "metro" | "underground" => ("Metro", "Metro"),
+        "rail" => ("Train", "Train"),
+        "tram" => ("Tramway", "Tramway"),
+        _ => ("Bus", "Bus"),
+    };
+    (
+        objects::PhysicalMode {
+            id: id.to_string(),
+            name: name.to_string(),
+            co2_emission: None,
+        },
+        objects::CommercialMode {
+            id: id.to_string(),
+            name: name.to_string(),
+        },
+    )
+}
+
+fn link_stop(node: &Element, ns: &str) -> Result<LinkStop> {
+    Ok(LinkStop {
+        stop_point_ref: required_text(node, "StopPointRef", ns)?,
+        activity: child_text(node, "Activity", ns),
+        wait_time: match child_text(node, "WaitTime", ns) {
+            Some(wait_time) => parse_duration(&wait_time)?,
+            None => 0,
+        },
+    })
+}
+
+pub struct TransXChangeReader {
+    dataset_id: String,
+    calendars: HashMap<BTreeSet<Date>, String>,
+    pub collections: Collections,
+}
+
+impl TransXChangeReader {
+    pub fn new(dataset_id: &str) -> Self {
+        TransXChangeReader {
+            dataset_id: dataset_id.to_string(),
+            calendars: HashMap::new(),
+            collections: Collections::default(),
+        }
+    }
+
+    pub fn read_file<R: Read>(&mut self, mut file: R) -> Result<()> {
+        let mut file_content = String::new();
+        file.read_to_string(&mut file_content)?;
+        let root: Element = file_content.parse()?;
+        let ns = root.ns().unwrap_or_default();
+        let ns = ns.as_str();
+
+        let operators = self.read_operators(&root, ns)?;
+        self.read_stop_points(&root, ns)?;
+        let sections = list_items(&root, "JourneyPatternSections", "JourneyPatternSection", ns)
+            .map(|section| {
+                let id = section
+                    .attr("id")
+                    .ok_or_else(|| format_err!("A 'JourneyPatternSection' doesn't have an 'id'"))?;
+                let links = section
+                    .children()
+                    .filter(|link| link.name() == "JourneyPatternTimingLink")
+                    .map(|link| {
+                        let child = |name| {
+                            link.get_child(name, ns).ok_or_else(|| {
+                                format_err!(
+                                    "A 'JourneyPatternTimingLink' doesn't have a '{}'",
+                                    name
+                                )
+                            })
+                        };
+                        Ok(TimingLink {
+                            from: link_stop(child("From")?, ns)?,
+                            to: link_stop(child("To")?, ns)?,
+                            run_time: parse_duration(&required_text(link, "RunTime", ns)?)?,
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok((id, links))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+
+        let mut services = HashMap::new();
+        let mut journey_patterns = HashMap::new();
+        for node in list_items(&root, "Services", "Service", ns) {
+            let service_code = required_text(node, "ServiceCode", ns)?;
+            let service = self
+                .read_service(node, &operators, ns)
+                .with_context(|_| format!("Reading Service {:?}", service_code))?;
+            if let Some(standard_service) = node.get_child("StandardService", ns) {
+                for pattern in standard_service
+                    .children()
+                    .filter(|p| p.name() == "JourneyPattern")
+                {
+                    let id = pattern
+                        .attr("id")
+                        .ok_or_else(|| format_err!("A 'JourneyPattern' doesn't have an 'id'"))?;
+                    let journey_pattern = JourneyPattern {
+                        direction: child_text(pattern, "Direction", ns)
+                            .unwrap_or_else(|| "outbound".to_string()),
+                        section_refs: pattern
+                            .children()
+                            .filter(|r| r.name() == "JourneyPatternSectionRefs")
+                            .map(|r| r.text().trim().to_string())
+                            .collect(),
+                    };
+                    journey_patterns.insert(id, journey_pattern);
+                }
+            }
+            services.insert(service_code, service);
+        }
+
+        let vehicle_journeys: Vec<_> =
+            list_items(&root, "VehicleJourneys", "VehicleJourney", ns).collect();
+        let pattern_of_journey: HashMap<String, String> = vehicle_journeys
+            .iter()
+            .filter_map(|vj| {
+                let code = child_text(vj, "VehicleJourneyCode", ns)?;
+                let pattern = child_text(vj, "JourneyPatternRef", ns)?;
+                Some((code, pattern))
+            })
+            .collect();
+        for vj in vehicle_journeys {
+            let code = required_text(vj, "VehicleJourneyCode", ns)?;
+            let pattern_id = child_text(vj, "JourneyPatternRef", ns)
+                .or_else(|| {
+                    child_text(vj, "VehicleJourneyRef", ns)
+                        .and_then(|r| pattern_of_journey.get(&r).cloned())
+                })
+                .ok_or_else(|| format_err!("The VehicleJourney {:?} has no pattern", code))?;
+            let pattern = journey_patterns
+                .get(pattern_id.as_str())
+                .ok_or_else(|| format_err!("The JourneyPattern {:?} is not found", pattern_id))?;
+            let links = pattern
+                .section_refs
+                .iter()
+                .map(|r| {
+                    sections.get(r.as_str()).ok_or_else(|| {
+                        format_err!("The JourneyPatternSection {:?} is not found", r)
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let links: Vec<&TimingLink> = links.into_iter().flat_map(|l| l.iter()).collect();
+            self.read_vehicle_journey(vj, &services, &pattern.direction, &links, &operators, ns)
+                .with_context(|_| format!("Reading VehicleJourney {:?}", code))?;
+        }
+        Ok(())
+    }
+
+    // Reads the operators as companies and networks, and returns the
+    // navitia ids of the operators by their id.
+    fn read_operators<'a>(
+        &mut self,
+        root: &'a Element,
+        ns: &str,
+    ) -> Result<HashMap<&'a str, String>> {
+        let mut operators = HashMap::new();
+        for operator in list_items(root, "Operators", "Operator", ns) {
+            let id = operator
+                .attr("id")
+                .ok_or_else(|| format_err!("An 'Operator' node doesn't have an 'id' property."))?;
+            let company_id = child_text(operator, "NationalOperatorCode", ns)
+                .or_else(|| child_text(operator, "OperatorCode", ns))
+                .unwrap_or_else(|| id.to_string());
+            let name = child_text(operator, "OperatorShortName", ns)
+                .or_else(|| child_text(operator, "TradingName", ns))
+                .or_else(|| child_text(operator, "OperatorNameOnLicence", ns))
+                .unwrap_or_else(|| company_id.clone());
+            if self.collections.companies.get(&company_id).is_none() {
+                self.collections.companies.push(objects::Company {
+                    id: company_id.clone(),
+                    name: name.clone(),
+                    ..Default::default()
+                })?;
+            }
+            if self.collections.networks.get(&company_id).is_none() {
+                self.collections.networks.push(objects::Network {
+                    id: company_id.clone(),
+                    name,
+                    url: None,
+                    codes: KeysValues::default(),
+                    timezone: Some("Europe/London".to_string()),
+                    lang: None,
+                    phone: None,
+                    address: None,
+                    sort_order: None,
+                })?;
+            }
+            operators.insert(id, company_id);
+        }
+        Ok(operators)
+    }
+
+    // Reads the NaPTAN stop points, given by reference or fully
+    // described, each one in its own stop area.
+    fn read_stop_points(&mut self, root: &Element, ns: &str) -> Result<()> {
+        let stops = match root.get_child("StopPoints", ns) {
+            Some(stops) => stops,
+            None => return Ok(()),
+        };
+        for stop in stops.children() {
+            let (id, name, location) = match stop.name() {
+                "AnnotatedStopPointRef" => (
+                    required_text(stop, "StopPointRef", ns)?,
+                    child_text(stop, "CommonName", ns),
+                    stop.get_child("Location", ns),
+                ),
+                "StopPoint" => (
+                    required_text(stop, "AtcoCode", ns)?,
+                    stop.get_child("Descriptor", ns)
+                        .and_then(|d| child_text(d, "CommonName", ns)),
+                    stop.get_child("Place", ns)
+                        .and_then(|p| p.get_child("Location", ns)),
+                ),
+                _ => continue,
+            };
+            if self.collections.stop_points.get(&id).is_some() {
+                continue;
+            }
+            let location = location.map(|l| l.get_child("Translation", ns).unwrap_or(l));
+            let coordinate = |location: &Element, name: &str| -> Result<f64> {
+                let value = required_text(location, name, ns)?;
+                Ok(value
+                    .parse::<f64>()
+                    .with_context(|_| format!("invalid {} {:?}", name, value))?)
+            };
+            let coord = match location {
+                Some(location) if location.get_child("Longitude", ns).is_some() => Coord {
+                    lon: coordinate(location, "Longitude")?,
+                    lat: coordinate(location, "Latitude")?,
+                },
+                _ => {
+                    warn!("The stop point {:?} has no coordinates", id);
+                    Coord { lon: 0., lat: 0. }
+                }
+            };
+            let name = name.unwrap_or_default();
+            let stop_area_id = format!("Navitia:{}", id);
+            self.collections.stop_areas.push(objects::StopArea {
+                id: stop_area_id.clone(),
+                name: name.clone(),
+                codes: KeysValues::default(),
+                object_properties: KeysValues::default(),
+                comment_links: CommentLinksT::default(),
+                visible: true,
+                coord,
+                timezone: None,
+                geometry_id: None,
+                equipment_id: None,
+            })?;
+            self.collections.stop_points.push(objects::StopPoint {
+                id,
+                name,
+                codes: KeysValues::default(),
+                object_properties: KeysValues::default(),
+                comment_links: CommentLinksT::default(),
+                visible: true,
+                coord,
+                stop_area_id: stop_area_id.into(),
+                timezone: None,
+                geometry_id: None,
+                equipment_id: None,
+                fare_zone_id: None,
+                address_id: None,
+            })?;
+        }
+        Ok(())
+    }
+
+    // Reads the lines and modes of a service.
+    fn read_service<'a>(
+        &mut self,
+        service: &'a Element,
+        operators: &HashMap<&str, String>,
+        ns: &str,
+    ) -> Result<Service<'a>> {
+        let service_code = required_text(service, "ServiceCode", ns)?;
+        let period = service
+            .get_child("OperatingPeriod", ns)
+            .ok_or_else(|| format_err!("The service doesn't have an 'OperatingPeriod'"))?;
+        let start_date = parse_date(&required_text(period, "StartDate", ns)?)?;
+        // an open-ended service is read for a year
+        let end_date = match child_text(period, "EndDate", ns) {
+            Some(end_date) => parse_date(&end_date)?,
+            None => start_date + chrono::Duration::days(365),
+        };
+        let company_id = child_text(service, "RegisteredOperatorRef", ns)
+            .and_then(|o| operators.get(o.as_str()).cloned())
+            .or_else(|| operators.values().next().cloned())
+            .ok_or_else(|| format_err!("The service doesn't have any operator"))?;
+        let (physical_mode, commercial_mode) =
+            modes(&child_text(service, "Mode", ns).unwrap_or_default());
+        let physical_mode_id = physical_mode.id.clone();
+        let commercial_mode_id = commercial_mode.id.clone();
+        if self
+            .collections
+            .physical_modes
+            .get(&physical_mode.id)
+            .is_none()
+        {
+            self.collections.physical_modes.push(physical_mode)?;
+        }
+        if self
+            .collections
+            .commercial_modes
+            .get(&commercial_mode.id)
+            .is_none()
+        {
+            self.collections.commercial_modes.push(commercial_mode)?;
+        }
+        let mut line_ids = HashMap::new();
+        for line in list_items(service, "Lines", "Line", ns) {
+            let id = line
+                .attr("id")
+                .ok_or_else(|| format_err!("A 'Line' node doesn't have an 'id' property."))?;
+            let line_id = format!("{}:{}", service_code, id);
+            let code = child_text(line, "LineName", ns);
+            self.collections.lines.push(objects::Line {
+                id: line_id.clone(),
+                code: code.clone(),
+                codes: KeysValues::default(),
+                object_properties: KeysValues::default(),
+                comment_links: CommentLinksT::default(),
+                name: child_text(service, "Description", ns)
+                    .or(code)
+                    .unwrap_or_default(),
+                forward_name: None,
+                forward_direction: None,
+                backward_name: None,
+                backward_direction: None,
+                color: None,
+                text_color: None,
+                sort_order: None,
+                network_id: company_id.clone().into(),
+                commercial_mode_id: commercial_mode_id.clone(),
+                geometry_id: None,
+                opening_time: None,
+                closing_time: None,
+            })?;
+            line_ids.insert(id, line_id);
+        }
+        Ok(Service {
+            node: service,
+            period: (start_date, end_date),
+            company_id,
+            physical_mode_id,
+            line_ids,
+        })
+    }
+
+    fn read_vehicle_journey(
+        &mut self,
+        vj: &Element,
+        services: &HashMap<String, Service>,
+        direction: &str,
+        links: &[&TimingLink],
+        operators: &HashMap<&str, String>,
+        ns: &str,
+    ) -> Result<()> {
+        let service_ref = required_text(vj, "ServiceRef", ns)?;
+        let service = services
+            .get(&service_ref)
+            .ok_or_else(|| format_err!("The Service {:?} is not found", service_ref))?;
+        let line_ref = required_text(vj, "LineRef", ns)?;
+        let line_id = service
+            .line_ids
+            .get(line_ref.as_str())
+            .ok_or_else(|| format_err!("The Line {:?} is not found", line_ref))?;
+        let profile = vj
+            .get_child("OperatingProfile", ns)
+            .or_else(|| service.node.get_child("OperatingProfile", ns));
+        let dates = operating_dates(profile, service.period, ns)?;
+        if dates.is_empty() {
+            warn!(
+                "A VehicleJourney of {:?} never runs, it is ignored",
+                line_id
+            );
+            return Ok(());
+        }
+        let service_id = self.calendar_id(dates)?;
+        let route_id = self.route_id(line_id, direction, service.node, ns)?;
+
+        let mut stop_times: Vec<StopTime> = vec![];
+        let mut time = seconds(
+            required_text(vj, "DepartureTime", ns)?
+                .parse::<Time>()
+                .with_context(|_| "invalid DepartureTime")?,
+        );
+        for (i, link) in links.iter().enumerate() {
+            if i == 0 {
+                let departure = time + link.from.wait_time;
+                stop_times.push(self.stop_time(&link.from, 0, time, departure)?);
+            } else if let Some(last) = stop_times.last_mut() {
+                // the wait time at the start of a link delays the
+                // departure from the end of the previous one
+                last.departure_time = Time::new(0, 0, time + link.from.wait_time);
+            }
+            time += link.from.wait_time + link.run_time;
+            let sequence = stop_times.len() as u32;
+            let departure = time + link.to.wait_time;
+            stop_times.push(self.stop_time(&link.to, sequence, time, departure)?);
+            time = departure;
+        }
+        let company_id = child_text(vj, "OperatorRef", ns)
+            .and_then(|o| operators.get(o.as_str()).cloned())
+            .unwrap_or_else(|| service.company_id.clone());
+        self.collections
+            .vehicle_journeys
+            .push(objects::VehicleJourney {
+                id: format!(
+                    "{}:{}",
+                    service_ref,
+                    required_text(vj, "VehicleJourneyCode", ns)?
+                ),
+                route_id: route_id.into(),
+                physical_mode_id: service.physical_mode_id.clone(),
+                dataset_id: self.dataset_id.clone(),
+                service_id: service_id.into(),
+                company_id,
+                stop_times,
+                ..Default::default()
+            })?;
+        Ok(())
+    }
+
+    fn stop_time(
+        &self,
+        stop: &LinkStop,
+        sequence: u32,
+        arrival: u32,
+        departure: u32,
+    ) -> Result<StopTime> {
+        let stop_point_idx = self
+            .collections
+            .stop_points
+            .get_idx(&stop.stop_point_ref)
+            .ok_or_else(|| format_err!("The stop point {:?} is not found", stop.stop_point_ref))?;
+        let activity = stop.activity.as_deref();
+        Ok(StopTime {
+            stop_point_idx,
+            sequence,
+            arrival_time: Time::new(0, 0, arrival),
+            departure_time: Time::new(0, 0, departure),
+            boarding_duration: 0,
+            alighting_duration: 0,
+            pickup_type: match activity {
+                Some("setDown") | Some("pass") => 1,
+                _ => 0,
+            },
+            drop_off_type: match activity {
+                Some("pickUp") | Some("pass") => 1,
+                _ => 0,
+            },
+            datetime_estimated: false,
+            local_zone_id: None,
+            extras: None,
+        })
+    }
+
+    // The route of the line in the direction, created if needed.
+    fn route_id(
+        &mut self,
+        line_id: &str,
+        direction: &str,
+        service: &Element,
+        ns: &str,
+    ) -> Result<String> {
+        let route_id = format!("{}:{}", line_id, direction);
+        if self.collections.routes.get(&route_id).is_none() {
+            let standard_service = service.get_child("StandardService", ns);
+            let destination = if direction == "inbound" {
+                "Origin"
+            } else {
+                "Destination"
+            };
+            self.collections.routes.push(objects::Route {
+                id: route_id.clone(),
+                name: standard_service
+                    .and_then(|s| child_text(s, destination, ns))
+                    .unwrap_or_default(),
+                direction_type: Some(direction.to_string()),
+                codes: KeysValues::default(),
+                object_properties: KeysValues::default(),
+                comment_links: CommentLinksT::default(),
+                line_id: line_id.into(),
+                geometry_id: None,
+                destination_id: None,
+                continuous_pickup: None,
+                continuous_drop_off: None,
+            })?;
+        }
+        Ok(route_id)
+    }
+
+    // The calendar of the dates, shared by the vehicle journeys
+    // running on the same dates.
+    fn calendar_id(&mut self, dates: BTreeSet<Date>) -> Result<String> {
+        if let Some(id) = self.calendars.get(&dates) {
+            return Ok(id.clone());
+        }
+        let id = self.calendars.len().to_string();
+        self.collections.calendars.push(objects::Calendar {
+            id: id.clone(),
+            dates: dates.clone(),
+        })?;
+        self.calendars.insert(dates, id.clone());
+        Ok(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        assert_eq!(0, parse_duration("PT0S").unwrap());
+        assert_eq!(390, parse_duration("PT6M30S").unwrap());
+        assert_eq!(3723, parse_duration("PT1H2M3S").unwrap());
+        assert!(parse_duration("P1D").is_err());
+        assert!(parse_duration("PT5").is_err());
+    }
+
+    #[test]
+    fn operating_profile_dates() {
+        let profile: Element = r#"<OperatingProfile xmlns="ns">
+            <RegularDayType><DaysOfWeek><Weekend/></DaysOfWeek></RegularDayType>
+            <SpecialDaysOperation>
+                <DaysOfOperation><DateRange>
+                    <StartDate>2018-06-06</StartDate>
+                </DateRange></DaysOfOperation>
+                <DaysOfNonOperation><DateRange>
+                    <StartDate>2018-06-09</StartDate><EndDate>2018-06-10</EndDate>
+                </DateRange></DaysOfNonOperation>
+            </SpecialDaysOperation>
+        </OperatingProfile>"#
+            .parse()
+            .unwrap();
+        let period = (Date::from_ymd(2018, 6, 1), Date::from_ymd(2018, 6, 17));
+        let dates: Vec<_> = operating_dates(Some(&profile), period, "ns")
+            .unwrap()
+            .into_iter()
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(
+            vec![
+                "2018-06-02",
+                "2018-06-03",
+                "2018-06-06",
+                "2018-06-16",
+                "2018-06-17"
+            ],
+            dates
+        );
+        assert_eq!(17, operating_dates(None, period, "ns").unwrap().len());
+    }
+}
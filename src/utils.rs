@@ -16,10 +16,10 @@
 
 use chrono::NaiveDate;
 use collection::{Collection, CollectionWithId, Id};
-use csv;
 use failure::ResultExt;
 use geo_types;
-use objects::{AddPrefix, Date};
+use objects::{AddPrefix, Date, PrefixConfiguration};
+use read_utils::{csv_reader, opt_csv_reader, FileHandler};
 use std::fs;
 use std::io::{Read, Write};
 use std::path;
@@ -67,6 +67,14 @@ where
     Ok(i != 0)
 }
 
+pub fn de_from_u8_with_empty_default<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: ::serde::Deserializer<'de>,
+{
+    let i: u8 = de_with_empty_default(deserializer)?;
+    Ok(i != 0)
+}
+
 pub fn ser_from_bool<S>(v: &bool, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: ::serde::Serializer,
@@ -92,12 +100,17 @@ where
     serializer.serialize_str(&s)
 }
 
+// Binary formats get back exactly what was serialized, i.e. the value
+// itself and not an optional one.
 pub fn de_with_empty_default<'de, T: Default, D>(de: D) -> Result<T, D::Error>
 where
     D: ::serde::Deserializer<'de>,
     T: ::serde::Deserialize<'de>,
 {
     use serde::Deserialize;
+    if !de.is_human_readable() {
+        return T::deserialize(de);
+    }
     Option::<T>::deserialize(de).map(|opt| opt.unwrap_or_else(Default::default))
 }
 
@@ -119,8 +132,11 @@ pub fn de_with_empty_or_invalid_default<'de, D, T>(de: D) -> Result<T, D::Error>
 where
     D: ::serde::Deserializer<'de>,
     Option<T>: ::serde::Deserialize<'de>,
-    T: Default,
+    T: ::serde::Deserialize<'de> + Default,
 {
+    if !de.is_human_readable() {
+        return T::deserialize(de);
+    }
     de_with_invalid_option(de).map(|opt| opt.unwrap_or_else(Default::default))
 }
 
@@ -151,30 +167,43 @@ macro_rules! ctx_from_path {
     };
 }
 
-pub fn make_opt_collection_with_id<T>(
-    path: &path::Path,
+pub fn make_opt_collection_with_id<T, H>(
+    file_handler: &mut H,
     file: &str,
 ) -> ::Result<CollectionWithId<T>>
 where
+    for<'a> &'a mut H: FileHandler,
     T: Id<T>,
     for<'de> T: ::serde::Deserialize<'de>,
 {
-    if !path.join(file).exists() {
-        info!("Skipping {}", file);
-        Ok(CollectionWithId::default())
-    } else {
-        make_collection_with_id(path, file)
+    let (rdr, path) = opt_csv_reader(file_handler, file)?;
+    match rdr {
+        None => {
+            info!("Skipping {}", file);
+            Ok(CollectionWithId::default())
+        }
+        Some(mut rdr) => {
+            info!("Reading {}", file);
+            let vec = rdr
+                .deserialize()
+                .collect::<Result<_, _>>()
+                .with_context(ctx_from_path!(path))?;
+            CollectionWithId::new(vec)
+        }
     }
 }
 
-pub fn make_collection_with_id<T>(path: &path::Path, file: &str) -> ::Result<CollectionWithId<T>>
+pub fn make_collection_with_id<T, H>(
+    file_handler: &mut H,
+    file: &str,
+) -> ::Result<CollectionWithId<T>>
 where
+    for<'a> &'a mut H: FileHandler,
     T: Id<T>,
     for<'de> T: ::serde::Deserialize<'de>,
 {
     info!("Reading {}", file);
-    let path = path.join(file);
-    let mut rdr = csv::Reader::from_path(&path).with_context(ctx_from_path!(path))?;
+    let (mut rdr, path) = csv_reader(file_handler, file)?;
     let vec = rdr
         .deserialize()
         .collect::<Result<_, _>>()
@@ -182,42 +211,38 @@ where
     CollectionWithId::new(vec)
 }
 
-pub fn make_opt_collection<T>(path: &path::Path, file: &str) -> ::Result<Collection<T>>
+pub fn make_opt_collection<T, H>(file_handler: &mut H, file: &str) -> ::Result<Collection<T>>
 where
+    for<'a> &'a mut H: FileHandler,
     for<'de> T: ::serde::Deserialize<'de>,
 {
-    if !path.join(file).exists() {
-        info!("Skipping {}", file);
-        Ok(Collection::default())
-    } else {
-        make_collection(path, file)
+    let (rdr, path) = opt_csv_reader(file_handler, file)?;
+    match rdr {
+        None => {
+            info!("Skipping {}", file);
+            Ok(Collection::default())
+        }
+        Some(mut rdr) => {
+            info!("Reading {}", file);
+            let vec = rdr
+                .deserialize()
+                .collect::<Result<_, _>>()
+                .with_context(ctx_from_path!(path))?;
+            Ok(Collection::new(vec))
+        }
     }
 }
 
-pub fn make_collection<T>(path: &path::Path, file: &str) -> ::Result<Collection<T>>
-where
-    for<'de> T: ::serde::Deserialize<'de>,
-{
-    info!("Reading {}", file);
-    let path = path.join(file);
-    let mut rdr = csv::Reader::from_path(&path).with_context(ctx_from_path!(path))?;
-    let vec = rdr
-        .deserialize()
-        .collect::<Result<_, _>>()
-        .with_context(ctx_from_path!(path))?;
-    Ok(Collection::new(vec))
-}
-
 pub fn add_prefix_to_collection_with_id<T>(
     collection: &mut CollectionWithId<T>,
-    prefix: &str,
+    prefix_conf: &PrefixConfiguration,
 ) -> ::Result<()>
 where
     T: AddPrefix + Id<T>,
 {
     let mut objects = collection.take();
     for obj in &mut objects {
-        obj.add_prefix(prefix);
+        obj.add_prefix(prefix_conf);
     }
 
     *collection = CollectionWithId::new(objects)?;
@@ -225,12 +250,14 @@ where
     Ok(())
 }
 
-pub fn add_prefix_to_collection<T>(collection: &mut Collection<T>, prefix: &str)
-where
+pub fn add_prefix_to_collection<T>(
+    collection: &mut Collection<T>,
+    prefix_conf: &PrefixConfiguration,
+) where
     T: AddPrefix,
 {
     for obj in &mut collection.values_mut() {
-        obj.add_prefix(prefix);
+        obj.add_prefix(prefix_conf);
     }
 }
 
@@ -244,4 +271,22 @@ macro_rules! skip_fail {
             }
         }
     };
+    ($res:expr, $issues:expr, $issue:expr, $file:expr) => {
+        match $res {
+            Ok(val) => val,
+            Err(e) => {
+                $issues.handle($issue, $file, e.to_string())?;
+                continue;
+            }
+        }
+    };
+    ($res:expr, $issues:expr, $issue:expr, $file:expr, $line:expr) => {
+        match $res {
+            Ok(val) => val,
+            Err(e) => {
+                $issues.handle_at($issue, $file, $line, e.to_string())?;
+                continue;
+            }
+        }
+    };
 }
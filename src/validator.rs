@@ -0,0 +1,427 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! Semantic validation of a model.
+
+use collection::{CollectionWithId, Id};
+use model::Collections;
+use objects::{StopPoint, VehicleJourney};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+use {Error, Result};
+
+/// The severity of a validation issue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Something worth knowing, without consequence on the data.
+    Info,
+    /// Suspicious data that may be an error.
+    Warning,
+    /// Invalid data.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match *self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        f.write_str(s)
+    }
+}
+
+impl FromStr for Severity {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "info" => Ok(Severity::Info),
+            "warning" => Ok(Severity::Warning),
+            "error" => Ok(Severity::Error),
+            _ => bail!("unknown severity {:?}, expected info, warning or error", s),
+        }
+    }
+}
+
+/// An issue found while validating a model.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValidationIssue {
+    /// The severity of the issue.
+    pub severity: Severity,
+    /// The type of the object concerned by the issue, as `stop_point`.
+    pub object_type: String,
+    /// The identifier of the object concerned by the issue.
+    pub object_id: String,
+    /// The description of the issue.
+    pub message: String,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "[{}] {} {}: {}",
+            self.severity, self.object_type, self.object_id, self.message
+        )
+    }
+}
+
+/// The issues found while validating a model.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ValidationReport {
+    /// The issues, in the order they were found.
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    fn push<S: Into<String>>(
+        &mut self,
+        severity: Severity,
+        object_type: &str,
+        object_id: &str,
+        message: S,
+    ) {
+        self.issues.push(ValidationIssue {
+            severity,
+            object_type: object_type.to_string(),
+            object_id: object_id.to_string(),
+            message: message.into(),
+        });
+    }
+
+    /// Returns the highest severity of the issues, `None` if there is
+    /// no issue.
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.issues.iter().map(|issue| issue.severity).max()
+    }
+
+    /// Returns the number of issues of the given severity.
+    pub fn count(&self, severity: Severity) -> usize {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity == severity)
+            .count()
+    }
+}
+
+fn validate_vehicle_journey(report: &mut ValidationReport, vj: &VehicleJourney) {
+    if vj.stop_times.is_empty() {
+        report.push(Severity::Error, "trip", &vj.id, "no stop time");
+        return;
+    }
+    for st in &vj.stop_times {
+        if st.departure_time < st.arrival_time {
+            report.push(
+                Severity::Error,
+                "trip",
+                &vj.id,
+                format!("departure before arrival at sequence {}", st.sequence),
+            );
+        }
+    }
+    for pair in vj.stop_times.windows(2) {
+        if pair[1].sequence <= pair[0].sequence {
+            report.push(
+                Severity::Error,
+                "trip",
+                &vj.id,
+                format!(
+                    "sequence {} overlaps sequence {}",
+                    pair[1].sequence, pair[0].sequence
+                ),
+            );
+        }
+        if pair[1].arrival_time < pair[0].departure_time {
+            report.push(
+                Severity::Error,
+                "trip",
+                &vj.id,
+                format!(
+                    "negative travel time between sequences {} and {}",
+                    pair[0].sequence, pair[1].sequence
+                ),
+            );
+        }
+    }
+}
+
+fn validate_stop_point(report: &mut ValidationReport, sp: &StopPoint) {
+    if sp.coord.lon == 0. && sp.coord.lat == 0. {
+        report.push(
+            Severity::Warning,
+            "stop_point",
+            &sp.id,
+            "coordinates are 0,0",
+        );
+    }
+}
+
+// Reports the objects referencing an unknown target.
+fn validate_references<T, U>(
+    report: &mut ValidationReport,
+    objects: &CollectionWithId<T>,
+    targets: &CollectionWithId<U>,
+    object_type: &str,
+    field: &str,
+) where
+    T: Id<T> + Id<U>,
+    U: Id<U>,
+{
+    for obj in objects.values() {
+        let target_id = <T as Id<U>>::id(obj);
+        if targets.get_idx(target_id).is_none() {
+            report.push(
+                Severity::Error,
+                object_type,
+                <T as Id<T>>::id(obj),
+                format!("unknown {} {:?}", field, target_id),
+            );
+        }
+    }
+}
+
+fn validate_all_references(report: &mut ValidationReport, c: &Collections) {
+    validate_references(report, &c.datasets, &c.contributors, "dataset", "contributor_id");
+    validate_references(report, &c.lines, &c.networks, "line", "network_id");
+    validate_references(report, &c.lines, &c.commercial_modes, "line", "commercial_mode_id");
+    validate_references(report, &c.routes, &c.lines, "route", "line_id");
+    validate_references(report, &c.vehicle_journeys, &c.routes, "trip", "route_id");
+    validate_references(
+        report,
+        &c.vehicle_journeys,
+        &c.physical_modes,
+        "trip",
+        "physical_mode_id",
+    );
+    validate_references(report, &c.vehicle_journeys, &c.datasets, "trip", "dataset_id");
+    validate_references(report, &c.vehicle_journeys, &c.companies, "trip", "company_id");
+    validate_references(report, &c.stop_points, &c.stop_areas, "stop_point", "stop_area_id");
+    validate_references(report, &c.entrances, &c.stop_areas, "entrance", "stop_area_id");
+    validate_references(report, &c.fare_zones, &c.geometries, "fare_zone", "geometry_id");
+    for vj in c.vehicle_journeys.values() {
+        if c.calendars.get_idx(&vj.service_id).is_none() {
+            report.push(
+                Severity::Error,
+                "trip",
+                &vj.id,
+                format!("unknown service_id {:?}", vj.service_id),
+            );
+        }
+    }
+    for transfer in c.transfers.values() {
+        for stop_id in &[&transfer.from_stop_id, &transfer.to_stop_id] {
+            if c.stop_points.get_idx(stop_id).is_none() {
+                report.push(
+                    Severity::Error,
+                    "transfer",
+                    &format!("{}-{}", transfer.from_stop_id, transfer.to_stop_id),
+                    format!("unknown stop_id {:?}", stop_id),
+                );
+            }
+        }
+    }
+}
+
+/// Checks the collections for semantic issues, as the dangling
+/// references that prevent building a model, reporting all of them
+/// instead of failing on the first one.
+pub fn validate(c: &Collections) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    validate_all_references(&mut report, c);
+    for vj in c.vehicle_journeys.values() {
+        validate_vehicle_journey(&mut report, vj);
+    }
+    let served: BTreeSet<_> = c
+        .vehicle_journeys
+        .values()
+        .flat_map(|vj| vj.stop_times.iter().map(|st| st.stop_point_idx))
+        .collect();
+    for (idx, sp) in c.stop_points.iter() {
+        validate_stop_point(&mut report, sp);
+        if !served.contains(&idx) {
+            report.push(
+                Severity::Warning,
+                "stop_point",
+                &sp.id,
+                "not served by any trip",
+            );
+        }
+    }
+    for calendar in c.calendars.values() {
+        if calendar.dates.is_empty() {
+            report.push(
+                Severity::Warning,
+                "calendar",
+                &calendar.id,
+                "no active date",
+            );
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use collection::Collection;
+    use model::Collections;
+    use objects::*;
+
+    fn stop_point(id: &str, lon: f64, lat: f64) -> StopPoint {
+        StopPoint {
+            id: id.to_string(),
+            name: id.to_string(),
+            codes: KeysValues::default(),
+            object_properties: KeysValues::default(),
+            comment_links: CommentLinksT::default(),
+            visible: true,
+            coord: Coord { lon, lat },
+            stop_area_id: "sa".into(),
+            timezone: None,
+            geometry_id: None,
+            equipment_id: None,
+            fare_zone_id: None,
+            address_id: None,
+        }
+    }
+
+    fn stop_time(c: &Collections, sp_id: &str, sequence: u32, time: &str) -> StopTime {
+        StopTime {
+            stop_point_idx: c.stop_points.get_idx(sp_id).unwrap(),
+            sequence,
+            arrival_time: time.parse().unwrap(),
+            departure_time: time.parse().unwrap(),
+            boarding_duration: 0,
+            alighting_duration: 0,
+            pickup_type: 0,
+            drop_off_type: 0,
+            datetime_estimated: false,
+            local_zone_id: None,
+            extras: None,
+        }
+    }
+
+    #[test]
+    fn report_issues() {
+        let mut calendar = Calendar::new("c".to_string());
+        calendar
+            .dates
+            .insert(::chrono::NaiveDate::from_ymd(2018, 1, 1));
+        let mut c = Collections {
+            stop_points: CollectionWithId::new(vec![
+                stop_point("sp1", 2.37, 48.84),
+                stop_point("sp2", 2.38, 48.85),
+                stop_point("sp3", 0., 0.),
+            ]).unwrap(),
+            calendars: CollectionWithId::new(vec![calendar, Calendar::new("empty".to_string())])
+                .unwrap(),
+            ..Default::default()
+        };
+        let stop_times = vec![
+            stop_time(&c, "sp1", 1, "10:00:00"),
+            stop_time(&c, "sp2", 2, "09:50:00"),
+        ];
+        c.vehicle_journeys = CollectionWithId::new(vec![
+            VehicleJourney {
+                id: "vj1".to_string(),
+                service_id: "c".into(),
+                stop_times,
+                ..Default::default()
+            },
+            VehicleJourney {
+                id: "vj2".to_string(),
+                service_id: "c".into(),
+                ..Default::default()
+            },
+        ]).unwrap();
+        let report = validate(&c);
+
+        // the references are checked by report_dangling_references
+        let issues: Vec<_> = report
+            .issues
+            .iter()
+            .filter(|i| !i.message.starts_with("unknown"))
+            .map(|i| i.to_string())
+            .collect();
+        assert_eq!(
+            vec![
+                "[error] trip vj1: negative travel time between sequences 1 and 2",
+                "[error] trip vj2: no stop time",
+                "[warning] stop_point sp3: coordinates are 0,0",
+                "[warning] stop_point sp3: not served by any trip",
+                "[warning] calendar empty: no active date",
+            ],
+            issues
+        );
+        assert_eq!(Some(Severity::Error), report.max_severity());
+        assert_eq!(3, report.count(Severity::Warning));
+    }
+
+    #[test]
+    fn report_dangling_references() {
+        let mut c = Collections {
+            contributors: CollectionWithId::new(vec![Contributor::default()]).unwrap(),
+            datasets: CollectionWithId::new(vec![Dataset::default()]).unwrap(),
+            companies: CollectionWithId::new(vec![Company::default()]).unwrap(),
+            stop_points: CollectionWithId::new(vec![stop_point("sp1", 2.37, 48.84)]).unwrap(),
+            calendars: CollectionWithId::new(vec![Calendar::new("c".to_string())]).unwrap(),
+            ..Default::default()
+        };
+        let stop_times = vec![
+            stop_time(&c, "sp1", 2, "10:00:00"),
+            stop_time(&c, "sp1", 2, "10:05:00"),
+        ];
+        c.vehicle_journeys = CollectionWithId::new(vec![VehicleJourney {
+            id: "vj1".to_string(),
+            service_id: "c".into(),
+            company_id: "default_company".to_string(),
+            stop_times,
+            ..Default::default()
+        }]).unwrap();
+        c.transfers = Collection::new(vec![Transfer {
+            from_stop_id: "sp1".to_string(),
+            to_stop_id: "sp2".to_string(),
+            min_transfer_time: None,
+            real_min_transfer_time: None,
+            equipment_id: None,
+        }]);
+        let report = validate(&c);
+
+        let issues: Vec<_> = report.issues.iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            vec![
+                "[error] trip vj1: unknown route_id \"default_route\"",
+                "[error] trip vj1: unknown physical_mode_id \"default_physical_mode\"",
+                "[error] stop_point sp1: unknown stop_area_id \"sa\"",
+                "[error] transfer sp1-sp2: unknown stop_id \"sp2\"",
+                "[error] trip vj1: sequence 2 overlaps sequence 2",
+                "[warning] calendar c: no active date",
+            ],
+            issues
+        );
+    }
+
+    #[test]
+    fn parse_severity() {
+        assert_eq!(Severity::Warning, "warning".parse().unwrap());
+        assert!("fatal".parse::<Severity>().is_err());
+        assert!(Severity::Info < Severity::Warning);
+    }
+}
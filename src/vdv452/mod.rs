@@ -0,0 +1,88 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+//! [VDV 452](https://www.vdv.de/452-sdsv15.pdfx) format management,
+//! the ASCII exchange format of the German operators' timetables.
+
+mod read;
+
+use collection::CollectionWithId;
+use model::{Collections, Model};
+use read_utils::{self, FileHandler, InputHandler};
+use std::path::Path;
+use Result;
+
+/// Imports a `Model` from the VDV 452 tables of the `path` directory,
+/// zip or tar.gz archive, each table in a `<table name>.x10` file as
+/// `rec_ort.x10`.
+///
+/// The stopping points of `REC_ORT` become stop points, grouped in
+/// stop areas by their reference location, the variants of the lines
+/// of `REC_LID` become routes, and the trips of `REC_FRT` vehicle
+/// journeys, their times being computed from the running and waiting
+/// times of their timing group.  The day types of `FIRMENKALENDER`
+/// become the calendars.
+///
+/// The `config_path` argument allows you to give a path to a file
+/// containing a json representing the contributor and dataset used
+/// for these files. If not given, default values will be created.
+///
+/// The `prefix` argument is a string that will be prepended to every
+/// identifiers, allowing to namespace the dataset. By default, no
+/// prefix will be added to the identifiers.
+pub fn read<P>(path: P, config_path: Option<P>, prefix: Option<String>) -> Result<Model>
+where
+    P: AsRef<Path>,
+{
+    let mut file_handler = InputHandler::from_path(path)?;
+    read_from_handler(&mut file_handler, config_path, prefix)
+}
+
+/// Imports a `Model` from the VDV 452 tables given by the file
+/// handler, like `read`.
+pub fn read_from_handler<H, P>(
+    file_handler: &mut H,
+    config_path: Option<P>,
+    prefix: Option<String>,
+) -> Result<Model>
+where
+    for<'a> &'a mut H: FileHandler,
+    P: AsRef<Path>,
+{
+    info!("Loading VDV 452 from {}", file_handler.source_name());
+    let (contributor, mut dataset) = read_utils::read_config(config_path)?;
+    let mut collections = Collections::default();
+
+    read::read_calendars(file_handler, &mut collections)?;
+    let vp = read_utils::get_validity_period(&collections.calendars)
+        .ok_or_else(|| format_err!("No valid calendar in VDV 452 data"))?;
+    dataset.start_date = vp.start_date;
+    dataset.end_date = vp.end_date;
+    dataset.system = Some("VDV 452".to_string());
+
+    read::read_companies(file_handler, &mut collections)?;
+    read::read_stops(file_handler, &mut collections)?;
+    read::read_lines(file_handler, &mut collections)?;
+    read::read_vehicle_journeys(file_handler, &mut collections, &dataset.id)?;
+
+    collections.contributors = CollectionWithId::new(vec![contributor])?;
+    collections.datasets = CollectionWithId::new(vec![dataset])?;
+    read_utils::add_id_mappings(&mut collections);
+    if let Some(prefix) = prefix {
+        read_utils::add_prefix(prefix, &mut collections)?;
+    }
+    Model::new(collections)
+}
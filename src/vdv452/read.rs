@@ -0,0 +1,589 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+use chrono::NaiveDate;
+use failure::ResultExt;
+use model::Collections;
+use objects::{self, CommentLinksT, Coord, KeysValues, StopTime, Time};
+use read_utils::FileHandler;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use std::path::PathBuf;
+use std::str::FromStr;
+use Result;
+
+// The only kind of location of `REC_ORT` where the passengers board
+// and alight, the others being the depots, the timing points...
+const STOPPING_POINT: &str = "1";
+
+// A table of a VDV 452 file, the names of its columns being given by
+// the `atr` line and its records by the `rec` lines.
+struct Table {
+    path: PathBuf,
+    columns: HashMap<String, usize>,
+    records: Vec<Vec<String>>,
+}
+
+struct Record<'a> {
+    table: &'a Table,
+    fields: &'a [String],
+}
+
+impl Table {
+    // Reads the `<table name>.x10` file, an absent file giving an
+    // empty table if the table is not required.
+    fn read<H>(file_handler: &mut H, name: &str, required: bool) -> Result<Table>
+    where
+        for<'a> &'a mut H: FileHandler,
+    {
+        let file_name = format!("{}.x10", name.to_lowercase());
+        let (reader, path) = file_handler.get_file_if_exists(&file_name)?;
+        let mut reader = match reader {
+            Some(reader) => reader,
+            None if required => bail!("file {:?} not found", path),
+            None => {
+                info!("Skipping {}", file_name);
+                return Ok(Table {
+                    path,
+                    columns: HashMap::new(),
+                    records: vec![],
+                });
+            }
+        };
+        info!("Reading {}", file_name);
+        let mut content = vec![];
+        reader
+            .read_to_end(&mut content)
+            .with_context(ctx_from_path!(path))?;
+        // the files are usually encoded in ISO-8859-1, whose bytes
+        // are the code points of the characters
+        let content = String::from_utf8(content)
+            .unwrap_or_else(|e| e.into_bytes().into_iter().map(char::from).collect());
+        let mut columns = HashMap::new();
+        let mut records = vec![];
+        for (i, line) in content.lines().enumerate() {
+            let mut fields = split_line(line)
+                .with_context(|_| format!("Error reading line {} of {:?}", i + 1, path))?;
+            let keyword = fields.remove(0);
+            match keyword.to_lowercase().as_str() {
+                "atr" => {
+                    columns = fields
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, column)| (column.to_uppercase(), i))
+                        .collect()
+                }
+                "rec" => records.push(fields),
+                _ => {}
+            }
+        }
+        Ok(Table {
+            path,
+            columns,
+            records,
+        })
+    }
+
+    fn records(&self) -> impl Iterator<Item = Record<'_>> {
+        self.records.iter().map(move |fields| Record {
+            table: self,
+            fields,
+        })
+    }
+}
+
+impl<'a> Record<'a> {
+    // The value of the column, `None` if it is empty or if the table
+    // has no such column.
+    fn get(&self, column: &str) -> Option<&'a str> {
+        self.table
+            .columns
+            .get(column)
+            .and_then(|&i| self.fields.get(i))
+            .map(|s| s.as_str())
+            .filter(|s| !s.is_empty())
+    }
+
+    fn required(&self, column: &str) -> Result<&'a str> {
+        self.get(column)
+            .ok_or_else(|| format_err!("no {} in a record of {:?}", column, self.table.path))
+    }
+
+    fn parse<T>(&self, column: &str) -> Result<T>
+    where
+        T: FromStr,
+        T::Err: ::std::fmt::Display,
+    {
+        let value = self.required(column)?;
+        value.parse().map_err(|e| {
+            format_err!(
+                "invalid {} {:?} in {:?}: {}",
+                column,
+                value,
+                self.table.path,
+                e
+            )
+        })
+    }
+}
+
+// Splits a line into its fields, separated by semicolons, the strings
+// being quoted and their quotes doubled.
+fn split_line(line: &str) -> Result<Vec<String>> {
+    let mut fields = vec![];
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+        let mut field = String::new();
+        let quoted = chars.peek() == Some(&'"');
+        if quoted {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                    None => bail!("unterminated string in {:?}", line),
+                }
+            }
+        }
+        let mut last = true;
+        for c in chars.by_ref() {
+            if c == ';' {
+                last = false;
+                break;
+            }
+            if !quoted {
+                field.push(c);
+            }
+        }
+        fields.push(if quoted {
+            field
+        } else {
+            field.trim_end().to_string()
+        });
+        if last {
+            return Ok(fields);
+        }
+    }
+}
+
+// Converts a WGS84 coordinate given as `GGGMMSSsss` (degrees,
+// minutes, seconds and milliseconds) to decimal degrees.
+fn coordinate(value: &str) -> Result<f64> {
+    let value: i64 = value
+        .parse()
+        .map_err(|_| format_err!("invalid coordinate {:?}", value))?;
+    let abs = value.abs();
+    let degrees = (abs / 10_000_000) as f64
+        + (abs / 100_000 % 100) as f64 / 60.
+        + (abs % 100_000) as f64 / 3_600_000.;
+    Ok(if value < 0 { -degrees } else { degrees })
+}
+
+/// Reads the day types of the operating days of `FIRMENKALENDER` as
+/// calendars.
+pub fn read_calendars<H>(file_handler: &mut H, collections: &mut Collections) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let table = Table::read(file_handler, "FIRMENKALENDER", true)?;
+    let mut calendars = BTreeMap::new();
+    for record in table.records() {
+        let date = record.required("BETRIEBSTAG")?;
+        let date = NaiveDate::parse_from_str(date, "%Y%m%d")
+            .with_context(|_| format!("invalid BETRIEBSTAG {:?}", date))?;
+        let day_type = record.required("TAGESART_NR")?;
+        calendars
+            .entry(day_type.to_string())
+            .or_insert_with(|| objects::Calendar::new(day_type.to_string()))
+            .dates
+            .insert(date);
+    }
+    for (_, calendar) in calendars {
+        collections.calendars.push(calendar)?;
+    }
+    Ok(())
+}
+
+/// Reads the transport companies of `ZUL_VERKEHRSBETRIEB` as companies
+/// and networks, a default company being used if there is none.
+pub fn read_companies<H>(file_handler: &mut H, collections: &mut Collections) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let table = Table::read(file_handler, "ZUL_VERKEHRSBETRIEB", false)?;
+    let mut companies = vec![];
+    for record in table.records() {
+        let id = record.required("UNTERNEHMEN")?;
+        let name = record
+            .get("BETRIEBSGEBIET_BEZ")
+            .or_else(|| record.get("ABK_UNTERNEHMEN"))
+            .unwrap_or(id);
+        companies.push(objects::Company {
+            id: id.to_string(),
+            name: name.to_string(),
+            ..Default::default()
+        });
+    }
+    if companies.is_empty() {
+        companies.push(objects::Company::default());
+    }
+    for company in companies {
+        collections.networks.push(objects::Network {
+            id: company.id.clone(),
+            name: company.name.clone(),
+            url: None,
+            codes: KeysValues::default(),
+            timezone: Some("Europe/Berlin".to_string()),
+            lang: None,
+            phone: None,
+            address: None,
+            sort_order: None,
+        })?;
+        collections.companies.push(company)?;
+    }
+    Ok(())
+}
+
+/// Reads the stopping points of `REC_ORT` as stop points, in the stop
+/// areas of their reference location placed at the barycenter of
+/// their stop points.
+pub fn read_stops<H>(file_handler: &mut H, collections: &mut Collections) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let table = Table::read(file_handler, "REC_ORT", true)?;
+    let mut stop_areas: BTreeMap<String, (String, Vec<Coord>)> = BTreeMap::new();
+    for record in table.records() {
+        if record.required("ONR_TYP_NR")? != STOPPING_POINT {
+            continue;
+        }
+        let id = record.required("ORT_NR")?;
+        let name = record.get("ORT_NAME").unwrap_or_default();
+        let coord = match (record.get("ORT_POS_LAENGE"), record.get("ORT_POS_BREITE")) {
+            (Some(lon), Some(lat)) => Coord {
+                lon: coordinate(lon)?,
+                lat: coordinate(lat)?,
+            },
+            _ => {
+                warn!("The stop point {:?} has no coordinates", id);
+                Coord { lon: 0., lat: 0. }
+            }
+        };
+        let stop_area_id = record
+            .get("ORT_REF_ORT")
+            .map_or_else(|| format!("Navitia:{}", id), |id| id.to_string());
+        stop_areas
+            .entry(stop_area_id.clone())
+            .or_insert_with(|| {
+                let name = record.get("ORT_REF_ORT_NAME").unwrap_or(name);
+                (name.to_string(), vec![])
+            })
+            .1
+            .push(coord);
+        collections.stop_points.push(objects::StopPoint {
+            id: id.to_string(),
+            name: name.to_string(),
+            codes: KeysValues::default(),
+            object_properties: KeysValues::default(),
+            comment_links: CommentLinksT::default(),
+            visible: true,
+            coord,
+            stop_area_id: stop_area_id.into(),
+            timezone: None,
+            geometry_id: None,
+            equipment_id: None,
+            fare_zone_id: None,
+            address_id: None,
+        })?;
+    }
+    for (id, (name, coords)) in stop_areas {
+        let n = coords.len() as f64;
+        collections.stop_areas.push(objects::StopArea {
+            id,
+            name,
+            codes: KeysValues::default(),
+            object_properties: KeysValues::default(),
+            comment_links: CommentLinksT::default(),
+            visible: true,
+            coord: Coord {
+                lon: coords.iter().map(|c| c.lon).sum::<f64>() / n,
+                lat: coords.iter().map(|c| c.lat).sum::<f64>() / n,
+            },
+            timezone: None,
+            geometry_id: None,
+            equipment_id: None,
+        })?;
+    }
+    Ok(())
+}
+
+/// Reads the lines of `REC_LID`, each variant of a line becoming a
+/// route.  VDV 452 giving no mode, the lines are buses.
+pub fn read_lines<H>(file_handler: &mut H, collections: &mut Collections) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let table = Table::read(file_handler, "REC_LID", true)?;
+    collections.physical_modes.push(objects::PhysicalMode {
+        id: "Bus".to_string(),
+        name: "Bus".to_string(),
+        co2_emission: None,
+    })?;
+    collections.commercial_modes.push(objects::CommercialMode {
+        id: "Bus".to_string(),
+        name: "Bus".to_string(),
+    })?;
+    let network_id = collections
+        .networks
+        .values()
+        .next()
+        .map(|n| n.id.clone())
+        .ok_or_else(|| format_err!("no network"))?;
+    for record in table.records() {
+        let line_id = record.required("LI_NR")?;
+        let variant = record.required("STR_LI_VAR")?;
+        let name = record.get("LIDNAME").unwrap_or_default();
+        if collections.lines.get(line_id).is_none() {
+            let code = record.get("LI_KUERZEL").map(|c| c.to_string());
+            collections.lines.push(objects::Line {
+                id: line_id.to_string(),
+                code: code.clone(),
+                codes: KeysValues::default(),
+                object_properties: KeysValues::default(),
+                comment_links: CommentLinksT::default(),
+                name: code.unwrap_or_else(|| name.to_string()),
+                forward_name: None,
+                forward_direction: None,
+                backward_name: None,
+                backward_direction: None,
+                color: None,
+                text_color: None,
+                sort_order: None,
+                network_id: network_id.clone().into(),
+                commercial_mode_id: "Bus".to_string(),
+                geometry_id: None,
+                opening_time: None,
+                closing_time: None,
+            })?;
+        }
+        collections.routes.push(objects::Route {
+            id: format!("{}:{}", line_id, variant),
+            name: name.to_string(),
+            direction_type: match record.get("LI_RI_NR") {
+                Some("1") => Some("forward".to_string()),
+                Some("2") => Some("backward".to_string()),
+                _ => None,
+            },
+            codes: KeysValues::default(),
+            object_properties: KeysValues::default(),
+            comment_links: CommentLinksT::default(),
+            line_id: line_id.into(),
+            geometry_id: None,
+            destination_id: None,
+            continuous_pickup: None,
+            continuous_drop_off: None,
+        })?;
+    }
+    Ok(())
+}
+
+// A location of a line variant, by its kind (`ONR_TYP_NR`) and its
+// number (`ORT_NR`).
+type Location = (String, String);
+
+fn location(record: &Record) -> Result<Location> {
+    Ok((
+        record.required("ONR_TYP_NR")?.to_string(),
+        record.required("ORT_NR")?.to_string(),
+    ))
+}
+
+/// Reads the trips of `REC_FRT` as vehicle journeys.  Their stops are
+/// the stopping points of the line variant in `LID_VERLAUF`, their
+/// running times between the locations are given by `SEL_FZT_FELD`
+/// and their waiting times by `REC_FRT_HZT` or, if not, by
+/// `ORT_HZTF`, for the timing group of the trip.
+pub fn read_vehicle_journeys<H>(
+    file_handler: &mut H,
+    collections: &mut Collections,
+    dataset_id: &str,
+) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let mut variants: HashMap<(String, String), Vec<(u32, Location)>> = HashMap::new();
+    for record in Table::read(file_handler, "LID_VERLAUF", true)?.records() {
+        let key = (
+            record.required("LI_NR")?.to_string(),
+            record.required("STR_LI_VAR")?.to_string(),
+        );
+        let sequence = record.parse("LI_LFD_NR")?;
+        variants
+            .entry(key)
+            .or_default()
+            .push((sequence, location(&record)?));
+    }
+    for locations in variants.values_mut() {
+        locations.sort();
+    }
+
+    let mut run_times = HashMap::new();
+    for record in Table::read(file_handler, "SEL_FZT_FELD", true)?.records() {
+        let to = (
+            record.required("SEL_ZIEL_TYP")?.to_string(),
+            record.required("SEL_ZIEL")?.to_string(),
+        );
+        let key = (
+            record.required("FGR_NR")?.to_string(),
+            location(&record)?,
+            to,
+        );
+        run_times.insert(key, record.parse::<u32>("SEL_FZT")?);
+    }
+    let mut wait_times = HashMap::new();
+    for record in Table::read(file_handler, "ORT_HZTF", false)?.records() {
+        let key = (record.required("FGR_NR")?.to_string(), location(&record)?);
+        wait_times.insert(key, record.parse::<u32>("HP_HZT")?);
+    }
+    let mut trip_wait_times = HashMap::new();
+    for record in Table::read(file_handler, "REC_FRT_HZT", false)?.records() {
+        let key = (record.required("FRT_FID")?.to_string(), location(&record)?);
+        trip_wait_times.insert(key, record.parse::<u32>("FRT_HZT_ZEIT")?);
+    }
+
+    let default_company_id = collections
+        .companies
+        .values()
+        .next()
+        .map(|c| c.id.clone())
+        .ok_or_else(|| format_err!("no company"))?;
+    let table = Table::read(file_handler, "REC_FRT", true)?;
+    for record in table.records() {
+        let id = record.required("FRT_FID")?;
+        let line_id = record.required("LI_NR")?;
+        let variant = record.required("STR_LI_VAR")?;
+        let service_id = record.required("TAGESART_NR")?;
+        if collections.calendars.get(service_id).is_none() {
+            warn!(
+                "The trip {:?} runs on the day type {:?} without operating day, it is ignored",
+                id, service_id
+            );
+            continue;
+        }
+        let route_id = format!("{}:{}", line_id, variant);
+        ensure!(
+            collections.routes.get(&route_id).is_some(),
+            "The line variant {:?} of the trip {:?} is not found in REC_LID",
+            route_id,
+            id
+        );
+        let locations = variants
+            .get(&(line_id.to_string(), variant.to_string()))
+            .ok_or_else(|| {
+                format_err!(
+                    "The line variant {:?} is not found in LID_VERLAUF",
+                    route_id
+                )
+            })?;
+        let timing_group = record.required("FGR_NR")?.to_string();
+
+        let mut stop_times = vec![];
+        let mut time: u32 = record.parse("FRT_START")?;
+        for (i, (_, location)) in locations.iter().enumerate() {
+            let arrival = time;
+            time += trip_wait_times
+                .get(&(id.to_string(), location.clone()))
+                .or_else(|| wait_times.get(&(timing_group.clone(), location.clone())))
+                .cloned()
+                .unwrap_or(0);
+            if location.0 == STOPPING_POINT {
+                let stop_point_idx = collections
+                    .stop_points
+                    .get_idx(&location.1)
+                    .ok_or_else(|| format_err!("The stop point {:?} is not found", location.1))?;
+                stop_times.push(StopTime {
+                    stop_point_idx,
+                    sequence: stop_times.len() as u32,
+                    arrival_time: Time::new(0, 0, arrival),
+                    departure_time: Time::new(0, 0, time),
+                    boarding_duration: 0,
+                    alighting_duration: 0,
+                    pickup_type: 0,
+                    drop_off_type: 0,
+                    datetime_estimated: false,
+                    local_zone_id: None,
+                    extras: None,
+                });
+            }
+            if let Some((_, next)) = locations.get(i + 1) {
+                let key = (timing_group.clone(), location.clone(), next.clone());
+                time += run_times.get(&key).ok_or_else(|| {
+                    format_err!(
+                        "No running time from {:?} to {:?} for the timing group {:?} in \
+                         SEL_FZT_FELD",
+                        location,
+                        next,
+                        timing_group
+                    )
+                })?;
+            }
+        }
+        let company_id = record
+            .get("UNTERNEHMEN")
+            .filter(|c| collections.companies.get(c).is_some())
+            .map_or_else(|| default_company_id.clone(), |c| c.to_string());
+        collections.vehicle_journeys.push(objects::VehicleJourney {
+            id: id.to_string(),
+            route_id: route_id.into(),
+            physical_mode_id: "Bus".to_string(),
+            dataset_id: dataset_id.to_string(),
+            service_id: service_id.into(),
+            company_id,
+            stop_times,
+            ..Default::default()
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_lines() {
+        assert_eq!(
+            vec!["rec", "1", "Rathaus \"Mitte\"", "", "a;b", "52"],
+            split_line(r#"rec; 1; "Rathaus ""Mitte"""; ; "a;b" ;52 "#).unwrap()
+        );
+        assert_eq!(vec!["eof", "1"], split_line("eof; 1").unwrap());
+        assert!(split_line(r#"rec; "Rathaus"#).is_err());
+    }
+
+    #[test]
+    fn coordinates() {
+        assert!((coordinate("132420000").unwrap() - 13.405_555).abs() < 1e-6);
+        assert!((coordinate("-0523130500").unwrap() + 52.525_138).abs() < 1e-6);
+        assert!(coordinate("52.5").is_err());
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+extern crate navitia_model;
+
+use navitia_model::apply_rules::{apply_rules, apply_rules_to_collections};
+use navitia_model::model::Model;
+use navitia_model::objects::Rgb;
+
+#[test]
+fn apply_code_and_property_rules() {
+    let model = navitia_model::ntfs::read("fixtures/minimal_ntfs/").unwrap();
+    let (model, warnings) = apply_rules(
+        model,
+        &["fixtures/apply_rules/complementary_codes.txt"],
+        &["fixtures/apply_rules/property_rules.txt"],
+    ).unwrap();
+
+    let m1 = model.lines.get("M1").unwrap();
+    assert_eq!(
+        vec![("source".to_string(), "METRO-1".to_string())],
+        m1.codes
+    );
+    assert_eq!("Métro 1", m1.name);
+    assert_eq!(
+        Some(Rgb {
+            red: 0xFF,
+            green: 0xCD,
+            blue: 0x00
+        }),
+        m1.color
+    );
+    assert_eq!(
+        vec![("UIC".to_string(), "8775860".to_string())],
+        model.stop_points.get("GDLR").unwrap().codes
+    );
+    assert_eq!(1, model.vehicle_journeys.get("RERAF1").unwrap().codes.len());
+    assert_eq!(
+        "Paris Gare de Lyon",
+        model.stop_areas.get("GDL").unwrap().name
+    );
+    assert_eq!(
+        "RapidTransit",
+        model
+            .vehicle_journeys
+            .get("RERAB1")
+            .unwrap()
+            .physical_mode_id
+    );
+
+    // rules on unknown objects or properties and with a wrong old value
+    // are ignored
+    assert_eq!("Bus 42", model.lines.get("B42").unwrap().name);
+    assert_eq!("RER", model.lines.get("RERA").unwrap().commercial_mode_id);
+    let messages: Vec<_> = warnings.iter().map(|w| w.message.as_str()).collect();
+    assert_eq!(
+        vec![
+            "rule 4 ignored: unknown not found",
+            "rule 3 ignored: line_name is \"Bus 42\" instead of \"Bus 43\"",
+            "rule 4 ignored: commercial_mode_id unknown not found",
+            "rule 7 ignored: property route_color not supported for route",
+        ],
+        messages
+    );
+}
+
+#[test]
+fn apply_rules_to_loaded_collections() {
+    let model = navitia_model::ntfs::read("fixtures/minimal_ntfs/").unwrap();
+    let mut collections = model.into_collections();
+    let warnings = apply_rules_to_collections(
+        &mut collections,
+        &["fixtures/apply_rules/complementary_codes.txt"],
+        &["fixtures/apply_rules/property_rules.txt"],
+    ).unwrap();
+    assert_eq!(4, warnings.len());
+    assert_eq!("Métro 1", collections.lines.get("M1").unwrap().name);
+    assert!(Model::new(collections).is_ok());
+}
@@ -0,0 +1,71 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+extern crate navitia_model;
+extern crate tempdir;
+
+use navitia_model::model;
+use navitia_model::ntfs;
+use navitia_model::objects::Route;
+use navitia_model::relations::IdxSet;
+use tempdir::TempDir;
+
+#[test]
+fn save_and_load_binary() {
+    let model = ntfs::read("fixtures/ntfs").unwrap();
+    let tmp_dir = TempDir::new("navitia_model_tests").unwrap();
+    let path = tmp_dir.path().join("model.bin");
+    model::save_binary(&model, &path).unwrap();
+    let loaded = model::load_binary(&path).unwrap();
+
+    assert_eq!(
+        model.vehicle_journeys.values().collect::<Vec<_>>(),
+        loaded.vehicle_journeys.values().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        model.stop_points.values().collect::<Vec<_>>(),
+        loaded.stop_points.values().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        model.calendars.values().collect::<Vec<_>>(),
+        loaded.calendars.values().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        model.lines.values().collect::<Vec<_>>(),
+        loaded.lines.values().collect::<Vec<_>>()
+    );
+    assert_eq!(model.feed_infos, loaded.feed_infos);
+    assert!(loaded
+        .vehicle_journeys
+        .values()
+        .any(|vj| !vj.stop_times.is_empty()));
+    let idx = loaded.lines.get_idx("M1").unwrap();
+    let routes: IdxSet<Route> = loaded.get_corresponding_from_idx(idx);
+    assert_eq!(model.get_corresponding_from_idx(idx), routes);
+}
+
+#[test]
+fn load_binary_with_wrong_version() {
+    let model = ntfs::read("fixtures/minimal_ntfs").unwrap();
+    let mut bytes = vec![];
+    model::write_binary(&model, &mut bytes).unwrap();
+    bytes[4] += 1;
+    match model::read_binary(&bytes[..]) {
+        Err(err) => assert!(format!("{}", err).contains("must be generated again")),
+        Ok(_) => panic!("a binary model of another version must be refused"),
+    }
+    assert!(model::read_binary(&b"some text"[..]).is_err());
+}
@@ -0,0 +1,56 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+extern crate navitia_model;
+
+use navitia_model::blocks::build_vehicle_journey_links;
+use navitia_model::ntfs;
+use navitia_model::objects::VehicleJourneyLink;
+use navitia_model::Model;
+
+#[test]
+fn link_consecutive_vehicle_journeys_of_blocks() {
+    let mut collections = ntfs::read("fixtures/minimal_ntfs")
+        .unwrap()
+        .into_collections();
+    for &(vj_id, block_id) in &[
+        ("M1F1", "b1"),
+        ("M1B1", "b1"),
+        ("B42F1", "b2"),
+        ("B42B1", "b2"),
+    ] {
+        collections
+            .vehicle_journeys
+            .get_mut(vj_id)
+            .unwrap()
+            .block_id = Some(block_id.to_string());
+    }
+
+    assert_eq!(2, build_vehicle_journey_links(&mut collections));
+    let model = Model::new(collections).unwrap();
+    let link = |from: &str, to: &str, stop: &str| VehicleJourneyLink {
+        from_vehicle_journey_id: from.to_string(),
+        to_vehicle_journey_id: to.to_string(),
+        stop_point_id: stop.into(),
+    };
+    assert_eq!(
+        vec![
+            &link("M1F1", "M1B1", "CDGM"),
+            &link("B42B1", "B42F1", "GDLB"),
+        ],
+        model.vehicle_journey_links.values().collect::<Vec<_>>()
+    );
+}
@@ -0,0 +1,78 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+extern crate navitia_model;
+
+use navitia_model::consolidation::{consolidate_lines, consolidate_routes};
+use navitia_model::ntfs;
+
+#[test]
+fn consolidate_lines_by_network_and_code() {
+    let model = ntfs::read("fixtures/consolidation/ntfs").unwrap();
+    let (model, warnings) =
+        consolidate_lines(model, &["fixtures/consolidation/line_rules.txt"]).unwrap();
+
+    let mut line_ids: Vec<_> = model.lines.values().map(|l| l.id.as_str()).collect();
+    line_ids.sort();
+    assert_eq!(vec!["B42", "M1", "RER:A"], line_ids);
+    let b42 = model.lines.get("B42").unwrap();
+    assert_eq!("Bus 42", b42.name);
+    assert_eq!(
+        vec![("original_id".to_string(), "B42X".to_string())],
+        b42.codes
+    );
+    assert_eq!("B42", model.routes.get("B42B").unwrap().line_id);
+    assert_eq!(
+        vec![("original_id".to_string(), "RERA".to_string())],
+        model.lines.get("RER:A").unwrap().codes
+    );
+    assert_eq!("RER:A", model.routes.get("RERAF").unwrap().line_id);
+
+    let messages: Vec<_> = warnings.iter().map(|w| w.message.as_str()).collect();
+    assert_eq!(
+        vec![
+            "rule 3 ignored: no line with code 99 in network TGN",
+            "rule 4 ignored: line B42 already exists",
+        ],
+        messages
+    );
+}
+
+#[test]
+fn consolidate_routes_by_direction_and_destination() {
+    let model = ntfs::read("fixtures/consolidation/ntfs").unwrap();
+    let (model, _) = consolidate_lines(model, &["fixtures/consolidation/line_rules.txt"]).unwrap();
+    let model = consolidate_routes(model).unwrap();
+
+    let mut route_ids: Vec<_> = model.routes.values().map(|r| r.id.as_str()).collect();
+    route_ids.sort();
+    assert_eq!(
+        vec!["B42B", "B42F", "M1B", "M1F", "RERAB", "RERAF"],
+        route_ids
+    );
+    let b42f = model.routes.get("B42F").unwrap();
+    assert_eq!(Some("MTP".to_string()), b42f.destination_id);
+    assert_eq!(
+        vec![("original_id".to_string(), "B42F2".to_string())],
+        b42f.codes
+    );
+    assert_eq!(
+        Some("GDL".to_string()),
+        model.routes.get("B42B").unwrap().destination_id
+    );
+    let vj = model.vehicle_journeys.get("B42F23").unwrap();
+    assert_eq!("B42F", vj.route_id);
+}
@@ -0,0 +1,95 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+extern crate chrono;
+extern crate navitia_model;
+
+use chrono::NaiveDate;
+use navitia_model::diff::{diff, CollectionDiff, ServiceDatesDiff};
+use navitia_model::ntfs;
+use navitia_model::objects::CommercialMode;
+use navitia_model::Model;
+
+#[test]
+fn no_difference() {
+    let old = ntfs::read("fixtures/minimal_ntfs").unwrap();
+    let new = ntfs::read("fixtures/minimal_ntfs").unwrap();
+    let diff = diff(&old, &new);
+    assert!(diff.is_empty());
+    assert_eq!("no difference\n", diff.summary());
+}
+
+#[test]
+fn differences_of_a_new_feed() {
+    let old = ntfs::read("fixtures/minimal_ntfs").unwrap();
+    let mut collections = ntfs::read("fixtures/minimal_ntfs")
+        .unwrap()
+        .into_collections();
+    collections.lines.get_mut("M1").unwrap().name = "Métro 1 bis".to_string();
+    collections
+        .commercial_modes
+        .push(CommercialMode {
+            id: "Tram".to_string(),
+            name: "Tramway".to_string(),
+        })
+        .unwrap();
+    let new_date = NaiveDate::from_ymd(2018, 2, 3);
+    collections
+        .calendars
+        .get_mut("Week")
+        .unwrap()
+        .dates
+        .insert(new_date);
+    let new = Model::new(collections).unwrap();
+
+    let diff = diff(&old, &new);
+    assert_eq!(
+        vec![
+            CollectionDiff {
+                collection: "commercial_modes".to_string(),
+                added: vec!["Tram".to_string()],
+                ..Default::default()
+            },
+            CollectionDiff {
+                collection: "lines".to_string(),
+                modified: vec!["M1".to_string()],
+                ..Default::default()
+            },
+            CollectionDiff {
+                collection: "calendars".to_string(),
+                modified: vec!["Week".to_string()],
+                ..Default::default()
+            },
+        ],
+        diff.collections
+    );
+    let service_dates = |line_id: &str| ServiceDatesDiff {
+        line_id: line_id.to_string(),
+        added: vec![new_date],
+        removed: vec![],
+    };
+    assert_eq!(
+        vec![
+            service_dates("B42"),
+            service_dates("M1"),
+            service_dates("RERA"),
+        ],
+        diff.service_dates
+    );
+    assert!(diff
+        .summary()
+        .starts_with("commercial_modes: 1 added, 0 removed, 0 modified\n"));
+}
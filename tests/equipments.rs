@@ -0,0 +1,118 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+extern crate navitia_model;
+
+use navitia_model::equipments::{enrich_equipments, read_accessibility};
+use navitia_model::ntfs;
+use navitia_model::objects::{Availability, Equipment};
+
+fn equipment(id: &str, wheelchair_boarding: Availability) -> Equipment {
+    Equipment {
+        id: id.to_string(),
+        wheelchair_boarding,
+        sheltered: Availability::InformationNotAvailable,
+        elevator: Availability::InformationNotAvailable,
+        escalator: Availability::InformationNotAvailable,
+        bike_accepted: Availability::InformationNotAvailable,
+        bike_depot: Availability::InformationNotAvailable,
+        visual_announcement: Availability::InformationNotAvailable,
+        audible_announcement: Availability::InformationNotAvailable,
+        appropriate_escort: Availability::InformationNotAvailable,
+        appropriate_signage: Availability::InformationNotAvailable,
+    }
+}
+
+#[test]
+fn enrich_equipments_from_accessibility_file() {
+    let mut collections = ntfs::read("fixtures/minimal_ntfs")
+        .unwrap()
+        .into_collections();
+    collections
+        .equipments
+        .push(equipment("0", Availability::Available))
+        .unwrap();
+    collections
+        .equipments
+        .push(equipment("1", Availability::Available))
+        .unwrap();
+    collections
+        .stop_points
+        .get_mut("GDLM")
+        .unwrap()
+        .equipment_id = Some("0".to_string());
+    collections
+        .stop_points
+        .get_mut("GDLB")
+        .unwrap()
+        .equipment_id = Some("1".to_string());
+
+    let accessibilities = read_accessibility("fixtures/equipments/accessibility.csv").unwrap();
+    assert_eq!(5, accessibilities.len());
+    assert_eq!(
+        4,
+        enrich_equipments(&mut collections, &accessibilities).unwrap()
+    );
+
+    let equipment_of = |stop_id: &str| {
+        let sp_equipment_id = collections
+            .stop_points
+            .get(stop_id)
+            .map(|sp| sp.equipment_id.clone());
+        let equipment_id = sp_equipment_id
+            .unwrap_or_else(|| {
+                collections
+                    .stop_areas
+                    .get(stop_id)
+                    .unwrap()
+                    .equipment_id
+                    .clone()
+            })
+            .unwrap();
+        collections.equipments.get(&equipment_id).unwrap().clone()
+    };
+    // the duplicated equipment is merged into the first one
+    assert_eq!(
+        Some("0".to_string()),
+        collections.stop_points.get("GDLB").unwrap().equipment_id
+    );
+
+    let gdlm = equipment_of("GDLM");
+    assert_eq!(Availability::Available, gdlm.wheelchair_boarding);
+    assert_eq!(Availability::Available, gdlm.elevator);
+    assert_eq!(Availability::InformationNotAvailable, gdlm.sheltered);
+
+    let gdl = equipment_of("GDL");
+    assert_eq!(
+        Availability::InformationNotAvailable,
+        gdl.wheelchair_boarding
+    );
+    assert_eq!(Availability::Available, gdl.sheltered);
+
+    assert_eq!(
+        equipment("", Availability::InformationNotAvailable),
+        Equipment {
+            id: String::new(),
+            ..equipment_of("NATR")
+        }
+    );
+    assert_eq!(
+        Availability::NotAvailable,
+        equipment_of("GDLR").audible_announcement
+    );
+    assert_ne!(gdlm.id, gdl.id);
+    assert_eq!(5, collections.equipments.len());
+}
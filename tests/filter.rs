@@ -0,0 +1,242 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+extern crate chrono;
+extern crate navitia_model;
+
+use chrono::NaiveDate;
+use navitia_model::filter::{filter, restrict_validity_period, Filter};
+use navitia_model::objects::{Comment, CommentType};
+use navitia_model::Model;
+
+fn ids<'a, I, T: 'a>(objects: I, id: fn(&T) -> &str) -> Vec<String>
+where
+    I: Iterator<Item = &'a T>,
+{
+    let mut ids: Vec<String> = objects.map(|o| id(o).to_string()).collect();
+    ids.sort();
+    ids
+}
+
+#[test]
+fn filter_by_bounding_box() {
+    let model = navitia_model::ntfs::read("fixtures/minimal_ntfs/").unwrap();
+    let filter_params = Filter {
+        bounding_box: Some("2.34,48.84,2.40,48.86".parse().unwrap()),
+        ..Default::default()
+    };
+    let model = filter(model, &filter_params).unwrap();
+
+    assert_eq!(
+        vec!["M1B1", "M1F1", "RERAB1", "RERAF1"],
+        ids(model.vehicle_journeys.values(), |vj| &vj.id)
+    );
+    assert_eq!(
+        vec!["CHAM", "GDLM", "GDLR", "NATM", "NATR"],
+        ids(model.stop_points.values(), |sp| &sp.id)
+    );
+    assert_eq!(
+        vec!["CHA", "GDL", "NAT"],
+        ids(model.stop_areas.values(), |sa| &sa.id)
+    );
+    assert_eq!(vec!["M1", "RERA"], ids(model.lines.values(), |l| &l.id));
+    assert_eq!(
+        vec!["Metro", "RER"],
+        ids(model.commercial_modes.values(), |m| &m.id)
+    );
+    let rer = model.vehicle_journeys.get("RERAF1").unwrap();
+    let stops: Vec<_> = rer
+        .stop_times
+        .iter()
+        .map(|st| model.stop_points[st.stop_point_idx].id.as_str())
+        .collect();
+    assert_eq!(vec!["NATR", "GDLR"], stops);
+}
+
+#[test]
+fn filter_by_period() {
+    let model = navitia_model::ntfs::read("fixtures/minimal_ntfs/").unwrap();
+    let start = NaiveDate::from_ymd(2018, 6, 1);
+    let end = NaiveDate::from_ymd(2018, 6, 30);
+    let filter_params = Filter {
+        period: Some((start, end)),
+        ..Default::default()
+    };
+    let model = filter(model, &filter_params).unwrap();
+
+    assert_eq!(6, model.vehicle_journeys.len());
+    let calendar = model.calendars.get("Week").unwrap();
+    assert_eq!(21, calendar.dates.len());
+    assert!(calendar.dates.iter().all(|d| start <= *d && *d <= end));
+    let dataset = model.datasets.get("TGDS").unwrap();
+    assert_eq!(start, dataset.start_date);
+    assert_eq!(end, dataset.end_date);
+
+    let filter_params = Filter {
+        period: Some((
+            NaiveDate::from_ymd(2019, 1, 1),
+            NaiveDate::from_ymd(2019, 1, 31),
+        )),
+        ..Default::default()
+    };
+    let model = filter(model, &filter_params).unwrap();
+    assert_eq!(0, model.vehicle_journeys.len());
+    assert_eq!(0, model.stop_points.len());
+    assert_eq!(0, model.networks.len());
+}
+
+#[test]
+fn filter_by_network() {
+    let model = navitia_model::ntfs::read("fixtures/minimal_ntfs/").unwrap();
+    let mut filter_params = Filter::default();
+    filter_params.network_ids.insert("TGN".to_string());
+    let model = filter(model, &filter_params).unwrap();
+    assert_eq!(6, model.vehicle_journeys.len());
+    assert_eq!(10, model.stop_points.len());
+
+    let mut filter_params = Filter::default();
+    filter_params.network_ids.insert("unknown".to_string());
+    let model = filter(model, &filter_params).unwrap();
+    assert_eq!(0, model.vehicle_journeys.len());
+    assert_eq!(0, model.lines.len());
+    assert_eq!(0, model.contributors.len());
+}
+
+#[test]
+fn filter_by_line_and_physical_mode() {
+    let model = navitia_model::ntfs::read("fixtures/minimal_ntfs/").unwrap();
+    let mut filter_params = Filter::default();
+    filter_params.line_ids.insert("RERA".to_string());
+    let model = filter(model, &filter_params).unwrap();
+    assert_eq!(
+        vec!["RERAB1", "RERAF1"],
+        ids(model.vehicle_journeys.values(), |vj| &vj.id)
+    );
+    assert_eq!(vec!["RERA"], ids(model.lines.values(), |l| &l.id));
+    assert_eq!(vec!["RERAB", "RERAF"], ids(model.routes.values(), |r| &r.id));
+
+    let mut filter_params = Filter::default();
+    filter_params
+        .physical_mode_ids
+        .insert("RapidTransit".to_string());
+    let model = filter(model, &filter_params).unwrap();
+    assert_eq!(
+        vec!["RERAF1"],
+        ids(model.vehicle_journeys.values(), |vj| &vj.id)
+    );
+    assert_eq!(
+        vec!["RapidTransit"],
+        ids(model.physical_modes.values(), |m| &m.id)
+    );
+    assert_eq!(vec!["RERAF"], ids(model.routes.values(), |r| &r.id));
+}
+
+#[test]
+fn filter_excluding_network_and_line() {
+    let model = navitia_model::ntfs::read("fixtures/minimal_ntfs/").unwrap();
+    let mut filter_params = Filter::default();
+    filter_params.excluded_line_ids.insert("B42".to_string());
+    let model = filter(model, &filter_params).unwrap();
+    assert_eq!(
+        vec!["M1B1", "M1F1", "RERAB1", "RERAF1"],
+        ids(model.vehicle_journeys.values(), |vj| &vj.id)
+    );
+    assert_eq!(vec!["M1", "RERA"], ids(model.lines.values(), |l| &l.id));
+    assert_eq!(
+        vec!["M1B", "M1F", "RERAB", "RERAF"],
+        ids(model.routes.values(), |r| &r.id)
+    );
+
+    let mut filter_params = Filter::default();
+    filter_params.excluded_network_ids.insert("TGN".to_string());
+    let model = filter(model, &filter_params).unwrap();
+    assert_eq!(0, model.vehicle_journeys.len());
+    assert_eq!(0, model.networks.len());
+}
+
+#[test]
+fn restrict_feed_dates() {
+    let model = navitia_model::ntfs::read("fixtures/minimal_ntfs/").unwrap();
+    let mut collections = model.into_collections();
+    collections
+        .feed_infos
+        .insert("feed_start_date".to_string(), "20180101".to_string());
+    collections
+        .feed_infos
+        .insert("feed_end_date".to_string(), "20180331".to_string());
+    let model = navitia_model::Model::new(collections).unwrap();
+    let model = restrict_validity_period(
+        model,
+        NaiveDate::from_ymd(2018, 3, 1),
+        NaiveDate::from_ymd(2018, 6, 30),
+    ).unwrap();
+    assert_eq!("20180301", model.feed_infos["feed_start_date"]);
+    assert_eq!("20180331", model.feed_infos["feed_end_date"]);
+}
+
+fn comment(id: &str) -> Comment {
+    Comment {
+        id: id.to_string(),
+        comment_type: CommentType::default(),
+        label: None,
+        name: id.to_string(),
+        url: None,
+    }
+}
+
+#[test]
+fn remove_vehicle_journeys_and_clean_up() {
+    let mut collections = navitia_model::ntfs::read("fixtures/minimal_ntfs/")
+        .unwrap()
+        .into_collections();
+    let bus_comment = collections.comments.push(comment("bus")).unwrap();
+    let metro_comment = collections.comments.push(comment("metro")).unwrap();
+    collections
+        .lines
+        .get_mut("B42")
+        .unwrap()
+        .comment_links
+        .push(bus_comment);
+    collections
+        .lines
+        .get_mut("M1")
+        .unwrap()
+        .comment_links
+        .push(metro_comment);
+
+    let removed = collections.remove_vehicle_journeys(|vj| vj.route_id.starts_with("B42"));
+    assert_eq!(2, removed);
+    // the objects used by the removed vehicle journeys are kept
+    assert_eq!(3, collections.lines.len());
+
+    collections.clean_up().unwrap();
+    assert_eq!(
+        vec!["M1B", "M1F", "RERAB", "RERAF"],
+        ids(collections.routes.values(), |r| &r.id)
+    );
+    assert_eq!(
+        vec!["M1", "RERA"],
+        ids(collections.lines.values(), |l| &l.id)
+    );
+    assert_eq!(
+        vec!["Metro", "RER"],
+        ids(collections.commercial_modes.values(), |m| &m.id)
+    );
+    assert_eq!(vec!["metro"], ids(collections.comments.values(), |c| &c.id));
+    let metro = collections.lines.get("M1").unwrap();
+    assert_eq!("metro", collections.comments[metro.comment_links[0]].id);
+    Model::new(collections).unwrap();
+}
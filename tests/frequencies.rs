@@ -0,0 +1,106 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+extern crate navitia_model;
+extern crate tempdir;
+
+use navitia_model::frequencies::{expand, ExpansionPolicy};
+use navitia_model::ntfs;
+use navitia_model::objects::{Frequency, Time};
+use navitia_model::Model;
+use tempdir::TempDir;
+
+fn model_with_frequencies() -> Model {
+    let mut collections = ntfs::read("fixtures/minimal_ntfs")
+        .unwrap()
+        .into_collections();
+    collections.frequencies.push(Frequency {
+        vehicle_journey_id: "M1F1".to_string(),
+        start_time: Time::new(10, 0, 0),
+        end_time: Time::new(10, 30, 0),
+        headway_secs: 600,
+        exact_times: true,
+    });
+    collections.frequencies.push(Frequency {
+        vehicle_journey_id: "B42F1".to_string(),
+        start_time: Time::new(6, 0, 0),
+        end_time: Time::new(7, 0, 0),
+        headway_secs: 1200,
+        exact_times: false,
+    });
+    Model::new(collections).unwrap()
+}
+
+#[test]
+fn frequencies_round_trip() {
+    let model = model_with_frequencies();
+    let tmp_dir = TempDir::new("navitia_model_tests").unwrap();
+    ntfs::write(&model, tmp_dir.path()).unwrap();
+    let read = ntfs::read(tmp_dir.path()).unwrap();
+    assert_eq!(
+        model.frequencies.values().collect::<Vec<_>>(),
+        read.frequencies.values().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn expand_all_frequencies() {
+    let model = expand(model_with_frequencies(), &ExpansionPolicy::default()).unwrap();
+
+    assert!(model.frequencies.is_empty());
+    assert!(model.vehicle_journeys.get("M1F1").is_none());
+    let departures = |id| {
+        let vj = model.vehicle_journeys.get(id).unwrap();
+        vj.stop_times
+            .iter()
+            .map(|st| st.departure_time)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        vec![
+            Time::new(10, 10, 0),
+            Time::new(10, 20, 0),
+            Time::new(10, 30, 0),
+            Time::new(10, 50, 0),
+        ],
+        departures("M1F1:1")
+    );
+    assert!(model.vehicle_journeys.get("M1F1:2").is_some());
+    assert!(model.vehicle_journeys.get("M1F1:3").is_none());
+    assert_eq!(Time::new(6, 40, 0), departures("B42F1:2")[0]);
+}
+
+#[test]
+fn expand_exact_frequencies_only() {
+    let policy = ExpansionPolicy {
+        expand_inexact: false,
+        id_template: "{trip_id}_{time}".to_string(),
+    };
+    let model = expand(model_with_frequencies(), &policy).unwrap();
+
+    let mut ids: Vec<_> = model
+        .vehicle_journeys
+        .values()
+        .map(|vj| vj.id.as_str())
+        .filter(|id| id.starts_with("M1F1") || id.starts_with("B42F1"))
+        .collect();
+    ids.sort();
+    assert_eq!(
+        vec!["B42F1", "M1F1_100000", "M1F1_101000", "M1F1_102000"],
+        ids
+    );
+    assert_eq!(1, model.frequencies.len());
+}
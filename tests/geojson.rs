@@ -0,0 +1,60 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+extern crate navitia_model;
+extern crate serde_json;
+extern crate tempdir;
+
+use navitia_model::export::geojson;
+use navitia_model::ntfs;
+use std::fs::File;
+use tempdir::TempDir;
+
+#[test]
+fn export_stops_and_routes() {
+    let model = ntfs::read("fixtures/minimal_ntfs").unwrap();
+    let tmp_dir = TempDir::new("navitia_model_tests").expect("create temp dir");
+    let path = tmp_dir.path().join("stops.geojson");
+    geojson::write_stops(&model, &path).unwrap();
+    let stops: serde_json::Value = serde_json::from_reader(File::open(&path).unwrap()).unwrap();
+    assert_eq!("FeatureCollection", stops["type"]);
+    let features = stops["features"].as_array().unwrap();
+    assert_eq!(
+        model.stop_areas.len() + model.stop_points.len(),
+        features.len()
+    );
+    let gdlm = features
+        .iter()
+        .find(|f| f["properties"]["id"] == "GDLM")
+        .unwrap();
+    assert_eq!("stop_point", gdlm["properties"]["type"]);
+    assert_eq!("GDL", gdlm["properties"]["stop_area_id"]);
+    assert_eq!("Point", gdlm["geometry"]["type"]);
+    assert_eq!(2.372_987, gdlm["geometry"]["coordinates"][0]);
+
+    let routes = geojson::routes(&model);
+    let m1f = routes["features"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|f| f["properties"]["route_id"] == "M1F")
+        .unwrap();
+    assert_eq!("Metro 1", m1f["properties"]["line_name"]);
+    assert_eq!("Metro", m1f["properties"]["commercial_mode"]);
+    // without geometry, the line between the stops of the route
+    assert_eq!("LineString", m1f["geometry"]["type"]);
+    assert_eq!(4, m1f["geometry"]["coordinates"].as_array().unwrap().len());
+}
@@ -0,0 +1,81 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+extern crate geo_types;
+extern crate navitia_model;
+
+use geo_types::{Geometry as GeoGeometry, LineString, Point};
+use navitia_model::geometries::{build_route_geometries, check_stops_on_shapes};
+use navitia_model::ntfs;
+use navitia_model::objects::Geometry;
+
+#[test]
+fn build_geometries_from_stops_and_shapes() {
+    let mut collections = ntfs::read("fixtures/minimal_ntfs")
+        .unwrap()
+        .into_collections();
+    let shape = LineString(vec![
+        Point::new(2.396497, 48.84849),
+        Point::new(2.36, 48.85),
+        Point::new(2.295354, 48.873965),
+    ]);
+    collections
+        .geometries
+        .push(Geometry {
+            id: "shape:M1F".to_string(),
+            geometry: GeoGeometry::LineString(shape),
+        })
+        .unwrap();
+    collections
+        .vehicle_journeys
+        .get_mut("M1F1")
+        .unwrap()
+        .geometry_id = Some("shape:M1F".to_string());
+
+    let messages: Vec<_> = check_stops_on_shapes(&collections, 200.)
+        .into_iter()
+        .map(|w| w.message)
+        .collect();
+    assert_eq!(
+        vec![
+            "stop point GDLM of vehicle journey M1F1 is 523m away from geometry shape:M1F",
+            "stop point CHAM of vehicle journey M1F1 is 363m away from geometry shape:M1F",
+        ],
+        messages
+    );
+    assert!(check_stops_on_shapes(&collections, 1000.).is_empty());
+
+    build_route_geometries(&mut collections).unwrap();
+
+    let route_geometry = |id| collections.routes.get(id).unwrap().geometry_id.clone();
+    assert_eq!(Some("shape:M1F".to_string()), route_geometry("M1F"));
+    assert_eq!(Some("route:B42F".to_string()), route_geometry("B42F"));
+    let geometry = &collections.geometries.get("route:B42F").unwrap().geometry;
+    assert_eq!(
+        GeoGeometry::LineString(LineString(vec![
+            Point::new(2.372987, 48.844746),
+            Point::new(2.321783, 48.842481),
+        ])),
+        *geometry
+    );
+
+    let line_geometry = |id| collections.lines.get(id).unwrap().geometry_id.clone();
+    assert_eq!(Some("line:B42".to_string()), line_geometry("B42"));
+    match collections.geometries.get("line:B42").unwrap().geometry {
+        GeoGeometry::MultiLineString(ref lines) => assert_eq!(2, lines.0.len()),
+        ref geometry => panic!("unexpected geometry {:?}", geometry),
+    }
+}
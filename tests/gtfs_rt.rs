@@ -0,0 +1,96 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+extern crate chrono;
+extern crate navitia_model;
+
+use chrono::FixedOffset;
+use navitia_model::gtfs_rt::{self, TripStatus};
+use navitia_model::ntfs;
+use navitia_model::objects::Time;
+
+#[test]
+fn read_gtfs_rt_feed() {
+    let model = ntfs::read("fixtures/minimal_ntfs").unwrap();
+    let updates =
+        gtfs_rt::read_from_path(&model, "fixtures/gtfs_rt/feed.pb", FixedOffset::east(3600))
+            .unwrap();
+    assert_eq!(Some(1_527_840_000), updates.timestamp);
+    // the trip M1F1 does not run on the 2018-06-02
+    assert_eq!(3, updates.trip_updates.len());
+
+    // a delay propagated to the following stops, then an absolute
+    // departure time
+    let update = &updates.trip_updates[0];
+    assert_eq!(TripStatus::Scheduled, update.status);
+    assert_eq!(
+        model.vehicle_journeys.get_idx("M1F1"),
+        update.vehicle_journey_idx
+    );
+    let times: Vec<_> = update
+        .stop_times
+        .iter()
+        .map(|st| (st.arrival_time, st.departure_time))
+        .collect();
+    assert_eq!(
+        vec![
+            (Time::new(9, 0, 0), Time::new(9, 0, 0)),
+            (Time::new(9, 12, 0), Time::new(9, 12, 0)),
+            (Time::new(9, 22, 0), Time::new(9, 22, 0)),
+            (Time::new(9, 42, 0), Time::new(9, 45, 0)),
+        ],
+        times
+    );
+
+    // a cancellation on the date of the feed
+    let update = &updates.trip_updates[1];
+    assert_eq!(TripStatus::Cancelled, update.status);
+    assert_eq!("2018-06-01", update.date.to_string());
+    assert!(update.stop_times.is_empty());
+
+    let update = &updates.trip_updates[2];
+    assert_eq!(TripStatus::Added, update.status);
+    assert_eq!(None, update.vehicle_journey_idx);
+    let stops: Vec<_> = update
+        .stop_times
+        .iter()
+        .map(|st| {
+            (
+                model.stop_points[st.stop_point_idx].id.as_str(),
+                st.arrival_time,
+            )
+        })
+        .collect();
+    assert_eq!(
+        vec![
+            ("NATM", Time::new(10, 0, 0)),
+            ("CDGM", Time::new(10, 30, 0))
+        ],
+        stops
+    );
+
+    assert_eq!(1, updates.disruptions.len());
+    let disruption = &updates.disruptions[0];
+    assert_eq!(Some("STRIKE".to_string()), disruption.cause);
+    assert_eq!(Some("NO_SERVICE".to_string()), disruption.effect);
+    assert_eq!(Some("Grève".to_string()), disruption.header);
+    assert_eq!(vec![(Some(1_527_840_000), None)], disruption.periods);
+    assert_eq!(vec![model.lines.get_idx("M1").unwrap()], disruption.lines);
+    assert_eq!(
+        vec![model.stop_points.get_idx("GDLM").unwrap()],
+        disruption.stop_points
+    );
+}
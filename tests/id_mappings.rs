@@ -0,0 +1,34 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+extern crate navitia_model;
+
+use navitia_model::export::id_mappings;
+use navitia_model::hafas;
+
+#[test]
+fn write_id_mappings_of_generated_stop_areas() {
+    let model = hafas::read("fixtures/hafas/sample", None, Some("HAFAS".into())).unwrap();
+    let mut csv = vec![];
+    id_mappings::write(&model.id_mappings, &mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert!(csv.starts_with("collection,source_id,generated_id\n"));
+    assert!(csv.contains("stop_areas,8503000,HAFAS:Navitia:8503000\n"));
+    assert_eq!(
+        model.id_mappings.len(),
+        csv.lines().filter(|l| l.starts_with("stop_areas,")).count()
+    );
+}
@@ -15,7 +15,9 @@
 // <http://www.gnu.org/licenses/>.
 
 extern crate navitia_model;
-use navitia_model::model::Collections;
+use navitia_model::merge::MergeOptions;
+use navitia_model::model::{Collections, MergeStrategies, MergeStrategy};
+use navitia_model::Model;
 
 #[test]
 #[should_panic(expected = "TGC already found")] // first collision is on contributor id
@@ -62,3 +64,113 @@ fn merge_collections_ok() {
     assert_eq!(collections.geometries.len(), 0);
     assert_eq!(collections.admin_stations.len(), 0);
 }
+
+#[test]
+fn merge_collections_keeping_first_objects() {
+    let mut collections = navitia_model::ntfs::read("fixtures/ntfs")
+        .unwrap()
+        .into_collections();
+    let mut patch = navitia_model::ntfs::read("fixtures/ntfs")
+        .unwrap()
+        .into_collections();
+    patch.networks.get_mut("TGN").unwrap().name = "The Good Network".to_string();
+    let mut strategies = MergeStrategies {
+        default: MergeStrategy::KeepFirst,
+        ..Default::default()
+    };
+    strategies
+        .insert("networks", MergeStrategy::KeepSecond)
+        .unwrap();
+    collections.merge_with(patch, &strategies).unwrap();
+    assert_eq!(collections.contributors.len(), 1);
+    assert_eq!(collections.lines.len(), 3);
+    assert_eq!(collections.vehicle_journeys.len(), 6);
+    assert_eq!(collections.comments.len(), 4);
+    assert_eq!(
+        "The Good Network",
+        collections.networks.get("TGN").unwrap().name
+    );
+    assert!(Model::new(collections).is_ok());
+}
+
+#[test]
+fn merge_collections_renaming_collisions() {
+    let mut collections = navitia_model::ntfs::read("fixtures/ntfs")
+        .unwrap()
+        .into_collections();
+    let patch = navitia_model::ntfs::read("fixtures/ntfs")
+        .unwrap()
+        .into_collections();
+    let strategies = MergeStrategies {
+        default: MergeStrategy::RenameWithSuffix(":patch".to_string()),
+        ..Default::default()
+    };
+    collections.merge_with(patch, &strategies).unwrap();
+    assert_eq!(collections.contributors.len(), 2);
+    assert_eq!(collections.lines.len(), 6);
+    assert_eq!(collections.comments.len(), 8);
+    let model = Model::new(collections).unwrap();
+
+    let vj = model.vehicle_journeys.get("M1F1:patch").unwrap();
+    assert_eq!("M1F:patch", vj.route_id);
+    assert_eq!("TGDS:patch", vj.dataset_id);
+    assert_eq!("Week:patch", vj.service_id);
+    let stops: Vec<_> = vj
+        .stop_times
+        .iter()
+        .map(|st| model.stop_points[st.stop_point_idx].id.as_str())
+        .collect();
+    assert_eq!(
+        vec!["NATM:patch", "GDLM:patch", "CHAM:patch", "CDGM:patch"],
+        stops
+    );
+    let stop_point = model.stop_points.get("NATM:patch").unwrap();
+    assert_eq!("NAT:patch", stop_point.stop_area_id);
+    let line = model.lines.get("RERA:patch").unwrap();
+    let comments: Vec<_> = model
+        .comments
+        .iter_from(&line.comment_links)
+        .map(|c| c.id.as_str())
+        .collect();
+    assert_eq!(4, comments.len());
+    assert!(comments.iter().all(|id| id.ends_with(":patch")));
+}
+
+#[test]
+fn merge_prefixed_datasets() {
+    let single = navitia_model::ntfs::read("fixtures/ntfs").unwrap();
+    let mut options = MergeOptions {
+        prefixes: vec![Some("A".to_string()), Some("B".to_string())],
+        ..Default::default()
+    };
+    // the calendars are not prefixed
+    options
+        .strategies
+        .insert("calendars", MergeStrategy::KeepFirst)
+        .unwrap();
+    let model = navitia_model::merge(&["fixtures/ntfs", "fixtures/ntfs"], &options).unwrap();
+    assert_eq!(2, model.contributors.len());
+    assert_eq!(2, model.datasets.len());
+    assert_eq!(2 * single.lines.len(), model.lines.len());
+    assert_eq!(single.physical_modes.len(), model.physical_modes.len());
+    assert_eq!(single.commercial_modes.len(), model.commercial_modes.len());
+
+    let vj = model.vehicle_journeys.get("B:M1F1").unwrap();
+    assert_eq!("B:M1F", vj.route_id);
+    assert_eq!("Metro", vj.physical_mode_id);
+    assert!(model.vehicle_journeys.get("A:M1F1").is_some());
+}
+
+#[test]
+fn merge_unprefixed_datasets() {
+    let options = MergeOptions::default();
+    let model = navitia_model::merge(&["fixtures/ntfs", "fixtures/merge-ntfs"], &options).unwrap();
+    assert_eq!(8, model.vehicle_journeys.len());
+    assert!(navitia_model::merge(&["fixtures/ntfs", "fixtures/ntfs"], &options).is_err());
+
+    let options = MergeOptions {
+        prefixes: vec![Some("A".to_string())],
+        ..Default::default()
+    };
+    assert!(navitia_model::merge(&["fixtures/ntfs", "fixtures/ntfs"], &options).is_err());
+}
@@ -0,0 +1,195 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+extern crate chrono;
+extern crate navitia_model;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use navitia_model::collection::Idx;
+use navitia_model::model::Model;
+use navitia_model::ntfs;
+use navitia_model::objects::{Route, StopPoint, VehicleJourney};
+use navitia_model::relations::IdxSet;
+
+fn route_ids(model: &Model, vj_id: &str) -> Vec<String> {
+    let idx = model.vehicle_journeys.get_idx(vj_id).unwrap();
+    let routes: IdxSet<Route> = model.get_corresponding_from_idx(idx);
+    routes
+        .into_iter()
+        .map(|r| model.routes[r].id.clone())
+        .collect()
+}
+
+#[test]
+fn apply_without_structural_change() {
+    let model = ntfs::read("fixtures/minimal_ntfs").unwrap();
+    let model = model
+        .apply(|c| {
+            c.stop_points.get_mut("GDLM").unwrap().name = "Lyon".to_string();
+            Ok(())
+        })
+        .unwrap();
+    let idx = model.stop_points.get_idx("GDLM").unwrap();
+    assert_eq!("Lyon", model.stop_points[idx].name);
+    let vjs: IdxSet<VehicleJourney> = model.get_corresponding_from_idx(idx);
+    assert_eq!(2, vjs.len());
+}
+
+#[test]
+fn apply_rebuilds_the_relations() {
+    let model = ntfs::read("fixtures/minimal_ntfs").unwrap();
+    assert_eq!(vec!["M1F"], route_ids(&model, "M1F1"));
+    let model = model
+        .apply(|c| {
+            c.vehicle_journeys.get_mut("M1F1").unwrap().route_id = "M1B".into();
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(vec!["M1B"], route_ids(&model, "M1F1"));
+    let idx = model.routes.get_idx("M1B").unwrap();
+    let stop_points: IdxSet<StopPoint> = model.get_corresponding_from_idx(idx);
+    assert!(!stop_points.is_empty());
+}
+
+#[test]
+fn apply_checks_the_references() {
+    let model = ntfs::read("fixtures/minimal_ntfs").unwrap();
+    let result = model.apply(|c| {
+        c.vehicle_journeys.get_mut("M1F1").unwrap().route_id = "unknown".into();
+        Ok(())
+    });
+    assert!(result.is_err());
+}
+
+fn ids<'a, T: 'a, I>(objects: I, id: fn(&T) -> &str) -> Vec<&'a str>
+where
+    I: Iterator<Item = (Idx<T>, &'a T)>,
+{
+    let mut ids: Vec<_> = objects.map(|(_, o)| id(o)).collect();
+    ids.sort();
+    ids
+}
+
+#[test]
+fn relation_queries() {
+    let model = ntfs::read("fixtures/minimal_ntfs").unwrap();
+    let m1 = model.lines.get_idx("M1").unwrap();
+    assert_eq!(
+        vec!["CDGM", "CHAM", "GDLM", "NATM"],
+        ids(model.stop_points_of_line(m1), |sp| &sp.id)
+    );
+    assert_eq!(
+        vec!["CDG", "CHA", "GDL", "NAT"],
+        ids(model.stop_areas_of_line(m1), |sa| &sa.id)
+    );
+    assert_eq!(
+        vec!["M1B1", "M1F1"],
+        ids(model.vehicle_journeys_of_line(m1), |vj| &vj.id)
+    );
+    let gdl = model.stop_areas.get_idx("GDL").unwrap();
+    assert_eq!(
+        vec!["B42", "M1", "RERA"],
+        ids(model.lines_of_stop_area(gdl), |l| &l.id)
+    );
+    let natm = model.stop_points.get_idx("NATM").unwrap();
+    assert_eq!(vec!["M1"], ids(model.lines_of_stop_point(natm), |l| &l.id));
+
+    let monday = NaiveDate::from_ymd(2018, 1, 1);
+    assert_eq!(6, model.vehicle_journeys_on_date(monday).count());
+    let saturday = NaiveDate::from_ymd(2018, 1, 6);
+    assert_eq!(0, model.vehicle_journeys_on_date(saturday).count());
+}
+
+#[test]
+fn vehicle_journeys_active_between() {
+    let model = ntfs::read("fixtures/minimal_ntfs").unwrap();
+    let calendar = model.calendars.get("Week").unwrap();
+    assert!(calendar.is_active_on(NaiveDate::from_ymd(2018, 1, 5)));
+    assert!(!calendar.is_active_on(NaiveDate::from_ymd(2018, 1, 6)));
+
+    let saturday = NaiveDate::from_ymd(2018, 1, 6);
+    let sunday = NaiveDate::from_ymd(2018, 1, 7);
+    let monday = NaiveDate::from_ymd(2018, 1, 8);
+    assert_eq!(
+        0,
+        model
+            .vehicle_journeys_active_between(saturday, sunday)
+            .count()
+    );
+    assert_eq!(
+        6,
+        model
+            .vehicle_journeys_active_between(saturday, monday)
+            .count()
+    );
+}
+
+#[test]
+fn next_departures() {
+    let model = ntfs::read("fixtures/minimal_ntfs").unwrap();
+    let departures = |stop_id: &str, datetime: &str, limit: usize| -> Vec<(String, String)> {
+        let stop_point_idx = model.stop_points.get_idx(stop_id).unwrap();
+        let datetime = NaiveDateTime::parse_from_str(datetime, "%Y-%m-%d %H:%M").unwrap();
+        model
+            .next_departures(stop_point_idx, datetime, limit)
+            .into_iter()
+            .map(|d| {
+                let vj = &model.vehicle_journeys[d.vehicle_journey_idx];
+                (d.datetime.to_string(), vj.id.clone())
+            })
+            .collect()
+    };
+    let expected = |v: &[(&str, &str)]| -> Vec<(String, String)> {
+        v.iter()
+            .map(|&(dt, vj)| (dt.to_string(), vj.to_string()))
+            .collect()
+    };
+
+    assert_eq!(
+        expected(&[
+            ("2018-01-01 09:10:00", "M1F1"),
+            ("2018-01-01 11:00:00", "M1B1"),
+            ("2018-01-02 09:10:00", "M1F1"),
+        ]),
+        departures("GDLM", "2018-01-01 08:00", 3)
+    );
+    // the terminus of M1B1 is not a departure, no service on week-ends
+    assert_eq!(
+        expected(&[
+            ("2018-01-08 09:00:00", "M1F1"),
+            ("2018-01-09 09:00:00", "M1F1"),
+        ]),
+        departures("NATM", "2018-01-05 09:01", 2)
+    );
+    assert!(departures("NATM", "2019-01-01 00:00", 2).is_empty());
+}
+
+#[test]
+fn interned_references() {
+    let model = ntfs::read("fixtures/minimal_ntfs").unwrap();
+    let service_id = |vj_id: &str| {
+        model
+            .vehicle_journeys
+            .get(vj_id)
+            .unwrap()
+            .service_id
+            .as_ptr()
+    };
+    assert_eq!(service_id("M1F1"), service_id("B42B1"));
+    assert_eq!(service_id("M1F1"), service_id("RERAB1"));
+    let line_id = |route_id: &str| model.routes.get(route_id).unwrap().line_id.as_ptr();
+    assert_eq!(line_id("M1F"), line_id("M1B"));
+}
@@ -0,0 +1,112 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+extern crate chrono;
+extern crate navitia_model;
+
+use chrono::NaiveDate;
+use navitia_model::model::Collections;
+use navitia_model::ntfs;
+use navitia_model::objects::Time;
+use navitia_model::overnight::{normalize_overnight, OvernightPolicy};
+
+// The minimal NTFS with M1F1 starting at 33:00 and M1B1 crossing
+// midnight.
+fn overnight_collections() -> Collections {
+    let mut collections = ntfs::read("fixtures/minimal_ntfs")
+        .unwrap()
+        .into_collections();
+    for &(vj_id, hours) in &[("M1F1", 24), ("M1B1", 13)] {
+        let mut vj = collections.vehicle_journeys.get_mut(vj_id).unwrap();
+        for st in &mut vj.stop_times {
+            st.arrival_time = Time::new(
+                st.arrival_time.hours() + hours,
+                st.arrival_time.minutes(),
+                0,
+            );
+            st.departure_time = Time::new(
+                st.departure_time.hours() + hours,
+                st.departure_time.minutes(),
+                0,
+            );
+        }
+    }
+    collections
+}
+
+fn times(collections: &Collections, vj_id: &str) -> Vec<Time> {
+    let vj = collections.vehicle_journeys.get(vj_id).unwrap();
+    vj.stop_times.iter().map(|st| st.departure_time).collect()
+}
+
+#[test]
+fn keep_overnight_vehicle_journeys() {
+    let mut collections = overnight_collections();
+    assert_eq!(
+        0,
+        normalize_overnight(&mut collections, OvernightPolicy::Keep).unwrap()
+    );
+    assert_eq!(Time::new(33, 0, 0), times(&collections, "M1F1")[0]);
+}
+
+#[test]
+fn shift_overnight_vehicle_journeys() {
+    let mut collections = overnight_collections();
+    assert_eq!(
+        1,
+        normalize_overnight(&mut collections, OvernightPolicy::Shift).unwrap()
+    );
+
+    let vj = collections.vehicle_journeys.get("M1F1").unwrap();
+    assert_eq!(Time::new(9, 0, 0), vj.stop_times[0].departure_time);
+    assert_eq!("Week:+1", vj.service_id);
+    let calendar = collections.calendars.get("Week:+1").unwrap();
+    assert_eq!(
+        Some(&NaiveDate::from_ymd(2018, 1, 2)),
+        calendar.dates.iter().next()
+    );
+    assert_eq!(Time::new(24, 10, 0), times(&collections, "M1B1")[3]);
+}
+
+#[test]
+fn split_overnight_vehicle_journeys() {
+    let mut collections = overnight_collections();
+    assert_eq!(
+        2,
+        normalize_overnight(&mut collections, OvernightPolicy::Split).unwrap()
+    );
+
+    assert_eq!(
+        vec![Time::new(23, 40, 0), Time::new(23, 50, 0)],
+        times(&collections, "M1B1")
+    );
+    assert_eq!(
+        vec![Time::new(0, 0, 0), Time::new(0, 10, 0)],
+        times(&collections, "M1B1:1")
+    );
+    assert_eq!(
+        "Week:+1",
+        collections
+            .vehicle_journeys
+            .get("M1B1:1")
+            .unwrap()
+            .service_id
+    );
+    assert_eq!(
+        "Week",
+        collections.vehicle_journeys.get("M1B1").unwrap().service_id
+    );
+}
@@ -0,0 +1,110 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+extern crate navitia_model;
+
+use navitia_model::diff::diff;
+use navitia_model::model::Collections;
+use navitia_model::ntfs;
+use navitia_model::objects::PrefixConfiguration;
+use navitia_model::Model;
+
+fn minimal_ntfs() -> Collections {
+    ntfs::read("fixtures/minimal_ntfs")
+        .unwrap()
+        .into_collections()
+}
+
+#[test]
+fn add_prefix_with_separator_and_exemptions() {
+    let mut collections = minimal_ntfs();
+    let mut prefix_conf = PrefixConfiguration::new("pfx");
+    prefix_conf.separator = "_".to_string();
+    prefix_conf
+        .exempt_collections
+        .insert("commercial_modes".to_string());
+    prefix_conf.exempt_patterns.push("GDL*".to_string());
+    collections.add_prefix(&prefix_conf).unwrap();
+
+    let line = collections.lines.get("pfx_M1").unwrap();
+    assert_eq!("pfx_TGN", line.network_id);
+    assert_eq!("Metro", line.commercial_mode_id);
+    assert!(collections.commercial_modes.get("Metro").is_some());
+    let gdlm = collections.stop_points.get("GDLM").unwrap();
+    assert_eq!("GDL", gdlm.stop_area_id);
+    assert_eq!(
+        "pfx_NAT",
+        collections
+            .stop_points
+            .get("pfx_NATM")
+            .unwrap()
+            .stop_area_id
+    );
+    // the physical modes are never prefixed
+    assert_eq!(
+        "Metro",
+        collections
+            .vehicle_journeys
+            .get("pfx_M1F1")
+            .unwrap()
+            .physical_mode_id
+    );
+}
+
+#[test]
+fn add_prefix_with_unknown_exempt_collection() {
+    let mut collections = minimal_ntfs();
+    let mut prefix_conf = PrefixConfiguration::new("pfx");
+    prefix_conf.exempt_collections.insert("modes".to_string());
+    assert!(collections.add_prefix(&prefix_conf).is_err());
+}
+
+#[test]
+fn remove_prefix() {
+    let mut collections = minimal_ntfs();
+    let prefix_conf = PrefixConfiguration::new("pfx");
+    collections.add_prefix(&prefix_conf).unwrap();
+    collections.remove_prefix(&prefix_conf).unwrap();
+
+    let original = Model::new(minimal_ntfs()).unwrap();
+    let unprefixed = Model::new(collections).unwrap();
+    assert!(diff(&original, &unprefixed).is_empty());
+}
+
+#[test]
+fn replace_prefix() {
+    let mut collections = minimal_ntfs();
+    let old_prefix_conf = PrefixConfiguration::new("old");
+    collections.add_prefix(&old_prefix_conf).unwrap();
+    let mut new_prefix_conf = PrefixConfiguration::new("new");
+    new_prefix_conf.separator = "-".to_string();
+    new_prefix_conf
+        .exempt_collections
+        .insert("networks".to_string());
+    collections
+        .replace_prefix(&old_prefix_conf, &new_prefix_conf)
+        .unwrap();
+
+    let line = collections.lines.get("new-M1").unwrap();
+    assert_eq!("TGN", line.network_id);
+    assert_eq!("new-Metro", line.commercial_mode_id);
+    assert!(collections.networks.get("TGN").is_some());
+    let vj = collections.vehicle_journeys.get("new-M1F1").unwrap();
+    assert_eq!("new-M1F", vj.route_id);
+    // the physical modes, never prefixed, are left as is
+    assert_eq!("Metro", vj.physical_mode_id);
+    assert!(Model::new(collections).is_ok());
+}
@@ -0,0 +1,94 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+extern crate navitia_model;
+
+use navitia_model::hafas;
+use navitia_model::objects::Time;
+
+#[test]
+fn read_hafas_directory() {
+    let model = hafas::read("fixtures/hafas/sample", None, Some("HAFAS".into())).unwrap();
+    assert_eq!(3, model.stop_points.len());
+    assert_eq!(3, model.stop_areas.len());
+    let stop_point = model.stop_points.get("HAFAS:8503000").unwrap();
+    assert_eq!("Zürich HB", stop_point.name);
+    assert_eq!("HAFAS:Navitia:8503000", stop_point.stop_area_id);
+    assert!((stop_point.coord.lat - 47.378_177).abs() < 1e-6);
+
+    let company = model.companies.get("HAFAS:000011").unwrap();
+    assert_eq!("Schweizerische Bundesbahnen SBB", company.name);
+    let line = model.lines.get("HAFAS:000011:1").unwrap();
+    assert_eq!("HAFAS:IC", line.commercial_mode_id);
+    // a journey without line is in the line of its category
+    assert!(model.lines.get("HAFAS:000011:IR").is_some());
+    let route = model.routes.get("HAFAS:000011:1:8503000:8504100").unwrap();
+    assert_eq!("Fribourg/Freiburg", route.name);
+    assert_eq!(Some("forward".to_string()), route.direction_type);
+
+    let dataset = model.datasets.values().next().unwrap();
+    assert_eq!("2018-06-01", dataset.start_date.to_string());
+    assert_eq!("2018-06-03", dataset.end_date.to_string());
+
+    // a journey repeated 3 times, every hour
+    let departures: Vec<_> = ["", ":1", ":2", ":3"]
+        .iter()
+        .map(|suffix| {
+            let id = format!("HAFAS:000011:000001{}", suffix);
+            model.vehicle_journeys.get(&id).unwrap().stop_times[0].departure_time
+        })
+        .collect();
+    assert_eq!(
+        vec![
+            Time::new(8, 0, 0),
+            Time::new(9, 0, 0),
+            Time::new(10, 0, 0),
+            Time::new(11, 0, 0),
+        ],
+        departures
+    );
+    let vj = model.vehicle_journeys.get("HAFAS:000011:000001:2").unwrap();
+    assert_eq!("LongDistanceTrain", vj.physical_mode_id);
+    assert_eq!(Time::new(10, 56, 0), vj.stop_times[1].arrival_time);
+    assert_eq!(Time::new(11, 22, 0), vj.stop_times[2].arrival_time);
+
+    let vj = model.vehicle_journeys.get("HAFAS:000011:000002").unwrap();
+    let dates = &model.calendars.get(&vj.service_id).unwrap().dates;
+    assert_eq!(
+        vec!["2018-06-01", "2018-06-03"],
+        dates.iter().map(|d| d.to_string()).collect::<Vec<_>>()
+    );
+    let stop_times: Vec<_> = vj
+        .stop_times
+        .iter()
+        .map(|st| {
+            (
+                st.arrival_time,
+                st.departure_time,
+                st.pickup_type,
+                st.drop_off_type,
+            )
+        })
+        .collect();
+    assert_eq!(
+        vec![
+            (Time::new(23, 30, 0), Time::new(23, 30, 0), 0, 0),
+            (Time::new(23, 52, 0), Time::new(23, 55, 0), 1, 1),
+            (Time::new(24, 56, 0), Time::new(24, 56, 0), 0, 0),
+        ],
+        stop_times
+    );
+}
@@ -19,6 +19,9 @@ extern crate navitia_model;
 extern crate tempdir;
 extern crate zip;
 
+use navitia_model::objects::Time;
+use navitia_model::read_utils::MemoryFileHandler;
+use navitia_model::report::ParseProfile;
 use navitia_model::Result;
 use std::fs;
 use std::io::Read;
@@ -62,21 +65,79 @@ where
 }
 
 #[test]
-#[should_panic(expected = "No valid calendar in Netex Data")] // for the moment, reading calendars is not implemented
 fn ratp_line7bis() {
     let input_data = "fixtures/netex/RATP_Line7bis-extract-2009-NeTEx.zip";
     let expected_result_file = "fixtures/netex/expected_result/ratp_result.zip";
 
     let read_result = navitia_model::netex::read(Path::new(input_data), None, None);
     assert!(read_result.is_ok(), "{:?}", read_result.err().unwrap());
+    let model = read_result.unwrap();
+    assert_eq!(4, model.vehicle_journeys.len());
+    let dataset = model.datasets.values().next().unwrap();
+    assert_eq!("2009-08-31", dataset.start_date.to_string());
+    assert_eq!("2009-12-17", dataset.end_date.to_string());
+
+    let vj = model
+        .vehicle_journeys
+        .get("RATP_PIVI:VehicleJourney:18798666")
+        .unwrap();
+    assert_eq!("RATP_PIVI:Route:317452", vj.route_id);
+    assert_eq!("RATP_PIVI:DayType:385159", vj.service_id);
+    assert_eq!("RATP_PIVI:Company:100", vj.company_id);
+    assert_eq!("Metro", vj.physical_mode_id);
+    assert_eq!(7, vj.stop_times.len());
+    let first = &vj.stop_times[0];
+    let stop_point = &model.stop_points[first.stop_point_idx];
+    assert_eq!("RATP_PIVI:Quay:5246063", stop_point.id);
+    let stop_area = model.stop_areas.get(&stop_point.stop_area_id).unwrap();
+    assert_eq!("RATP_PIVI:StopArea:430395", stop_area.id);
+    assert_eq!("Pré-Saint-Gervais", stop_area.name);
+    assert_eq!(Time::new(5, 31, 0), first.departure_time);
     let tmp_dir = TempDir::new("netex_computed_result").unwrap();
     let file_path = tmp_dir.path().join("netex_computed_result_ratp.zip");
-    navitia_model::ntfs::write_to_zip(&read_result.unwrap(), file_path.clone()).unwrap();
+    navitia_model::ntfs::write_to_zip(&model, file_path.clone()).unwrap();
     compare_ntfs_zips(expected_result_file, file_path.as_path()).unwrap();
 }
 
 #[test]
-#[should_panic(expected = "No valid calendar in Netex Data")] // for the moment, reading calendars is not implemented
+fn read_netex_directory() {
+    let input_data = "fixtures/netex/RATP_Line7bis-extract-2009-NeTEx.zip";
+    let input_dir = TempDir::new("netex_input").unwrap();
+    let mut handler = MemoryFileHandler::new("memory");
+    let mut zip = zip::ZipArchive::new(fs::File::open(input_data).unwrap()).unwrap();
+    for i in 0..zip.len() {
+        let mut file = zip.by_index(i).unwrap();
+        let mut content = vec![];
+        file.read_to_end(&mut content).unwrap();
+        fs::write(input_dir.path().join(file.sanitized_name()), &content).unwrap();
+        handler.insert(file.name(), content);
+    }
+    fs::write(input_dir.path().join("README.txt"), "not a netex file").unwrap();
+    handler.insert("README.txt", "not a netex file");
+
+    let from_zip = navitia_model::netex::read(Path::new(input_data), None, None).unwrap();
+    let from_dir = navitia_model::netex::read(input_dir.path(), None, None).unwrap();
+    let (from_memory, _) = navitia_model::netex::read_from_handler(
+        &mut handler,
+        None::<&str>,
+        None,
+        &ParseProfile::default(),
+    ).unwrap();
+    for model in &[from_dir, from_memory] {
+        assert_eq!(
+            from_zip.vehicle_journeys.len(),
+            model.vehicle_journeys.len()
+        );
+        assert_eq!(from_zip.stop_points.len(), model.stop_points.len());
+        assert_eq!(
+            from_zip.datasets.values().next().unwrap().end_date,
+            model.datasets.values().next().unwrap().end_date
+        );
+    }
+}
+
+#[test]
+#[should_panic(expected = "No valid calendar in Netex Data")] // the day types of the example have no date
 fn read_netex_oslo() {
     let input_data = "fixtures/netex/Full_PublicationDelivery_109_Oslo_morningbus_example.xml";
     let expected_result_file = "fixtures/netex/expected_result/oslo_result.zip";
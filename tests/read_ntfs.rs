@@ -14,11 +14,20 @@
 // along with this program.  If not, see
 // <http://www.gnu.org/licenses/>.
 
+extern crate flate2;
+extern crate geo_types;
 extern crate navitia_model;
+extern crate tar;
+extern crate tempdir;
+use geo_types::{Geometry as GeoGeometry, LineString, Polygon};
 use navitia_model::collection::{CollectionWithId, Id, Idx};
 use navitia_model::model::{GetCorresponding, Model};
 use navitia_model::objects::*;
+use navitia_model::read_utils::MemoryFileHandler;
 use navitia_model::relations::IdxSet;
+use navitia_model::report::ParseProfile;
+use std::fs::{self, File};
+use tempdir::TempDir;
 
 fn get<T, U>(idx: Idx<T>, collection: &CollectionWithId<U>, objects: &Model) -> Vec<String>
 where
@@ -131,3 +140,77 @@ fn ntfs() {
     );
     assert_eq!(iter.next(), None);
 }
+
+fn assert_same_model(expected: &Model, actual: &Model) {
+    assert_eq!(expected.stop_points, actual.stop_points);
+    assert_eq!(expected.lines, actual.lines);
+    assert_eq!(expected.vehicle_journeys, actual.vehicle_journeys);
+    assert_eq!(expected.calendars, actual.calendars);
+}
+
+#[test]
+fn read_from_archives() {
+    let ntm = navitia_model::ntfs::read("fixtures/minimal_ntfs/").unwrap();
+    let tmp_dir = TempDir::new("navitia_model_tests").unwrap();
+
+    let zip_path = tmp_dir.path().join("ntfs.zip");
+    navitia_model::ntfs::write_to_zip(&ntm, &zip_path).unwrap();
+    assert_same_model(&ntm, &navitia_model::ntfs::read(&zip_path).unwrap());
+
+    let tar_gz_path = tmp_dir.path().join("ntfs.tar.gz");
+    let encoder = flate2::write::GzEncoder::new(
+        File::create(&tar_gz_path).unwrap(),
+        flate2::Compression::default(),
+    );
+    let mut builder = tar::Builder::new(encoder);
+    builder
+        .append_dir_all("ntfs", "fixtures/minimal_ntfs")
+        .unwrap();
+    builder.into_inner().unwrap().finish().unwrap();
+    assert_same_model(&ntm, &navitia_model::ntfs::read(&tar_gz_path).unwrap());
+
+    assert!(navitia_model::ntfs::read(tmp_dir.path().join("ntfs.txt")).is_err());
+}
+
+#[test]
+fn read_from_memory() {
+    let ntm = navitia_model::ntfs::read("fixtures/minimal_ntfs/").unwrap();
+    let mut handler = MemoryFileHandler::new("memory");
+    for entry in fs::read_dir("fixtures/minimal_ntfs").unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        handler.insert(name, fs::read(&path).unwrap());
+    }
+    let (model, _) =
+        navitia_model::ntfs::read_from_handler(&mut handler, &ParseProfile::Permissive).unwrap();
+    assert_same_model(&ntm, &model);
+}
+
+#[test]
+fn fare_zones_round_trip() {
+    let mut collections = navitia_model::ntfs::read("fixtures/minimal_ntfs/")
+        .unwrap()
+        .into_collections();
+    let ring = LineString::from(vec![(2.3, 48.8), (2.4, 48.8), (2.4, 48.9), (2.3, 48.8)]);
+    collections
+        .geometries
+        .push(Geometry {
+            id: "zone_1_geometry".to_string(),
+            geometry: GeoGeometry::Polygon(Polygon::new(ring, vec![])),
+        })
+        .unwrap();
+    collections
+        .fare_zones
+        .push(FareZone {
+            id: "zone_1".to_string(),
+            geometry_id: "zone_1_geometry".to_string(),
+        })
+        .unwrap();
+    let model = Model::new(collections).unwrap();
+
+    let tmp_dir = TempDir::new("navitia_model_tests").unwrap();
+    navitia_model::ntfs::write(&model, tmp_dir.path()).unwrap();
+    let written = navitia_model::ntfs::read(tmp_dir.path()).unwrap();
+    assert_eq!(model.fare_zones, written.fare_zones);
+    assert!(written.geometries.get("zone_1_geometry").is_some());
+}
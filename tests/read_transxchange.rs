@@ -0,0 +1,107 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+extern crate navitia_model;
+
+use navitia_model::objects::Time;
+use navitia_model::read_utils::MemoryFileHandler;
+use navitia_model::transxchange;
+use std::fs;
+
+#[test]
+fn read_transxchange_directory() {
+    let model =
+        transxchange::read_from_path("fixtures/transxchange/sample", None, Some("TXC".into()))
+            .unwrap();
+    assert_eq!(3, model.stop_points.len());
+    assert_eq!(3, model.stop_areas.len());
+    let line = model.lines.get("TXC:YB_1:L1").unwrap();
+    assert_eq!(Some("1".to_string()), line.code);
+    assert_eq!("Bus Station - Hospital", line.name);
+    assert_eq!("TXC:YBUS", line.network_id);
+    let route = model.routes.get("TXC:YB_1:L1:outbound").unwrap();
+    assert_eq!("Hospital", route.name);
+
+    let dataset = model.datasets.values().next().unwrap();
+    assert_eq!("2018-06-01", dataset.start_date.to_string());
+    assert_eq!("2018-06-30", dataset.end_date.to_string());
+
+    let vj = model.vehicle_journeys.get("TXC:YB_1:VJ1").unwrap();
+    assert_eq!("TXC:YBUS", vj.company_id);
+    assert_eq!("Bus", vj.physical_mode_id);
+    assert_eq!(21, model.calendars.get(&vj.service_id).unwrap().dates.len());
+    let stop_times: Vec<_> = vj
+        .stop_times
+        .iter()
+        .map(|st| {
+            (
+                model.stop_points[st.stop_point_idx].id.as_str(),
+                st.arrival_time,
+                st.departure_time,
+                st.pickup_type,
+                st.drop_off_type,
+            )
+        })
+        .collect();
+    assert_eq!(
+        vec![
+            (
+                "TXC:490000001A",
+                Time::new(8, 0, 0),
+                Time::new(8, 0, 0),
+                0,
+                1
+            ),
+            (
+                "TXC:490000002B",
+                Time::new(8, 4, 0),
+                Time::new(8, 5, 0),
+                0,
+                0
+            ),
+            (
+                "TXC:490000003C",
+                Time::new(8, 11, 30),
+                Time::new(8, 11, 30),
+                1,
+                0
+            ),
+        ],
+        stop_times
+    );
+
+    // a saturday journey running after midnight, except on the 16th
+    let vj = model.vehicle_journeys.get("TXC:YB_1:VJ2").unwrap();
+    assert_eq!(4, model.calendars.get(&vj.service_id).unwrap().dates.len());
+    assert_eq!(Time::new(24, 6, 30), vj.stop_times[2].arrival_time);
+}
+
+#[test]
+fn read_transxchange_from_memory() {
+    let mut handler = MemoryFileHandler::new("memory");
+    let content = fs::read("fixtures/transxchange/sample/SVC1.xml").unwrap();
+    handler.insert("SVC1.xml", content);
+    handler.insert("README.txt", "not a TransXChange file");
+
+    let from_memory =
+        transxchange::read_from_handler(&mut handler, None::<&str>, Some("TXC".into())).unwrap();
+    let from_path =
+        transxchange::read_from_path("fixtures/transxchange/sample", None, Some("TXC".into()))
+            .unwrap();
+    assert_eq!(from_path.lines, from_memory.lines);
+    assert_eq!(from_path.stop_points, from_memory.stop_points);
+    assert_eq!(from_path.vehicle_journeys, from_memory.vehicle_journeys);
+}
@@ -0,0 +1,103 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+extern crate navitia_model;
+
+use navitia_model::objects::Time;
+use navitia_model::vdv452;
+
+#[test]
+fn read_vdv452_directory() {
+    let model = vdv452::read("fixtures/vdv452/sample", None, Some("VDV".into())).unwrap();
+    assert_eq!(3, model.stop_points.len());
+    assert_eq!(2, model.stop_areas.len());
+    let stop_area = model.stop_areas.get("VDV:100").unwrap();
+    assert_eq!("Hauptbahnhof", stop_area.name);
+    assert!((stop_area.coord.lat - 52.526_389).abs() < 1e-6);
+    assert_eq!(
+        "Rathaus \"Mitte\"",
+        model.stop_areas.get("VDV:200").unwrap().name
+    );
+
+    let line = model.lines.get("VDV:42").unwrap();
+    assert_eq!(Some("42".to_string()), line.code);
+    assert_eq!("VDV:7", line.network_id);
+    let route = model.routes.get("VDV:42:2").unwrap();
+    assert_eq!("Rathaus - Hauptbahnhof", route.name);
+    assert_eq!(Some("backward".to_string()), route.direction_type);
+
+    let dataset = model.datasets.values().next().unwrap();
+    assert_eq!("2018-06-01", dataset.start_date.to_string());
+    assert_eq!("2018-06-04", dataset.end_date.to_string());
+
+    let stop_times = |id: &str| -> Vec<_> {
+        model
+            .vehicle_journeys
+            .get(id)
+            .unwrap()
+            .stop_times
+            .iter()
+            .map(|st| {
+                (
+                    model.stop_points[st.stop_point_idx].id.clone(),
+                    st.arrival_time,
+                    st.departure_time,
+                )
+            })
+            .collect()
+    };
+    // the depot is not a stop, but its running times are counted
+    assert_eq!(
+        vec![
+            (
+                "VDV:1001".to_string(),
+                Time::new(6, 0, 0),
+                Time::new(6, 0, 0)
+            ),
+            (
+                "VDV:2001".to_string(),
+                Time::new(6, 5, 0),
+                Time::new(6, 5, 30)
+            ),
+        ],
+        stop_times("VDV:5001")
+    );
+    // the waiting time of the trip overrides the one of its timing group
+    assert_eq!(
+        vec![
+            (
+                "VDV:2001".to_string(),
+                Time::new(7, 0, 0),
+                Time::new(7, 1, 0)
+            ),
+            (
+                "VDV:1002".to_string(),
+                Time::new(7, 5, 0),
+                Time::new(7, 5, 0)
+            ),
+        ],
+        stop_times("VDV:5002")
+    );
+
+    let vj = model.vehicle_journeys.get("VDV:5003").unwrap();
+    assert_eq!("VDV:7", vj.company_id);
+    let dates = &model.calendars.get(&vj.service_id).unwrap().dates;
+    assert_eq!(
+        vec!["2018-06-02"],
+        dates.iter().map(|d| d.to_string()).collect::<Vec<_>>()
+    );
+    assert_eq!(Time::new(24, 5, 30), vj.stop_times[1].departure_time);
+}
@@ -0,0 +1,132 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+extern crate geo_types;
+extern crate navitia_model;
+extern crate tempdir;
+
+use geo_types::{Geometry as GeoGeometry, Point};
+use navitia_model::ntfs;
+use navitia_model::objects::*;
+use navitia_model::Model;
+use tempdir::TempDir;
+
+#[test]
+fn sanitize_ids_and_write() {
+    let mut collections = ntfs::read("fixtures/ntfs").unwrap().into_collections();
+    let mut prefix_conf = PrefixConfiguration::new("pfx");
+    prefix_conf.separator = ";".to_string();
+    collections.add_prefix(&prefix_conf).unwrap();
+    collections
+        .equipments
+        .push(Equipment {
+            id: "eq;1".to_string(),
+            wheelchair_boarding: Availability::Available,
+            sheltered: Availability::default(),
+            elevator: Availability::default(),
+            escalator: Availability::default(),
+            bike_accepted: Availability::default(),
+            bike_depot: Availability::default(),
+            visual_announcement: Availability::default(),
+            audible_announcement: Availability::default(),
+            appropriate_escort: Availability::default(),
+            appropriate_signage: Availability::default(),
+        })
+        .unwrap();
+    collections
+        .trip_properties
+        .push(TripProperty {
+            id: "tp;1".to_string(),
+            wheelchair_accessible: Availability::Available,
+            bike_accepted: Availability::default(),
+            air_conditioned: Availability::default(),
+            visual_announcement: Availability::default(),
+            audible_announcement: Availability::default(),
+            appropriate_escort: Availability::default(),
+            appropriate_signage: Availability::default(),
+            school_vehicle_type: TransportType::default(),
+        })
+        .unwrap();
+    collections
+        .geometries
+        .push(Geometry {
+            id: "geo;1".to_string(),
+            geometry: GeoGeometry::Point(Point::new(2.37, 48.84)),
+        })
+        .unwrap();
+    let mut calendar = Calendar::new("cal;1".to_string());
+    calendar.dates = collections.calendars.get("Week").unwrap().dates.clone();
+    collections.calendars.push(calendar).unwrap();
+    {
+        let mut vj = collections.vehicle_journeys.get_mut("pfx;M1F1").unwrap();
+        vj.service_id = "cal;1".into();
+        vj.trip_property_id = Some("tp;1".to_string());
+        vj.geometry_id = Some("geo;1".to_string());
+    }
+    collections
+        .stop_points
+        .get_mut("pfx;GDLM")
+        .unwrap()
+        .equipment_id = Some("eq;1".to_string());
+    let model = Model::new(collections).unwrap();
+    assert!(ntfs::check_ids(&model).is_err());
+
+    let model = ntfs::sanitize_ids(model).unwrap();
+    assert!(ntfs::check_ids(&model).is_ok());
+    assert!(model
+        .id_mappings
+        .values()
+        .any(|m| m.collection == "calendars" && m.source_id == "cal;1"));
+
+    let tmp_dir = TempDir::new("navitia_model_tests").unwrap();
+    ntfs::write(&model, tmp_dir.path()).unwrap();
+    let model = ntfs::read(tmp_dir.path()).unwrap();
+
+    let vj = model.vehicle_journeys.get("pfx_M1F1").unwrap();
+    assert_eq!(
+        vec![(ntfs::ORIGINAL_ID_SYSTEM.to_string(), "pfx;M1F1".to_string())],
+        vj.codes
+    );
+    assert_eq!("pfx_M1F", vj.route_id);
+    assert_eq!("cal_1", vj.service_id);
+    assert!(model.calendars.get(&vj.service_id).is_some());
+    assert_eq!("pfx_TGC", vj.company_id);
+    assert!(model.companies.get(&vj.company_id).is_some());
+    assert_eq!("pfx_TGDS", vj.dataset_id);
+    assert_eq!(Some("tp_1"), vj.trip_property_id.as_deref());
+    assert!(model.trip_properties.get("tp_1").is_some());
+    assert_eq!(Some("geo_1"), vj.geometry_id.as_deref());
+    assert!(model.geometries.get("geo_1").is_some());
+    let stop_point = model.stop_points.get("pfx_GDLM").unwrap();
+    assert_eq!("pfx_GDL", stop_point.stop_area_id);
+    assert_eq!(Some("eq_1"), stop_point.equipment_id.as_deref());
+    assert!(model.equipments.get("eq_1").is_some());
+    let line = model.lines.get("pfx_RERA").unwrap();
+    let comment_ids: Vec<_> = model
+        .comments
+        .iter_from(&line.comment_links)
+        .map(|c| c.id.as_str())
+        .collect();
+    assert_eq!(
+        vec![
+            "pfx_RERACOM1",
+            "pfx_RERACOM2",
+            "pfx_RERACOM3",
+            "pfx_RERACOM4"
+        ],
+        comment_ids
+    );
+}
@@ -0,0 +1,91 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+extern crate navitia_model;
+
+use navitia_model::gtfs_rt::TripStatus;
+use navitia_model::ntfs;
+use navitia_model::objects::Time;
+use navitia_model::siri;
+
+#[test]
+fn read_estimated_timetable() {
+    let model = ntfs::read("fixtures/minimal_ntfs").unwrap();
+    let updates = siri::read_from_path(&model, "fixtures/siri/estimated_timetable.xml").unwrap();
+    // the unknown journey is ignored
+    assert_eq!(3, updates.trip_updates.len());
+
+    let update = &updates.trip_updates[0];
+    assert_eq!(TripStatus::Scheduled, update.status);
+    assert_eq!(
+        model.vehicle_journeys.get_idx("M1F1"),
+        update.vehicle_journey_idx
+    );
+    let times: Vec<_> = update
+        .stop_times
+        .iter()
+        .map(|st| (st.arrival_time, st.departure_time, st.skipped))
+        .collect();
+    assert_eq!(
+        vec![
+            (Time::new(9, 0, 0), Time::new(9, 1, 0), false),
+            (Time::new(9, 11, 0), Time::new(9, 11, 0), false),
+            (Time::new(9, 25, 0), Time::new(9, 26, 0), false),
+            (Time::new(9, 46, 0), Time::new(9, 46, 0), true),
+        ],
+        times
+    );
+
+    let update = &updates.trip_updates[1];
+    assert_eq!(TripStatus::Cancelled, update.status);
+    assert_eq!("2018-06-01", update.date.to_string());
+
+    let update = &updates.trip_updates[2];
+    assert_eq!(TripStatus::Added, update.status);
+    assert_eq!("M1-EXTRA", update.trip_id);
+    assert_eq!(Time::new(10, 0, 0), update.stop_times[0].departure_time);
+    assert_eq!(Time::new(24, 30, 0), update.stop_times[1].arrival_time);
+}
+
+#[test]
+fn read_situation_exchange() {
+    let model = ntfs::read("fixtures/minimal_ntfs").unwrap();
+    let updates = siri::read_from_path(&model, "fixtures/siri/situation_exchange.xml").unwrap();
+    assert!(updates.trip_updates.is_empty());
+    assert_eq!(1, updates.disruptions.len());
+    let disruption = &updates.disruptions[0];
+    assert_eq!("SX-1", disruption.id);
+    assert_eq!(Some("industrialAction".to_string()), disruption.cause);
+    assert_eq!(Some("noService".to_string()), disruption.effect);
+    assert_eq!(Some("Strike".to_string()), disruption.header);
+    assert_eq!(
+        Some("http://example.com/strike".to_string()),
+        disruption.url
+    );
+    assert_eq!(
+        vec![(Some(1_527_832_800), Some(1_527_876_000))],
+        disruption.periods
+    );
+    assert_eq!(
+        vec![model.networks.get_idx("TGN").unwrap()],
+        disruption.networks
+    );
+    assert_eq!(vec![model.lines.get_idx("M1").unwrap()], disruption.lines);
+    assert_eq!(
+        vec![model.stop_points.get_idx("GDLM").unwrap()],
+        disruption.stop_points
+    );
+}
@@ -0,0 +1,44 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+extern crate navitia_model;
+
+use navitia_model::export::sql;
+use navitia_model::ntfs;
+
+#[test]
+fn write_sql_statements() {
+    let model = ntfs::read("fixtures/minimal_ntfs").unwrap();
+    let mut statements = vec![];
+    sql::write(&model, &mut statements).unwrap();
+    let statements = String::from_utf8(statements).unwrap();
+    assert!(statements.starts_with("BEGIN;\n"));
+    assert!(statements.ends_with("COMMIT;\n"));
+    assert!(statements.contains(
+        "CREATE TABLE \"stop_points\" (\"address_id\" TEXT, \"coord_lat\" DOUBLE PRECISION, \
+         \"coord_lon\" DOUBLE PRECISION, \"equipment_id\" TEXT, \"fare_zone_id\" TEXT, \
+         \"geometry_id\" TEXT, \"id\" TEXT, \"name\" TEXT, \"stop_area_id\" TEXT, \
+         \"timezone\" TEXT, \"visible\" BOOLEAN);"
+    ));
+    assert!(statements.contains(
+        "(0, '09:00:00', 0, NULL, NULL, FALSE, '09:00:00', 0, NULL, 0, NULL, 'NATM', 0, \
+         'M1F1')"
+    ));
+    assert!(statements.contains("DROP TABLE IF EXISTS \"calendar_dates\";"));
+    assert!(statements.contains("('2018-01-01', 'Week')"));
+    // no table without rows
+    assert!(!statements.contains("\"tickets\""));
+}
@@ -0,0 +1,62 @@
+// Copyright 2017-2018 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <http://www.gnu.org/licenses/>.
+
+extern crate chrono;
+extern crate navitia_model;
+extern crate serde_json;
+
+use chrono::NaiveDate;
+use navitia_model::ntfs;
+use navitia_model::statistics::{DayStopTimes, Extent, Period};
+
+#[test]
+fn minimal_ntfs_statistics() {
+    let statistics = ntfs::read("fixtures/minimal_ntfs").unwrap().statistics();
+    assert_eq!(3, statistics.counts["lines"]);
+    assert_eq!(6, statistics.counts["vehicle_journeys"]);
+    assert_eq!(20, statistics.counts["stop_times"]);
+    assert_eq!(
+        Some(Period {
+            start_date: NaiveDate::from_ymd(2018, 1, 1),
+            end_date: NaiveDate::from_ymd(2018, 12, 31),
+        }),
+        statistics.validity_period
+    );
+    // the weekdays of 2018
+    assert_eq!(261, statistics.service_days);
+    assert_eq!(
+        DayStopTimes {
+            date: NaiveDate::from_ymd(2018, 1, 1),
+            stop_times: 20,
+        },
+        statistics.stop_times_per_day[0]
+    );
+    assert_eq!(vec!["Bus", "Metro", "RapidTransit"], statistics.modes);
+    assert_eq!(
+        Some(Extent {
+            min_lon: 2.238964,
+            min_lat: 48.842481,
+            max_lon: 2.396497,
+            max_lat: 48.891737,
+        }),
+        statistics.extent
+    );
+
+    let json = serde_json::to_value(&statistics).unwrap();
+    assert_eq!("20180101", json["validity_period"]["start_date"]);
+    assert_eq!("20180101", json["stop_times_per_day"][0]["date"]);
+    assert_eq!(20, json["stop_times_per_day"][0]["stop_times"]);
+}